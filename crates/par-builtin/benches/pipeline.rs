@@ -0,0 +1,297 @@
+//! Benchmarks for each stage of the compiler, and for running compiled code.
+//!
+//! The stages run on the bundled examples and on generated programs that
+//! stress one thing at a time: many small definitions (`wide`), and one long
+//! definition (`deep`). Every run includes the built-in packages, the same as
+//! `par run` or `par check` would.
+//!
+//! The `reduce` group also puts the list functions backed by Rust externals
+//! next to the Par definitions they replaced, kept here as `InPar`.
+//!
+//! Run with `cargo bench -p par-builtin`, or `cargo bench -p par-builtin -- check`
+//! for a single group.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arcstr::literal;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use num_bigint::BigUint;
+use par_core::pipeline::{Pipeline, PipelineError, SourcePackage, Stage};
+use par_core::source::FileName;
+use par_core::workspace::LoadedPackageFile;
+use par_runtime::environment::Environment;
+use par_runtime::pkgid::PackageId;
+use par_runtime::spawn::TokioSpawn;
+
+struct Program {
+    name: &'static str,
+    root_package: PackageId,
+    sources: Vec<SourcePackage>,
+}
+
+impl Program {
+    fn examples() -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/src/HelloWorld.par");
+        let pipeline = Pipeline::from_path(path, None).expect("examples package");
+        let artifacts = pipeline.into_artifacts();
+        Self {
+            name: "examples",
+            root_package: artifacts.root_package,
+            sources: artifacts.sources,
+        }
+    }
+
+    fn generated(name: &'static str, module: &str, source: String) -> Self {
+        let id = PackageId::Special(literal!("bench"));
+        Self {
+            name,
+            root_package: id.clone(),
+            sources: vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from(format!("bench/{module}.par").as_str()),
+                    relative_path_from_src: PathBuf::from(format!("{module}.par")),
+                    source,
+                }],
+            }],
+        }
+    }
+
+    /// Many small types and functions on them.
+    fn wide(count: usize) -> Self {
+        let mut source = String::from("module Wide\n");
+        for i in 0..count {
+            write!(
+                source,
+                r#"
+type Shape{i} = either {{
+  .circle Nat,
+  .rectangle(Nat) Nat,
+  .named(String) Shape{i},
+}}
+
+dec Area{i} : [Shape{i}] Nat
+def Area{i} = [shape] shape.begin.case {{
+  .circle r => 3 * r * r,
+  .rectangle(w) h => w * h,
+  .named(_) inner => inner.loop,
+}}
+
+dec Describe{i} : [Shape{i}] String
+def Describe{i} = [shape] `area #{{Area{i}(shape)}}`
+"#
+            )
+            .expect("write to a string");
+        }
+        Self::generated("wide", "Wide", source)
+    }
+
+    /// One definition with a long chain of bindings.
+    fn deep(depth: usize) -> Self {
+        let mut source =
+            String::from("module Deep\n\ndec Deep : [Nat] Nat\ndef Deep = [x0] do {\n");
+        for i in 1..=depth {
+            let prev = i - 1;
+            writeln!(
+                source,
+                "  let x{i} = if {{ x{prev} < {i} => x{prev} + {i}, else => x{prev} }}"
+            )
+            .expect("write to a string");
+        }
+        writeln!(source, "}} in x{depth}").expect("write to a string");
+        Self::generated("deep", "Deep", source)
+    }
+
+    /// Functions to run, each taking their input size as a `Nat`.
+    fn reduce() -> Self {
+        let source = r#"module Reduce
+
+import {
+  @core/List
+  @core/Nat
+}
+
+dec SumSquares : [Nat] Nat
+def SumSquares = [n] List.Sum(List.Map(Nat.Range(0, n), box [x] x * x))
+
+dec SortReversed : [Nat] Nat
+def SortReversed = [n] List.Sum(List.Sort(List.Reverse(Nat.Range(0, n))))
+
+dec Length : [Nat] Nat
+def Length = [n] List.Length(Nat.Range(0, n))
+
+dec LengthInPar : [Nat] Nat
+def LengthInPar = [n]
+  let len = 0 in
+  Nat.Range(0, n).begin.case {
+    .end! => len,
+    .item(_) list => do { len += 1 } in list.loop,
+  }
+
+dec Reverse : [Nat] Nat
+def Reverse = [n] List.Length(List.Reverse(Nat.Range(0, n)))
+
+dec ReverseInPar : [Nat] Nat
+def ReverseInPar = [n]
+  let reversed: List<Nat> = .end! in
+  Nat.Range(0, n).begin.case {
+    .end! => List.Length(reversed),
+    .item(v) list => do {
+      let reversed = .item(v) reversed
+    } in list.loop,
+  }
+
+dec Concat : [Nat] Nat
+def Concat = [n] List.Length(List.Concat(*(Nat.Range(0, n), Nat.Range(0, n))))
+
+dec ConcatInPar : [Nat] Nat
+def ConcatInPar = [n]
+  let lists: List<List<Nat>> = *(Nat.Range(0, n), Nat.Range(0, n)) in
+  let concatenated: List<Nat> = chan yield {
+    lists.begin.case {
+      .item(list) => {
+        yield->List.Copy(list)
+        lists.loop
+      }
+      .end! => {
+        yield.end!
+      }
+    }
+  } in
+  List.Length(concatenated)
+"#;
+        Self::generated("reduce", "Reduce", source.to_owned())
+    }
+
+    fn pipeline(&self) -> Pipeline<'static> {
+        let mut pipeline = Pipeline::new(self.root_package.clone(), self.sources.clone());
+        pipeline.add_pass(Stage::Parse, |artifacts| {
+            let packages = artifacts.packages.as_mut().expect("parsed packages");
+            par_builtin::inject_builtin_packages(packages).map_err(PipelineError::Discovery)
+        });
+        pipeline
+    }
+
+    /// A pipeline that has run every stage before `stage`.
+    fn pipeline_before(&self, stage: Stage) -> Pipeline<'static> {
+        let mut pipeline = self.pipeline();
+        if let Some(index) = Stage::ALL
+            .iter()
+            .position(|&s| s == stage)
+            .filter(|&i| i > 0)
+        {
+            pipeline
+                .run_until(Stage::ALL[index - 1])
+                .expect("earlier stages");
+        }
+        pipeline
+    }
+}
+
+fn programs() -> Vec<Program> {
+    vec![Program::examples(), Program::wide(200), Program::deep(300)]
+}
+
+fn bench_stages(c: &mut Criterion) {
+    let programs = programs();
+    // Parsing lexes each file as it goes, so it's measured from the start.
+    let groups = [
+        ("lex", None, Stage::Lex),
+        ("parse", None, Stage::Parse),
+        ("check", Some(Stage::Lower), Stage::TypeCheck),
+        ("compile", Some(Stage::Compile), Stage::Compile),
+    ];
+    for (group_name, first, last) in groups {
+        let mut group = c.benchmark_group(group_name);
+        group
+            .sample_size(10)
+            .measurement_time(Duration::from_secs(10));
+        for program in &programs {
+            group.bench_function(program.name, |b| {
+                b.iter_batched(
+                    || match first {
+                        Some(first) => program.pipeline_before(first),
+                        None => program.pipeline(),
+                    },
+                    |mut pipeline| {
+                        pipeline.run_until(last).expect("benchmarked stages");
+                        pipeline
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_reduce(c: &mut Criterion) {
+    let program = Program::reduce();
+    let mut pipeline = program.pipeline();
+    pipeline
+        .run_until(Stage::TypeCheck)
+        .expect("checked program");
+    let artifacts = pipeline.into_artifacts();
+    assert!(
+        artifacts.type_errors.is_empty(),
+        "{:?}",
+        artifacts.type_errors
+    );
+    let checked = artifacts.checked.expect("checked workspace");
+    let compiled = checked
+        .compile_runtime(10_000)
+        .expect("compiled program")
+        .link()
+        .expect("linked program");
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    let mut group = c.benchmark_group("reduce");
+    group.sample_size(20);
+    let benchmarked = [
+        ("SumSquares", 10_000u32),
+        ("SortReversed", 10_000),
+        ("Length", 10_000),
+        ("LengthInPar", 10_000),
+        ("Reverse", 10_000),
+        ("ReverseInPar", 10_000),
+        ("Concat", 10_000),
+        ("ConcatInPar", 10_000),
+    ];
+    for (name, n) in benchmarked {
+        let global = checked
+            .checked_module()
+            .definitions
+            .keys()
+            .find(|global: &&_| global.module.module == "Reduce" && global.primary == name)
+            .cloned()
+            .expect("benchmarked definition");
+        let package = compiled.package(&global).expect("compiled definition");
+        group.bench_function(format!("{name}/{n}"), |b| {
+            b.to_async(&runtime).iter(|| async {
+                let (mut root, fut) = par_runtime::start_and_instantiate(
+                    Arc::new(TokioSpawn::new()),
+                    Environment::new(),
+                    compiled.arena(),
+                    package,
+                );
+                root.send().provide_nat(BigUint::from(n));
+                let result = root.nat().await;
+                fut.await;
+                black_box(result)
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_stages, bench_reduce);
+criterion_main!(benches);
@@ -1,8 +1,11 @@
+mod assets;
 mod bench;
 mod boxmap;
 mod byte;
 mod bytes;
 mod char_;
+mod codec;
+mod config;
 mod console;
 mod data;
 mod debug;
@@ -18,15 +21,22 @@ mod number;
 #[cfg(not(target_family = "wasm"))]
 mod os;
 mod parser;
+mod protocol;
+#[cfg(not(target_family = "wasm"))]
+mod remote;
+mod set;
 mod string;
 mod time;
 mod url;
 
+#[cfg(not(target_family = "wasm"))]
+pub use remote::serve_session;
+
 use std::collections::{BTreeMap, btree_map::Entry};
 use std::env;
 use std::path::PathBuf;
 
-use par_core::frontend::{TypeDef, get_external_type_defs};
+use par_core::frontend::{TypeDef, get_external_type_defs, get_external_type_functions};
 use par_core::source::FileName;
 use par_core::workspace::{
     ExternalModule, LoadedPackageFile, ModulePath, WorkspaceDiscoveryError, WorkspacePackage,
@@ -70,6 +80,10 @@ struct BuiltinSourceFile {
 }
 
 const CORE_SOURCE_FILES: &[BuiltinSourceFile] = &[
+    BuiltinSourceFile {
+        relative_path_from_src: "Assets.par",
+        source: include_str!("../packages/core/src/Assets.par"),
+    },
     BuiltinSourceFile {
         relative_path_from_src: "Bench.par",
         source: include_str!("../packages/core/src/Bench.par"),
@@ -110,6 +124,14 @@ const CORE_SOURCE_FILES: &[BuiltinSourceFile] = &[
         relative_path_from_src: "Char.par",
         source: include_str!("../packages/core/src/Char.par"),
     },
+    BuiltinSourceFile {
+        relative_path_from_src: "Codec.par",
+        source: include_str!("../packages/core/src/Codec.par"),
+    },
+    BuiltinSourceFile {
+        relative_path_from_src: "Config.par",
+        source: include_str!("../packages/core/src/Config.par"),
+    },
     BuiltinSourceFile {
         relative_path_from_src: "Int.par",
         source: include_str!("../packages/core/src/Int.par"),
@@ -142,6 +164,14 @@ const CORE_SOURCE_FILES: &[BuiltinSourceFile] = &[
         relative_path_from_src: "Ordering.par",
         source: include_str!("../packages/core/src/Ordering.par"),
     },
+    BuiltinSourceFile {
+        relative_path_from_src: "Protocol.par",
+        source: include_str!("../packages/core/src/Protocol.par"),
+    },
+    BuiltinSourceFile {
+        relative_path_from_src: "Set.par",
+        source: include_str!("../packages/core/src/Set.par"),
+    },
     BuiltinSourceFile {
         relative_path_from_src: "Stream.par",
         source: include_str!("../packages/core/src/Stream.par"),
@@ -182,6 +212,10 @@ const BASIC_SOURCE_FILES: &[BuiltinSourceFile] = &[
         relative_path_from_src: "Os.par",
         source: include_str!("../packages/basic/src/Os.par"),
     },
+    BuiltinSourceFile {
+        relative_path_from_src: "Remote.par",
+        source: include_str!("../packages/basic/src/Remote.par"),
+    },
 ];
 
 pub fn get_builtin_source(filename: &str) -> Option<&'static str> {
@@ -196,7 +230,7 @@ pub fn get_builtin_source(filename: &str) -> Option<&'static str> {
         .find_map(|file| (file.relative_path_from_src == path).then_some(file.source))
 }
 
-pub const PAR_BUILTIN_URI_SCHEME: &str = "par-builtin";
+pub const PAR_BUILTIN_URI_SCHEME: &str = par_core::source::BUILTIN_URI_SCHEME;
 
 fn parse_builtin_sources(
     package_name: &str,
@@ -230,6 +264,23 @@ fn load_external_type_defs(name: BuiltinPackage) -> BTreeMap<ModulePath, Externa
             type_def.typ.clone(),
         ));
     }
+    for type_function in get_external_type_functions(PackageRef::Builtin(name)) {
+        let module_path = ModulePath {
+            directories: type_function
+                .path
+                .path
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            module: type_function.path.module.into(),
+        };
+        let module = externals.entry(module_path).or_default();
+        module.type_defs.push(TypeDef::external_function(
+            type_function.path.name,
+            type_function.doc,
+            type_function.function,
+        ));
+    }
     externals
 }
 
@@ -0,0 +1,63 @@
+//package: core
+use arcstr::literal;
+use par_runtime::primitive::ParString;
+use par_runtime::readback::Handle;
+use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+
+use crate::builtin::bytes::provide_bytes_reader_from_bytes;
+
+macro_rules! core_assets_external {
+    ($name:literal, $f:path $(, $arg:expr)*) => {
+        inventory::submit!(ExternalDef {
+            path: DefinitionRef {
+                package: PackageRef::CORE,
+                path: &[],
+                module: "Assets",
+                name: $name,
+            },
+            f: |handle| Box::pin($f(handle $(, $arg)*)),
+        });
+    };
+}
+
+core_assets_external!("Names", assets_names);
+core_assets_external!("Read", assets_read);
+core_assets_external!("Open", assets_open);
+
+async fn assets_names(mut handle: Handle) {
+    let names = handle.assets().keys().cloned().collect::<Vec<_>>();
+    for name in names {
+        handle.signal(literal!("item"));
+        handle.send().provide_string(ParString::from(name));
+    }
+    handle.signal(literal!("end"));
+    handle.break_();
+}
+
+async fn assets_read(mut handle: Handle) {
+    let name = handle.receive().string().await;
+    match handle.assets().get(name.as_str()).cloned() {
+        Some(bytes) => {
+            handle.signal(literal!("some"));
+            handle.provide_bytes(bytes);
+        }
+        None => {
+            handle.signal(literal!("none"));
+            handle.break_();
+        }
+    }
+}
+
+async fn assets_open(mut handle: Handle) {
+    let name = handle.receive().string().await;
+    match handle.assets().get(name.as_str()).cloned() {
+        Some(bytes) => {
+            handle.signal(literal!("some"));
+            provide_bytes_reader_from_bytes(handle, bytes).await;
+        }
+        None => {
+            handle.signal(literal!("none"));
+            handle.break_();
+        }
+    }
+}
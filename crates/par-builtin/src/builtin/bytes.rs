@@ -1,4 +1,6 @@
 //package: core
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
 use arcstr::literal;
 use bytes::Bytes;
 use futures::{StreamExt, channel::mpsc};
@@ -6,7 +8,7 @@ use num_bigint::BigUint;
 use std::{
     cmp::Ordering,
     sync::{
-        Arc, Mutex,
+        Arc, Mutex, MutexGuard, PoisonError,
         atomic::{AtomicBool, Ordering as AtomicOrdering},
     },
 };
@@ -103,7 +105,7 @@ async fn bytes_empty_reader(mut handle: Handle) {
     }
 }
 
-async fn provide_bytes_reader_from_bytes(mut handle: Handle, bytes: Bytes) {
+pub(crate) async fn provide_bytes_reader_from_bytes(mut handle: Handle, bytes: Bytes) {
     let mut offset = 0usize;
     let len = bytes.len();
     loop {
@@ -154,7 +156,7 @@ impl PipeReaderState {
     }
 
     fn take_sender(&self) -> Option<mpsc::UnboundedSender<PipeMessage>> {
-        self.sender.lock().expect("lock failed").take()
+        lock(&self.sender).take()
     }
 
     fn reader_closed(&self) -> bool {
@@ -168,7 +170,7 @@ impl PipeReaderState {
     }
 
     fn error_present(&self) -> bool {
-        self.error.lock().expect("lock failed").is_some()
+        lock(&self.error).is_some()
     }
 
     fn set_result_ok(&self) {
@@ -179,7 +181,7 @@ impl PipeReaderState {
     async fn set_result_err(&self, err: Handle) {
         // This tricky is necessary to avoid holding `guard` while awaiting.
         if let Some(err) = {
-            let mut guard = self.error.lock().expect("lock failed");
+            let mut guard = lock(&self.error);
             if guard.is_none() {
                 *guard = Some(err);
                 None
@@ -201,10 +203,16 @@ impl PipeReaderState {
     }
 
     fn take_error(&self) -> Option<Handle> {
-        self.error.lock().expect("lock failed").take()
+        lock(&self.error).take()
     }
 }
 
+/// Locks `mutex` even if a panic poisoned it, as what it guards is only ever
+/// replaced whole, so it's never left half-updated.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 async fn bytes_pipe_reader(mut handle: Handle) {
     let mut closure = handle.receive();
 
@@ -270,7 +278,7 @@ fn write_chunk(state: &PipeReaderState, bytes: Bytes) -> bool {
     if state.reader_closed() || state.error_present() {
         return false;
     }
-    match state.sender.lock().expect("lock failed").as_ref() {
+    match lock(&state.sender).as_ref() {
         Some(sender) => sender.unbounded_send(PipeMessage::Chunk(bytes)).is_ok(),
         None => false,
     }
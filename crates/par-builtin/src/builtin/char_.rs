@@ -45,11 +45,16 @@ async fn char_code(mut handle: Handle) {
 
 async fn char_from_code(mut handle: Handle) {
     let code = handle.receive().nat().await;
-    let ch = code
-        .to_u32()
-        .and_then(char::from_u32)
-        .unwrap_or(char::REPLACEMENT_CHARACTER);
-    handle.provide_char(ch);
+    match code.to_u32().and_then(char::from_u32) {
+        Some(ch) => {
+            handle.signal(literal!("some"));
+            handle.provide_char(ch);
+        }
+        None => {
+            handle.signal(literal!("none"));
+            handle.break_();
+        }
+    }
 }
 
 async fn char_to_lower(mut handle: Handle) {
@@ -77,11 +82,16 @@ async fn char_is(mut handle: Handle) {
 pub(super) enum CharClass {
     Any,
     Char(char),
+    Range(char, char),
     Whitespace,
+    Letter,
+    Digit,
+    Punctuation,
     AsciiAny,
     AsciiAlpha,
     AsciiAlphanum,
     AsciiDigit,
+    AsciiPunctuation,
 }
 
 impl CharClass {
@@ -108,9 +118,31 @@ impl CharClass {
                     handle.continue_();
                     Self::AsciiDigit
                 }
+                "punctuation" => {
+                    handle.continue_();
+                    Self::AsciiPunctuation
+                }
                 _ => unreachable!(),
             },
             "char" => Self::Char(handle.char().await),
+            "digit" => {
+                handle.continue_();
+                Self::Digit
+            }
+            "letter" => {
+                handle.continue_();
+                Self::Letter
+            }
+            "punctuation" => {
+                handle.continue_();
+                Self::Punctuation
+            }
+            "range" => {
+                let min = handle.receive().char().await;
+                let max = handle.receive().char().await;
+                handle.continue_();
+                Self::Range(min, max)
+            }
             "whitespace" => {
                 handle.continue_();
                 Self::Whitespace
@@ -123,11 +155,16 @@ impl CharClass {
         match self {
             Self::Any => true,
             Self::Char(ch1) => ch == *ch1,
+            Self::Range(min, max) => *min <= ch && ch <= *max,
             Self::Whitespace => ch.is_whitespace(),
+            Self::Letter => ch.is_alphabetic(),
+            Self::Digit => ch.is_numeric(),
+            Self::Punctuation => !(ch.is_alphanumeric() || ch.is_whitespace() || ch.is_control()),
             Self::AsciiAny => ch.is_ascii(),
             Self::AsciiAlpha => ch.is_ascii_alphabetic(),
             Self::AsciiAlphanum => ch.is_ascii_alphanumeric(),
             Self::AsciiDigit => ch.is_ascii_digit(),
+            Self::AsciiPunctuation => ch.is_ascii_punctuation(),
         }
     }
 }
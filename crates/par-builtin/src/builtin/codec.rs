@@ -0,0 +1,194 @@
+//package: core
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+use arcstr::literal;
+use bytes::{BufMut, Bytes, BytesMut};
+use num_bigint::{BigInt, BigUint, Sign};
+use par_runtime::primitive::ParString;
+use par_runtime::readback::Handle;
+use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+
+use crate::builtin::list::readback_list;
+
+macro_rules! core_codec_external {
+    ($name:literal, $f:path $(, $arg:expr)*) => {
+        inventory::submit!(ExternalDef {
+            path: DefinitionRef {
+                package: PackageRef::CORE,
+                path: &[],
+                module: "Codec",
+                name: $name,
+            },
+            f: |handle| Box::pin($f(handle $(, $arg)*)),
+        });
+    };
+}
+
+core_codec_external!("EncodeNat", codec_encode_nat);
+core_codec_external!("EncodeInt", codec_encode_int);
+core_codec_external!("EncodeFloat", codec_encode_float);
+core_codec_external!("EncodeString", codec_encode_string);
+core_codec_external!("EncodeBytes", codec_encode_bytes);
+core_codec_external!("Concat", codec_concat);
+core_codec_external!("DecodeNat", codec_decode_nat);
+core_codec_external!("DecodeInt", codec_decode_int);
+core_codec_external!("DecodeFloat", codec_decode_float);
+core_codec_external!("DecodeString", codec_decode_string);
+core_codec_external!("DecodeBytes", codec_decode_bytes);
+
+async fn codec_encode_nat(mut handle: Handle) {
+    let value = handle.receive().nat().await;
+    let mut buf = BytesMut::new();
+    write_nat(&mut buf, &value);
+    handle.provide_bytes(buf.freeze());
+}
+
+async fn codec_encode_int(mut handle: Handle) {
+    let value = handle.receive().int().await;
+    let mut buf = BytesMut::new();
+    write_nat(&mut buf, &zigzag(&value));
+    handle.provide_bytes(buf.freeze());
+}
+
+async fn codec_encode_float(mut handle: Handle) {
+    let value = handle.receive().float().await;
+    handle.provide_bytes(Bytes::copy_from_slice(&value.to_le_bytes()));
+}
+
+async fn codec_encode_string(mut handle: Handle) {
+    let value = handle.receive().string().await;
+    handle.provide_bytes(length_prefixed(&value.as_bytes()));
+}
+
+async fn codec_encode_bytes(mut handle: Handle) {
+    let value = handle.receive().bytes().await;
+    handle.provide_bytes(length_prefixed(&value));
+}
+
+async fn codec_concat(mut handle: Handle) {
+    let parts = readback_list(handle.receive(), |part| part.bytes()).await;
+    let mut buf = BytesMut::with_capacity(parts.iter().map(Bytes::len).sum());
+    for part in parts {
+        buf.put(part);
+    }
+    handle.provide_bytes(buf.freeze());
+}
+
+async fn codec_decode_nat(mut handle: Handle) {
+    let bytes = handle.receive().bytes().await;
+    provide_decoded(handle, read_nat(&bytes), Handle::provide_nat);
+}
+
+async fn codec_decode_int(mut handle: Handle) {
+    let bytes = handle.receive().bytes().await;
+    let decoded = read_nat(&bytes).map(|(value, rest)| (unzigzag(value), rest));
+    provide_decoded(handle, decoded, Handle::provide_int);
+}
+
+async fn codec_decode_float(mut handle: Handle) {
+    let bytes = handle.receive().bytes().await;
+    let decoded = bytes
+        .get(..8)
+        .and_then(|value| value.try_into().ok())
+        .map(|value| (f64::from_le_bytes(value), bytes.slice(8..)));
+    provide_decoded(handle, decoded, Handle::provide_float);
+}
+
+async fn codec_decode_string(mut handle: Handle) {
+    let bytes = handle.receive().bytes().await;
+    let decoded = read_length_prefixed(&bytes).and_then(|(value, rest)| {
+        std::str::from_utf8(&value).ok()?;
+        Some((ParString::from_utf8_lossy(value), rest))
+    });
+    provide_decoded(handle, decoded, Handle::provide_string);
+}
+
+async fn codec_decode_bytes(mut handle: Handle) {
+    let bytes = handle.receive().bytes().await;
+    provide_decoded(handle, read_length_prefixed(&bytes), Handle::provide_bytes);
+}
+
+/// Provides `.ok (value) rest`, or `.err!` if decoding failed.
+fn provide_decoded<T>(mut handle: Handle, decoded: Option<(T, Bytes)>, provide: fn(Handle, T)) {
+    match decoded {
+        Some((value, rest)) => {
+            handle.signal(literal!("ok"));
+            provide(handle.send(), value);
+            handle.provide_bytes(rest);
+        }
+        None => {
+            handle.signal(literal!("err"));
+            handle.break_();
+        }
+    }
+}
+
+/// Unsigned LEB128: seven bits per byte, least significant first, with the
+/// high bit set on all bytes but the last.
+pub(super) fn write_nat(buf: &mut BytesMut, value: &BigUint) {
+    let bits = value.bits();
+    let mut offset = 0;
+    loop {
+        let mut byte = 0u8;
+        for bit in 0..7 {
+            if value.bit(offset + bit) {
+                byte |= 1 << bit;
+            }
+        }
+        offset += 7;
+        if offset >= bits {
+            buf.put_u8(byte);
+            return;
+        }
+        buf.put_u8(byte | 0x80);
+    }
+}
+
+/// Reads a number written by `write_nat`, rejecting padded encodings so that
+/// every number has exactly one.
+pub(super) fn read_nat(bytes: &Bytes) -> Option<(BigUint, Bytes)> {
+    let mut value = BigUint::ZERO;
+    for (index, &byte) in bytes.iter().enumerate() {
+        value |= BigUint::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            if byte == 0 && index > 0 {
+                return None;
+            }
+            return Some((value, bytes.slice(index + 1..)));
+        }
+    }
+    None
+}
+
+/// Maps `0, -1, 1, -2, ...` to `0, 1, 2, 3, ...`, so small negative numbers
+/// stay short.
+pub(super) fn zigzag(value: &BigInt) -> BigUint {
+    let magnitude = value.magnitude();
+    match value.sign() {
+        Sign::Minus => (magnitude << 1u32) - 1u32,
+        _ => magnitude << 1u32,
+    }
+}
+
+pub(super) fn unzigzag(value: BigUint) -> BigInt {
+    if value.bit(0) {
+        -BigInt::from((value + 1u32) >> 1u32)
+    } else {
+        BigInt::from(value >> 1u32)
+    }
+}
+
+pub(super) fn length_prefixed(value: &[u8]) -> Bytes {
+    let mut buf = BytesMut::new();
+    write_nat(&mut buf, &BigUint::from(value.len()));
+    buf.put_slice(value);
+    buf.freeze()
+}
+
+pub(super) fn read_length_prefixed(bytes: &Bytes) -> Option<(Bytes, Bytes)> {
+    let (length, rest) = read_nat(bytes)?;
+    let length = usize::try_from(length)
+        .ok()
+        .filter(|&len| len <= rest.len())?;
+    Some((rest.slice(..length), rest.slice(length..)))
+}
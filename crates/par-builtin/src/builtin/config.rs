@@ -0,0 +1,46 @@
+//package: core
+use arcstr::literal;
+use par_runtime::primitive::ParString;
+use par_runtime::readback::Handle;
+use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+
+macro_rules! core_config_external {
+    ($name:literal, $f:path $(, $arg:expr)*) => {
+        inventory::submit!(ExternalDef {
+            path: DefinitionRef {
+                package: PackageRef::CORE,
+                path: &[],
+                module: "Config",
+                name: $name,
+            },
+            f: |handle| Box::pin($f(handle $(, $arg)*)),
+        });
+    };
+}
+
+core_config_external!("Keys", config_keys);
+core_config_external!("Get", config_get);
+
+async fn config_keys(mut handle: Handle) {
+    let keys = handle.config().keys().cloned().collect::<Vec<_>>();
+    for key in keys {
+        handle.signal(literal!("item"));
+        handle.send().provide_string(ParString::from(key));
+    }
+    handle.signal(literal!("end"));
+    handle.break_();
+}
+
+async fn config_get(mut handle: Handle) {
+    let key = handle.receive().string().await;
+    match handle.config().get(key.as_str()).cloned() {
+        Some(value) => {
+            handle.signal(literal!("some"));
+            handle.provide_string(ParString::from(value));
+        }
+        None => {
+            handle.signal(literal!("none"));
+            handle.break_();
+        }
+    }
+}
@@ -1,5 +1,5 @@
 //package: basic
-use std::io::{Write, stdin, stdout};
+use std::io::{BufRead, Write, stdin, stdout};
 
 use arcstr::literal;
 use par_runtime::readback::Handle;
@@ -7,6 +7,8 @@ use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 
 use par_core::frontend::ParString;
 
+use crate::builtin::string::{provide_line, provide_lines_end};
+
 async fn console_open(mut handle: Handle) {
     loop {
         match handle.case().await.as_str() {
@@ -16,25 +18,31 @@ async fn console_open(mut handle: Handle) {
             }
 
             "print" => {
-                println!("{}", handle.receive().string().await.as_str(),);
+                let string = handle.receive().string().await;
+                handle
+                    .effects()
+                    .have_now("Console.Print", || println!("{}", string.as_str()));
             }
 
             "prompt" => {
                 let prompt = handle.receive().string().await;
-                print!("{}", prompt.as_str());
-                let _ = stdout().flush();
-                let mut buf = String::new();
-                let result = stdin().read_line(&mut buf);
+                let line = handle.effects().have_now("Console.Prompt", || {
+                    print!("{}", prompt.as_str());
+                    let _ = stdout().flush();
+                    let mut buf = String::new();
+                    match stdin().read_line(&mut buf) {
+                        Ok(n) if n > 0 => Some(buf.trim_end_matches(['\n', '\r']).to_owned()),
+                        _ => None,
+                    }
+                });
 
                 handle.send().concurrently(|mut handle| async move {
-                    match result {
-                        Ok(n) if n > 0 => {
-                            let string =
-                                ParString::copy_from_slice(buf.trim_end_matches(&['\n', '\r']));
+                    match line {
+                        Some(line) => {
                             handle.signal(literal!("ok"));
-                            handle.provide_string(string);
+                            handle.provide_string(ParString::copy_from_slice(&line));
                         }
-                        _ => {
+                        None => {
                             handle.signal(literal!("err"));
                             handle.break_();
                         }
@@ -46,6 +54,23 @@ async fn console_open(mut handle: Handle) {
     }
 }
 
+async fn console_lines(mut handle: Handle) {
+    let mut lines = stdin().lock().lines();
+    let result = loop {
+        let next = handle.effects().have_now("Console.Lines", || {
+            lines
+                .next()
+                .map(|line| line.map_err(|error| error.to_string()))
+        });
+        match next {
+            Some(Ok(line)) => handle = provide_line(handle, &line),
+            Some(Err(error)) => break Err(error),
+            None => break Ok(()),
+        }
+    };
+    provide_lines_end(handle, result);
+}
+
 macro_rules! basic_console_external {
     ($name:literal, $f:path $(, $arg:expr)*) => {
         inventory::submit!(ExternalDef {
@@ -61,3 +86,4 @@ macro_rules! basic_console_external {
 }
 
 basic_console_external!("Open", console_open);
+basic_console_external!("Lines", console_lines);
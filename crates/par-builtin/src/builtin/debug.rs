@@ -3,7 +3,9 @@ use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 
 async fn debug_log(mut handle: Handle) {
     let string = handle.receive().string().await;
-    eprintln!("{}", string.as_str());
+    handle
+        .effects()
+        .have_now("Debug.Log", || eprintln!("{}", string.as_str()));
     handle.break_();
 }
 
@@ -1,12 +1,11 @@
 //package: core
 use std::f64::consts;
 
-use arcstr::literal;
 use num_bigint::{BigInt, Sign};
 use num_traits::{FromPrimitive, ToPrimitive};
 use par_core::frontend::{ExternalTypeDef, PrimitiveType, Type};
 use par_core::source::Span;
-use par_runtime::primitive::parse_float_text;
+use par_runtime::primitive::{ParString, parse_float_text};
 use par_runtime::readback::Handle;
 use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 
@@ -64,15 +63,6 @@ core_float_external!("Cos", float_cos);
 core_float_external!("Tan", float_tan);
 core_float_external!("Atan2", float_atan2);
 
-fn signal_bool(mut handle: Handle, value: bool) {
-    if value {
-        handle.signal(literal!("true"));
-    } else {
-        handle.signal(literal!("false"));
-    }
-    handle.break_();
-}
-
 fn min_float(left: f64, right: f64) -> f64 {
     if left.is_nan() || right.is_nan() {
         f64::NAN
@@ -126,138 +116,105 @@ async fn float_e(mut handle: Handle) {
     handle.provide_float(consts::E);
 }
 
-async fn float_is_nan(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    signal_bool(handle, value.is_nan());
+async fn float_is_nan(handle: Handle) {
+    handle.unary(|value: f64| value.is_nan()).await
 }
 
-async fn float_is_finite(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    signal_bool(handle, value.is_finite());
+async fn float_is_finite(handle: Handle) {
+    handle.unary(|value: f64| value.is_finite()).await
 }
 
-async fn float_is_infinite(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    signal_bool(handle, value.is_infinite());
+async fn float_is_infinite(handle: Handle) {
+    handle.unary(|value: f64| value.is_infinite()).await
 }
 
-async fn float_from_int(mut handle: Handle) {
-    let value = handle.receive().int().await;
-    handle.provide_float(bigint_to_float(value));
+async fn float_from_int(handle: Handle) {
+    handle.unary(|value: BigInt| bigint_to_float(value)).await
 }
 
-async fn float_to_int(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_int(float_to_bigint(value));
+async fn float_to_int(handle: Handle) {
+    handle.unary(|value: f64| float_to_bigint(value)).await
 }
 
-async fn float_from_string(mut handle: Handle) {
-    let string = handle.receive().string().await;
-    match parse_float_text(string.as_str()) {
-        Some(value) => {
-            handle.signal(literal!("some"));
-            handle.provide_float(value);
-        }
-        None => {
-            handle.signal(literal!("none"));
-            handle.break_();
-        }
-    }
+async fn float_from_string(handle: Handle) {
+    handle
+        .unary(|string: ParString| parse_float_text(string.as_str()))
+        .await
 }
 
-async fn float_neg(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(-value);
+async fn float_neg(handle: Handle) {
+    handle.unary(|value: f64| -value).await
 }
 
-async fn float_abs(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.abs());
+async fn float_abs(handle: Handle) {
+    handle.unary(|value: f64| value.abs()).await
 }
 
-async fn float_floor(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.floor());
+async fn float_floor(handle: Handle) {
+    handle.unary(|value: f64| value.floor()).await
 }
 
-async fn float_ceil(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.ceil());
+async fn float_ceil(handle: Handle) {
+    handle.unary(|value: f64| value.ceil()).await
 }
 
-async fn float_round(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.round());
+async fn float_round(handle: Handle) {
+    handle.unary(|value: f64| value.round()).await
 }
 
-async fn float_pow(mut handle: Handle) {
-    let x = handle.receive().float().await;
-    let y = handle.receive().float().await;
-    handle.provide_float(x.powf(y));
+async fn float_pow(handle: Handle) {
+    handle.uncurry(|(x, y): (f64, f64)| x.powf(y)).await
 }
 
-async fn float_min(mut handle: Handle) {
-    let x = handle.receive().float().await;
-    let y = handle.receive().float().await;
-    handle.provide_float(min_float(x, y));
+async fn float_min(handle: Handle) {
+    handle.uncurry(|(x, y): (f64, f64)| min_float(x, y)).await
 }
 
-async fn float_max(mut handle: Handle) {
-    let x = handle.receive().float().await;
-    let y = handle.receive().float().await;
-    handle.provide_float(max_float(x, y));
+async fn float_max(handle: Handle) {
+    handle.uncurry(|(x, y): (f64, f64)| max_float(x, y)).await
 }
 
-async fn float_clamp(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    let lo = handle.receive().float().await;
-    let hi = handle.receive().float().await;
-    handle.provide_float(max_float(min_float(value, hi), lo));
+async fn float_clamp(handle: Handle) {
+    handle
+        .uncurry(|(value, lo, hi): (f64, f64, f64)| max_float(min_float(value, hi), lo))
+        .await
 }
 
-async fn float_equals(mut handle: Handle) {
-    let x = handle.receive().float().await;
-    let y = handle.receive().float().await;
-    let tolerance = handle.receive().float().await;
-    let equal = !x.is_nan()
-        && !y.is_nan()
-        && !tolerance.is_nan()
-        && (x == y || (x - y).abs() <= tolerance.abs());
-    signal_bool(handle, equal);
+async fn float_equals(handle: Handle) {
+    handle
+        .uncurry(|(x, y, tolerance): (f64, f64, f64)| {
+            !x.is_nan()
+                && !y.is_nan()
+                && !tolerance.is_nan()
+                && (x == y || (x - y).abs() <= tolerance.abs())
+        })
+        .await
 }
 
-async fn float_sqrt(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.sqrt());
+async fn float_sqrt(handle: Handle) {
+    handle.unary(|value: f64| value.sqrt()).await
 }
 
-async fn float_exp(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.exp());
+async fn float_exp(handle: Handle) {
+    handle.unary(|value: f64| value.exp()).await
 }
 
-async fn float_ln(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.ln());
+async fn float_ln(handle: Handle) {
+    handle.unary(|value: f64| value.ln()).await
 }
 
-async fn float_sin(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.sin());
+async fn float_sin(handle: Handle) {
+    handle.unary(|value: f64| value.sin()).await
 }
 
-async fn float_cos(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.cos());
+async fn float_cos(handle: Handle) {
+    handle.unary(|value: f64| value.cos()).await
 }
 
-async fn float_tan(mut handle: Handle) {
-    let value = handle.receive().float().await;
-    handle.provide_float(value.tan());
+async fn float_tan(handle: Handle) {
+    handle.unary(|value: f64| value.tan()).await
 }
 
-async fn float_atan2(mut handle: Handle) {
-    let y = handle.receive().float().await;
-    let x = handle.receive().float().await;
-    handle.provide_float(y.atan2(x));
+async fn float_atan2(handle: Handle) {
+    handle.uncurry(|(y, x): (f64, f64)| y.atan2(x)).await
 }
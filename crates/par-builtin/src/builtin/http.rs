@@ -78,17 +78,57 @@ async fn http_fetch(mut handle: Handle) {
         consume_http_reader(handle, tx, body_done_tx).await;
     });
 
-    let client = match reqwest::Client::builder()
-        .connect_timeout(Duration::from_secs(10))
-        .read_timeout(Duration::from_secs(10))
-        .build()
-    {
-        Ok(c) => c,
+    // A replaying run doesn't send the request again, but its body is still
+    // read to the end, as the earlier run's was.
+    let mut response = None;
+    let mut unsent = Some(rx);
+    let key = format!("Http.Fetch {} {}", method.as_str(), url.as_str());
+    let head = handle
+        .effects()
+        .have(key.clone(), || {
+            let body = unsent.take();
+            send_request(method, url, header_pairs, body, body_done_rx, &mut response)
+        })
+        .await;
+    if let Some(rx) = unsent {
+        rx.for_each(|_| async {}).await;
+    }
+
+    match head {
+        Ok((status, headers)) => {
+            handle.signal(literal!("ok"));
+            handle.send().provide_nat(BigUint::from(status));
+            provide_headers_list(handle.send(), &headers);
+            provide_body_reader(handle, format!("{key} body"), response).await;
+        }
         Err(err) => {
             handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
+            handle.provide_string(ParString::from(err));
         }
+    }
+}
+
+/// The status and headers of a response.
+type ResponseHead = (u16, Vec<(ParString, Bytes)>);
+
+/// Sends a request with the body from `body`, and puts the response in
+/// `response`, to read its body from.
+async fn send_request(
+    method: ParString,
+    url: ParString,
+    header_pairs: Vec<(ParString, ParString)>,
+    body: Option<mpsc::UnboundedReceiver<Result<Bytes, std::io::Error>>>,
+    body_done: oneshot::Receiver<Result<(), ParString>>,
+    response: &mut Option<reqwest::Response>,
+) -> Result<ResponseHead, ParString> {
+    let Some(body) = body else {
+        unreachable!("the request is sent once")
     };
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|err| ParString::from(err.to_string()))?;
 
     let method = reqwest::Method::from_bytes(&method.as_bytes()).unwrap_or(reqwest::Method::GET);
 
@@ -105,73 +145,91 @@ async fn http_fetch(mut handle: Handle) {
     let request = client.request(method, url.as_str());
     let request = request
         .headers(headers)
-        .body(reqwest::Body::wrap_stream(rx));
+        .body(reqwest::Body::wrap_stream(body));
 
     let response_result = request.send().await;
-    let body_result = body_done_rx.await.unwrap_or(Ok(()));
+    let body_result = body_done.await.unwrap_or(Ok(()));
 
-    let response = match response_result {
-        Ok(response) => {
-            if let Err(body_err) = body_result {
-                handle.signal(literal!("err"));
-                return handle.provide_string(ParString::from(body_err));
-            }
-            response
+    let sent = match response_result {
+        Ok(sent) => {
+            body_result?;
+            sent
         }
         Err(err) => {
-            handle.signal(literal!("err"));
-            if let Err(body_err) = body_result {
-                return handle.provide_string(ParString::from(body_err));
-            }
-            return handle.provide_string(ParString::from(err.to_string()));
+            body_result?;
+            return Err(ParString::from(err.to_string()));
         }
     };
 
-    handle.signal(literal!("ok"));
-    handle
-        .send()
-        .provide_nat(BigUint::from(response.status().as_u16()));
-    provide_headers_list(handle.send(), response.headers());
-    provide_body_reader(handle, response).await;
+    let headers = sent
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                ParString::copy_from_slice(name.as_str()),
+                Bytes::copy_from_slice(value.as_bytes()),
+            )
+        })
+        .collect();
+    let head = (sent.status().as_u16(), headers);
+    *response = Some(sent);
+    Ok(head)
 }
 
-async fn provide_body_reader(mut handle: Handle, response: reqwest::Response) {
-    let mut stream = response.bytes_stream();
+/// Provides a reader of the body of `response`, whose reads are had under
+/// `key`. A replayed response is `None`; its body can only be read as far as
+/// the earlier run read it.
+async fn provide_body_reader(mut handle: Handle, key: String, response: Option<reqwest::Response>) {
+    let effects = handle.effects().clone();
+    let mut stream = response.map(|response| response.bytes_stream());
     loop {
         match handle.case().await.as_str() {
             "close" => {
                 handle.signal(literal!("ok"));
                 return handle.break_();
             }
-            "read" => match stream.next().await {
-                Some(Ok(bytes)) => {
-                    handle.signal(literal!("ok"));
-                    handle.signal(literal!("chunk"));
-                    handle.send().provide_bytes(bytes);
-                    continue;
-                }
-                Some(Err(err)) => {
-                    handle.signal(literal!("err"));
-                    return handle.provide_string(ParString::from(err.to_string()));
-                }
-                None => {
-                    handle.signal(literal!("ok"));
-                    handle.signal(literal!("end"));
-                    return handle.break_();
+            "read" => {
+                let read = effects
+                    .have(key.as_str(), || async {
+                        let Some(stream) = &mut stream else {
+                            return Err(String::from(
+                                "the response was received before the run was started over",
+                            ));
+                        };
+                        match stream.next().await {
+                            Some(Ok(bytes)) => Ok(Some(bytes)),
+                            Some(Err(err)) => Err(err.to_string()),
+                            None => Ok(None),
+                        }
+                    })
+                    .await;
+                match read {
+                    Ok(Some(bytes)) => {
+                        handle.signal(literal!("ok"));
+                        handle.signal(literal!("chunk"));
+                        handle.send().provide_bytes(bytes);
+                        continue;
+                    }
+                    Err(err) => {
+                        handle.signal(literal!("err"));
+                        return handle.provide_string(ParString::from(err));
+                    }
+                    Ok(None) => {
+                        handle.signal(literal!("ok"));
+                        handle.signal(literal!("end"));
+                        return handle.break_();
+                    }
                 }
-            },
+            }
             _ => unreachable!(),
         }
     }
 }
 
-fn provide_headers_list(mut handle: Handle, headers: &reqwest::header::HeaderMap) {
+fn provide_headers_list(mut handle: Handle, headers: &[(ParString, Bytes)]) {
     for (name, value) in headers {
         handle.signal(literal!("item"));
-        let (name, value) = (
-            ParString::copy_from_slice(name.as_str()),
-            Bytes::copy_from_slice(value.as_bytes()),
-        );
+        let (name, value) = (name.clone(), value.clone());
         handle.send().concurrently(|mut handle| async {
             handle.send().provide_string(name);
             handle.provide_bytes(value);
@@ -244,6 +302,8 @@ async fn close_reader(mut handle: Handle) -> Result<(), ParString> {
 // ----------
 
 async fn http_listen(mut handle: Handle) {
+    // The requests served are the run's own inputs, and aren't recorded.
+    handle.effects().forbid_replay();
     let address = handle.receive().string().await;
     match start_listener(address.as_str().to_string()).await {
         Ok(state) => provide_listener_value(handle, state).await,
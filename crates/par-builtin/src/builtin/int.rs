@@ -4,6 +4,7 @@ use num_bigint::{BigInt, BigUint};
 use num_traits::Zero;
 use par_core::frontend::{ExternalTypeDef, PrimitiveType, Type};
 use par_core::source::Span;
+use par_runtime::primitive::ParString;
 use par_runtime::readback::Handle;
 use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 
@@ -40,47 +41,40 @@ core_int_external!("Clamp", int_clamp);
 core_int_external!("Range", int_range);
 core_int_external!("FromString", int_from_string);
 
-async fn int_mod(mut handle: Handle) {
-    let x = handle.receive().int().await;
-    let y = handle.receive().nat().await;
-    let result = if y.is_zero() {
-        BigUint::ZERO
-    } else {
-        let modulus = num_integer::mod_floor(x, BigInt::from(y));
-        BigUint::try_from(modulus)
-            .expect("y is always positive so the result should always be positive")
-    };
-    handle.provide_nat(result);
+async fn int_mod(handle: Handle) {
+    handle
+        .uncurry(|(x, y): (BigInt, BigUint)| {
+            if y.is_zero() {
+                BigUint::ZERO
+            } else {
+                let modulus = num_integer::mod_floor(x, BigInt::from(y));
+                BigUint::try_from(modulus)
+                    .expect("y is always positive so the result should always be positive")
+            }
+        })
+        .await
 }
 
-async fn int_min(mut handle: Handle) {
-    let x = handle.receive().int().await;
-    let y = handle.receive().int().await;
-    handle.provide_int(x.min(y));
+async fn int_min(handle: Handle) {
+    handle.uncurry(|(x, y): (BigInt, BigInt)| x.min(y)).await
 }
 
-async fn int_max(mut handle: Handle) {
-    let x = handle.receive().int().await;
-    let y = handle.receive().int().await;
-    handle.provide_int(x.max(y));
+async fn int_max(handle: Handle) {
+    handle.uncurry(|(x, y): (BigInt, BigInt)| x.max(y)).await
 }
 
-async fn int_clamp(mut handle: Handle) {
-    let int = handle.receive().int().await;
-    let min = handle.receive().int().await;
-    let max = handle.receive().int().await;
-    handle.provide_int(int.min(max).max(min));
+async fn int_clamp(handle: Handle) {
+    handle
+        .uncurry(|(int, min, max): (BigInt, BigInt, BigInt)| int.min(max).max(min))
+        .await
 }
 
-async fn int_abs(mut handle: Handle) {
-    let int = handle.receive().int().await;
-    let (_sign, magnitude) = int.into_parts();
-    handle.provide_nat(magnitude);
+async fn int_abs(handle: Handle) {
+    handle.unary(|int: BigInt| int.into_parts().1).await
 }
 
 async fn int_range(mut handle: Handle) {
-    let lo = handle.receive().int().await;
-    let hi = handle.receive().int().await;
+    let (lo, hi): (BigInt, BigInt) = handle.receive_args().await;
 
     let mut i = lo;
     while i < hi {
@@ -92,16 +86,8 @@ async fn int_range(mut handle: Handle) {
     handle.break_();
 }
 
-async fn int_from_string(mut handle: Handle) {
-    let string = handle.receive().string().await;
-    match string.as_str().parse::<BigInt>() {
-        Ok(num) => {
-            handle.signal(literal!("some"));
-            handle.provide_int(num);
-        }
-        Err(_) => {
-            handle.signal(literal!("none"));
-            handle.break_();
-        }
-    };
+async fn int_from_string(handle: Handle) {
+    handle
+        .unary(|string: ParString| string.as_str().parse::<BigInt>().ok())
+        .await
 }
@@ -2,6 +2,8 @@
 use arcstr::literal;
 use par_runtime::readback::{Data, Handle};
 use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::future::Future;
 
 macro_rules! core_list_external {
@@ -18,12 +20,17 @@ macro_rules! core_list_external {
     };
 }
 
+core_list_external!("Length", list_length);
+core_list_external!("Concat", list_concat);
+core_list_external!("Reverse", list_reverse);
 core_list_external!("Sort", list_sort, false);
 core_list_external!("SortDesc", list_sort, true);
 core_list_external!("SortBy", list_sort_by, false);
 core_list_external!("SortDescBy", list_sort_by, true);
 core_list_external!("SortLinearBy", list_sort_linear_by, false);
 core_list_external!("SortLinearDescBy", list_sort_linear_by, true);
+core_list_external!("SortWith", list_sort_with);
+core_list_external!("BinarySearchBy", list_binary_search_by);
 
 pub(super) async fn readback_list<T, F>(
     mut handle: Handle,
@@ -48,6 +55,33 @@ where
     }
 }
 
+async fn list_length(mut handle: Handle) {
+    let items = readback_list(handle.receive(), |item| async { item.erase() }).await;
+    handle.provide_nat(items.len().into());
+}
+
+/// Passes each item on as soon as it's read, instead of reading all the lists
+/// first.
+async fn list_concat(mut handle: Handle) {
+    let mut lists = handle.receive();
+    while lists.case().await == "item" {
+        let mut list = lists.receive();
+        while list.case().await == "item" {
+            handle.signal(literal!("item"));
+            handle.send().link(list.receive());
+        }
+        list.continue_();
+    }
+    lists.continue_();
+    handle.signal(literal!("end"));
+    handle.break_();
+}
+
+async fn list_reverse(mut handle: Handle) {
+    let items = readback_list(handle.receive(), |item| async { item }).await;
+    provide_handle_list(handle, items.into_iter().rev());
+}
+
 async fn list_sort(mut handle: Handle, descending: bool) {
     let mut items = readback_list(handle.receive(), |handle| async { handle.data().await }).await;
     sort_by_key(&mut items, descending, |item| item);
@@ -89,6 +123,107 @@ async fn list_sort_linear_by(mut handle: Handle, descending: bool) {
     provide_handle_list(handle, keyed.into_iter().map(|(_, item)| item));
 }
 
+async fn list_sort_with(mut handle: Handle) {
+    let items = readback_list(handle.receive(), |item| async { item }).await;
+    let mut compare = handle.receive();
+    let sorted = merge_sort_with(items, &mut compare).await;
+    compare.erase();
+    provide_handle_list(handle, sorted);
+}
+
+async fn list_binary_search_by(mut handle: Handle) {
+    let mut items = readback_list(handle.receive(), |item| async { item }).await;
+    let mut probe = handle.receive();
+
+    let (mut low, mut high) = (0, items.len());
+    let mut found = None;
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let mut f = probe.duplicate();
+        f.send().link(items[middle].duplicate());
+        match ordering(&f.case().await) {
+            Some(Ordering::Less) => low = middle + 1,
+            Some(Ordering::Greater) => high = middle,
+            Some(Ordering::Equal) => found = Some(middle),
+            // Not an answer to search by, so nothing is found.
+            None => high = low,
+        }
+        f.continue_();
+        if found.is_some() {
+            break;
+        }
+    }
+
+    probe.erase();
+    for item in items.drain(..) {
+        item.erase();
+    }
+    match found {
+        Some(index) => {
+            handle.signal(literal!("ok"));
+            handle.provide_nat(index.into());
+        }
+        None => {
+            handle.signal(literal!("err"));
+            handle.provide_nat(low.into());
+        }
+    }
+}
+
+/// Stable bottom-up merge sort, asking a Par comparator for every comparison.
+async fn merge_sort_with(items: Vec<Handle>, compare: &mut Handle) -> Vec<Handle> {
+    let mut runs: Vec<Vec<Handle>> = items.into_iter().map(|item| vec![item]).collect();
+    while runs.len() > 1 {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(2));
+        let mut pending = runs.into_iter();
+        while let Some(left) = pending.next() {
+            match pending.next() {
+                Some(right) => merged.push(merge_with(left, right, compare).await),
+                None => merged.push(left),
+            }
+        }
+        runs = merged;
+    }
+    runs.pop().unwrap_or_default()
+}
+
+async fn merge_with(left: Vec<Handle>, right: Vec<Handle>, compare: &mut Handle) -> Vec<Handle> {
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = VecDeque::from(left);
+    let mut right = VecDeque::from(right);
+    while let (Some(l), Some(r)) = (left.front_mut(), right.front_mut()) {
+        let next = match call_comparator(compare, l, r).await {
+            Ordering::Greater => right.pop_front(),
+            Ordering::Less | Ordering::Equal => left.pop_front(),
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    merged
+}
+
+/// Calls a boxed `[a, a] Ordering` function on copies of two items.
+async fn call_comparator(compare: &mut Handle, left: &mut Handle, right: &mut Handle) -> Ordering {
+    let mut f = compare.duplicate();
+    f.send().link(left.duplicate());
+    f.send().link(right.duplicate());
+    // Not an answer to sort by, so the items stay in the order they're in.
+    let ordering = ordering(&f.case().await).unwrap_or(Ordering::Equal);
+    f.continue_();
+    ordering
+}
+
+/// The `Ordering` a comparator answered with, if it's one of its labels.
+fn ordering(label: &str) -> Option<Ordering> {
+    match label {
+        "less" => Some(Ordering::Less),
+        "equal" => Some(Ordering::Equal),
+        "greater" => Some(Ordering::Greater),
+        _ => None,
+    }
+}
+
 fn sort_by_key<T>(items: &mut [T], descending: bool, key: impl Fn(&T) -> &Data) {
     items.sort_by(|left, right| {
         if descending {
@@ -6,6 +6,7 @@ use num_integer::Integer;
 use num_traits::Zero;
 use par_core::frontend::{ExternalTypeDef, PrimitiveType, Type};
 use par_core::source::Span;
+use par_runtime::primitive::ParString;
 use par_runtime::readback::Handle;
 use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 
@@ -43,33 +44,32 @@ core_nat_external!("RepeatLazy", nat_repeat_lazy);
 core_nat_external!("Range", nat_range);
 core_nat_external!("FromString", nat_from_string);
 
-async fn nat_mod(mut handle: Handle) {
-    let x = handle.receive().nat().await;
-    let y = handle.receive().nat().await;
-    handle.provide_nat(if y.is_zero() { y } else { x % y });
+async fn nat_mod(handle: Handle) {
+    handle
+        .uncurry(|(x, y): (BigUint, BigUint)| if y.is_zero() { y } else { x % y })
+        .await
 }
 
-async fn nat_min(mut handle: Handle) {
-    let x = handle.receive().nat().await;
-    let y = handle.receive().nat().await;
-    handle.provide_nat(x.min(y));
+async fn nat_min(handle: Handle) {
+    handle.uncurry(|(x, y): (BigUint, BigUint)| x.min(y)).await
 }
 
-async fn nat_max(mut handle: Handle) {
-    let x = handle.receive().nat().await;
-    let y = handle.receive().int().await;
-    // max of int and nat is always nat, so we can ignore the sign.
-    let (_sign, max) = BigInt::from(x).max(y).into_parts();
-    handle.provide_nat(max);
+async fn nat_max(handle: Handle) {
+    handle
+        .uncurry(|(x, y): (BigUint, BigInt)| {
+            // max of int and nat is always nat, so we can ignore the sign.
+            BigInt::from(x).max(y).into_parts().1
+        })
+        .await
 }
 
-async fn nat_clamp(mut handle: Handle) {
-    let int = handle.receive().int().await;
-    let min = handle.receive().nat().await;
-    let max = handle.receive().nat().await;
-    // int clamped to two nats is always nat, so we can ignore the sign.
-    let (_sign, clamped) = int.clamp(min.into(), max.into()).into_parts();
-    handle.provide_nat(clamped);
+async fn nat_clamp(handle: Handle) {
+    handle
+        .uncurry(|(int, min, max): (BigInt, BigUint, BigUint)| {
+            // int clamped to two nats is always nat, so we can ignore the sign.
+            int.clamp(min.into(), max.into()).into_parts().1
+        })
+        .await
 }
 
 async fn nat_repeat(mut handle: Handle) {
@@ -106,8 +106,7 @@ fn nat_repeat_lazy_inner(mut handle: Handle, n: BigUint) {
 }
 
 async fn nat_range(mut handle: Handle) {
-    let lo = handle.receive().nat().await;
-    let hi = handle.receive().nat().await;
+    let (lo, hi): (BigUint, BigUint) = handle.receive_args().await;
 
     let mut i = lo;
     while i < hi {
@@ -119,16 +118,8 @@ async fn nat_range(mut handle: Handle) {
     handle.break_();
 }
 
-async fn nat_from_string(mut handle: Handle) {
-    let string = handle.receive().string().await;
-    match string.as_str().parse::<BigUint>() {
-        Ok(num) => {
-            handle.signal(literal!("some"));
-            handle.provide_nat(num);
-        }
-        Err(_) => {
-            handle.signal(literal!("none"));
-            handle.break_();
-        }
-    };
+async fn nat_from_string(handle: Handle) {
+    handle
+        .unary(|string: ParString| string.as_str().parse::<BigUint>().ok())
+        .await
 }
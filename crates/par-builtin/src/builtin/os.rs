@@ -1,6 +1,16 @@
 //package: basic
+//! The file system, the standard streams and the environment variables of
+//! the host.
+//!
+//! Everything here reaches outside the net, so it's had through the run's
+//! [`Effects`](par_runtime::effects::Effects): a run replaying an earlier one
+//! is answered with what the earlier one got, and doesn't touch the file
+//! system again. A file a replayed run opened is only opened for real once
+//! its reads or writes stop being replayed: reading picks up where the
+//! replayed reads left off, and writing appends.
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
+    io::SeekFrom,
     path::{Path, PathBuf},
 };
 
@@ -12,10 +22,12 @@ use par_runtime::primitive::ParString;
 use par_runtime::readback::Handle;
 use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
 use tokio::{
-    fs::{self, DirEntry, File, OpenOptions, ReadDir},
-    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    fs::{self, DirEntry, File, OpenOptions},
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader},
 };
 
+use crate::builtin::string::{provide_line, provide_lines_end};
+
 macro_rules! basic_os_external {
     ($name:literal, $f:path $(, $arg:expr)*) => {
         inventory::submit!(ExternalDef {
@@ -35,6 +47,7 @@ basic_os_external!("Stdin", os_stdin);
 basic_os_external!("Stdout", os_stdout);
 basic_os_external!("Stderr", os_stderr);
 basic_os_external!("OpenFile", os_open_file);
+basic_os_external!("ReadLines", os_read_lines);
 basic_os_external!("CreateOrReplaceFile", os_create_or_replace_file);
 basic_os_external!("CreateNewFile", os_create_new_file);
 basic_os_external!("AppendToFile", os_append_to_file);
@@ -139,7 +152,83 @@ fn os_to_bytes(os: &OsStr) -> Bytes {
     Bytes::from(os.to_string_lossy().as_ref())
 }
 
-async fn provide_bytes_reader_from_async(mut handle: Handle, mut reader: impl AsyncRead + Unpin) {
+/// Where bytes are read from.
+enum Reader {
+    Stdin(tokio::io::Stdin),
+    /// A file, opened once it's first read for real, at `offset`.
+    File {
+        path: PathBuf,
+        file: Option<File>,
+        offset: u64,
+    },
+}
+
+impl Reader {
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Reader::Stdin(stdin) => stdin.read(buf).await,
+            Reader::File { path, file, offset } => {
+                if file.is_none() {
+                    let mut opened = File::open(&*path).await?;
+                    opened.seek(SeekFrom::Start(*offset)).await?;
+                    *file = Some(opened);
+                }
+                let Some(file) = file else { unreachable!() };
+                file.read(buf).await
+            }
+        }
+    }
+
+    /// Moves past `n` bytes, read for real or replayed.
+    fn advance(&mut self, n: usize) {
+        if let Reader::File { offset, .. } = self {
+            *offset += n as u64;
+        }
+    }
+}
+
+/// Where bytes are written to.
+enum Writer {
+    Stdout(tokio::io::Stdout),
+    Stderr(tokio::io::Stderr),
+    /// A file, opened for appending once it's first written to for real, if
+    /// it wasn't open already.
+    File {
+        path: PathBuf,
+        file: Option<File>,
+    },
+}
+
+impl Writer {
+    async fn writer(&mut self) -> std::io::Result<&mut (dyn AsyncWrite + Send + Unpin)> {
+        Ok(match self {
+            Writer::Stdout(stdout) => stdout,
+            Writer::Stderr(stderr) => stderr,
+            Writer::File { path, file } => {
+                if file.is_none() {
+                    let options = OpenOptions::new().append(true).clone();
+                    *file = Some(options.open(&*path).await?);
+                }
+                let Some(file) = file else { unreachable!() };
+                file
+            }
+        })
+    }
+
+    async fn write_all(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let result = async { self.writer().await?.write_all(bytes).await };
+        result.await.map_err(|err| err.to_string())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        let result = async { self.writer().await?.flush().await };
+        result.await.map_err(|err| err.to_string())
+    }
+}
+
+/// Provides a reader of `reader`, whose reads are had under `key`.
+async fn provide_bytes_reader(mut handle: Handle, key: String, mut reader: Reader) {
+    let effects = handle.effects().clone();
     let mut buf = vec![0u8; 512];
     loop {
         match handle.case().await.as_str() {
@@ -147,66 +236,88 @@ async fn provide_bytes_reader_from_async(mut handle: Handle, mut reader: impl As
                 handle.signal(literal!("ok"));
                 return handle.break_();
             }
-            "read" => match reader.read(&mut buf[..]).await {
-                Ok(n) => {
-                    if n == 0 {
+            "read" => {
+                let read = effects
+                    .have(key.as_str(), || async {
+                        match reader.read(&mut buf[..]).await {
+                            Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                            Err(err) => Err(err.to_string()),
+                        }
+                    })
+                    .await;
+                match read {
+                    Ok(bytes) => {
+                        reader.advance(bytes.len());
+                        if bytes.is_empty() {
+                            handle.signal(literal!("ok"));
+                            handle.signal(literal!("end"));
+                            return handle.break_();
+                        }
                         handle.signal(literal!("ok"));
-                        handle.signal(literal!("end"));
-                        return handle.break_();
+                        handle.signal(literal!("chunk"));
+                        handle.send().provide_bytes(bytes);
+                        continue;
+                    }
+                    Err(err) => {
+                        handle.signal(literal!("err"));
+                        return handle.provide_string(ParString::from(err));
                     }
-                    handle.signal(literal!("ok"));
-                    handle.signal(literal!("chunk"));
-                    handle
-                        .send()
-                        .provide_bytes(Bytes::copy_from_slice(&buf[..n]));
-                    continue;
-                }
-                Err(err) => {
-                    handle.signal(literal!("err"));
-                    return handle.provide_string(ParString::from(err.to_string()));
                 }
-            },
+            }
             _ => unreachable!(),
         }
     }
 }
 
-async fn provide_bytes_writer_from_async(mut handle: Handle, mut writer: impl AsyncWrite + Unpin) {
+/// Provides a writer to `writer`, whose writes are had under `key`.
+async fn provide_bytes_writer(mut handle: Handle, key: String, mut writer: Writer) {
+    let effects = handle.effects().clone();
     loop {
         match handle.case().await.as_str() {
             "close" => {
                 // Try to flush pending data before closing
-                match writer.flush().await {
+                let closed = effects
+                    .have(format!("{key} close"), || writer.flush())
+                    .await;
+                match closed {
                     Ok(()) => {
                         handle.signal(literal!("ok"));
                         return handle.break_();
                     }
                     Err(err) => {
                         handle.signal(literal!("err"));
-                        return handle.provide_string(ParString::from(err.to_string()));
+                        return handle.provide_string(ParString::from(err));
                     }
                 }
             }
-            "flush" => match writer.flush().await {
-                Ok(()) => {
-                    handle.signal(literal!("ok"));
-                    continue;
-                }
-                Err(err) => {
-                    handle.signal(literal!("err"));
-                    return handle.provide_string(ParString::from(err.to_string()));
+            "flush" => {
+                let flushed = effects
+                    .have(format!("{key} flush"), || writer.flush())
+                    .await;
+                match flushed {
+                    Ok(()) => {
+                        handle.signal(literal!("ok"));
+                        continue;
+                    }
+                    Err(err) => {
+                        handle.signal(literal!("err"));
+                        return handle.provide_string(ParString::from(err));
+                    }
                 }
-            },
+            }
             "write" => {
                 let bytes = handle.receive().bytes().await;
-                match writer.write_all(bytes.as_ref()).await {
+                let written = effects
+                    .have(format!("{key} write"), || writer.write_all(bytes.as_ref()))
+                    .await;
+                match written {
                     Ok(()) => {
                         handle.signal(literal!("ok"));
                         continue;
                     }
                     Err(err) => {
                         handle.signal(literal!("err"));
-                        return handle.provide_string(ParString::from(err.to_string()));
+                        return handle.provide_string(ParString::from(err));
                     }
                 }
             }
@@ -215,7 +326,41 @@ async fn provide_bytes_writer_from_async(mut handle: Handle, mut writer: impl As
     }
 }
 
-fn provide_unit_io_result(mut handle: Handle, result: std::io::Result<()>) {
+/// Opens the file at `path` with `options`, as an effect under `key`. The
+/// file is `None` if the run was answered with an earlier run's opening.
+async fn open_file(
+    handle: &Handle,
+    key: String,
+    path: &Path,
+    options: &OpenOptions,
+) -> Result<Option<File>, String> {
+    let mut opened = None;
+    let slot = &mut opened;
+    handle
+        .effects()
+        .have(key, move || async move {
+            *slot = Some(options.open(path).await.map_err(|err| err.to_string())?);
+            Ok::<(), String>(())
+        })
+        .await?;
+    Ok(opened)
+}
+
+/// Changes the file system with `change`, as an effect under `key`.
+async fn change(
+    handle: &Handle,
+    key: String,
+    change: impl Future<Output = std::io::Result<()>>,
+) -> Result<(), String> {
+    handle
+        .effects()
+        .have(key, move || async move {
+            change.await.map_err(|err| err.to_string())
+        })
+        .await
+}
+
+fn provide_unit_io_result(mut handle: Handle, result: Result<(), String>) {
     match result {
         Ok(()) => {
             handle.signal(literal!("ok"));
@@ -223,14 +368,15 @@ fn provide_unit_io_result(mut handle: Handle, result: std::io::Result<()>) {
         }
         Err(err) => {
             handle.signal(literal!("err"));
-            handle.provide_string(ParString::from(err.to_string()));
+            handle.provide_string(ParString::from(err));
         }
     }
 }
 
-// Provide List<Os.Path> for the directory entries of `base` using a pre-opened ReadDir.
-async fn provide_list_dir(mut handle: Handle, base: &Path, rd: &mut ReadDir) {
-    let mut entries: Vec<(Bytes, std::ffi::OsString)> = Vec::new();
+// The names of the entries of the directory at `path`.
+async fn list_dir(path: &Path) -> Result<Vec<OsString>, String> {
+    let mut rd = fs::read_dir(path).await.map_err(|e| e.to_string())?;
+    let mut entries: Vec<(Bytes, OsString)> = Vec::new();
     while let Ok(Some(entry)) = rd.next_entry().await {
         let name = entry.file_name();
         // Sort key: raw bytes if available, fallback to lossy string
@@ -239,8 +385,12 @@ async fn provide_list_dir(mut handle: Handle, base: &Path, rd: &mut ReadDir) {
     }
     // Sort deterministically by the byte-representation of file name
     entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+    Ok(entries.into_iter().map(|(_, name)| name).collect())
+}
 
-    for (_, name) in entries {
+// Provide List<Os.Path> for the directory entries of `base` with the given names.
+fn provide_list_dir(mut handle: Handle, base: &Path, names: Vec<OsString>) {
+    for name in names {
         let child = base.join(Path::new(&name));
         handle.signal(literal!("item"));
         provide_path(handle.send(), child);
@@ -250,6 +400,7 @@ async fn provide_list_dir(mut handle: Handle, base: &Path, rd: &mut ReadDir) {
 }
 
 // Directory tree node used for traverseDir
+#[derive(Clone)]
 enum DirNode {
     File(PathBuf),
     Dir {
@@ -314,162 +465,191 @@ fn provide_dir_tree<'a>(mut handle: Handle, nodes: &'a [DirNode]) {
 }
 
 async fn os_stdin(handle: Handle) {
-    provide_bytes_reader_from_async(handle, tokio::io::stdin()).await;
+    let reader = Reader::Stdin(tokio::io::stdin());
+    provide_bytes_reader(handle, String::from("Os.Stdin"), reader).await;
 }
 
 async fn os_stdout(handle: Handle) {
-    provide_bytes_writer_from_async(handle, tokio::io::stdout()).await;
+    let writer = Writer::Stdout(tokio::io::stdout());
+    provide_bytes_writer(handle, String::from("Os.Stdout"), writer).await;
 }
 
 async fn os_stderr(handle: Handle) {
-    provide_bytes_writer_from_async(handle, tokio::io::stderr()).await;
+    let writer = Writer::Stderr(tokio::io::stderr());
+    provide_bytes_writer(handle, String::from("Os.Stderr"), writer).await;
 }
 
 async fn os_open_file(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match File::open(&path).await {
+    let key = format!("Os.OpenFile {}", path.display());
+    let options = OpenOptions::new().read(true).clone();
+    match open_file(&handle, key.clone(), &path, &options).await {
         Ok(file) => {
             handle.signal(literal!("ok"));
-            return provide_bytes_reader_from_async(handle, file).await;
+            let reader = Reader::File {
+                path,
+                file,
+                offset: 0,
+            };
+            return provide_bytes_reader(handle, format!("{key} read"), reader).await;
         }
         Err(err) => {
             handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
+            return handle.provide_string(ParString::from(err));
         }
     }
 }
 
-async fn os_create_or_replace_file(mut handle: Handle) {
+async fn os_read_lines(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&path)
-        .await
-    {
-        Ok(file) => {
-            handle.signal(literal!("ok"));
-            return provide_bytes_writer_from_async(handle, file).await;
-        }
+    let key = format!("Os.ReadLines {}", path.display());
+    let options = OpenOptions::new().read(true).clone();
+    let file = match open_file(&handle, key.clone(), &path, &options).await {
+        Ok(file) => file,
         Err(err) => {
             handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
+            return handle.provide_string(ParString::from(err));
         }
-    }
+    };
+    handle.signal(literal!("ok"));
+
+    // Once the lines stop being replayed, the file is opened past those.
+    let mut lines = file.map(|file| BufReader::new(file).lines());
+    let mut read = 0;
+    let effects = handle.effects().clone();
+    let result = loop {
+        let next = effects
+            .have(format!("{key} line"), || async {
+                if lines.is_none() {
+                    let file = File::open(&path).await.map_err(|err| err.to_string())?;
+                    let mut skipped = BufReader::new(file).lines();
+                    for _ in 0..read {
+                        skipped.next_line().await.map_err(|err| err.to_string())?;
+                    }
+                    lines = Some(skipped);
+                }
+                let Some(lines) = &mut lines else {
+                    unreachable!()
+                };
+                lines.next_line().await.map_err(|err| err.to_string())
+            })
+            .await;
+        read += 1;
+        match next {
+            Ok(Some(line)) => handle = provide_line(handle, &line),
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
+        }
+    };
+    provide_lines_end(handle, result);
 }
 
-async fn os_create_new_file(mut handle: Handle) {
-    let path = pathbuf_from_os_path(handle.receive()).await;
-    match OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .open(&path)
-        .await
-    {
+/// Opens the file at `path` for writing, with `options`, and provides the
+/// writer.
+async fn provide_file_writer(mut handle: Handle, path: PathBuf, options: OpenOptions) {
+    let key = format!("Os.Write {}", path.display());
+    match open_file(&handle, key.clone(), &path, &options).await {
         Ok(file) => {
             handle.signal(literal!("ok"));
-            return provide_bytes_writer_from_async(handle, file).await;
+            let writer = Writer::File { path, file };
+            return provide_bytes_writer(handle, key, writer).await;
         }
         Err(err) => {
             handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
+            return handle.provide_string(ParString::from(err));
         }
     }
 }
 
+async fn os_create_or_replace_file(mut handle: Handle) {
+    let path = pathbuf_from_os_path(handle.receive()).await;
+    let mut options = OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    provide_file_writer(handle, path, options).await;
+}
+
+async fn os_create_new_file(mut handle: Handle) {
+    let path = pathbuf_from_os_path(handle.receive()).await;
+    let mut options = OpenOptions::new();
+    options.create_new(true).write(true);
+    provide_file_writer(handle, path, options).await;
+}
+
 async fn os_append_to_file(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open(&path)
-        .await
-    {
-        Ok(file) => {
-            handle.signal(literal!("ok"));
-            return provide_bytes_writer_from_async(handle, file).await;
-        }
-        Err(err) => {
-            handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
-        }
-    }
+    let mut options = OpenOptions::new();
+    options.write(true).append(true);
+    provide_file_writer(handle, path, options).await;
 }
 
 async fn os_create_or_append_to_file(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(&path)
-        .await
-    {
-        Ok(file) => {
-            handle.signal(literal!("ok"));
-            return provide_bytes_writer_from_async(handle, file).await;
-        }
-        Err(err) => {
-            handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
-        }
-    }
+    let mut options = OpenOptions::new();
+    options.create(true).write(true).append(true);
+    provide_file_writer(handle, path, options).await;
 }
 
 async fn os_create_dir(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match fs::create_dir_all(&path).await {
-        Ok(()) => {
-            handle.signal(literal!("ok"));
-            return handle.break_();
-        }
-        Err(err) => {
-            handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
-        }
-    }
+    let key = format!("Os.CreateDir {}", path.display());
+    let result = change(&handle, key, fs::create_dir_all(&path)).await;
+    provide_unit_io_result(handle, result);
 }
 
 async fn os_remove_file(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    provide_unit_io_result(handle, fs::remove_file(&path).await);
+    let key = format!("Os.RemoveFile {}", path.display());
+    let result = change(&handle, key, fs::remove_file(&path)).await;
+    provide_unit_io_result(handle, result);
 }
 
 async fn os_remove_dir(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    provide_unit_io_result(handle, fs::remove_dir(&path).await);
+    let key = format!("Os.RemoveDir {}", path.display());
+    let result = change(&handle, key, fs::remove_dir(&path)).await;
+    provide_unit_io_result(handle, result);
 }
 
 async fn os_move_file(mut handle: Handle) {
     let src = pathbuf_from_os_path(handle.receive()).await;
     let dst = pathbuf_from_os_path(handle.receive()).await;
-    provide_unit_io_result(handle, fs::rename(&src, &dst).await);
+    let key = format!("Os.MoveFile {} {}", src.display(), dst.display());
+    let result = change(&handle, key, fs::rename(&src, &dst)).await;
+    provide_unit_io_result(handle, result);
 }
 
 async fn os_move_dir(mut handle: Handle) {
     let src = pathbuf_from_os_path(handle.receive()).await;
     let dst = pathbuf_from_os_path(handle.receive()).await;
-    provide_unit_io_result(handle, fs::rename(&src, &dst).await);
+    let key = format!("Os.MoveDir {} {}", src.display(), dst.display());
+    let result = change(&handle, key, fs::rename(&src, &dst)).await;
+    provide_unit_io_result(handle, result);
 }
 
 async fn os_list_dir(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match fs::read_dir(&path).await {
-        Ok(mut rd) => {
+    let key = format!("Os.ListDir {}", path.display());
+    let listed = handle.effects().have(key, || list_dir(&path)).await;
+    match listed {
+        Ok(names) => {
             handle.signal(literal!("ok"));
-            return provide_list_dir(handle, &path, &mut rd).await;
+            return provide_list_dir(handle, &path, names);
         }
         Err(err) => {
             handle.signal(literal!("err"));
-            return handle.provide_string(ParString::from(err.to_string()));
+            return handle.provide_string(ParString::from(err));
         }
     }
 }
 
 async fn os_traverse_dir(mut handle: Handle) {
     let path = pathbuf_from_os_path(handle.receive()).await;
-    match build_dir_tree(path.clone()).await {
+    let key = format!("Os.TraverseDir {}", path.display());
+    let traversed = handle
+        .effects()
+        .have(key, || build_dir_tree(path.clone()))
+        .await;
+    match traversed {
         Ok(nodes) => {
             handle.signal(literal!("ok"));
             return provide_dir_tree(handle, nodes.as_slice());
@@ -485,10 +665,11 @@ async fn envmap_new(handle: Handle) {
     handle.provide_box(move |mut handle| async move {
         match handle.case().await.as_str() {
             "size" => {
-                return handle.provide_nat(BigUint::from(std::env::vars_os().count()));
+                let size = handle.effects().have_now("Os.Env", env_vars).len();
+                return handle.provide_nat(BigUint::from(size));
             }
             "keys" => {
-                let vars: Vec<_> = std::env::vars_os().into_iter().collect();
+                let vars = handle.effects().have_now("Os.Env", env_vars);
                 for (name, _) in vars {
                     handle.signal(literal!("item"));
                     handle.send().provide_bytes(os_to_bytes(&name));
@@ -497,7 +678,7 @@ async fn envmap_new(handle: Handle) {
                 return handle.break_();
             }
             "list" => {
-                let vars: Vec<_> = std::env::vars_os().into_iter().collect();
+                let vars = handle.effects().have_now("Os.Env", env_vars);
                 for (name, value) in vars {
                     handle.signal(literal!("item"));
                     let mut pair = handle.send();
@@ -510,7 +691,8 @@ async fn envmap_new(handle: Handle) {
             "get" => {
                 let name = handle.receive().bytes().await;
                 let name_os: &OsStr = unsafe { OsStr::from_encoded_bytes_unchecked(name.as_ref()) };
-                match std::env::var_os(name_os) {
+                let key = format!("Os.Env {}", name_os.display());
+                match handle.effects().have_now(key, || std::env::var_os(name_os)) {
                     Some(val) => {
                         let bytes = os_to_bytes(&val);
                         handle.signal(literal!("some"));
@@ -527,6 +709,10 @@ async fn envmap_new(handle: Handle) {
     });
 }
 
+fn env_vars() -> Vec<(OsString, OsString)> {
+    std::env::vars_os().collect()
+}
+
 async fn pathbuf_from_os_path(mut handle: Handle) -> PathBuf {
     handle.signal(literal!("absolute"));
     let path_bytes = handle.bytes().await;
@@ -0,0 +1,14 @@
+//package: core
+use par_core::frontend::{ExternalTypeFunction, TypeFunction};
+use par_runtime::registry::{DefinitionRef, PackageRef};
+
+inventory::submit!(ExternalTypeFunction {
+    path: DefinitionRef {
+        package: PackageRef::CORE,
+        path: &[],
+        module: "Protocol",
+        name: "Instrument"
+    },
+    doc: r"The protocol `t`, with a choice to log a `String` before each of its steps: `.log(String)`, after which the choice comes again, or `.step`, which goes on with the step of `t`.",
+    function: TypeFunction::Instrument
+});
@@ -0,0 +1,753 @@
+//package: basic
+//! Sessions between two programs, over a TCP connection.
+//!
+//! The serving side knows the session's type, and reads it back from the
+//! served definition step by step. Each step becomes a frame telling the
+//! connecting side what to do on its end: send or receive, signal or wait for
+//! a signal, provide or ask for a primitive. The connecting side doesn't need
+//! the type, only to follow the frames, and answers the ones that wait for it.
+//!
+//! Every frame is its length, followed by the channel it's about and what to
+//! do, all laid out like `@core/Codec` lays out values. Channel `0` is the
+//! whole session, and sending or receiving opens a new channel for the part
+//! that goes separately.
+//!
+//! Before any frame, both sides send a hello with the version of the protocol
+//! they speak, the version of Par they run, and the session's type as they
+//! know it. A side that speaks a different protocol, or none, is turned away
+//! with an error saying so, instead of following frames it would misread, and
+//! so is a side that has the session at a different type. The serving side
+//! has the type from the definition it serves, and the connecting side from
+//! the compiler, which gives `Remote.Connect` the [`SessionType`] of its type
+//! argument.
+//!
+//! Past the hello, the connecting side trusts the serving side to keep to
+//! the type they agreed on. Its frames are checked to be well-formed and to
+//! be about channels that are open, but not against the type: the type's
+//! layout only tells whether two types are the same, and can't be followed
+//! step by step. A serving side that sends other steps than its type's makes
+//! the connecting program go wrong, so only connect to servers you trust.
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use arcstr::{ArcStr, literal};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::StreamExt;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{BoxFuture, try_join};
+use num_bigint::{BigInt, BigUint};
+use par_core::frontend::PrimitiveType;
+use par_core::runtime::{SessionType, SessionTypeArgument, TypedHandle, TypedReadback};
+use par_runtime::data::Data;
+use par_runtime::primitive::{ParString, Primitive};
+use par_runtime::readback::Handle;
+use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+
+use crate::builtin::codec::{
+    length_prefixed, read_length_prefixed, read_nat, unzigzag, write_nat, zigzag,
+};
+
+macro_rules! basic_remote_external {
+    ($name:literal, $f:path $(, $arg:expr)*) => {
+        inventory::submit!(ExternalDef {
+            path: DefinitionRef {
+                package: PackageRef::BASIC,
+                path: &[],
+                module: "Remote",
+                name: $name,
+            },
+            f: |handle| Box::pin($f(handle $(, $arg)*)),
+        });
+    };
+}
+
+basic_remote_external!("Connect", remote_connect);
+
+inventory::submit!(SessionTypeArgument {
+    path: DefinitionRef {
+        package: PackageRef::BASIC,
+        path: &[],
+        module: "Remote",
+        name: "Connect",
+    },
+});
+
+/// Longest frame accepted from the other side.
+const MAX_FRAME_LENGTH: usize = 64 << 20;
+
+/// Starts the hello, so anything that isn't a Par session is recognized.
+const HELLO_MAGIC: &[u8] = b"par-remote";
+
+/// Bump whenever the frames change, so both sides find out before the first
+/// one. To keep talking to sides that speak an older protocol, accept its
+/// version in [`handshake`] and decode its frames by it.
+const PROTOCOL_VERSION: u64 = 2;
+
+/// One step of a session, on one of its channels.
+#[derive(Debug, PartialEq)]
+struct Frame {
+    channel: u64,
+    step: Step,
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// A pair: the part sent first goes on the new channel.
+    Send(u64),
+    /// A function: the argument goes on the new channel.
+    Receive(u64),
+    Signal(ArcStr),
+    /// Waits for the other side to choose, and to answer with a `Signal`.
+    Case,
+    Break,
+    Continue,
+    Value(Value),
+    /// Waits for the other side to answer with a `Value` of this type.
+    Request(PrimitiveType),
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Nat(BigUint),
+    Int(BigInt),
+    Float(f64),
+    String(ParString),
+    Char(char),
+    Byte(u8),
+    Bytes(Bytes),
+}
+
+impl Value {
+    fn primitive_type(&self) -> PrimitiveType {
+        match self {
+            Self::Nat(_) => PrimitiveType::Nat,
+            Self::Int(_) => PrimitiveType::Int,
+            Self::Float(_) => PrimitiveType::Float,
+            Self::String(_) => PrimitiveType::String,
+            Self::Char(_) => PrimitiveType::Char,
+            Self::Byte(_) => PrimitiveType::Byte,
+            Self::Bytes(_) => PrimitiveType::Bytes,
+        }
+    }
+
+    async fn read(handle: Handle, typ: PrimitiveType) -> Self {
+        match typ {
+            PrimitiveType::Nat => Self::Nat(handle.nat().await),
+            PrimitiveType::Int => Self::Int(handle.int().await),
+            PrimitiveType::Float => Self::Float(handle.float().await),
+            PrimitiveType::String => Self::String(handle.string().await),
+            PrimitiveType::Char => Self::Char(handle.char().await),
+            PrimitiveType::Byte => Self::Byte(handle.byte().await),
+            PrimitiveType::Bytes => Self::Bytes(handle.bytes().await),
+        }
+    }
+
+    fn provide(self, handle: Handle) {
+        match self {
+            Self::Nat(value) => handle.provide_nat(value),
+            Self::Int(value) => handle.provide_int(value),
+            Self::Float(value) => handle.provide_float(value),
+            Self::String(value) => handle.provide_string(value),
+            Self::Char(value) => handle.provide_char(value),
+            Self::Byte(value) => handle.provide_byte(value),
+            Self::Bytes(value) => handle.provide_bytes(value),
+        }
+    }
+}
+
+impl Frame {
+    fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        write_nat(&mut buf, &self.channel.into());
+        match &self.step {
+            Step::Send(channel) => {
+                buf.put_u8(0);
+                write_nat(&mut buf, &(*channel).into());
+            }
+            Step::Receive(channel) => {
+                buf.put_u8(1);
+                write_nat(&mut buf, &(*channel).into());
+            }
+            Step::Signal(label) => {
+                buf.put_u8(2);
+                buf.put(length_prefixed(label.as_bytes()));
+            }
+            Step::Case => buf.put_u8(3),
+            Step::Break => buf.put_u8(4),
+            Step::Continue => buf.put_u8(5),
+            Step::Value(value) => {
+                buf.put_u8(6);
+                encode_value(&mut buf, value);
+            }
+            Step::Request(typ) => {
+                buf.put_u8(7);
+                buf.put_u8(primitive_tag(typ));
+            }
+        }
+        buf.freeze()
+    }
+
+    fn decode(bytes: Bytes) -> Option<Self> {
+        let (channel, bytes) = read_u64(&bytes)?;
+        let tag = *bytes.first()?;
+        let rest = bytes.slice(1..);
+        let (step, rest) = match tag {
+            0 => read_u64(&rest).map(|(channel, rest)| (Step::Send(channel), rest))?,
+            1 => read_u64(&rest).map(|(channel, rest)| (Step::Receive(channel), rest))?,
+            2 => {
+                let (label, rest) = read_length_prefixed(&rest)?;
+                let label = std::str::from_utf8(&label).ok()?;
+                (Step::Signal(ArcStr::from(label)), rest)
+            }
+            3 => (Step::Case, rest),
+            4 => (Step::Break, rest),
+            5 => (Step::Continue, rest),
+            6 => decode_value(&rest).map(|(value, rest)| (Step::Value(value), rest))?,
+            7 => {
+                let typ = primitive_from_tag(*rest.first()?)?;
+                (Step::Request(typ), rest.slice(1..))
+            }
+            _ => return None,
+        };
+        rest.is_empty().then_some(Self { channel, step })
+    }
+}
+
+/// Primitives are tagged in the order they're listed in `PrimitiveType`.
+fn primitive_tag(typ: &PrimitiveType) -> u8 {
+    match typ {
+        PrimitiveType::Nat => 0,
+        PrimitiveType::Int => 1,
+        PrimitiveType::Float => 2,
+        PrimitiveType::String => 3,
+        PrimitiveType::Char => 4,
+        PrimitiveType::Byte => 5,
+        PrimitiveType::Bytes => 6,
+    }
+}
+
+fn primitive_from_tag(tag: u8) -> Option<PrimitiveType> {
+    Some(match tag {
+        0 => PrimitiveType::Nat,
+        1 => PrimitiveType::Int,
+        2 => PrimitiveType::Float,
+        3 => PrimitiveType::String,
+        4 => PrimitiveType::Char,
+        5 => PrimitiveType::Byte,
+        6 => PrimitiveType::Bytes,
+        _ => return None,
+    })
+}
+
+/// A tag from [`primitive_tag`], followed by the value the way `@core/Codec`
+/// encodes it. A `Char` is its code point, and a `Byte` is itself.
+fn encode_value(buf: &mut BytesMut, value: &Value) {
+    buf.put_u8(primitive_tag(&value.primitive_type()));
+    match value {
+        Value::Nat(value) => write_nat(buf, value),
+        Value::Int(value) => write_nat(buf, &zigzag(value)),
+        Value::Float(value) => buf.put_slice(&value.to_le_bytes()),
+        Value::String(value) => buf.put(length_prefixed(&value.as_bytes())),
+        Value::Char(value) => write_nat(buf, &u32::from(*value).into()),
+        Value::Byte(value) => buf.put_u8(*value),
+        Value::Bytes(value) => buf.put(length_prefixed(value)),
+    }
+}
+
+fn decode_value(bytes: &Bytes) -> Option<(Value, Bytes)> {
+    let typ = primitive_from_tag(*bytes.first()?)?;
+    let bytes = bytes.slice(1..);
+    match typ {
+        PrimitiveType::Nat => read_nat(&bytes).map(|(value, rest)| (Value::Nat(value), rest)),
+        PrimitiveType::Int => {
+            read_nat(&bytes).map(|(value, rest)| (Value::Int(unzigzag(value)), rest))
+        }
+        PrimitiveType::Float => {
+            let value = bytes.get(..8)?.try_into().ok()?;
+            Some((Value::Float(f64::from_le_bytes(value)), bytes.slice(8..)))
+        }
+        PrimitiveType::String => {
+            let (value, rest) = read_length_prefixed(&bytes)?;
+            std::str::from_utf8(&value).ok()?;
+            Some((Value::String(ParString::from_utf8_lossy(value)), rest))
+        }
+        PrimitiveType::Char => {
+            let (value, rest) = read_nat(&bytes)?;
+            let value = char::from_u32(u32::try_from(value).ok()?)?;
+            Some((Value::Char(value), rest))
+        }
+        PrimitiveType::Byte => {
+            let value = *bytes.first()?;
+            Some((Value::Byte(value), bytes.slice(1..)))
+        }
+        PrimitiveType::Bytes => {
+            read_length_prefixed(&bytes).map(|(value, rest)| (Value::Bytes(value), rest))
+        }
+    }
+}
+
+fn read_u64(bytes: &Bytes) -> Option<(u64, Bytes)> {
+    let (value, rest) = read_nat(bytes)?;
+    Some((u64::try_from(value).ok()?, rest))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads the next frame, or `None` if the other side closed the connection
+/// between frames.
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<Frame>> {
+    let Some(body) = read_body(reader).await? else {
+        return Ok(None);
+    };
+    Frame::decode(body)
+        .map(Some)
+        .ok_or_else(|| invalid("malformed frame"))
+}
+
+/// Reads the length-prefixed body of the next frame.
+async fn read_body(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<Bytes>> {
+    let mut length = 0usize;
+    for index in 0.. {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(error) if index == 0 && error.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None);
+            }
+            Err(error) => return Err(error),
+        };
+        length |= usize::from(byte & 0x7f)
+            .checked_shl(7 * index)
+            .filter(|_| index < 4)
+            .ok_or_else(|| invalid("frame too long"))?;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if length > MAX_FRAME_LENGTH {
+        return Err(invalid("frame too long"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(Bytes::from(body)))
+}
+
+/// Sends the hello, and checks the one from the other side, which must have
+/// the session at the same type as this side.
+async fn handshake(
+    reader: &mut OwnedReadHalf,
+    writer: &mut OwnedWriteHalf,
+    session_type: &SessionType,
+) -> io::Result<()> {
+    writer
+        .write_all(&length_prefixed(&hello(session_type)))
+        .await?;
+
+    let body = read_body(reader)
+        .await?
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    check_hello(&body, session_type)
+}
+
+fn hello(session_type: &SessionType) -> Bytes {
+    let mut hello = BytesMut::new();
+    hello.put_slice(HELLO_MAGIC);
+    write_nat(&mut hello, &PROTOCOL_VERSION.into());
+    hello.put(length_prefixed(env!("CARGO_PKG_VERSION").as_bytes()));
+    hello.put(length_prefixed(&session_type.to_bytes()));
+    hello.freeze()
+}
+
+fn check_hello(body: &[u8], session_type: &SessionType) -> io::Result<()> {
+    let Some(body) = body.strip_prefix(HELLO_MAGIC) else {
+        return Err(invalid(
+            "the other side didn't say hello: it's not a Par session, or it was created by a \
+             version from before sessions were versioned",
+        ));
+    };
+    let (protocol, release, rest) = read_u64(&Bytes::copy_from_slice(body))
+        .and_then(|(protocol, rest)| {
+            let (release, rest) = read_length_prefixed(&rest)?;
+            Some((protocol, release, rest))
+        })
+        .ok_or_else(|| invalid("malformed hello"))?;
+    if protocol != PROTOCOL_VERSION {
+        return Err(invalid(format!(
+            "the other side was created by version {}, incompatible because it speaks session \
+             protocol {protocol}, and this side speaks {PROTOCOL_VERSION}",
+            String::from_utf8_lossy(&release),
+        )));
+    }
+    let theirs = read_length_prefixed(&rest)
+        .filter(|(_, rest)| rest.is_empty())
+        .and_then(|(theirs, _)| SessionType::from_bytes(&theirs))
+        .ok_or_else(|| invalid("malformed hello"))?;
+    if theirs != *session_type {
+        return Err(invalid(format!(
+            "the session's types don't match: this side has it as `{}`, and the other side as \
+             `{}`",
+            session_type.shown(),
+            theirs.shown(),
+        )));
+    }
+    Ok(())
+}
+
+/// Writes frames as they come, until every sender is gone.
+async fn write_frames(
+    mut writer: impl AsyncWrite + Unpin,
+    mut frames: mpsc::UnboundedReceiver<Frame>,
+) -> io::Result<()> {
+    while let Some(frame) = frames.next().await {
+        writer.write_all(&length_prefixed(&frame.encode())).await?;
+    }
+    writer.shutdown().await
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// ----------
+
+/// Serves a session over a connection, until the session or the connection
+/// ends.
+///
+/// The session's type must support readback, which can be checked with
+/// [`type_supports_readback`](par_core::runtime::type_supports_readback).
+/// The other side follows along with `Remote.Connect`, given the same type.
+pub async fn serve_session(session: TypedHandle, stream: TcpStream) -> io::Result<()> {
+    let Some(session_type) = session.session_type() else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the session's type can't be served: it has type variables or boxes",
+        ));
+    };
+    let (mut reader, mut writer) = stream.into_split();
+    handshake(&mut reader, &mut writer, &session_type).await?;
+    let (outgoing, frames) = mpsc::unbounded();
+    let server = Server {
+        outgoing,
+        answers: Mutex::new(HashMap::new()),
+        next_channel: AtomicU64::new(1),
+    };
+    let serving = async {
+        let served = server.serve(0, session).await;
+        server.outgoing.close_channel();
+        served
+    };
+    let answering = async {
+        while let Some(frame) = read_frame(&mut reader).await? {
+            let answer = lock(&server.answers).remove(&frame.channel);
+            let Some(answer) = answer else {
+                return Err(invalid("unexpected frame"));
+            };
+            _ = answer.send(frame.step);
+        }
+        Err::<(), _>(io::ErrorKind::UnexpectedEof.into())
+    };
+    tokio::select! {
+        biased;
+        result = try_join(serving, write_frames(writer, frames)) => result.map(|_| ()),
+        result = answering => result,
+    }
+}
+
+struct Server {
+    outgoing: mpsc::UnboundedSender<Frame>,
+    /// Where to deliver the answer each waiting channel gets.
+    answers: Mutex<HashMap<u64, oneshot::Sender<Step>>>,
+    next_channel: AtomicU64,
+}
+
+/// Waits for a value of a primitive type, and provides it.
+macro_rules! answer_request {
+    ($server:expr, $channel:expr, $provide:expr, $typ:ident) => {
+        match $server.request($channel, PrimitiveType::$typ).await? {
+            Value::$typ(value) => {
+                $provide(value);
+                Ok(())
+            }
+            _ => Err(invalid(concat!(
+                "expected a value of type ",
+                stringify!($typ)
+            ))),
+        }
+    };
+}
+
+impl Server {
+    fn serve(&self, channel: u64, session: TypedHandle) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            match session.readback().await {
+                TypedReadback::Nat(value) => self.send(channel, Step::Value(Value::Nat(value))),
+                TypedReadback::Int(value) => self.send(channel, Step::Value(Value::Int(value))),
+                TypedReadback::Float(value) => self.send(channel, Step::Value(Value::Float(value))),
+                TypedReadback::String(value) => {
+                    self.send(channel, Step::Value(Value::String(value)))
+                }
+                TypedReadback::Char(value) => self.send(channel, Step::Value(Value::Char(value))),
+                TypedReadback::Byte(value) => self.send(channel, Step::Value(Value::Byte(value))),
+                TypedReadback::Bytes(value) => self.send(channel, Step::Value(Value::Bytes(value))),
+
+                TypedReadback::NatRequest(provide) => {
+                    answer_request!(self, channel, provide, Nat)
+                }
+                TypedReadback::IntRequest(provide) => {
+                    answer_request!(self, channel, provide, Int)
+                }
+                TypedReadback::FloatRequest(provide) => {
+                    answer_request!(self, channel, provide, Float)
+                }
+                TypedReadback::StringRequest(provide) => {
+                    answer_request!(self, channel, provide, String)
+                }
+                TypedReadback::CharRequest(provide) => {
+                    answer_request!(self, channel, provide, Char)
+                }
+                TypedReadback::ByteRequest(provide) => {
+                    answer_request!(self, channel, provide, Byte)
+                }
+                TypedReadback::BytesRequest(provide) => {
+                    answer_request!(self, channel, provide, Bytes)
+                }
+
+                TypedReadback::Times(first, rest) => {
+                    let opened = self.next_channel.fetch_add(1, Ordering::Relaxed);
+                    self.send(channel, Step::Send(opened))?;
+                    try_join(self.serve(opened, first), self.serve(channel, rest)).await?;
+                    Ok(())
+                }
+                TypedReadback::Par(argument, rest) => {
+                    let opened = self.next_channel.fetch_add(1, Ordering::Relaxed);
+                    self.send(channel, Step::Receive(opened))?;
+                    try_join(self.serve(opened, argument), self.serve(channel, rest)).await?;
+                    Ok(())
+                }
+                TypedReadback::Either(label, rest) => {
+                    self.send(channel, Step::Signal(label))?;
+                    self.serve(channel, rest).await
+                }
+                TypedReadback::Choice(labels, choose) => {
+                    let label = match self.answer(channel, Step::Case).await? {
+                        Step::Signal(label) if labels.contains(&label) => label,
+                        _ => return Err(invalid("expected one of the choices")),
+                    };
+                    self.serve(channel, choose(label)).await
+                }
+                TypedReadback::Break => self.send(channel, Step::Break),
+                TypedReadback::Continue => self.send(channel, Step::Continue),
+                TypedReadback::Unreadable { .. } => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the session's type can't be read back",
+                )),
+            }
+        })
+    }
+
+    fn send(&self, channel: u64, step: Step) -> io::Result<()> {
+        self.outgoing
+            .unbounded_send(Frame { channel, step })
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    /// Sends a step that waits, and waits for its answer.
+    async fn answer(&self, channel: u64, step: Step) -> io::Result<Step> {
+        let (answer, answered) = oneshot::channel();
+        lock(&self.answers).insert(channel, answer);
+        self.send(channel, step)?;
+        answered
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    async fn request(&self, channel: u64, typ: PrimitiveType) -> io::Result<Value> {
+        match self.answer(channel, Step::Request(typ)).await? {
+            Step::Value(value) => Ok(value),
+            _ => Err(invalid("expected a value")),
+        }
+    }
+}
+
+// ----------
+
+async fn remote_connect(mut handle: Handle) {
+    // What the other side does is the run's own input, and isn't recorded.
+    handle.effects().forbid_replay();
+    // The compiler sends the session type ahead of the address, unless the
+    // type isn't known where `Remote.Connect` is called.
+    let (session_type, address) = match handle.receive().data().await {
+        Data::Primitive(Primitive::String(address)) => (None, address),
+        session_type => {
+            let session_type = match session_type {
+                Data::Primitive(Primitive::Bytes(bytes)) => SessionType::from_bytes(&bytes),
+                _ => None,
+            };
+            (session_type, handle.receive().string().await)
+        }
+    };
+    let connected = async {
+        let Some(session_type) = session_type else {
+            return Err(invalid(
+                "the session's type is not known: call `Remote.Connect(type T, address)` \
+                 directly, with a type `T` that has no type variables or boxes",
+            ));
+        };
+        let (mut reader, mut writer) = TcpStream::connect(address.as_str()).await?.into_split();
+        handshake(&mut reader, &mut writer, &session_type).await?;
+        Ok::<_, io::Error>((reader, writer))
+    };
+    match connected.await {
+        Ok((reader, writer)) => {
+            handle.signal(literal!("ok"));
+            // The connection is only closed by the other side, once the
+            // session is over, so any error has nowhere to go.
+            _ = follow_session(handle, reader, writer).await;
+        }
+        Err(err) => {
+            handle.signal(literal!("err"));
+            handle.provide_string(ParString::from(err.to_string()));
+        }
+    }
+}
+
+/// Does on `session` whatever the serving side's frames say, until it closes
+/// the connection. The serving side is trusted to send the steps of the
+/// session's type; see the module documentation.
+async fn follow_session(
+    session: Handle,
+    mut reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+) -> io::Result<()> {
+    let (outgoing, frames) = mpsc::unbounded();
+    let channels = Arc::new(Mutex::new(HashMap::from([(0, session)])));
+    let following = async {
+        while let Some(Frame { channel, step }) = read_frame(&mut reader).await? {
+            let handle = lock(&channels).remove(&channel);
+            let Some(mut handle) = handle else {
+                return Err(invalid("frame for an unknown channel"));
+            };
+            let kept = match step {
+                Step::Send(opened) => {
+                    let part = handle.send();
+                    lock(&channels).insert(opened, part);
+                    Some(handle)
+                }
+                Step::Receive(opened) => {
+                    let part = handle.receive();
+                    lock(&channels).insert(opened, part);
+                    Some(handle)
+                }
+                Step::Signal(label) => {
+                    handle.signal(label);
+                    Some(handle)
+                }
+                Step::Case => {
+                    let (channels, outgoing) = (channels.clone(), outgoing.clone());
+                    handle.concurrently(move |mut handle| async move {
+                        let label = handle.case().await;
+                        // Back in place before the answer, which is what the
+                        // serving side waits for to go on with this channel.
+                        lock(&channels).insert(channel, handle);
+                        _ = outgoing.unbounded_send(Frame {
+                            channel,
+                            step: Step::Signal(label),
+                        });
+                    });
+                    None
+                }
+                Step::Break => {
+                    handle.break_();
+                    None
+                }
+                Step::Continue => {
+                    handle.continue_();
+                    None
+                }
+                Step::Value(value) => {
+                    value.provide(handle);
+                    None
+                }
+                Step::Request(typ) => {
+                    let outgoing = outgoing.clone();
+                    handle.concurrently(move |handle| async move {
+                        let value = Value::read(handle, typ).await;
+                        _ = outgoing.unbounded_send(Frame {
+                            channel,
+                            step: Step::Value(value),
+                        });
+                    });
+                    None
+                }
+            };
+            if let Some(handle) = kept {
+                lock(&channels).insert(channel, handle);
+            }
+        }
+        outgoing.close_channel();
+        Ok(())
+    };
+    try_join(following, write_frames(writer, frames)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<Frame> {
+        let steps = vec![
+            Step::Send(1),
+            Step::Receive(u64::MAX),
+            Step::Signal(literal!("item")),
+            Step::Case,
+            Step::Break,
+            Step::Continue,
+            Step::Value(Value::Nat(BigUint::from(300u32))),
+            Step::Value(Value::Int(BigInt::from(-300))),
+            Step::Value(Value::Float(-1.5)),
+            Step::Value(Value::String(ParString::from("héllo"))),
+            Step::Value(Value::Char('λ')),
+            Step::Value(Value::Byte(0xff)),
+            Step::Value(Value::Bytes(Bytes::from_static(b"\0\x80"))),
+            Step::Request(PrimitiveType::Nat),
+            Step::Request(PrimitiveType::Bytes),
+        ];
+        steps
+            .into_iter()
+            .enumerate()
+            .map(|(channel, step)| Frame {
+                channel: channel as u64 * 1000,
+                step,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_decode_to_what_was_encoded() {
+        for frame in frames() {
+            assert_eq!(Frame::decode(frame.encode()).as_ref(), Some(&frame));
+        }
+    }
+
+    #[test]
+    fn frames_cut_short_or_with_extra_bytes_are_malformed() {
+        for frame in frames() {
+            let encoded = frame.encode();
+            for length in 0..encoded.len() {
+                assert_eq!(Frame::decode(encoded.slice(..length)), None);
+            }
+            let mut longer = BytesMut::from(&encoded[..]);
+            longer.put_u8(0);
+            assert_eq!(Frame::decode(longer.freeze()), None);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//package: core
+use crate::builtin::list::readback_list;
+use arcstr::literal;
+use im::OrdSet;
+use par_runtime::readback::{Data, Handle};
+use par_runtime::registry::{DefinitionRef, ExternalDef, PackageRef};
+
+macro_rules! core_set_external {
+    ($name:literal, $f:path $(, $arg:expr)*) => {
+        inventory::submit!(ExternalDef {
+            path: DefinitionRef {
+                package: PackageRef::CORE,
+                path: &[],
+                module: "Set",
+                name: $name,
+            },
+            f: |handle| Box::pin($f(handle $(, $arg)*)),
+        });
+    };
+}
+
+core_set_external!("Empty", set_empty);
+core_set_external!("FromList", set_from_list);
+core_set_external!("Union", set_combine, SetOperation::Union);
+core_set_external!("Intersect", set_combine, SetOperation::Intersect);
+core_set_external!("Difference", set_combine, SetOperation::Difference);
+
+#[derive(Clone, Copy)]
+enum SetOperation {
+    Union,
+    Intersect,
+    Difference,
+}
+
+async fn set_empty(handle: Handle) {
+    provide_set(handle, OrdSet::new());
+}
+
+async fn set_from_list(mut handle: Handle) {
+    let items = readback_list(handle.receive(), |handle| async { handle.data().await }).await;
+    provide_set(handle, items.into_iter().collect());
+}
+
+async fn set_combine(mut handle: Handle, operation: SetOperation) {
+    let left = readback_set(handle.receive()).await;
+    let right = readback_set(handle.receive()).await;
+    let set = match operation {
+        SetOperation::Union => left.union(right),
+        SetOperation::Intersect => left.intersection(right),
+        SetOperation::Difference => left.relative_complement(right),
+    };
+    provide_set(handle, set);
+}
+
+async fn readback_set(mut handle: Handle) -> OrdSet<Data> {
+    handle.signal(literal!("list"));
+    readback_list(handle, |handle| async { handle.data().await })
+        .await
+        .into_iter()
+        .collect()
+}
+
+fn provide_set(handle: Handle, set: OrdSet<Data>) {
+    handle.provide_box(move |mut handle| {
+        let mut set = set.clone();
+        async move {
+            match handle.case().await.as_str() {
+                "size" => handle.provide_nat(set.len().into()),
+                "list" => {
+                    for item in set.iter() {
+                        handle.signal(literal!("item"));
+                        handle.send_data(item);
+                    }
+                    handle.signal(literal!("end"));
+                    handle.break_()
+                }
+                "contains" => {
+                    let item = handle.receive_data().await;
+                    if set.contains(&item) {
+                        handle.signal(literal!("true"));
+                    } else {
+                        handle.signal(literal!("false"));
+                    }
+                    handle.break_()
+                }
+                "insert" => {
+                    let item = handle.receive_data().await;
+                    set.insert(item);
+                    provide_set(handle, set)
+                }
+                "delete" => {
+                    let item = handle.receive_data().await;
+                    set.remove(&item);
+                    provide_set(handle, set)
+                }
+                _ => unreachable!(),
+            }
+        }
+    })
+}
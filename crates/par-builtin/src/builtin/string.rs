@@ -1,6 +1,7 @@
 //package: core
 use std::cmp::Ordering;
 
+use arcstr::literal;
 use num_bigint::BigUint;
 
 use crate::builtin::{
@@ -47,6 +48,30 @@ core_string_external!("FromBytes", string_from_bytes);
 core_string_external!("ToLower", string_to_lower);
 core_string_external!("ToUpper", string_to_upper);
 
+/// Provides the next line of a `String.Lines<e>`, returning the rest of the stream.
+pub(super) fn provide_line(mut handle: Handle, line: &str) -> Handle {
+    handle.signal(literal!("line"));
+    handle
+        .send()
+        .provide_string(ParString::copy_from_slice(line));
+    handle
+}
+
+/// Ends a `String.Lines<e>`, with the error that stopped reading, if any.
+pub(super) fn provide_lines_end(mut handle: Handle, result: Result<(), String>) {
+    handle.signal(literal!("end"));
+    match result {
+        Ok(()) => {
+            handle.signal(literal!("ok"));
+            handle.break_();
+        }
+        Err(error) => {
+            handle.signal(literal!("err"));
+            handle.provide_string(ParString::from(error));
+        }
+    }
+}
+
 async fn string_builder(mut handle: Handle) {
     let mut buf = String::new();
     loop {
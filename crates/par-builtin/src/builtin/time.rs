@@ -330,8 +330,13 @@ fn provide_none(mut handle: Handle) {
 
 async fn time_now(mut handle: Handle) {
     handle.receive().continue_();
-    let now = Timestamp::now();
-    handle.provide_int(BigInt::from(now.as_nanosecond()));
+    let nanos = match handle.environment().virtual_now_nanos() {
+        Some(nanos) => nanos,
+        None => handle
+            .effects()
+            .have_now("Time.Now", || Timestamp::now().as_nanosecond()),
+    };
+    handle.provide_int(BigInt::from(nanos));
 }
 
 async fn time_from_unix_nanos(mut handle: Handle) {
@@ -366,7 +371,12 @@ async fn time_utc(handle: Handle) {
 }
 
 async fn time_local(handle: Handle) {
-    provide_zone(handle, TimeZone::system());
+    // The host's zone would leak into deterministic runs, so those see UTC.
+    if handle.environment().is_deterministic() {
+        provide_zone(handle, TimeZone::UTC);
+    } else {
+        provide_zone(handle, TimeZone::system());
+    }
 }
 
 async fn time_offset(mut handle: Handle) {
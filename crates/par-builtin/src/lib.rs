@@ -2,6 +2,8 @@
 
 mod builtin;
 
+#[cfg(not(target_family = "wasm"))]
+pub use builtin::serve_session;
 pub use builtin::{
     PAR_BUILTIN_URI_SCHEME, builtin_packages, get_builtin_source, inject_builtin_packages,
 };
@@ -0,0 +1,40 @@
+//! Stopping runs that never wait on anything outside the reducer.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{Program, block_on_multi_thread, run_with_large_stack};
+use par_runtime::environment::Environment;
+use par_runtime::setup::Setup;
+use tokio_util::sync::CancellationToken;
+
+const SOURCE: &str = "module Spin
+
+def Driver : recursive ! = !
+
+def Spin : ! = chan out {
+  Driver.unfounded
+  if 0 == 1 => { out! }
+  Driver.loop
+}
+";
+
+#[test]
+fn cancelled_runs_stop_between_rewrites() {
+    run_with_large_stack(|| {
+        let program = Program::compile("Spin", SOURCE);
+        let cancel_token = CancellationToken::new();
+        let setup = Setup::new(Environment::new()).with_cancel_token(cancel_token.clone());
+        block_on_multi_thread(async {
+            let (root, fut) = program.start("Spin", setup);
+            root.continue_();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel_token.cancel();
+            let rewrites = tokio::time::timeout(Duration::from_secs(10), fut)
+                .await
+                .expect("the cancelled run to stop");
+            assert!(rewrites.total() > 0);
+        });
+    });
+}
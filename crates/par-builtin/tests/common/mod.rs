@@ -0,0 +1,195 @@
+//! What the integration tests share: compiling a module against the builtin
+//! packages, starting its definitions, and giving them a stack to do it on.
+
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arcstr::literal;
+use futures::future::RemoteHandle;
+use par_core::frontend::language::{GlobalName, Universal};
+use par_core::pipeline::{Pipeline, PipelineError, SourcePackage, Stage};
+use par_core::runtime::{Compiled, Overrides};
+use par_core::source::FileName;
+use par_core::workspace::{CheckedWorkspace, LoadedPackageFile};
+use par_runtime::flat::runtime::PackagePtr;
+use par_runtime::flat::stats::{Progress, Rewrites};
+use par_runtime::linker::Linked;
+use par_runtime::pkgid::PackageId;
+use par_runtime::readback::Handle;
+use par_runtime::setup::Setup;
+use par_runtime::spawn::TokioSpawn;
+
+/// Enough stack for compiling and running the test programs in debug builds.
+pub const LARGE_TEST_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Type checks `source`, the only file of a package, as the module `module`.
+pub fn check(module: &str, source: impl Into<String>) -> CheckedWorkspace {
+    let id = PackageId::Special(literal!("test"));
+    let mut pipeline = Pipeline::new(
+        id.clone(),
+        vec![SourcePackage {
+            id,
+            dependencies: BTreeMap::new(),
+            files: vec![LoadedPackageFile {
+                name: FileName::from(format!("test/{module}.par")),
+                relative_path_from_src: PathBuf::from(format!("{module}.par")),
+                source: source.into(),
+            }],
+        }],
+    );
+    pipeline.add_pass(Stage::Parse, |artifacts| {
+        let packages = artifacts.packages.as_mut().expect("parsed packages");
+        par_builtin::inject_builtin_packages(packages).map_err(PipelineError::Discovery)
+    });
+    pipeline
+        .run_until(Stage::TypeCheck)
+        .expect("checked program");
+    let artifacts = pipeline.into_artifacts();
+    assert!(
+        artifacts.type_errors.is_empty(),
+        "{:?}",
+        artifacts.type_errors
+    );
+    artifacts.checked.expect("checked workspace")
+}
+
+/// A module, compiled and linked.
+pub struct Program {
+    pub checked: CheckedWorkspace,
+    pub compiled: Compiled<Linked>,
+    module: String,
+}
+
+impl Program {
+    pub fn compile(module: &str, source: impl Into<String>) -> Self {
+        Self::compile_with(module, source, &Overrides::new())
+    }
+
+    /// Like [`Program::compile`], with builtin definitions replaced by
+    /// `overrides`.
+    pub fn compile_with(module: &str, source: impl Into<String>, overrides: &Overrides) -> Self {
+        let checked = check(module, source);
+        let compiled = checked
+            .compile_runtime(10_000)
+            .expect("compiled program")
+            .link_with(overrides)
+            .expect("linked program");
+        Self {
+            checked,
+            compiled,
+            module: module.to_owned(),
+        }
+    }
+
+    /// The definition of the module named `name`.
+    pub fn definition(&self, name: &str) -> GlobalName<Universal> {
+        self.checked
+            .checked_module()
+            .definitions
+            .keys()
+            .find(|global| global.module.module == self.module && global.primary == name)
+            .cloned()
+            .unwrap_or_else(|| panic!("no {name} in {}", self.module))
+    }
+
+    fn package(&self, name: &str) -> PackagePtr<Linked> {
+        self.compiled
+            .package(&self.definition(name))
+            .expect("compiled definition")
+    }
+
+    /// Starts a run of the definition named `name` with `setup`, returning a
+    /// handle to its root, and the reducer.
+    pub fn start(&self, name: &str, setup: impl Into<Setup>) -> (Handle, RemoteHandle<Rewrites>) {
+        par_runtime::start_and_instantiate(
+            Arc::new(TokioSpawn::new()),
+            setup,
+            self.compiled.arena(),
+            self.package(name),
+        )
+    }
+
+    /// Like [`Program::start`], also returning the live statistics of the run.
+    pub fn start_with_progress(
+        &self,
+        name: &str,
+        setup: impl Into<Setup>,
+    ) -> (Handle, RemoteHandle<Rewrites>, Progress) {
+        par_runtime::start_and_instantiate_with_progress(
+            Arc::new(TokioSpawn::new()),
+            setup,
+            self.compiled.arena(),
+            self.package(name),
+        )
+    }
+}
+
+/// Reads the list `root` is, reading each item with `item`.
+pub async fn read_list<T>(mut root: Handle, item: impl AsyncFn(Handle) -> T) -> Vec<T> {
+    let mut items = Vec::new();
+    loop {
+        match root.case().await.as_str() {
+            "item" => items.push(item(root.receive()).await),
+            "end" => {
+                root.continue_();
+                return items;
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Runs `future` to completion on a runtime of its own.
+pub fn block_on<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime")
+        .block_on(future)
+}
+
+/// Like [`block_on`], on a runtime with worker threads, for futures waiting
+/// on tasks of their own.
+pub fn block_on_multi_thread<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime")
+        .block_on(future)
+}
+
+/// A new directory for a test named `name` to put its files in.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let unique = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("par-{name}-{unique}"));
+    std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+    dir
+}
+
+/// Runs `f` on a thread with `stack_size` bytes of stack.
+pub fn run_with_stack<T>(stack_size: usize, f: impl FnOnce() -> T + Send + 'static) -> T
+where
+    T: Send + 'static,
+{
+    let handle = std::thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(f)
+        .expect("failed to spawn test thread");
+    match handle.join() {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+pub fn run_with_large_stack<T>(f: impl FnOnce() -> T + Send + 'static) -> T
+where
+    T: Send + 'static,
+{
+    run_with_stack(LARGE_TEST_STACK_SIZE, f)
+}
@@ -0,0 +1,23 @@
+//! Moving sources written in an older edition to the latest one.
+
+mod common;
+
+use common::{check, run_with_large_stack};
+use par_core::frontend::lexer::Edition;
+use par_core::frontend::migrate::migrate_source;
+use par_core::source::FileName;
+
+const OLD_EDITION: &str = include_str!("../../../tests/src/OldEdition.par");
+
+#[test]
+fn fixed_old_editions_check_in_the_latest_one() {
+    let migrated = migrate_source(
+        OLD_EDITION,
+        &FileName::from("tests/OldEdition.par"),
+        Edition::LATEST,
+        Edition::LATEST,
+    );
+    assert_eq!(migrated.from, Edition::E2025);
+    assert!(migrated.source.starts_with("edition 2026\n"));
+    run_with_large_stack(move || check("OldEdition", migrated.source));
+}
@@ -0,0 +1,45 @@
+//! Runs seeing the world through the environment they're started with.
+
+mod common;
+
+use common::{Program, block_on, read_list, run_with_large_stack};
+use num_bigint::BigInt;
+use par_runtime::environment::Environment;
+use par_runtime::readback::Handle;
+
+const CLOCK: &str = r#"module Clock
+
+import {
+  @core/Int
+  @core/List
+  @core/Time
+}
+
+def Clock: List<Int> = *(
+  Time.Since(Time.Now, Time.Epoch),
+  Time.Since(Time.Now, Time.Epoch),
+)
+"#;
+
+/// The times `Clock` reads in `environment`.
+fn clock(program: &Program, environment: Environment) -> Vec<BigInt> {
+    block_on(async {
+        let (root, fut) = program.start("Clock", environment);
+        let times = read_list(root, Handle::int).await;
+        fut.await;
+        times
+    })
+}
+
+#[test]
+fn every_deterministic_run_starts_its_own_clock() {
+    let (first, again) = run_with_large_stack(|| {
+        let program = Program::compile("Clock", CLOCK);
+        (
+            clock(&program, Environment::deterministic()),
+            clock(&program, Environment::deterministic()),
+        )
+    });
+    assert_eq!(first, again);
+    assert!(first[0] < first[1]);
+}
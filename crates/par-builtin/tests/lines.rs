@@ -0,0 +1,135 @@
+//! Reading text line by line, from files and from the console, however the
+//! lines end.
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use common::{Program, block_on, read_list, run_with_large_stack, temp_dir};
+use par_runtime::environment::Environment;
+use par_runtime::readback::Handle;
+
+/// The inputs the lines are read from, by name: lines ending in CRLF, a last
+/// line without a line ending, and no lines at all.
+const INPUTS: [(&str, &[u8]); 3] = [
+    ("crlf", b"first\r\nsecond\r\n"),
+    ("unterminated", b"first\nsecond"),
+    ("empty", b""),
+];
+
+/// Marks the lines the console child prints among the test harness's output.
+const CHILD_LINE: &str = "line: ";
+
+/// The `Lines` program, reading the files in `dir`.
+fn lines_program(dir: &Path) -> Program {
+    let mut source = r#"module Lines
+
+import {
+  @core/List
+  @core/String
+  @basic/Console
+  @basic/Os
+}
+
+dec Collect : [String.Lines<String>] List<String>
+def Collect = [lines] lines.begin.case {
+  .end _ => .end!,
+  .line(text) rest => .item(text) rest.loop,
+}
+
+def FromConsole: List<String> = Collect(Console.Lines)
+"#
+    .to_owned();
+    for (name, _) in INPUTS {
+        source.push_str(&format!(
+            "\ndef File_{name}: List<String> = \
+             Os.ReadLines(Os.Path(\"{}\")).case {{\n  \
+             .ok lines => Collect(lines),\n  \
+             .err e => *(e),\n}}\n",
+            dir.join(name).display()
+        ));
+    }
+    Program::compile("Lines", source)
+}
+
+/// Runs the definition named `name`, a list of strings, to completion.
+fn run(program: &Program, name: &str) -> Vec<String> {
+    block_on(async {
+        let (root, fut) = program.start(name, Environment::new());
+        let lines = read_list(root, async |line: Handle| {
+            line.string().await.as_str().to_owned()
+        })
+        .await;
+        fut.await;
+        lines
+    })
+}
+
+fn expected(name: &str) -> Vec<String> {
+    match name {
+        "empty" => vec![],
+        _ => vec!["first".to_owned(), "second".to_owned()],
+    }
+}
+
+#[test]
+fn files_are_read_line_by_line() {
+    let dir = temp_dir("lines");
+    for (name, input) in INPUTS {
+        std::fs::write(dir.join(name), input).expect("failed to write input");
+    }
+
+    let program_dir = dir.clone();
+    let read = run_with_large_stack(move || {
+        let program = lines_program(&program_dir);
+        INPUTS.map(|(name, _)| (name, run(&program, &format!("File_{name}"))))
+    });
+    for (name, lines) in read {
+        assert_eq!(lines, expected(name), "reading the {name} file");
+    }
+    std::fs::remove_dir_all(dir).ok();
+}
+
+/// Prints the lines `Console.Lines` reads from stdin, for
+/// [`the_console_is_read_line_by_line`] to check. Only run by it.
+#[test]
+#[ignore = "reads stdin; run by the_console_is_read_line_by_line"]
+fn console_lines_child() {
+    let lines = run_with_large_stack(|| run(&lines_program(Path::new("")), "FromConsole"));
+    for line in lines {
+        println!("{CHILD_LINE}{line:?}");
+    }
+}
+
+#[test]
+fn the_console_is_read_line_by_line() {
+    for (name, input) in INPUTS {
+        let mut child = Command::new(std::env::current_exe().expect("test binary"))
+            .args(["console_lines_child", "--exact", "--ignored", "--nocapture"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to start the console child");
+        child
+            .stdin
+            .take()
+            .expect("child stdin")
+            .write_all(input)
+            .expect("failed to write to the console child");
+        let output = child.wait_with_output().expect("console child");
+        assert!(output.status.success(), "the console child failed");
+        let lines = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            // The harness may have started the line of the first one.
+            .filter_map(|line| Some(line.split_once(CHILD_LINE)?.1))
+            .map(|line| line.trim_matches('"').to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            expected(name),
+            "reading {name} input from the console"
+        );
+    }
+}
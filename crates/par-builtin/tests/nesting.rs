@@ -0,0 +1,42 @@
+//! Programs nested as deeply as parsing allows, going through every pass on
+//! no more stack than a program's main thread gets.
+
+mod common;
+
+use common::{Program, run_with_stack};
+
+/// What the main thread gets on Linux, where `par` runs all its passes.
+const MAIN_THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The deepest nesting the parser accepts by default.
+const DEEPEST: usize = 127;
+
+#[test]
+fn the_deepest_expression_makes_it_through_every_pass() {
+    run_with_stack(MAIN_THREAD_STACK_SIZE, || {
+        Program::compile(
+            "Deep",
+            format!(
+                "module Deep\n\nimport @core/Nat\n\ndec F : [Nat] Nat\ndef F = [x] x\n\n\
+                 def X: Nat = {}1{}\n",
+                "F(".repeat(DEEPEST),
+                ")".repeat(DEEPEST),
+            ),
+        );
+    });
+}
+
+#[test]
+fn the_deepest_type_makes_it_through_every_pass() {
+    run_with_stack(MAIN_THREAD_STACK_SIZE, || {
+        Program::compile(
+            "Deep",
+            format!(
+                "module Deep\n\nimport {{\n  @core/List\n  @core/Nat\n}}\n\n\
+                 type Lists = {}Nat{}\n\ndec Empty : Lists\ndef Empty = .end!\n",
+                "List<".repeat(DEEPEST - 1),
+                ">".repeat(DEEPEST - 1),
+            ),
+        );
+    });
+}
@@ -0,0 +1,105 @@
+//! Replacing builtin definitions with stubs when linking a program.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use arcstr::literal;
+use common::{Program, block_on_multi_thread as block_on, run_with_large_stack};
+use par_core::frontend::ParString;
+use par_core::runtime::Overrides;
+use par_runtime::environment::Environment;
+use par_runtime::readback::Handle;
+use par_runtime::registry::{DefinitionRef, PackageRef};
+
+const GREETER: &str = r#"module Greeter
+
+import @basic/Console
+
+def Main: ! = chan exit {
+  let console = Console.Open
+  console.prompt("Name: ")[name]
+  if not name is .ok name => {
+    console.print("Nobody there.")
+    console.close
+    exit!
+  }
+  console.print(`Hello, ${name}!`)
+  console.close
+  exit!
+}
+"#;
+
+/// A `Console` that records what's printed, and answers prompts from `input`.
+async fn recording_console(
+    mut handle: Handle,
+    input: Vec<&'static str>,
+    printed: Arc<Mutex<Vec<String>>>,
+) {
+    let mut input = input.into_iter();
+    loop {
+        match handle.case().await.as_str() {
+            "close" => {
+                handle.break_();
+                break;
+            }
+            "print" => {
+                let line = handle.receive().string().await;
+                printed.lock().unwrap().push(line.as_str().to_owned());
+            }
+            "prompt" => {
+                handle.receive().string().await;
+                let mut answer = handle.send();
+                match input.next() {
+                    Some(line) => {
+                        answer.signal(literal!("ok"));
+                        answer.provide_string(ParString::copy_from_slice(line));
+                    }
+                    None => {
+                        answer.signal(literal!("err"));
+                        answer.break_();
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Runs `Greeter.Main` with `Console.Open` replaced, and returns what it printed.
+fn run_greeter(input: Vec<&'static str>) -> Vec<String> {
+    let printed = Arc::new(Mutex::new(Vec::new()));
+    let overrides = {
+        let printed = Arc::clone(&printed);
+        Overrides::new().replace(
+            DefinitionRef {
+                package: PackageRef::BASIC,
+                path: &[],
+                module: "Console",
+                name: "Open",
+            },
+            move |handle| recording_console(handle, input.clone(), Arc::clone(&printed)),
+        )
+    };
+    let program = Program::compile_with("Greeter", GREETER, &overrides);
+
+    block_on(async {
+        let (root, fut) = program.start("Main", Environment::new());
+        root.continue_();
+        fut.await;
+    });
+
+    printed.lock().unwrap().clone()
+}
+
+#[test]
+fn console_open_can_be_replaced() {
+    let printed = run_with_large_stack(|| run_greeter(vec!["Par"]));
+    assert_eq!(printed, ["Hello, Par!"]);
+}
+
+#[test]
+fn replaced_console_can_report_end_of_input() {
+    let printed = run_with_large_stack(|| run_greeter(vec![]));
+    assert_eq!(printed, ["Nobody there."]);
+}
@@ -0,0 +1,45 @@
+//! The live statistics of a run, reported while it's still going.
+
+mod common;
+
+use common::{Program, block_on, read_list, run_with_large_stack};
+use par_runtime::environment::Environment;
+use par_runtime::readback::Handle;
+
+const SOURCE: &str = "module Counting
+
+import {
+  @core/List
+  @core/Nat
+}
+
+def Numbers: List<Nat> = Nat.Range(0, 1000)
+";
+
+/// Runs `Numbers`, giving back the rewrites reported after each item is read,
+/// the ones reported once the run is over, and the run's own count.
+fn run_numbers() -> (Vec<u64>, u64, u64) {
+    let program = Program::compile("Counting", SOURCE);
+    block_on(async {
+        let (root, fut, progress) = program.start_with_progress("Numbers", Environment::new());
+        let reported = read_list(root, async |number: Handle| {
+            number.nat().await;
+            progress.rewrites()
+        })
+        .await;
+        let rewrites = fut.await;
+        (reported, progress.rewrites(), rewrites.total())
+    })
+}
+
+#[test]
+fn progress_is_reported_while_the_run_goes_on() {
+    let (reported, last, total) = run_with_large_stack(run_numbers);
+    assert_eq!(reported.len(), 1000);
+    // The reducer reports whenever it waits for the reader, so by the time
+    // an item is read, the rewrites made for the ones before are counted.
+    assert!(reported[1] > 0, "{reported:?}");
+    assert!(reported.is_sorted(), "{reported:?}");
+    assert!(reported[999] > reported[1], "{reported:?}");
+    assert_eq!(last, total);
+}
@@ -0,0 +1,135 @@
+//! Sessions between two programs over a loopback connection, one serving a
+//! definition and the other connecting to it with `Remote.Connect`.
+
+mod common;
+
+use common::{Program, block_on_multi_thread as block_on, run_with_large_stack};
+use par_core::frontend::ParString;
+use par_core::runtime::TypedHandle;
+use par_runtime::environment::Environment;
+
+const COUNTING: &str = r#"module Counting
+
+import {
+  @core/Int
+  @core/Nat
+  @core/String
+  @core/Try
+  @basic/Remote
+}
+
+type Counter = iterative choice {
+  .add(Nat) => self,
+  .close => Nat,
+}
+
+type Miscounter = iterative choice {
+  .add(Int) => self,
+  .close => Nat,
+}
+
+def Served: Counter = let total: Nat = 0 in begin case {
+  .add(n) => let total = total + n in loop,
+  .close => total,
+}
+
+dec Total : [String] Try<String, Nat>
+def Total = [address] Remote.Connect(type Counter, address).case {
+  .ok counter => .ok counter.add(1).add(2).close,
+  .err message => .err message,
+}
+
+dec Miscounted : [String] Try<String, Nat>
+def Miscounted = [address] Remote.Connect(type Miscounter, address).case {
+  .ok counter => .ok counter.close,
+  .err message => .err message,
+}
+
+dec Connect : [type a, String] Try<String, a>
+def Connect = [type a, address] Remote.Connect(type a, address)
+
+dec Indirect : [String] Try<String, Nat>
+def Indirect = [address] Connect(type Counter, address).case {
+  .ok counter => .ok counter.close,
+  .err message => .err message,
+}
+"#;
+
+/// The `Counting` program, compiled and linked.
+struct Counting(Program);
+
+impl Counting {
+    fn compile() -> Self {
+        Self(Program::compile("Counting", COUNTING))
+    }
+
+    /// Serves `Served` on a loopback address, and runs `client` with that
+    /// address. Returns what the client returned, and how serving ended.
+    fn connect(&self, client: &str) -> (Result<u64, String>, Result<(), String>) {
+        let served = self.0.definition("Served");
+        block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("loopback listener");
+            let address = listener.local_addr().expect("local address").to_string();
+            let (session, serving) = self.0.start("Served", Environment::new());
+            let session = TypedHandle::new(
+                self.0.checked.checked_module().type_defs.clone(),
+                self.0.compiled.get_type_of(&served).expect("served type"),
+                session,
+            );
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+                par_builtin::serve_session(session, stream)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                serving.await;
+                Ok(())
+            });
+            let result = self.run(client, &address).await;
+            (result, server.await.expect("server task"))
+        })
+    }
+
+    /// Runs `client` with `address`, reading back the `Try<String, Nat>` it
+    /// returns.
+    async fn run(&self, client: &str, address: &str) -> Result<u64, String> {
+        let (mut root, running) = self.0.start(client, Environment::new());
+        root.send()
+            .provide_string(ParString::copy_from_slice(address));
+        let result = match root.case().await.as_str() {
+            "ok" => Ok(u64::try_from(root.nat().await).expect("small total")),
+            "err" => Err(root.string().await.as_str().to_owned()),
+            _ => unreachable!(),
+        };
+        running.await;
+        result
+    }
+}
+
+#[test]
+fn a_connected_program_follows_the_served_session() {
+    let (total, served) = run_with_large_stack(|| Counting::compile().connect("Total"));
+    assert_eq!(total, Ok(3));
+    assert_eq!(served, Ok(()));
+}
+
+#[test]
+fn a_session_of_another_type_is_turned_away() {
+    let (total, served) = run_with_large_stack(|| Counting::compile().connect("Miscounted"));
+    let message = total.expect_err("connected at the wrong type");
+    assert!(message.contains("types don't match"), "{message}");
+    assert!(message.contains("Miscounter"), "{message}");
+    assert!(served.is_err());
+}
+
+#[test]
+fn a_session_type_only_known_as_a_variable_is_refused() {
+    // Refused before connecting, so nothing needs to listen.
+    let total = run_with_large_stack(|| {
+        let counting = Counting::compile();
+        block_on(counting.run("Indirect", "127.0.0.1:9"))
+    });
+    let message = total.expect_err("connected without a type");
+    assert!(message.contains("type is not known"), "{message}");
+}
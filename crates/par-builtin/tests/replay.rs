@@ -0,0 +1,177 @@
+//! Runs replaying the effects of an earlier run, answered with the results
+//! it got instead of having them again.
+
+mod common;
+
+use std::path::Path;
+
+use common::{Program, block_on, run_with_large_stack, temp_dir};
+use par_runtime::effects::Effects;
+use par_runtime::environment::Environment;
+use par_runtime::setup::Setup;
+
+/// The `Effects` program, writing into `dir`.
+fn effects_program(dir: &Path) -> Program {
+    let dir = dir.display();
+    let source = format!(
+        r#"module Effects
+
+import {{
+  @core/String
+  @core/Try
+  @basic/Console
+  @basic/Os
+}}
+
+dec CreateDir : Try<Os.Error, !>
+def CreateDir = Os.CreateDir(Os.Path("{dir}/created"))
+
+dec Write : Try<Os.Error, !>
+def Write = Os.CreateOrReplaceFile(Os.Path("{dir}/written")).case {{
+  .ok writer => writer.write("hello").case {{
+    .ok writer => writer.close,
+    .err e => .err e,
+  }},
+  .err e => .err e,
+}}
+
+dec CreateNew : Try<Os.Error, !>
+def CreateNew = Os.CreateNewFile(Os.Path("{dir}/existing")).case {{
+  .ok writer => writer.close,
+  .err e => .err e,
+}}
+
+dec Prompt : String
+def Prompt = do {{
+  let console = Console.Open
+  console.prompt("Name: ")[name]
+  console.close
+}} in name.case {{
+  .ok line => line,
+  .err! => "none",
+}}
+"#
+    );
+    Program::compile("Effects", source)
+}
+
+/// Runs the definition named `name` with `effects`, and gives back its error,
+/// if any.
+fn run(program: &Program, name: &str, effects: &Effects) -> Result<(), String> {
+    block_on(async {
+        let (mut root, fut) = program.start(
+            name,
+            Setup::new(Environment::new()).with_effects(effects.clone()),
+        );
+        let result = match root.case().await.as_str() {
+            "ok" => {
+                root.continue_();
+                Ok(())
+            }
+            "err" => Err(root.string().await.as_str().to_owned()),
+            _ => unreachable!(),
+        };
+        fut.await;
+        result
+    })
+}
+
+/// Runs `Prompt` with `effects`, and gives back the line it read.
+fn prompt(program: &Program, effects: &Effects) -> String {
+    block_on(async {
+        let (root, fut) = program.start(
+            "Prompt",
+            Setup::new(Environment::new()).with_effects(effects.clone()),
+        );
+        let line = root.string().await.as_str().to_owned();
+        fut.await;
+        line
+    })
+}
+
+/// The effects of a run replaying all of `earlier`'s.
+fn replaying(earlier: &Effects) -> Effects {
+    Effects::replaying(earlier, earlier.len())
+}
+
+#[test]
+fn a_replaying_run_leaves_the_file_system_alone() {
+    let dir = temp_dir("replay-replaying");
+    let program_dir = dir.clone();
+    let (create_dir, write, replayed_dirs, replayed_files) = run_with_large_stack(move || {
+        let program = effects_program(&program_dir);
+        let (dirs, files) = (Effects::new(), Effects::new());
+        let create_dir = run(&program, "CreateDir", &dirs);
+        let write = run(&program, "Write", &files);
+        std::fs::remove_dir(program_dir.join("created")).unwrap();
+        std::fs::remove_file(program_dir.join("written")).unwrap();
+        let (replayed_dirs, replayed_files) = (replaying(&dirs), replaying(&files));
+        let replayed = (
+            run(&program, "CreateDir", &replayed_dirs),
+            run(&program, "Write", &replayed_files),
+        );
+        assert!(!replayed_dirs.is_replaying());
+        assert!(!replayed_files.is_replaying());
+        (create_dir, write, replayed.0, replayed.1)
+    });
+    assert_eq!(create_dir, Ok(()));
+    assert_eq!(write, Ok(()));
+    assert_eq!(replayed_dirs, Ok(()));
+    assert_eq!(replayed_files, Ok(()));
+    assert!(!dir.join("created").exists());
+    assert!(!dir.join("written").exists());
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn a_replaying_run_fails_where_the_earlier_run_failed() {
+    let dir = temp_dir("replay-failing");
+    std::fs::write(dir.join("existing"), "there").unwrap();
+    let program_dir = dir.clone();
+    let (earlier, replayed) = run_with_large_stack(move || {
+        let program = effects_program(&program_dir);
+        let effects = Effects::new();
+        let earlier = run(&program, "CreateNew", &effects);
+        std::fs::remove_file(program_dir.join("existing")).unwrap();
+        (earlier, run(&program, "CreateNew", &replaying(&effects)))
+    });
+    assert!(earlier.is_err());
+    assert_eq!(replayed, earlier);
+    assert!(!dir.join("existing").exists());
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn a_run_that_is_not_replaying_changes_the_file_system() {
+    let dir = temp_dir("replay-live");
+    let program_dir = dir.clone();
+    let (create_dir, write) = run_with_large_stack(move || {
+        let program = effects_program(&program_dir);
+        (
+            run(&program, "CreateDir", &Effects::new()),
+            run(&program, "Write", &Effects::new()),
+        )
+    });
+    assert_eq!(create_dir, Ok(()));
+    assert_eq!(write, Ok(()));
+    assert!(dir.join("created").is_dir());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("written")).unwrap(),
+        "hello"
+    );
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn a_replaying_run_is_answered_with_the_lines_read_before() {
+    let dir = temp_dir("replay-prompt");
+    let program_dir = dir.clone();
+    let line = run_with_large_stack(move || {
+        let program = effects_program(&program_dir);
+        let earlier = Effects::new();
+        earlier.have_now("Console.Prompt", || Some(String::from("Ada")));
+        prompt(&program, &replaying(&earlier))
+    });
+    assert_eq!(line, "Ada");
+    std::fs::remove_dir_all(dir).ok();
+}
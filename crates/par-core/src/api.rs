@@ -1,50 +1,114 @@
+/// Locations in source files, shared by every diagnostic.
 pub mod source {
-    pub use crate::location::{FileName, Point, Span, Spanning};
+    pub use crate::location::{BUILTIN_URI_SCHEME, Expansion, FileName, Point, Span, Spanning};
 }
 
+/// Parsing, lowering, and type checking single modules.
+///
+/// A module goes through [`parse`](frontend::parse), [`lower`](frontend::lower),
+/// and [`type_check`](frontend::type_check), and then to
+/// [`compile_runtime`](frontend::compile_runtime) to get something to run.
+/// To do the same for whole packages, with imports between modules, use
+/// [`workspace`](crate::workspace) or [`pipeline`](crate::pipeline) instead.
 pub mod frontend {
-    use crate::frontend_impl::language::{CompileError, Context};
+    use crate::frontend_impl::language::{CompileError, Context, DeclaredParameters};
     use crate::frontend_impl::parse::{parse_module, parse_source_file as parse_source_file_impl};
     use crate::location::FileName;
     use crate::runtime_impl::{Compiled, RuntimeCompilerError};
     use par_runtime::linker::Unlinked;
     use std::sync::Arc;
 
+    /// The syntax tree of parsed modules, and names at every stage of
+    /// resolution.
     pub mod language {
         pub use crate::frontend_impl::language::*;
     }
 
+    /// The lowered form of definitions that the type checker works on. It's
+    /// rebuilt as the compiler changes, so it's only here with the
+    /// `internals` feature, for tools that inspect the checked bodies.
+    #[cfg(feature = "internals")]
     pub mod process {
         pub use crate::frontend_impl::process::*;
     }
 
+    /// The codes of diagnostics, and their explanations.
+    pub mod codes {
+        pub use crate::frontend_impl::codes::{
+            DIAGNOSTIC_CODES, DiagnosticCode, SYNTAX_ERROR, explain_code,
+        };
+    }
+
+    /// The tokens and comments of sources, exactly as the parser sees them,
+    /// for tools like highlighters.
+    pub mod lexer {
+        pub use crate::frontend_impl::lexer::{
+            Comment, CommentKind, Edition, Lexeme, LexemeKind, Lexer, OwnedLexeme, ReadLexer,
+            Token, TokenKind,
+        };
+    }
+
+    /// The whitespace and comments between tokens, for tools that rewrite
+    /// sources without losing them.
+    pub mod trivia {
+        pub use crate::frontend_impl::lexer::{
+            SourceTrivia, TokenTrivia, Trivia, TriviaKind, lex_trivia,
+        };
+    }
+
+    /// Rewriting sources written for older versions of the language.
+    pub mod migrate {
+        pub use crate::frontend_impl::migrate::{
+            MIGRATIONS, MigratedSource, Migration, MigrationEdit, migrate_source,
+        };
+    }
+
+    pub use crate::frontend_impl::grammar::GRAMMAR;
     pub use crate::frontend_impl::parse::SyntaxError;
     pub use crate::frontend_impl::parse_bytes;
     pub use crate::frontend_impl::program::{
-        CheckedModule, Declaration, Definition, DefinitionBody, DocComment, Docs, HoverIndex,
-        ImportDecl, ImportPath, Module, ModuleDecl, ParseAndCompileError, SourceFile, TypeDef,
+        CheckedModule, Declaration, Definition, DefinitionBody, DocComment, Docs, EntryPoint,
+        HoverIndex, ImportDecl, ImportPath, Module, ModuleDecl, ParseAndCompileError, SourceFile,
+        TypeDef,
     };
     pub use crate::frontend_impl::set_miette_hook;
+    pub use crate::frontend_impl::skeleton::{PLACEHOLDER, case_skeleton};
+    pub use crate::frontend_impl::totality::{
+        DEFAULT_TOTALITY_BUDGET, TotalityHint, TotalityVerdict, totality_hints,
+    };
     pub use crate::frontend_impl::types::lattice::{intersect_types, union_types};
-    pub use crate::frontend_impl::types::registry::{ExternalTypeDef, get_external_type_defs};
+    pub use crate::frontend_impl::types::registry::{
+        ExternalTypeDef, ExternalTypeFunction, get_external_type_defs, get_external_type_functions,
+    };
     pub use crate::frontend_impl::types::visibility::Visibility;
     pub use crate::frontend_impl::types::{
-        GlobalNameWriter, Operation, PrimitiveType, Type, TypeDefs, TypeError,
+        ExpansionLimit, GlobalNameWriter, Operation, PrimitiveType, ProtocolStep, Type, TypeDefs,
+        TypeError, TypeFunction, TypeTruncation, UnionKind, set_full_types_in_reports,
     };
     pub use par_runtime::data::Data;
     pub use par_runtime::primitive::{Number, ParString, Primitive};
 
+    /// A module as parsed.
     pub type HighLevelModule =
         Module<language::Expression<language::Unresolved>, language::Unresolved>;
-    pub type LowLevelModule =
-        Module<Arc<process::Expression<(), language::Universal>>, language::Universal>;
-    pub type LowLevelUnresolvedModule =
-        Module<Arc<process::Expression<(), language::Unresolved>>, language::Unresolved>;
+    /// A module lowered to processes, with its names resolved.
+    pub type LowLevelModule = Module<
+        Arc<crate::frontend_impl::process::Expression<(), language::Universal>>,
+        language::Universal,
+    >;
+    /// A module lowered to processes, before its names are resolved.
+    pub type LowLevelUnresolvedModule = Module<
+        Arc<crate::frontend_impl::process::Expression<(), language::Unresolved>>,
+        language::Unresolved,
+    >;
 
+    /// Parses the items of a module, ignoring its `module` and `import`
+    /// declarations.
     pub fn parse(source: &str, file: FileName) -> Result<HighLevelModule, SyntaxError> {
         parse_module(source, file)
     }
 
+    /// Parses a whole file.
     pub fn parse_source_file(
         source: &str,
         file: FileName,
@@ -52,7 +116,17 @@ pub mod frontend {
         parse_source_file_impl(source, file)
     }
 
+    /// Lowers the definitions of a module to processes.
     pub fn lower(module: HighLevelModule) -> Result<LowLevelUnresolvedModule, CompileError> {
+        lower_with_declared_parameters(module, Arc::default())
+    }
+
+    /// Like `lower`, with the arguments of the definitions in
+    /// `declared_parameters` passable by name.
+    pub(crate) fn lower_with_declared_parameters(
+        module: HighLevelModule,
+        declared_parameters: Arc<DeclaredParameters>,
+    ) -> Result<LowLevelUnresolvedModule, CompileError> {
         let compiled_definitions = module
             .definitions
             .into_iter()
@@ -62,7 +136,9 @@ pub mod frontend {
                     name,
                     body: match body {
                         DefinitionBody::Par(expr) => {
-                            let compiled = Context::new().compile_expression(&expr)?;
+                            let compiled =
+                                Context::with_declared_parameters(Arc::clone(&declared_parameters))
+                                    .compile_expression(&expr)?;
                             let compiled =
                                 compiled.optimize().fix_captures().0.optimize_subject(None);
                             DefinitionBody::Par(compiled)
@@ -80,6 +156,8 @@ pub mod frontend {
         })
     }
 
+    /// Checks a lowered module, returning what could be checked along with
+    /// every error found.
     pub fn type_check(
         module: &LowLevelModule,
     ) -> (
@@ -89,25 +167,51 @@ pub mod frontend {
         module.type_check()
     }
 
+    /// Compiles a checked module to nets, ready to be linked and run.
     pub fn compile_runtime(
         module: &CheckedModule<language::Universal>,
         max_interactions: u32,
     ) -> Result<Compiled<Unlinked>, RuntimeCompilerError> {
         Compiled::compile_file(module, max_interactions)
     }
+
+    /// Compiles `module` and reads the net of every definition back into
+    /// process syntax, as far as possible. Meant for debugging the compiler,
+    /// so only here with the `internals` feature.
+    #[cfg(feature = "internals")]
+    pub fn decompile_runtime(
+        module: &CheckedModule<language::Universal>,
+        max_interactions: u32,
+    ) -> Result<Vec<(language::GlobalName<language::Universal>, String)>, RuntimeCompilerError>
+    {
+        use crate::backend::tree::compiler::IcCompiled;
+        use crate::runtime_impl::tree::decompile::decompile_globals;
+
+        let compiled = IcCompiled::compile_file(module, max_interactions)?;
+        Ok(decompile_globals(&compiled))
+    }
 }
 
+/// Compiled definitions, and reading back the values they produce.
 pub mod runtime {
-    pub use crate::runtime_impl::{Compiled, RuntimeCompilerError};
-    pub use crate::typed_readback::{TypedHandle, TypedReadback, type_supports_readback};
+    pub use crate::history::{HISTORY_FORMAT, History, HistoryError, Input, Path, Replay};
+    pub use crate::runtime_impl::{Compiled, Profile, RuntimeCompilerError};
+    pub use crate::session_type::{SessionType, SessionTypeArgument};
+    pub use crate::typed_readback::{
+        ReadbackStep, TypedHandle, TypedReadback, UnsupportedReadback, type_supports_readback,
+        type_supports_readback_with, unsupported_readback_with,
+    };
     pub use par_runtime::data::Data;
     pub use par_runtime::primitive::Number;
+    pub use par_runtime::registry::Overrides;
 }
 
+/// What the runtime needs to spawn its tasks.
 pub mod execution {
     pub use par_runtime::spawn::TokioSpawn;
 }
 
+/// Providing the `Test` value that tests take, and collecting its assertions.
 pub mod testing {
     pub use crate::test_assertion::{AssertionResult, provide_test};
 }
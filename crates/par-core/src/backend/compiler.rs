@@ -4,12 +4,19 @@ use crate::location::Span;
 use std::collections::HashMap;
 
 use crate::backend::flat::transpiler::{Transpiled, link_transpiled};
-use par_runtime::linker::{LinkError, Linked, Unlinked};
+use par_runtime::flat::arena::Arena;
+use par_runtime::flat::runtime::PackagePtr;
+use par_runtime::linker::{Artifact, LinkError, Linked, Unlinked};
+use par_runtime::pkgid::PackageId;
+use par_runtime::registry::Overrides;
 use std::fmt::Display;
+use std::sync::Arc;
 
+/// The nets of every definition in a program, along with their types.
 #[derive(Clone)]
 pub struct Compiled<Ext: Clone> {
-    pub code: Transpiled<Ext>,
+    /// The nets themselves, in the runtime's own format.
+    pub(crate) code: Transpiled<Ext>,
     pub name_to_ty: HashMap<GlobalName<Universal>, Type<Universal>>,
 }
 
@@ -69,15 +76,40 @@ impl Compiled<Unlinked> {
     }
 
     pub fn link(self) -> Result<Compiled<Linked>, RuntimeCompilerError> {
+        self.link_with(&Overrides::new())
+    }
+
+    /// Links the program with some of its external definitions replaced, such
+    /// as by stubs in tests.
+    pub fn link_with(
+        self,
+        overrides: &Overrides,
+    ) -> Result<Compiled<Linked>, RuntimeCompilerError> {
         Ok(Compiled {
-            code: link_transpiled(self.code)?,
+            code: link_transpiled(self.code, overrides)?,
             name_to_ty: self.name_to_ty,
         })
     }
+
+    /// The definitions of `root_package`, to be written as an artifact and
+    /// run without compiling them again.
+    pub fn into_artifact(self, root_package: &PackageId) -> Artifact<Unlinked> {
+        self.code.into_artifact(root_package)
+    }
 }
 
 impl<Ext: Clone> Compiled<Ext> {
     pub fn get_type_of(&self, name: &GlobalName<Universal>) -> Option<Type<Universal>> {
         self.name_to_ty.get(name).cloned()
     }
+
+    /// The net of the definition named `name`, to start a run of it with.
+    pub fn package(&self, name: &GlobalName<Universal>) -> Option<PackagePtr<Ext>> {
+        self.code.get_with_name(name)
+    }
+
+    /// The arena holding the nets of every definition, which each run shares.
+    pub fn arena(&self) -> Arc<Arena<Ext>> {
+        Arc::clone(&self.code.arena)
+    }
 }
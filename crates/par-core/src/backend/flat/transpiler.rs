@@ -18,8 +18,11 @@ use par_runtime::flat::runtime::{
 
 use crate::runtime_impl::tree::Net;
 use par_runtime::flat::runtime::Global;
-use par_runtime::linker::{Artifact, LinkError, Linked, Unlinked, link_arena, link_package_ptr};
+use par_runtime::linker::{
+    Artifact, LinkError, Linked, Unlinked, link_arena_with, link_package_ptr,
+};
 use par_runtime::pkgid::PackageId;
+use par_runtime::registry::Overrides;
 
 #[derive(Default)]
 pub(crate) struct NetTranspiler {
@@ -37,14 +40,18 @@ pub(crate) struct ProgramTranspiler {
 }
 
 #[derive(Clone)]
-pub struct Transpiled<Ext: Clone> {
+pub(crate) struct Transpiled<Ext: Clone> {
     pub arena: Arc<Arena<Ext>>,
     pub name_to_package: HashMap<GlobalName<Universal>, PackagePtr<Ext>>,
+    /// The definition each package belongs to, by package index. Case
+    /// branches aren't packages of their own here, so they count as part of
+    /// the package they're inlined into.
+    pub package_owners: HashMap<usize, GlobalName<Universal>>,
     pub type_defs: TypeDefs<Universal>,
 }
 
 impl Transpiled<Unlinked> {
-    pub fn into_artifact(self, root_package: &PackageId) -> Artifact<Unlinked> {
+    pub(crate) fn into_artifact(self, root_package: &PackageId) -> Artifact<Unlinked> {
         Artifact {
             arena: self.arena.clone(),
             definition_to_package: self
@@ -57,6 +64,8 @@ impl Transpiled<Unlinked> {
                     (format!("{}.{}", path.join("/"), k.primary), v.clone())
                 })
                 .collect(),
+            assets: Default::default(),
+            config: Default::default(),
         }
     }
 }
@@ -68,29 +77,27 @@ impl<Ext: Clone> Display for Transpiled<Ext> {
 }
 
 impl Transpiled<Unlinked> {
-    pub fn transpile(ic_compiled: IcCompiled, type_defs: TypeDefs<Universal>) -> Self {
+    pub(crate) fn transpile(ic_compiled: IcCompiled, type_defs: TypeDefs<Universal>) -> Self {
         let this: ProgramTranspiler = ProgramTranspiler::transpile_program(&ic_compiled);
         let mut arena = this.dest;
-        let mut closure = |ty: &Type<Universal>| {
-            fn helper(
-                ty: &Type<Universal>,
-                defs: &TypeDefs<Universal>,
-                arena: &mut Arena<Unlinked>,
-            ) -> Result<(), TypeError<Universal>> {
-                match ty {
-                    Type::Either(_, variants) | Type::Choice(_, variants) => {
-                        for k in variants.keys() {
-                            arena.intern(k.string.as_str());
-                        }
+        // Every definition is visited on its own, so names needn't be expanded,
+        // which wouldn't end for mutually recursive ones.
+        fn intern_labels(
+            ty: &Type<Universal>,
+            arena: &mut Arena<Unlinked>,
+        ) -> Result<(), TypeError<Universal>> {
+            match ty {
+                Type::Either(_, variants) | Type::Choice(_, variants) => {
+                    for k in variants.keys() {
+                        arena.intern(k.string.as_str());
                     }
-                    _ => {}
                 }
-                visit::continue_deref(&ty, defs, |ty: &Type<Universal>| helper(ty, &defs, arena))
+                _ => {}
             }
-            helper(ty, &type_defs, &mut arena)
-        };
-        for (_, _, ty) in type_defs.clone().globals.values() {
-            closure(ty).unwrap();
+            visit::continue_(ty, |ty: &Type<Universal>| intern_labels(ty, arena))
+        }
+        for (_, _, ty) in type_defs.globals.values() {
+            intern_labels(ty, &mut arena).unwrap();
         }
 
         Self {
@@ -101,10 +108,15 @@ impl Transpiled<Unlinked> {
                 .iter()
                 .map(|(a, b)| (a.clone(), this.packages_in_nodes.get(b).unwrap().clone()))
                 .collect(),
+            package_owners: ic_compiled
+                .package_owners
+                .iter()
+                .filter_map(|(id, owner)| Some((this.packages_in_nodes.get(id)?.0, owner.clone())))
+                .collect(),
         }
     }
 
-    pub fn compile_file(
+    pub(crate) fn compile_file(
         module: &crate::frontend_impl::program::CheckedModule<Universal>,
         max_interactions: u32,
     ) -> Result<Self, crate::runtime_impl::RuntimeCompilerError> {
@@ -117,22 +129,25 @@ impl Transpiled<Unlinked> {
 }
 
 impl<Ext: Clone> Transpiled<Ext> {
-    pub fn get_with_name(&self, name: &GlobalName<Universal>) -> Option<PackagePtr<Ext>> {
+    pub(crate) fn get_with_name(&self, name: &GlobalName<Universal>) -> Option<PackagePtr<Ext>> {
         Some(self.name_to_package.get(name).cloned()?)
     }
 }
 
 pub(crate) fn link_transpiled(
     transpiled: Transpiled<Unlinked>,
+    overrides: &Overrides,
 ) -> Result<Transpiled<Linked>, LinkError> {
     Ok(Transpiled {
         type_defs: transpiled.type_defs,
-        arena: Arc::new(link_arena(transpiled.arena.as_ref())?),
+        arena: Arc::new(link_arena_with(transpiled.arena.as_ref(), overrides)?),
         name_to_package: transpiled
             .name_to_package
             .iter()
             .map(|(k, v)| (k.clone(), link_package_ptr(v)))
             .collect(),
+        // Linking keeps packages where they are.
+        package_owners: transpiled.package_owners,
     })
 }
 impl NetTranspiler {
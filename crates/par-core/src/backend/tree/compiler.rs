@@ -1,9 +1,10 @@
 use std::{
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, HashMap},
     fmt::{Debug, Display},
     sync::Arc,
 };
 
+use crate::backend::tree::fusion::fuse_list_pipelines;
 use crate::frontend_impl::process::VariableUsage;
 use crate::frontend_impl::program::DefinitionBody;
 use crate::frontend_impl::types::core::get_primitive_type;
@@ -13,6 +14,8 @@ use crate::frontend_impl::{
     types::Type,
 };
 use crate::runtime_impl::tree::net::{Net, Tree};
+use crate::session_type::{self, SessionType};
+use crate::stack;
 use crate::{
     frontend_impl::{
         program::{CheckedModule, Definition},
@@ -25,6 +28,7 @@ use indexmap::{IndexMap, IndexSet};
 use par_runtime::fan_behavior::FanBehavior;
 use par_runtime::linker::Unlinked;
 use par_runtime::poll::POLL_TOKEN;
+use par_runtime::primitive::Primitive;
 use std::hash::Hash;
 
 #[derive(Clone, Debug)]
@@ -118,14 +122,17 @@ pub(crate) struct LoopLabel(Option<LocalName>);
 #[derive(Debug)]
 pub(crate) struct Context {
     vars: BTreeMap<Var, TypedTree>,
-    loop_points: BTreeMap<LoopLabel, BTreeSet<LoopLabel>>,
+    /// The package of the body of every loop in scope. It has no captures, so a
+    /// `loop` instantiates it directly, instead of carrying a copy of it along
+    /// with the variables from one iteration to the next.
+    loop_points: BTreeMap<LoopLabel, usize>,
     unguarded_loop_labels: Vec<LoopLabel>,
 }
 
 pub(crate) struct PackData {
     names: Vec<Var>,
     types: Vec<Type<Universal>>,
-    loop_points: BTreeMap<LoopLabel, BTreeSet<LoopLabel>>,
+    loop_points: BTreeMap<LoopLabel, usize>,
     unguarded_loop_labels: Vec<LoopLabel>,
 }
 
@@ -134,7 +141,6 @@ impl Context {
         &self,
         driver: Option<&LocalName>,
         captures: Option<&Captures>,
-        labels_in_scope: Option<&BTreeSet<LoopLabel>>,
     ) -> PackData {
         let mut m_tys = vec![];
         let mut m_vars = vec![];
@@ -146,13 +152,6 @@ impl Context {
                     }
                 }
             }
-            if let Some(labels_in_scope) = labels_in_scope {
-                if let Var::Loop(label) = name {
-                    if !labels_in_scope.contains(&LoopLabel(label.clone())) {
-                        continue;
-                    }
-                }
-            }
             m_vars.push(name.clone());
             m_tys.push(tree.ty.clone());
         }
@@ -169,7 +168,6 @@ impl Context {
         &mut self,
         driver: Option<&LocalName>,
         captures: Option<&Captures>,
-        labels_in_scope: Option<&BTreeSet<LoopLabel>>,
         net: &mut Net<Unlinked>,
     ) -> (Tree<Unlinked>, PackData) {
         let mut m_trees = vec![];
@@ -184,14 +182,6 @@ impl Context {
                     }
                 }
             }
-            if let Some(labels_in_scope) = labels_in_scope {
-                if let Var::Loop(label) = &name {
-                    if !labels_in_scope.contains(&LoopLabel(label.clone())) {
-                        net.link(tree.tree, Tree::Era);
-                        continue;
-                    }
-                }
-            }
             m_vars.push(name);
             m_trees.push(tree.tree);
             m_tys.push(tree.ty);
@@ -245,15 +235,19 @@ pub(crate) struct Compiler {
     lazy_redexes: Vec<(Tree<Unlinked>, Tree<Unlinked>)>,
     compile_global_stack: IndexSet<GlobalName<Universal>>,
     package_is_case_branch: IndexMap<usize, ArcStr>,
+    /// The definition each package was compiled as part of.
+    package_owners: IndexMap<usize, GlobalName<Universal>>,
     blocks: IndexMap<usize, Arc<Process<Type<Universal>, Universal>>>,
     poll_packages: IndexMap<LocalName, PollInfo>,
+    /// Packages of external definitions given the session type of their type
+    /// argument, see [`SessionTypeArgument`](crate::session_type::SessionTypeArgument).
+    session_type_arguments: IndexSet<usize>,
     max_interactions: u32,
 }
 
 #[derive(Clone)]
 struct PollInfo {
     package_id: usize,
-    labels_in_scope: BTreeSet<LoopLabel>,
     driver: LocalName,
 }
 
@@ -319,6 +313,7 @@ impl Compiler {
                 dependents: self.compile_global_stack.clone(),
             });
         }
+        let mut takes_session_type = false;
         let global = match self.definitions.get(name).cloned() {
             Some((def, _typ)) => match def.body {
                 DefinitionBody::Par(expr) => expr,
@@ -329,13 +324,16 @@ impl Compiler {
                         module: name.module.module.clone(),
                         name: name.primary.clone(),
                     };
+                    takes_session_type = session_type::takes_session_type(&def_ref);
                     Arc::new(Expression::External(def_ref, Type::Break(Span::None)))
                 }
             },
             _ => return Err(Error::GlobalNotFound(name.clone())),
         };
 
-        let (id, typ) = self.in_package(name.to_string(), |this, _| {
+        let (id, typ) = self.in_package(name.to_string(), |this, id| {
+            // Corecursive references to this global resolve to its package.
+            this.global_name_to_id.insert(name.clone(), id);
             let mut s = String::new();
             global.pretty(&mut s, 0).unwrap();
             Ok((
@@ -343,8 +341,10 @@ impl Compiler {
                 (Tree::Continue).with_type(Type::Continue(Span::None)),
             ))
         })?;
-        self.global_name_to_id.insert(name.clone(), id);
         self.compile_global_stack.shift_remove(name);
+        if takes_session_type {
+            self.session_type_arguments.insert(id);
+        }
         Ok(Tree::Package(id, Box::new(Tree::Break), FanBehavior::Expand).with_type(typ))
     }
 
@@ -404,6 +404,9 @@ impl Compiler {
         f: impl FnOnce(&mut Self, usize) -> Result<(TypedTree, TypedTree)>,
     ) -> Result<(usize, Type<Universal>)> {
         let id = self.id_to_package.len();
+        if let Some(owner) = self.compile_global_stack.last() {
+            self.package_owners.insert(id, owner.clone());
+        }
         let old_net = core::mem::take(&mut self.net);
         let old_lazy_redexes = core::mem::take(&mut self.lazy_redexes);
         // Allocate package
@@ -453,6 +456,9 @@ impl Compiler {
             .redexes
             .append(&mut core::mem::take(&mut self.lazy_redexes).into());
         self.net.assert_valid();
+        // The snapshot of the other packages was only needed for normalizing;
+        // keeping it would make every package hold a copy of all before it.
+        self.net.packages = Default::default();
         *self.id_to_package.get_mut(id).unwrap() = core::mem::take(&mut self.net);
         self.lazy_redexes = old_lazy_redexes;
         self.net = old_net;
@@ -470,10 +476,6 @@ impl Compiler {
             let tree = self.use_variable(name, usage, false)?;
             vars.insert(Var::Name(name.clone()), tree);
         }
-        for (label, _) in self.context.loop_points.clone().iter() {
-            let tree = self.use_var(&Var::Loop(label.0.clone()), &VariableUsage::Copy, false)?;
-            vars.insert(Var::Loop(label.0.clone()), tree);
-        }
         let loop_points_before = self.context.loop_points.clone();
         core::mem::swap(&mut vars, &mut self.context.vars);
         let t = f(self);
@@ -576,6 +578,13 @@ impl Compiler {
     fn compile_expression(
         &mut self,
         expr: &Expression<Type<Universal>, Universal>,
+    ) -> Result<TypedTree> {
+        stack::grow(|| self.compile_expression_inner(expr))
+    }
+
+    fn compile_expression_inner(
+        &mut self,
+        expr: &Expression<Type<Universal>, Universal>,
     ) -> Result<TypedTree> {
         match expr {
             Expression::Global(_, name, _) => self.use_global(name),
@@ -585,8 +594,7 @@ impl Compiler {
 
             Expression::Box(span, captures, expression, typ) => {
                 self.with_captures(captures, |this| {
-                    let (context_in, pack_data) =
-                        this.context.pack(None, None, None, &mut this.net);
+                    let (context_in, pack_data) = this.context.pack(None, None, &mut this.net);
                     let (package_id, _) =
                         this.in_package(format!("Box at {span}"), |this, _| {
                             let context_out = this.context.unpack(&pack_data, &mut this.net);
@@ -627,6 +635,10 @@ impl Compiler {
     }
 
     fn compile_process(&mut self, proc: &Process<Type<Universal>, Universal>) -> Result<()> {
+        stack::grow(|| self.compile_process_inner(proc))
+    }
+
+    fn compile_process_inner(&mut self, proc: &Process<Type<Universal>, Universal>) -> Result<()> {
         match proc {
             Process::Let {
                 name, value, then, ..
@@ -685,8 +697,8 @@ impl Compiler {
             Command::Noop(process) => self.compile_process(process)?,
             Command::Link(expr) => self.compile_command_link(&name, usage, expr)?,
             // types get erased.
-            Command::SendType(_argument, process) => {
-                self.compile_command_send_type(name, usage, process)?
+            Command::SendType(argument, process) => {
+                self.compile_command_send_type(name, usage, argument, process)?
             }
             Command::ReceiveType(parameter, process) => {
                 self.compile_command_receive_type(name, usage, parameter, process)?
@@ -752,8 +764,6 @@ impl Compiler {
         then: &Arc<Process<Type<Universal>, Universal>>,
         else_: &Arc<Process<Type<Universal>, Universal>>,
     ) -> Result<()> {
-        let labels_in_scope: BTreeSet<_> = self.context.loop_points.keys().cloned().collect();
-
         if matches!(kind, PollKind::Poll) {
             self.bind_variable(
                 driver.clone(),
@@ -783,16 +793,11 @@ impl Compiler {
         let then = then.clone();
         let else_ = else_.clone();
 
-        let pack_template =
-            self.context
-                .pack_template(Some(&driver), Some(&captures), Some(&labels_in_scope));
+        let pack_template = self.context.pack_template(Some(&driver), Some(&captures));
 
-        let (context_in, _pack_data) = self.context.pack(
-            Some(&driver),
-            Some(&captures),
-            Some(&labels_in_scope),
-            &mut self.net,
-        );
+        let (context_in, _pack_data) =
+            self.context
+                .pack(Some(&driver), Some(&captures), &mut self.net);
 
         let (poll_package_id, _) = self.in_package(
             format!("poll body at {:?}", proc.span()),
@@ -801,7 +806,6 @@ impl Compiler {
                     point.clone(),
                     PollInfo {
                         package_id,
-                        labels_in_scope: labels_in_scope.clone(),
                         driver: driver.clone(),
                     },
                 );
@@ -818,7 +822,7 @@ impl Compiler {
 
                 this.context.unguarded_loop_labels.clear();
                 let (case_context_in, case_pack_data) =
-                    this.context.pack(None, None, None, &mut this.net);
+                    this.context.pack(None, None, &mut this.net);
 
                 let mut branches = HashMap::new();
 
@@ -910,12 +914,9 @@ impl Compiler {
         self.net.link(driver_tree.tree, request);
         self.bind_variable(driver.clone(), next0)?;
 
-        let (context_in, _pack_data) = self.context.pack(
-            Some(driver),
-            Some(captures),
-            Some(&poll_info.labels_in_scope),
-            &mut self.net,
-        );
+        let (context_in, _pack_data) =
+            self.context
+                .pack(Some(driver), Some(captures), &mut self.net);
         self.lazy_redexes.push((
             Tree::Package(poll_package_id, Box::new(context_in), FanBehavior::Expand),
             Tree::Break,
@@ -973,10 +974,31 @@ impl Compiler {
         &mut self,
         name: LocalName,
         usage: &VariableUsage,
+        argument: &Type<Universal>,
         process: &Arc<Process<Type<Universal>, Universal>>,
     ) -> Result<()> {
         let subject = self.use_variable(&name, usage, true)?;
-        self.bind_variable(name, subject.tree.with_type(Type::Break(Span::None)))?;
+        // Types are erased, except for the external definitions that can't do
+        // without, which are sent the session type when called directly.
+        let session_type = match &subject.tree {
+            Tree::Package(id, ..) if self.session_type_arguments.contains(id) => {
+                SessionType::of(&self.type_defs, argument)
+            }
+            _ => None,
+        };
+        let Some(session_type) = session_type else {
+            self.bind_variable(name, subject.tree.with_type(Type::Break(Span::None)))?;
+            return self.compile_process(process);
+        };
+        let (v0, v1) = self.create_typed_wire();
+        self.bind_variable(name, v0)?;
+        self.net.link(
+            Tree::Times(
+                Box::new(v1.tree),
+                Box::new(Tree::Primitive(Primitive::Bytes(session_type.to_bytes()))),
+            ),
+            subject.tree,
+        );
         self.compile_process(process)
     }
 
@@ -1064,7 +1086,7 @@ impl Compiler {
     ) -> Result<()> {
         self.context.unguarded_loop_labels.clear();
         let old_tree = self.use_variable(&name, usage, true)?;
-        let (context_in, pack_data) = self.context.pack(None, None, None, &mut self.net);
+        let (context_in, pack_data) = self.context.pack(None, None, &mut self.net);
 
         let mut branches = HashMap::new();
         let mut choice_and_process: Vec<_> = names.iter().zip(processes.iter()).collect();
@@ -1138,39 +1160,18 @@ impl Compiler {
         body: &Arc<Process<Type<Universal>, Universal>>,
     ) -> Result<()> {
         let label = LoopLabel(label.clone());
-
-        let (def0, def1) = self.net.create_wire();
-        let prev = self.context.vars.insert(
-            Var::Loop(label.0.clone()),
-            def0.with_type(Type::Break(Span::default())),
-        );
-
-        if let Some(prev_tree) = prev {
-            self.net.link(prev_tree.tree, Tree::Era);
-        }
-
-        let mut labels_in_scope: BTreeSet<_> = self.context.loop_points.keys().cloned().collect();
-        labels_in_scope.insert(label.clone());
-        self.context
-            .loop_points
-            .insert(label.clone(), labels_in_scope);
         self.context.unguarded_loop_labels.push(label.clone());
 
-        let (context_in, pack_data) =
-            self.context
-                .pack(Some(name), Some(captures), None, &mut self.net);
-        let (id, _) = self.in_package(format!("Loop body at {span}"), |this, _| {
+        let (context_in, pack_data) = self.context.pack(Some(name), Some(captures), &mut self.net);
+        let (id, _) = self.in_package(format!("Loop body at {span}"), |this, id| {
             let context_out = this.context.unpack(&pack_data, &mut this.net);
+            this.context.loop_points.insert(label, id);
             this.compile_process(body)?;
             Ok((
                 context_out.with_type(Type::Break(Span::default())),
                 (Tree::Continue).with_type(Type::Continue(Span::default())),
             ))
         })?;
-        self.net.link(
-            def1,
-            Tree::Package(id, Box::new(Tree::Break), FanBehavior::Propagate),
-        );
         self.net.link(
             context_in,
             Tree::Package(id, Box::new(Tree::Break), FanBehavior::Propagate),
@@ -1191,17 +1192,18 @@ impl Compiler {
         if self.context.unguarded_loop_labels.contains(&label) {
             return Err(Error::UnguardedLoop(span.clone(), label.clone().0));
         }
-        let tree = self.use_var(&Var::Loop(label.0.clone()), &VariableUsage::Copy, false)?;
+        let Some(&id) = self.context.loop_points.get(&label) else {
+            return Err(Error::UnboundVar(span.clone(), Var::Loop(label.0)));
+        };
         let driver_tree = self.use_variable(name, usage, true)?;
         self.bind_variable(driver.clone(), driver_tree)?;
-        let labels_in_scope = self.context.loop_points.get(&label).unwrap().clone();
-        let (context_in, _) = self.context.pack(
-            Some(driver),
-            Some(captures),
-            Some(&labels_in_scope),
-            &mut self.net,
-        );
-        self.net.redexes.push_back((tree.tree, context_in));
+        let (context_in, _) = self
+            .context
+            .pack(Some(driver), Some(captures), &mut self.net);
+        self.net.redexes.push_back((
+            Tree::Package(id, Box::new(Tree::Break), FanBehavior::Propagate),
+            context_in,
+        ));
         Ok(())
     }
 
@@ -1216,10 +1218,11 @@ impl Compiler {
 }
 
 #[derive(Clone, Default)]
-pub struct IcCompiled {
+pub(crate) struct IcCompiled {
     pub(crate) id_to_package: Arc<IndexMap<usize, Net<Unlinked>>>,
     pub(crate) name_to_id: IndexMap<GlobalName<Universal>, usize>,
     package_is_case_branch: IndexMap<usize, ArcStr>,
+    pub(crate) package_owners: IndexMap<usize, GlobalName<Universal>>,
 }
 
 impl Display for IcCompiled {
@@ -1238,22 +1241,11 @@ impl Display for IcCompiled {
 }
 
 impl IcCompiled {
-    pub fn get_with_name(&self, name: &GlobalName<Universal>) -> Option<Net<Unlinked>> {
-        let id = self.name_to_id.get(name)?;
-        self.id_to_package.get(id).cloned()
-    }
-
-    pub fn get_case_branch_name(&self, id: usize) -> Option<ArcStr> {
+    pub(crate) fn get_case_branch_name(&self, id: usize) -> Option<ArcStr> {
         self.package_is_case_branch.get(&id).cloned()
     }
 
-    pub fn create_net(&self) -> Net<Unlinked> {
-        let mut net = Net::default();
-        net.packages = self.id_to_package.clone();
-        net
-    }
-
-    pub fn compile_file(
+    pub(crate) fn compile_file(
         program: &CheckedModule<Universal>,
         max_interactions: u32,
     ) -> Result<IcCompiled> {
@@ -1265,14 +1257,16 @@ impl IcCompiled {
                 unguarded_loop_labels: Default::default(),
             },
             type_defs: program.type_defs.clone(),
-            definitions: program.definitions.clone(),
+            definitions: fuse_list_pipelines(&program.definitions),
             global_name_to_id: Default::default(),
             id_to_package: Default::default(),
             compile_global_stack: Default::default(),
             lazy_redexes: vec![],
             package_is_case_branch: Default::default(),
+            package_owners: Default::default(),
             blocks: IndexMap::new(),
             poll_packages: Default::default(),
+            session_type_arguments: Default::default(),
             max_interactions: max_interactions,
         };
 
@@ -1284,6 +1278,147 @@ impl IcCompiled {
             id_to_package: Arc::new(compiler.id_to_package.into_iter().enumerate().collect()),
             name_to_id: compiler.global_name_to_id,
             package_is_case_branch: compiler.package_is_case_branch,
+            package_owners: compiler.package_owners,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_runtime::pkgid::PackageId;
+
+    use super::*;
+    use crate::location::FileName;
+    use crate::pipeline::{Pipeline, SourcePackage, Stage};
+    use crate::runtime_impl::tree::net::VarState;
+    use crate::workspace::LoadedPackageFile;
+
+    fn compile(source: &str) -> IcCompiled {
+        let id = PackageId::Special(literal!("__test__"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("local/Main.par"),
+                    relative_path_from_src: PathBuf::from("Main.par"),
+                    source: source.to_owned(),
+                }],
+            }],
+        );
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(pipeline.artifacts().type_errors.is_empty());
+        let checked = pipeline.artifacts().checked.as_ref().unwrap();
+        IcCompiled::compile_file(checked.checked_module(), 10_000).unwrap()
+    }
+
+    /// Calls `f` on every node reachable from `tree`, following linked variables.
+    fn visit(net: &Net<Unlinked>, tree: &Tree<Unlinked>, f: &mut impl FnMut(&Tree<Unlinked>)) {
+        f(tree);
+        match tree {
+            Tree::Par(a, b) | Tree::Times(a, b) | Tree::Dup(a, b) => {
+                visit(net, a, f);
+                visit(net, b, f);
+            }
+            Tree::Signal(_, tree) | Tree::Package(_, tree, _) | Tree::Choice(tree, _, _) => {
+                visit(net, tree, f)
+            }
+            Tree::Var(id) => {
+                if let Some(VarState::Linked(tree)) = net.variables.get(*id) {
+                    visit(net, tree, f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn loops_instantiate_their_body_without_copying_it() {
+        let compiled = compile(
+            "\
+module Main
+
+type Stream = iterative choice {
+  .close => !,
+  .next => self,
+}
+
+dec Forever : Stream
+def Forever = begin case {
+  .close => !,
+  .next => loop,
+}
+",
+        );
+        let loop_body = compiled
+            .id_to_package
+            .iter()
+            .find(|(_, net)| net.debug_name.starts_with("Loop body"))
+            .map(|(id, _)| *id)
+            .expect("a package for the loop body");
+
+        let mut references = 0;
+        for net in compiled.id_to_package.values() {
+            let trees = net
+                .ports
+                .iter()
+                .chain(net.redexes.iter().flat_map(|(a, b)| [a, b]));
+            for tree in trees {
+                visit(net, tree, &mut |node| match node {
+                    Tree::Dup(..) => panic!("loops copy their body in {}", net.show()),
+                    Tree::Package(id, _, _) if *id == loop_body => references += 1,
+                    _ => {}
+                });
+            }
+        }
+        assert!(references > 0, "nothing instantiates the loop body");
+    }
+
+    #[test]
+    fn packages_are_owned_by_the_definition_they_were_compiled_for() {
+        let compiled = compile(
+            "\
+module Main
+
+type Stream = iterative choice {
+  .close => !,
+  .next => self,
+}
+
+dec Alias : Stream
+def Alias = Forever
+
+dec Forever : Stream
+def Forever = begin case {
+  .close => !,
+  .next => loop,
+}
+",
+        );
+        let name_of = |id: usize| {
+            compiled
+                .package_owners
+                .get(&id)
+                .unwrap_or_else(|| panic!("package {id} has no owner"))
+                .primary
+                .clone()
+        };
+        for (name, id) in &compiled.name_to_id {
+            assert_eq!(name_of(*id), name.primary);
+        }
+        // `Alias` only refers to `Forever`, whose case branches and loop body
+        // are packages of its own.
+        let inner: Vec<_> = compiled
+            .id_to_package
+            .keys()
+            .filter(|id| compiled.name_to_id.values().all(|named| named != *id))
+            .map(|id| name_of(*id))
+            .collect();
+        assert!(inner.iter().any(|name| name == "Forever"));
+        assert!(inner.iter().all(|name| name != "Alias"));
+    }
+}
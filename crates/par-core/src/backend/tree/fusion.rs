@@ -0,0 +1,912 @@
+//! Fusing consecutive steps of list pipelines.
+//!
+//! A pipeline like `xs->List.Map(f)->List.Filter(g)` builds a whole list
+//! between its two steps, only to take it apart again right away. Steps that
+//! go together are replaced with what does both from `@core/List`: two steps
+//! giving lists become a single `Map`, `Filter` or `FilterMap` step, with their
+//! functions combined, as in `xs->List.FilterMap(List.MapThenFilter(f, g))`.
+//! A step followed by `Sum` or `Fold` becomes one definition, like
+//! `MapThenSum`, which takes the arguments of both and walks the list once.
+//!
+//! Fusing goes on until no two steps go together, so a whole pipeline, like
+//! `xs->List.Map(f)->List.Filter(g)->List.Sum`, builds no list in between.
+//!
+//! This runs on checked programs right before they're compiled, so type errors
+//! and editor tooling keep seeing the steps as written.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use par_runtime::pkgid::{BuiltinPackage, PackageId};
+
+use crate::frontend_impl::{
+    language::{GlobalName, LocalName, Universal},
+    process::{Captures, Command, Expression, Process, VariableUsage},
+    program::{Definition, DefinitionBody},
+    types::Type,
+};
+use crate::location::{Span, Spanning};
+
+type Expr = Expression<Type<Universal>, Universal>;
+type Proc = Process<Type<Universal>, Universal>;
+type Definitions =
+    IndexMap<GlobalName<Universal>, (Definition<Arc<Expr>, Universal>, Type<Universal>)>;
+
+/// Two steps of a pipeline, and what does both. The first step always takes a
+/// single argument besides the list.
+const FUSED_STEPS: &[(&str, &str, Fused<&str>)] = &[
+    ("Map", "Map", Fused::Step("Map", "MapThenMap")),
+    ("Map", "Filter", Fused::Step("FilterMap", "MapThenFilter")),
+    ("Map", "FilterMap", Fused::Step("FilterMap", "MapThenMap")),
+    ("Map", "Sum", Fused::Sink("MapThenSum")),
+    ("Map", "Fold", Fused::Sink("MapThenFold")),
+    ("Filter", "Map", Fused::Step("FilterMap", "FilterThenMap")),
+    (
+        "Filter",
+        "Filter",
+        Fused::Step("Filter", "FilterThenFilter"),
+    ),
+    (
+        "Filter",
+        "FilterMap",
+        Fused::Step("FilterMap", "FilterThenFilterMap"),
+    ),
+    ("Filter", "Sum", Fused::Sink("FilterThenSum")),
+    ("Filter", "Fold", Fused::Sink("FilterThenFold")),
+    (
+        "FilterMap",
+        "Map",
+        Fused::Step("FilterMap", "FilterMapThenMap"),
+    ),
+    (
+        "FilterMap",
+        "Filter",
+        Fused::Step("FilterMap", "FilterMapThenFilter"),
+    ),
+    (
+        "FilterMap",
+        "FilterMap",
+        Fused::Step("FilterMap", "FilterMapThenFilterMap"),
+    ),
+    ("FilterMap", "Sum", Fused::Sink("FilterMapThenSum")),
+    ("FilterMap", "Fold", Fused::Sink("FilterMapThenFold")),
+];
+
+/// What two steps of a pipeline fuse into.
+enum Fused<G> {
+    /// A single step, whose argument is the combinator applied to the
+    /// arguments of both.
+    Step(G, G),
+    /// A definition taking the list, the argument of the first step, and then
+    /// the arguments of the second.
+    Sink(G),
+}
+
+/// Fuses the list pipelines in every definition. Does nothing if `@core/List`
+/// isn't part of the program.
+pub(crate) fn fuse_list_pipelines(definitions: &Definitions) -> Definitions {
+    let fusion = Fusion::new(definitions);
+    if fusion.fused.is_empty() {
+        return definitions.clone();
+    }
+    definitions
+        .iter()
+        .map(|(name, (definition, typ))| {
+            let body = match &definition.body {
+                DefinitionBody::Par(expression) => {
+                    DefinitionBody::Par(fusion.expression(expression))
+                }
+                DefinitionBody::External(span) => DefinitionBody::External(span.clone()),
+            };
+            let definition = Definition {
+                span: definition.span.clone(),
+                name: definition.name.clone(),
+                body,
+            };
+            (name.clone(), (definition, typ.clone()))
+        })
+        .collect()
+}
+
+type Global = (GlobalName<Universal>, Type<Universal>);
+
+struct Fusion {
+    /// The fusions whose definitions are present in the program, with their
+    /// types.
+    fused: HashMap<(&'static str, &'static str), Fused<Global>>,
+}
+
+impl Fusion {
+    fn new(definitions: &Definitions) -> Self {
+        let global = |primary: &str| {
+            let name = list_global(primary);
+            let (_, typ) = definitions.get(&name)?;
+            Some((name, typ.clone()))
+        };
+        let fused = FUSED_STEPS
+            .iter()
+            .filter_map(|(first, second, fused)| {
+                let fused = match fused {
+                    Fused::Step(step, combinator) => {
+                        Fused::Step(global(step)?, global(combinator)?)
+                    }
+                    Fused::Sink(sink) => Fused::Sink(global(sink)?),
+                };
+                Some(((*first, *second), fused))
+            })
+            .collect();
+        Self { fused }
+    }
+
+    /// What does both `first` and `second`, if they can be fused.
+    fn fused(
+        &self,
+        first: &GlobalName<Universal>,
+        second: &GlobalName<Universal>,
+    ) -> Option<&Fused<Global>> {
+        if !is_in_list(first) || !is_in_list(second) {
+            return None;
+        }
+        self.fused
+            .iter()
+            .find(|((f, s), _)| *f == first.primary && *s == second.primary)
+            .map(|(_, fused)| fused)
+    }
+
+    /// `xs->First(f)->Second(...)`, which lowers to
+    ///
+    /// ```text
+    /// let #temp = First; #temp(#object); let #object = #temp; #object(f)
+    /// let #temp = Second; #temp(#object); ...
+    /// ```
+    ///
+    /// becomes `xs->Step(Combinator(f, g))` or `xs->Sink(f, ...)`. The last
+    /// step of a pipe is linked to `#result` as `#temp` if it takes nothing
+    /// but the list, so only a sink may follow without `let #object = #temp`.
+    fn fuse_pipe(&self, process: &Arc<Proc>) -> Option<Arc<Proc>> {
+        let (first, _, rebind) = piped(process)?;
+        let after_rebind = rebound(rebind)?;
+        let (argument, after_argument) = send_to_object(after_rebind)?;
+        let (second, second_send, rest) = piped(after_argument)?;
+        match self.fused(first, second)? {
+            Fused::Step((step, typ), combinator) => {
+                let (second_argument, rest) = send_to_object(rebound(rest)?)?;
+                let argument = combined(after_rebind.span(), combinator, argument, second_argument);
+                let then = with_then(rebind, with_sent(after_rebind, argument, Arc::clone(rest)));
+                Some(with_piped(process, step, typ, then))
+            }
+            Fused::Sink((sink, typ)) => {
+                let then = with_sent(second_send, Arc::clone(argument), Arc::clone(rest));
+                Some(with_piped(process, sink, typ, then))
+            }
+        }
+    }
+
+    /// `Second(First(xs, f), ...)`, which lowers to
+    ///
+    /// ```text
+    /// let #object = Second
+    /// #object(chan #result {
+    ///   let #object = First; #object(xs); #object(f); #result <> #object
+    /// })
+    /// ...
+    /// ```
+    ///
+    /// becomes `Step(xs, Combinator(f, g))` or `Sink(xs, f, ...)`.
+    fn fuse_call(&self, process: &Arc<Proc>) -> Option<Arc<Proc>> {
+        let (second, after_second) = call(process)?;
+        let (list, rest) = send_to_object(after_second)?;
+        let Expression::Chan {
+            chan_name,
+            chan_annotation: None,
+            process: inner,
+            ..
+        } = list.as_ref()
+        else {
+            return None;
+        };
+        if *chan_name != LocalName::result() {
+            return None;
+        }
+        let (first, after_first) = call(inner)?;
+        let (list, after_list) = send_to_object(after_first)?;
+        let (argument, end) = send_to_object(after_list)?;
+        if !links_result_to_object(end) {
+            return None;
+        }
+        match self.fused(first, second)? {
+            Fused::Step((step, typ), combinator) => {
+                let (second_argument, rest) = send_to_object(rest)?;
+                let argument = combined(after_first.span(), combinator, argument, second_argument);
+                let then = with_sent(
+                    after_first,
+                    Arc::clone(list),
+                    with_sent(after_list, argument, Arc::clone(rest)),
+                );
+                Some(with_call(process, step, typ, then))
+            }
+            Fused::Sink((sink, typ)) => {
+                let then = with_sent(
+                    after_first,
+                    Arc::clone(list),
+                    with_sent(after_list, Arc::clone(argument), Arc::clone(rest)),
+                );
+                Some(with_call(process, sink, typ, then))
+            }
+        }
+    }
+
+    /// Pipes are fused from their start before going inside, calls from the
+    /// inside out, since the first step of a call is nested in the second.
+    fn process(&self, process: &Arc<Proc>) -> Arc<Proc> {
+        let mut process = Arc::clone(process);
+        while let Some(fused) = self.fuse_pipe(&process) {
+            process = fused;
+        }
+        let mut process = self.inside(&process);
+        while let Some(fused) = self.fuse_call(&process) {
+            process = fused;
+        }
+        process
+    }
+
+    fn inside(&self, process: &Arc<Proc>) -> Arc<Proc> {
+        match process.as_ref() {
+            Process::Let {
+                span,
+                name,
+                annotation,
+                typ,
+                value,
+                then,
+            } => Arc::new(Process::Let {
+                span: span.clone(),
+                name: name.clone(),
+                annotation: annotation.clone(),
+                typ: typ.clone(),
+                value: self.expression(value),
+                then: self.process(then),
+            }),
+            Process::Do {
+                span,
+                name,
+                usage,
+                typ,
+                command,
+            } => Arc::new(Process::Do {
+                span: span.clone(),
+                name: name.clone(),
+                usage: usage.clone(),
+                typ: typ.clone(),
+                command: self.command(command),
+            }),
+            Process::Poll {
+                span,
+                kind,
+                driver,
+                point,
+                clients,
+                name,
+                name_typ,
+                captures,
+                then,
+                else_,
+            } => Arc::new(Process::Poll {
+                span: span.clone(),
+                kind: kind.clone(),
+                driver: driver.clone(),
+                point: point.clone(),
+                clients: clients.iter().map(|e| self.expression(e)).collect(),
+                name: name.clone(),
+                name_typ: name_typ.clone(),
+                captures: captures.clone(),
+                then: self.process(then),
+                else_: self.process(else_),
+            }),
+            Process::Submit {
+                span,
+                driver,
+                point,
+                values,
+                captures,
+            } => Arc::new(Process::Submit {
+                span: span.clone(),
+                driver: driver.clone(),
+                point: point.clone(),
+                values: values.iter().map(|e| self.expression(e)).collect(),
+                captures: captures.clone(),
+            }),
+            Process::Block(span, index, body, then) => Arc::new(Process::Block(
+                span.clone(),
+                *index,
+                self.process(body),
+                self.process(then),
+            )),
+            Process::Goto(..) | Process::Unreachable(_) => Arc::clone(process),
+        }
+    }
+
+    fn command(
+        &self,
+        command: &Command<Type<Universal>, Universal>,
+    ) -> Command<Type<Universal>, Universal> {
+        match command {
+            Command::Noop(process) => Command::Noop(self.process(process)),
+            Command::Link(expression) => Command::Link(self.expression(expression)),
+            Command::Send(argument, process) => {
+                Command::Send(self.expression(argument), self.process(process))
+            }
+            Command::Receive(parameter, annotation, typ, process, vars) => Command::Receive(
+                parameter.clone(),
+                annotation.clone(),
+                typ.clone(),
+                self.process(process),
+                vars.clone(),
+            ),
+            Command::Signal(chosen, process) => {
+                Command::Signal(chosen.clone(), self.process(process))
+            }
+            Command::Case(branches, processes, else_process) => Command::Case(
+                Arc::clone(branches),
+                processes.iter().map(|p| self.process(p)).collect(),
+                else_process.as_ref().map(|p| self.process(p)),
+            ),
+            Command::Break => Command::Break,
+            Command::Continue(process) => Command::Continue(self.process(process)),
+            Command::Begin {
+                unfounded,
+                label,
+                subjects,
+                captures,
+                body,
+            } => Command::Begin {
+                unfounded: *unfounded,
+                label: label.clone(),
+                subjects: subjects.clone(),
+                captures: captures.clone(),
+                body: self.process(body),
+            },
+            Command::Loop(label, driver, captures) => {
+                Command::Loop(label.clone(), driver.clone(), captures.clone())
+            }
+            Command::SendType(argument, process) => {
+                Command::SendType(argument.clone(), self.process(process))
+            }
+            Command::ReceiveType(parameter, process) => {
+                Command::ReceiveType(parameter.clone(), self.process(process))
+            }
+        }
+    }
+
+    fn expression(&self, expression: &Arc<Expr>) -> Arc<Expr> {
+        match expression.as_ref() {
+            Expression::Box(span, captures, inner, typ) => Arc::new(Expression::Box(
+                span.clone(),
+                captures.clone(),
+                self.expression(inner),
+                typ.clone(),
+            )),
+            Expression::Chan {
+                span,
+                captures,
+                chan_name,
+                chan_annotation,
+                chan_type,
+                expr_type,
+                process,
+            } => Arc::new(Expression::Chan {
+                span: span.clone(),
+                captures: captures.clone(),
+                chan_name: chan_name.clone(),
+                chan_annotation: chan_annotation.clone(),
+                chan_type: chan_type.clone(),
+                expr_type: expr_type.clone(),
+                process: self.process(process),
+            }),
+            Expression::Global(..)
+            | Expression::Variable(..)
+            | Expression::Primitive(..)
+            | Expression::External(..) => Arc::clone(expression),
+        }
+    }
+}
+
+fn list_global(primary: &str) -> GlobalName<Universal> {
+    GlobalName::new(
+        Default::default(),
+        Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("List"),
+        },
+        primary.to_owned(),
+    )
+}
+
+fn is_in_list(name: &GlobalName<Universal>) -> bool {
+    name.module.package == PackageId::Builtin(BuiltinPackage::Core)
+        && name.module.directories.is_empty()
+        && name.module.module == "List"
+}
+
+fn is_variable(expression: &Expr, name: &LocalName) -> bool {
+    matches!(expression, Expression::Variable(_, variable, _, _) if variable == name)
+}
+
+/// `let #temp = G; #temp(#object); then`, giving `G`, the send and `then`.
+fn piped(process: &Proc) -> Option<(&GlobalName<Universal>, &Arc<Proc>, &Arc<Proc>)> {
+    let Process::Let {
+        name, value, then, ..
+    } = process
+    else {
+        return None;
+    };
+    let Expression::Global(_, global, _) = value.as_ref() else {
+        return None;
+    };
+    let Process::Do {
+        name: sender,
+        command: Command::Send(object, after),
+        ..
+    } = then.as_ref()
+    else {
+        return None;
+    };
+    (*name == LocalName::temp() && sender == name && is_variable(object, &LocalName::object()))
+        .then_some((global, then, after))
+}
+
+/// `let #object = #temp; then`
+fn rebound(process: &Proc) -> Option<&Arc<Proc>> {
+    let Process::Let {
+        name, value, then, ..
+    } = process
+    else {
+        return None;
+    };
+    (*name == LocalName::object() && is_variable(value, &LocalName::temp())).then_some(then)
+}
+
+/// `let #object = G; then`
+fn call(process: &Proc) -> Option<(&GlobalName<Universal>, &Arc<Proc>)> {
+    let Process::Let {
+        name, value, then, ..
+    } = process
+    else {
+        return None;
+    };
+    let Expression::Global(_, global, _) = value.as_ref() else {
+        return None;
+    };
+    (*name == LocalName::object()).then_some((global, then))
+}
+
+/// `#object(argument); then`
+fn send_to_object(process: &Proc) -> Option<(&Arc<Expr>, &Arc<Proc>)> {
+    let Process::Do {
+        name,
+        command: Command::Send(argument, then),
+        ..
+    } = process
+    else {
+        return None;
+    };
+    (*name == LocalName::object()).then_some((argument, then))
+}
+
+/// `#result <> #object`
+fn links_result_to_object(process: &Proc) -> bool {
+    matches!(
+        process,
+        Process::Do { name, command: Command::Link(linked), .. }
+            if *name == LocalName::result() && is_variable(linked, &LocalName::object())
+    )
+}
+
+/// `Combinator(first, second)`, lowered like a call:
+///
+/// ```text
+/// chan #result {
+///   let #object = Combinator; #object(first); #object(second); #result <> #object
+/// }
+/// ```
+fn combined(span: Span, combinator: &Global, first: &Arc<Expr>, second: &Arc<Expr>) -> Arc<Expr> {
+    let (name, typ) = combinator;
+    let applied_once = applied(typ);
+    let returned = applied(&applied_once);
+    let link = Arc::new(Process::Do {
+        span: span.clone(),
+        name: LocalName::result(),
+        usage: VariableUsage::Move,
+        typ: returned.clone().dual(Span::None),
+        command: Command::Link(Arc::new(Expression::Variable(
+            span.clone(),
+            LocalName::object(),
+            returned.clone(),
+            VariableUsage::Move,
+        ))),
+    });
+    let send = |typ: &Type<Universal>, argument: &Arc<Expr>, then| {
+        Arc::new(Process::Do {
+            span: span.clone(),
+            name: LocalName::object(),
+            usage: VariableUsage::Move,
+            typ: typ.clone(),
+            command: Command::Send(Arc::clone(argument), then),
+        })
+    };
+    let process = Arc::new(Process::Let {
+        span: span.clone(),
+        name: LocalName::object(),
+        annotation: None,
+        typ: typ.clone(),
+        value: Arc::new(Expression::Global(span.clone(), name.clone(), typ.clone())),
+        then: send(typ, first, send(&applied_once, second, link)),
+    });
+    let mut captures = Captures::new();
+    for argument in [first, second] {
+        match argument.as_ref() {
+            Expression::Variable(span, name, _, usage) => {
+                captures.add(name.clone(), span.clone(), usage.clone())
+            }
+            Expression::Box(_, inner, ..)
+            | Expression::Chan {
+                captures: inner, ..
+            } => captures.extend(inner.clone()),
+            Expression::Global(..) | Expression::Primitive(..) | Expression::External(..) => {}
+        }
+    }
+    Arc::new(Expression::Chan {
+        span,
+        captures,
+        chan_name: LocalName::result(),
+        chan_annotation: None,
+        chan_type: returned.clone().dual(Span::None),
+        expr_type: returned,
+        process,
+    })
+}
+
+/// What a function of type `typ` gives back once it's sent an argument.
+fn applied(typ: &Type<Universal>) -> Type<Universal> {
+    match typ {
+        Type::Function(_, _, then, _) => (**then).clone(),
+        Type::Forall(_, _, body) => applied(body),
+        typ => typ.clone(),
+    }
+}
+
+/// `send`, a send, sending `argument` and continuing with `then`.
+fn with_sent(send: &Arc<Proc>, argument: Arc<Expr>, then: Arc<Proc>) -> Arc<Proc> {
+    let Process::Do {
+        span,
+        name,
+        usage,
+        typ,
+        ..
+    } = send.as_ref()
+    else {
+        unreachable!("matched by `send_to_object`")
+    };
+    Arc::new(Process::Do {
+        span: span.clone(),
+        name: name.clone(),
+        usage: usage.clone(),
+        typ: typ.clone(),
+        command: Command::Send(argument, then),
+    })
+}
+
+/// `step`, a pipe step, piping into `global` and continuing with `then`.
+fn with_piped(
+    step: &Arc<Proc>,
+    global: &GlobalName<Universal>,
+    global_type: &Type<Universal>,
+    then: Arc<Proc>,
+) -> Arc<Proc> {
+    let Process::Let {
+        span,
+        name,
+        annotation,
+        typ,
+        value,
+        then: send,
+    } = step.as_ref()
+    else {
+        unreachable!("matched by `piped`")
+    };
+    let Process::Do {
+        command: Command::Send(object, _),
+        ..
+    } = send.as_ref()
+    else {
+        unreachable!("matched by `piped`")
+    };
+    Arc::new(Process::Let {
+        span: span.clone(),
+        name: name.clone(),
+        annotation: annotation.clone(),
+        typ: typ.clone(),
+        value: with_global(value, global, global_type),
+        then: with_sent(send, Arc::clone(object), then),
+    })
+}
+
+/// `binding`, a `let`, continuing with `then`.
+fn with_then(binding: &Arc<Proc>, then: Arc<Proc>) -> Arc<Proc> {
+    let Process::Let {
+        span,
+        name,
+        annotation,
+        typ,
+        value,
+        ..
+    } = binding.as_ref()
+    else {
+        unreachable!("matched by `rebound`")
+    };
+    Arc::new(Process::Let {
+        span: span.clone(),
+        name: name.clone(),
+        annotation: annotation.clone(),
+        typ: typ.clone(),
+        value: Arc::clone(value),
+        then,
+    })
+}
+
+/// `call`, a call, calling `global` and continuing with `then`.
+fn with_call(
+    call: &Arc<Proc>,
+    global: &GlobalName<Universal>,
+    global_type: &Type<Universal>,
+    then: Arc<Proc>,
+) -> Arc<Proc> {
+    let Process::Let {
+        span,
+        name,
+        annotation,
+        typ,
+        value,
+        ..
+    } = call.as_ref()
+    else {
+        unreachable!("matched by `call`")
+    };
+    Arc::new(Process::Let {
+        span: span.clone(),
+        name: name.clone(),
+        annotation: annotation.clone(),
+        typ: typ.clone(),
+        value: with_global(value, global, global_type),
+        then,
+    })
+}
+
+/// `value`, a global, replaced with `global`.
+fn with_global(
+    value: &Expr,
+    global: &GlobalName<Universal>,
+    global_type: &Type<Universal>,
+) -> Arc<Expr> {
+    let Expression::Global(span, _, _) = value else {
+        unreachable!("matched as a global")
+    };
+    Arc::new(Expression::Global(
+        span.clone(),
+        global.clone(),
+        global_type.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::location::FileName;
+    use crate::pipeline::{Pipeline, SourcePackage, Stage};
+    use crate::workspace::{CheckedWorkspace, LoadedPackageFile};
+
+    const LIST: &str = "\
+module List
+
+export {
+  type List<a> = recursive either {
+    .end!,
+    .item(a) self,
+  }
+  type Bool = either { .false!, .true! }
+  type Option<a> = either { .some a, .none! }
+
+  dec Map : <a>[List<a>] <b>[box [a] b] List<b>
+  dec Filter : <a: box>[List<a>] [box [a] Bool] List<a>
+  dec FilterMap : <a>[List<a>] <b>[box [a] Option<b>] List<b>
+  dec Fold : <a>[List<a>] <r>[r] [box [r, a] r] r
+  dec Sum : [List<Bool>] List<Bool>
+}
+
+dec MapThenFilter : <a, b: box>[box [a] b] [box [b] Bool] box [a] Option<b>
+dec FilterMapThenMap : <a, b, c>[box [a] Option<b>] [box [b] c] box [a] Option<c>
+dec FilterMapThenFold : <a>[List<a>] <b>[box [a] Option<b>] <r>[r] [box [r, b] r] r
+dec FilterMapThenSum : [List<Bool>] [box [Bool] Option<Bool>] List<Bool>
+
+def Map = <a>[list] <b>[f] list.begin.case {
+  .end! => .end!,
+  .item(v) list => .item(f(v)) list.loop,
+}
+
+def Filter = <a: box>[list] [g] list.begin.case {
+  .end! => .end!,
+  .item(v) list => g(v).case {
+    .true! => .item(v) list.loop,
+    .false! => list.loop,
+  },
+}
+
+def FilterMap = <a>[list] <b>[f] list.begin.case {
+  .end! => .end!,
+  .item(v) list => f(v).case {
+    .some v => .item(v) list.loop,
+    .none! => list.loop,
+  },
+}
+
+def Fold = <a>[list] <r>[result] [f] list.begin.case {
+  .end! => result,
+  .item(v) list => do { result->f(v) } in list.loop,
+}
+
+def Sum = [list] list
+
+def MapThenFilter = <a, b: box>[f] [g] box [x] let y = f(x) in g(y).case {
+  .true! => .some y,
+  .false! => .none!,
+}
+
+def FilterMapThenMap = <a, b, c>[f] [g] box [x] f(x).case {
+  .some y => .some {g(y)},
+  .none! => .none!,
+}
+
+def FilterMapThenFold = <a>[list] <b>[f] <r>[result] [g] list.begin.case {
+  .end! => result,
+  .item(v) list => f(v).case {
+    .some w => do { result->g(w) } in list.loop,
+    .none! => list.loop,
+  },
+}
+
+dec Piped : [List<Bool>] List<Bool>
+def Piped = [xs] xs->Map(box [x] x)->Filter(box [x] x)
+
+dec Chained : [List<Bool>] List<Bool>
+def Chained = [xs] xs->Map(box [x] x)->Filter(box [x] x)->Map(box [x] x)
+
+dec Folded : [List<Bool>, List<Bool>] List<Bool>
+def Folded = [xs, ys] xs->Map(box [x] x)->Filter(box [x] x)->Fold(ys, box [r, x] .item(x) r)
+
+def FilterMapThenSum = [list] [f] list->FilterMap(f)
+
+dec Summed : [List<Bool>] List<Bool>
+def Summed = [xs] xs->Map(box [x] x)->Filter(box [x] x)->Sum
+
+dec Called : [List<Bool>] List<Bool>
+def Called = [xs] Filter(Map(xs, box [x] x), box [x] x)
+
+dec CalledFolded : [List<Bool>, List<Bool>] List<Bool>
+def CalledFolded = [xs, ys] Fold(Filter(Map(xs, box [x] x), box [x] x), ys, box [r, x] .item(x) r)
+";
+
+    fn globals(process: &Proc, found: &mut Vec<String>) {
+        match process {
+            Process::Let { value, then, .. } => {
+                globals_in(value, found);
+                globals(then, found);
+            }
+            Process::Do { command, .. } => match command {
+                Command::Send(argument, then) => {
+                    globals_in(argument, found);
+                    globals(then, found);
+                }
+                Command::Link(expression) => globals_in(expression, found),
+                Command::Noop(then)
+                | Command::Receive(_, _, _, then, _)
+                | Command::SendType(_, then)
+                | Command::ReceiveType(_, then) => globals(then, found),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn globals_in(expression: &Expr, found: &mut Vec<String>) {
+        match expression {
+            Expression::Global(_, name, _) => found.push(name.primary.to_string()),
+            Expression::Box(_, _, inner, _) => globals_in(inner, found),
+            Expression::Chan { process, .. } => globals(process, found),
+            _ => {}
+        }
+    }
+
+    fn globals_of(definitions: &Definitions, primary: &str) -> Vec<String> {
+        let (definition, _) = definitions
+            .iter()
+            .find(|(name, _)| name.primary == primary)
+            .map(|(_, definition)| definition)
+            .unwrap_or_else(|| panic!("no definition of {primary}"));
+        let mut found = Vec::new();
+        if let DefinitionBody::Par(expression) = &definition.body {
+            globals_in(expression, &mut found);
+        }
+        found
+    }
+
+    fn checked_list() -> CheckedWorkspace {
+        let id = PackageId::Builtin(BuiltinPackage::Core);
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("@core/List.par"),
+                    relative_path_from_src: PathBuf::from("List.par"),
+                    source: LIST.to_owned(),
+                }],
+            }],
+        );
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        let artifacts = pipeline.into_artifacts();
+        assert!(
+            artifacts.type_errors.is_empty(),
+            "{:?}",
+            artifacts.type_errors
+        );
+        artifacts.checked.unwrap()
+    }
+
+    #[test]
+    fn consecutive_steps_become_one_step() {
+        let checked = checked_list();
+        let definitions = &checked.checked_module().definitions;
+
+        for primary in ["Piped", "Called"] {
+            assert!(globals_of(definitions, primary).contains(&"Map".to_owned()));
+            assert_eq!(
+                globals_of(&fuse_list_pipelines(definitions), primary),
+                ["FilterMap", "MapThenFilter"]
+            );
+        }
+    }
+
+    #[test]
+    fn longer_chains_fuse_until_nothing_goes_together() {
+        let checked = checked_list();
+        let definitions = &checked.checked_module().definitions;
+
+        assert_eq!(
+            globals_of(&fuse_list_pipelines(definitions), "Chained"),
+            ["FilterMap", "FilterMapThenMap", "MapThenFilter"]
+        );
+    }
+
+    #[test]
+    fn chains_ending_in_a_sink_build_no_list() {
+        let checked = checked_list();
+        let definitions = &checked.checked_module().definitions;
+
+        for primary in ["Folded", "CalledFolded"] {
+            assert_eq!(
+                globals_of(&fuse_list_pipelines(definitions), primary),
+                ["FilterMapThenFold", "MapThenFilter"]
+            );
+        }
+        assert_eq!(
+            globals_of(&fuse_list_pipelines(definitions), "Summed"),
+            ["FilterMapThenSum", "MapThenFilter"]
+        );
+    }
+}
@@ -1 +1,2 @@
 pub(crate) mod compiler;
+pub(crate) mod fusion;
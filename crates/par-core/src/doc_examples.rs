@@ -0,0 +1,214 @@
+//! Examples in documentation comments, run as tests.
+//!
+//! A `# Example` or `# Examples` heading in the documentation of a module, a
+//! type, or a declaration starts an example section, which lasts until the
+//! next heading. Every `par` code block in such a section is an example. An
+//! example is the body of a `do` block: it's appended to its file as a
+//! definition of type `!`, so it's checked along with the rest of the file,
+//! and it passes if it runs to the end.
+
+use crate::frontend_impl::parse::parse_source_file;
+use crate::frontend_impl::program::DocComment;
+use crate::location::FileName;
+
+/// The prefix of the names of the definitions that examples are run as.
+pub const DOC_EXAMPLE_PREFIX: &str = "DocExample";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocExample {
+    /// The name of the definition the example is run as, in the module of the
+    /// documented item.
+    pub name: String,
+    /// The documented item, or the module, if it's the module's documentation.
+    pub item: String,
+    pub code: String,
+}
+
+/// The code of every example in a piece of documentation, in order.
+pub fn examples_in_markdown(markdown: &str) -> Vec<String> {
+    let mut examples = Vec::new();
+    let mut in_section = false;
+    let mut code_block: Option<(bool, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if let Some((is_example, lines)) = &mut code_block {
+            if trimmed.starts_with("```") {
+                if *is_example {
+                    examples.push(dedent(lines));
+                }
+                code_block = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            let info = info.trim();
+            code_block = Some((in_section && (info.is_empty() || info == "par"), Vec::new()));
+        } else if let Some(heading) = trimmed.strip_prefix('#') {
+            let title = heading.trim_start_matches('#').trim();
+            in_section = title == "Example" || title == "Examples";
+        }
+    }
+    examples
+}
+
+/// `source` with a definition appended for every example in the documentation
+/// it contains, and the examples. Sources that don't parse are returned as
+/// they are, for the parser to report.
+pub fn with_doc_examples(source: &str, file: &FileName) -> (String, Vec<DocExample>) {
+    let Ok(parsed) = parse_source_file(source, file.clone()) else {
+        return (source.to_string(), Vec::new());
+    };
+
+    let mut documented: Vec<(&str, &DocComment)> = Vec::new();
+    if let Some(module_decl) = &parsed.module_decl
+        && let Some(doc) = &module_decl.doc
+    {
+        documented.push((&module_decl.name, doc));
+    }
+    for type_def in &parsed.body.type_defs {
+        if let Some(doc) = &type_def.doc {
+            documented.push((&type_def.name.primary, doc));
+        }
+    }
+    for declaration in &parsed.body.declarations {
+        if let Some(doc) = &declaration.doc {
+            documented.push((&declaration.name.primary, doc));
+        }
+    }
+
+    let mut extended = source.to_string();
+    let mut examples = Vec::new();
+    for (item, doc) in documented {
+        for (index, code) in examples_in_markdown(&doc.markdown).into_iter().enumerate() {
+            let name = format!("{DOC_EXAMPLE_PREFIX}{item}{}", index + 1);
+            extended.push_str(&format!("\n\ndef {name}: ! = do {{\n"));
+            for line in code.lines() {
+                extended.push_str(&format!("  {line}\n"));
+            }
+            extended.push_str("} in !\n");
+            examples.push(DocExample {
+                name,
+                item: item.to_string(),
+                code,
+            });
+        }
+    }
+    (extended, examples)
+}
+
+/// The lines joined, without the indentation they all share.
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Pipeline, SourcePackage, Stage};
+    use crate::workspace::LoadedPackageFile;
+    use arcstr::literal;
+    use par_runtime::pkgid::PackageId;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    const SOURCE: &str = "\
+module Main
+
+// The unit.
+//
+// # Example
+//
+// ```par
+// let unit = Unit
+// unit.case { .unit! => {} }
+// ```
+dec Unit : either { .unit! }
+def Unit = .unit!
+";
+
+    #[test]
+    fn only_par_blocks_in_example_sections_are_examples() {
+        let markdown = "\
+Not an example:
+
+```par
+let before = Unit
+```
+
+## Examples
+
+```
+  let first = Unit
+```
+
+```text
+not par
+```
+
+```par
+let second = Unit
+```
+
+# See also
+
+```par
+let after = Unit
+```";
+        assert_eq!(
+            examples_in_markdown(markdown),
+            ["let first = Unit", "let second = Unit"]
+        );
+    }
+
+    #[test]
+    fn examples_are_appended_as_definitions_that_check() {
+        let file = FileName::from("local/Main.par");
+        let (source, examples) = with_doc_examples(SOURCE, &file);
+        assert_eq!(
+            examples,
+            [DocExample {
+                name: String::from("DocExampleUnit1"),
+                item: String::from("Unit"),
+                code: String::from("let unit = Unit\nunit.case { .unit! => {} }"),
+            }]
+        );
+        assert!(source.ends_with(
+            "\
+def DocExampleUnit1: ! = do {
+  let unit = Unit
+  unit.case { .unit! => {} }
+} in !
+"
+        ));
+
+        let id = PackageId::Special(literal!("__test__"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: file,
+                    relative_path_from_src: PathBuf::from("Main.par"),
+                    source,
+                }],
+            }],
+        );
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(pipeline.artifacts().type_errors.is_empty());
+    }
+}
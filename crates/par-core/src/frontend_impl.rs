@@ -1,9 +1,19 @@
+pub(crate) mod builders;
 pub(crate) mod captures;
+pub(crate) mod codes;
+pub(crate) mod corecursion;
+pub(crate) mod derive;
+pub(crate) mod desugar;
+pub(crate) mod grammar;
 pub(crate) mod language;
 pub(crate) mod lexer;
+pub(crate) mod macros;
+pub(crate) mod migrate;
 pub(crate) mod parse;
 pub(crate) mod process;
 pub(crate) mod program;
+pub(crate) mod skeleton;
+pub(crate) mod totality;
 pub(crate) mod types;
 
 pub use parse::{parse_bytes, set_miette_hook};
@@ -0,0 +1,503 @@
+//! Constructors for building syntax trees in code, instead of parsing them.
+//!
+//! Meant for tools that generate Par: macro systems, or frontends for other
+//! languages. Nothing built here has a span; errors about generated code point
+//! at no source, the same as errors about builtins. A built [`Module`] goes
+//! into a [`ParsedModule`] with [`ParsedModule::generated`], and from there
+//! through the rest of the compiler like a parsed one, for example with
+//! [`Pipeline::from_packages`](crate::pipeline::Pipeline::from_packages).
+//!
+//! The constructors mirror the syntax. `f(x)` is
+//! `Expression::global(f).call(x)`, `.ok!` is
+//! `Expression::construct(Construct::signal("ok", Construct::break_()))`, and
+//! `[x] x` is `Expression::lambda(Pattern::name("x"), Expression::variable("x"))`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arcstr::ArcStr;
+use bytes::Bytes;
+use indexmap::IndexMap;
+use num_bigint::BigInt;
+use par_runtime::primitive::{Number, ParString, Primitive};
+
+use super::language::{
+    Apply, ApplyBranch, ApplyBranches, Command, CommandBranch, CommandBranches, Construct,
+    ConstructBranch, ConstructBranches, Expression, GlobalName, LocalName, Pattern, Process,
+    TypeParameter, Unresolved,
+};
+use super::program::{
+    Declaration, Definition, DefinitionBody, ImportDecl, Module, ModuleDecl, SourceFile, TypeDef,
+};
+use super::types::Type;
+use crate::location::{FileName, Span};
+use crate::workspace::{ModulePath, ParsedModule, ParsedPackageFile};
+
+impl LocalName {
+    pub fn new(name: impl Into<ArcStr>) -> Self {
+        Self {
+            span: Span::None,
+            string: name.into(),
+        }
+    }
+}
+
+impl GlobalName<Unresolved> {
+    /// A name defined in the same module, or imported unqualified.
+    pub fn local(primary: impl Into<String>) -> Self {
+        Self::new(
+            Span::None,
+            Unresolved::Path { qualifier: None },
+            primary.into(),
+        )
+    }
+
+    /// A name from an imported module, like `List.Map`.
+    pub fn qualified(module: impl Into<String>, primary: impl Into<String>) -> Self {
+        Self::new(
+            Span::None,
+            Unresolved::Path {
+                qualifier: Some(module.into()),
+            },
+            primary.into(),
+        )
+    }
+}
+
+impl<S> Type<S> {
+    pub fn global(name: GlobalName<S>, args: Vec<Self>) -> Self {
+        Self::Name(Span::None, name, args)
+    }
+
+    /// Like [`Type::var`], but for names not known in advance.
+    pub fn variable(name: impl Into<ArcStr>) -> Self {
+        Self::Var(Span::None, LocalName::new(name))
+    }
+
+    /// Like [`Type::either`], but for labels not known in advance.
+    pub fn either_of(branches: impl IntoIterator<Item = (impl Into<ArcStr>, Self)>) -> Self {
+        Self::Either(Span::None, labeled(branches))
+    }
+
+    /// Like [`Type::choice`], but for labels not known in advance.
+    pub fn choice_of(branches: impl IntoIterator<Item = (impl Into<ArcStr>, Self)>) -> Self {
+        Self::Choice(Span::None, labeled(branches))
+    }
+}
+
+fn labeled<T, C: FromIterator<(LocalName, T)>>(
+    branches: impl IntoIterator<Item = (impl Into<ArcStr>, T)>,
+) -> C {
+    branches
+        .into_iter()
+        .map(|(label, branch)| (LocalName::new(label), branch))
+        .collect()
+}
+
+impl<S> Pattern<S> {
+    pub fn name(name: impl Into<ArcStr>) -> Self {
+        Self::Name(Span::None, LocalName::new(name), None)
+    }
+
+    pub fn typed(name: impl Into<ArcStr>, typ: Type<S>) -> Self {
+        Self::Name(Span::None, LocalName::new(name), Some(typ))
+    }
+
+    /// `(first) rest`
+    pub fn receive(first: Self, rest: Self) -> Self {
+        Self::Receive(Span::None, Box::new(first), Box::new(rest), vec![])
+    }
+
+    /// `!`
+    pub fn continue_() -> Self {
+        Self::Continue(Span::None)
+    }
+}
+
+impl<S> Expression<S> {
+    pub fn global(name: GlobalName<S>) -> Self {
+        Self::Global(Span::None, name)
+    }
+
+    pub fn variable(name: impl Into<ArcStr>) -> Self {
+        Self::Variable(Span::None, LocalName::new(name))
+    }
+
+    pub fn primitive(primitive: Primitive) -> Self {
+        Self::Primitive(Span::None, primitive)
+    }
+
+    /// An integer literal.
+    pub fn int(value: impl Into<BigInt>) -> Self {
+        Self::primitive(Primitive::Number(Number::Int(value.into())))
+    }
+
+    pub fn float(value: f64) -> Self {
+        Self::primitive(Primitive::Number(Number::Float(value)))
+    }
+
+    pub fn string(value: impl Into<String>) -> Self {
+        Self::primitive(Primitive::String(ParString::from(value.into())))
+    }
+
+    pub fn bytes(value: impl Into<Bytes>) -> Self {
+        Self::primitive(Primitive::Bytes(value.into()))
+    }
+
+    pub fn list(items: Vec<Self>) -> Self {
+        Self::List(Span::None, items)
+    }
+
+    /// `typ: expr`
+    pub fn annotated(typ: Type<S>, expr: Self) -> Self {
+        Self::TypeIn {
+            span: Span::None,
+            typ,
+            expr: Box::new(expr),
+        }
+    }
+
+    /// `let pattern = value in then`
+    pub fn let_(pattern: Pattern<S>, value: Self, then: Self) -> Self {
+        Self::Let {
+            span: Span::None,
+            pattern,
+            expression: Box::new(value),
+            then: Box::new(then),
+        }
+    }
+
+    /// `do { process } in then`
+    pub fn do_(process: Process<S>, then: Self) -> Self {
+        Self::Do {
+            span: Span::None,
+            process: Box::new(process),
+            then: Box::new(then),
+        }
+    }
+
+    /// `chan pattern { process }`
+    pub fn chan(pattern: Pattern<S>, process: Process<S>) -> Self {
+        Self::Chan {
+            span: Span::None,
+            pattern,
+            process: Box::new(process),
+        }
+    }
+
+    pub fn box_(expr: Self) -> Self {
+        Self::Box(Span::None, Box::new(expr))
+    }
+
+    pub fn construct(construct: Construct<S>) -> Self {
+        Self::Construction(Span::None, construct)
+    }
+
+    /// `[pattern] body`
+    pub fn lambda(pattern: Pattern<S>, body: Self) -> Self {
+        Self::construct(Construct::receive(pattern, Construct::then(body)))
+    }
+
+    /// `.label!`
+    pub fn unit_signal(label: impl Into<ArcStr>) -> Self {
+        Self::construct(Construct::signal(label, Construct::break_()))
+    }
+
+    pub fn apply(self, apply: Apply<S>) -> Self {
+        Self::Application(Span::None, Box::new(self), apply)
+    }
+
+    /// `self(arg)`
+    pub fn call(self, arg: Self) -> Self {
+        self.apply(Apply::send(arg, Apply::noop()))
+    }
+
+    /// `self.label`
+    pub fn signal(self, label: impl Into<ArcStr>) -> Self {
+        self.apply(Apply::signal(label, Apply::noop()))
+    }
+}
+
+impl<S> Construct<S> {
+    pub fn then(expr: Expression<S>) -> Self {
+        Self::Then(Box::new(expr))
+    }
+
+    /// `(value) then`
+    pub fn send(value: Expression<S>, then: Self) -> Self {
+        Self::Send(Span::None, Box::new(value), Box::new(then))
+    }
+
+    /// `[pattern] then`
+    pub fn receive(pattern: Pattern<S>, then: Self) -> Self {
+        Self::Receive(Span::None, pattern, Box::new(then), vec![])
+    }
+
+    /// `.label then`
+    pub fn signal(label: impl Into<ArcStr>, then: Self) -> Self {
+        Self::Signal(Span::None, LocalName::new(label), Box::new(then))
+    }
+
+    /// `case { .label => ... }`
+    pub fn case(
+        branches: impl IntoIterator<Item = (impl Into<ArcStr>, ConstructBranch<S>)>,
+    ) -> Self {
+        Self::Case(Span::None, ConstructBranches(labeled(branches)), None)
+    }
+
+    /// `!`
+    pub fn break_() -> Self {
+        Self::Break(Span::None)
+    }
+
+    /// `begin then`
+    pub fn begin(label: Option<&str>, then: Self) -> Self {
+        Self::Begin {
+            span: Span::None,
+            unfounded: false,
+            label: label.map(LocalName::new),
+            then: Box::new(then),
+        }
+    }
+
+    /// `loop`
+    pub fn loop_(label: Option<&str>) -> Self {
+        Self::Loop(Span::None, label.map(LocalName::new))
+    }
+}
+
+impl<S> ConstructBranch<S> {
+    pub fn then(expr: Expression<S>) -> Self {
+        Self::Then(Span::None, expr)
+    }
+
+    pub fn receive(pattern: Pattern<S>, then: Self) -> Self {
+        Self::Receive(Span::None, pattern, Box::new(then), vec![])
+    }
+}
+
+impl<S> Apply<S> {
+    pub fn noop() -> Self {
+        Self::Noop(Span::None)
+    }
+
+    pub fn send(arg: Expression<S>, then: Self) -> Self {
+        Self::Send(Span::None, Box::new(arg), Box::new(then))
+    }
+
+    pub fn signal(label: impl Into<ArcStr>, then: Self) -> Self {
+        Self::Signal(Span::None, LocalName::new(label), Box::new(then))
+    }
+
+    /// `.case { .label => ... }`
+    pub fn case(branches: impl IntoIterator<Item = (impl Into<ArcStr>, ApplyBranch<S>)>) -> Self {
+        Self::Case(Span::None, ApplyBranches(labeled(branches)), None)
+    }
+
+    pub fn begin(label: Option<&str>, then: Self) -> Self {
+        Self::Begin {
+            span: Span::None,
+            unfounded: false,
+            label: label.map(LocalName::new),
+            then: Box::new(then),
+        }
+    }
+
+    pub fn loop_(label: Option<&str>) -> Self {
+        Self::Loop(Span::None, label.map(LocalName::new))
+    }
+}
+
+impl<S> ApplyBranch<S> {
+    /// `name => expr`, binding what's left of the subject to `name`.
+    pub fn then(name: impl Into<ArcStr>, expr: Expression<S>) -> Self {
+        Self::Then(Span::None, LocalName::new(name), expr)
+    }
+
+    pub fn receive(pattern: Pattern<S>, then: Self) -> Self {
+        Self::Receive(Span::None, pattern, Box::new(then), vec![])
+    }
+
+    /// `! => expr`
+    pub fn continue_(expr: Expression<S>) -> Self {
+        Self::Continue(Span::None, expr)
+    }
+}
+
+impl<S> Process<S> {
+    /// `let pattern = value` followed by `then`.
+    pub fn let_(pattern: Pattern<S>, value: Expression<S>, then: Self) -> Self {
+        Self::Let {
+            span: Span::None,
+            pattern,
+            value: Box::new(value),
+            then: Box::new(then),
+        }
+    }
+
+    /// A command on the local variable `subject`.
+    pub fn command(subject: impl Into<ArcStr>, command: Command<S>) -> Self {
+        Self::Command(Span::None, LocalName::new(subject), command)
+    }
+
+    /// A command on a global definition, like `Console.Open`.
+    pub fn global_command(subject: GlobalName<S>, command: Command<S>) -> Self {
+        Self::GlobalCommand(Span::None, subject, command)
+    }
+}
+
+impl<S> Command<S> {
+    pub fn then(process: Process<S>) -> Self {
+        Self::Then(Box::new(process))
+    }
+
+    /// `subject <> value`
+    pub fn link(value: Expression<S>) -> Self {
+        Self::Link(Span::None, Box::new(value))
+    }
+
+    /// `subject(value)` followed by `then`.
+    pub fn send(value: Expression<S>, then: Self) -> Self {
+        Self::Send(Span::None, value, Box::new(then))
+    }
+
+    /// `subject[pattern]` followed by `then`.
+    pub fn receive(pattern: Pattern<S>, then: Self) -> Self {
+        Self::Receive(Span::None, pattern, Box::new(then), vec![])
+    }
+
+    /// `subject.label` followed by `then`.
+    pub fn signal(label: impl Into<ArcStr>, then: Self) -> Self {
+        Self::Signal(Span::None, LocalName::new(label), Box::new(then))
+    }
+
+    /// `subject.case { .label => ... }`
+    pub fn case(branches: impl IntoIterator<Item = (impl Into<ArcStr>, CommandBranch<S>)>) -> Self {
+        Self::Case(Span::None, CommandBranches(labeled(branches)), None, None)
+    }
+
+    /// `subject!`
+    pub fn break_() -> Self {
+        Self::Break(Span::None)
+    }
+
+    /// `subject?` followed by `then`.
+    pub fn continue_(then: Process<S>) -> Self {
+        Self::Continue(Span::None, Box::new(then))
+    }
+
+    pub fn begin(label: Option<&str>, then: Self) -> Self {
+        Self::Begin {
+            span: Span::None,
+            unfounded: false,
+            label: label.map(LocalName::new),
+            subjects: Vec::new(),
+            then: Box::new(then),
+        }
+    }
+
+    pub fn loop_(label: Option<&str>) -> Self {
+        Self::Loop(Span::None, label.map(LocalName::new))
+    }
+}
+
+impl<S> CommandBranch<S> {
+    pub fn then(process: Process<S>) -> Self {
+        Self::Then(Span::None, process)
+    }
+
+    pub fn receive(pattern: Pattern<S>, then: Self) -> Self {
+        Self::Receive(Span::None, pattern, Box::new(then), vec![])
+    }
+
+    /// `! => { process }`
+    pub fn continue_(process: Process<S>) -> Self {
+        Self::Continue(Span::None, process)
+    }
+}
+
+impl<S> Definition<Expression<S>, S> {
+    pub fn new(name: GlobalName<S>, body: Expression<S>) -> Self {
+        Self {
+            span: Span::None,
+            name,
+            body: DefinitionBody::Par(body),
+        }
+    }
+}
+
+impl<S> Declaration<S> {
+    /// A declaration private to its module; see [`Declaration::exported`].
+    pub fn new(name: GlobalName<S>, typ: Type<S>) -> Self {
+        Self {
+            span: Span::None,
+            exported: false,
+            doc: None,
+            name,
+            parameter_names: Vec::new(),
+            typ,
+        }
+    }
+
+    pub fn exported(mut self) -> Self {
+        self.exported = true;
+        self
+    }
+}
+
+impl<S> TypeDef<S> {
+    /// A type private to its module; see [`TypeDef::exported`].
+    pub fn new(name: GlobalName<S>, params: Vec<&str>, typ: Type<S>) -> Self {
+        Self {
+            span: Span::None,
+            exported: false,
+            doc: None,
+            branch_docs: IndexMap::new(),
+            name,
+            params: params
+                .into_iter()
+                .map(|param| TypeParameter::any(LocalName::new(param)))
+                .collect(),
+            typ,
+            function: None,
+        }
+    }
+
+    pub fn exported(mut self) -> Self {
+        self.exported = true;
+        self
+    }
+}
+
+impl ParsedModule {
+    /// A module made of a single generated file, with no source text. Its
+    /// file is named after the module, under `generated/`.
+    pub fn generated(
+        path: ModulePath,
+        imports: Vec<ImportDecl>,
+        body: Module<Expression<Unresolved>, Unresolved>,
+    ) -> Self {
+        let relative_path_from_src = PathBuf::from(format!("{}.par", path.to_slash_path()));
+        let file = ParsedPackageFile {
+            name: FileName::from(format!("generated/{}", relative_path_from_src.display())),
+            relative_path_from_src,
+            source: Arc::from(""),
+            module_part_suffix: None,
+            source_file: SourceFile {
+                module_decl: Some(ModuleDecl {
+                    span: Span::None,
+                    exported: true,
+                    doc: None,
+                    name: path.module.clone(),
+                }),
+                imports,
+                body,
+            },
+        };
+        Self {
+            path,
+            doc: None,
+            files: vec![file],
+        }
+    }
+}
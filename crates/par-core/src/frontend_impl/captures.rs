@@ -3,6 +3,7 @@ use super::{
     process::{Command, Expression, Process},
 };
 use crate::location::Span;
+use crate::stack;
 use indexmap::IndexMap;
 use std::{collections::VecDeque, sync::Arc};
 
@@ -141,6 +142,14 @@ impl CaptureAnalysis {
         &self,
         process: &Process<(), Unresolved>,
         env: &LoopEnv,
+    ) -> (Arc<Process<(), Unresolved>>, Captures) {
+        stack::grow(|| self.fix_process_inner(process, env))
+    }
+
+    fn fix_process_inner(
+        &self,
+        process: &Process<(), Unresolved>,
+        env: &LoopEnv,
     ) -> (Arc<Process<(), Unresolved>>, Captures) {
         match process {
             Process::Let {
@@ -361,6 +370,7 @@ impl CaptureAnalysis {
             Command::Begin {
                 unfounded,
                 label,
+                subjects,
                 captures: _,
                 body,
             } => {
@@ -372,6 +382,7 @@ impl CaptureAnalysis {
                     Command::Begin {
                         unfounded: *unfounded,
                         label: label.clone(),
+                        subjects: subjects.clone(),
                         captures: loop_caps,
                         body: process,
                     },
@@ -412,6 +423,15 @@ impl CaptureAnalysis {
         expression: &Expression<(), Unresolved>,
         env: &LoopEnv,
         later_captures: &Captures,
+    ) -> (Arc<Expression<(), Unresolved>>, Captures) {
+        stack::grow(|| self.fix_expression_inner(expression, env, later_captures))
+    }
+
+    fn fix_expression_inner(
+        &self,
+        expression: &Expression<(), Unresolved>,
+        env: &LoopEnv,
+        later_captures: &Captures,
     ) -> (Arc<Expression<(), Unresolved>>, Captures) {
         match expression {
             Expression::Global(span, name, typ) => (
@@ -542,6 +562,10 @@ impl BlockEnvAnalyzer {
     }
 
     fn visit_expression(&mut self, expression: &Expression<(), Unresolved>, env: &LoopEnv) {
+        stack::grow(|| self.visit_expression_inner(expression, env))
+    }
+
+    fn visit_expression_inner(&mut self, expression: &Expression<(), Unresolved>, env: &LoopEnv) {
         match expression {
             Expression::Box(_, _, expr, _) => self.visit_expression(expr, env),
             Expression::Chan { process, .. } => self.visit_process(process, env),
@@ -553,6 +577,10 @@ impl BlockEnvAnalyzer {
     }
 
     fn visit_process(&mut self, process: &Process<(), Unresolved>, env: &LoopEnv) {
+        stack::grow(|| self.visit_process_inner(process, env))
+    }
+
+    fn visit_process_inner(&mut self, process: &Process<(), Unresolved>, env: &LoopEnv) {
         match process {
             Process::Let { value, then, .. } => {
                 self.visit_expression(value, env);
@@ -0,0 +1,818 @@
+//! Stable codes identifying each kind of diagnostic, with an extended
+//! explanation of each, for `par explain`.
+//!
+//! A code is never reused for a different diagnostic: when one goes away, its
+//! code goes with it, and new diagnostics get new codes at the end.
+
+/// One kind of diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticCode {
+    pub code: &'static str,
+    /// What the diagnostic reports, in a few words.
+    pub title: &'static str,
+    /// Why it's reported and how to fix it, followed by an example of code
+    /// that has it.
+    pub explanation: &'static str,
+}
+
+pub const SYNTAX_ERROR: &str = "PAR0001";
+
+/// Every diagnostic code, in order.
+pub const DIAGNOSTIC_CODES: &[DiagnosticCode] = &[
+    DiagnosticCode {
+        code: SYNTAX_ERROR,
+        title: "syntax error",
+        explanation: "\
+The source doesn't follow Par's grammar. The error points at the first token
+that couldn't be parsed, and lists what was being parsed there.
+
+Example:
+
+    def Main = [x]
+",
+    },
+    DiagnosticCode {
+        code: "PAR0002",
+        title: "type already defined",
+        explanation: "\
+Two types in the same module have the same name. Rename or remove one of them.
+
+Example:
+
+    type Color = either { .red!, .green! }
+    type Color = either { .blue! }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0003",
+        title: "name already declared",
+        explanation: "\
+A name has two `dec`s in the same module. Keep one of them.
+
+Example:
+
+    dec Zero : Nat
+    dec Zero : Int
+",
+    },
+    DiagnosticCode {
+        code: "PAR0004",
+        title: "name already defined",
+        explanation: "\
+A name has two `def`s in the same module. Keep one of them.
+
+Example:
+
+    def Zero = 0
+    def Zero = 1
+",
+    },
+    DiagnosticCode {
+        code: "PAR0005",
+        title: "declared but not defined",
+        explanation: "\
+A `dec` gives a name a type, but there is no `def` giving it a value. Add the
+definition, or remove the declaration.
+
+Example:
+
+    dec Zero : Nat
+",
+    },
+    DiagnosticCode {
+        code: "PAR0006",
+        title: "inconsistent module export",
+        explanation: "\
+The files of a module disagree on whether the module is exported. Mark the
+module `export` in all of its files, or in none.
+
+Example:
+
+    // Main.par
+    export module Main
+
+    // Main/Extra.par
+    module Main
+",
+    },
+    DiagnosticCode {
+        code: "PAR0007",
+        title: "imported module not exported",
+        explanation: "\
+A module of another package is imported, but that package doesn't export it.
+Only exported modules can be used from other packages.
+
+Example:
+
+    import @dependency/Internal
+",
+    },
+    DiagnosticCode {
+        code: "PAR0008",
+        title: "name not visible",
+        explanation: "\
+A type or a value is used outside of where it's visible: an item that isn't
+exported can only be used in its own module. Export it, or use something that
+is exported instead.
+
+Example:
+
+    // Other.par
+    module Other
+    def Secret = 42
+
+    // Main.par
+    import Other
+    def Main = Other.Secret
+",
+    },
+    DiagnosticCode {
+        code: "PAR0009",
+        title: "visible item exposes a hidden type",
+        explanation: "\
+An item is visible more widely than a type it mentions, so code that can see
+the item couldn't name its type. Export the type too, or make the item less
+visible.
+
+Example:
+
+    type Hidden = Nat
+
+    export {
+      dec Exposed : Hidden
+    }
+    def Exposed = 0
+",
+    },
+    DiagnosticCode {
+        code: "PAR0010",
+        title: "`self` outside of a recursive or iterative type",
+        explanation: "\
+`self` refers to the nearest `recursive` or `iterative` type around it, with
+the same label if it has one. There is no such type here.
+
+Example:
+
+    type List = either { .end!, .item(Nat) self }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0011",
+        title: "`self` in a negative position",
+        explanation: "\
+`self` may only occur where values flow the same way as in the whole type: not
+in the argument of a function, or under a `dual`. The error shows each place
+where the direction flips on the way to `self`.
+
+Example:
+
+    type Bad = recursive either { .wrap [self] ! }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0012",
+        title: "unguarded `self` in a recursive type",
+        explanation: "\
+A `recursive` type must do something before getting back to `self`, such as
+going through an `either`. Otherwise, unrolling it never gets anywhere.
+
+Example:
+
+    type Loop = recursive self
+",
+    },
+    DiagnosticCode {
+        code: "PAR0013",
+        title: "unguarded `self` in an iterative type",
+        explanation: "\
+An `iterative` type must do something before getting back to `self`, such as
+going through a `choice`. Otherwise, unrolling it never gets anywhere.
+
+Example:
+
+    type Spin = iterative self
+",
+    },
+    DiagnosticCode {
+        code: "PAR0014",
+        title: "type not defined",
+        explanation: "\
+A type name doesn't refer to any type that's defined or imported. Check the
+spelling, and the imports.
+
+Example:
+
+    dec Count : Natural
+",
+    },
+    DiagnosticCode {
+        code: "PAR0015",
+        title: "type variable not defined",
+        explanation: "\
+A lower-case name in a type refers to a type parameter, but there is none by
+that name in scope. Add it to the parameters, or check the spelling.
+
+Example:
+
+    type Pair<a> = (a) b
+",
+    },
+    DiagnosticCode {
+        code: "PAR0016",
+        title: "cyclic type definitions",
+        explanation: "\
+Type definitions refer to one another in a cycle, without a `recursive` or
+`iterative` in between, so expanding them never ends. Use `recursive` or
+`iterative` with `self` for types that contain themselves.
+
+Example:
+
+    type A = B
+    type B = A
+",
+    },
+    DiagnosticCode {
+        code: "PAR0017",
+        title: "type expansion too deep",
+        explanation: "\
+Expanding a type went past the expansion limit, usually because a generic
+type keeps instantiating itself with ever bigger arguments, or a `recursive`
+or `iterative` type grows each time it's unrolled. The error lists the chain
+of named types expanded, if there is one.
+
+Example:
+
+    type Grow<a> = either { .done a, .more Grow<(a) a> }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0019",
+        title: "wrong number of type arguments",
+        explanation: "\
+A generic type is given a different number of type arguments than it has
+parameters.
+
+Example:
+
+    type Pair<a, b> = (a) b
+    dec Numbers : Pair<Nat>
+",
+    },
+    DiagnosticCode {
+        code: "PAR0020",
+        title: "name not defined",
+        explanation: "\
+A global name doesn't refer to any definition that exists or is imported.
+Check the spelling, and the imports.
+
+Example:
+
+    def Main = Undefined
+",
+    },
+    DiagnosticCode {
+        code: "PAR0021",
+        title: "variable does not exist",
+        explanation: "\
+A local variable is used, but there is none by that name here. It may never
+have been bound, or it may have been used up already: a linear variable can
+only be used once.
+
+Example:
+
+    def Main = [x] y
+",
+    },
+    DiagnosticCode {
+        code: "PAR0022",
+        title: "shadowed obligation",
+        explanation: "\
+A variable is bound again while it still holds a value that must be handled.
+Doing so would silently drop that value. Handle the first value before
+re-assigning the variable, or use a different name.
+
+Example:
+
+    type Door = iterative choice { .close => !, .knock => self }
+
+    def Swap : [Door, Door] ! = [door, other] do {
+      let door = other
+    } in !
+",
+    },
+    DiagnosticCode {
+        code: "PAR0023",
+        title: "type must be known here",
+        explanation: "\
+The type of a variable is needed at this point, but it can't be inferred yet.
+Add a type annotation where the variable is bound.
+
+Example:
+
+    def Main = [x] x.case { .a! => ! }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0024",
+        title: "parameter type must be known",
+        explanation: "\
+The type of a function's parameter can't be inferred. Annotate the parameter,
+or declare the function's type with a `dec`.
+
+Example:
+
+    def Identity = [x] x
+",
+    },
+    DiagnosticCode {
+        code: "PAR0025",
+        title: "incompatible types",
+        explanation: "\
+A value of one type is used where a different, incompatible type is required.
+The error shows both types.
+
+Example:
+
+    dec Answer : Nat
+    def Answer = \"forty-two\"
+",
+    },
+    DiagnosticCode {
+        code: "PAR0026",
+        title: "type argument doesn't satisfy constraint",
+        explanation: "\
+A type parameter has a constraint, such as `box` or `data`, and the type given
+for it doesn't satisfy it.
+
+Example:
+
+    dec Copy : [type a: box] [a] (a) a
+    def Twice = Copy(type [Nat] Nat)
+",
+    },
+    DiagnosticCode {
+        code: "PAR0027",
+        title: "type parameter constraint mismatch",
+        explanation: "\
+A type parameter is annotated with a different constraint than the one the
+type it's checked against requires. Use the same constraint.
+
+Example:
+
+    dec Copy : [type a: box] [a] (a) a
+    def Copy = [type a] [x] (x, x)!
+",
+    },
+    DiagnosticCode {
+        code: "PAR0028",
+        title: "unfulfilled obligations",
+        explanation: "\
+A process ends while some variables still hold values that must be handled:
+linear values can't be dropped. The error shows where each one was bound and
+what's left to do with it.
+
+Example:
+
+    type Door = iterative choice { .close => !, .knock => self }
+
+    def Leave : [Door] ! = [door] !
+",
+    },
+    DiagnosticCode {
+        code: "PAR0029",
+        title: "invalid operation",
+        explanation: "\
+An operation is used on a value whose type doesn't support it, such as
+selecting a branch of something that isn't a choice.
+
+Example:
+
+    def Main = [x: Nat] x.close
+",
+    },
+    DiagnosticCode {
+        code: "PAR0030",
+        title: "invalid branch",
+        explanation: "\
+A branch is selected or handled that the type doesn't have. Check the
+spelling, and the type.
+
+Example:
+
+    type Bool = either { .true!, .false! }
+    dec Maybe : Bool
+    def Maybe = .perhaps!
+",
+    },
+    DiagnosticCode {
+        code: "PAR0031",
+        title: "missing branch",
+        explanation: "\
+A `.case` doesn't handle every branch of the `either` it takes apart, or a
+`choice` being constructed doesn't offer every branch of its type. Add the
+missing branches, or an `else`.
+
+Example:
+
+    type Bool = either { .true!, .false! }
+    dec Not : [Bool] Bool
+    def Not = [b] b.case { .true! => .false! }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0032",
+        title: "redundant branch",
+        explanation: "\
+A branch is handled twice, or handled when the type doesn't have it. Remove
+it.
+
+Example:
+
+    type Bool = either { .true!, .false! }
+    dec Not : [Bool] Bool
+    def Not = [b] b.case {
+      .true! => .false!,
+      .false! => .true!,
+      .maybe! => .true!,
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0033",
+        title: "variable missing after branches",
+        explanation: "\
+Where branches come back together, a variable is available after some of them
+but not after others. Use it up, or keep it, in all of them.
+
+Example:
+
+    def Main = [x: Nat, b: Bool] do {
+      b.case {
+        .true! => { drop x }
+        .false! => {}
+      }
+    } in !
+",
+    },
+    DiagnosticCode {
+        code: "PAR0034",
+        title: "variable types can't be merged after branches",
+        explanation: "\
+Where branches come back together, a variable has types in different branches
+that have nothing in common. Give it the same type in every branch.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0035",
+        title: "variable escapes its type's scope",
+        explanation: "\
+A variable's type mentions a type variable that's only in scope in part of the
+program, and the variable is used outside of that part.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0036",
+        title: "types can't be unified",
+        explanation: "\
+Two types are expected to agree, such as the results of the branches of a
+`.case`, but they have nothing in common. The error shows both types.
+
+Example:
+
+    def Main = [b: Bool] b.case {
+      .true! => 0,
+      .false! => \"zero\",
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0037",
+        title: "branch payloads can't be unified",
+        explanation: "\
+Two types that should agree both have a branch with the same label, but with
+payloads that have nothing in common. The error shows both payload types.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0038",
+        title: "no such loop point",
+        explanation: "\
+`.loop` goes back to the nearest `.begin`, with the same label if it has one.
+There is no such `.begin` here.
+
+Example:
+
+    def Main = [n: Nat] n.loop
+",
+    },
+    DiagnosticCode {
+        code: "PAR0039",
+        title: "loop doesn't descend the subject of `.begin`",
+        explanation: "\
+`.loop` must be applied to a part of the value `.begin` was applied to, so
+that every loop makes progress and ends.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0040",
+        title: "loop may diverge",
+        explanation: "\
+`.loop` is used in a way that may go on forever: the loop variable is operated
+on before looping. If that's intended, mark the loop with `.unfounded`.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0041",
+        title: "loop variable not preserved",
+        explanation: "\
+A variable that was available at `.begin` is no longer there at `.loop`. Each
+iteration must have the same variables as the first.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0042",
+        title: "loop variable changed type",
+        explanation: "\
+A variable has a different type at `.loop` than it had at `.begin`. Each
+iteration must have the same variables, of the same types, as the first.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0043",
+        title: "poll without clients",
+        explanation: "\
+A `poll` must start with at least one client.
+
+Example:
+
+    def Main = poll() {
+      client => submit(),
+      else => 0,
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0044",
+        title: "poll client not recursive",
+        explanation: "\
+The clients of a `poll` must be of a `recursive` type, so that submitting
+their descendants makes progress.
+
+Example:
+
+    def Main = [x: Nat] poll(x) {
+      n => submit(),
+      else => 0,
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0045",
+        title: "submit outside of poll",
+        explanation: "\
+`submit` is only allowed in the active branch of a `poll`.
+
+Example:
+
+    def Main = submit()
+",
+    },
+    DiagnosticCode {
+        code: "PAR0046",
+        title: "repoll outside of poll",
+        explanation: "\
+`repoll` is only allowed in the active branch of a `poll`.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0047",
+        title: "submitted client not assignable to the pool",
+        explanation: "\
+A client is submitted whose type doesn't fit the type of the pool's clients.
+The error shows both types.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0048",
+        title: "submitted client doesn't descend",
+        explanation: "\
+Only descendants of the active client may be submitted, so that polling makes
+progress: a client can't be put back into the pool unchanged.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0049",
+        title: "submit can't target this poll point",
+        explanation: "\
+`submit` targets a `poll` whose clients have a type the submitted client
+doesn't fit. The error shows both types.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0050",
+        title: "poll variable not preserved",
+        explanation: "\
+A variable that was available at `poll` is no longer there at `submit`. The
+variables used by the `poll` move to its next iteration, so they must all be
+there.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0051",
+        title: "poll variable changed type",
+        explanation: "\
+A variable has a different type at `submit` than it had at `poll`. The
+variables used by the `poll` move to its next iteration, so their types must
+stay the same.
+",
+    },
+    DiagnosticCode {
+        code: "PAR0052",
+        title: "poll branch must submit",
+        explanation: "\
+The active branch of a `poll` ends without calling `submit`. Call it exactly
+once in every path through the branch, even with no clients: `submit()`.
+
+Example:
+
+    def PollSum = [nums: List<Int>] poll(nums) {
+      list => list.case {
+        .end! => 0,
+        .item(x) xs => x + {submit(xs)},
+      }
+      else => 0,
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0053",
+        title: "linear variable used in a box",
+        explanation: "\
+A `box` value can be used any number of times, so it can't capture a variable
+that must be used exactly once.
+
+Example:
+
+    type Door = iterative choice { .close => !, .knock => self }
+
+    dec Forever : [Door] box [!] !
+    def Forever = [door] box [u] door.close
+",
+    },
+    DiagnosticCode {
+        code: "PAR0054",
+        title: "non-exhaustive `if`",
+        explanation: "\
+An `if` doesn't cover every case. Add an `else` branch.
+
+Example:
+
+    def Main = [a: Bool] if { a => 1 }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0055",
+        title: "invalid union operand",
+        explanation: "\
+`|` combines `either` types, and `&` combines `choice` types. One side of the
+union isn't the kind of type it combines.
+
+Example:
+
+    type Bad = either { .a! } | Nat
+",
+    },
+    DiagnosticCode {
+        code: "PAR0056",
+        title: "overlapping union labels",
+        explanation: "\
+Both sides of a union have a branch with the same label, so it's ambiguous
+which one is meant. Rename one of them.
+
+Example:
+
+    type Bad = either { .a! } | either { .a Nat }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0057",
+        title: "unused type parameter",
+        explanation: "\
+A warning. A parameter of a type definition never occurs in its body. Usually
+it's a typo: a name in the body that was meant to be the parameter, but is
+spelled differently, refers to some other type instead. Remove the parameter,
+or use it.
+
+Example:
+
+    type Tagged<tag, a> = (a) !
+",
+    },
+    DiagnosticCode {
+        code: "PAR0058",
+        title: "unguarded corecursive definition",
+        explanation: "\
+Definitions of iterative values refer to each other in a cycle, but not all of
+them wait for a request before referring to the next one, so using them would
+loop forever. A definition may only refer back as what a branch of a `case`
+offered by the value it defines continues with, not use it within the branch.
+
+Example:
+
+    type Stream = iterative choice { .close => !, .next => (Nat) self }
+
+    dec Ones : Stream
+    def Ones = Ones
+",
+    },
+    DiagnosticCode {
+        code: "PAR0059",
+        title: "union of two type variables",
+        explanation: "\
+Both sides of a union are type variables. One side of a union may be a type
+variable, standing for the rest of the branches, which makes it open. But with
+two, it isn't known which branches go where. Put the known branches on one of
+the sides.
+
+Example:
+
+    dec Bad : [type r, type s] [either { .a! } | r | s] !
+",
+    },
+    DiagnosticCode {
+        code: "PAR0060",
+        title: "`.case` on an open row without `else`",
+        explanation: "\
+A `.case` on a value of an open `either`, one with a type variable standing
+for the rest of its branches, must have an `else`. Those branches aren't known,
+so they can't be listed. The `else` receives them as a value of the type
+variable, or of the union of it with the branches not handled.
+
+Example:
+
+    dec Bad : [type r] [either { .a! } | r] !
+    def Bad = [type r] [x] x.case {
+      .a! => !,
+    }
+",
+    },
+    DiagnosticCode {
+        code: "PAR0061",
+        title: "loop doesn't descend any subject of `.begin`",
+        explanation: "\
+When `.begin` is followed by more subjects after `&`, each `.loop` must make
+one of them smaller, as long as every subject before it is kept as it was. The
+error names the first subject that's neither kept nor smaller.
+",
+    },
+];
+
+/// The diagnostic with a code, ignoring case. The `PAR` prefix and the leading
+/// zeros may be left out: `PAR0007`, `par7`, and `7` are all the same code.
+pub fn explain_code(code: &str) -> Option<&'static DiagnosticCode> {
+    let code = code.trim();
+    let digits = match code.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("par") => &code[3..],
+        _ => code,
+    };
+    let number = digits.parse::<u32>().ok()?;
+    DIAGNOSTIC_CODES
+        .iter()
+        .find(|entry| entry.code[3..].parse::<u32>() == Ok(number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_numbered_in_order() {
+        for entry in DIAGNOSTIC_CODES {
+            assert_eq!(entry.code.len(), "PAR0000".len());
+            assert!(entry.code.starts_with("PAR"));
+        }
+        for pair in DIAGNOSTIC_CODES.windows(2) {
+            assert!(
+                pair[0].code < pair[1].code,
+                "{} {}",
+                pair[0].code,
+                pair[1].code
+            );
+        }
+    }
+
+    #[test]
+    fn codes_can_be_looked_up_loosely() {
+        assert_eq!(
+            explain_code("PAR0007").map(|entry| entry.code),
+            Some("PAR0007")
+        );
+        assert_eq!(
+            explain_code("par7").map(|entry| entry.code),
+            Some("PAR0007")
+        );
+        assert_eq!(explain_code("7").map(|entry| entry.code), Some("PAR0007"));
+        assert_eq!(explain_code("PAR9999"), None);
+        assert_eq!(explain_code("nonsense"), None);
+    }
+}
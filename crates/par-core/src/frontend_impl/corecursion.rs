@@ -0,0 +1,163 @@
+//! Which globals a definition refers to unproductively, for corecursive
+//! definitions.
+//!
+//! A definition may refer back to itself, directly or through others, if it
+//! only does so one step at a time, at the request of whoever uses it. Here,
+//! that means inside a branch of a `case` on the value being defined, possibly
+//! boxed, as what the branch continues with: nothing in there runs until a
+//! signal picks the branch, and the reference itself is only used once the
+//! next request comes. A reference the branch uses itself, like one it sends
+//! a request to, makes the step unfold the definition again, so it doesn't
+//! count. The checker also requires such a definition to produce an
+//! `iterative` value, so that unfolding it again and again is fine.
+
+use std::hash::Hash;
+
+use indexmap::IndexSet;
+
+use super::language::{GlobalName, LocalName};
+use super::process::{Command, Expression, Process};
+use crate::location::Span;
+
+/// The references `expression`, the body of a definition, makes to globals
+/// other than as the continuation of a branch of a `case` on the value it
+/// defines, along with where they are.
+pub(crate) fn unguarded_globals<Typ, S: Clone + Eq + Hash>(
+    expression: &Expression<Typ, S>,
+) -> IndexSet<(GlobalName<S>, Span)> {
+    let mut unguarded = IndexSet::new();
+    in_value(expression, &mut unguarded);
+    unguarded
+}
+
+/// An expression for the value being defined.
+fn in_value<Typ, S: Clone + Eq + Hash>(
+    expression: &Expression<Typ, S>,
+    unguarded: &mut IndexSet<(GlobalName<S>, Span)>,
+) {
+    match expression {
+        Expression::Chan {
+            chan_name, process, ..
+        } => in_process(process, Some(chan_name), false, unguarded),
+        Expression::Box(_, _, expression, _) => in_value(expression, unguarded),
+        _ => in_expression(expression, unguarded),
+    }
+}
+
+/// An expression for what's left of the value being defined, once a request
+/// for it is answered. Its globals wait for the next request.
+fn in_continuation<Typ, S: Clone + Eq + Hash>(
+    expression: &Expression<Typ, S>,
+    unguarded: &mut IndexSet<(GlobalName<S>, Span)>,
+) {
+    match expression {
+        Expression::Global(..) => {}
+        Expression::Chan {
+            chan_name, process, ..
+        } => in_process(process, Some(chan_name), true, unguarded),
+        Expression::Box(_, _, expression, _) => in_continuation(expression, unguarded),
+        _ => in_expression(expression, unguarded),
+    }
+}
+
+/// An expression for some other value, where every global counts.
+fn in_expression<Typ, S: Clone + Eq + Hash>(
+    expression: &Expression<Typ, S>,
+    unguarded: &mut IndexSet<(GlobalName<S>, Span)>,
+) {
+    match expression {
+        Expression::Global(span, name, _) => {
+            unguarded.insert((name.clone(), span.clone()));
+        }
+        Expression::Box(_, _, expression, _) => in_expression(expression, unguarded),
+        Expression::Chan { process, .. } => in_process(process, None, false, unguarded),
+        Expression::Variable(..) | Expression::Primitive(..) | Expression::External(..) => {}
+    }
+}
+
+/// A process, along with the name of the channel the value being defined is
+/// provided on, while it's in scope, and whether it runs at a request for it.
+fn in_process<Typ, S: Clone + Eq + Hash>(
+    process: &Process<Typ, S>,
+    result: Option<&LocalName>,
+    requested: bool,
+    unguarded: &mut IndexSet<(GlobalName<S>, Span)>,
+) {
+    match process {
+        Process::Let {
+            name, value, then, ..
+        } => {
+            in_expression(value, unguarded);
+            in_process(
+                then,
+                result.filter(|result| *result != name),
+                requested,
+                unguarded,
+            );
+        }
+        Process::Do { name, command, .. } => {
+            let on_result = result == Some(name);
+            match command {
+                // Each branch only runs once it's requested.
+                Command::Case(_, processes, else_process) if on_result => {
+                    for process in processes.iter().chain(else_process) {
+                        in_process(process, result, true, unguarded);
+                    }
+                }
+                Command::Link(expression) if on_result && requested => {
+                    in_continuation(expression, unguarded)
+                }
+                Command::Link(expression) if on_result => in_value(expression, unguarded),
+                Command::Link(expression) => in_expression(expression, unguarded),
+                Command::Send(argument, process) => {
+                    in_expression(argument, unguarded);
+                    in_process(process, result, requested, unguarded);
+                }
+                Command::Receive(bound, _, _, process, _) => {
+                    in_process(
+                        process,
+                        result.filter(|result| *result != bound),
+                        requested,
+                        unguarded,
+                    );
+                }
+                Command::Case(_, processes, else_process) => {
+                    for process in processes.iter().chain(else_process) {
+                        in_process(process, result, requested, unguarded);
+                    }
+                }
+                Command::Noop(process)
+                | Command::Signal(_, process)
+                | Command::Continue(process)
+                | Command::Begin { body: process, .. }
+                | Command::SendType(_, process)
+                | Command::ReceiveType(_, process) => {
+                    in_process(process, result, requested, unguarded)
+                }
+                Command::Break | Command::Loop(..) => {}
+            }
+        }
+        Process::Poll {
+            clients,
+            then,
+            else_,
+            ..
+        } => {
+            for client in clients {
+                in_expression(client, unguarded);
+            }
+            in_process(then, result, requested, unguarded);
+            in_process(else_, result, requested, unguarded);
+        }
+        Process::Submit { values, .. } => {
+            for value in values {
+                in_expression(value, unguarded);
+            }
+        }
+        Process::Block(_, _, body, then) => {
+            in_process(body, result, requested, unguarded);
+            in_process(then, result, requested, unguarded);
+        }
+        Process::Goto(..) | Process::Unreachable(_) => {}
+    }
+}
@@ -0,0 +1,531 @@
+//! `derive Codec`: `SuitToBytes : [Suit] Bytes` and `SuitFromBytes : Codec.Decoder<Suit>`,
+//! following the structure of the type as written:
+//!
+//! - `!` is no bytes, and `Nat`, `Int`, `Float`, `String`, `Bytes`, `Bool`, `List<a>`,
+//!   and `Option<a>` are laid out by `@core/Codec`.
+//! - A pair `(a, b) c` is its elements one after another.
+//! - A variant is the index of its label among the type's labels in alphabetical
+//!   order, as a `Nat`, followed by its value.
+//! - Other named types are encoded by what they derive themselves.
+//!
+//! A recursive type's decoder is built by `Codec.Recursive`, from a decoder of one
+//! level of it that decodes `self` with the decoder it's given. There's no
+//! recursive value to loop on, only bytes, and referring back to itself while
+//! decoding isn't allowed.
+
+use std::{collections::BTreeMap, ops::Deref};
+
+use num_bigint::BigInt;
+use par_runtime::primitive::{Number, Primitive};
+
+use super::{DeriveError, Derived, Named, Synth, pair_items, subject};
+use crate::{
+    frontend_impl::{
+        language::{
+            Apply, ApplyBranch, ApplyBranches, BuiltinOperatorModule, ComparisonOperator,
+            ComparisonStep, Condition, Construct, ConstructBranch, ConstructBranches, Expression,
+            GlobalName, LocalName, Pattern, Unresolved,
+        },
+        program::TypeDef,
+        types::Type,
+    },
+    location::Spanning,
+};
+
+/// The `@core` types laid out by `@core/Codec`.
+const CODEC_BUILTINS: [&str; 8] = [
+    "Nat", "Int", "Float", "String", "Bytes", "Bool", "List", "Option",
+];
+
+pub(super) struct CodecDeriver<'a>(pub(super) Synth<'a>);
+
+impl<'a> Deref for CodecDeriver<'a> {
+    type Target = Synth<'a>;
+
+    fn deref(&self) -> &Synth<'a> {
+        &self.0
+    }
+}
+
+impl CodecDeriver<'_> {
+    /// `ToBytes : [T] Bytes` and `FromBytes : Codec.Decoder<T>`.
+    pub(super) fn derive(
+        &self,
+        type_def: &TypeDef<Unresolved>,
+    ) -> Result<Vec<Derived>, DeriveError> {
+        if let Some(param) = type_def.params.first() {
+            return Err(DeriveError {
+                span: param.name.span.clone(),
+                message: "`derive Codec` needs a type without type parameters".to_owned(),
+            });
+        }
+        let name = &type_def.name.primary;
+        let span = self.span;
+        let subject = subject(span, type_def);
+
+        let value = self.fresh();
+        let to_bytes = Derived {
+            suffix: "ToBytes",
+            doc: format!("Encodes a `{name}` value as bytes."),
+            typ: Type::Function(
+                span.clone(),
+                Box::new(subject.clone()),
+                Box::new(self.bytes_type()),
+                vec![],
+            ),
+            body: Expression::Construction(
+                span.clone(),
+                Construct::Receive(
+                    span.clone(),
+                    Pattern::Name(span.clone(), value.clone(), None),
+                    Box::new(Construct::Then(Box::new(self.encode(
+                        &type_def.typ,
+                        self.var(&value),
+                        false,
+                    )?))),
+                    vec![],
+                ),
+            ),
+        };
+
+        let from_bytes = Derived {
+            suffix: "FromBytes",
+            doc: format!("Decodes a `{name}` value from bytes, as encoded by its `ToBytes`."),
+            typ: Type::Name(
+                span.clone(),
+                GlobalName::builtin_operator(span.clone(), BuiltinOperatorModule::Codec, "Decoder"),
+                vec![subject.clone()],
+            ),
+            body: match &type_def.typ {
+                // `Codec.Recursive(type T, box [inner] decoder)`, with `self` decoded
+                // by `inner`.
+                Type::Recursive {
+                    label: None, body, ..
+                } => {
+                    let inner = self.fresh();
+                    let level =
+                        self.boxed_function(inner.clone(), self.decoder(body, Some(&inner))?);
+                    Expression::Application(
+                        span.clone(),
+                        Box::new(self.codec_global("Recursive")),
+                        Apply::SendType(
+                            span.clone(),
+                            subject.clone(),
+                            Box::new(Apply::Send(
+                                span.clone(),
+                                Box::new(level),
+                                Box::new(Apply::Noop(span.clone())),
+                            )),
+                        ),
+                    )
+                }
+                typ => self.decoder(typ, None)?,
+            },
+        };
+
+        Ok(vec![to_bytes, from_bytes])
+    }
+
+    /// An expression of type `Bytes` for `value`, of type `typ`.
+    fn encode(
+        &self,
+        typ: &Type<Unresolved>,
+        value: Expression<Unresolved>,
+        in_recursive: bool,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        Ok(match typ {
+            Type::Break(_) => {
+                Expression::Primitive(span.clone(), Primitive::Bytes(Default::default()))
+            }
+            Type::Box(_, inner) => self.encode(inner, value, in_recursive)?,
+            Type::Self_(_, None) if in_recursive => Expression::Application(
+                span.clone(),
+                Box::new(value),
+                Apply::Loop(span.clone(), None),
+            ),
+            Type::Name(_, name, args) => match (self.codec(typ, name)?, args.as_slice()) {
+                (Named::Builtin(builtin), []) if !matches!(builtin, "List" | "Option") => {
+                    self.call(self.codec_global(&format!("Encode{builtin}")), vec![value])
+                }
+                (Named::Builtin(builtin @ ("List" | "Option")), [item]) => {
+                    let x = self.fresh();
+                    let encode_item = self.encode(item, self.var(&x), in_recursive)?;
+                    self.call(
+                        self.codec_global(&format!("Encode{builtin}")),
+                        vec![value, self.boxed_function(x, encode_item)],
+                    )
+                }
+                (Named::Derived(qualifier, type_name), []) => {
+                    self.call(self.derived(qualifier, &type_name, "ToBytes"), vec![value])
+                }
+                _ => return Err(self.unsupported(typ)),
+            },
+            Type::Pair(_, _, _, params) if params.is_empty() => {
+                let (items, tail) = pair_items(typ);
+                let names = items.iter().map(|_| self.fresh()).collect::<Vec<_>>();
+                let tail_name = tail.map(|_| self.fresh());
+                let mut pattern = match &tail_name {
+                    Some(name) => Pattern::Name(span.clone(), name.clone(), None),
+                    None => Pattern::Continue(span.clone()),
+                };
+                for name in names.iter().rev() {
+                    pattern = Pattern::Receive(
+                        span.clone(),
+                        Box::new(Pattern::Name(span.clone(), name.clone(), None)),
+                        Box::new(pattern),
+                        vec![],
+                    );
+                }
+                let mut parts = Vec::new();
+                for (item, name) in items.iter().zip(&names) {
+                    parts.push(self.encode(item, self.var(name), in_recursive)?);
+                }
+                if let (Some(tail), Some(name)) = (tail, &tail_name) {
+                    parts.push(self.encode(tail, self.var(name), in_recursive)?);
+                }
+                Expression::Let {
+                    span: span.clone(),
+                    pattern,
+                    expression: Box::new(value),
+                    then: Box::new(self.concat(parts)),
+                }
+            }
+            Type::Either(_, variants) => Expression::Application(
+                span.clone(),
+                Box::new(value),
+                self.encode_variants(variants, in_recursive)?,
+            ),
+            Type::Recursive {
+                label: None, body, ..
+            } => {
+                let Type::Either(_, variants) = body.as_ref() else {
+                    return Err(self.unsupported(typ));
+                };
+                Expression::Application(
+                    span.clone(),
+                    Box::new(value),
+                    Apply::Begin {
+                        span: span.clone(),
+                        unfounded: false,
+                        label: None,
+                        then: Box::new(self.encode_variants(variants, true)?),
+                    },
+                )
+            }
+            _ => return Err(self.unsupported(typ)),
+        })
+    }
+
+    /// `.case { .label! => Codec.EncodeNat(i), .label x => Codec.Concat(*(Codec.EncodeNat(i), x)) }`
+    fn encode_variants(
+        &self,
+        variants: &BTreeMap<LocalName, Type<Unresolved>>,
+        in_recursive: bool,
+    ) -> Result<Apply<Unresolved>, DeriveError> {
+        let span = self.span;
+        let mut branches = ApplyBranches(Default::default());
+        for (index, (label, payload)) in variants.iter().enumerate() {
+            let tag = self.call(self.codec_global("EncodeNat"), vec![self.nat(index)]);
+            let branch = match payload {
+                Type::Break(_) => ApplyBranch::Continue(span.clone(), tag),
+                _ => {
+                    let x = self.fresh();
+                    let value = self.encode(payload, self.var(&x), in_recursive)?;
+                    ApplyBranch::Then(span.clone(), x, self.concat(vec![tag, value]))
+                }
+            };
+            branches.0.insert(self.label(&label.string), branch);
+        }
+        Ok(Apply::Case(span.clone(), branches, None))
+    }
+
+    /// An expression of type `Codec.Decoder<T>` for `typ`. Inside a recursive
+    /// type, `this` is the variable holding the decoder `self` is decoded with.
+    fn decoder(
+        &self,
+        typ: &Type<Unresolved>,
+        this: Option<&LocalName>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        match typ {
+            Type::Break(_) => return Ok(self.codec_global("Unit")),
+            Type::Self_(_, None) if this.is_some() => {
+                return Ok(self.var(this.unwrap()));
+            }
+            Type::Name(_, name, args) => match (self.codec(typ, name)?, args.as_slice()) {
+                (Named::Builtin(builtin), []) if !matches!(builtin, "List" | "Option") => {
+                    return Ok(self.codec_global(builtin));
+                }
+                (Named::Builtin(builtin @ ("List" | "Option")), [item]) => {
+                    return Ok(
+                        self.call(self.codec_global(builtin), vec![self.decoder(item, this)?])
+                    );
+                }
+                (Named::Derived(qualifier, type_name), []) => {
+                    return Ok(self.derived(qualifier, &type_name, "FromBytes"));
+                }
+                _ => return Err(self.unsupported(typ)),
+            },
+            _ => {}
+        }
+
+        let span = self.span;
+        let bytes = self.fresh();
+        let decode = self.decode(typ, self.var(&bytes), this)?;
+        let mut branches = ConstructBranches(Default::default());
+        branches.0.insert(
+            self.label("decode"),
+            ConstructBranch::Receive(
+                span.clone(),
+                Pattern::Name(span.clone(), bytes, None),
+                Box::new(ConstructBranch::Then(span.clone(), decode)),
+                vec![],
+            ),
+        );
+        Ok(Expression::Box(
+            span.clone(),
+            Box::new(Expression::Construction(
+                span.clone(),
+                Construct::Case(span.clone(), branches, None),
+            )),
+        ))
+    }
+
+    /// An expression of type `Try<!, (T) Bytes>` for `bytes`.
+    fn decode(
+        &self,
+        typ: &Type<Unresolved>,
+        bytes: Expression<Unresolved>,
+        this: Option<&LocalName>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        Ok(match typ {
+            Type::Box(_, inner) => self.decode(inner, bytes, this)?,
+            Type::Pair(_, _, _, params) if params.is_empty() => {
+                self.decode_pair(typ, bytes, this, |value| value)?
+            }
+            Type::Either(_, variants) => self.decode_variants(variants, bytes, this)?,
+            Type::Break(_) | Type::Self_(_, None) | Type::Name(..) => {
+                self.decode_with(self.decoder(typ, this)?, bytes)
+            }
+            _ => return Err(self.unsupported(typ)),
+        })
+    }
+
+    /// `if { decode(bytes) is .ok (x) rest and decode(rest) is .ok (y) rest and ... =>
+    /// .ok wrap((x, y, ...)!) rest, else => .err! }`
+    fn decode_pair(
+        &self,
+        typ: &Type<Unresolved>,
+        bytes: Expression<Unresolved>,
+        this: Option<&LocalName>,
+        wrap: impl FnOnce(Expression<Unresolved>) -> Expression<Unresolved>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        let (mut items, tail) = pair_items(typ);
+        items.extend(tail);
+
+        let mut bytes = bytes;
+        let mut decoded = Vec::new();
+        let mut values = Vec::new();
+        for item in &items {
+            let value = self.fresh();
+            let rest = self.fresh();
+            decoded.push(self.is_decoded(self.decode(item, bytes, this)?, &value, &rest));
+            values.push(value);
+            bytes = self.var(&rest);
+        }
+
+        let mut result = match tail {
+            Some(_) => Construct::Then(Box::new(self.var(&values.pop().unwrap()))),
+            None => Construct::Break(span.clone()),
+        };
+        for value in values.iter().rev() {
+            result = Construct::Send(span.clone(), Box::new(self.var(value)), Box::new(result));
+        }
+        Ok(self.if_else_err(
+            self.and(decoded),
+            self.decoded(wrap(Expression::Construction(span.clone(), result)), bytes),
+        ))
+    }
+
+    /// `if { Codec.Nat.decode(bytes) is .ok (tag) rest => if { tag == 0 => if {
+    /// decode(rest) is .ok (x) rest => .ok (.label x) rest, else => .err! }, ...,
+    /// else => .err! }, else => .err! }`
+    fn decode_variants(
+        &self,
+        variants: &BTreeMap<LocalName, Type<Unresolved>>,
+        bytes: Expression<Unresolved>,
+        this: Option<&LocalName>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        let tag = self.fresh();
+        let rest = self.fresh();
+
+        let mut branches = Vec::new();
+        for (index, (label, payload)) in variants.iter().enumerate() {
+            let is_tag = Condition::Bool(
+                span.clone(),
+                Box::new(Expression::ComparisonChain {
+                    span: span.clone(),
+                    first: Box::new(self.var(&tag)),
+                    rest: vec![ComparisonStep {
+                        op_span: span.clone(),
+                        op: ComparisonOperator::Equal,
+                        expr: self.nat(index),
+                    }],
+                }),
+            );
+            let decode = match payload {
+                Type::Break(_) => self.decoded(
+                    self.signal(&label.string, Construct::Break(span.clone())),
+                    self.var(&rest),
+                ),
+                // A pair is decoded right into the variant, as the `if` decoding it
+                // would be compiled again for each of its conditions if it was
+                // matched on.
+                Type::Pair(_, _, _, params) if params.is_empty() => {
+                    self.decode_pair(payload, self.var(&rest), this, |value| {
+                        self.signal_then(&label.string, value)
+                    })?
+                }
+                // Each failing condition of a branch repeats the branches after it
+                // when compiled, so the tag is matched on its own.
+                _ => {
+                    let value = self.fresh();
+                    let after = self.fresh();
+                    self.if_else_err(
+                        self.is_decoded(
+                            self.decode(payload, self.var(&rest), this)?,
+                            &value,
+                            &after,
+                        ),
+                        self.decoded(
+                            self.signal_then(&label.string, self.var(&value)),
+                            self.var(&after),
+                        ),
+                    )
+                }
+            };
+            branches.push((is_tag, decode));
+        }
+        let by_tag = Expression::If {
+            span: span.clone(),
+            branches,
+            else_: Some(Box::new(self.err())),
+        };
+
+        Ok(self.if_else_err(
+            self.is_decoded(
+                self.decode_with(self.codec_global("Nat"), bytes),
+                &tag,
+                &rest,
+            ),
+            by_tag,
+        ))
+    }
+
+    /// `decoder.decode(bytes)`
+    fn decode_with(
+        &self,
+        decoder: Expression<Unresolved>,
+        bytes: Expression<Unresolved>,
+    ) -> Expression<Unresolved> {
+        Expression::Application(
+            self.span.clone(),
+            Box::new(decoder),
+            Apply::Signal(
+                self.span.clone(),
+                self.label("decode"),
+                Box::new(Apply::Send(
+                    self.span.clone(),
+                    Box::new(bytes),
+                    Box::new(Apply::Noop(self.span.clone())),
+                )),
+            ),
+        )
+    }
+
+    /// `result is .ok (value) rest`
+    fn is_decoded(
+        &self,
+        result: Expression<Unresolved>,
+        value: &LocalName,
+        rest: &LocalName,
+    ) -> Condition<Unresolved> {
+        self.is(
+            result,
+            "ok",
+            Pattern::Receive(
+                self.span.clone(),
+                Box::new(self.pattern(value)),
+                Box::new(self.pattern(rest)),
+                vec![],
+            ),
+        )
+    }
+
+    /// `.ok (value) rest`
+    fn decoded(
+        &self,
+        value: Expression<Unresolved>,
+        rest: Expression<Unresolved>,
+    ) -> Expression<Unresolved> {
+        self.ok(Expression::Construction(
+            self.span.clone(),
+            Construct::Send(
+                self.span.clone(),
+                Box::new(value),
+                Box::new(Construct::Then(Box::new(rest))),
+            ),
+        ))
+    }
+
+    /// `Codec.Concat(*(part, ...))`
+    fn concat(&self, parts: Vec<Expression<Unresolved>>) -> Expression<Unresolved> {
+        self.call(
+            self.codec_global("Concat"),
+            vec![Expression::List(self.span.clone(), parts)],
+        )
+    }
+
+    fn nat(&self, value: usize) -> Expression<Unresolved> {
+        Expression::Primitive(
+            self.span.clone(),
+            Primitive::Number(Number::Int(BigInt::from(value))),
+        )
+    }
+
+    fn codec_global(&self, name: &str) -> Expression<Unresolved> {
+        self.global(BuiltinOperatorModule::Codec, name)
+    }
+
+    /// The type `Bytes`.
+    fn bytes_type(&self) -> Type<Unresolved> {
+        Type::Name(
+            self.span.clone(),
+            GlobalName::builtin_operator(self.span.clone(), BuiltinOperatorModule::Bytes, "Bytes"),
+            vec![],
+        )
+    }
+
+    /// Finds what handles the named type `typ`, from how the file refers to it.
+    fn codec(
+        &self,
+        typ: &Type<Unresolved>,
+        name: &GlobalName<Unresolved>,
+    ) -> Result<Named, DeriveError> {
+        self.named(name, &CODEC_BUILTINS)
+            .ok_or_else(|| self.unsupported(typ))
+    }
+
+    fn unsupported(&self, typ: &Type<Unresolved>) -> DeriveError {
+        DeriveError {
+            span: typ.span(),
+            message: "`derive Codec` can't encode this type. It supports `!`, pairs, \
+                `either` types, `recursive` `either` types, and named types that are \
+                `Nat`, `Int`, `Float`, `String`, `Bytes`, `Bool`, `List`, `Option`, or \
+                derive `Codec` themselves"
+                .to_owned(),
+        }
+    }
+}
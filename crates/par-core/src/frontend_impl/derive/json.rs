@@ -0,0 +1,700 @@
+//! `derive Json`: `SuitToJson : [Suit] Json` and `SuitFromJson : Json.Format<Suit>`,
+//! following the structure of the type as written:
+//!
+//! - `!` is `null`, and `Nat`, `Int`, `Float`, `String`, and `Bool` are scalars.
+//! - A pair `(a, b) c` is an array `[a, b, c]`, leaving out a final `!`.
+//! - A variant `.label value` is an object `{"tag": "label", "value": value}`,
+//!   leaving out the `"value"` if it's `!`.
+//! - `List<a>` is an array, and `Option<a>` is `null` or the value.
+//! - Other named types are encoded by what they derive themselves.
+
+use std::{collections::BTreeMap, ops::Deref};
+
+use super::{DeriveError, Derived, Named, Synth, mentions_self, pair_items, replace_self, subject};
+use crate::{
+    frontend_impl::{
+        language::{
+            Apply, ApplyBranch, ApplyBranches, BuiltinOperatorModule, ComparisonOperator,
+            ComparisonStep, Condition, Construct, ConstructBranch, ConstructBranches, Expression,
+            GlobalName, LocalName, Pattern, Unresolved,
+        },
+        program::TypeDef,
+        types::Type,
+    },
+    location::Spanning,
+};
+
+/// The `@core` types with a JSON form of their own.
+const JSON_BUILTINS: [&str; 7] = ["Nat", "Int", "Float", "String", "Bool", "List", "Option"];
+
+/// The loop label for decoding a list in place, apart from the recursive type's own loop.
+const LIST_LOOP: &str = "#items";
+
+pub(super) struct JsonDeriver<'a>(pub(super) Synth<'a>);
+
+impl<'a> Deref for JsonDeriver<'a> {
+    type Target = Synth<'a>;
+
+    fn deref(&self) -> &Synth<'a> {
+        &self.0
+    }
+}
+
+impl JsonDeriver<'_> {
+    /// `ToJson : [T] Json` and `FromJson : Json.Format<T>`.
+    pub(super) fn derive(
+        &self,
+        type_def: &TypeDef<Unresolved>,
+    ) -> Result<Vec<Derived>, DeriveError> {
+        if let Some(param) = type_def.params.first() {
+            return Err(DeriveError {
+                span: param.name.span.clone(),
+                message: "`derive Json` needs a type without type parameters".to_owned(),
+            });
+        }
+        let name = &type_def.name.primary;
+        let span = self.span;
+        let subject = subject(span, type_def);
+
+        let value = self.fresh();
+        let to_json = Derived {
+            suffix: "ToJson",
+            doc: format!("Encodes a `{name}` value as JSON."),
+            typ: Type::Function(
+                span.clone(),
+                Box::new(subject.clone()),
+                Box::new(self.json_type("Json", vec![])),
+                vec![],
+            ),
+            body: Expression::Construction(
+                span.clone(),
+                Construct::Receive(
+                    span.clone(),
+                    Pattern::Name(span.clone(), value.clone(), None),
+                    Box::new(Construct::Then(Box::new(self.encode(
+                        &type_def.typ,
+                        self.var(&value),
+                        false,
+                    )?))),
+                    vec![],
+                ),
+            ),
+        };
+
+        let from_json = Derived {
+            suffix: "FromJson",
+            doc: format!("Decodes a `{name}` value from JSON, as encoded by its `ToJson`."),
+            typ: self.json_type("Format", vec![subject]),
+            body: self.format(&type_def.typ, None)?,
+        };
+
+        Ok(vec![to_json, from_json])
+    }
+
+    /// An expression of type `Json` for `value`, of type `typ`.
+    fn encode(
+        &self,
+        typ: &Type<Unresolved>,
+        value: Expression<Unresolved>,
+        in_recursive: bool,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        Ok(match typ {
+            Type::Break(_) => self.signal("null", Construct::Break(span.clone())),
+            Type::Box(_, inner) => self.encode(inner, value, in_recursive)?,
+            Type::Self_(_, None) if in_recursive => Expression::Application(
+                span.clone(),
+                Box::new(value),
+                Apply::Loop(span.clone(), None),
+            ),
+            Type::Name(_, name, args) => match (self.codec(typ, name)?, args.as_slice()) {
+                (Named::Builtin("Nat" | "Int"), []) => self.signal_then(
+                    "number",
+                    self.call(
+                        self.global(BuiltinOperatorModule::Float, "FromInt"),
+                        vec![value],
+                    ),
+                ),
+                (Named::Builtin("Float"), []) => self.signal_then("number", value),
+                (Named::Builtin("String"), []) => self.signal_then("string", value),
+                (Named::Builtin("Bool"), []) => self.signal_then("bool", value),
+                (Named::Builtin("List"), [item]) => {
+                    let x = self.fresh();
+                    let encode_item = self.encode(item, self.var(&x), in_recursive)?;
+                    self.signal_then(
+                        "list",
+                        self.call(
+                            self.global(BuiltinOperatorModule::List, "Map"),
+                            vec![value, self.boxed_function(x, encode_item)],
+                        ),
+                    )
+                }
+                (Named::Builtin("Option"), [item]) => {
+                    let x = self.fresh();
+                    let mut branches = ApplyBranches(Default::default());
+                    branches.0.insert(
+                        self.label("none"),
+                        ApplyBranch::Continue(
+                            span.clone(),
+                            self.signal("null", Construct::Break(span.clone())),
+                        ),
+                    );
+                    branches.0.insert(
+                        self.label("some"),
+                        ApplyBranch::Then(
+                            span.clone(),
+                            x.clone(),
+                            self.encode(item, self.var(&x), in_recursive)?,
+                        ),
+                    );
+                    Expression::Application(
+                        span.clone(),
+                        Box::new(value),
+                        Apply::Case(span.clone(), branches, None),
+                    )
+                }
+                (Named::Derived(qualifier, type_name), []) => {
+                    self.call(self.derived(qualifier, &type_name, "ToJson"), vec![value])
+                }
+                _ => return Err(self.unsupported(typ)),
+            },
+            Type::Pair(_, _, _, params) if params.is_empty() => {
+                let (items, tail) = pair_items(typ);
+                let names = items.iter().map(|_| self.fresh()).collect::<Vec<_>>();
+                let tail_name = tail.map(|_| self.fresh());
+                let mut pattern = match &tail_name {
+                    Some(name) => Pattern::Name(span.clone(), name.clone(), None),
+                    None => Pattern::Continue(span.clone()),
+                };
+                for name in names.iter().rev() {
+                    pattern = Pattern::Receive(
+                        span.clone(),
+                        Box::new(Pattern::Name(span.clone(), name.clone(), None)),
+                        Box::new(pattern),
+                        vec![],
+                    );
+                }
+                let mut elements = Vec::new();
+                for (item, name) in items.iter().zip(&names) {
+                    elements.push(self.encode(item, self.var(name), in_recursive)?);
+                }
+                if let (Some(tail), Some(name)) = (tail, &tail_name) {
+                    elements.push(self.encode(tail, self.var(name), in_recursive)?);
+                }
+                Expression::Let {
+                    span: span.clone(),
+                    pattern,
+                    expression: Box::new(value),
+                    then: Box::new(
+                        self.signal_then("list", Expression::List(span.clone(), elements)),
+                    ),
+                }
+            }
+            Type::Either(_, variants) => Expression::Application(
+                span.clone(),
+                Box::new(value),
+                self.encode_variants(variants, in_recursive)?,
+            ),
+            Type::Recursive {
+                label: None, body, ..
+            } => {
+                let Type::Either(_, variants) = body.as_ref() else {
+                    return Err(self.unsupported(typ));
+                };
+                Expression::Application(
+                    span.clone(),
+                    Box::new(value),
+                    Apply::Begin {
+                        span: span.clone(),
+                        unfounded: false,
+                        label: None,
+                        then: Box::new(self.encode_variants(variants, true)?),
+                    },
+                )
+            }
+            _ => return Err(self.unsupported(typ)),
+        })
+    }
+
+    /// `.case { .label! => {"tag": "label"}, .label x => {"tag": "label", "value": x} }`
+    fn encode_variants(
+        &self,
+        variants: &BTreeMap<LocalName, Type<Unresolved>>,
+        in_recursive: bool,
+    ) -> Result<Apply<Unresolved>, DeriveError> {
+        let span = self.span;
+        let mut branches = ApplyBranches(Default::default());
+        for (label, payload) in variants {
+            let tag = (
+                "tag",
+                self.signal_then("string", self.string(&label.string)),
+            );
+            let branch = match payload {
+                Type::Break(_) => ApplyBranch::Continue(span.clone(), self.object(vec![tag])),
+                _ => {
+                    let x = self.fresh();
+                    let value = self.encode(payload, self.var(&x), in_recursive)?;
+                    ApplyBranch::Then(span.clone(), x, self.object(vec![tag, ("value", value)]))
+                }
+            };
+            branches.0.insert(self.label(&label.string), branch);
+        }
+        Ok(Apply::Case(span.clone(), branches, None))
+    }
+
+    /// An expression of type `Json.Format<T>` for `typ`.
+    fn format(
+        &self,
+        typ: &Type<Unresolved>,
+        recursive: Option<&Type<Unresolved>>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        if let Type::Name(_, name, args) = typ {
+            let codec = self.codec(typ, name)?;
+            let builtin = match (&codec, args.as_slice()) {
+                (Named::Builtin("Nat"), []) => Some("Nat"),
+                (Named::Builtin("Int"), []) => Some("Int"),
+                (Named::Builtin("Float"), []) => Some("Number"),
+                (Named::Builtin("String"), []) => Some("String"),
+                (Named::Builtin("Bool"), []) => Some("Bool"),
+                _ => None,
+            };
+            if let Some(builtin) = builtin {
+                return Ok(self.global(BuiltinOperatorModule::Json, builtin));
+            }
+            match (codec, args.as_slice()) {
+                (Named::Builtin("List"), [item]) => {
+                    return Ok(self.call(
+                        self.global(BuiltinOperatorModule::Json, "List"),
+                        vec![self.format(item, recursive)?],
+                    ));
+                }
+                (Named::Builtin("Option"), [item]) => {
+                    return Ok(self.call(
+                        self.global(BuiltinOperatorModule::Json, "OrNull"),
+                        vec![self.format(item, recursive)?],
+                    ));
+                }
+                (Named::Derived(qualifier, type_name), []) => {
+                    return Ok(self.derived(qualifier, &type_name, "FromJson"));
+                }
+                _ => {}
+            }
+        }
+        if let Type::Break(_) = typ {
+            return Ok(self.global(BuiltinOperatorModule::Json, "Null"));
+        }
+        let json = self.fresh();
+        let decode = self.decode(typ, self.var(&json), recursive)?;
+        Ok(Expression::Box(
+            self.span.clone(),
+            Box::new(self.parse_case(json, decode)),
+        ))
+    }
+
+    /// `case { .parse(json) => decode }`
+    fn parse_case(
+        &self,
+        json: LocalName,
+        decode: Expression<Unresolved>,
+    ) -> Expression<Unresolved> {
+        let span = self.span;
+        let mut branches = ConstructBranches(Default::default());
+        branches.0.insert(
+            self.label("parse"),
+            ConstructBranch::Receive(
+                span.clone(),
+                Pattern::Name(span.clone(), json, None),
+                Box::new(ConstructBranch::Then(span.clone(), decode)),
+                vec![],
+            ),
+        );
+        Expression::Construction(span.clone(), Construct::Case(span.clone(), branches, None))
+    }
+
+    /// An expression of type `Try<!, T>` for `json`, of type `Json`.
+    fn decode(
+        &self,
+        typ: &Type<Unresolved>,
+        json: Expression<Unresolved>,
+        recursive: Option<&Type<Unresolved>>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        Ok(match typ {
+            Type::Box(_, inner) => self.decode(inner, json, recursive)?,
+            Type::Self_(_, None) if recursive.is_some() => Expression::Application(
+                span.clone(),
+                Box::new(json),
+                Apply::Loop(span.clone(), None),
+            ),
+            Type::Pair(_, _, _, params) if params.is_empty() => Expression::Application(
+                span.clone(),
+                Box::new(json),
+                self.decode_pair(typ, recursive)?,
+            ),
+            Type::Either(_, variants) => Expression::Application(
+                span.clone(),
+                Box::new(json),
+                self.decode_variants(variants, recursive)?,
+            ),
+            Type::Recursive {
+                label: None, body, ..
+            } => {
+                let Type::Either(_, variants) = body.as_ref() else {
+                    return Err(self.unsupported(typ));
+                };
+                Expression::Application(
+                    span.clone(),
+                    Box::new(json),
+                    Apply::Begin {
+                        span: span.clone(),
+                        unfounded: false,
+                        label: None,
+                        then: Box::new(self.decode_variants(variants, Some(typ))?),
+                    },
+                )
+            }
+            // A `Json.Format` receives plain `Json`, which can't loop back to the
+            // recursive decoder, so lists and options of `self` are decoded in place.
+            Type::Name(_, name, args) if args.iter().any(mentions_self) => {
+                let Some(recursive) = recursive else {
+                    return Err(self.unsupported(typ));
+                };
+                let decode = match (self.codec(typ, name)?, args.as_slice()) {
+                    (Named::Builtin("List"), [item]) => Expression::Application(
+                        span.clone(),
+                        Box::new(json),
+                        self.decode_list(item, recursive)?,
+                    ),
+                    (Named::Builtin("Option"), [item]) => {
+                        self.decode_option(item, json, recursive)?
+                    }
+                    _ => return Err(self.unsupported(typ)),
+                };
+                // The loops inside need the type of the result to be known.
+                Expression::TypeIn {
+                    span: span.clone(),
+                    typ: self.try_type(replace_self(typ, recursive)),
+                    expr: Box::new(decode),
+                }
+            }
+            Type::Break(_) | Type::Name(..) => Expression::Application(
+                span.clone(),
+                Box::new(self.format(typ, recursive)?),
+                Apply::Signal(
+                    span.clone(),
+                    self.label("parse"),
+                    Box::new(Apply::Send(
+                        span.clone(),
+                        Box::new(json),
+                        Box::new(Apply::Noop(span.clone())),
+                    )),
+                ),
+            ),
+            _ => return Err(self.unsupported(typ)),
+        })
+    }
+
+    /// `.case { .list xs => if { xs is .item(x) xs and ... and xs is .end! and
+    /// decode(x) is .ok y and ... => .ok (y, ...)!, else => .err! }, else _ => .err! }`
+    fn decode_pair(
+        &self,
+        typ: &Type<Unresolved>,
+        recursive: Option<&Type<Unresolved>>,
+    ) -> Result<Apply<Unresolved>, DeriveError> {
+        let span = self.span;
+        let (mut items, tail) = pair_items(typ);
+        items.extend(tail);
+
+        let mut list = self.fresh();
+        let list_name = list.clone();
+        let mut shape = Vec::new();
+        let mut decoded = Vec::new();
+        let mut values = Vec::new();
+        for item in &items {
+            let json = self.fresh();
+            let rest = self.fresh();
+            shape.push(self.is(
+                self.var(&list),
+                "item",
+                Pattern::Receive(
+                    span.clone(),
+                    Box::new(self.pattern(&json)),
+                    Box::new(self.pattern(&rest)),
+                    vec![],
+                ),
+            ));
+            let value = self.fresh();
+            decoded.push(self.is(
+                self.decode(item, self.var(&json), recursive)?,
+                "ok",
+                self.pattern(&value),
+            ));
+            values.push(value);
+            list = rest;
+        }
+        shape.push(self.is(self.var(&list), "end", Pattern::Continue(span.clone())));
+
+        let mut result = match tail {
+            Some(_) => Construct::Then(Box::new(self.var(&values.pop().unwrap()))),
+            None => Construct::Break(span.clone()),
+        };
+        for value in values.iter().rev() {
+            result = Construct::Send(span.clone(), Box::new(self.var(value)), Box::new(result));
+        }
+        shape.extend(decoded);
+        let decode = self.if_else_err(
+            self.and(shape),
+            self.ok(Expression::Construction(span.clone(), result)),
+        );
+        Ok(self.case_or_err("list", list_name, decode))
+    }
+
+    /// `.case { .list xs => xs.begin@items.case { .end! => .ok .end!, .item(x) xs =>
+    /// if { decode(x) is .ok y and xs.loop@items is .ok ys => .ok .item(y) ys,
+    /// else => .err! } }, else _ => .err! }`
+    fn decode_list(
+        &self,
+        item: &Type<Unresolved>,
+        recursive: &Type<Unresolved>,
+    ) -> Result<Apply<Unresolved>, DeriveError> {
+        let span = self.span;
+        let loop_label = Some(self.label(LIST_LOOP));
+        let list = self.fresh();
+        let x = self.fresh();
+        let rest = self.fresh();
+        let y = self.fresh();
+        let ys = self.fresh();
+
+        let decode_item = self.if_else_err(
+            self.and(vec![
+                self.is(
+                    self.decode(item, self.var(&x), Some(recursive))?,
+                    "ok",
+                    self.pattern(&y),
+                ),
+                self.is(
+                    Expression::Application(
+                        span.clone(),
+                        Box::new(self.var(&rest)),
+                        Apply::Loop(span.clone(), loop_label.clone()),
+                    ),
+                    "ok",
+                    self.pattern(&ys),
+                ),
+            ]),
+            self.ok(self.signal(
+                "item",
+                Construct::Send(
+                    span.clone(),
+                    Box::new(self.var(&y)),
+                    Box::new(Construct::Then(Box::new(self.var(&ys)))),
+                ),
+            )),
+        );
+        let mut branches = ApplyBranches(Default::default());
+        branches.0.insert(
+            self.label("end"),
+            ApplyBranch::Continue(
+                span.clone(),
+                self.ok(self.signal("end", Construct::Break(span.clone()))),
+            ),
+        );
+        branches.0.insert(
+            self.label("item"),
+            ApplyBranch::Receive(
+                span.clone(),
+                self.pattern(&x),
+                Box::new(ApplyBranch::Then(span.clone(), rest, decode_item)),
+                vec![],
+            ),
+        );
+        let decode = Expression::Application(
+            span.clone(),
+            Box::new(self.var(&list)),
+            Apply::Begin {
+                span: span.clone(),
+                unfounded: false,
+                label: loop_label,
+                then: Box::new(Apply::Case(span.clone(), branches, None)),
+            },
+        );
+        Ok(self.case_or_err("list", list, decode))
+    }
+
+    /// `let j = json in if { decode(j) is .ok x => .ok .some x, j is .null! =>
+    /// .ok .none!, else => .err! }`, trying the value first, as checking for
+    /// `null` first would leave `j` unable to loop.
+    fn decode_option(
+        &self,
+        item: &Type<Unresolved>,
+        json: Expression<Unresolved>,
+        recursive: &Type<Unresolved>,
+    ) -> Result<Expression<Unresolved>, DeriveError> {
+        let span = self.span;
+        let j = self.fresh();
+        let x = self.fresh();
+        let decode = Expression::If {
+            span: span.clone(),
+            branches: vec![
+                (
+                    self.is(
+                        self.decode(item, self.var(&j), Some(recursive))?,
+                        "ok",
+                        self.pattern(&x),
+                    ),
+                    self.ok(self.signal_then("some", self.var(&x))),
+                ),
+                (
+                    self.is(self.var(&j), "null", Pattern::Continue(span.clone())),
+                    self.ok(self.signal("none", Construct::Break(span.clone()))),
+                ),
+            ],
+            else_: Some(Box::new(self.err())),
+        };
+        Ok(Expression::Let {
+            span: span.clone(),
+            pattern: self.pattern(&j),
+            expression: Box::new(json),
+            then: Box::new(decode),
+        })
+    }
+
+    /// `.case { .object o => if { o.get("tag") is .some t and t is .string tag =>
+    /// if { tag == "label" => if { o.get("value") is .some v and decode(v) is .ok x =>
+    /// .ok .label x, else => .err! }, ..., else => .err! }, else => .err! },
+    /// else _ => .err! }`
+    fn decode_variants(
+        &self,
+        variants: &BTreeMap<LocalName, Type<Unresolved>>,
+        recursive: Option<&Type<Unresolved>>,
+    ) -> Result<Apply<Unresolved>, DeriveError> {
+        let object = self.fresh();
+        let tag_json = self.fresh();
+        let tag = self.fresh();
+
+        let mut branches = Vec::new();
+        for (label, payload) in variants {
+            let is_tag = Condition::Bool(
+                self.span.clone(),
+                Box::new(Expression::ComparisonChain {
+                    span: self.span.clone(),
+                    first: Box::new(self.var(&tag)),
+                    rest: vec![ComparisonStep {
+                        op_span: self.span.clone(),
+                        op: ComparisonOperator::Equal,
+                        expr: self.string(&label.string),
+                    }],
+                }),
+            );
+            let branch = match payload {
+                Type::Break(_) => (
+                    is_tag,
+                    self.ok(self.signal(&label.string, Construct::Break(self.span.clone()))),
+                ),
+                // Each failing condition of a branch repeats the branches after it
+                // when compiled, so the tag is matched on its own.
+                _ => {
+                    let json = self.fresh();
+                    let value = self.fresh();
+                    (
+                        is_tag,
+                        self.if_else_err(
+                            self.and(vec![
+                                self.is(self.get(&object, "value"), "some", self.pattern(&json)),
+                                self.is(
+                                    self.decode(payload, self.var(&json), recursive)?,
+                                    "ok",
+                                    self.pattern(&value),
+                                ),
+                            ]),
+                            self.ok(self.signal_then(&label.string, self.var(&value))),
+                        ),
+                    )
+                }
+            };
+            branches.push(branch);
+        }
+        let by_tag = Expression::If {
+            span: self.span.clone(),
+            branches,
+            else_: Some(Box::new(self.err())),
+        };
+
+        let decode = self.if_else_err(
+            self.and(vec![
+                self.is(self.get(&object, "tag"), "some", self.pattern(&tag_json)),
+                self.is(self.var(&tag_json), "string", self.pattern(&tag)),
+            ]),
+            by_tag,
+        );
+        Ok(self.case_or_err("object", object, decode))
+    }
+
+    /// The type `Json.{name}<args>`.
+    fn json_type(&self, name: &str, args: Vec<Type<Unresolved>>) -> Type<Unresolved> {
+        Type::Name(
+            self.span.clone(),
+            GlobalName::builtin_operator(self.span.clone(), BuiltinOperatorModule::Json, name),
+            args,
+        )
+    }
+
+    /// Finds what handles the named type `typ`, from how the file refers to it.
+    fn codec(
+        &self,
+        typ: &Type<Unresolved>,
+        name: &GlobalName<Unresolved>,
+    ) -> Result<Named, DeriveError> {
+        self.named(name, &JSON_BUILTINS)
+            .ok_or_else(|| self.unsupported(typ))
+    }
+
+    fn unsupported(&self, typ: &Type<Unresolved>) -> DeriveError {
+        DeriveError {
+            span: typ.span(),
+            message: "`derive Json` can't encode this type. It supports `!`, pairs, \
+                `either` types, `recursive` `either` types, and named types that are \
+                `Nat`, `Int`, `Float`, `String`, `Bool`, `List`, `Option`, or derive \
+                `Json` themselves"
+                .to_owned(),
+        }
+    }
+
+    /// `object.get("key")`
+    fn get(&self, object: &LocalName, key: &str) -> Expression<Unresolved> {
+        Expression::Application(
+            self.span.clone(),
+            Box::new(self.var(object)),
+            Apply::Signal(
+                self.span.clone(),
+                self.label("get"),
+                Box::new(Apply::Send(
+                    self.span.clone(),
+                    Box::new(self.string(key)),
+                    Box::new(Apply::Noop(self.span.clone())),
+                )),
+            ),
+        )
+    }
+
+    /// `Json.FromFields(*(("key") value, ...))`
+    fn object(&self, fields: Vec<(&str, Expression<Unresolved>)>) -> Expression<Unresolved> {
+        let fields = fields
+            .into_iter()
+            .map(|(key, value)| {
+                Expression::Construction(
+                    self.span.clone(),
+                    Construct::Send(
+                        self.span.clone(),
+                        Box::new(self.string(key)),
+                        Box::new(Construct::Then(Box::new(value))),
+                    ),
+                )
+            })
+            .collect();
+        self.call(
+            self.global(BuiltinOperatorModule::Json, "FromFields"),
+            vec![Expression::List(self.span.clone(), fields)],
+        )
+    }
+}
@@ -0,0 +1,584 @@
+//! Definitions synthesized from `derive` clauses on type definitions.
+//!
+//! ```par
+//! type Suit = either { .hearts!, .spades! } derive Equals, Compare
+//! ```
+//!
+//! adds `SuitEquals : [Suit, Suit] Bool` and `SuitCompare : [Suit, Suit] Ordering`
+//! to the module, exported together with the type. A module's primary type gets
+//! them under the bare names instead, so they read as `Suit.Equals` and
+//! `Suit.Compare` from other modules. Both compare structurally, the same way
+//! `==` and `<` do, so the type must be `data`.
+//!
+//! `derive Json` and `derive Codec` generate an encoder and a decoder instead,
+//! following the structure of the type as written; see their modules.
+
+mod codec;
+mod json;
+
+use std::{cell::Cell, collections::BTreeMap};
+
+use arcstr::ArcStr;
+use par_runtime::primitive::{ParString, Primitive};
+
+use super::{
+    desugar::Expander,
+    language::{
+        Apply, ApplyBranch, ApplyBranches, BuiltinOperatorModule, ComparisonOperator,
+        ComparisonStep, Condition, Construct, Expression, GlobalName, LocalName, Pattern,
+        TypeConstraint, TypeParameter, Unresolved,
+    },
+    program::{
+        Declaration, Definition, DefinitionBody, DocComment, ImportDecl, SourceFile, TypeDef,
+    },
+    types::Type,
+};
+use crate::location::Span;
+use codec::CodecDeriver;
+use json::JsonDeriver;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Derivable {
+    Equals,
+    Compare,
+    Json,
+    Codec,
+}
+
+impl Derivable {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Equals" => Some(Self::Equals),
+            "Compare" => Some(Self::Compare),
+            "Json" => Some(Self::Json),
+            "Codec" => Some(Self::Codec),
+            _ => None,
+        }
+    }
+}
+
+/// A `derive` clause, for the type definition at `type_def` in its file.
+#[derive(Clone, Debug)]
+pub(crate) struct DeriveClause {
+    pub(crate) span: Span,
+    pub(crate) type_def: usize,
+    pub(crate) derived: Vec<(Span, Derivable)>,
+}
+
+/// A type definition that can't derive what it asks for.
+#[derive(Clone, Debug)]
+pub(crate) struct DeriveError {
+    pub(crate) span: Span,
+    pub(crate) message: String,
+}
+
+/// One declaration and definition asked for by a clause, named after the type
+/// followed by `suffix`.
+struct Derived {
+    suffix: &'static str,
+    doc: String,
+    typ: Type<Unresolved>,
+    body: Expression<Unresolved>,
+}
+
+/// Adds the declarations and definitions asked for by `clauses` to the file.
+pub(crate) fn expand_derives(
+    source_file: &mut SourceFile<Expression<Unresolved>>,
+    clauses: Vec<DeriveClause>,
+) -> Result<(), DeriveError> {
+    let module_name = source_file
+        .module_decl
+        .as_ref()
+        .map(|module_decl| module_decl.name.clone());
+    for clause in clauses {
+        let type_def = source_file.body.type_defs[clause.type_def].clone();
+        let is_primary = module_name.as_deref() == Some(type_def.name.primary.as_str());
+        let expander = Expander::new("`derive`", &clause.span);
+        for (span, derivable) in &clause.derived {
+            let span = expander.derive(span);
+            let derived = match derivable {
+                Derivable::Equals | Derivable::Compare => {
+                    vec![comparison(&expander, &span, &type_def, *derivable)]
+                }
+                Derivable::Json => JsonDeriver(Synth {
+                    span: &span,
+                    module_name: module_name.as_deref(),
+                    imports: &source_file.imports,
+                    prefix: "#json",
+                    fresh: Cell::new(0),
+                })
+                .derive(&type_def)?,
+                Derivable::Codec => CodecDeriver(Synth {
+                    span: &span,
+                    module_name: module_name.as_deref(),
+                    imports: &source_file.imports,
+                    prefix: "#codec",
+                    fresh: Cell::new(0),
+                })
+                .derive(&type_def)?,
+            };
+            for Derived {
+                suffix,
+                doc,
+                typ,
+                body,
+            } in derived
+            {
+                let name = GlobalName::new(
+                    span.clone(),
+                    Unresolved::Path { qualifier: None },
+                    derived_name(&type_def.name.primary, is_primary, suffix),
+                );
+                source_file.body.declarations.push(Declaration {
+                    span: span.clone(),
+                    exported: type_def.exported,
+                    doc: Some(DocComment {
+                        span: span.clone(),
+                        markdown: ArcStr::from(doc),
+                    }),
+                    name: name.clone(),
+                    parameter_names: Vec::new(),
+                    typ,
+                });
+                source_file.body.definitions.push(Definition {
+                    span: span.clone(),
+                    name,
+                    body: DefinitionBody::Par(body),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn derived_name(type_name: &str, is_primary: bool, suffix: &str) -> String {
+    if is_primary {
+        suffix.to_owned()
+    } else {
+        format!("{type_name}{suffix}")
+    }
+}
+
+/// The type the derived items are about, `T<a, ...>`.
+fn subject(span: &Span, type_def: &TypeDef<Unresolved>) -> Type<Unresolved> {
+    Type::Name(
+        span.clone(),
+        type_def.name.clone(),
+        type_def
+            .params
+            .iter()
+            .map(|param| Type::Var(span.clone(), param.name.clone()))
+            .collect(),
+    )
+}
+
+/// The derived type parameters: those of the type, all constrained to `data`.
+fn type_params(type_def: &TypeDef<Unresolved>) -> Vec<TypeParameter> {
+    type_def
+        .params
+        .iter()
+        .map(|param| TypeParameter {
+            name: param.name.clone(),
+            constraint: TypeConstraint::Data,
+        })
+        .collect()
+}
+
+/// `<a: data, ...>[T<a, ...>, T<a, ...>] Bool`, or `Ordering`, defined as
+/// `[left] [right] left == right`, or `Data.Compare(left, right)`.
+fn comparison(
+    expander: &Expander,
+    span: &Span,
+    type_def: &TypeDef<Unresolved>,
+    derivable: Derivable,
+) -> Derived {
+    let name = &type_def.name.primary;
+    let (suffix, doc, module, result) = match derivable {
+        Derivable::Equals => (
+            "Equals",
+            format!("Whether two `{name}` values are structurally equal."),
+            BuiltinOperatorModule::Bool,
+            "Bool",
+        ),
+        Derivable::Compare => (
+            "Compare",
+            format!("Compares two `{name}` values structurally."),
+            BuiltinOperatorModule::Ordering,
+            "Ordering",
+        ),
+        Derivable::Json | Derivable::Codec => {
+            unreachable!("encoders are derived by `JsonDeriver` and `CodecDeriver`")
+        }
+    };
+
+    let subject = subject(span, type_def);
+    let result = Type::Name(
+        span.clone(),
+        GlobalName::builtin_operator(span.clone(), module, result),
+        vec![],
+    );
+    let typ = Type::Function(
+        span.clone(),
+        Box::new(subject.clone()),
+        Box::new(Type::Function(
+            span.clone(),
+            Box::new(subject),
+            Box::new(result),
+            vec![],
+        )),
+        type_params(type_def),
+    );
+
+    let left = expander.local(span, "left");
+    let right = expander.local(span, "right");
+    let left_var = Expression::Variable(span.clone(), left.clone());
+    let right_var = Expression::Variable(span.clone(), right.clone());
+    let result = match derivable {
+        Derivable::Equals => Expression::ComparisonChain {
+            span: span.clone(),
+            first: Box::new(left_var),
+            rest: vec![ComparisonStep {
+                op_span: span.clone(),
+                op: ComparisonOperator::Equal,
+                expr: right_var,
+            }],
+        },
+        _ => Expression::Application(
+            span.clone(),
+            Box::new(Expression::Global(
+                span.clone(),
+                GlobalName::builtin_operator(span.clone(), BuiltinOperatorModule::Data, "Compare"),
+            )),
+            Apply::Send(
+                span.clone(),
+                Box::new(Expression::Construction(
+                    span.clone(),
+                    Construct::Send(
+                        span.clone(),
+                        Box::new(left_var),
+                        Box::new(Construct::Then(Box::new(right_var))),
+                    ),
+                )),
+                Box::new(Apply::Noop(span.clone())),
+            ),
+        ),
+    };
+    let body = Expression::Construction(
+        span.clone(),
+        Construct::Receive(
+            span.clone(),
+            Pattern::Name(span.clone(), left, None),
+            Box::new(Construct::Receive(
+                span.clone(),
+                Pattern::Name(span.clone(), right, None),
+                Box::new(Construct::Then(Box::new(result))),
+                vec![],
+            )),
+            type_params(type_def),
+        ),
+    );
+
+    Derived {
+        suffix,
+        doc,
+        typ,
+        body,
+    }
+}
+
+/// What encodes and decodes values of a named type.
+enum Named {
+    /// A type from `@core`, handled by the module of the derived functions
+    /// directly.
+    Builtin(&'static str),
+    /// A type deriving the same itself, by the module qualifier and the name it
+    /// was written with.
+    Derived(Option<String>, String),
+}
+
+/// Builds the expressions of derived encoders and decoders.
+struct Synth<'a> {
+    span: &'a Span,
+    module_name: Option<&'a str>,
+    imports: &'a [ImportDecl],
+    /// What generated variable names start with.
+    prefix: &'static str,
+    fresh: Cell<usize>,
+}
+
+impl Synth<'_> {
+    /// Finds what handles the named type, from how the file refers to it, or
+    /// `None` if it's not a path.
+    fn named(&self, name: &GlobalName<Unresolved>, builtins: &[&'static str]) -> Option<Named> {
+        let Unresolved::Path { qualifier } = &name.module else {
+            return None;
+        };
+        let import = |alias: &str| {
+            self.imports
+                .iter()
+                .find(|import| import.alias.as_deref().unwrap_or(&import.path.module) == alias)
+        };
+        Some(match qualifier {
+            // A type of the file's own module, or the primary type of an
+            // imported one.
+            None => match import(&name.primary) {
+                Some(import)
+                    if import.path.dependency.as_deref() == Some("core")
+                        && import.path.directories.is_empty() =>
+                {
+                    match builtins
+                        .iter()
+                        .find(|builtin| **builtin == import.path.module)
+                    {
+                        Some(builtin) => Named::Builtin(builtin),
+                        None => Named::Derived(Some(name.primary.clone()), String::new()),
+                    }
+                }
+                Some(_) => Named::Derived(Some(name.primary.clone()), String::new()),
+                None if Some(name.primary.as_str()) == self.module_name => {
+                    Named::Derived(None, String::new())
+                }
+                None => Named::Derived(None, name.primary.clone()),
+            },
+            Some(qualifier) => match import(qualifier) {
+                Some(import) if import.path.module == name.primary => {
+                    Named::Derived(Some(qualifier.clone()), String::new())
+                }
+                _ => Named::Derived(Some(qualifier.clone()), name.primary.clone()),
+            },
+        })
+    }
+
+    /// The type `either { .ok T, .err! }`.
+    fn try_type(&self, typ: Type<Unresolved>) -> Type<Unresolved> {
+        Type::Either(
+            self.span.clone(),
+            BTreeMap::from([
+                (self.label("ok"), typ),
+                (self.label("err"), Type::Break(self.span.clone())),
+            ]),
+        )
+    }
+
+    fn fresh(&self) -> LocalName {
+        let index = self.fresh.get();
+        self.fresh.set(index + 1);
+        LocalName {
+            span: self.span.clone(),
+            string: arcstr::format!("{}{index}", self.prefix),
+        }
+    }
+
+    fn label(&self, label: &str) -> LocalName {
+        LocalName {
+            span: self.span.clone(),
+            string: ArcStr::from(label),
+        }
+    }
+
+    fn pattern(&self, name: &LocalName) -> Pattern<Unresolved> {
+        Pattern::Name(self.span.clone(), name.clone(), None)
+    }
+
+    fn var(&self, name: &LocalName) -> Expression<Unresolved> {
+        Expression::Variable(self.span.clone(), name.clone())
+    }
+
+    fn string(&self, value: &str) -> Expression<Unresolved> {
+        Expression::Primitive(
+            self.span.clone(),
+            Primitive::String(ParString::from(value.to_owned())),
+        )
+    }
+
+    fn global(&self, module: BuiltinOperatorModule, name: &str) -> Expression<Unresolved> {
+        Expression::Global(
+            self.span.clone(),
+            GlobalName::builtin_operator(self.span.clone(), module, name),
+        )
+    }
+
+    /// `Qualifier.TypeSuffix`, where `type_name` is empty for a primary type.
+    fn derived(
+        &self,
+        qualifier: Option<String>,
+        type_name: &str,
+        suffix: &str,
+    ) -> Expression<Unresolved> {
+        Expression::Global(
+            self.span.clone(),
+            GlobalName::new(
+                self.span.clone(),
+                Unresolved::Path { qualifier },
+                format!("{type_name}{suffix}"),
+            ),
+        )
+    }
+
+    /// `function(arg, ...)`
+    fn call(
+        &self,
+        function: Expression<Unresolved>,
+        args: Vec<Expression<Unresolved>>,
+    ) -> Expression<Unresolved> {
+        let mut apply = Apply::Noop(self.span.clone());
+        for arg in args.into_iter().rev() {
+            apply = Apply::Send(self.span.clone(), Box::new(arg), Box::new(apply));
+        }
+        Expression::Application(self.span.clone(), Box::new(function), apply)
+    }
+
+    /// `box [x] body`
+    fn boxed_function(&self, x: LocalName, body: Expression<Unresolved>) -> Expression<Unresolved> {
+        Expression::Box(
+            self.span.clone(),
+            Box::new(Expression::Construction(
+                self.span.clone(),
+                Construct::Receive(
+                    self.span.clone(),
+                    self.pattern(&x),
+                    Box::new(Construct::Then(Box::new(body))),
+                    vec![],
+                ),
+            )),
+        )
+    }
+
+    /// `.label then`
+    fn signal(&self, label: &str, then: Construct<Unresolved>) -> Expression<Unresolved> {
+        Expression::Construction(
+            self.span.clone(),
+            Construct::Signal(self.span.clone(), self.label(label), Box::new(then)),
+        )
+    }
+
+    /// `.label value`
+    fn signal_then(&self, label: &str, value: Expression<Unresolved>) -> Expression<Unresolved> {
+        self.signal(label, Construct::Then(Box::new(value)))
+    }
+
+    fn ok(&self, value: Expression<Unresolved>) -> Expression<Unresolved> {
+        self.signal_then("ok", value)
+    }
+
+    fn err(&self) -> Expression<Unresolved> {
+        self.signal("err", Construct::Break(self.span.clone()))
+    }
+
+    /// `value is .variant pattern`
+    fn is(
+        &self,
+        value: Expression<Unresolved>,
+        variant: &str,
+        pattern: Pattern<Unresolved>,
+    ) -> Condition<Unresolved> {
+        Condition::Is {
+            span: self.span.clone(),
+            value,
+            variant: self.label(variant),
+            pattern,
+        }
+    }
+
+    fn and(&self, conditions: Vec<Condition<Unresolved>>) -> Condition<Unresolved> {
+        conditions
+            .into_iter()
+            .reduce(|left, right| {
+                Condition::And(self.span.clone(), Box::new(left), Box::new(right))
+            })
+            .expect("at least one condition")
+    }
+
+    /// `if { condition => then, else => .err! }`
+    fn if_else_err(
+        &self,
+        condition: Condition<Unresolved>,
+        then: Expression<Unresolved>,
+    ) -> Expression<Unresolved> {
+        Expression::If {
+            span: self.span.clone(),
+            branches: vec![(condition, then)],
+            else_: Some(Box::new(self.err())),
+        }
+    }
+
+    /// `.case { .variant name => then, else _ => .err! }`
+    fn case_or_err(
+        &self,
+        variant: &str,
+        name: LocalName,
+        then: Expression<Unresolved>,
+    ) -> Apply<Unresolved> {
+        let mut branches = ApplyBranches(Default::default());
+        branches.0.insert(
+            self.label(variant),
+            ApplyBranch::Then(self.span.clone(), name, then),
+        );
+        Apply::Case(
+            self.span.clone(),
+            branches,
+            Some(Box::new(ApplyBranch::Then(
+                self.span.clone(),
+                self.fresh(),
+                self.err(),
+            ))),
+        )
+    }
+}
+
+/// The elements of a pair type `(a, b) c`, and its last one, `c`, unless it's `!`.
+fn pair_items(typ: &Type<Unresolved>) -> (Vec<&Type<Unresolved>>, Option<&Type<Unresolved>>) {
+    let mut items = Vec::new();
+    let mut rest = typ;
+    while let Type::Pair(_, first, next, params) = rest
+        && params.is_empty()
+    {
+        items.push(first.as_ref());
+        rest = next;
+    }
+    match rest {
+        Type::Break(_) => (items, None),
+        _ => (items, Some(rest)),
+    }
+}
+
+/// Whether `typ` refers to the `self` of an enclosing `recursive` type.
+fn mentions_self(typ: &Type<Unresolved>) -> bool {
+    match typ {
+        Type::Self_(_, None) => true,
+        Type::Box(_, inner) => mentions_self(inner),
+        Type::Name(_, _, args) => args.iter().any(mentions_self),
+        Type::Pair(_, first, rest, _) => mentions_self(first) || mentions_self(rest),
+        Type::Either(_, variants) => variants.values().any(mentions_self),
+        _ => false,
+    }
+}
+
+/// `typ` with the `self` of an enclosing `recursive` type replaced by that type.
+fn replace_self(typ: &Type<Unresolved>, recursive: &Type<Unresolved>) -> Type<Unresolved> {
+    match typ {
+        Type::Self_(_, None) => recursive.clone(),
+        Type::Box(span, inner) => Type::Box(span.clone(), Box::new(replace_self(inner, recursive))),
+        Type::Name(span, name, args) => Type::Name(
+            span.clone(),
+            name.clone(),
+            args.iter()
+                .map(|arg| replace_self(arg, recursive))
+                .collect(),
+        ),
+        Type::Pair(span, first, rest, params) => Type::Pair(
+            span.clone(),
+            Box::new(replace_self(first, recursive)),
+            Box::new(replace_self(rest, recursive)),
+            params.clone(),
+        ),
+        Type::Either(span, variants) => Type::Either(
+            span.clone(),
+            variants
+                .iter()
+                .map(|(label, payload)| (label.clone(), replace_self(payload, recursive)))
+                .collect(),
+        ),
+        _ => typ.clone(),
+    }
+}
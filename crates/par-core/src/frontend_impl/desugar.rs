@@ -0,0 +1,98 @@
+//! Building blocks for lowering syntactic sugar.
+//!
+//! Nodes synthesized while expanding a sugar get spans marked with where the
+//! sugar was, so a diagnostic about one of them can point back at the code the
+//! user actually wrote, as in "in code expanded from this `if`".
+
+use std::sync::Arc;
+
+use arcstr::ArcStr;
+
+use super::language::LocalName;
+use super::process::{Captures, Command, Expression, Process, VariableUsage};
+use crate::location::Span;
+
+pub(crate) struct Expander {
+    sugar: &'static str,
+    origin: Span,
+}
+
+impl Expander {
+    /// Starts expanding `sugar`, written at `origin`. `sugar` should read well
+    /// after "in code expanded from this", like "`if`" or "list literal".
+    pub(crate) fn new(sugar: &'static str, origin: &Span) -> Self {
+        Self {
+            sugar,
+            origin: origin.clone(),
+        }
+    }
+
+    /// The span for a node that stands for the whole sugar.
+    pub(crate) fn span(&self) -> Span {
+        self.derive(&self.origin)
+    }
+
+    /// The span for a node synthesized from the part of the sugar at `span`.
+    pub(crate) fn derive(&self, span: &Span) -> Span {
+        span.expanded_from(self.sugar, &self.origin)
+    }
+
+    pub(crate) fn local(&self, span: &Span, name: &'static str) -> LocalName {
+        LocalName {
+            span: self.derive(span),
+            string: ArcStr::from(name),
+        }
+    }
+
+    /// `result.signal; then`
+    pub(crate) fn signal<S>(
+        &self,
+        span: &Span,
+        signal: &'static str,
+        then: Arc<Process<(), S>>,
+    ) -> Arc<Process<(), S>> {
+        self.on_result(span, Command::Signal(self.local(span, signal), then))
+    }
+
+    /// `result(value); then`
+    pub(crate) fn send<S>(
+        &self,
+        span: &Span,
+        value: Arc<Expression<(), S>>,
+        then: Arc<Process<(), S>>,
+    ) -> Arc<Process<(), S>> {
+        self.on_result(span, Command::Send(value, then))
+    }
+
+    /// `result!`
+    pub(crate) fn break_<S>(&self, span: &Span) -> Arc<Process<(), S>> {
+        self.on_result(span, Command::Break)
+    }
+
+    pub(crate) fn unreachable<S>(&self) -> Arc<Process<(), S>> {
+        Arc::new(Process::Unreachable(self.span()))
+    }
+
+    /// `chan result { process }`, the expression the sugar expands to.
+    pub(crate) fn chan<S>(&self, process: Arc<Process<(), S>>) -> Arc<Expression<(), S>> {
+        Arc::new(Expression::Chan {
+            span: self.span(),
+            captures: Captures::new(),
+            chan_name: LocalName::result(),
+            chan_annotation: None,
+            chan_type: (),
+            expr_type: (),
+            process,
+        })
+    }
+
+    fn on_result<S>(&self, span: &Span, command: Command<(), S>) -> Arc<Process<(), S>> {
+        Arc::new(Process::Do {
+            span: self.derive(span),
+            name: LocalName::result(),
+            usage: VariableUsage::Unknown,
+            typ: (),
+            command,
+        })
+    }
+}
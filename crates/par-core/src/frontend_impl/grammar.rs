@@ -0,0 +1,511 @@
+//! The grammar of the language in EBNF, for editors and tools that need to
+//! know the syntax but can't use the parser.
+//!
+//! It's written by hand next to the parser, with its rules named after the
+//! parser's functions. The tests below keep the two in step as far as they
+//! can be checked without generating programs: every rule is defined and used,
+//! is named after a function of the parser, and every token of the lexer is in
+//! the grammar.
+//!
+//! Next to the crate, `tree-sitter-par` has a tree-sitter grammar written from
+//! this one, for highlighting and folding in editors. It's tested here too, on
+//! the level of tokens: every source file in the repository must lex the same
+//! way with its tokens as with the lexer.
+
+/// The grammar of Par source files, in EBNF. The notation is described at the
+/// top.
+pub const GRAMMAR: &str = include_str!("grammar.ebnf");
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::GRAMMAR;
+    use crate::frontend_impl::lexer::{TokenKind, lex};
+    use crate::location::FileName;
+
+    const PARSER: &str = include_str!("parse.rs");
+    const LEXER: &str = include_str!("lexer.rs");
+
+    /// Rules for what's done before parsing, so the parser has no functions
+    /// for them, and nothing in the rest of the grammar refers to them.
+    const MACRO_RULES: &[&str] = &[
+        "macro_definition",
+        "macro_parameter",
+        "macro_body",
+        "macro_invocation",
+        "macro_argument",
+    ];
+
+    /// The token classes of the grammar, and the kinds of tokens they stand
+    /// for.
+    const TOKEN_CLASSES: &[(&str, TokenKind)] = &[
+        ("LOWER_IDENT", TokenKind::LowercaseIdentifier),
+        ("UPPER_IDENT", TokenKind::UppercaseIdentifier),
+        ("INTEGER", TokenKind::Integer),
+        ("FLOAT", TokenKind::Float),
+        ("STRING", TokenKind::String),
+        ("TEMPLATE_START", TokenKind::TemplateStart),
+        ("TEMPLATE_END", TokenKind::TemplateEnd),
+        ("TEMPLATE_TEXT", TokenKind::TemplateText),
+        ("TEMPLATE_STRING_START", TokenKind::TemplateStringStart),
+        ("TEMPLATE_DATA_START", TokenKind::TemplateDataStart),
+    ];
+
+    /// Kinds of tokens that only come out of malformed sources.
+    const INVALID_TOKENS: &[TokenKind] = &[
+        TokenKind::InvalidString,
+        TokenKind::InvalidChar,
+        TokenKind::Unknown,
+    ];
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    enum Symbol {
+        Rule(String),
+        Class(String),
+        Terminal(String),
+    }
+
+    /// The rules of the grammar, with the symbols each one refers to, in order.
+    fn rules() -> Vec<(String, Vec<Symbol>)> {
+        let mut rules = Vec::new();
+        let mut current: Option<(String, Vec<Symbol>)> = None;
+        let mut depth = Vec::new();
+        let mut rest = GRAMMAR;
+        while let Some(c) = rest.chars().next() {
+            if c.is_whitespace() {
+                rest = &rest[c.len_utf8()..];
+            } else if let Some(comment) = rest.strip_prefix("(*") {
+                let end = comment.find("*)").expect("unclosed comment");
+                rest = &comment[end + 2..];
+            } else if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').expect("unclosed terminal");
+                let (_, symbols) = current.as_mut().expect("terminal outside of a rule");
+                symbols.push(Symbol::Terminal(quoted[..end].to_owned()));
+                rest = &quoted[end + 1..];
+            } else if let Some(special) = rest.strip_prefix('?') {
+                let end = special.find('?').expect("unclosed special sequence");
+                rest = &special[end + 1..];
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let name = rest[..end].to_owned();
+                rest = rest[end..].trim_start();
+                match &mut current {
+                    None => {
+                        rest = rest
+                            .strip_prefix('=')
+                            .unwrap_or_else(|| panic!("expected `=` after `{name}`"));
+                        current = Some((name, Vec::new()));
+                    }
+                    Some((_, symbols)) if name.chars().all(|c| !c.is_ascii_lowercase()) => {
+                        symbols.push(Symbol::Class(name));
+                    }
+                    Some((_, symbols)) => symbols.push(Symbol::Rule(name)),
+                }
+            } else {
+                let (rule, _) = current.as_ref().expect("punctuation outside of a rule");
+                match c {
+                    '(' | '[' | '{' => depth.push(c),
+                    ')' | ']' | '}' => {
+                        let open = depth.pop();
+                        let expected = match c {
+                            ')' => '(',
+                            ']' => '[',
+                            _ => '{',
+                        };
+                        assert_eq!(open, Some(expected), "unbalanced `{c}` in `{rule}`");
+                    }
+                    '|' => {}
+                    ';' => {
+                        assert!(depth.is_empty(), "unclosed brackets in `{rule}`");
+                        rules.extend(current.take());
+                    }
+                    _ => panic!("unexpected `{c}` in `{rule}`"),
+                }
+                rest = &rest[1..];
+            }
+        }
+        assert!(current.is_none(), "the last rule isn't ended by `;`");
+        rules
+    }
+
+    fn terminals() -> BTreeSet<String> {
+        rules()
+            .into_iter()
+            .flat_map(|(_, symbols)| symbols)
+            .filter_map(|symbol| match symbol {
+                Symbol::Terminal(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_rule_is_defined_once_and_used() {
+        let rules = rules();
+        let mut defined = BTreeMap::new();
+        for (name, symbols) in &rules {
+            assert!(
+                defined.insert(name.as_str(), symbols).is_none(),
+                "`{name}` is defined twice"
+            );
+        }
+
+        let mut reachable = BTreeSet::new();
+        let mut pending = vec!["source_file"];
+        pending.extend(MACRO_RULES);
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name) {
+                continue;
+            }
+            let symbols = defined
+                .get(name)
+                .unwrap_or_else(|| panic!("`{name}` is used, but never defined"));
+            for symbol in symbols.iter() {
+                if let Symbol::Rule(name) = symbol {
+                    pending.push(name);
+                }
+            }
+        }
+        for name in defined.keys() {
+            assert!(reachable.contains(name), "`{name}` is never used");
+        }
+    }
+
+    #[test]
+    fn rules_are_named_after_parser_functions() {
+        for (name, _) in rules() {
+            if MACRO_RULES.contains(&name.as_str()) {
+                continue;
+            }
+            assert!(
+                PARSER.contains(&format!("fn {name}(")) || PARSER.contains(&format!("fn {name}<")),
+                "`{name}` isn't a function of the parser"
+            );
+        }
+    }
+
+    #[test]
+    fn terminals_are_single_tokens() {
+        let file = FileName::from("grammar.par");
+        for text in terminals() {
+            let tokens = lex(&text, &file);
+            assert_eq!(tokens.len(), 1, "`{text}` isn't a single token");
+            let kind = tokens[0].kind;
+            assert!(!INVALID_TOKENS.contains(&kind), "`{text}` isn't a token");
+            // Names with a meaning in some places, like the constraint `data`.
+            if kind == TokenKind::LowercaseIdentifier {
+                continue;
+            }
+            assert_eq!(kind.expected(), text, "`{text}` lexes as {kind:?}");
+        }
+        for (_, symbols) in rules() {
+            for symbol in symbols {
+                if let Symbol::Class(class) = symbol {
+                    assert!(
+                        TOKEN_CLASSES.iter().any(|(name, _)| *name == class),
+                        "`{class}` isn't a token class"
+                    );
+                }
+            }
+        }
+    }
+
+    /// The text of every kind of token spelled one way, from the arms of
+    /// `TokenKind::expected`, which lists every kind of token.
+    fn spelled_tokens() -> Vec<&'static str> {
+        let classes = TOKEN_CLASSES
+            .iter()
+            .map(|(_, kind)| format!("{kind:?}"))
+            .chain(INVALID_TOKENS.iter().map(|kind| format!("{kind:?}")))
+            .collect::<BTreeSet<_>>();
+        let expected = LEXER
+            .lines()
+            .filter_map(|line| {
+                let (kind, text) = line
+                    .trim()
+                    .strip_prefix("TokenKind::")?
+                    .split_once(" => ")?;
+                let text = text.strip_prefix('"')?.strip_suffix("\",")?;
+                Some((kind, text))
+            })
+            .collect::<Vec<_>>();
+        assert!(expected.len() > classes.len());
+        expected
+            .into_iter()
+            .filter(|(kind, _)| !classes.contains(*kind))
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn every_token_is_in_the_grammar() {
+        let terminals = terminals();
+        for text in spelled_tokens() {
+            assert!(
+                terminals.contains(text),
+                "the token `{text}` isn't in the grammar"
+            );
+        }
+    }
+
+    mod tree_sitter {
+        use std::collections::BTreeSet;
+        use std::fs;
+        use std::path::{Path, PathBuf};
+
+        use regex::Regex;
+
+        use super::spelled_tokens;
+        use crate::frontend_impl::lexer::{
+            Edition, Lexeme, Lexer, TokenKind, lex, lex_with_comments,
+        };
+        use crate::location::{FileName, Span};
+
+        const GRAMMAR: &str = include_str!("../../tree-sitter-par/grammar.js");
+        const HIGHLIGHTS: &str = include_str!("../../tree-sitter-par/queries/highlights.scm");
+        const FOLDS: &str = include_str!("../../tree-sitter-par/queries/folds.scm");
+        const TREE_SITTER_JSON: &str = include_str!("../../tree-sitter-par/tree-sitter.json");
+        const PACKAGE_JSON: &str = include_str!("../../tree-sitter-par/package.json");
+
+        /// The rules of the tree-sitter grammar matching the classes of tokens.
+        const TOKEN_RULES: &[(TokenKind, &str)] = &[
+            (TokenKind::LowercaseIdentifier, "lower_name"),
+            (TokenKind::UppercaseIdentifier, "upper_name"),
+            (TokenKind::Integer, "integer"),
+            (TokenKind::Float, "float"),
+            (TokenKind::String, "string"),
+            (TokenKind::TemplateText, "template_text"),
+        ];
+
+        /// Tokens only lexed inside of template strings.
+        const TEMPLATE_TOKENS: &[&str] = &["`", "${", "#{"];
+
+        /// The grammar without its comments, which are all on lines of their
+        /// own.
+        fn grammar_code() -> String {
+            let mut code = String::new();
+            let mut in_comment = false;
+            for line in GRAMMAR.lines() {
+                let trimmed = line.trim_start();
+                if in_comment || trimmed.starts_with("/*") {
+                    in_comment = !trimmed.contains("*/");
+                } else if !trimmed.starts_with("//") {
+                    code.push_str(line);
+                    code.push('\n');
+                }
+            }
+            code
+        }
+
+        /// The tokens spelled out in the grammar, which it writes in single
+        /// quotes, like the names of fields and of the grammar.
+        fn literals() -> BTreeSet<String> {
+            let quoted = Regex::new(r"(field\(|name: )?'([^']*)'").unwrap();
+            quoted
+                .captures_iter(&grammar_code())
+                .filter(|captures| captures.get(1).is_none())
+                .map(|captures| captures[2].to_owned())
+                .collect()
+        }
+
+        /// The names of the rules of the grammar, and the external tokens.
+        fn rule_names() -> BTreeSet<String> {
+            let definition = Regex::new(r"(?m)^    ([a-z_]+): [$_] =>").unwrap();
+            let mut names = definition
+                .captures_iter(GRAMMAR)
+                .map(|captures| captures[1].to_owned())
+                .collect::<BTreeSet<_>>();
+            let externals = Regex::new(r"externals: \$ => \[([^\]]*)\]").unwrap();
+            let externals = externals.captures(GRAMMAR).expect("no externals");
+            names.extend(
+                externals[1]
+                    .split(',')
+                    .map(|name| name.trim().trim_start_matches("$.").to_owned()),
+            );
+            names
+        }
+
+        /// The regular expression a token rule is matched by, anchored at both
+        /// ends. Tree-sitter reads them with the same syntax as `regex`.
+        fn token_regex(rule: &str) -> Regex {
+            let line = GRAMMAR
+                .lines()
+                .find(|line| line.trim_start().starts_with(&format!("{rule}: _ =>")))
+                .unwrap_or_else(|| panic!("`{rule}` isn't a rule of the grammar"));
+            let (_, body) = line.split_once("=>").unwrap();
+            let start = body.find('/').expect("not a regular expression") + 1;
+            let end = body.rfind('/').expect("not a regular expression");
+            Regex::new(&format!("^(?:{})$", &body[start..end])).unwrap()
+        }
+
+        fn source_files(directory: &Path, files: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(directory).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    source_files(&path, files);
+                } else if path.extension().is_some_and(|extension| extension == "par") {
+                    files.push(path);
+                }
+            }
+        }
+
+        fn repository_source_files() -> Vec<PathBuf> {
+            let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+            let mut files = Vec::new();
+            for directory in [
+                "examples",
+                "playground-examples",
+                "tests",
+                "crates/par-builtin/packages",
+            ] {
+                source_files(&root.join(directory), &mut files);
+            }
+            assert!(files.len() > 50, "only found {} files", files.len());
+            files
+        }
+
+        fn text_of<'a>(source: &'a str, span: &Span) -> &'a str {
+            let start = span.start().unwrap().offset as usize;
+            let end = span.end().unwrap().offset as usize;
+            &source[start..end]
+        }
+
+        #[test]
+        fn every_file_lexes_the_same_with_the_grammar() {
+            let literals = literals();
+            let token_rules = TOKEN_RULES
+                .iter()
+                .map(|(kind, rule)| (*kind, token_regex(rule)))
+                .collect::<Vec<_>>();
+            let line_comment = token_regex("line_comment");
+
+            for path in repository_source_files() {
+                let source = fs::read_to_string(&path).unwrap();
+                let file = FileName::from(path.display().to_string());
+                let lexed = lex_with_comments(&source, &file);
+                for token in &lexed.tokens {
+                    let text = text_of(&source, &token.span);
+                    let place = format!("`{text}` in {}", path.display());
+                    match token_rules.iter().find(|(kind, _)| *kind == token.kind) {
+                        Some((_, regex)) => {
+                            assert!(regex.is_match(text), "{place} doesn't match its rule")
+                        }
+                        None => assert!(literals.contains(text), "{place} isn't in the grammar"),
+                    }
+                }
+                for comment in &lexed.comments {
+                    let text = text_of(&source, &comment.span);
+                    assert!(
+                        line_comment.is_match(text)
+                            || (text.starts_with("/*") && text.ends_with("*/")),
+                        "the comment `{text}` in {} isn't one for the grammar",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn every_token_is_in_the_grammar() {
+            let literals = literals();
+            for text in spelled_tokens() {
+                assert!(
+                    literals.contains(text),
+                    "the token `{text}` isn't in the tree-sitter grammar"
+                );
+            }
+        }
+
+        #[test]
+        fn literals_are_single_tokens() {
+            let file = FileName::from("grammar.js");
+            let lower_name = token_regex("lower_name");
+            for text in literals() {
+                if TEMPLATE_TOKENS.contains(&text.as_str()) {
+                    continue;
+                }
+                // In the latest edition, which has all the keywords.
+                let tokens = Lexer::with_edition(&text, file.clone(), Edition::LATEST)
+                    .filter_map(|lexeme| match lexeme {
+                        Lexeme::Token(token) => Some(token),
+                        Lexeme::Comment(_) => None,
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(tokens.len(), 1, "`{text}` isn't a single token");
+                let kind = tokens[0].kind;
+                assert_eq!(kind.expected(), text, "`{text}` lexes as {kind:?}");
+                // Tree-sitter reads these as keywords, so the lexer has to too.
+                if lower_name.is_match(&text) {
+                    assert_ne!(kind, TokenKind::LowercaseIdentifier);
+                }
+            }
+        }
+
+        #[test]
+        fn rules_refer_to_defined_rules() {
+            let names = rule_names();
+            let reference = Regex::new(r"\$\.([a-z_]+)").unwrap();
+            for captures in reference.captures_iter(&grammar_code()) {
+                assert!(
+                    names.contains(&captures[1]),
+                    "`{}` isn't a rule of the tree-sitter grammar",
+                    &captures[1]
+                );
+            }
+        }
+
+        #[test]
+        fn queries_refer_to_the_grammar() {
+            let names = rule_names();
+            let literals = literals();
+            let fields = Regex::new(r"field\('([a-z_]+)'")
+                .unwrap()
+                .captures_iter(GRAMMAR)
+                .map(|captures| captures[1].to_owned())
+                .collect::<BTreeSet<_>>();
+            let quoted = Regex::new(r#""((?:[^"\\]|\\.)*)""#).unwrap();
+            let node = Regex::new(r"\(([a-z_]+)").unwrap();
+            let field = Regex::new(r"([a-z_]+):").unwrap();
+
+            for (query, source) in [("highlights", HIGHLIGHTS), ("folds", FOLDS)] {
+                let code = source
+                    .lines()
+                    .filter(|line| !line.trim_start().starts_with(';'))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                for captures in quoted.captures_iter(&code) {
+                    let text = captures[1].replace("\\\"", "\"");
+                    assert!(
+                        literals.contains(&text),
+                        "`{text}` in {query} isn't in the grammar"
+                    );
+                }
+                let code = quoted.replace_all(&code, "");
+                for captures in node.captures_iter(&code) {
+                    let name = &captures[1];
+                    assert!(
+                        !name.starts_with('_') && names.contains(name),
+                        "`{name}` in {query} isn't a node of the grammar"
+                    );
+                }
+                for captures in field.captures_iter(&code) {
+                    assert!(
+                        fields.contains(&captures[1]),
+                        "`{}` in {query} isn't a field of the grammar",
+                        &captures[1]
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn grammar_is_versioned_with_the_crate() {
+            let version = format!("\"version\": \"{}\"", env!("CARGO_PKG_VERSION"));
+            assert!(TREE_SITTER_JSON.contains(&version));
+            assert!(PACKAGE_JSON.contains(&version));
+        }
+    }
+}
@@ -8,12 +8,14 @@ use std::{
 };
 
 use super::{
+    desugar::Expander,
     process::{self, Captures},
     types::Type,
 };
 use crate::frontend_impl::process::VariableUsage;
+use crate::stack;
 use crate::{
-    frontend_impl::types::error::labels_from_span,
+    frontend_impl::types::error::{labels_from_span, source_of},
     location::{Span, Spanning},
 };
 use arcstr::{ArcStr, literal};
@@ -117,8 +119,15 @@ impl Spanning for TypeParameter {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BuiltinOperatorModule {
+    Bool,
+    Bytes,
+    Codec,
     Data,
+    Float,
+    Json,
+    List,
     Number,
+    Ordering,
     String,
 }
 
@@ -486,6 +495,9 @@ pub enum Apply<S> {
     Try(Span, Option<LocalName>, Box<Self>),
     Default(Span, Box<Expression<S>>, Box<Self>),
     Pipe(Span, Box<Expression<S>>, Box<Self>),
+    /// Arguments passed by name, as in `F(count: 3, label: "x")`. Lowering
+    /// puts them in the order of the definition's declaration.
+    Named(Span, Vec<(LocalName, Expression<S>)>, Box<Self>),
 }
 
 #[derive(Clone, Debug)]
@@ -569,6 +581,9 @@ pub enum Command<S> {
         span: Span,
         unfounded: bool,
         label: Option<LocalName>,
+        /// The subjects given after `&`, which loops may make smaller
+        /// instead of this one.
+        subjects: Vec<LocalName>,
         then: Box<Self>,
     },
     Loop(Span, Option<LocalName>),
@@ -641,10 +656,19 @@ impl Display for Resolved {
                     }
                 }
             }
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Bool) => write!(f, "<builtin-bool>"),
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Bytes) => write!(f, "<builtin-bytes>"),
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Codec) => write!(f, "<builtin-codec>"),
             Resolved::BuiltinOperator(BuiltinOperatorModule::Data) => write!(f, "<builtin-data>"),
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Float) => write!(f, "<builtin-float>"),
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Json) => write!(f, "<builtin-json>"),
+            Resolved::BuiltinOperator(BuiltinOperatorModule::List) => write!(f, "<builtin-list>"),
             Resolved::BuiltinOperator(BuiltinOperatorModule::Number) => {
                 write!(f, "<builtin-number>")
             }
+            Resolved::BuiltinOperator(BuiltinOperatorModule::Ordering) => {
+                write!(f, "<builtin-ordering>")
+            }
             Resolved::BuiltinOperator(BuiltinOperatorModule::String) => {
                 write!(f, "<builtin-string>")
             }
@@ -659,12 +683,33 @@ impl Display for Unresolved {
                 Some(qualifier) => write!(f, "{qualifier}"),
                 None => Ok(()),
             },
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Bool) => {
+                write!(f, "<builtin-bool>")
+            }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Bytes) => {
+                write!(f, "<builtin-bytes>")
+            }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Codec) => {
+                write!(f, "<builtin-codec>")
+            }
             Unresolved::BuiltinOperator(BuiltinOperatorModule::Data) => {
                 write!(f, "<builtin-data>")
             }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Float) => {
+                write!(f, "<builtin-float>")
+            }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Json) => {
+                write!(f, "<builtin-json>")
+            }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::List) => {
+                write!(f, "<builtin-list>")
+            }
             Unresolved::BuiltinOperator(BuiltinOperatorModule::Number) => {
                 write!(f, "<builtin-number>")
             }
+            Unresolved::BuiltinOperator(BuiltinOperatorModule::Ordering) => {
+                write!(f, "<builtin-ordering>")
+            }
             Unresolved::BuiltinOperator(BuiltinOperatorModule::String) => {
                 write!(f, "<builtin-string>")
             }
@@ -729,6 +774,7 @@ pub enum CompileError {
     NoMatchingCatch(Span),
     MatchingCatchDisabled(Span, CatchDisabledReason),
     NoSuchPollPoint(Span, Option<LocalName>),
+    BadNamedArguments(Span, NamedArgumentsError),
 }
 
 #[derive(Clone, Debug)]
@@ -737,6 +783,17 @@ pub enum CatchDisabledReason {
     ValuePartiallyConstructed,
 }
 
+#[derive(Clone, Debug)]
+pub enum NamedArgumentsError {
+    /// Passed to something other than the name of a definition.
+    NotADefinition,
+    /// Passed to a definition whose declaration doesn't name its arguments.
+    NotDeclared(String),
+    Unknown(LocalName),
+    Duplicate(LocalName),
+    Missing(LocalName),
+}
+
 impl Spanning for CompileError {
     fn span(&self) -> Span {
         match self {
@@ -745,6 +802,7 @@ impl Spanning for CompileError {
             Self::NoMatchingCatch(span) => span.clone(),
             Self::MatchingCatchDisabled(span, _) => span.clone(),
             Self::NoSuchPollPoint(span, _) => span.clone(),
+            Self::BadNamedArguments(span, _) => span.clone(),
         }
     }
 }
@@ -758,7 +816,7 @@ impl CompileError {
             } else {
                 Arc::clone(&source_code)
             };
-            miette::miette! { labels = labels, "{}", msg }.with_source_code(code)
+            miette::miette! { labels = labels, "{}", msg }.with_source_code(source_of(span, code))
         };
         let mk_report_owned = |span: &Span, msg: String| {
             let labels = labels_from_span(&source_code, span);
@@ -767,7 +825,7 @@ impl CompileError {
             } else {
                 Arc::clone(&source_code)
             };
-            miette::miette! { labels = labels, "{}", msg }.with_source_code(code)
+            miette::miette! { labels = labels, "{}", msg }.with_source_code(source_of(span, code))
         };
 
         match self {
@@ -790,6 +848,27 @@ impl CompileError {
                 span,
                 format!("No such `poll@...`/`repoll@...` label `@{label}` is in scope here."),
             ),
+            Self::BadNamedArguments(span, NamedArgumentsError::NotADefinition) => mk_report(
+                span,
+                "Arguments can only be passed by name right after the name of a definition.",
+            ),
+            Self::BadNamedArguments(span, NamedArgumentsError::NotDeclared(name)) => {
+                mk_report_owned(
+                    span,
+                    format!(
+                        "`{name}` isn't declared with named arguments, like `dec {name} : [count: Nat] ...`."
+                    ),
+                )
+            }
+            Self::BadNamedArguments(span, NamedArgumentsError::Unknown(name)) => {
+                mk_report_owned(span, format!("No argument is named `{name}`."))
+            }
+            Self::BadNamedArguments(span, NamedArgumentsError::Duplicate(name)) => {
+                mk_report_owned(span, format!("The argument `{name}` is passed twice."))
+            }
+            Self::BadNamedArguments(span, NamedArgumentsError::Missing(name)) => {
+                mk_report_owned(span, format!("The argument `{name}` is missing."))
+            }
         }
     }
 }
@@ -798,8 +877,13 @@ impl CompileError {
 pub(crate) struct Context {
     passes: Passes,
     original_object_name: Option<LocalName>,
+    declared_parameters: Arc<DeclaredParameters>,
 }
 
+/// The names declarations give to the arguments of definitions, by the names
+/// the code being lowered refers to them with.
+pub(crate) type DeclaredParameters = HashMap<GlobalName<Unresolved>, Vec<LocalName>>;
+
 #[derive(Clone, Debug)]
 pub(crate) struct Passes {
     next_block_index: usize,
@@ -833,10 +917,11 @@ struct Pass {
 }
 
 impl Context {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn with_declared_parameters(declared_parameters: Arc<DeclaredParameters>) -> Self {
         Self {
             passes: Passes::new(),
             original_object_name: None,
+            declared_parameters,
         }
     }
 
@@ -1487,7 +1572,12 @@ impl Context {
             annotation: pattern.annotation(),
             typ: (),
             value: expression,
-            then: self.compile_pattern_helper(pattern, 0, process)?,
+            then: self.compile_pattern_helper(
+                &Expander::new("pattern", &pattern.span()),
+                pattern,
+                0,
+                process,
+            )?,
         }))
     }
 
@@ -1515,7 +1605,12 @@ impl Context {
             chan_annotation: None,
             chan_type: (),
             expr_type: (),
-            process: self.compile_pattern_helper(pattern, 0, process)?,
+            process: self.compile_pattern_helper(
+                &Expander::new("pattern", &pattern.span()),
+                pattern,
+                0,
+                process,
+            )?,
         }))
     }
 
@@ -1551,7 +1646,12 @@ impl Context {
                 (),
                 VariableUsage::Unknown,
             )),
-            then: self.compile_pattern_helper(pattern, 0, block)?,
+            then: self.compile_pattern_helper(
+                &Expander::new("pattern", &pattern.span()),
+                pattern,
+                0,
+                block,
+            )?,
         }))
     }
 
@@ -1588,26 +1688,36 @@ impl Context {
                 LocalName::match_(level),
                 pattern.annotation(),
                 (),
-                self.compile_pattern_helper(pattern, level, process)?,
+                self.compile_pattern_helper(
+                    &Expander::new("pattern", &pattern.span()),
+                    pattern,
+                    level,
+                    process,
+                )?,
                 vars,
             ),
         }))
     }
 
+    /// Destructures `pattern` part by part, in order. None of the patterns
+    /// test a variant, so each one matches, and there are no tests for a
+    /// decision tree to share; compiling them to one waits on nested either
+    /// patterns.
     fn compile_pattern_helper(
         &mut self,
+        expander: &Expander,
         pattern: &Pattern<Unresolved>,
         level: usize,
         process: Arc<process::Process<(), Unresolved>>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         match pattern {
             Pattern::Name(span, name, annotation) => Ok(Arc::new(process::Process::Let {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: name.clone(),
                 annotation: annotation.clone(),
                 typ: (),
                 value: Arc::new(process::Expression::Variable(
-                    span.clone(),
+                    expander.derive(span),
                     LocalName::match_(level),
                     (),
                     VariableUsage::Unknown,
@@ -1616,19 +1726,34 @@ impl Context {
             })),
 
             Pattern::Receive(span, first, rest, vars) => {
-                let then_process = self.compile_pattern_helper(rest, level, process)?;
-                self.compile_pattern_receive(
-                    first,
-                    level + 1,
-                    span,
-                    &LocalName::match_(level),
-                    then_process,
-                    vars.clone(),
-                )
+                let then_process = self.compile_pattern_helper(expander, rest, level, process)?;
+                let (name, annotation, then_process) = match first.as_ref() {
+                    Pattern::Name(_, name, annotation) => {
+                        (name.clone(), annotation.clone(), then_process)
+                    }
+                    _ => (
+                        LocalName::match_(level + 1),
+                        first.annotation(),
+                        self.compile_pattern_helper(expander, first, level + 1, then_process)?,
+                    ),
+                };
+                Ok(Arc::new(process::Process::Do {
+                    span: expander.derive(span),
+                    name: LocalName::match_(level),
+                    usage: VariableUsage::Unknown,
+                    typ: (),
+                    command: process::Command::Receive(
+                        name,
+                        annotation,
+                        (),
+                        then_process,
+                        vars.clone(),
+                    ),
+                }))
             }
 
             Pattern::Continue(span) => Ok(Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::match_(level),
                 usage: VariableUsage::Unknown,
                 typ: (),
@@ -1636,13 +1761,13 @@ impl Context {
             })),
 
             Pattern::ReceiveType(span, parameter, rest) => Ok(Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::match_(level),
                 usage: VariableUsage::Unknown,
                 typ: (),
                 command: process::Command::ReceiveType(
                     parameter.clone(),
-                    self.compile_pattern_helper(rest, level, process)?,
+                    self.compile_pattern_helper(expander, rest, level, process)?,
                 ),
             })),
 
@@ -1665,13 +1790,13 @@ impl Context {
                 } else {
                     catch_block
                 };
-                let then_process = self.compile_pattern_helper(rest, level, process)?;
+                let then_process = self.compile_pattern_helper(expander, rest, level, process)?;
                 Ok(self.compile_try(span, LocalName::match_(level), catch_block, then_process))
             }
 
             Pattern::Default(span, expr, rest) => {
                 let default_expr = self.compile_expression(expr)?;
-                let ok_process = self.compile_pattern_helper(rest, level, process)?;
+                let ok_process = self.compile_pattern_helper(expander, rest, level, process)?;
                 Ok(self.compile_default(span, LocalName::match_(level), default_expr, ok_process))
             }
         }
@@ -1680,6 +1805,13 @@ impl Context {
     pub(crate) fn compile_expression(
         &mut self,
         expr: &Expression<Unresolved>,
+    ) -> Result<Arc<process::Expression<(), Unresolved>>, CompileError> {
+        stack::grow(|| self.compile_expression_inner(expr))
+    }
+
+    fn compile_expression_inner(
+        &mut self,
+        expr: &Expression<Unresolved>,
     ) -> Result<Arc<process::Expression<(), Unresolved>>, CompileError> {
         let original_name = std::mem::take(&mut self.original_object_name);
         let res = Ok(match expr {
@@ -1695,59 +1827,17 @@ impl Context {
             }
 
             Expression::List(span, items) => {
-                let mut process = Arc::new(process::Process::Do {
-                    span: span.clone(),
-                    name: LocalName::result(),
-                    usage: VariableUsage::Unknown,
-                    typ: (),
-                    command: process::Command::Signal(
-                        LocalName {
-                            span: span.clone(),
-                            string: literal!("end"),
-                        },
-                        Arc::new(process::Process::Do {
-                            span: span.clone(),
-                            name: LocalName::result(),
-                            usage: VariableUsage::Unknown,
-                            typ: (),
-                            command: process::Command::Break,
-                        }),
-                    ),
-                });
+                let expander = Expander::new("list literal", span);
+                let mut process = expander.signal(span, "end", expander.break_(span));
                 for item in items.iter().rev() {
                     let span = item.span();
-                    process = Arc::new(process::Process::Do {
-                        span: span.clone(),
-                        name: LocalName::result(),
-                        usage: VariableUsage::Unknown,
-                        typ: (),
-                        command: process::Command::Signal(
-                            LocalName {
-                                span: span.clone(),
-                                string: literal!("item"),
-                            },
-                            Arc::new(process::Process::Do {
-                                span,
-                                name: LocalName::result(),
-                                usage: VariableUsage::Unknown,
-                                typ: (),
-                                command: process::Command::Send(
-                                    self.compile_expression(item)?,
-                                    process,
-                                ),
-                            }),
-                        ),
-                    });
+                    process = expander.signal(
+                        &span,
+                        "item",
+                        expander.send(&span, self.compile_expression(item)?, process),
+                    );
                 }
-                Arc::new(process::Expression::Chan {
-                    span: span.clone(),
-                    captures: Captures::new(),
-                    chan_name: LocalName::result(),
-                    chan_annotation: None,
-                    chan_type: (),
-                    expr_type: (),
-                    process,
-                })
+                expander.chan(process)
             }
 
             Expression::Global(span, name) => {
@@ -2052,22 +2142,15 @@ impl Context {
                 branches,
                 else_,
             } => {
+                let expander = Expander::new("`if`", span);
                 let else_proc = match else_ {
                     Some(expr) => self.compile_process(&link_process_from_expr(expr))?,
-                    None => Arc::new(process::Process::Unreachable(span.clone())),
+                    None => expander.unreachable(),
                 };
                 let compiled = self.compile_if_branches(branches, else_proc, |body, pass| {
                     pass.compile_process(&link_process_from_expr(body))
                 })?;
-                Arc::new(process::Expression::Chan {
-                    span: span.clone(),
-                    captures: Captures::new(),
-                    chan_name: LocalName::result(),
-                    chan_annotation: None,
-                    chan_type: (),
-                    expr_type: (),
-                    process: compiled,
-                })
+                expander.chan(compiled)
             }
 
             Expression::Do {
@@ -2111,11 +2194,12 @@ impl Context {
             }
 
             Expression::Construction(span, construct) => {
+                let expander = Expander::new("construction", span);
                 self.disable_catches(CatchDisabledReason::ValuePartiallyConstructed);
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(&expander, construct)?;
                 self.enable_catches();
                 Arc::new(process::Expression::Chan {
-                    span: span.clone(),
+                    span: expander.span(),
                     captures: Captures::new(),
                     chan_name: LocalName::result(),
                     chan_annotation: None,
@@ -2127,18 +2211,28 @@ impl Context {
 
             Expression::Application(_, expr, Apply::Noop(_)) => self.compile_expression(expr)?,
 
+            Expression::Application(span, expr, Apply::Named(named_span, arguments, apply)) => {
+                let apply = self.order_named_arguments(expr, named_span, arguments, apply)?;
+                self.compile_expression(&Expression::Application(
+                    span.clone(),
+                    expr.clone(),
+                    apply,
+                ))?
+            }
+
             Expression::Application(span, expr, apply) => {
+                let expander = Expander::new("application", span);
                 let expr = self.compile_expression(expr)?;
-                let process = self.compile_apply(apply)?;
+                let process = self.compile_apply(&expander, apply)?;
                 Arc::new(process::Expression::Chan {
-                    span: span.clone(),
+                    span: expander.span(),
                     captures: Captures::new(),
                     chan_name: LocalName::result(),
                     chan_annotation: None,
                     chan_type: (),
                     expr_type: (),
                     process: Arc::new(process::Process::Let {
-                        span: span.clone(),
+                        span: expander.span(),
                         name: LocalName::object(),
                         annotation: None,
                         typ: (),
@@ -2158,6 +2252,7 @@ impl Context {
 
     pub(crate) fn compile_construct(
         &mut self,
+        expander: &Expander,
         construct: &Construct<Unresolved>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         Ok(match construct {
@@ -2174,9 +2269,9 @@ impl Context {
 
             Construct::Send(span, argument, construct) => {
                 let argument = self.compile_expression(argument)?;
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2185,11 +2280,11 @@ impl Context {
             }
 
             Construct::Receive(span, pattern, construct, vars) => {
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 self.compile_pattern_receive(
                     pattern,
                     0,
-                    span,
+                    &expander.derive(span),
                     &LocalName::result(),
                     process,
                     vars.clone(),
@@ -2197,9 +2292,9 @@ impl Context {
             }
 
             Construct::Signal(span, chosen, construct) => {
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2212,16 +2307,16 @@ impl Context {
                 let mut processes = Vec::new();
                 for (branch_name, construct_branch) in construct_branches {
                     branches.push(branch_name.clone());
-                    processes.push(self.compile_construct_branch(construct_branch)?);
+                    processes.push(self.compile_construct_branch(expander, construct_branch)?);
                 }
                 let else_process = match else_branch {
-                    Some(branch) => Some(self.compile_construct_branch(branch)?),
+                    Some(branch) => Some(self.compile_construct_branch(expander, branch)?),
                     None => None,
                 };
                 let branches = Arc::from(branches);
                 let processes = Box::from(processes);
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2230,7 +2325,7 @@ impl Context {
             }
 
             Construct::Break(span) => Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::result(),
                 usage: VariableUsage::Unknown,
                 typ: (),
@@ -2243,15 +2338,16 @@ impl Context {
                 label,
                 then: construct,
             } => {
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
                     command: process::Command::Begin {
                         unfounded: *unfounded,
                         label: label.clone(),
+                        subjects: Arc::from([]),
                         captures: Captures::new(),
                         body: process,
                     },
@@ -2259,7 +2355,7 @@ impl Context {
             }
 
             Construct::Loop(span, label) => Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::result(),
                 usage: VariableUsage::Unknown,
                 typ: (),
@@ -2271,9 +2367,9 @@ impl Context {
             }),
 
             Construct::SendType(span, argument, construct) => {
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2282,9 +2378,9 @@ impl Context {
             }
 
             Construct::ReceiveType(span, parameter, construct) => {
-                let process = self.compile_construct(construct)?;
+                let process = self.compile_construct(expander, construct)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2296,6 +2392,7 @@ impl Context {
 
     pub(crate) fn compile_construct_branch(
         &mut self,
+        expander: &Expander,
         branch: &ConstructBranch<Unresolved>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         Ok(match branch {
@@ -2311,11 +2408,11 @@ impl Context {
             }
 
             ConstructBranch::Receive(span, pattern, branch, vars) => {
-                let process = self.compile_construct_branch(branch)?;
+                let process = self.compile_construct_branch(expander, branch)?;
                 self.compile_pattern_receive(
                     pattern,
                     0,
-                    span,
+                    &expander.derive(span),
                     &LocalName::result(),
                     process,
                     vars.clone(),
@@ -2323,9 +2420,9 @@ impl Context {
             }
 
             ConstructBranch::ReceiveType(span, parameter, branch) => {
-                let process = self.compile_construct_branch(branch)?;
+                let process = self.compile_construct_branch(expander, branch)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::result(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2337,16 +2434,17 @@ impl Context {
 
     pub(crate) fn compile_apply(
         &mut self,
+        expander: &Expander,
         apply: &Apply<Unresolved>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         Ok(match apply {
             Apply::Noop(span) => Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::result(),
                 usage: VariableUsage::Unknown,
                 typ: (),
                 command: process::Command::Link(Arc::new(process::Expression::Variable(
-                    span.clone(),
+                    expander.derive(span),
                     LocalName::object(),
                     (),
                     VariableUsage::Unknown,
@@ -2355,9 +2453,9 @@ impl Context {
 
             Apply::Send(span, expression, apply) => {
                 let expression = self.compile_expression(expression)?;
-                let process = self.compile_apply(apply)?;
+                let process = self.compile_apply(expander, apply)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2366,9 +2464,9 @@ impl Context {
             }
 
             Apply::Signal(span, chosen, apply) => {
-                let process = self.compile_apply(apply)?;
+                let process = self.compile_apply(expander, apply)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2381,16 +2479,16 @@ impl Context {
                 let mut processes = Vec::new();
                 for (branch_name, expression_branch) in expression_branches {
                     branches.push(branch_name.clone());
-                    processes.push(self.compile_apply_branch(expression_branch)?);
+                    processes.push(self.compile_apply_branch(expander, expression_branch)?);
                 }
                 let else_process = match else_branch {
-                    Some(branch) => Some(self.compile_apply_branch(branch)?),
+                    Some(branch) => Some(self.compile_apply_branch(expander, branch)?),
                     None => None,
                 };
                 let branches = Arc::from(branches);
                 let processes = Box::from(processes);
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2404,15 +2502,16 @@ impl Context {
                 label,
                 then: apply,
             } => {
-                let process = self.compile_apply(apply)?;
+                let process = self.compile_apply(expander, apply)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
                     command: process::Command::Begin {
                         unfounded: *unfounded,
                         label: label.clone(),
+                        subjects: Arc::from([]),
                         captures: Captures::new(),
                         body: process,
                     },
@@ -2420,7 +2519,7 @@ impl Context {
             }
 
             Apply::Loop(span, label) => Arc::new(process::Process::Do {
-                span: span.clone(),
+                span: expander.derive(span),
                 name: LocalName::object(),
                 usage: VariableUsage::Unknown,
                 typ: (),
@@ -2432,9 +2531,9 @@ impl Context {
             }),
 
             Apply::SendType(span, argument, apply) => {
-                let process = self.compile_apply(apply)?;
+                let process = self.compile_apply(expander, apply)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2444,44 +2543,87 @@ impl Context {
 
             Apply::Default(span, expr, apply) => {
                 let default_expr = self.compile_expression(expr)?;
-                let ok_process = self.compile_apply(apply)?;
+                let ok_process = self.compile_apply(expander, apply)?;
                 self.compile_default(span, LocalName::object(), default_expr, ok_process)
             }
 
             Apply::Try(span, label, apply) => {
                 let catch_block = self.use_catch(span, label)?;
-                let ok_process = self.compile_apply(apply)?;
+                let ok_process = self.compile_apply(expander, apply)?;
                 self.compile_try(span, LocalName::object(), catch_block, ok_process)
             }
 
             Apply::Pipe(span, function, apply) => {
                 let function = self.compile_expression(function)?;
-                let then = self.compile_apply(apply)?;
+                let then = self.compile_apply(expander, apply)?;
                 self.compile_pipe(span, LocalName::object(), function, then)
             }
+
+            Apply::Named(span, _, _) => {
+                return Err(CompileError::BadNamedArguments(
+                    span.clone(),
+                    NamedArgumentsError::NotADefinition,
+                ));
+            }
         })
     }
 
+    /// Turns arguments passed by name to `callee` into ones passed in the
+    /// order its declaration names them.
+    fn order_named_arguments(
+        &self,
+        callee: &Expression<Unresolved>,
+        span: &Span,
+        arguments: &[(LocalName, Expression<Unresolved>)],
+        then: &Apply<Unresolved>,
+    ) -> Result<Apply<Unresolved>, CompileError> {
+        let error = |span: &Span, error| Err(CompileError::BadNamedArguments(span.clone(), error));
+        let Expression::Global(_, name) = callee else {
+            return error(span, NamedArgumentsError::NotADefinition);
+        };
+        let Some(parameters) = self.declared_parameters.get(name) else {
+            return error(span, NamedArgumentsError::NotDeclared(name.to_string()));
+        };
+
+        let mut given = HashMap::new();
+        for (name, argument) in arguments {
+            if !parameters.contains(name) {
+                return error(&name.span, NamedArgumentsError::Unknown(name.clone()));
+            }
+            if given.insert(name, argument).is_some() {
+                return error(&name.span, NamedArgumentsError::Duplicate(name.clone()));
+            }
+        }
+        if let Some(missing) = parameters.iter().find(|name| !given.contains_key(name)) {
+            return error(span, NamedArgumentsError::Missing(missing.clone()));
+        }
+
+        Ok(parameters.iter().rfold(then.clone(), |then, name| {
+            Apply::Send(span.clone(), Box::new(given[name].clone()), Box::new(then))
+        }))
+    }
+
     pub(crate) fn compile_apply_branch(
         &mut self,
+        expander: &Expander,
         branch: &ApplyBranch<Unresolved>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         Ok(match branch {
             ApplyBranch::Then(span, name, expression) => {
                 let expression = self.compile_expression(expression)?;
                 Arc::new(process::Process::Let {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: name.clone(),
                     annotation: None,
                     typ: (),
                     value: Arc::new(process::Expression::Variable(
-                        span.clone(),
+                        expander.derive(span),
                         LocalName::object(),
                         (),
                         VariableUsage::Unknown,
                     )),
                     then: Arc::new(process::Process::Do {
-                        span: span.clone(),
+                        span: expander.derive(span),
                         name: LocalName::result(),
                         usage: VariableUsage::Unknown,
                         typ: (),
@@ -2491,11 +2633,11 @@ impl Context {
             }
 
             ApplyBranch::Receive(span, pattern, branch, vars) => {
-                let process = self.compile_apply_branch(branch)?;
+                let process = self.compile_apply_branch(expander, branch)?;
                 self.compile_pattern_receive(
                     pattern,
                     0,
-                    span,
+                    &expander.derive(span),
                     &LocalName::object(),
                     process,
                     vars.clone(),
@@ -2505,12 +2647,12 @@ impl Context {
             ApplyBranch::Continue(span, expression) => {
                 let expression = self.compile_expression(expression)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
                     command: process::Command::Continue(Arc::new(process::Process::Do {
-                        span: span.clone(),
+                        span: expander.derive(span),
                         name: LocalName::result(),
                         usage: VariableUsage::Unknown,
                         typ: (),
@@ -2520,9 +2662,9 @@ impl Context {
             }
 
             ApplyBranch::ReceiveType(span, parameter, branch) => {
-                let process = self.compile_apply_branch(branch)?;
+                let process = self.compile_apply_branch(expander, branch)?;
                 Arc::new(process::Process::Do {
-                    span: span.clone(),
+                    span: expander.derive(span),
                     name: LocalName::object(),
                     usage: VariableUsage::Unknown,
                     typ: (),
@@ -2532,13 +2674,13 @@ impl Context {
 
             ApplyBranch::Try(span, label, branch) => {
                 let catch_block = self.use_catch(span, label)?;
-                let process = self.compile_apply_branch(branch)?;
+                let process = self.compile_apply_branch(expander, branch)?;
                 self.compile_try(span, LocalName::object(), catch_block, process)
             }
 
             ApplyBranch::Default(span, expr, branch) => {
                 let default_expr = self.compile_expression(expr)?;
-                let ok_process = self.compile_apply_branch(branch)?;
+                let ok_process = self.compile_apply_branch(expander, branch)?;
                 self.compile_default(span, LocalName::object(), default_expr, ok_process)
             }
         })
@@ -2547,6 +2689,13 @@ impl Context {
     pub(crate) fn compile_process(
         &mut self,
         process: &Process<Unresolved>,
+    ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
+        stack::grow(|| self.compile_process_inner(process))
+    }
+
+    fn compile_process_inner(
+        &mut self,
+        process: &Process<Unresolved>,
     ) -> Result<Arc<process::Process<(), Unresolved>>, CompileError> {
         Ok(match process {
             Process::If {
@@ -2902,6 +3051,7 @@ impl Context {
                 span,
                 unfounded,
                 label,
+                subjects,
                 then: command,
             } => {
                 let process = self.compile_command(command, object_name)?;
@@ -2913,6 +3063,7 @@ impl Context {
                     command: process::Command::Begin {
                         unfounded: *unfounded,
                         label: label.clone(),
+                        subjects: subjects.clone().into(),
                         captures: Captures::new(),
                         body: process,
                     },
@@ -3050,6 +3201,7 @@ impl Context {
         catch_block: Arc<process::Process<(), Unresolved>>,
         ok_process: Arc<process::Process<(), Unresolved>>,
     ) -> Arc<process::Process<(), Unresolved>> {
+        let span = &Expander::new("`.try`", span).span();
         Arc::new(process::Process::Do {
             span: span.clone(),
             name: variable.clone(),
@@ -3088,6 +3240,7 @@ impl Context {
         default_expr: Arc<process::Expression<(), Unresolved>>,
         ok_process: Arc<process::Process<(), Unresolved>>,
     ) -> Arc<process::Process<(), Unresolved>> {
+        let span = &Expander::new("`.default`", span).span();
         self.with_fallthrough(ok_process, |pass| {
             Ok(Arc::new(process::Process::Do {
                 span: span.clone(),
@@ -3124,6 +3277,7 @@ impl Context {
         function: Arc<process::Expression<(), Unresolved>>,
         then: Arc<process::Process<(), Unresolved>>,
     ) -> Arc<process::Process<(), Unresolved>> {
+        let span = &Expander::new("`->`", span).span();
         Arc::new(process::Process::Let {
             span: span.clone(),
             name: LocalName::temp(),
@@ -3487,7 +3641,8 @@ impl<S> Spanning for Apply<S> {
             | Self::Noop(span)
             | Self::Try(span, _, _)
             | Self::Default(span, _, _)
-            | Self::Pipe(span, _, _) => span.clone(),
+            | Self::Pipe(span, _, _)
+            | Self::Named(span, _, _) => span.clone(),
         }
     }
 }
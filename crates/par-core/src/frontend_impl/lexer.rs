@@ -1,5 +1,9 @@
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
 use crate::location::{FileName, Point, Span};
+use core::ops::Range;
 use core::str::FromStr;
+use std::io::{self, Read};
 use winnow::{
     Parser, Result,
     error::ParserError,
@@ -40,6 +44,8 @@ pub enum TokenKind {
     PlusEq,
     Minus,
     MinusEq,
+    Bar,
+    Ampersand,
     Link,
 
     Float,
@@ -82,6 +88,7 @@ pub enum TokenKind {
     Or,
     Not,
     Loop,
+    Macro,
     Poll,
     Repoll,
     Submit,
@@ -93,6 +100,8 @@ pub enum TokenKind {
     Type,
     Unfounded,
     External,
+    Derive,
+    Edition,
 
     Unknown,
 }
@@ -103,23 +112,314 @@ pub struct Token<'i> {
     pub span: Span,
 }
 
+/// A version of the syntax, named by an `edition` declaration at the top of a
+/// file. Each edition may reserve new keywords and add sugar that changes what
+/// older sources meant, so a file keeps its meaning by naming the edition it
+/// was written for. Files without a declaration are in the first one, so the
+/// programs written before editions keep theirs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Edition {
+    /// The syntax before macros, `derive` and placeholder arguments.
+    E2025,
+    /// Reserves `macro` and `derive`, and reads an argument written `_` as a
+    /// placeholder.
+    E2026,
+}
+
+impl Edition {
+    pub const ALL: &[Edition] = &[Edition::E2025, Edition::E2026];
+    pub const LATEST: Edition = Edition::E2026;
+    /// The edition of a file without a declaration.
+    pub const UNDECLARED: Edition = Edition::E2025;
+
+    pub fn year(self) -> u32 {
+        match self {
+            Edition::E2025 => 2025,
+            Edition::E2026 => 2026,
+        }
+    }
+
+    pub fn from_year(year: &str) -> Option<Edition> {
+        Edition::ALL
+            .iter()
+            .copied()
+            .find(|edition| edition.year().to_string() == year)
+    }
+}
+
+impl Default for Edition {
+    fn default() -> Self {
+        Edition::UNDECLARED
+    }
+}
+
+impl core::fmt::Display for Edition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.year())
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub(crate) enum CommentKind {
+pub enum CommentKind {
     Line,
     Block,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct Comment<'i> {
+pub struct Comment<'i> {
     pub kind: CommentKind,
     pub raw: &'i str,
     pub span: Span,
 }
 
+/// A token or a comment, as the lexer comes across them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lexeme<'i> {
+    Token(Token<'i>),
+    Comment(Comment<'i>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LexemeKind {
+    Token(TokenKind),
+    Comment(CommentKind),
+}
+
+/// A [`Lexeme`] with its own copy of its text, for sources that are read
+/// in pieces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedLexeme {
+    pub kind: LexemeKind,
+    pub raw: String,
+    pub span: Span,
+}
+
+impl<'i> Lexeme<'i> {
+    pub fn kind(&self) -> LexemeKind {
+        match self {
+            Self::Token(token) => LexemeKind::Token(token.kind),
+            Self::Comment(comment) => LexemeKind::Comment(comment.kind),
+        }
+    }
+
+    /// The text of the lexeme. For a string, that's without its quotes.
+    pub fn raw(&self) -> &'i str {
+        match self {
+            Self::Token(token) => token.raw,
+            Self::Comment(comment) => comment.raw,
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            Self::Token(token) => &token.span,
+            Self::Comment(comment) => &comment.span,
+        }
+    }
+
+    pub fn to_owned_lexeme(&self) -> OwnedLexeme {
+        OwnedLexeme {
+            kind: self.kind(),
+            raw: self.raw().to_owned(),
+            span: self.span().clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Lexed<'i> {
     pub tokens: Vec<Token<'i>>,
     pub comments: Vec<Comment<'i>>,
+    pub trivia: SourceTrivia,
+}
+
+/// What makes up the source text between tokens.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Blank space, up to and including the end of a line, if any.
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+/// The trivia around one token, as ranges of [`SourceTrivia::trivia`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenTrivia {
+    pub span: Span,
+    pub leading: Range<usize>,
+    pub trailing: Range<usize>,
+}
+
+/// The whitespace and comments of a file, attached to its tokens, so that
+/// tools rewriting the source can keep them.
+///
+/// A token's trailing trivia run up to the end of its line, and everything
+/// else between two tokens leads the second one. Laying out every token
+/// between its leading and trailing trivia, and then the
+/// [`end`](Self::end), gives back the source exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceTrivia {
+    /// All trivia of the file, in order.
+    pub trivia: Vec<Trivia>,
+    /// One for each token, in order.
+    pub tokens: Vec<TokenTrivia>,
+    /// The trivia after the last token's line.
+    pub end: Range<usize>,
+}
+
+impl SourceTrivia {
+    fn collect(input: &str, file: &FileName, tokens: &[Token], comments: &[Comment]) -> Self {
+        let mut trivia = Vec::new();
+        let mut token_trivia = Vec::<TokenTrivia>::with_capacity(tokens.len());
+        let mut comments = comments.iter().peekable();
+        let mut cursor = Point {
+            offset: 0,
+            row: 0,
+            column: 0,
+        };
+
+        let push_whitespace = |trivia: &mut Vec<Trivia>, from: Point, to: usize| {
+            let mut start = from;
+            let raw = &input[from.offset as usize..to];
+            for line in raw.split_inclusive('\n') {
+                let end = end_point_for_raw(start, line);
+                trivia.push(Trivia {
+                    kind: TriviaKind::Whitespace,
+                    span: Span::At {
+                        start,
+                        end,
+                        file: file.clone(),
+                        expansion: None,
+                    },
+                });
+                start = end;
+            }
+        };
+
+        // Collects the trivia up to `until`, and returns where the previous
+        // token's trailing trivia stop.
+        let mut gap = |trivia: &mut Vec<Trivia>, cursor: &mut Point, until: usize| {
+            let gap_start = trivia.len();
+            while let Some(comment) = comments.next_if(|comment| {
+                comment
+                    .span
+                    .start()
+                    .is_some_and(|start| (start.offset as usize) < until)
+            }) {
+                let Some((start, end)) = comment.span.points() else {
+                    continue;
+                };
+                push_whitespace(trivia, *cursor, start.offset as usize);
+                trivia.push(Trivia {
+                    kind: match comment.kind {
+                        CommentKind::Line => TriviaKind::LineComment,
+                        CommentKind::Block => TriviaKind::BlockComment,
+                    },
+                    span: comment.span.clone(),
+                });
+                *cursor = end;
+            }
+            push_whitespace(trivia, *cursor, until);
+            *cursor = end_point_for_raw(*cursor, &input[cursor.offset as usize..until]);
+
+            trivia[gap_start..]
+                .iter()
+                .position(|piece| {
+                    piece.kind == TriviaKind::Whitespace
+                        && piece
+                            .span
+                            .end()
+                            .is_some_and(|end| input[..end.offset as usize].ends_with('\n'))
+                })
+                .map_or(trivia.len(), |newline| gap_start + newline + 1)
+        };
+
+        for token in tokens {
+            let Some((start, end)) = token.span.points() else {
+                continue;
+            };
+            let gap_start = trivia.len();
+            let trailing_end = gap(&mut trivia, &mut cursor, start.offset as usize);
+            let leading_start = match token_trivia.last_mut() {
+                Some(previous) => {
+                    previous.trailing = gap_start..trailing_end;
+                    trailing_end
+                }
+                None => gap_start,
+            };
+            token_trivia.push(TokenTrivia {
+                span: token.span.clone(),
+                leading: leading_start..trivia.len(),
+                trailing: trivia.len()..trivia.len(),
+            });
+            cursor = end;
+        }
+
+        let gap_start = trivia.len();
+        let trailing_end = gap(&mut trivia, &mut cursor, input.len());
+        let end_start = match token_trivia.last_mut() {
+            Some(last) => {
+                last.trailing = gap_start..trailing_end;
+                trailing_end
+            }
+            None => gap_start,
+        };
+
+        Self {
+            end: end_start..trivia.len(),
+            trivia,
+            tokens: token_trivia,
+        }
+    }
+
+    /// `span` widened to take in the leading trivia of its first token and
+    /// the trailing trivia of its last one, so that cutting or moving it
+    /// takes its comments along. Works with the spans of any syntax tree
+    /// parsed from the same source.
+    pub fn span_with_trivia(&self, span: &Span) -> Span {
+        let Span::At {
+            start,
+            end,
+            file,
+            expansion,
+        } = span.clone()
+        else {
+            return Span::None;
+        };
+        let offset = |token: &TokenTrivia, point: fn(&Span) -> Option<Point>| {
+            point(&token.span).map_or(0, |point| point.offset)
+        };
+        let first = self
+            .tokens
+            .partition_point(|token| offset(token, Span::start) < start.offset);
+        let last = self
+            .tokens
+            .partition_point(|token| offset(token, Span::end) <= end.offset);
+        if first >= last {
+            return span.clone();
+        }
+
+        let start = self.trivia[self.tokens[first].leading.clone()]
+            .first()
+            .and_then(|trivia| trivia.span.start())
+            .unwrap_or(start);
+        let end = self.trivia[self.tokens[last - 1].trailing.clone()]
+            .last()
+            .and_then(|trivia| trivia.span.end())
+            .unwrap_or(end);
+        Span::At {
+            start,
+            end,
+            file,
+            expansion,
+        }
+    }
 }
 
 impl Token<'_> {
@@ -175,6 +475,8 @@ impl TokenKind {
             TokenKind::PlusEq => "+=",
             TokenKind::Minus => "-",
             TokenKind::MinusEq => "-=",
+            TokenKind::Bar => "|",
+            TokenKind::Ampersand => "&",
             TokenKind::Link => "<>",
 
             TokenKind::Float => "float",
@@ -217,6 +519,7 @@ impl TokenKind {
             TokenKind::Or => "or",
             TokenKind::Not => "not",
             TokenKind::Loop => "loop",
+            TokenKind::Macro => "macro",
             TokenKind::Poll => "poll",
             TokenKind::Repoll => "repoll",
             TokenKind::Submit => "submit",
@@ -228,6 +531,8 @@ impl TokenKind {
             TokenKind::Type => "type",
             TokenKind::Unfounded => "unfounded",
             TokenKind::External => "external",
+            TokenKind::Derive => "derive",
+            TokenKind::Edition => "edition",
 
             TokenKind::Unknown => "???",
         }
@@ -272,6 +577,191 @@ pub(crate) fn lex<'s>(input: &'s str, file: &FileName) -> Vec<Token<'s>> {
     lex_with_comments(input, file).tokens
 }
 
+/// The whitespace and comments of `input`, attached to its tokens.
+pub fn lex_trivia(input: &str, file: &FileName) -> SourceTrivia {
+    lex_with_comments(input, file).trivia
+}
+
+pub(crate) fn lex_with_comments<'s>(input: &'s str, file: &FileName) -> Lexed<'s> {
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    for lexeme in Lexer::new(input, file.clone()) {
+        match lexeme {
+            Lexeme::Token(token) => tokens.push(token),
+            Lexeme::Comment(comment) => comments.push(comment),
+        }
+    }
+    let trivia = SourceTrivia::collect(input, file, &tokens, &comments);
+    Lexed {
+        tokens,
+        comments,
+        trivia,
+    }
+}
+
+/// The tokens and comments of a source, lexed one at a time, as they're asked
+/// for. They're exactly the ones the parser works on, so tools like
+/// highlighters can go by them instead of guessing.
+pub struct Lexer<'s> {
+    input: &'s str,
+    state: LexState,
+}
+
+impl<'s> Lexer<'s> {
+    pub fn new(input: &'s str, file: FileName) -> Self {
+        Self {
+            input,
+            state: LexState::new(file),
+        }
+    }
+
+    /// A lexer for a source in `edition` unless it declares another, instead
+    /// of [`Edition::UNDECLARED`].
+    pub fn with_edition(input: &'s str, file: FileName, edition: Edition) -> Self {
+        let mut lexer = Self::new(input, file);
+        lexer.state.edition = edition;
+        lexer
+    }
+
+    /// The edition of the source, as far as it's been lexed.
+    pub fn edition(&self) -> Edition {
+        self.state.edition
+    }
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Lexeme<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.state.next_lexeme(self.input)
+    }
+}
+
+/// The most the lexer looks past the end of a lexeme to decide it: past the
+/// `1.5` of `1.5e+1`, to see if there's an exponent.
+const LOOKAHEAD: usize = 3;
+
+/// How much a [`ReadLexer`] reads at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// A [`Lexer`] for a source that's read as it's lexed, from a file, a pipe,
+/// or a socket. A lexeme is only given out once enough has been read past it
+/// that what comes next can't change it, and the source read before it is let
+/// go of, so it keeps little of the source at a time.
+pub struct ReadLexer<R> {
+    reader: R,
+    state: LexState,
+    /// What's been read and not let go of yet. The lexer is at its `start`.
+    text: String,
+    start: usize,
+    /// The bytes of a character cut short at the end of what's been read.
+    partial: Vec<u8>,
+    /// How much there was to lex when a lexeme last couldn't be decided. It's
+    /// only lexed again once there's twice as much, or the reader has nothing
+    /// more for now, so a long token isn't lexed over and over.
+    tried: usize,
+    /// Whether the reader has reached its end.
+    done: bool,
+}
+
+impl<R: Read> ReadLexer<R> {
+    pub fn new(reader: R, file: FileName) -> Self {
+        Self {
+            reader,
+            state: LexState::new(file),
+            text: String::new(),
+            start: 0,
+            partial: Vec::new(),
+            tried: 0,
+            done: false,
+        }
+    }
+
+    /// The edition of the source, as far as it's been lexed.
+    pub fn edition(&self) -> Edition {
+        self.state.edition
+    }
+
+    /// Reads the next piece of the source, after the part that's been lexed,
+    /// and checks only that piece is valid text. Returns whether it's worth
+    /// lexing again.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.text.drain(..self.start);
+        self.start = 0;
+        let wanted = READ_SIZE.max(self.text.len());
+        let mut bytes = std::mem::take(&mut self.partial);
+        let len = bytes.len();
+        bytes.resize(len + wanted, 0);
+        let read = loop {
+            match self.reader.read(&mut bytes[len..]) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    bytes.truncate(len);
+                    self.partial = bytes;
+                    return Err(error);
+                }
+            }
+        };
+        bytes.truncate(len + read);
+        self.done = read == 0;
+        let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+        match std::str::from_utf8(&bytes) {
+            Ok(text) => self.text.push_str(text),
+            Err(error) if error.error_len().is_none() && !self.done => {
+                let (valid, partial) = bytes.split_at(error.valid_up_to());
+                self.text
+                    .push_str(std::str::from_utf8(valid).map_err(invalid)?);
+                self.partial = partial.to_vec();
+            }
+            Err(error) => {
+                self.partial = bytes;
+                return Err(invalid(error));
+            }
+        }
+        Ok(self.done || read < wanted || self.text.len() >= 2 * self.tried)
+    }
+}
+
+impl<R: Read> Iterator for ReadLexer<R> {
+    type Item = io::Result<OwnedLexeme>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let input = &self.text[self.start..];
+            // Lexed on a copy of the state, to be tried again with more of the
+            // source if it's too close to the end of what's been read.
+            let mut state = self.state.clone();
+            match state.next_lexeme(input) {
+                Some(lexeme) => {
+                    // A `/*` without its end read yet is lexed as a slash.
+                    let opens_comment = lexeme.kind() == LexemeKind::Token(TokenKind::Slash)
+                        && input[state.idx..].starts_with('*');
+                    if self.done || (state.idx + LOOKAHEAD <= input.len() && !opens_comment) {
+                        let lexeme = lexeme.to_owned_lexeme();
+                        self.start += state.idx;
+                        self.tried = 0;
+                        state.base += state.idx;
+                        state.idx = 0;
+                        self.state = state;
+                        return Some(Ok(lexeme));
+                    }
+                }
+                None if self.done => return None,
+                None => {}
+            }
+            self.tried = input.len();
+            loop {
+                match self.fill() {
+                    Ok(true) => break,
+                    Ok(false) => continue,
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+        }
+    }
+}
+
 fn scan_digit_run(input: &str, start: usize) -> Option<usize> {
     let bytes = input.as_bytes();
     if !matches!(bytes.get(start), Some(b'0'..=b'9')) {
@@ -332,95 +822,88 @@ enum LexMode {
     Interpolation { brace_depth: usize },
 }
 
-struct LexState<'s, 'f> {
-    file: &'f FileName,
-    tokens: Vec<Token<'s>>,
-    comments: Vec<Comment<'s>>,
+#[derive(Clone)]
+struct LexState {
+    file: FileName,
+    /// Where the input starts in the source: all of it, except for a
+    /// [`ReadLexer`], which lets go of what it's done with.
+    base: usize,
     idx: usize,
     row: usize,
     column: usize,
     modes: Vec<LexMode>,
+    /// The edition the keywords are read in, changed by a declaration at the
+    /// top of the source.
+    edition: Edition,
+    /// The kind of the last token, if there was one.
+    previous: Option<TokenKind>,
 }
 
-impl<'s> LexState<'s, '_> {
-    fn new(file: &FileName) -> LexState<'s, '_> {
+impl LexState {
+    fn new(file: FileName) -> LexState {
         LexState {
             file,
-            tokens: Vec::new(),
-            comments: Vec::new(),
+            base: 0,
             idx: 0,
             row: 0,
             column: 0,
             modes: vec![LexMode::Normal],
-        }
-    }
-
-    fn finish(self) -> Lexed<'s> {
-        Lexed {
-            tokens: self.tokens,
-            comments: self.comments,
+            edition: Edition::default(),
+            previous: None,
         }
     }
 
     fn start_point(&self) -> Point {
         Point {
-            offset: self.idx.try_into().expect("position too large"),
-            row: self.row.try_into().expect("row too large"),
-            column: self.column.try_into().expect("column too large"),
+            // Positions past `u32::MAX` all point at its end.
+            offset: (self.base + self.idx).try_into().unwrap_or(u32::MAX),
+            row: self.row.try_into().unwrap_or(u32::MAX),
+            column: self.column.try_into().unwrap_or(u32::MAX),
         }
     }
 
-    fn advance_to(&mut self, end: Point) {
-        self.idx = end.offset as usize;
+    fn advance(&mut self, raw: &str) {
+        let end = end_point_for_raw(self.start_point(), raw);
+        self.idx += raw.len();
         self.row = end.row as usize;
         self.column = end.column as usize;
     }
 
-    fn advance(&mut self, raw: &str) {
-        let end = end_point_for_raw(self.start_point(), raw);
-        self.advance_to(end);
+    fn span(&self, consumed: &str) -> Span {
+        let start = self.start_point();
+        Span::At {
+            start,
+            end: end_point_for_raw(start, consumed),
+            file: self.file.clone(),
+            expansion: None,
+        }
     }
 
-    fn push_token(&mut self, kind: TokenKind, raw: &'s str) {
-        self.push_token_consumed(kind, raw, raw);
+    fn token<'s>(&mut self, kind: TokenKind, raw: &'s str) -> Lexeme<'s> {
+        self.token_consumed(kind, raw, raw)
     }
 
-    fn push_token_consumed(&mut self, kind: TokenKind, raw: &'s str, consumed: &str) {
-        let start = self.start_point();
-        let end = end_point_for_raw(start, consumed);
-        self.tokens.push(Token {
-            kind,
-            raw,
-            span: Span::At {
-                start,
-                end,
-                file: self.file.clone(),
-            },
-        });
-        self.advance_to(end);
+    fn token_consumed<'s>(&mut self, kind: TokenKind, raw: &'s str, consumed: &str) -> Lexeme<'s> {
+        let span = self.span(consumed);
+        self.advance(consumed);
+        if self.previous == Some(TokenKind::Edition) && kind == TokenKind::Integer {
+            // An unknown edition is left for the parser to report.
+            self.edition = Edition::from_year(raw).unwrap_or(self.edition);
+        }
+        self.previous = Some(kind);
+        Lexeme::Token(Token { kind, raw, span })
     }
 
-    fn push_comment(&mut self, kind: CommentKind, raw: &'s str) {
-        let start = self.start_point();
-        let end = end_point_for_raw(start, raw);
-        self.comments.push(Comment {
-            kind,
-            raw,
-            span: Span::At {
-                start,
-                end,
-                file: self.file.clone(),
-            },
-        });
-        self.advance_to(end);
+    fn comment<'s>(&mut self, kind: CommentKind, raw: &'s str) -> Lexeme<'s> {
+        let span = self.span(raw);
+        self.advance(raw);
+        Lexeme::Comment(Comment { kind, raw, span })
     }
 }
 
 fn scan_string_content(input: &str) -> (usize, bool) {
     let mut idx = 1;
-    while idx < input.len() {
-        let rest = &input[idx..];
-        let c = rest.chars().next().unwrap();
+    while let Some(c) = input[idx..].chars().next() {
         match c {
             '"' => return (idx - 1, true),
             '\\' => {
@@ -458,7 +941,7 @@ fn scan_block_comment(input: &str) -> Option<usize> {
             nesting -= 1;
             idx += 2;
         } else {
-            idx += rest.chars().next().unwrap().len_utf8();
+            idx += rest.chars().next().map_or(1, char::len_utf8);
         }
     }
     None
@@ -466,13 +949,12 @@ fn scan_block_comment(input: &str) -> Option<usize> {
 
 fn scan_template_text(input: &str) -> usize {
     let mut idx = 0;
-    while idx < input.len() {
+    while let Some(c) = input[idx..].chars().next() {
         let rest = &input[idx..];
         if rest.starts_with('`') || rest.starts_with("${") || rest.starts_with("#{") {
             break;
         }
 
-        let c = rest.chars().next().unwrap();
         idx += c.len_utf8();
         if c == '\\'
             && let Some(next) = input[idx..].chars().next()
@@ -486,7 +968,7 @@ fn scan_template_text(input: &str) -> usize {
 pub(crate) fn unescape_template_text(raw: &str) -> unescaper::Result<String> {
     let mut rewritten = String::with_capacity(raw.len());
     let mut rest = raw;
-    while !rest.is_empty() {
+    while let Some(c) = rest.chars().next() {
         if let Some(after) = rest.strip_prefix("\\`") {
             rewritten.push_str("\\u{60}");
             rest = after;
@@ -497,7 +979,6 @@ pub(crate) fn unescape_template_text(raw: &str) -> unescaper::Result<String> {
             rewritten.push_str("\\u{23}{");
             rest = after;
         } else {
-            let c = rest.chars().next().unwrap();
             rewritten.push(c);
             rest = &rest[c.len_utf8()..];
         }
@@ -505,285 +986,294 @@ pub(crate) fn unescape_template_text(raw: &str) -> unescaper::Result<String> {
     unescaper::unescape(&rewritten)
 }
 
-pub(crate) fn lex_with_comments<'s>(input: &'s str, file: &FileName) -> Lexed<'s> {
-    let mut state = LexState::new(file);
-
-    while state.idx < input.len() {
-        let rest = &input[state.idx..];
+impl LexState {
+    /// The next token or comment of `input`, past the whitespace before it.
+    fn next_lexeme<'s>(&mut self, input: &'s str) -> Option<Lexeme<'s>> {
+        loop {
+            let rest = &input[self.idx..];
+            let c = rest.chars().next()?;
 
-        if matches!(state.modes.last(), Some(LexMode::Template)) {
-            if let Some(raw) = rest.strip_prefix('`').map(|_| &rest[..1]) {
-                state.push_token(TokenKind::TemplateEnd, raw);
-                state.modes.pop();
-                continue;
-            }
-            if let Some(raw) = rest.strip_prefix("${").map(|_| &rest[..2]) {
-                state.push_token(TokenKind::TemplateStringStart, raw);
-                state.modes.push(LexMode::Interpolation { brace_depth: 0 });
-                continue;
-            }
-            if let Some(raw) = rest.strip_prefix("#{").map(|_| &rest[..2]) {
-                state.push_token(TokenKind::TemplateDataStart, raw);
-                state.modes.push(LexMode::Interpolation { brace_depth: 0 });
-                continue;
-            }
+            if matches!(self.modes.last(), Some(LexMode::Template)) {
+                if let Some(raw) = rest.strip_prefix('`').map(|_| &rest[..1]) {
+                    self.modes.pop();
+                    return Some(self.token(TokenKind::TemplateEnd, raw));
+                }
+                if let Some(raw) = rest.strip_prefix("${").map(|_| &rest[..2]) {
+                    self.modes.push(LexMode::Interpolation { brace_depth: 0 });
+                    return Some(self.token(TokenKind::TemplateStringStart, raw));
+                }
+                if let Some(raw) = rest.strip_prefix("#{").map(|_| &rest[..2]) {
+                    self.modes.push(LexMode::Interpolation { brace_depth: 0 });
+                    return Some(self.token(TokenKind::TemplateDataStart, raw));
+                }
 
-            let len = scan_template_text(rest);
-            if len == 0 {
-                let raw = &rest[..rest.chars().next().unwrap().len_utf8()];
-                state.push_token(TokenKind::Unknown, raw);
-            } else {
+                let len = scan_template_text(rest);
+                if len == 0 {
+                    let raw = &rest[..c.len_utf8()];
+                    return Some(self.token(TokenKind::Unknown, raw));
+                }
                 let raw = &rest[..len];
                 let kind = if unescape_template_text(raw).is_ok() {
                     TokenKind::TemplateText
                 } else {
                     TokenKind::InvalidString
                 };
-                state.push_token(kind, raw);
+                return Some(self.token(kind, raw));
             }
-            continue;
-        }
 
-        let c = rest.chars().next().unwrap();
-        match c {
-            '-' => {
-                if rest.starts_with("->") {
-                    let raw = &rest[..2];
-                    state.push_token(TokenKind::ThinArrow, raw);
-                } else if rest.starts_with("-=") {
-                    let raw = &rest[..2];
-                    state.push_token(TokenKind::MinusEq, raw);
-                } else if let Some((raw, kind, len)) = scan_number_token(rest) {
-                    state.push_token_consumed(kind, raw, &rest[..len]);
-                } else {
+            let lexeme = match c {
+                '-' => {
+                    if rest.starts_with("->") {
+                        let raw = &rest[..2];
+                        self.token(TokenKind::ThinArrow, raw)
+                    } else if rest.starts_with("-=") {
+                        let raw = &rest[..2];
+                        self.token(TokenKind::MinusEq, raw)
+                    } else if let Some((raw, kind, len)) = scan_number_token(rest) {
+                        self.token_consumed(kind, raw, &rest[..len])
+                    } else {
+                        let raw = &rest[..1];
+                        self.token(TokenKind::Minus, raw)
+                    }
+                }
+                '0'..='9' | '+' => {
+                    if rest.starts_with("+=") {
+                        let raw = &rest[..2];
+                        self.token(TokenKind::PlusEq, raw)
+                    } else if let Some((raw, kind, len)) = scan_number_token(rest) {
+                        self.token_consumed(kind, raw, &rest[..len])
+                    } else {
+                        let raw = &rest[..1];
+                        self.token(TokenKind::Plus, raw)
+                    }
+                }
+                '"' => {
+                    let (content_len, is_closed) = scan_string_content(rest);
+                    let raw = &rest[1..1 + content_len];
+                    let consumed_len = raw.len() + 1 + usize::from(is_closed);
+                    let kind = if is_closed && unescaper::unescape(raw).is_ok() {
+                        TokenKind::String
+                    } else {
+                        TokenKind::InvalidString
+                    };
+                    self.token_consumed(kind, raw, &rest[..consumed_len])
+                }
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let len = rest
+                        .char_indices()
+                        .take_while(|(_, c)| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_'))
+                        .last()
+                        .map(|(idx, c)| idx + c.len_utf8())
+                        .unwrap_or(0);
+                    let raw = &rest[..len];
+                    let kind = match raw {
+                        "begin" => TokenKind::Begin,
+                        "box" => TokenKind::Box,
+                        "case" => TokenKind::Case,
+                        "catch" => TokenKind::Catch,
+                        "chan" => TokenKind::Chan,
+                        "choice" => TokenKind::Choice,
+                        "dec" => TokenKind::Dec,
+                        "def" => TokenKind::Def,
+                        "do" => TokenKind::Do,
+                        "dual" => TokenKind::Dual,
+                        "either" => TokenKind::Either,
+                        "else" => TokenKind::Else,
+                        "export" => TokenKind::Export,
+                        "if" => TokenKind::If,
+                        "import" => TokenKind::Import,
+                        "is" => TokenKind::Is,
+                        "in" => TokenKind::In,
+                        "iterative" => TokenKind::Iterative,
+                        "let" => TokenKind::Let,
+                        "and" => TokenKind::And,
+                        "as" => TokenKind::As,
+                        "module" => TokenKind::Module,
+                        "neg" => TokenKind::Neg,
+                        "or" => TokenKind::Or,
+                        "not" => TokenKind::Not,
+                        "loop" => TokenKind::Loop,
+                        "macro" if self.edition >= Edition::E2026 => TokenKind::Macro,
+                        "poll" => TokenKind::Poll,
+                        "repoll" => TokenKind::Repoll,
+                        "submit" => TokenKind::Submit,
+                        "recursive" => TokenKind::Recursive,
+                        "self" => TokenKind::Self_,
+                        "throw" => TokenKind::Throw,
+                        "try" => TokenKind::Try,
+                        "default" => TokenKind::Default,
+                        "type" => TokenKind::Type,
+                        "unfounded" => TokenKind::Unfounded,
+                        "external" => TokenKind::External,
+                        "derive" if self.edition >= Edition::E2026 => TokenKind::Derive,
+                        "edition" if self.previous.is_none() => TokenKind::Edition,
+                        raw => {
+                            if raw.starts_with(char::is_uppercase) {
+                                TokenKind::UppercaseIdentifier
+                            } else {
+                                TokenKind::LowercaseIdentifier
+                            }
+                        }
+                    };
+                    self.token(kind, raw)
+                }
+                '\n' | ' ' | '\t' | '\r' => {
+                    let raw = &rest[..c.len_utf8()];
+                    self.advance(raw);
+                    continue;
+                }
+                '`' => {
                     let raw = &rest[..1];
-                    state.push_token(TokenKind::Minus, raw);
+                    let token = self.token(TokenKind::TemplateStart, raw);
+                    self.modes.push(LexMode::Template);
+                    token
                 }
-            }
-            '0'..='9' | '+' => {
-                if rest.starts_with("+=") {
-                    let raw = &rest[..2];
-                    state.push_token(TokenKind::PlusEq, raw);
-                } else if let Some((raw, kind, len)) = scan_number_token(rest) {
-                    state.push_token_consumed(kind, raw, &rest[..len]);
-                } else {
+                ':' => {
                     let raw = &rest[..1];
-                    state.push_token(TokenKind::Plus, raw);
+                    self.token(TokenKind::Colon, raw)
                 }
-            }
-            '"' => {
-                let (content_len, is_closed) = scan_string_content(rest);
-                let raw = &rest[1..1 + content_len];
-                let consumed_len = raw.len() + 1 + usize::from(is_closed);
-                let kind = if is_closed && unescaper::unescape(raw).is_ok() {
-                    TokenKind::String
-                } else {
-                    TokenKind::InvalidString
-                };
-                state.push_token_consumed(kind, raw, &rest[..consumed_len]);
-            }
-            'a'..='z' | 'A'..='Z' | '_' => {
-                let len = rest
-                    .char_indices()
-                    .take_while(|(_, c)| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_'))
-                    .last()
-                    .map(|(idx, c)| idx + c.len_utf8())
-                    .unwrap_or(0);
-                let raw = &rest[..len];
-                let kind = match raw {
-                    "begin" => TokenKind::Begin,
-                    "box" => TokenKind::Box,
-                    "case" => TokenKind::Case,
-                    "catch" => TokenKind::Catch,
-                    "chan" => TokenKind::Chan,
-                    "choice" => TokenKind::Choice,
-                    "dec" => TokenKind::Dec,
-                    "def" => TokenKind::Def,
-                    "do" => TokenKind::Do,
-                    "dual" => TokenKind::Dual,
-                    "either" => TokenKind::Either,
-                    "else" => TokenKind::Else,
-                    "export" => TokenKind::Export,
-                    "if" => TokenKind::If,
-                    "import" => TokenKind::Import,
-                    "is" => TokenKind::Is,
-                    "in" => TokenKind::In,
-                    "iterative" => TokenKind::Iterative,
-                    "let" => TokenKind::Let,
-                    "and" => TokenKind::And,
-                    "as" => TokenKind::As,
-                    "module" => TokenKind::Module,
-                    "neg" => TokenKind::Neg,
-                    "or" => TokenKind::Or,
-                    "not" => TokenKind::Not,
-                    "loop" => TokenKind::Loop,
-                    "poll" => TokenKind::Poll,
-                    "repoll" => TokenKind::Repoll,
-                    "submit" => TokenKind::Submit,
-                    "recursive" => TokenKind::Recursive,
-                    "self" => TokenKind::Self_,
-                    "throw" => TokenKind::Throw,
-                    "try" => TokenKind::Try,
-                    "default" => TokenKind::Default,
-                    "type" => TokenKind::Type,
-                    "unfounded" => TokenKind::Unfounded,
-                    "external" => TokenKind::External,
-                    raw => {
-                        if raw.starts_with(char::is_uppercase) {
-                            TokenKind::UppercaseIdentifier
+                ';' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Semicolon, raw)
+                }
+                '[' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::LBrack, raw)
+                }
+                ']' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::RBrack, raw)
+                }
+                '(' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::LParen, raw)
+                }
+                ')' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::RParen, raw)
+                }
+                '{' => {
+                    if let Some(LexMode::Interpolation { brace_depth }) = self.modes.last_mut() {
+                        *brace_depth += 1;
+                    }
+                    let raw = &rest[..1];
+                    self.token(TokenKind::LCurly, raw)
+                }
+                '}' => {
+                    let should_close_interpolation = matches!(
+                        self.modes.last(),
+                        Some(LexMode::Interpolation { brace_depth: 0 })
+                    );
+                    if should_close_interpolation {
+                        self.modes.pop();
+                    } else if let Some(LexMode::Interpolation { brace_depth }) =
+                        self.modes.last_mut()
+                    {
+                        *brace_depth -= 1;
+                    }
+                    let raw = &rest[..1];
+                    self.token(TokenKind::RCurly, raw)
+                }
+                '<' => {
+                    let (kind, len) = if rest.starts_with("<>") {
+                        (TokenKind::Link, 2)
+                    } else if rest.starts_with("<=") {
+                        (TokenKind::LtEq, 2)
+                    } else {
+                        (TokenKind::Lt, 1)
+                    };
+                    let raw = &rest[..len];
+                    self.token(kind, raw)
+                }
+                '>' => {
+                    let (kind, len) = if rest.starts_with(">=") {
+                        (TokenKind::GtEq, 2)
+                    } else {
+                        (TokenKind::Gt, 1)
+                    };
+                    let raw = &rest[..len];
+                    self.token(kind, raw)
+                }
+                '/' => {
+                    if rest.starts_with("//") {
+                        let len = scan_line_comment(rest);
+                        let raw = &rest[..len];
+                        self.comment(CommentKind::Line, raw)
+                    } else if let Some(len) = scan_block_comment(rest) {
+                        let raw = &rest[..len];
+                        self.comment(CommentKind::Block, raw)
+                    } else {
+                        let (kind, len) = if rest.starts_with("/=") {
+                            (TokenKind::SlashEq, 2)
                         } else {
-                            TokenKind::LowercaseIdentifier
-                        }
+                            (TokenKind::Slash, 1)
+                        };
+                        let raw = &rest[..len];
+                        self.token(kind, raw)
                     }
-                };
-                state.push_token(kind, raw);
-            }
-            '\n' | ' ' | '\t' | '\r' => {
-                let raw = &rest[..c.len_utf8()];
-                state.advance(raw);
-            }
-            '`' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::TemplateStart, raw);
-                state.modes.push(LexMode::Template);
-            }
-            ':' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::Colon, raw);
-            }
-            ';' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::Semicolon, raw);
-            }
-            '[' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::LBrack, raw);
-            }
-            ']' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::RBrack, raw);
-            }
-            '(' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::LParen, raw);
-            }
-            ')' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::RParen, raw);
-            }
-            '{' => {
-                if let Some(LexMode::Interpolation { brace_depth }) = state.modes.last_mut() {
-                    *brace_depth += 1;
                 }
-                let raw = &rest[..1];
-                state.push_token(TokenKind::LCurly, raw);
-            }
-            '}' => {
-                let should_close_interpolation = matches!(
-                    state.modes.last(),
-                    Some(LexMode::Interpolation { brace_depth: 0 })
-                );
-                if should_close_interpolation {
-                    state.modes.pop();
-                } else if let Some(LexMode::Interpolation { brace_depth }) = state.modes.last_mut()
-                {
-                    *brace_depth -= 1;
+                '@' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::At, raw)
                 }
-                let raw = &rest[..1];
-                state.push_token(TokenKind::RCurly, raw);
-            }
-            '<' => {
-                let (kind, len) = if rest.starts_with("<>") {
-                    (TokenKind::Link, 2)
-                } else if rest.starts_with("<=") {
-                    (TokenKind::LtEq, 2)
-                } else {
-                    (TokenKind::Lt, 1)
-                };
-                let raw = &rest[..len];
-                state.push_token(kind, raw);
-            }
-            '>' => {
-                let (kind, len) = if rest.starts_with(">=") {
-                    (TokenKind::GtEq, 2)
-                } else {
-                    (TokenKind::Gt, 1)
-                };
-                let raw = &rest[..len];
-                state.push_token(kind, raw);
-            }
-            '/' => {
-                if rest.starts_with("//") {
-                    let len = scan_line_comment(rest);
+                ',' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Comma, raw)
+                }
+                '.' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Dot, raw)
+                }
+                '=' => {
+                    let (kind, len) = if rest.starts_with("=>") {
+                        (TokenKind::FatArrow, 2)
+                    } else if rest.starts_with("==") {
+                        (TokenKind::EqEq, 2)
+                    } else {
+                        (TokenKind::Eq, 1)
+                    };
                     let raw = &rest[..len];
-                    state.push_comment(CommentKind::Line, raw);
-                } else if let Some(len) = scan_block_comment(rest) {
+                    self.token(kind, raw)
+                }
+                '!' => {
+                    let (kind, len) = if rest.starts_with("!=") {
+                        (TokenKind::BangEq, 2)
+                    } else {
+                        (TokenKind::Bang, 1)
+                    };
                     let raw = &rest[..len];
-                    state.push_comment(CommentKind::Block, raw);
-                } else {
-                    let (kind, len) = if rest.starts_with("/=") {
-                        (TokenKind::SlashEq, 2)
+                    self.token(kind, raw)
+                }
+                '?' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Quest, raw)
+                }
+                '|' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Bar, raw)
+                }
+                '&' => {
+                    let raw = &rest[..1];
+                    self.token(TokenKind::Ampersand, raw)
+                }
+                '*' => {
+                    let (kind, len) = if rest.starts_with("*=") {
+                        (TokenKind::StarEq, 2)
                     } else {
-                        (TokenKind::Slash, 1)
+                        (TokenKind::Star, 1)
                     };
                     let raw = &rest[..len];
-                    state.push_token(kind, raw);
+                    self.token(kind, raw)
                 }
-            }
-            '@' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::At, raw);
-            }
-            ',' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::Comma, raw);
-            }
-            '.' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::Dot, raw);
-            }
-            '=' => {
-                let (kind, len) = if rest.starts_with("=>") {
-                    (TokenKind::FatArrow, 2)
-                } else if rest.starts_with("==") {
-                    (TokenKind::EqEq, 2)
-                } else {
-                    (TokenKind::Eq, 1)
-                };
-                let raw = &rest[..len];
-                state.push_token(kind, raw);
-            }
-            '!' => {
-                let (kind, len) = if rest.starts_with("!=") {
-                    (TokenKind::BangEq, 2)
-                } else {
-                    (TokenKind::Bang, 1)
-                };
-                let raw = &rest[..len];
-                state.push_token(kind, raw);
-            }
-            '?' => {
-                let raw = &rest[..1];
-                state.push_token(TokenKind::Quest, raw);
-            }
-            '*' => {
-                let (kind, len) = if rest.starts_with("*=") {
-                    (TokenKind::StarEq, 2)
-                } else {
-                    (TokenKind::Star, 1)
-                };
-                let raw = &rest[..len];
-                state.push_token(kind, raw);
-            }
-            _ => {
-                let len = c.len_utf8();
-                let raw = &rest[..len];
-                state.push_token(TokenKind::Unknown, raw);
-            }
+                _ => {
+                    let len = c.len_utf8();
+                    let raw = &rest[..len];
+                    self.token(TokenKind::Unknown, raw)
+                }
+            };
+            return Some(lexeme);
         }
     }
-
-    state.finish()
 }
 
 fn end_point_for_raw(start: Point, raw: &str) -> Point {
@@ -856,7 +1346,8 @@ mod lexer_test {
                             row: 0,
                             column: 3
                         },
-                        file: FILE
+                        file: FILE,
+                        expansion: None
                     },
                 },
                 Token {
@@ -873,7 +1364,8 @@ mod lexer_test {
                             row: 4,
                             column: 16
                         },
-                        file: FILE
+                        file: FILE,
+                        expansion: None
                     },
                 },
                 Token {
@@ -890,7 +1382,8 @@ mod lexer_test {
                             row: 4,
                             column: 25
                         },
-                        file: FILE
+                        file: FILE,
+                        expansion: None
                     },
                 }
             ]
@@ -1009,6 +1502,65 @@ mod lexer_test {
         );
     }
 
+    #[test]
+    fn trivia_lay_out_the_source_around_the_tokens() {
+        let source = "// lead\ndef A = /* mid */ \"x\" // tail\n\n  /* next */ B\n// end\n";
+        let trivia = lex_trivia(source, &FILE);
+        let text = |span: &Span| {
+            let (start, end) = span.points().unwrap();
+            &source[start.offset as usize..end.offset as usize]
+        };
+        let pieces = |range: &Range<usize>| {
+            trivia.trivia[range.clone()]
+                .iter()
+                .map(|piece| text(&piece.span))
+                .collect::<Vec<_>>()
+        };
+
+        let mut rebuilt = String::new();
+        for token in &trivia.tokens {
+            rebuilt.extend(pieces(&token.leading));
+            rebuilt.push_str(text(&token.span));
+            rebuilt.extend(pieces(&token.trailing));
+        }
+        rebuilt.extend(pieces(&trivia.end));
+        assert_eq!(rebuilt, source);
+
+        let string = &trivia.tokens[3];
+        assert_eq!(text(&string.span), "\"x\"");
+        assert_eq!(pieces(&string.leading), Vec::<&str>::new());
+        assert_eq!(pieces(&string.trailing), vec![" ", "// tail", "\n"]);
+        let b = &trivia.tokens[4];
+        assert_eq!(pieces(&b.leading), vec!["\n", "  ", "/* next */", " "]);
+        assert_eq!(pieces(&b.trailing), vec!["\n"]);
+        assert_eq!(pieces(&trivia.end), vec!["// end", "\n"]);
+        assert_eq!(pieces(&trivia.tokens[0].leading), vec!["// lead", "\n"]);
+    }
+
+    #[test]
+    fn spans_with_trivia_take_their_comments_along() {
+        let source = "def A = 1\n\n// B's comment\ndef B = 2 // trailing\ndef C = 3\n";
+        let trivia = lex_trivia(source, &FILE);
+        let start = source.find("def B").unwrap() as u32;
+        let end = source.find(" // trailing").unwrap() as u32;
+        let point = |offset: u32| Point {
+            offset,
+            row: 0,
+            column: 0,
+        };
+        let span = Span::At {
+            start: point(start),
+            end: point(end),
+            file: FILE,
+            expansion: None,
+        };
+        let (start, end) = trivia.span_with_trivia(&span).points().unwrap();
+        assert_eq!(
+            &source[start.offset as usize..end.offset as usize],
+            "\n// B's comment\ndef B = 2 // trailing\n"
+        );
+    }
+
     #[test]
     fn template_text_tracks_multiline_spans() {
         let tokens = lex("`a\nb`", &FILE);
@@ -1026,7 +1578,8 @@ mod lexer_test {
                     row: 1,
                     column: 1
                 },
-                file: FILE
+                file: FILE,
+                expansion: None
             }
         );
         assert_eq!(
@@ -1042,8 +1595,115 @@ mod lexer_test {
                     row: 1,
                     column: 2
                 },
-                file: FILE
+                file: FILE,
+                expansion: None
             }
         );
     }
+
+    /// Gives out its bytes one at a time, so the source gets cut everywhere.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&byte, rest)), Some(first)) => {
+                    *first = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn read_lexer_matches_lexer() {
+        for source in [
+            "1.5e+3 - 1 -2 1. x.y -= 3",
+            "abc/* a /* b */ c */ / d // e\n<> <= => ==",
+            "`a${b}c#{ {d} }\\${` `",
+            "\"\u{e9}\" \"é\" 日本 \"unclosed",
+            "f /* unclosed",
+            "edition 2025 macro derive",
+        ] {
+            let lexed: Vec<_> = Lexer::new(source, FILE)
+                .map(|lexeme| lexeme.to_owned_lexeme())
+                .collect();
+            let read = ReadLexer::new(Trickle(source.as_bytes()), FILE)
+                .collect::<io::Result<Vec<_>>>()
+                .ok();
+            assert_eq!(read, Some(lexed), "{source}");
+        }
+    }
+
+    /// Counts how many times it's read from.
+    struct Counted<'a>(&'a [u8], usize);
+
+    impl Read for Counted<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.1 += 1;
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn read_lexer_reads_more_at_a_time_for_long_tokens() {
+        let source = format!("x \"{}\" y", "a".repeat(64 * READ_SIZE));
+        let mut reader = Counted(source.as_bytes(), 0);
+        let read = ReadLexer::new(&mut reader, FILE)
+            .collect::<io::Result<Vec<_>>>()
+            .expect("lexed");
+        let lexed: Vec<_> = Lexer::new(&source, FILE)
+            .map(|lexeme| lexeme.to_owned_lexeme())
+            .collect();
+        assert_eq!(read, lexed);
+        // Reading a fixed amount at a time would take 64 reads, each followed
+        // by lexing the string again from its start.
+        assert!(reader.1 <= 10, "{} reads", reader.1);
+    }
+
+    #[test]
+    fn read_lexer_rejects_invalid_utf8() {
+        let mut lexer = ReadLexer::new(Trickle(b"abc \xff"), FILE);
+        assert!(
+            matches!(lexer.next(), Some(Err(error)) if error.kind() == io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn editions_decide_keywords() {
+        let kinds = |source| {
+            lex(source, &FILE)
+                .iter()
+                .map(|token| token.kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds("macro derive edition"),
+            vec![
+                TokenKind::LowercaseIdentifier,
+                TokenKind::LowercaseIdentifier,
+                TokenKind::LowercaseIdentifier
+            ]
+        );
+        assert_eq!(
+            kinds("// Old.\nedition 2025 macro derive"),
+            vec![
+                TokenKind::Edition,
+                TokenKind::Integer,
+                TokenKind::LowercaseIdentifier,
+                TokenKind::LowercaseIdentifier,
+            ]
+        );
+        assert_eq!(
+            kinds("edition 2026 macro"),
+            vec![TokenKind::Edition, TokenKind::Integer, TokenKind::Macro]
+        );
+
+        let mut lexer = Lexer::new("edition 2026 module Main", FILE);
+        assert_eq!(lexer.edition(), Edition::UNDECLARED);
+        lexer.by_ref().take(2).for_each(drop);
+        assert_eq!(lexer.edition(), Edition::E2026);
+    }
 }
@@ -0,0 +1,347 @@
+//! Macros: families of similar items, written once with parameters.
+//!
+//! ```par
+//! macro Counter(Name, field) {
+//!   type Name = iterative choice {
+//!     .field => Nat,
+//!     .add(Nat) => self,
+//!   }
+//! }
+//!
+//! Counter!(Width, width)
+//! Counter!(Height, height)
+//! ```
+//!
+//! Expansion happens on the tokens of a file, before its items are parsed:
+//! every invocation is replaced by the body of its macro, with each parameter
+//! replaced by the tokens of its argument. Macros are seen only by the file
+//! that defines them, and can be invoked anywhere in it after being defined,
+//! including in the bodies of other macros.
+//!
+//! Substitution is kept hygienic by working on whole tokens. A parameter only
+//! replaces identifiers spelled the same, never parts of names or the text of
+//! strings, and every argument is a group with its brackets balanced, so it
+//! can't close anything it was substituted into. Names in a body that aren't
+//! parameters stay as they are, so a body can't pick up the locals of the
+//! place it's invoked from, except through its arguments.
+//!
+//! Expanded tokens keep the spans of where they're written, marked as
+//! expanded from the invocation, so errors in them point at both.
+
+use std::collections::HashMap;
+
+use crate::frontend_impl::lexer::{Token, TokenKind};
+use crate::location::Span;
+
+/// How deep invocations may expand into further invocations, to stop macros
+/// that invoke themselves.
+const MAX_DEPTH: usize = 64;
+
+const SUGAR: &str = "macro invocation";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MacroError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl MacroError {
+    fn new(span: &Span, message: impl Into<String>) -> Self {
+        Self {
+            span: span.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+struct Macro<'i> {
+    params: Vec<&'i str>,
+    body: Vec<Token<'i>>,
+}
+
+struct Invocation<'m, 'i> {
+    mac: &'m Macro<'i>,
+    span: Span,
+    args: Vec<Vec<Token<'i>>>,
+    /// The index just past the invocation.
+    end: usize,
+}
+
+/// Takes out the macro definitions of a file, and expands their invocations.
+pub(crate) fn expand_macros<'i>(tokens: Vec<Token<'i>>) -> Result<Vec<Token<'i>>, MacroError> {
+    if !tokens.iter().any(|token| token.kind == TokenKind::Macro) {
+        return Ok(tokens);
+    }
+    let mut macros = HashMap::new();
+    let mut rest = Vec::with_capacity(tokens.len());
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match token.kind {
+            TokenKind::Macro if depth > 0 => {
+                return Err(MacroError::new(
+                    &token.span,
+                    "Macros can only be defined at the top level of a file.",
+                ));
+            }
+            TokenKind::Macro => {
+                let (name, definition, end) = definition(&tokens, i)?;
+                if macros.contains_key(name.raw) {
+                    return Err(MacroError::new(
+                        &name.span,
+                        format!("Macro `{}` is already defined.", name.raw),
+                    ));
+                }
+                macros.insert(name.raw, definition);
+                i = end;
+                continue;
+            }
+            _ if opens(token.kind) => depth += 1,
+            _ if closes(token.kind) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if let Some(invocation) = invocation(&macros, &tokens, i)? {
+            i = invocation.end;
+            rest.extend(invoke(&macros, invocation, 0)?);
+            continue;
+        }
+        rest.push(token.clone());
+        i += 1;
+    }
+    Ok(rest)
+}
+
+fn opens(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::LParen
+            | TokenKind::LCurly
+            | TokenKind::LBrack
+            | TokenKind::TemplateStringStart
+            | TokenKind::TemplateDataStart
+    )
+}
+
+fn closes(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::RParen | TokenKind::RCurly | TokenKind::RBrack
+    )
+}
+
+/// The index just past the bracket closing the one at `open`.
+fn group_end(tokens: &[Token<'_>], open: usize) -> Result<usize, MacroError> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if opens(token.kind) {
+            depth += 1;
+        } else if closes(token.kind) {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(i + 1);
+            }
+        }
+    }
+    Err(MacroError::new(
+        &tokens[open].span,
+        format!("This `{}` is never closed.", tokens[open].raw),
+    ))
+}
+
+fn expect<'t, 'i>(
+    tokens: &'t [Token<'i>],
+    at: usize,
+    kind: TokenKind,
+    after: &Token<'_>,
+    expected: &str,
+) -> Result<&'t Token<'i>, MacroError> {
+    match tokens.get(at) {
+        Some(token) if token.kind == kind => Ok(token),
+        Some(token) => Err(MacroError::new(
+            &token.span,
+            format!("Expected {expected} here."),
+        )),
+        None => Err(MacroError::new(
+            &after.span.only_end(),
+            format!("Expected {expected} after this."),
+        )),
+    }
+}
+
+/// Parses `macro Name(params) { body }` starting at `start`.
+fn definition<'i>(
+    tokens: &[Token<'i>],
+    start: usize,
+) -> Result<(Token<'i>, Macro<'i>, usize), MacroError> {
+    let keyword = &tokens[start];
+    let name = expect(
+        tokens,
+        start + 1,
+        TokenKind::UppercaseIdentifier,
+        keyword,
+        "an uppercase macro name",
+    )?;
+    let open = expect(tokens, start + 2, TokenKind::LParen, name, "`(`")?;
+
+    let mut params = Vec::new();
+    let mut i = start + 3;
+    loop {
+        match tokens.get(i) {
+            Some(token) if token.kind == TokenKind::RParen => break,
+            Some(token)
+                if matches!(
+                    token.kind,
+                    TokenKind::LowercaseIdentifier | TokenKind::UppercaseIdentifier
+                ) =>
+            {
+                if params.contains(&token.raw) {
+                    return Err(MacroError::new(
+                        &token.span,
+                        format!("Parameter `{}` is already defined.", token.raw),
+                    ));
+                }
+                params.push(token.raw);
+                i += 1;
+                match tokens.get(i) {
+                    Some(token) if token.kind == TokenKind::Comma => i += 1,
+                    _ => {
+                        expect(tokens, i, TokenKind::RParen, &tokens[i - 1], "`,` or `)`")?;
+                    }
+                }
+            }
+            Some(token) => {
+                return Err(MacroError::new(
+                    &token.span,
+                    "Expected a parameter name here.",
+                ));
+            }
+            None => return Err(MacroError::new(&open.span, "This `(` is never closed.")),
+        }
+    }
+
+    expect(tokens, i + 1, TokenKind::LCurly, &tokens[i], "`{`")?;
+    let end = group_end(tokens, i + 1)?;
+    let body = tokens[i + 2..end - 1].to_vec();
+    Ok((name.clone(), Macro { params, body }, end))
+}
+
+/// Parses `Name!(args)` at `start`, if `Name` is a macro.
+fn invocation<'m, 'i>(
+    macros: &'m HashMap<&'i str, Macro<'i>>,
+    tokens: &[Token<'i>],
+    start: usize,
+) -> Result<Option<Invocation<'m, 'i>>, MacroError> {
+    let name = &tokens[start];
+    if name.kind != TokenKind::UppercaseIdentifier {
+        return Ok(None);
+    }
+    let Some(mac) = macros.get(name.raw) else {
+        return Ok(None);
+    };
+    let (Some(bang), Some(open)) = (tokens.get(start + 1), tokens.get(start + 2)) else {
+        return Ok(None);
+    };
+    if bang.kind != TokenKind::Bang || open.kind != TokenKind::LParen {
+        return Ok(None);
+    }
+
+    let end = group_end(tokens, start + 2)?;
+    let mut args = vec![Vec::new()];
+    let mut depth = 0usize;
+    for token in &tokens[start + 3..end - 1] {
+        if opens(token.kind) {
+            depth += 1;
+        } else if closes(token.kind) {
+            depth -= 1;
+        } else if depth == 0 && token.kind == TokenKind::Comma {
+            args.push(Vec::new());
+            continue;
+        }
+        args.last_mut().unwrap().push(token.clone());
+    }
+    if args.last().is_some_and(Vec::is_empty) {
+        args.pop();
+    }
+
+    let span = name.span.join(tokens[end - 1].span.clone());
+    if args.len() != mac.params.len() {
+        return Err(MacroError::new(
+            &span,
+            format!(
+                "Macro `{}` takes {} argument(s), but {} were given.",
+                name.raw,
+                mac.params.len(),
+                args.len(),
+            ),
+        ));
+    }
+    Ok(Some(Invocation {
+        mac,
+        span,
+        args,
+        end,
+    }))
+}
+
+/// The tokens an invocation expands to.
+fn invoke<'i>(
+    macros: &HashMap<&'i str, Macro<'i>>,
+    Invocation {
+        mac, span, args, ..
+    }: Invocation<'_, 'i>,
+    depth: usize,
+) -> Result<Vec<Token<'i>>, MacroError> {
+    if depth >= MAX_DEPTH {
+        return Err(MacroError::new(
+            &span,
+            format!("Macros expand more than {MAX_DEPTH} levels deep here."),
+        ));
+    }
+    let mut expanded = Vec::with_capacity(mac.body.len());
+    for token in &mac.body {
+        let is_name = matches!(
+            token.kind,
+            TokenKind::LowercaseIdentifier | TokenKind::UppercaseIdentifier
+        );
+        let param = if is_name {
+            mac.params.iter().position(|param| *param == token.raw)
+        } else {
+            None
+        };
+        match param {
+            Some(param) => expanded.extend(args[param].iter().cloned()),
+            None => expanded.push(token.clone()),
+        }
+    }
+    for token in &mut expanded {
+        token.span = token.span.expanded_from(SUGAR, &span);
+    }
+    expand(macros, expanded, depth + 1)
+}
+
+/// Expands the invocations among `tokens`.
+fn expand<'i>(
+    macros: &HashMap<&'i str, Macro<'i>>,
+    tokens: Vec<Token<'i>>,
+    depth: usize,
+) -> Result<Vec<Token<'i>>, MacroError> {
+    let mut expanded = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].kind == TokenKind::Macro {
+            return Err(MacroError::new(
+                &tokens[i].span,
+                "Macros can only be defined at the top level of a file.",
+            ));
+        }
+        if let Some(invocation) = invocation(macros, &tokens, i)? {
+            i = invocation.end;
+            expanded.extend(invoke(macros, invocation, depth)?);
+            continue;
+        }
+        expanded.push(tokens[i].clone());
+        i += 1;
+    }
+    Ok(expanded)
+}
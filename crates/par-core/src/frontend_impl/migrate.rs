@@ -0,0 +1,268 @@
+//! Source-to-source rewrites from an older edition of Par to a newer one.
+//!
+//! Every migration belongs to the edition that changed the syntax it rewrites.
+//! It inspects the token stream of a file, lexed in the edition the file is
+//! written in, and proposes edits that replace the text of individual tokens.
+//! Comments and whitespace are never touched, so a migrated file keeps its
+//! layout and documentation.
+
+use super::lexer::{Edition, Lexeme, Lexer, Token, TokenKind};
+use crate::location::{FileName, Span};
+
+/// A single syntax change, with the rewrite that undoes it in old sources.
+pub struct Migration {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The first edition with the new syntax. Sources in an earlier one are
+    /// rewritten when they're moved to this one or a later one.
+    pub edition: Edition,
+    find: fn(&[Token]) -> Vec<(usize, String)>,
+}
+
+/// All known migrations, oldest first.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "reserved-names",
+        description: "`macro` and `derive` became keywords, so names spelled that way get a `_` after them",
+        edition: Edition::E2026,
+        find: rename_reserved,
+    },
+    Migration {
+        name: "underscore-variable",
+        description: "an argument written `_` became a placeholder, so a variable called `_` gets a name",
+        edition: Edition::E2026,
+        find: rename_underscore,
+    },
+];
+
+/// The name of the edit that changes a file's `edition` declaration.
+const EDITION_DECLARATION: &str = "edition";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationEdit {
+    pub migration: &'static str,
+    pub span: Span,
+    pub original: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigratedSource {
+    pub source: String,
+    /// The edition the source was written in.
+    pub from: Edition,
+    pub edits: Vec<MigrationEdit>,
+}
+
+impl MigratedSource {
+    pub fn is_changed(&self) -> bool {
+        !self.edits.is_empty()
+    }
+}
+
+/// Moves `source` to the edition `to`, applying the migrations of every
+/// edition after the one it's written in, up to `to`. A source without an
+/// `edition` declaration is taken to be in `undeclared`. The declaration is
+/// updated to name `to`, and added if the source didn't have one and `to`
+/// isn't [`Edition::UNDECLARED`].
+pub fn migrate_source(
+    source: &str,
+    file: &FileName,
+    undeclared: Edition,
+    to: Edition,
+) -> MigratedSource {
+    let mut lexer = Lexer::with_edition(source, file.clone(), undeclared);
+    let tokens = lexer
+        .by_ref()
+        .filter_map(|lexeme| match lexeme {
+            Lexeme::Token(token) => Some(token),
+            Lexeme::Comment(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let from = lexer.edition();
+
+    let mut edits = Vec::new();
+    for migration in MIGRATIONS {
+        if migration.edition <= from || migration.edition > to {
+            continue;
+        }
+        for (index, replacement) in (migration.find)(&tokens) {
+            let token = &tokens[index];
+            edits.push(MigrationEdit {
+                migration: migration.name,
+                span: token.span(),
+                original: token.raw.to_owned(),
+                replacement,
+            });
+        }
+    }
+    edits.sort_by_key(|edit| start_offset(&edit.span));
+    edits.dedup_by_key(|edit| start_offset(&edit.span));
+
+    let mut migrated = String::with_capacity(source.len());
+    let mut copied = 0;
+    if from < to {
+        match declared_year(&tokens) {
+            Some(year) => edits.insert(
+                0,
+                MigrationEdit {
+                    migration: EDITION_DECLARATION,
+                    span: year.span(),
+                    original: year.raw.to_owned(),
+                    replacement: to.to_string(),
+                },
+            ),
+            None if to != Edition::UNDECLARED => {
+                migrated.push_str(&format!("edition {to}\n"));
+                edits.insert(
+                    0,
+                    MigrationEdit {
+                        migration: EDITION_DECLARATION,
+                        span: Span::None,
+                        original: String::new(),
+                        replacement: format!("edition {to}"),
+                    },
+                );
+            }
+            None => {}
+        }
+    }
+    for edit in &edits {
+        let Some((start, end)) = edit.span.points() else {
+            continue;
+        };
+        migrated.push_str(&source[copied..start.offset as usize]);
+        migrated.push_str(&edit.replacement);
+        copied = end.offset as usize;
+    }
+    migrated.push_str(&source[copied..]);
+
+    MigratedSource {
+        source: migrated,
+        from,
+        edits,
+    }
+}
+
+fn start_offset(span: &Span) -> u32 {
+    span.start().map_or(0, |start| start.offset)
+}
+
+/// The year token of the `edition` declaration at the top of a source.
+fn declared_year<'t, 's>(tokens: &'t [Token<'s>]) -> Option<&'t Token<'s>> {
+    match tokens {
+        [edition, year, ..]
+            if edition.kind == TokenKind::Edition && year.kind == TokenKind::Integer =>
+        {
+            Some(year)
+        }
+        _ => None,
+    }
+}
+
+/// Finds the names the keywords of edition 2026 used to be. They're renamed
+/// the same way in every file, so a label or a name shared between files stays
+/// the same.
+fn rename_reserved(tokens: &[Token]) -> Vec<(usize, String)> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| {
+            token.kind == TokenKind::LowercaseIdentifier && matches!(token.raw, "macro" | "derive")
+        })
+        .map(|(index, token)| (index, format!("{}_", token.raw)))
+        .collect()
+}
+
+/// Finds the variables called `_`, and renames all of them to a name no
+/// other token of the file has.
+fn rename_underscore(tokens: &[Token]) -> Vec<(usize, String)> {
+    let underscores = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| token.kind == TokenKind::LowercaseIdentifier && token.raw == "_")
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if underscores.is_empty() {
+        return Vec::new();
+    }
+    let name = (0..)
+        .map(|n| match n {
+            0 => String::from("unnamed"),
+            n => format!("unnamed{n}"),
+        })
+        .find(|name| tokens.iter().all(|token| token.raw != name))
+        .expect("a free name");
+    underscores
+        .into_iter()
+        .map(|index| (index, name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod migrate_test {
+    use super::*;
+
+    const FILE: FileName = FileName(arcstr::literal!("Test.par"));
+
+    fn migrate(source: &str) -> MigratedSource {
+        migrate_source(source, &FILE, Edition::LATEST, Edition::LATEST)
+    }
+
+    #[test]
+    fn renames_reserved_names_and_keeps_comments() {
+        let source = "edition 2025\n// A derive.\ndef Main = do {\n  let derive = .macro! /* kept */\n} in derive // inline\n";
+        let migrated = migrate(source);
+        assert_eq!(migrated.from, Edition::E2025);
+        assert_eq!(
+            migrated.source,
+            "edition 2026\n// A derive.\ndef Main = do {\n  let derive_ = .macro_! /* kept */\n} in derive_ // inline\n"
+        );
+        assert_eq!(
+            migrated
+                .edits
+                .iter()
+                .map(|edit| edit.migration)
+                .collect::<Vec<_>>(),
+            vec![
+                "edition",
+                "reserved-names",
+                "reserved-names",
+                "reserved-names"
+            ]
+        );
+    }
+
+    #[test]
+    fn names_underscore_variables_apart_from_the_others() {
+        let source =
+            "edition 2025\ndef Main = do {\n  let _ = 5\n  let unnamed = 3\n} in F(_, unnamed)\n";
+        assert_eq!(
+            migrate(source).source,
+            "edition 2026\ndef Main = do {\n  let unnamed1 = 5\n  let unnamed = 3\n} in F(unnamed1, unnamed)\n"
+        );
+    }
+
+    #[test]
+    fn leaves_newer_sources_alone() {
+        let source = "def Main = do {\n  let x = 1\n} in F(_, x)\n";
+        let migrated = migrate_source(source, &FILE, Edition::E2026, Edition::LATEST);
+        assert!(!migrated.is_changed());
+        assert_eq!(migrated.source, source);
+    }
+
+    #[test]
+    fn goes_by_the_source_and_target_editions() {
+        let source = "def A = derive\n";
+        assert!(!migrate(source).is_changed());
+        assert_eq!(
+            migrate_source(source, &FILE, Edition::E2025, Edition::LATEST).source,
+            "edition 2026\ndef A = derive_\n"
+        );
+        assert!(!migrate_source(source, &FILE, Edition::E2025, Edition::E2025).is_changed());
+
+        let declared = "edition 2025\ndef A = derive\n";
+        let kept = migrate_source(declared, &FILE, Edition::LATEST, Edition::E2025);
+        assert!(!kept.is_changed());
+    }
+}
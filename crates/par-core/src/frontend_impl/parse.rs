@@ -1,3 +1,5 @@
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
 use super::{
     language::{
         Apply, ApplyBranch, ApplyBranches, ArithmeticOperator, Command, CommandBranch,
@@ -6,10 +8,12 @@ use super::{
         TypeParameter, Unresolved,
     },
     lexer::{
-        Comment, CommentKind, Input, Token, TokenKind, lex, lex_with_comments,
+        Comment, CommentKind, Edition, Input, Lexed, Token, TokenKind, lex, lex_with_comments,
         unescape_template_text,
     },
+    macros::expand_macros,
 };
+use crate::frontend_impl::derive::{Derivable, DeriveClause, expand_derives};
 use crate::frontend_impl::program::DefinitionBody;
 use crate::frontend_impl::{
     language::LocalName,
@@ -17,18 +21,21 @@ use crate::frontend_impl::{
         Declaration, Definition, DocComment, ImportDecl, ImportPath, Module, ModuleDecl,
         SourceFile, TypeDef,
     },
-    types::Type,
+    types::{Type, UnionKind, visit},
 };
 use crate::location::{FileName, Point, Span, Spanning};
+use crate::stack;
 use arcstr::ArcStr;
 use bytes::Bytes;
 use core::fmt::Display;
+use indexmap::IndexMap;
 use miette::{SourceOffset, SourceSpan};
 use num_bigint::BigInt;
 use par_runtime::{
     primitive::{ParString, Primitive},
     readback::Number,
 };
+use std::cell::Cell;
 use std::collections::BTreeMap;
 use winnow::token::literal;
 use winnow::{
@@ -78,15 +85,14 @@ impl<I: Stream, C> AddContext<I, C> for ParseContextError<C> {
                 ContextError::new().add_context(input, token_start, context),
             )
         };
-        if self.context.is_empty() {
-            self.context.push(new_context(context));
-            return self;
-        }
-        let last = self.context.pop().unwrap();
-        if last.0 != input.eof_offset() {
-            self.context.push(new_context(context));
-            return self;
-        }
+        let last = match self.context.pop() {
+            Some(last) if last.0 == input.eof_offset() => last,
+            last => {
+                self.context.extend(last);
+                self.context.push(new_context(context));
+                return self;
+            }
+        };
         let last = (
             last.0.min(input.eof_offset()),
             last.1.add_context(input, token_start, context),
@@ -132,6 +138,48 @@ where
     )
 }
 
+/// How many levels deep the parser may go into nested expressions and types,
+/// unless set otherwise with [`parse_lexed_with_limit`]. Real code stays well
+/// below this.
+pub(crate) const MAX_NESTING_DEPTH: usize = 128;
+
+/// What nests, for saying which went too deep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Nesting {
+    Expression,
+    Type,
+}
+
+thread_local! {
+    /// How many levels deep the file being parsed may go.
+    static NESTING_LIMIT: Cell<usize> = const { Cell::new(MAX_NESTING_DEPTH) };
+    /// How deeply the parser is nested right now.
+    static NESTING_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Where the parser went past `NESTING_LIMIT`, and into what, if it did.
+    static TOO_DEEP: Cell<Option<(Span, Nesting)>> = const { Cell::new(None) };
+    /// The edition of the file being parsed, set by `edition_decl`.
+    static EDITION: Cell<Edition> = const { Cell::new(Edition::UNDECLARED) };
+}
+
+/// Runs `parser` one level deeper into `nesting`. Past `NESTING_LIMIT`, fails
+/// without backtracking instead, recording where in `TOO_DEEP`.
+fn nested<'i, O>(
+    input: &mut Input<'i>,
+    nesting: Nesting,
+    parser: impl FnOnce(&mut Input<'i>) -> Result<O>,
+) -> Result<O> {
+    let depth = NESTING_DEPTH.get();
+    if depth >= NESTING_LIMIT.get() {
+        let span = input.first().map_or(Span::None, Token::span);
+        TOO_DEEP.set(Some((span, nesting)));
+        return Err(ErrMode::Cut(ParserError::from_input(input)));
+    }
+    NESTING_DEPTH.set(depth + 1);
+    let result = stack::grow(|| parser(input));
+    NESTING_DEPTH.set(depth);
+    result
+}
+
 fn commit_after<Input, Prefix, Output, Error, PrefixParser, ParseNext>(
     prefix: PrefixParser,
     parser: ParseNext,
@@ -202,6 +250,25 @@ fn global_binding_name(input: &mut Input) -> Result<GlobalName<Unresolved>> {
         .parse_next(input)
 }
 
+/// `edition 2025`, before anything else in the file. The lexer has already
+/// read the keywords of the file in it, and the sugar of the rest of it is
+/// read in it from here on.
+fn edition_decl(input: &mut Input) -> Result<Edition> {
+    commit_after(
+        t(TokenKind::Edition),
+        t(TokenKind::Integer)
+            .verify_map(|token| Edition::from_year(token.raw))
+            .context(StrContext::Expected(StrContextValue::StringLiteral("2025")))
+            .context(StrContext::Expected(StrContextValue::StringLiteral("2026"))),
+    )
+    .map(|(_, edition)| {
+        EDITION.set(edition);
+        edition
+    })
+    .context(StrContext::Label("edition declaration"))
+    .parse_next(input)
+}
+
 fn module_decl(input: &mut Input) -> Result<ModuleDecl> {
     (
         opt(t(TokenKind::Export)),
@@ -322,9 +389,12 @@ fn import_statement(input: &mut Input) -> Result<Vec<ImportDecl>> {
 
 #[derive(Clone)]
 enum ModuleItem<Expr> {
-    TypeDef(TypeDef<Unresolved>),
+    TypeDef(TypeDef<Unresolved>, Option<DeriveClause>),
     Declaration(Declaration<Unresolved>),
-    Definition(Definition<Expr, Unresolved>, Option<Type<Unresolved>>),
+    Definition(
+        Definition<Expr, Unresolved>,
+        Option<(Vec<LocalName>, Type<Unresolved>)>,
+    ),
 }
 
 fn mark_exported_type_def(
@@ -351,7 +421,9 @@ fn mark_exported_declaration(
 
 fn export_block_item(input: &mut Input) -> Result<ModuleItem<Expression<Unresolved>>> {
     alt((
-        type_def.map(|type_def| ModuleItem::TypeDef(mark_exported_type_def(type_def, None))),
+        type_def.map(|(type_def, derives)| {
+            ModuleItem::TypeDef(mark_exported_type_def(type_def, None), derives)
+        }),
         declaration.map(|declaration| {
             ModuleItem::Declaration(mark_exported_declaration(declaration, None))
         }),
@@ -363,7 +435,7 @@ fn export_block_item(input: &mut Input) -> Result<ModuleItem<Expression<Unresolv
 fn export_statement(input: &mut Input) -> Result<Vec<ModuleItem<Expression<Unresolved>>>> {
     enum ExportStatement {
         Block(Vec<ModuleItem<Expression<Unresolved>>>),
-        TypeDef(TypeDef<Unresolved>),
+        TypeDef(TypeDef<Unresolved>, Option<DeriveClause>),
         Declaration(Declaration<Unresolved>),
     }
 
@@ -375,16 +447,16 @@ fn export_statement(input: &mut Input) -> Result<Vec<ModuleItem<Expression<Unres
                 (repeat(0.., export_block_item), t(TokenKind::RCurly)),
             )
             .map(|(_lcurly, (items, _rcurly))| ExportStatement::Block(items)),
-            type_def.map(ExportStatement::TypeDef),
+            type_def.map(|(type_def, derives)| ExportStatement::TypeDef(type_def, derives)),
             declaration.map(ExportStatement::Declaration),
         )),
     )
     .map(|(export_kw, statement)| match statement {
         ExportStatement::Block(items) => items,
-        ExportStatement::TypeDef(type_def) => vec![ModuleItem::TypeDef(mark_exported_type_def(
-            type_def,
-            Some(export_kw.span.clone()),
-        ))],
+        ExportStatement::TypeDef(type_def, derives) => vec![ModuleItem::TypeDef(
+            mark_exported_type_def(type_def, Some(export_kw.span.clone())),
+            derives,
+        )],
         ExportStatement::Declaration(declaration) => {
             vec![ModuleItem::Declaration(mark_exported_declaration(
                 declaration,
@@ -399,7 +471,7 @@ fn export_statement(input: &mut Input) -> Result<Vec<ModuleItem<Expression<Unres
 fn module_item_statement(input: &mut Input) -> Result<Vec<ModuleItem<Expression<Unresolved>>>> {
     alt((
         export_statement,
-        type_def.map(|type_def| vec![ModuleItem::TypeDef(type_def)]),
+        type_def.map(|(type_def, derives)| vec![ModuleItem::TypeDef(type_def, derives)]),
         declaration.map(|declaration| vec![ModuleItem::Declaration(declaration)]),
         definition.map(|(definition, typ)| vec![ModuleItem::Definition(definition, typ)]),
     ))
@@ -421,40 +493,52 @@ impl ProgramParseError {
 
 fn source_file(
     mut input: Input,
-) -> std::result::Result<SourceFile<Expression<Unresolved>>, ProgramParseError> {
+) -> std::result::Result<(SourceFile<Expression<Unresolved>>, Vec<DeriveClause>), ProgramParseError>
+{
     let parser = repeat(
         0..,
         module_item_statement.context(StrContext::Label("item")),
     )
-    .fold(Module::default, |mut acc, item| {
-        for item in item {
-            match item {
-                ModuleItem::TypeDef(type_def) => {
-                    acc.type_defs.push(type_def);
-                }
-                ModuleItem::Declaration(dec) => {
-                    acc.declarations.push(dec);
-                }
-                ModuleItem::Definition(Definition { span, name, body }, annotation) => {
-                    if let Some(typ) = annotation {
-                        acc.declarations.push(Declaration {
-                            span: span.clone(),
-                            exported: false,
-                            doc: None,
-                            name: name.clone(),
-                            typ,
-                        });
+    .fold(
+        || (Module::default(), Vec::new()),
+        |(mut acc, mut derives), item| {
+            for item in item {
+                match item {
+                    ModuleItem::TypeDef(type_def, derive) => {
+                        if let Some(derive) = derive {
+                            derives.push(DeriveClause {
+                                type_def: acc.type_defs.len(),
+                                ..derive
+                            });
+                        }
+                        acc.type_defs.push(type_def);
+                    }
+                    ModuleItem::Declaration(dec) => {
+                        acc.declarations.push(dec);
+                    }
+                    ModuleItem::Definition(Definition { span, name, body }, annotation) => {
+                        if let Some((parameter_names, typ)) = annotation {
+                            acc.declarations.push(Declaration {
+                                span: span.clone(),
+                                exported: false,
+                                doc: None,
+                                name: name.clone(),
+                                parameter_names,
+                                typ,
+                            });
+                        }
+                        acc.definitions.push(Definition { span, name, body });
                     }
-                    acc.definitions.push(Definition { span, name, body });
                 }
             }
-        }
-        acc
-    });
+            (acc, derives)
+        },
+    );
 
     let start = input.checkpoint();
     (
         (
+            opt(edition_decl),
             opt(module_decl),
             repeat(0.., import_statement).map(|groups: Vec<Vec<ImportDecl>>| {
                 groups.into_iter().flatten().collect::<Vec<ImportDecl>>()
@@ -479,15 +563,22 @@ fn source_file(
             ))),
     )
         .parse_next(&mut input)
-        .map(|((module_decl, imports, body), _eof)| SourceFile {
-            module_decl,
-            imports,
-            body,
+        .map(|((_, module_decl, imports, (body, derives)), _eof)| {
+            (
+                SourceFile {
+                    module_decl,
+                    imports,
+                    body,
+                },
+                derives,
+            )
         })
         .map_err(|e: Error| {
-            let e = e.into_inner().unwrap_or_else(|_err| {
-                panic!("complete parsers should not report `ErrMode::Incomplete(_)`")
-            });
+            // Complete parsers don't report `ErrMode::Incomplete(_)`, which
+            // has no context to show anyway.
+            let e = e
+                .into_inner()
+                .unwrap_or_else(|_| ParseContextError { context: vec![] });
 
             ProgramParseError {
                 offset: winnow::stream::Offset::offset_from(&input, &start),
@@ -497,7 +588,7 @@ fn source_file(
 }
 
 #[derive(Debug, Clone, miette::Diagnostic)]
-#[diagnostic(severity(Error))]
+#[diagnostic(code(PAR0001), severity(Error))]
 pub struct SyntaxError {
     #[label]
     source_span: SourceSpan,
@@ -548,43 +639,89 @@ pub(crate) fn parse_source_file(
     input: &str,
     file: FileName,
 ) -> std::result::Result<SourceFile<Expression<Unresolved>>, SyntaxError> {
-    let lexed = lex_with_comments(&input, &file);
+    parse_lexed(input, lex_with_comments(input, &file))
+}
+
+/// Parses a file that was already lexed by `lex_with_comments`, after
+/// expanding its macros.
+pub(crate) fn parse_lexed(
+    input: &str,
+    lexed: Lexed<'_>,
+) -> std::result::Result<SourceFile<Expression<Unresolved>>, SyntaxError> {
+    parse_lexed_with_limit(input, lexed, MAX_NESTING_DEPTH)
+}
+
+/// Like [`parse_lexed`], with expressions and types nesting at most
+/// `max_nesting_depth` levels deep.
+pub(crate) fn parse_lexed_with_limit(
+    input: &str,
+    lexed: Lexed<'_>,
+    max_nesting_depth: usize,
+) -> std::result::Result<SourceFile<Expression<Unresolved>>, SyntaxError> {
     let comments = lexed.comments;
-    let tokens = lexed.tokens;
+    let tokens = match expand_macros(lexed.tokens) {
+        Ok(tokens) => tokens,
+        Err(e) => return Err(SyntaxError::at(input, e.span, e.message)),
+    };
+    NESTING_LIMIT.set(max_nesting_depth);
+    NESTING_DEPTH.set(0);
+    TOO_DEEP.set(None);
+    EDITION.set(Edition::UNDECLARED);
     let e = match source_file(Input::new(&tokens)) {
-        Ok(mut x) => {
+        Ok((mut x, derives)) => {
             attach_doc_comments(input, &comments, &mut x);
+            if let Err(e) = expand_derives(&mut x, derives) {
+                return Err(SyntaxError::at(input, e.span, e.message));
+            }
             return Ok(x);
         }
         Err(e) => e,
     };
-    // Empty input doesn't error so this won't panic.
-    let error_tok = tokens
+    if let Some((span, nesting)) = TOO_DEEP.take() {
+        let help = match nesting {
+            Nesting::Expression => {
+                "expression too deeply nested here; try naming some of its parts with `let`\n"
+            }
+            Nesting::Type => {
+                "type too deeply nested here; try naming some of its parts with `type`\n"
+            }
+        };
+        return Err(SyntaxError::at(input, span, help.to_owned()));
+    }
+    let error_span = tokens
         .get(e.offset())
-        .unwrap_or(tokens.last().unwrap())
-        .clone();
-    let error_tok_span = error_tok.span();
-    Err(SyntaxError {
-        span: error_tok_span.clone(),
-        source_span: match error_tok_span {
-            Span::None => SourceSpan::new(SourceOffset::from(0), input.len()),
-            span @ Span::At { start, .. } => SourceSpan::new(
-                SourceOffset::from(start.offset as usize),
-                if span.len() == 1 {
-                    // miette unicode format for 1 length span is a hard-to-notice line, so don't set length to 1.
-                    0
-                } else {
-                    span.len() as usize
-                },
-            ),
-        },
-        help: e
-            .inner()
+        .or(tokens.last())
+        .map_or(Span::None, Token::span);
+    Err(SyntaxError::at(
+        input,
+        error_span,
+        e.inner()
             .context
             .iter()
             .map(|x| x.1.to_string().chars().chain(['\n']).collect::<String>())
             .collect::<String>(),
-    })
+    ))
+}
+
+impl SyntaxError {
+    fn at(input: &str, span: Span, help: String) -> Self {
+        Self {
+            source_span: match &span {
+                Span::None => SourceSpan::new(SourceOffset::from(0), input.len()),
+                span @ Span::At { start, .. } => SourceSpan::new(
+                    SourceOffset::from(start.offset as usize),
+                    if span.len() == 1 {
+                        // miette unicode format for 1 length span is a hard-to-notice line, so don't set length to 1.
+                        0
+                    } else {
+                        span.len() as usize
+                    },
+                ),
+            },
+            help,
+            span,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -707,6 +844,57 @@ fn attach_doc_comments(
         previous_barrier_end = barrier.end.offset;
         comment_start = comment_end;
     }
+
+    for type_def in &mut source_file.body.type_defs {
+        type_def.branch_docs = branch_doc_comments(comments, &type_def.typ);
+    }
+}
+
+/// The doc comments right before the branches of the `either` and `choice`
+/// types in a type definition, by label. Of nested branches with the same
+/// label, the first one documented counts.
+fn branch_doc_comments(
+    comments: &[Comment<'_>],
+    typ: &Type<Unresolved>,
+) -> IndexMap<LocalName, DocComment> {
+    let mut labels = Vec::new();
+    collect_branch_labels(typ, &mut labels);
+    labels.sort_by_key(|label| label.span.start().map(|start| start.offset));
+
+    let mut docs = IndexMap::new();
+    let mut previous_end = typ.span().start().map_or(0, |start| start.offset);
+    for label in labels {
+        let Some((start, end)) = label.span.points() else {
+            continue;
+        };
+        let before = comments
+            .iter()
+            .filter(|comment| {
+                comment
+                    .span
+                    .points()
+                    .is_some_and(|(comment_start, comment_end)| {
+                        comment_start.offset >= previous_end && comment_end.offset <= start.offset
+                    })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(doc) = doc_comment_before_item(&before, start) {
+            docs.entry(label).or_insert(doc);
+        }
+        previous_end = end.offset;
+    }
+    docs
+}
+
+fn collect_branch_labels(typ: &Type<Unresolved>, labels: &mut Vec<LocalName>) {
+    if let Type::Either(_, branches) | Type::Choice(_, branches) = typ {
+        labels.extend(branches.keys().cloned());
+    }
+    let _ = visit::continue_(typ, |child| {
+        collect_branch_labels(child, labels);
+        Ok::<_, ()>(())
+    });
 }
 
 fn is_explicit_declaration(source: &str, declaration: &Declaration<Unresolved>) -> bool {
@@ -768,7 +956,8 @@ fn doc_comment_before_item(comments: &[Comment<'_>], item_start: Point) -> Optio
             Some(DocComment {
                 span: run
                     .first()
-                    .map(|first| first.span.join(run.last().unwrap().span.clone()))
+                    .zip(run.last())
+                    .map(|(first, end)| first.span.join(end.span.clone()))
                     .unwrap_or_else(|| last.span.clone()),
                 markdown: markdown.into(),
             })
@@ -781,10 +970,13 @@ fn has_blank_line_between(previous_end_row: u32, next_start_row: u32) -> bool {
 }
 
 fn strip_line_comment(raw: &str) -> String {
-    raw.strip_prefix("//")
-        .unwrap_or(raw)
-        .strip_prefix(' ')
-        .unwrap_or_else(|| raw.strip_prefix("//").unwrap_or(raw))
+    // `///` works too, for those used to it from other languages.
+    let text = raw
+        .strip_prefix("///")
+        .or_else(|| raw.strip_prefix("//"))
+        .unwrap_or(raw);
+    text.strip_prefix(' ')
+        .unwrap_or(text)
         .trim_end_matches('\r')
         .to_string()
 }
@@ -806,50 +998,140 @@ pub fn parse_bytes(input: &str, file: &FileName) -> Option<Vec<u8>> {
         .ok()
 }
 
-fn type_def(input: &mut Input) -> Result<TypeDef<Unresolved>> {
+fn type_def(input: &mut Input) -> Result<(TypeDef<Unresolved>, Option<DeriveClause>)> {
     commit_after(
         t(TokenKind::Type),
-        (global_binding_name, type_params, t(TokenKind::Eq), typ),
+        (
+            global_binding_name,
+            type_params,
+            t(TokenKind::Eq),
+            typ,
+            opt(derive_clause),
+        ),
     )
-    .map(|(pre, (name, type_params, _, typ))| TypeDef {
-        span: pre.span.join(typ.span()),
-        exported: false,
-        doc: None,
-        name,
-        params: type_params.map_or_else(Vec::new, |(_, params)| params),
-        typ,
+    .map(|(pre, (name, type_params, _, typ, derives))| {
+        let end = derives
+            .as_ref()
+            .map_or_else(|| typ.span(), |derives| derives.span.clone());
+        (
+            TypeDef {
+                span: pre.span.join(end),
+                exported: false,
+                doc: None,
+                branch_docs: IndexMap::new(),
+                name,
+                params: type_params.map_or_else(Vec::new, |(_, params)| params),
+                typ,
+                function: None,
+            },
+            derives,
+        )
     })
     .context(StrContext::Label("type definition"))
     .parse_next(input)
 }
 
+/// `derive Equals, Compare`. The index of the type definition it belongs to
+/// is filled in once the whole file is parsed.
+fn derive_clause(input: &mut Input) -> Result<DeriveClause> {
+    commit_after(
+        t(TokenKind::Derive),
+        list1(
+            uppercase_identifier
+                .verify_map(|(span, name)| Derivable::from_name(&name).map(|d| (span, d)))
+                .context(StrContext::Expected(StrContextValue::StringLiteral(
+                    "Equals",
+                )))
+                .context(StrContext::Expected(StrContextValue::StringLiteral(
+                    "Compare",
+                )))
+                .context(StrContext::Expected(StrContextValue::StringLiteral("Json")))
+                .context(StrContext::Expected(StrContextValue::StringLiteral(
+                    "Codec",
+                ))),
+        ),
+    )
+    .map(|(pre, derived)| DeriveClause {
+        span: derived
+            .last()
+            .map_or_else(|| pre.span(), |(span, _)| pre.span.join(span.clone())),
+        type_def: 0,
+        derived,
+    })
+    .context(StrContext::Label("derive clause"))
+    .parse_next(input)
+}
+
 fn declaration(input: &mut Input) -> Result<Declaration<Unresolved>> {
     commit_after(
         t(TokenKind::Dec),
-        (global_binding_name, t(TokenKind::Colon), typ),
+        (
+            global_binding_name,
+            t(TokenKind::Colon),
+            declaration_type.with_taken(),
+        ),
     )
-    .map(|(pre, (name, _, typ))| Declaration {
-        span: pre.span.join(typ.span()),
-        exported: false,
-        doc: None,
-        name,
-        typ,
+    .map(|(pre, (name, _, ((parameter_names, typ), taken)))| {
+        // The span of a type like `[Nat] Nat` covers only its `[Nat]`, so the
+        // declaration ends where its last token does.
+        let end = taken
+            .last()
+            .map_or_else(|| typ.span(), |last| last.span.clone());
+        Declaration {
+            span: pre.span.join(end),
+            exported: false,
+            doc: None,
+            name,
+            parameter_names,
+            typ,
+        }
     })
     .context(StrContext::Label("declaration"))
     .parse_next(input)
 }
 
+/// The type of a declaration, which may name the arguments it starts with,
+/// as in `[count: Nat, label: String] R`. The names are returned along with
+/// the function type, so calls can pass the arguments by name.
+fn declaration_type(input: &mut Input) -> Result<(Vec<LocalName>, Type<Unresolved>)> {
+    alt((declaration_named_type, typ.map(|typ| (Vec::new(), typ)))).parse_next(input)
+}
+
+fn declaration_named_type(input: &mut Input) -> Result<(Vec<LocalName>, Type<Unresolved>)> {
+    commit_after(
+        (
+            t(TokenKind::LBrack),
+            peek((local_name, t(TokenKind::Colon))),
+        ),
+        (
+            list1((local_name, t(TokenKind::Colon), typ)),
+            t(TokenKind::RBrack),
+            typ,
+        ),
+    )
+    .map(|((open, _), (parameters, close, then))| {
+        let span = open.span.join(close.span());
+        let names = parameters.iter().map(|(name, _, _)| name.clone()).collect();
+        let typ = parameters.into_iter().rfold(then, |then, (_, _, arg)| {
+            Type::Function(span.clone(), Box::new(arg), Box::new(then), vec![])
+        });
+        (names, typ)
+    })
+    .parse_next(input)
+}
+
 fn definition(
     input: &mut Input,
 ) -> Result<(
     Definition<Expression<Unresolved>, Unresolved>,
-    Option<Type<Unresolved>>,
+    Option<(Vec<LocalName>, Type<Unresolved>)>,
 )> {
     commit_after(
         t(TokenKind::Def),
         (
             global_binding_name,
-            annotation,
+            opt(commit_after(t(TokenKind::Colon), declaration_type))
+                .map(|annotation| annotation.map(|(_, annotation)| annotation)),
             t(TokenKind::Eq),
             alt((
                 t(TokenKind::External).map(|t| DefinitionBody::External(t.span.clone())),
@@ -947,6 +1229,36 @@ where
 }
 
 fn typ(input: &mut Input) -> Result<Type<Unresolved>> {
+    nested(input, Nesting::Type, |input| {
+        (
+            typ_operand,
+            repeat(
+                0..,
+                (
+                    alt((
+                        t(TokenKind::Bar).value(UnionKind::Either),
+                        t(TokenKind::Ampersand).value(UnionKind::Choice),
+                    )),
+                    cut_err(typ_operand),
+                ),
+            ),
+        )
+            .map(|(first, rest): (_, Vec<_>)| {
+                rest.into_iter().fold(first, |left, (kind, right)| {
+                    Type::Union(
+                        left.span().join(right.span()),
+                        kind,
+                        Box::new(left),
+                        Box::new(right),
+                    )
+                })
+            })
+            .parse_next(input)
+    })
+}
+
+/// A type that isn't combined with another one by `|` or `&`.
+fn typ_operand(input: &mut Input) -> Result<Type<Unresolved>> {
     alt((
         typ_var,
         typ_name,
@@ -1508,17 +1820,19 @@ fn multiplicative_operator(input: &mut Input) -> Result<(Span, ArithmeticOperato
 }
 
 fn infix_or(input: &mut Input) -> Result<Expression<Unresolved>> {
-    (infix_and, repeat(0.., (t(TokenKind::Or), infix_and)))
-        .map(
-            |(first, rest): (Expression<Unresolved>, Vec<(_, Expression<Unresolved>)>)| {
-                rest.into_iter().fold(first, |left, (_or_tok, right)| {
-                    fold_condition_expression(left, right, |span, left, right| {
-                        Condition::Or(span, Box::new(left), Box::new(right))
+    nested(input, Nesting::Expression, |input| {
+        (infix_and, repeat(0.., (t(TokenKind::Or), infix_and)))
+            .map(
+                |(first, rest): (Expression<Unresolved>, Vec<(_, Expression<Unresolved>)>)| {
+                    rest.into_iter().fold(first, |left, (_or_tok, right)| {
+                        fold_condition_expression(left, right, |span, left, right| {
+                            Condition::Or(span, Box::new(left), Box::new(right))
+                        })
                     })
-                })
-            },
-        )
-        .parse_next(input)
+                },
+            )
+            .parse_next(input)
+    })
 }
 
 fn infix_and(input: &mut Input) -> Result<Expression<Unresolved>> {
@@ -1587,18 +1901,16 @@ fn infix_comparison(input: &mut Input) -> Result<Expression<Unresolved>> {
                 Expression<Unresolved>,
                 Vec<((Span, ComparisonOperator), Expression<Unresolved>)>,
             )| {
-                if rest.is_empty() {
-                    first
-                } else {
-                    let span = first.span().join(rest.last().unwrap().1.span());
-                    Expression::ComparisonChain {
-                        span,
-                        first: Box::new(first),
-                        rest: rest
-                            .into_iter()
-                            .map(|((op_span, op), expr)| ComparisonStep { op_span, op, expr })
-                            .collect(),
-                    }
+                let Some(span) = rest.last().map(|(_, last)| first.span().join(last.span())) else {
+                    return first;
+                };
+                Expression::ComparisonChain {
+                    span,
+                    first: Box::new(first),
+                    rest: rest
+                        .into_iter()
+                        .map(|((op_span, op), expr)| ComparisonStep { op_span, op, expr })
+                        .collect(),
                 }
             },
         )
@@ -1658,15 +1970,17 @@ fn infix_multiplicative(input: &mut Input) -> Result<Expression<Unresolved>> {
 }
 
 fn infix_unary(input: &mut Input) -> Result<Expression<Unresolved>> {
-    alt((
-        (t(TokenKind::Neg), infix_unary).map(|(neg_tok, expr)| Expression::Neg {
-            span: neg_tok.span.join(expr.span()),
-            op_span: neg_tok.span(),
-            expr: Box::new(expr),
-        }),
-        data_expression,
-    ))
-    .parse_next(input)
+    nested(input, Nesting::Expression, |input| {
+        alt((
+            (t(TokenKind::Neg), infix_unary).map(|(neg_tok, expr)| Expression::Neg {
+                span: neg_tok.span.join(expr.span()),
+                op_span: neg_tok.span(),
+                expr: Box::new(expr),
+            }),
+            data_expression,
+        ))
+        .parse_next(input)
+    })
 }
 
 fn starts_data_expression_token(kind: TokenKind) -> bool {
@@ -1795,35 +2109,39 @@ fn looks_like_infix(input: &Input) -> bool {
 }
 
 fn expression(input: &mut Input) -> Result<Expression<Unresolved>> {
-    if looks_like_infix(input) {
-        let checkpoint = input.checkpoint();
-        match expr_infix.parse_next(input) {
-            Ok(expr) => return Ok(expr),
-            Err(ErrMode::Backtrack(_)) => {
-                input.reset(&checkpoint);
+    nested(input, Nesting::Expression, |input| {
+        if looks_like_infix(input) {
+            let checkpoint = input.checkpoint();
+            match expr_infix.parse_next(input) {
+                Ok(expr) => return Ok(expr),
+                Err(ErrMode::Backtrack(_)) => {
+                    input.reset(&checkpoint);
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => return Err(e),
         }
-    }
-    expression_no_condition
-        .context(StrContext::Label("expression"))
-        .parse_next(input)
+        expression_no_condition
+            .context(StrContext::Label("expression"))
+            .parse_next(input)
+    })
 }
 
 fn expression_without_construction(input: &mut Input) -> Result<Expression<Unresolved>> {
-    if looks_like_infix(input) {
-        let checkpoint = input.checkpoint();
-        match expr_infix.parse_next(input) {
-            Ok(expr) => return Ok(expr),
-            Err(ErrMode::Backtrack(_)) => {
-                input.reset(&checkpoint);
+    nested(input, Nesting::Expression, |input| {
+        if looks_like_infix(input) {
+            let checkpoint = input.checkpoint();
+            match expr_infix.parse_next(input) {
+                Ok(expr) => return Ok(expr),
+                Err(ErrMode::Backtrack(_)) => {
+                    input.reset(&checkpoint);
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => return Err(e),
         }
-    }
-    expression_no_condition_without_construction
-        .context(StrContext::Label("expression"))
-        .parse_next(input)
+        expression_no_condition_without_construction
+            .context(StrContext::Label("expression"))
+            .parse_next(input)
+    })
 }
 
 fn expression_no_condition(input: &mut Input) -> Result<Expression<Unresolved>> {
@@ -1900,9 +2218,9 @@ fn expr_literal_float(input: &mut Input) -> Result<Expression<Unresolved>> {
 
 fn literal_float(input: &mut Input) -> Result<(Span, f64)> {
     t(TokenKind::Float)
-        .map(|token| {
+        .verify_map(|token| {
             let s: String = token.raw.chars().filter(|c| *c != '_').collect();
-            (token.span(), s.parse::<f64>().unwrap())
+            Some((token.span(), s.parse::<f64>().ok()?))
         })
         .parse_next(input)
 }
@@ -1928,19 +2246,22 @@ fn expr_literal_int(input: &mut Input) -> Result<Expression<Unresolved>> {
 
 fn literal_int(input: &mut Input) -> Result<(Span, BigInt)> {
     t(TokenKind::Integer)
-        .map(|token| {
+        .verify_map(|token| {
             let s: String = token.raw.chars().filter(|c| *c != '_').collect();
-            (token.span(), BigInt::parse_bytes(s.as_bytes(), 10).unwrap())
+            Some((token.span(), BigInt::parse_bytes(s.as_bytes(), 10)?))
         })
         .parse_next(input)
 }
 
 fn expr_literal_string(input: &mut Input) -> Result<Expression<Unresolved>> {
     t(TokenKind::String)
-        .map(|token| {
-            // validated in lexer
-            let value = unescaper::unescape(token.raw).unwrap();
-            Expression::Primitive(token.span(), Primitive::String(ParString::from(value)))
+        .verify_map(|token| {
+            // validated in lexer, unless the tokens were made up elsewhere
+            let value = unescaper::unescape(token.raw).ok()?;
+            Some(Expression::Primitive(
+                token.span(),
+                Primitive::String(ParString::from(value)),
+            ))
         })
         .parse_next(input)
 }
@@ -1960,9 +2281,10 @@ fn expr_literal_template(input: &mut Input) -> Result<Expression<Unresolved>> {
 
 fn template_part(input: &mut Input) -> Result<TemplatePart<Unresolved>> {
     alt((
-        t(TokenKind::TemplateText).map(|token| {
-            // validated in lexer
-            TemplatePart::Literal(ArcStr::from(unescape_template_text(token.raw).unwrap()))
+        t(TokenKind::TemplateText).verify_map(|token| {
+            // validated in lexer, unless the tokens were made up elsewhere
+            let text = unescape_template_text(token.raw).ok()?;
+            Some(TemplatePart::Literal(ArcStr::from(text)))
         }),
         (
             t(TokenKind::TemplateStringStart),
@@ -2624,8 +2946,24 @@ fn application(input: &mut Input) -> Result<Expression<Unresolved>> {
         apply,
     )
         .map(|(expr, apply)| match apply {
-            Some((full_span, apply)) => {
-                Expression::Application(expr.span().join(full_span), Box::new(expr), apply)
+            Some((full_span, mut apply)) => {
+                let span = expr.span().join(full_span);
+                let mut placeholders = Vec::new();
+                if EDITION.get() >= Edition::E2026 {
+                    name_placeholders(&mut apply, &mut placeholders);
+                }
+                let application = Expression::Application(span.clone(), Box::new(expr), apply);
+                if placeholders.is_empty() {
+                    return application;
+                }
+                let construct = placeholders.into_iter().rfold(
+                    Construct::Then(Box::new(application)),
+                    |then, name| {
+                        let pattern = Pattern::Name(name.span.clone(), name, None);
+                        Construct::Receive(span.clone(), pattern, Box::new(then), vec![])
+                    },
+                );
+                Expression::Construction(span, construct)
             }
             None => expr,
         })
@@ -2633,6 +2971,45 @@ fn application(input: &mut Input) -> Result<Expression<Unresolved>> {
         .parse_next(input)
 }
 
+/// Gives the arguments written `_` along `apply` fresh names, in order, for
+/// `application` to receive them first. That makes `F(_, x)` a function of
+/// the missing argument, the same as `[a] F(a, x)`.
+fn name_placeholders(apply: &mut Apply<Unresolved>, names: &mut Vec<LocalName>) {
+    let mut name = |argument: &mut Expression<Unresolved>| {
+        let Expression::Variable(span, variable) = argument else {
+            return;
+        };
+        if variable.string != "_" {
+            return;
+        }
+        let placeholder = LocalName {
+            span: span.clone(),
+            string: arcstr::format!("#placeholder{}", names.len()),
+        };
+        names.push(placeholder.clone());
+        *argument = Expression::Variable(span.clone(), placeholder);
+    };
+    match apply {
+        Apply::Send(_, argument, then) => {
+            name(argument);
+            name_placeholders(then, names);
+        }
+        Apply::Named(_, arguments, then) => {
+            for (_, argument) in arguments {
+                name(argument);
+            }
+            name_placeholders(then, names);
+        }
+        Apply::SendType(_, _, then)
+        | Apply::Signal(_, _, then)
+        | Apply::Try(_, _, then)
+        | Apply::Default(_, _, then)
+        | Apply::Pipe(_, _, then)
+        | Apply::Begin { then, .. } => name_placeholders(then, names),
+        Apply::Noop(_) | Apply::Loop(_, _) | Apply::Case(_, _, _) => {}
+    }
+}
+
 fn apply(input: &mut Input) -> Result<Option<(Span, Apply<Unresolved>)>> {
     opt(alt((
         apply_begin,
@@ -2640,6 +3017,7 @@ fn apply(input: &mut Input) -> Result<Option<(Span, Apply<Unresolved>)>> {
         apply_loop,
         apply_signal,
         apply_case,
+        apply_send_named,
         apply_send,
         apply_default,
         apply_try,
@@ -2678,6 +3056,40 @@ fn apply_send(input: &mut Input) -> Result<(Span, Apply<Unresolved>)> {
     .parse_next(input)
 }
 
+fn apply_send_named(input: &mut Input) -> Result<(Span, Apply<Unresolved>)> {
+    commit_after(
+        (
+            t(TokenKind::LParen),
+            peek((local_name, t(TokenKind::Colon))),
+        ),
+        (
+            list1((local_name, t(TokenKind::Colon), expression)),
+            t(TokenKind::RParen),
+            apply,
+        ),
+    )
+    .map(|((open, _), (arguments, close, then))| {
+        let (then_full_span, then) = match then {
+            Some((span, apply)) => (span, apply),
+            None => {
+                let s = close.span.only_end();
+                (s.clone(), Apply::Noop(s))
+            }
+        };
+        let short_span = open.span.join(close.span());
+        let full_span = open.span.join(then_full_span);
+        let arguments = arguments
+            .into_iter()
+            .map(|(name, _, argument)| (name, argument))
+            .collect();
+        (
+            full_span,
+            Apply::Named(short_span, arguments, Box::new(then)),
+        )
+    })
+    .parse_next(input)
+}
+
 fn apply_signal(input: &mut Input) -> Result<(Span, Apply<Unresolved>)> {
     (t(TokenKind::Dot), (local_name, apply))
         .map(|(pre, (chosen, then))| {
@@ -3607,35 +4019,52 @@ fn cmd_continue(input: &mut Input) -> Result<(Span, Command<Unresolved>)> {
 }
 
 fn cmd_begin(input: &mut Input) -> Result<(Span, Command<Unresolved>)> {
-    commit_after((t(TokenKind::Dot), t(TokenKind::Begin)), (label, cmd))
-        .map(|((pre, begin_kw), (label, cmd))| {
-            let (cmd_full_span, cmd) = match (&label, cmd) {
-                (_, Some((span, cmd))) => (span, cmd),
-                (Some(label), None) => {
-                    let s = label.span.only_end();
-                    (s.clone(), noop_cmd(s))
-                }
-                (None, None) => {
-                    let s = begin_kw.span.only_end();
-                    (s.clone(), noop_cmd(s))
-                }
-            };
-            let short_span = match &label {
-                Some(label) => pre.span.join(label.span()),
-                None => pre.span.join(begin_kw.span()),
-            };
-            let full_span = pre.span.join(cmd_full_span);
-            (
-                full_span,
-                Command::Begin {
-                    span: short_span,
-                    unfounded: false,
-                    label,
-                    then: Box::new(cmd),
-                },
-            )
-        })
-        .parse_next(input)
+    commit_after(
+        (t(TokenKind::Dot), t(TokenKind::Begin)),
+        (label, begin_subjects_then),
+    )
+    .map(|((pre, begin_kw), (label, (subjects, cmd)))| {
+        let short_span = match (subjects.last(), &label) {
+            (Some(subject), _) => pre.span.join(subject.span()),
+            (None, Some(label)) => pre.span.join(label.span()),
+            (None, None) => pre.span.join(begin_kw.span()),
+        };
+        let (cmd_full_span, cmd) = match cmd {
+            Some((span, cmd)) => (span, cmd),
+            None => {
+                let s = short_span.only_end();
+                (s.clone(), noop_cmd(s))
+            }
+        };
+        let full_span = pre.span.join(cmd_full_span);
+        (
+            full_span,
+            Command::Begin {
+                span: short_span,
+                unfounded: false,
+                label,
+                subjects,
+                then: Box::new(cmd),
+            },
+        )
+    })
+    .parse_next(input)
+}
+
+/// The other subjects of a `begin`, each after a `&`, and what follows. With
+/// any, the command can't go on right after them, or it'd read as if it was on
+/// the last one.
+fn begin_subjects_then(
+    input: &mut Input,
+) -> Result<(Vec<LocalName>, Option<(Span, Command<Unresolved>)>)> {
+    let subjects: Vec<_> =
+        repeat(0.., preceded(t(TokenKind::Ampersand), local_name)).parse_next(input)?;
+    let then = if subjects.is_empty() {
+        cmd.parse_next(input)?
+    } else {
+        cmd_then.parse_next(input)?
+    };
+    Ok((subjects, then))
 }
 
 fn cmd_unfounded(input: &mut Input) -> Result<(Span, Command<Unresolved>)> {
@@ -3663,6 +4092,7 @@ fn cmd_unfounded(input: &mut Input) -> Result<(Span, Command<Unresolved>)> {
                     span: short_span,
                     unfounded: true,
                     label,
+                    subjects: Vec::new(),
                     then: Box::new(cmd),
                 },
             )
@@ -4299,6 +4729,129 @@ type Boxed<a: box> = a
         assert!(parse_module(source, "minimal.par".into()).is_err());
     }
 
+    #[test]
+    fn test_derive_adds_declarations_and_definitions() {
+        let source = "\
+edition 2026
+module Suit
+
+export type Suit = either { .hearts!, .spades! } derive Equals, Compare
+type Pair<a> = (a) a derive Equals
+
+dec Run : !
+";
+        let parsed = parse_module(source, "Suit.par".into()).unwrap();
+
+        let declarations = parsed
+            .declarations
+            .iter()
+            .map(|declaration| (declaration.name.primary.as_str(), declaration.exported))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            [
+                ("Run", false),
+                ("Equals", true),
+                ("Compare", true),
+                ("PairEquals", false),
+            ]
+        );
+        let definitions = parsed
+            .definitions
+            .iter()
+            .map(|definition| definition.name.primary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(definitions, ["Equals", "Compare", "PairEquals"]);
+    }
+
+    #[test]
+    fn test_derive_json_adds_encoder_and_decoder() {
+        let source = "\
+edition 2026
+module Suit
+
+import @core/Nat
+
+export type Suit = either { .hearts!, .spades! } derive Json
+type Rank = (Nat) Suit derive Json
+";
+        let parsed = parse_module(source, "Suit.par".into()).unwrap();
+
+        let declarations = parsed
+            .declarations
+            .iter()
+            .map(|declaration| declaration.name.primary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            ["ToJson", "FromJson", "RankToJson", "RankFromJson"]
+        );
+    }
+
+    #[test]
+    fn test_reject_unsupported_json_derives() {
+        for type_def in [
+            "type Item<a> = (a) ! derive Json",
+            "type Item = [String] String derive Json",
+            "type Item = choice { .close => ! } derive Json",
+        ] {
+            let source = format!("module Minimal\n\n{type_def}\n");
+            assert!(
+                parse_module(&source, "minimal.par".into()).is_err(),
+                "{type_def}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_derive_codec_adds_encoder_and_decoder() {
+        let source = "\
+edition 2026
+module Suit
+
+import @core/Int
+
+export type Suit = either { .hearts!, .spades! } derive Codec
+type Tree = recursive either { .leaf Int, .node(self) self } derive Codec
+";
+        let parsed = parse_module(source, "Suit.par".into()).unwrap();
+
+        let declarations = parsed
+            .declarations
+            .iter()
+            .map(|declaration| declaration.name.primary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            declarations,
+            ["ToBytes", "FromBytes", "TreeToBytes", "TreeFromBytes"]
+        );
+    }
+
+    #[test]
+    fn test_reject_unsupported_codec_derives() {
+        for type_def in [
+            "type Item<a> = (a) ! derive Codec",
+            "type Item = [String] String derive Codec",
+            "type Item = iterative choice { .next => self } derive Codec",
+        ] {
+            let source = format!("module Minimal\n\n{type_def}\n");
+            assert!(
+                parse_module(&source, "minimal.par".into()).is_err(),
+                "{type_def}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reject_unknown_derives() {
+        let source = "\
+module Minimal
+
+type Item = ! derive Hash
+";
+        assert!(parse_module(source, "minimal.par".into()).is_err());
+    }
+
     #[test]
     fn test_doc_comments_attach_to_type_and_explicit_declaration() {
         let source = "\
@@ -4370,6 +4923,171 @@ dec Run : !
         assert_eq!(parsed.body.declarations[0].doc, None);
     }
 
+    #[test]
+    fn test_doc_comments_attach_to_branches() {
+        let source = "\
+module Main
+
+type Door = iterative choice {
+  // Walks through.
+  .enter => !,
+
+  // Not attached
+
+  .knock => self,
+  /*Opens it.*/
+  .open => either {
+    // Already open.
+    .ajar!,
+    .opened self,
+  },
+}
+";
+        let parsed = parse_source_file(source, "Main.par".into()).unwrap();
+        let docs = parsed.body.type_defs[0]
+            .branch_docs
+            .iter()
+            .map(|(label, doc)| (label.string.as_str(), doc.markdown.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            [
+                ("enter", "Walks through."),
+                ("open", "Opens it."),
+                ("ajar", "Already open."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macros_expand_before_parsing() {
+        let source = "\
+edition 2026
+module Main
+
+macro Counter(Name, Zero, field) {
+  type Name = iterative choice {
+    .field => Nat,
+    .add(Nat) => self,
+  }
+  def Zero: Name = 0
+}
+
+Counter!(Width, ZeroWidth, width)
+Counter!(Height, ZeroHeight, height)
+";
+        let parsed = parse_source_file(source, "Main.par".into()).unwrap();
+        let names = parsed
+            .body
+            .type_defs
+            .iter()
+            .map(|type_def| type_def.name.primary.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Width", "Height"]);
+        assert_eq!(parsed.body.definitions.len(), 2);
+        assert_eq!(parsed.body.declarations.len(), 2);
+
+        let invocation = source.find("Counter!(Height").unwrap();
+        let expansion = parsed.body.type_defs[1]
+            .name
+            .span
+            .expansion()
+            .expect("the name comes from an invocation");
+        assert_eq!(
+            expansion.span.start().map(|start| start.offset as usize),
+            Some(invocation)
+        );
+    }
+
+    #[test]
+    fn test_macro_parameters_replace_whole_identifiers() {
+        let source = "\
+module Main
+
+macro Pick(x) {
+  def Used = x
+  def Picked = \"x\"
+  def Other = xx
+}
+
+Pick!(value)
+";
+        let tokens = lex(source, &"Main.par".into());
+        let expanded = expand_macros(tokens).unwrap();
+        assert!(
+            expanded
+                .iter()
+                .any(|token| token.kind == TokenKind::String && token.raw == "x")
+        );
+        let raws = expanded.iter().map(|token| token.raw).collect::<Vec<_>>();
+        assert!(raws.contains(&"xx"));
+        assert_eq!(raws.iter().filter(|raw| **raw == "value").count(), 1);
+    }
+
+    #[test]
+    fn test_reject_literals_the_lexer_would_not_produce() {
+        let source = "module Main\n\ndef S = \"s\"\ndef N = 1\n";
+        for (kind, raw) in [(TokenKind::String, "\\q"), (TokenKind::Integer, "1x")] {
+            let mut lexed = lex_with_comments(source, &"Main.par".into());
+            for token in &mut lexed.tokens {
+                if token.kind == kind {
+                    token.raw = raw;
+                }
+            }
+            assert!(parse_lexed(source, lexed).is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn test_reject_bad_macro_invocations() {
+        for source in [
+            "module Main\nmacro One(A) { type A = ! }\nOne!(X, Y)\n",
+            "module Main\nmacro Loop(A) { Loop!(A) }\nLoop!(X)\n",
+            "module Main\nmacro Open(A) { type A = !\n",
+            "module Main\ndef X = { macro Inner() {} }\n",
+        ] {
+            assert!(parse_module(source, "bad_macro.par".into()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting_is_a_syntax_error() {
+        let nested_calls =
+            |depth: usize| format!("def X = {}x{}", "F(".repeat(depth), ")".repeat(depth));
+        let nested_types =
+            |depth: usize| format!("type X = {}!{}", "List<".repeat(depth), ">".repeat(depth));
+
+        assert!(parse_module(&nested_calls(MAX_NESTING_DEPTH - 1), "Main.par".into()).is_ok());
+        assert!(parse_module(&nested_types(MAX_NESTING_DEPTH - 1), "Main.par".into()).is_ok());
+
+        let source = nested_calls(10_000);
+        let error = parse_module(&source, "Main.par".into()).unwrap_err();
+        assert!(error.help.starts_with("expression too deeply nested"));
+        let offset = error.span().start().unwrap().offset as usize;
+        assert!(source[offset..].starts_with("F("));
+
+        let source = nested_types(10_000);
+        let error = parse_module(&source, "Main.par".into()).unwrap_err();
+        assert!(error.help.starts_with("type too deeply nested"));
+        let offset = error.span().start().unwrap().offset as usize;
+        assert!(source[offset..].starts_with("List<"));
+    }
+
+    #[test]
+    fn test_nesting_limit_is_configurable() {
+        let source = format!("def X = {}x{}", "F(".repeat(20), ")".repeat(20));
+        let parse_with_limit = |limit| {
+            parse_lexed_with_limit(
+                &source,
+                lex_with_comments(&source, &"Main.par".into()),
+                limit,
+            )
+        };
+        assert!(parse_with_limit(30).is_ok());
+        let error = parse_with_limit(10).unwrap_err();
+        assert!(error.help.starts_with("expression too deeply nested"));
+    }
+
     #[test]
     fn test_parse_float_literals() {
         let source = "\
@@ -4398,4 +5116,74 @@ def F = 1.0e-6
             assert!(parse_module(source, "bad_float.par".into()).is_err());
         }
     }
+
+    #[test]
+    fn test_parse_placeholder_arguments() {
+        let expr = parse_single_definition_expression(
+            "\
+edition 2026
+module Main
+
+def Value = F(_, x, G(_))
+",
+        );
+
+        let Expression::Construction(_, Construct::Receive(_, Pattern::Name(_, name, _), then, _)) =
+            expr
+        else {
+            panic!("unexpected AST: {expr:#?}");
+        };
+        assert_eq!(name.string.as_str(), "#placeholder0");
+        let Construct::Then(application) = *then else {
+            panic!("expected a single receive, got {then:#?}");
+        };
+        let Expression::Application(_, _, Apply::Send(_, first, rest)) = *application else {
+            panic!("unexpected application: {application:#?}");
+        };
+        assert!(matches!(
+            *first,
+            Expression::Variable(_, LocalName { ref string, .. }) if string.as_str() == "#placeholder0"
+        ));
+        let Apply::Send(_, _, rest) = *rest else {
+            panic!("unexpected arguments: {rest:#?}");
+        };
+        let Apply::Send(_, last, _) = *rest else {
+            panic!("unexpected arguments: {rest:#?}");
+        };
+        assert!(matches!(
+            *last,
+            Expression::Construction(_, Construct::Receive(..))
+        ));
+    }
+
+    #[test]
+    fn test_parse_editions() {
+        let expr = parse_single_definition_expression(
+            "\
+edition 2025
+module Main
+
+type T = either { .derive ! }
+
+def Value = F(_, macro)
+",
+        );
+        assert!(
+            matches!(expr, Expression::Application(..)),
+            "placeholders aren't sugar in edition 2025: {expr:#?}"
+        );
+
+        let expr = parse_single_definition_expression("edition 2026\ndef Value = F(_)\n");
+        assert!(matches!(expr, Expression::Construction(..)));
+
+        assert!(parse_module("edition 2030\nmodule Main\n", "Main.par".into()).is_err());
+        assert!(parse_module("module Main\nedition 2025\n", "Main.par".into()).is_err());
+        assert!(
+            parse_module(
+                "module Main\ndef Value = do { let edition = 1 } in edition\n",
+                "Main.par".into()
+            )
+            .is_ok()
+        );
+    }
 }
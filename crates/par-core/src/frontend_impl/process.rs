@@ -1,8 +1,9 @@
-pub use super::captures::{Captures, VariableUsage};
+pub(crate) use super::captures::{Captures, VariableUsage};
 use super::{
     language::{GlobalName, LocalName, TypeParameter},
     types::{GlobalNameWriter, Type},
 };
+use crate::stack;
 use crate::{
     frontend_impl::program::{CheckedModule, DocComment, Docs},
     location::{Span, Spanning},
@@ -85,6 +86,9 @@ pub enum Command<Typ, S> {
     Begin {
         unfounded: bool,
         label: Option<LocalName>,
+        /// The subjects given after `&`, which loops may make smaller
+        /// instead of this one.
+        subjects: Arc<[LocalName]>,
         captures: Captures,
         body: Arc<Process<Typ, S>>,
     },
@@ -127,6 +131,10 @@ impl<Typ, S> Spanning for Process<Typ, S> {
 
 impl<S: Clone> Process<(), S> {
     pub fn optimize(&self) -> Arc<Self> {
+        stack::grow(|| self.optimize_inner())
+    }
+
+    fn optimize_inner(&self) -> Arc<Self> {
         match self {
             Self::Let {
                 span,
@@ -135,14 +143,40 @@ impl<S: Clone> Process<(), S> {
                 typ,
                 value: expression,
                 then: process,
-            } => Arc::new(Self::Let {
-                span: span.clone(),
-                name: name.clone(),
-                annotation: annotation.clone(),
-                typ: typ.clone(),
-                value: expression.optimize(),
-                then: process.optimize(),
-            }),
+            } => {
+                let value = expression.optimize();
+                let then = process.optimize();
+                // `let x = e; c <> x` only names `e` to link it.
+                if annotation.is_none()
+                    && let Self::Do {
+                        span: link_span,
+                        name: channel,
+                        usage,
+                        command: Command::Link(linked),
+                        ..
+                    } = then.as_ref()
+                    && let Expression::Variable(_, linked, _, _) = linked.as_ref()
+                    && linked == name
+                    && channel != name
+                {
+                    return Self::link(link_span, channel, usage, value);
+                }
+                Arc::new(Self::Let {
+                    span: span.clone(),
+                    name: name.clone(),
+                    annotation: annotation.clone(),
+                    typ: *typ,
+                    value,
+                    then,
+                })
+            }
+            Self::Do {
+                span,
+                name,
+                usage,
+                command: Command::Link(expression),
+                ..
+            } => Self::link(span, name, usage, expression.optimize()),
             Self::Do {
                 span,
                 name,
@@ -156,36 +190,7 @@ impl<S: Clone> Process<(), S> {
                 usage: usage.clone(),
                 command: match command {
                     Command::Noop(process) => Command::Noop(process.optimize()),
-                    Command::Link(expression) => {
-                        let expression = expression.optimize();
-                        match expression.optimize().as_ref() {
-                            Expression::Chan {
-                                chan_name: channel,
-                                chan_annotation: annotation,
-                                process,
-                                ..
-                            } => {
-                                if name == channel && annotation.is_none() {
-                                    return Arc::clone(process);
-                                } else {
-                                    return Arc::new(Process::Let {
-                                        span: span.clone(),
-                                        name: channel.clone(),
-                                        annotation: annotation.clone(),
-                                        typ: (),
-                                        value: Arc::new(Expression::Variable(
-                                            span.clone(),
-                                            name.clone(),
-                                            (),
-                                            VariableUsage::Unknown,
-                                        )),
-                                        then: Arc::clone(process),
-                                    });
-                                }
-                            }
-                            _ => Command::Link(expression),
-                        }
-                    }
+                    Command::Link(_) => unreachable!("optimized by `Self::link`"),
                     Command::Send(argument, process) => {
                         Command::Send(argument.optimize(), process.optimize())
                     }
@@ -211,11 +216,13 @@ impl<S: Clone> Process<(), S> {
                     Command::Begin {
                         unfounded,
                         label,
+                        subjects,
                         captures,
                         body: process,
                     } => Command::Begin {
                         unfounded: unfounded.clone(),
                         label: label.clone(),
+                        subjects: subjects.clone(),
                         captures: captures.clone(),
                         body: process.optimize(),
                     },
@@ -278,7 +285,54 @@ impl<S: Clone> Process<(), S> {
             Self::Unreachable(span) => Arc::new(Self::Unreachable(span.clone())),
         }
     }
+    /// Links `name` with an already optimized `expression`, running the
+    /// process of a channel expression in place instead of forking it.
+    fn link(
+        span: &Span,
+        name: &LocalName,
+        usage: &VariableUsage,
+        expression: Arc<Expression<(), S>>,
+    ) -> Arc<Self> {
+        match expression.as_ref() {
+            Expression::Chan {
+                chan_name: channel,
+                chan_annotation: None,
+                process,
+                ..
+            } if name == channel => Arc::clone(process),
+            Expression::Chan {
+                chan_name: channel,
+                chan_annotation: annotation,
+                process,
+                ..
+            } => Arc::new(Process::Let {
+                span: span.clone(),
+                name: channel.clone(),
+                annotation: annotation.clone(),
+                typ: (),
+                value: Arc::new(Expression::Variable(
+                    span.clone(),
+                    name.clone(),
+                    (),
+                    VariableUsage::Unknown,
+                )),
+                then: Arc::clone(process),
+            }),
+            _ => Arc::new(Self::Do {
+                span: span.clone(),
+                name: name.clone(),
+                usage: usage.clone(),
+                typ: (),
+                command: Command::Link(expression),
+            }),
+        }
+    }
+
     pub fn optimize_subject(&self, replace: Option<&LocalName>) -> Arc<Self> {
+        stack::grow(|| self.optimize_subject_inner(replace))
+    }
+
+    fn optimize_subject_inner(&self, replace: Option<&LocalName>) -> Arc<Self> {
         match self {
             Process::Let {
                 span,
@@ -377,11 +431,13 @@ impl<S: Clone> Process<(), S> {
                         Command::Begin {
                             unfounded,
                             label,
+                            subjects,
                             captures,
                             body: process,
                         } => Command::Begin {
                             unfounded: unfounded.clone(),
                             label: label.clone(),
+                            subjects: subjects.clone(),
                             captures: captures.clone(),
                             body: process.optimize_subject(replace),
                         },
@@ -491,6 +547,15 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> Process<Type<S>, S> {
         program: &CheckedModule<S>,
         docs: &Docs<S>,
         consume: &mut impl FnMut(Span, HoverInfo<S>),
+    ) {
+        stack::grow(|| self.types_at_spans_inner(program, docs, consume))
+    }
+
+    fn types_at_spans_inner(
+        &self,
+        program: &CheckedModule<S>,
+        docs: &Docs<S>,
+        consume: &mut impl FnMut(Span, HoverInfo<S>),
     ) {
         match self {
             Process::Let {
@@ -602,6 +667,15 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> Command<Type<S>, S> {
         program: &CheckedModule<S>,
         docs: &Docs<S>,
         consume: &mut impl FnMut(Span, HoverInfo<S>),
+    ) {
+        stack::grow(|| self.types_at_spans_inner(program, docs, consume))
+    }
+
+    fn types_at_spans_inner(
+        &self,
+        program: &CheckedModule<S>,
+        docs: &Docs<S>,
+        consume: &mut impl FnMut(Span, HoverInfo<S>),
     ) {
         match self {
             Self::Noop(process) => {
@@ -653,6 +727,10 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> Command<Type<S>, S> {
 
 impl<S: Clone> Expression<(), S> {
     pub(crate) fn optimize_subject(&self, replace: Option<&LocalName>) -> Arc<Expression<(), S>> {
+        stack::grow(|| self.optimize_subject_inner(replace))
+    }
+
+    fn optimize_subject_inner(&self, replace: Option<&LocalName>) -> Arc<Expression<(), S>> {
         match self {
             Self::Global(span, name, typ) => {
                 Arc::new(Self::Global(span.clone(), name.clone(), typ.clone()))
@@ -703,6 +781,10 @@ impl<S: Clone> Expression<(), S> {
     }
 
     pub fn optimize(&self) -> Arc<Self> {
+        stack::grow(|| self.optimize_inner())
+    }
+
+    fn optimize_inner(&self) -> Arc<Self> {
         match self {
             Self::Global(span, name, typ) => {
                 Arc::new(Self::Global(span.clone(), name.clone(), typ.clone()))
@@ -727,15 +809,35 @@ impl<S: Clone> Expression<(), S> {
                 chan_type,
                 expr_type,
                 process,
-            } => Arc::new(Self::Chan {
-                span: span.clone(),
-                captures: captures.clone(),
-                chan_name: chan_name.clone(),
-                chan_annotation: chan_annotation.clone(),
-                chan_type: chan_type.clone(),
-                expr_type: expr_type.clone(),
-                process: process.optimize(),
-            }),
+            } => {
+                let process = process.optimize();
+                // `chan c { c <> e }` is just `e`, as long as `e` can't see `c`.
+                if chan_annotation.is_none()
+                    && let Process::Do {
+                        name,
+                        command: Command::Link(linked),
+                        ..
+                    } = process.as_ref()
+                    && name == chan_name
+                {
+                    match linked.as_ref() {
+                        Self::Global(..) | Self::Primitive(..) => return Arc::clone(linked),
+                        Self::Variable(_, name, _, _) if name != chan_name => {
+                            return Arc::clone(linked);
+                        }
+                        _ => {}
+                    }
+                }
+                Arc::new(Self::Chan {
+                    span: span.clone(),
+                    captures: captures.clone(),
+                    chan_name: chan_name.clone(),
+                    chan_annotation: chan_annotation.clone(),
+                    chan_type: *chan_type,
+                    expr_type: *expr_type,
+                    process,
+                })
+            }
             Self::Primitive(span, value, typ) => {
                 Arc::new(Self::Primitive(span.clone(), value.clone(), typ.clone()))
             }
@@ -991,6 +1093,15 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> Expression<Type<S>, S>
         program: &CheckedModule<S>,
         docs: &Docs<S>,
         consume: &mut impl FnMut(Span, HoverInfo<S>),
+    ) {
+        stack::grow(|| self.types_at_spans_inner(program, docs, consume))
+    }
+
+    fn types_at_spans_inner(
+        &self,
+        program: &CheckedModule<S>,
+        docs: &Docs<S>,
+        consume: &mut impl FnMut(Span, HoverInfo<S>),
     ) {
         match self {
             Self::Global(_, name, typ) => {
@@ -1162,11 +1273,13 @@ impl<S: Clone> Command<Type<S>, S> {
             Command::Begin {
                 unfounded,
                 label,
+                subjects,
                 captures,
                 body,
             } => Command::Begin {
                 unfounded: *unfounded,
                 label: label.clone(),
+                subjects: subjects.clone(),
                 captures: captures.clone(),
                 body: body.map_types(f),
             },
@@ -1257,6 +1370,13 @@ impl<S: Clone> Process<(), S> {
     pub fn map_global_names<T, E>(
         self,
         f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
+    ) -> Result<Process<(), T>, E> {
+        stack::grow(|| self.map_global_names_inner(f))
+    }
+
+    fn map_global_names_inner<T, E>(
+        self,
+        f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
     ) -> Result<Process<(), T>, E> {
         match self {
             Process::Let {
@@ -1400,6 +1520,13 @@ impl<S: Clone> Command<(), S> {
     pub fn map_global_names<T, E>(
         self,
         f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
+    ) -> Result<Command<(), T>, E> {
+        stack::grow(|| self.map_global_names_inner(f))
+    }
+
+    fn map_global_names_inner<T, E>(
+        self,
+        f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
     ) -> Result<Command<(), T>, E> {
         match self {
             Command::Noop(process) => Self::map_global_names_noop(process, f),
@@ -1417,9 +1544,10 @@ impl<S: Clone> Command<(), S> {
             Command::Begin {
                 unfounded,
                 label,
+                subjects,
                 captures,
                 body,
-            } => Self::map_global_names_begin(unfounded, label, captures, body, f),
+            } => Self::map_global_names_begin(unfounded, label, subjects, captures, body, f),
             Command::Loop(label, driver, captures) => Ok(Command::Loop(label, driver, captures)),
             Command::SendType(argument, process) => {
                 Self::map_global_names_send_type(argument, process, f)
@@ -1504,6 +1632,7 @@ impl<S: Clone> Command<(), S> {
     fn map_global_names_begin<T, E>(
         unfounded: bool,
         label: Option<LocalName>,
+        subjects: Arc<[LocalName]>,
         captures: Captures,
         body: Arc<Process<(), S>>,
         f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
@@ -1511,6 +1640,7 @@ impl<S: Clone> Command<(), S> {
         Ok(Command::Begin {
             unfounded,
             label,
+            subjects,
             captures,
             body: map_arc_process(body, f)?,
         })
@@ -1543,6 +1673,13 @@ impl<S: Clone> Expression<(), S> {
     pub fn map_global_names<T, E>(
         self,
         f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
+    ) -> Result<Expression<(), T>, E> {
+        stack::grow(|| self.map_global_names_inner(f))
+    }
+
+    fn map_global_names_inner<T, E>(
+        self,
+        f: &mut impl FnMut(GlobalName<S>) -> Result<GlobalName<T>, E>,
     ) -> Result<Expression<(), T>, E> {
         match self {
             Expression::Global(span, name, ()) => Ok(Expression::Global(span, f(name)?, ())),
@@ -1710,19 +1847,30 @@ impl<Typ, S> Process<Typ, S> {
 
 impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
     pub fn pretty(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        self.pretty_annotated(f, indent, &|_, _| Ok(()))
+    }
+
+    fn pretty_annotated<W: Write>(
+        &self,
+        f: &mut W,
+        indent: usize,
+        annotate: &dyn Fn(&mut W, &Typ) -> fmt::Result,
+    ) -> fmt::Result {
         match self {
             Self::Let {
                 span: _,
                 name,
                 annotation: _,
-                typ: _,
+                typ,
                 value: expression,
                 then: process,
             } => {
                 indentation(f, indent)?;
-                write!(f, "let {} = ", name)?;
-                expression.pretty(f, indent)?;
-                process.pretty(f, indent)
+                write!(f, "let {}", name)?;
+                annotate(f, typ)?;
+                write!(f, " = ")?;
+                expression.pretty_annotated(f, indent, annotate)?;
+                process.pretty_annotated(f, indent, annotate)
             }
 
             Self::Unreachable(_) => {
@@ -1736,6 +1884,8 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                 point,
                 clients,
                 name,
+                name_typ,
+                captures,
                 then,
                 else_,
                 ..
@@ -1747,21 +1897,25 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                 }
                 write!(f, "[{} -> {}](", driver, point)?;
                 if let Some(first) = clients.first() {
-                    first.pretty(f, indent)?;
+                    first.pretty_annotated(f, indent, annotate)?;
                     for client in &clients[1..] {
                         write!(f, ", ")?;
-                        client.pretty(f, indent)?;
+                        client.pretty_annotated(f, indent, annotate)?;
                     }
                 }
-                write!(f, ") {{")?;
+                write!(f, ")")?;
+                write_captures(f, captures)?;
+                write!(f, " {{")?;
                 indentation(f, indent + 1)?;
-                write!(f, "{} => {{", name)?;
-                then.pretty(f, indent + 2)?;
+                write!(f, "{}", name)?;
+                annotate(f, name_typ)?;
+                write!(f, " => {{")?;
+                then.pretty_annotated(f, indent + 2, annotate)?;
                 indentation(f, indent + 1)?;
                 write!(f, "}}")?;
                 indentation(f, indent + 1)?;
                 write!(f, "else => {{")?;
-                else_.pretty(f, indent + 2)?;
+                else_.pretty_annotated(f, indent + 2, annotate)?;
                 indentation(f, indent + 1)?;
                 write!(f, "}}")?;
                 indentation(f, indent)?;
@@ -1773,19 +1927,20 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                 driver,
                 point,
                 values,
+                captures,
                 ..
             } => {
                 indentation(f, indent)?;
                 write!(f, "submit[{} -> {}](", driver, point)?;
                 if let Some(first) = values.first() {
-                    first.pretty(f, indent)?;
+                    first.pretty_annotated(f, indent, annotate)?;
                     for value in &values[1..] {
                         write!(f, ", ")?;
-                        value.pretty(f, indent)?;
+                        value.pretty_annotated(f, indent, annotate)?;
                     }
                 }
                 write!(f, ")")?;
-                Ok(())
+                write_captures(f, captures)
             }
 
             Self::Do {
@@ -1799,20 +1954,20 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                 write!(f, "{}", subject)?;
 
                 match command {
-                    Command::Noop(process) => process.pretty(f, indent),
+                    Command::Noop(process) => process.pretty_annotated(f, indent, annotate),
                     Command::Link(expression) => {
                         write!(f, " <> ")?;
-                        expression.pretty(f, indent)
+                        expression.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::Send(argument, process) => {
                         write!(f, "(")?;
-                        argument.pretty(f, indent)?;
+                        argument.pretty_annotated(f, indent, annotate)?;
                         write!(f, ")")?;
-                        process.pretty(f, indent)
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
-                    Command::Receive(parameter, _, _, process, vars) => {
+                    Command::Receive(parameter, _, typ, process, vars) => {
                         if !vars.is_empty() {
                             write!(f, "<")?;
                             write!(f, "{}", vars[0])?;
@@ -1821,13 +1976,15 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                             }
                             write!(f, ">")?;
                         }
-                        write!(f, "[{}]", parameter)?;
-                        process.pretty(f, indent)
+                        write!(f, "[{}", parameter)?;
+                        annotate(f, typ)?;
+                        write!(f, "]")?;
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::Signal(chosen, process) => {
                         write!(f, ".{}", chosen)?;
-                        process.pretty(f, indent)
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::Case(choices, branches, else_process) => {
@@ -1835,14 +1992,14 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                         for (choice, process) in choices.iter().zip(branches.iter()) {
                             indentation(f, indent + 1)?;
                             write!(f, ".{} => {{", choice)?;
-                            process.pretty(f, indent + 2)?;
+                            process.pretty_annotated(f, indent + 2, annotate)?;
                             indentation(f, indent + 1)?;
                             write!(f, "}}")?;
                         }
                         if let Some(process) = else_process {
                             indentation(f, indent + 1)?;
                             write!(f, "else => {{")?;
-                            process.pretty(f, indent + 2)?;
+                            process.pretty_annotated(f, indent + 2, annotate)?;
                             indentation(f, indent + 1)?;
                             write!(f, "}}")?;
                         }
@@ -1856,14 +2013,15 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
 
                     Command::Continue(process) => {
                         write!(f, "?")?;
-                        process.pretty(f, indent)
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::Begin {
                         unfounded,
                         label,
+                        subjects,
+                        captures,
                         body: process,
-                        ..
                     } => {
                         if *unfounded {
                             write!(f, ".unfounded")?;
@@ -1873,7 +2031,11 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                         if let Some(label) = label {
                             write!(f, "@{}", label)?;
                         }
-                        process.pretty(f, indent)
+                        for subject in subjects.iter() {
+                            write!(f, " & {}", subject)?;
+                        }
+                        write_captures(f, captures)?;
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::Loop(label, driver, caps) => {
@@ -1893,12 +2055,12 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
                         write!(f, "(type ")?;
                         argument.pretty(f, &CanonicalGlobalNameWriter, indent)?;
                         write!(f, ")")?;
-                        process.pretty(f, indent)
+                        process.pretty_annotated(f, indent, annotate)
                     }
 
                     Command::ReceiveType(parameter, process) => {
                         write!(f, "[type {}]", parameter)?;
-                        process.pretty(f, indent)
+                        process.pretty_annotated(f, indent, annotate)
                     }
                 }
             }
@@ -1906,15 +2068,16 @@ impl<Typ, S: Clone + std::fmt::Display> Process<Typ, S> {
             Self::Block(_, index, body, process) => {
                 indentation(f, indent)?;
                 write!(f, "block@{} {{", index)?;
-                body.pretty(f, indent + 1)?;
+                body.pretty_annotated(f, indent + 1, annotate)?;
                 indentation(f, indent)?;
                 write!(f, "}}")?;
-                process.pretty(f, indent)
+                process.pretty_annotated(f, indent, annotate)
             }
 
-            Self::Goto(_, index, _) => {
+            Self::Goto(_, index, captures) => {
                 indentation(f, indent)?;
-                write!(f, "goto@{}", index)
+                write!(f, "goto@{}", index)?;
+                write_captures(f, captures)
             }
         }
     }
@@ -1939,6 +2102,15 @@ impl<Typ, S> Expression<Typ, S> {
 
 impl<Typ, S: Clone + std::fmt::Display> Expression<Typ, S> {
     pub fn pretty(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        self.pretty_annotated(f, indent, &|_, _| Ok(()))
+    }
+
+    fn pretty_annotated<W: Write>(
+        &self,
+        f: &mut W,
+        indent: usize,
+        annotate: &dyn Fn(&mut W, &Typ) -> fmt::Result,
+    ) -> fmt::Result {
         match self {
             Self::Global(_, name, _) => write!(f, "{name}"),
 
@@ -1946,18 +2118,25 @@ impl<Typ, S: Clone + std::fmt::Display> Expression<Typ, S> {
                 write!(f, "{}", name)
             }
 
-            Self::Box(_, _, expression, _) => {
-                write!(f, "box ")?;
-                expression.pretty(f, indent)
+            Self::Box(_, captures, expression, _) => {
+                write!(f, "box")?;
+                write_captures(f, captures)?;
+                write!(f, " ")?;
+                expression.pretty_annotated(f, indent, annotate)
             }
 
             Self::Chan {
+                captures,
                 chan_name: channel,
+                chan_type,
                 process,
                 ..
             } => {
-                write!(f, "chan {} {{", channel)?;
-                process.pretty(f, indent + 1)?;
+                write!(f, "chan {}", channel)?;
+                annotate(f, chan_type)?;
+                write_captures(f, captures)?;
+                write!(f, " {{")?;
+                process.pretty_annotated(f, indent + 1, annotate)?;
                 indentation(f, indent)?;
                 write!(f, "}}")
             }
@@ -1971,6 +2150,42 @@ impl<Typ, S: Clone + std::fmt::Display> Expression<Typ, S> {
     }
 }
 
+impl<S: Clone + std::fmt::Display> Process<Type<S>, S> {
+    /// Like [`pretty`](Self::pretty), but with the type of every bound variable.
+    pub fn pretty_typed(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        self.pretty_annotated(f, indent, &|f, typ| write_type_annotation(f, typ))
+    }
+}
+
+impl<S: Clone + std::fmt::Display> Expression<Type<S>, S> {
+    /// Like [`pretty`](Self::pretty), but with the type of every bound variable.
+    pub fn pretty_typed(&self, f: &mut impl Write, indent: usize) -> fmt::Result {
+        self.pretty_annotated(f, indent, &|f, typ| write_type_annotation(f, typ))
+    }
+}
+
+fn write_type_annotation<S: Clone + std::fmt::Display>(
+    f: &mut impl Write,
+    typ: &Type<S>,
+) -> fmt::Result {
+    write!(f, " : ")?;
+    typ.pretty_compact(f, &CanonicalGlobalNameWriter)
+}
+
+fn write_captures(f: &mut impl Write, captures: &Captures) -> fmt::Result {
+    if captures.names.is_empty() {
+        return Ok(());
+    }
+    write!(f, " |")?;
+    for (i, name) in captures.names.keys().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", name)?;
+    }
+    write!(f, "|")
+}
+
 fn indentation(f: &mut impl Write, indent: usize) -> fmt::Result {
     write!(f, "\n")?;
     for _ in 0..indent {
@@ -1986,3 +2201,97 @@ impl<S: std::fmt::Display> GlobalNameWriter<S> for CanonicalGlobalNameWriter {
         write!(f, "{name}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_runtime::pkgid::PackageId;
+
+    use crate::frontend::DefinitionBody;
+    use crate::location::FileName;
+    use crate::pipeline::{Pipeline, SourcePackage, Stage};
+    use crate::workspace::LoadedPackageFile;
+
+    const SOURCE: &str = "module Test
+
+type Bool = either { .true!, .false! }
+type Bools = recursive either { .end!, .item(Bool) self }
+
+dec Flip : [Bool] Bool
+def Flip = [b] b.case {
+  .true! => .false!,
+  .false! => .true!,
+}
+
+dec FlipAll : [Bools] Bools
+def FlipAll = [bools] bools.begin.case {
+  .end! => .end!,
+  .item(b) rest => .item(Flip(b)) rest.loop,
+}
+
+dec Choose : [Bool, Bool] Bool
+def Choose = [which, other] which.case {
+  .true! => other,
+  .false! => Flip(other),
+}
+";
+
+    #[test]
+    fn processes_match_golden_file() {
+        let id = PackageId::Special(literal!("test"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("test/Test.par"),
+                    relative_path_from_src: PathBuf::from("Test.par"),
+                    source: SOURCE.to_owned(),
+                }],
+            }],
+        );
+        pipeline
+            .run_until(Stage::TypeCheck)
+            .expect("checked program");
+        let artifacts = pipeline.into_artifacts();
+        assert!(
+            artifacts.type_errors.is_empty(),
+            "{:?}",
+            artifacts.type_errors
+        );
+        let checked = artifacts.checked.expect("checked workspace");
+
+        let mut sections = Vec::new();
+        for (definition, _) in checked.checked_module().definitions.values() {
+            let DefinitionBody::Par(body) = &definition.body else {
+                continue;
+            };
+            let mut pretty = String::new();
+            body.pretty(&mut pretty, 0).unwrap();
+            sections.push(format!("== {} (pretty)\n{pretty}\n", definition.name));
+            let mut typed = String::new();
+            body.pretty_typed(&mut typed, 0).unwrap();
+            sections.push(format!("== {} (typed)\n{typed}\n", definition.name));
+        }
+        let actual = sections.join("\n");
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/frontend_impl/process.golden"
+        );
+        if std::env::var_os("PAR_UPDATE_GOLDEN").is_some() {
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        let expected = include_str!("process.golden");
+        assert!(
+            actual == expected,
+            "process rendering changed; rerun with PAR_UPDATE_GOLDEN=1 to accept it, \
+             and review the diff of {path}\n\n{actual}"
+        );
+    }
+}
@@ -1,15 +1,20 @@
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use arcstr::ArcStr;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 
 use crate::location::{FileName, Point, Span, Spanning};
 
 use super::{
-    language::{CompileError, GlobalName, TypeParameter, Unresolved},
+    language::{CompileError, GlobalName, LocalName, TypeParameter, Unresolved},
     parse::SyntaxError,
     process::{self, HoverInfo},
-    types::{Context, Type, TypeDefs, TypeError},
+    types::{Context, ExpansionLimit, Type, TypeDefs, TypeError, TypeFunction},
 };
 
 use crate::frontend::language::Expression;
@@ -50,12 +55,21 @@ pub struct SourceFile<Expr> {
     pub body: Module<Expr, Unresolved>,
 }
 
+/// The definitions of a [`CheckedModule`], with their types.
+pub(crate) type CheckedDefinitions<S> =
+    IndexMap<GlobalName<S>, (Definition<Arc<process::Expression<Type<S>, S>>, S>, Type<S>)>;
+
 #[derive(Debug, Clone)]
 pub struct CheckedModule<S> {
     pub type_defs: TypeDefs<S>,
     pub declarations: IndexMap<GlobalName<S>, Declaration<S>>,
     pub definitions:
         IndexMap<GlobalName<S>, (Definition<Arc<process::Expression<Type<S>, S>>, S>, Type<S>)>,
+    /// Problems that don't stop the module from being checked or run, see
+    /// [`TypeError::is_warning`].
+    pub warnings: Vec<TypeError<S>>,
+    /// How long each definition took to check, in the order they were checked.
+    pub check_times: IndexMap<GlobalName<S>, Duration>,
 }
 
 #[derive(Clone, Debug)]
@@ -63,9 +77,15 @@ pub struct TypeDef<S> {
     pub span: Span,
     pub exported: bool,
     pub doc: Option<DocComment>,
+    /// Doc comments on the branches of the `either` and `choice` types in the
+    /// definition, by label.
+    pub branch_docs: IndexMap<LocalName, DocComment>,
     pub name: GlobalName<S>,
     pub params: Vec<TypeParameter>,
     pub typ: Type<S>,
+    /// Set for types built into the compiler, whose `typ` only stands in for
+    /// what's computed from the arguments.
+    pub function: Option<TypeFunction>,
 }
 
 #[derive(Clone, Debug)]
@@ -74,6 +94,9 @@ pub struct Declaration<S> {
     pub exported: bool,
     pub doc: Option<DocComment>,
     pub name: GlobalName<S>,
+    /// Names given to the leading arguments, as in `dec F : [count: Nat] T`,
+    /// for calls passing them by name. Empty if they aren't named.
+    pub parameter_names: Vec<LocalName>,
     pub typ: Type<S>,
 }
 
@@ -83,10 +106,53 @@ pub struct DocComment {
     pub markdown: ArcStr,
 }
 
+impl DocComment {
+    /// How the documented definition presents itself in menus of things to
+    /// run. See [`EntryPoint`].
+    pub fn entry_point(&self) -> EntryPoint {
+        let category = self
+            .markdown
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Category:"))
+            .map(str::trim)
+            .filter(|category| !category.is_empty())
+            .map(str::to_owned);
+        let description = self
+            .markdown
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("Category:"))
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        EntryPoint {
+            description: (!description.is_empty()).then(|| description.join(" ")),
+            category,
+        }
+    }
+}
+
+/// A definition to run, as shown in the run menu of the playground: the
+/// first paragraph of the doc comment on its declaration describes it, and a
+/// `Category:` line anywhere in that comment groups it with others.
+///
+/// ```par
+/// // Plays a round against the computer.
+/// //
+/// // Category: Games
+/// dec PlayRound : [Move] Outcome
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub description: Option<String>,
+    pub category: Option<String>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Docs<S> {
     pub modules: IndexMap<S, DocComment>,
     pub types: IndexMap<GlobalName<S>, DocComment>,
+    pub branches: IndexMap<GlobalName<S>, IndexMap<LocalName, DocComment>>,
     pub declarations: IndexMap<GlobalName<S>, DocComment>,
 }
 
@@ -102,6 +168,36 @@ impl<S: std::hash::Hash + Eq> Docs<S> {
     pub fn declaration_doc(&self, name: &GlobalName<S>) -> Option<&DocComment> {
         self.declarations.get(name)
     }
+
+    /// The doc comments on the branches of a type definition, by label.
+    pub fn branch_docs(&self, name: &GlobalName<S>) -> Option<&IndexMap<LocalName, DocComment>> {
+        self.branches.get(name)
+    }
+
+    /// The documentation of a type, followed by a list of its documented
+    /// branches, for showing the whole of it in one place.
+    pub fn type_doc_with_branches(&self, name: &GlobalName<S>) -> Option<DocComment> {
+        let doc = self.type_doc(name);
+        let Some(branches) = self
+            .branch_docs(name)
+            .filter(|branches| !branches.is_empty())
+        else {
+            return doc.cloned();
+        };
+        let mut markdown = doc.map_or_else(String::new, |doc| format!("{}\n\n", doc.markdown));
+        markdown.push_str("## Branches\n");
+        for (label, branch_doc) in branches {
+            markdown.push_str(&format!(
+                "\n- `.{}`: {}",
+                label,
+                branch_doc.markdown.replace('\n', "\n  ")
+            ));
+        }
+        Some(DocComment {
+            span: doc.map_or(Span::None, |doc| doc.span.clone()),
+            markdown: markdown.into(),
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -134,9 +230,32 @@ impl TypeDef<Unresolved> {
                 span: Span::None,
                 markdown: ArcStr::from(doc),
             }),
+            branch_docs: IndexMap::new(),
             name: GlobalName::<Unresolved>::external(None, name),
             params: Vec::new(),
             typ,
+            function: None,
+        }
+    }
+
+    /// A [type function](TypeFunction), declared with the parameters it
+    /// takes. Its body is the first of them, standing in for the type
+    /// computed from them.
+    pub fn external_function(
+        name: &'static str,
+        doc: &'static str,
+        function: TypeFunction,
+    ) -> Self {
+        let params: Vec<_> = function
+            .params()
+            .iter()
+            .map(|&param| TypeParameter::any(LocalName::new(param)))
+            .collect();
+        Self {
+            typ: Type::Var(Span::None, params[0].name.clone()),
+            params,
+            function: Some(function),
+            ..Self::external(name, doc, Type::Fail(Span::None))
         }
     }
 }
@@ -173,17 +292,21 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                          span,
                          exported,
                          doc,
+                         branch_docs,
                          name,
                          params,
                          typ,
+                         function,
                      }| {
                         Ok(TypeDef {
                             span,
                             exported,
                             doc,
+                            branch_docs,
                             name: map_name(name)?,
                             params,
                             typ: typ.map_global_names(&mut map_name)?,
+                            function,
                         })
                     },
                 )
@@ -197,6 +320,7 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                          exported,
                          doc,
                          name,
+                         parameter_names,
                          typ,
                      }| {
                         Ok(Declaration {
@@ -204,6 +328,7 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                             exported,
                             doc,
                             name: map_name(name)?,
+                            parameter_names,
                             typ: typ.map_global_names(&mut map_name)?,
                         })
                     },
@@ -232,53 +357,46 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
     where
         S: Eq + std::hash::Hash,
     {
-        let (type_defs, mut errors) = TypeDefs::new_with_validation(
-            self.type_defs
-                .iter()
-                .map(|d| (&d.span, &d.name, &d.params, &d.typ)),
-        );
-
-        let mut unchecked_definitions = IndexMap::new();
-        for Definition { span, name, body } in &self.definitions {
-            if let Some((span1, _)) =
-                unchecked_definitions.insert(name.clone(), (span.clone(), body.clone()))
-            {
-                errors.insert(TypeError::NameAlreadyDefined(
-                    span.clone(),
-                    span1,
-                    name.clone(),
-                ));
-            }
-        }
+        self.type_check_reusing(IndexMap::new(), ExpansionLimit::default())
+    }
 
-        let mut declarations = IndexMap::new();
-        for Declaration {
-            span, name, typ, ..
-        } in &self.declarations
-        {
-            if !unchecked_definitions.contains_key(name) {
-                errors.insert(TypeError::DeclaredButNotDefined(span.clone(), name.clone()));
-            }
-            if let Some((span1, _)) = declarations.insert(name.clone(), (span.clone(), typ.clone()))
-            {
-                errors.insert(TypeError::NameAlreadyDeclared(
-                    span.clone(),
-                    span1,
-                    name.clone(),
-                ));
-            }
-        }
+    /// Type checks the module like [`Self::type_check`], except for the
+    /// definitions in `reused`, which are taken as already checked. They
+    /// aren't checked again, nor timed. Types are expanded only as far as
+    /// `expansion_limit`.
+    pub fn type_check_reusing(
+        &self,
+        reused: CheckedDefinitions<S>,
+        expansion_limit: ExpansionLimit,
+    ) -> (CheckedModule<S>, Vec<TypeError<S>>)
+    where
+        S: Eq + std::hash::Hash,
+    {
+        let Globals {
+            type_defs,
+            declarations,
+            definitions: unchecked_definitions,
+            mut errors,
+            warnings,
+        } = self.collect_globals(expansion_limit);
 
         let names_to_check = unchecked_definitions
             .iter()
+            .filter(|(name, _)| !reused.contains_key(*name))
             .map(|(name, (span, _))| (span.clone(), name.clone()))
             .collect::<Vec<_>>();
 
         let mut context = Context::new(type_defs, declarations, unchecked_definitions);
+        for (name, (definition, typ)) in reused {
+            context.reuse_checked_definition(name, definition.span, definition.body, typ);
+        }
+        let mut check_times = IndexMap::new();
         for (span, name) in names_to_check {
+            let start = Instant::now();
             context.check_definition(&span, &name, &mut |e| {
                 errors.insert(e);
             });
+            check_times.insert(name, start.elapsed());
         }
 
         (
@@ -293,6 +411,9 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                         });
                         let doc =
                             source_declaration.and_then(|declaration| declaration.doc.clone());
+                        let parameter_names = source_declaration
+                            .map(|declaration| declaration.parameter_names.clone())
+                            .unwrap_or_default();
                         (
                             name.clone(),
                             Declaration {
@@ -301,6 +422,7 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                                     .is_some_and(|declaration| declaration.exported),
                                 doc,
                                 name,
+                                parameter_names,
                                 typ,
                             },
                         )
@@ -313,10 +435,130 @@ impl<S: Clone> Module<Arc<process::Expression<(), S>>, S> {
                         (name.clone(), (Definition { span, name, body }, typ))
                     })
                     .collect(),
+                warnings,
+                check_times,
             },
             errors.into_iter().collect(),
         )
     }
+
+    /// What can be found wrong with the names in the module without inferring
+    /// any types: type definitions that are invalid or have the wrong number
+    /// of arguments, types and values that are used but not defined, and
+    /// names that are defined twice. Much faster than [`Self::type_check`],
+    /// and reports a subset of its errors.
+    ///
+    /// Values and types are looked up together, so a type used as a value,
+    /// or the other way around, is left for the type checker to report.
+    pub fn check_names(&self) -> Vec<TypeError<S>>
+    where
+        S: Eq + std::hash::Hash,
+    {
+        let Globals {
+            type_defs,
+            declarations,
+            definitions,
+            mut errors,
+            warnings: _,
+        } = self.collect_globals(ExpansionLimit::default());
+
+        for (_, typ) in declarations.values() {
+            if let Err(error) = type_defs.validate_type(typ) {
+                errors.insert(error);
+            }
+        }
+
+        let is_defined = |name: &GlobalName<S>| {
+            definitions.contains_key(name)
+                || declarations.contains_key(name)
+                || type_defs.globals.contains_key(name)
+        };
+        for (_, body) in definitions.values() {
+            let DefinitionBody::Par(expression) = body else {
+                continue;
+            };
+            let _ = Arc::unwrap_or_clone(expression.clone()).map_global_names(
+                &mut |name: GlobalName<S>| {
+                    if !is_defined(&name) {
+                        errors.insert(TypeError::GlobalNameNotDefined(
+                            name.span.clone(),
+                            name.clone(),
+                        ));
+                    }
+                    Ok::<_, std::convert::Infallible>(name)
+                },
+            );
+        }
+
+        errors.into_iter().collect()
+    }
+
+    /// The type definitions, declarations, and definitions of the module by
+    /// name, and the errors in collecting them: invalid type definitions,
+    /// names defined or declared twice, and declarations without definitions.
+    /// Unused type parameters are collected as warnings.
+    fn collect_globals(&self, expansion_limit: ExpansionLimit) -> Globals<S>
+    where
+        S: Eq + std::hash::Hash,
+    {
+        let (type_defs, mut errors) = TypeDefs::new_with_functions_and_validation(
+            self.type_defs
+                .iter()
+                .map(|d| (&d.span, &d.name, &d.params, &d.typ)),
+            self.type_defs
+                .iter()
+                .filter_map(|d| Some((d.name.clone(), d.function?)))
+                .collect(),
+            expansion_limit,
+        );
+
+        let mut definitions = IndexMap::new();
+        for Definition { span, name, body } in &self.definitions {
+            if let Some((span1, _)) = definitions.insert(name.clone(), (span.clone(), body.clone()))
+            {
+                errors.insert(TypeError::NameAlreadyDefined(
+                    span.clone(),
+                    span1,
+                    name.clone(),
+                ));
+            }
+        }
+
+        let mut declarations = IndexMap::new();
+        for Declaration {
+            span, name, typ, ..
+        } in &self.declarations
+        {
+            if !definitions.contains_key(name) {
+                errors.insert(TypeError::DeclaredButNotDefined(span.clone(), name.clone()));
+            }
+            if let Some((span1, _)) = declarations.insert(name.clone(), (span.clone(), typ.clone()))
+            {
+                errors.insert(TypeError::NameAlreadyDeclared(
+                    span.clone(),
+                    span1,
+                    name.clone(),
+                ));
+            }
+        }
+
+        let warnings = type_defs.unused_type_parameters();
+        Globals {
+            type_defs,
+            declarations,
+            definitions,
+            errors,
+            warnings,
+        }
+    }
+}
+
+struct Globals<S> {
+    type_defs: TypeDefs<S>,
+    declarations: IndexMap<GlobalName<S>, (Span, Type<S>)>,
+    definitions: IndexMap<GlobalName<S>, (Span, DefinitionBody<Arc<process::Expression<(), S>>>)>,
+    errors: IndexSet<TypeError<S>>,
+    warnings: Vec<TypeError<S>>,
 }
 
 impl<Expr, S> Default for Module<Expr, S> {
@@ -338,6 +580,12 @@ impl<Expr, S: Clone + Eq + std::hash::Hash> Module<Expr, S> {
                 .iter()
                 .filter_map(|type_def| type_def.doc.clone().map(|doc| (type_def.name.clone(), doc)))
                 .collect(),
+            branches: self
+                .type_defs
+                .iter()
+                .filter(|type_def| !type_def.branch_docs.is_empty())
+                .map(|type_def| (type_def.name.clone(), type_def.branch_docs.clone()))
+                .collect(),
             declarations: self
                 .declarations
                 .iter()
@@ -393,7 +641,7 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> HoverIndex<S> {
                 name.clone(),
                 params.clone(),
                 typ.clone(),
-                docs.type_doc(name).cloned(),
+                docs.type_doc_with_branches(name),
                 span.clone(),
             );
             if is_visible(&file, &hover) {
@@ -510,7 +758,42 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> HoverIndex<S> {
     }
 
     pub fn query(&self, file: &FileName, row: u32, column: u32) -> Option<HoverInfo<S>> {
-        self.files.get(file)?.query(row, column)
+        let (_, hover) = self.files.get(file)?.query(row, column)?;
+        Some(hover.clone())
+    }
+
+    /// Like [`HoverIndex::query`], but also returns the span the entry covers.
+    pub(crate) fn query_span(
+        &self,
+        file: &FileName,
+        row: u32,
+        column: u32,
+    ) -> Option<(Span, HoverInfo<S>)> {
+        let ((start, end), hover) = self.files.get(file)?.query(row, column)?;
+        let span = Span::At {
+            start: *start,
+            end: *end,
+            file: file.clone(),
+            expansion: None,
+        };
+        Some((span, hover.clone()))
+    }
+
+    /// Every entry in the index, with the span it covers.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (Span, &HoverInfo<S>)> {
+        self.files.iter().flat_map(|(file, hovers)| {
+            hovers.pairs.iter().map(move |((start, end), hover)| {
+                (
+                    Span::At {
+                        start: *start,
+                        end: *end,
+                        file: file.clone(),
+                        expansion: None,
+                    },
+                    hover,
+                )
+            })
+        })
     }
 }
 
@@ -524,7 +807,7 @@ impl<S: Clone> FileHovers<S> {
         self.pairs.sort_by_key(|((start, _), _)| start.offset);
         self.pairs.dedup_by_key(|((start, _), _)| start.offset);
     }
-    fn query(&self, row: u32, column: u32) -> Option<HoverInfo<S>> {
+    fn query(&self, row: u32, column: u32) -> Option<&((Point, Point), HoverInfo<S>)> {
         let sorted_pairs = &self.pairs;
         if sorted_pairs.is_empty() {
             return None;
@@ -542,7 +825,8 @@ impl<S: Clone> FileHovers<S> {
             }
         }
 
-        let ((start, end), typ) = &sorted_pairs[lo];
+        let pair = &sorted_pairs[lo];
+        let ((start, end), _) = pair;
 
         // check if queried (row, column) is in the found span
         if row < start.row || (row == start.row && column < start.column) {
@@ -553,6 +837,6 @@ impl<S: Clone> FileHovers<S> {
         }
 
         // found a good span
-        Some(typ.clone())
+        Some(pair)
     }
 }
@@ -0,0 +1,198 @@
+//! Generating the source of a `.case` that takes apart a value of an `either`
+//! type, for editors to insert.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use indexmap::IndexMap;
+
+use super::language::{GlobalName, LocalName, TypeParameter};
+use super::lexer::{TokenKind, lex};
+use super::program::DocComment;
+use super::types::{PrimitiveType, Type, TypeDefs};
+use crate::location::FileName;
+
+/// What each generated branch evaluates to, for the user to replace.
+pub const PLACEHOLDER: &str = "todo";
+
+/// A `.case { ... }` with one branch per label of `typ`, to be appended right
+/// after an expression of that type. Every branch binds its payload with a
+/// pattern shaped after the payload's type, and evaluates to
+/// [`PLACEHOLDER`]. A branch documented in `branch_docs` comes with its
+/// documentation as a comment. `indent` is the indentation of the line the
+/// expression is on.
+///
+/// Returns `None` if `typ` isn't an `either`.
+pub fn case_skeleton<S: Clone + Eq + Hash>(
+    typ: &Type<S>,
+    type_defs: &TypeDefs<S>,
+    branch_docs: &IndexMap<GlobalName<S>, IndexMap<LocalName, DocComment>>,
+    indent: &str,
+) -> Option<String> {
+    let branches = typ.either_branches(type_defs)?;
+    let docs = documented_branches(typ, type_defs, branch_docs);
+    let mut skeleton = String::from(".case {\n");
+    for (label, payload) in &branches {
+        if let Some(doc) = docs.and_then(|docs| docs.get(label)) {
+            for line in doc.markdown.lines() {
+                let separator = if line.is_empty() { "" } else { " " };
+                skeleton.push_str(&format!("{indent}  //{separator}{line}\n"));
+            }
+        }
+        let mut names = Names::default();
+        let pattern = payload_pattern(payload, type_defs, &mut names);
+        let separator = if pattern.starts_with(['(', '<', '!']) {
+            ""
+        } else {
+            " "
+        };
+        skeleton.push_str(&format!(
+            "{indent}  .{label}{separator}{pattern} => {PLACEHOLDER},\n"
+        ));
+    }
+    skeleton.push_str(&format!("{indent}}}"));
+    Some(skeleton)
+}
+
+/// The branch documentation of the named type `typ` is, or of the first type
+/// it's an alias of that has any.
+fn documented_branches<'a, S: Clone + Eq + Hash>(
+    typ: &Type<S>,
+    type_defs: &TypeDefs<S>,
+    branch_docs: &'a IndexMap<GlobalName<S>, IndexMap<LocalName, DocComment>>,
+) -> Option<&'a IndexMap<LocalName, DocComment>> {
+    let mut typ = typ.clone();
+    // A name can't come up twice without the definitions being rejected as
+    // cyclic.
+    for _ in 0..=type_defs.globals.len() {
+        let Type::Name(_, name, _) = &typ else {
+            return None;
+        };
+        if let Some(docs) = branch_docs.get(name) {
+            return Some(docs);
+        }
+        typ = typ.expand_definition(type_defs).ok()?;
+    }
+    None
+}
+
+/// A pattern that binds every part of a payload of this type: a name for each
+/// value received, `type` for each type received, and `!` at the end if
+/// nothing is left.
+fn payload_pattern<S: Clone + Eq + Hash>(
+    typ: &Type<S>,
+    type_defs: &TypeDefs<S>,
+    names: &mut Names,
+) -> String {
+    let mut pattern = String::new();
+    let mut items = Vec::new();
+    let mut typ = typ.clone();
+    loop {
+        if matches!(typ, Type::Name(..) | Type::DualName(..)) {
+            match typ.expand_definition(type_defs) {
+                Ok(expanded) if matches!(expanded, Type::Pair(..) | Type::Exists(..)) => {
+                    typ = expanded;
+                }
+                _ => {}
+            }
+        }
+        match typ {
+            Type::Pair(_, argument, rest, vars) => {
+                if !vars.is_empty() {
+                    flush_items(&mut pattern, &mut items);
+                    pattern.push_str(&format!("<{}>", type_parameters(&vars)));
+                }
+                items.push(payload_pattern(&argument, type_defs, names));
+                if !vars.is_empty() {
+                    flush_items(&mut pattern, &mut items);
+                }
+                typ = *rest;
+            }
+            Type::Exists(_, parameter, body) => {
+                items.push(format!("type {}", parameter.name));
+                typ = *body;
+            }
+            Type::Break(_) => {
+                flush_items(&mut pattern, &mut items);
+                pattern.push('!');
+                return pattern;
+            }
+            typ => {
+                let prefixed = !pattern.is_empty() || !items.is_empty();
+                flush_items(&mut pattern, &mut items);
+                if prefixed {
+                    pattern.push(' ');
+                }
+                pattern.push_str(&names.fresh(&typ));
+                return pattern;
+            }
+        }
+    }
+}
+
+fn flush_items(pattern: &mut String, items: &mut Vec<String>) {
+    if !items.is_empty() {
+        pattern.push_str(&format!("({})", items.join(", ")));
+        items.clear();
+    }
+}
+
+fn type_parameters(parameters: &[TypeParameter]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| parameter.name.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Variable names for the parts of a pattern, after their types, made unique
+/// by numbering.
+#[derive(Default)]
+struct Names {
+    used: HashMap<String, usize>,
+}
+
+impl Names {
+    fn fresh<S>(&mut self, typ: &Type<S>) -> String {
+        let base = match typ {
+            Type::Name(_, name, _) | Type::DualName(_, name, _) => lowercase_first(&name.primary),
+            Type::Primitive(_, primitive) => match primitive {
+                PrimitiveType::Nat => "n",
+                PrimitiveType::Int => "i",
+                PrimitiveType::Float => "x",
+                PrimitiveType::String => "s",
+                PrimitiveType::Char => "c",
+                PrimitiveType::Byte => "b",
+                PrimitiveType::Bytes => "bytes",
+            }
+            .to_string(),
+            Type::Function(..) => String::from("f"),
+            _ => String::from("value"),
+        };
+        let base = if is_identifier(&base) {
+            base
+        } else {
+            String::from("value")
+        };
+        let count = self.used.entry(base.clone()).or_default();
+        *count += 1;
+        match *count {
+            1 => base,
+            count => format!("{base}{count}"),
+        }
+    }
+}
+
+fn lowercase_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Whether `name` lexes as a variable name, and not as a keyword.
+fn is_identifier(name: &str) -> bool {
+    let tokens = lex(name, &FileName::from("skeleton.par"));
+    matches!(tokens.as_slice(), [token] if token.kind == TokenKind::LowercaseIdentifier)
+}
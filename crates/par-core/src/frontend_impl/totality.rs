@@ -0,0 +1,364 @@
+//! A heuristic check of whether an `unfounded` loop needs to be unfounded.
+//!
+//! The type checker only accepts a `begin`/`loop` if every `loop` goes around
+//! with a value that descends from the subject of the `begin`; `unfounded`
+//! opts out of that. For each `unfounded` loop, this runs a small size-change
+//! analysis on the checked process: a variable is smaller than the subject if
+//! a command took it apart from the subject, and unrelated if it was bound to
+//! anything else. If every `loop` goes around with something smaller, the
+//! loop appears total. Otherwise, the first path to a `loop` that doesn't is
+//! reported.
+//!
+//! This is only a hint. Functions, boxes, and channels are opaque to the
+//! analysis, and it gives up on a loop after a fixed number of steps.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use super::language::LocalName;
+use super::process::{Command, Expression, Process};
+use super::program::{CheckedModule, DefinitionBody};
+use super::types::Type;
+use crate::location::Span;
+
+/// How many processes the analysis visits for one loop before giving up.
+pub const DEFAULT_TOTALITY_BUDGET: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct TotalityHint {
+    /// The `unfounded` command.
+    pub span: Span,
+    pub verdict: TotalityVerdict,
+}
+
+#[derive(Debug, Clone)]
+pub enum TotalityVerdict {
+    /// Every `loop` goes around with a part of the subject.
+    AppearsTotal,
+    /// A `loop` goes around with something that isn't a part of the subject.
+    /// The path lists the branches taken to get there, ending at the `loop`.
+    LikelyDiverges { path: Vec<(Span, String)> },
+}
+
+impl Display for TotalityHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.verdict {
+            TotalityVerdict::AppearsTotal => write!(
+                f,
+                "{}: this loop actually appears total, consider removing `unfounded`",
+                self.span
+            ),
+            TotalityVerdict::LikelyDiverges { path } => {
+                write!(f, "{}: this loop likely diverges via this path:", self.span)?;
+                for (span, step) in path {
+                    write!(f, "\n  {span}: {step}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Analyzes every `unfounded` loop in the checked definitions, visiting at
+/// most `budget` processes for each. Loops that run out of budget get no hint.
+pub fn totality_hints<S: Clone>(module: &CheckedModule<S>, budget: usize) -> Vec<TotalityHint> {
+    let mut hints = Vec::new();
+    for (definition, _) in module.definitions.values() {
+        if let DefinitionBody::Par(expression) = &definition.body {
+            collect_in_expression(expression, budget, &mut hints);
+        }
+    }
+    hints
+}
+
+fn collect_in_expression<S: Clone>(
+    expression: &Expression<Type<S>, S>,
+    budget: usize,
+    hints: &mut Vec<TotalityHint>,
+) {
+    match expression {
+        Expression::Box(_, _, expression, _) => collect_in_expression(expression, budget, hints),
+        Expression::Chan { process, .. } => collect_in_process(process, budget, hints),
+        Expression::Global(..)
+        | Expression::Variable(..)
+        | Expression::Primitive(..)
+        | Expression::External(..) => {}
+    }
+}
+
+fn collect_in_process<S: Clone>(
+    process: &Process<Type<S>, S>,
+    budget: usize,
+    hints: &mut Vec<TotalityHint>,
+) {
+    match process {
+        Process::Let { value, then, .. } => {
+            collect_in_expression(value, budget, hints);
+            collect_in_process(then, budget, hints);
+        }
+        Process::Do {
+            span,
+            name,
+            command,
+            ..
+        } => {
+            if let Command::Begin {
+                unfounded: true,
+                label,
+                body,
+                ..
+            } = command
+            {
+                let mut analysis = LoopAnalysis {
+                    label,
+                    budget,
+                    path: Vec::new(),
+                    diverging: None,
+                };
+                let sizes = HashMap::from([(name.clone(), Size::Same)]);
+                if analysis.process(body, sizes) {
+                    hints.push(TotalityHint {
+                        span: span.clone(),
+                        verdict: match analysis.diverging {
+                            Some(path) => TotalityVerdict::LikelyDiverges { path },
+                            None => TotalityVerdict::AppearsTotal,
+                        },
+                    });
+                }
+            }
+            match command {
+                Command::Link(expression) => collect_in_expression(expression, budget, hints),
+                Command::Send(argument, process) => {
+                    collect_in_expression(argument, budget, hints);
+                    collect_in_process(process, budget, hints);
+                }
+                Command::Case(_, processes, else_process) => {
+                    for process in processes.iter().chain(else_process) {
+                        collect_in_process(process, budget, hints);
+                    }
+                }
+                Command::Noop(process)
+                | Command::Receive(_, _, _, process, _)
+                | Command::Signal(_, process)
+                | Command::Continue(process)
+                | Command::Begin { body: process, .. }
+                | Command::SendType(_, process)
+                | Command::ReceiveType(_, process) => collect_in_process(process, budget, hints),
+                Command::Break | Command::Loop(..) => {}
+            }
+        }
+        Process::Poll {
+            clients,
+            then,
+            else_,
+            ..
+        } => {
+            for client in clients {
+                collect_in_expression(client, budget, hints);
+            }
+            collect_in_process(then, budget, hints);
+            collect_in_process(else_, budget, hints);
+        }
+        Process::Submit { values, .. } => {
+            for value in values {
+                collect_in_expression(value, budget, hints);
+            }
+        }
+        Process::Block(_, _, body, then) => {
+            collect_in_process(body, budget, hints);
+            collect_in_process(then, budget, hints);
+        }
+        Process::Goto(..) | Process::Unreachable(_) => {}
+    }
+}
+
+/// How a variable relates to the subject of the `begin` being analyzed.
+#[derive(Debug, Clone)]
+enum Size {
+    /// The subject itself, not taken apart yet.
+    Same,
+    /// A part of the subject.
+    Smaller,
+    /// Something else, bound at this span if it's known.
+    Unrelated(Option<Span>),
+}
+
+struct LoopAnalysis<'a> {
+    label: &'a Option<LocalName>,
+    budget: usize,
+    /// The branches taken to get to the current process.
+    path: Vec<(Span, String)>,
+    /// The path to the first `loop` that doesn't go around with a part of
+    /// the subject.
+    diverging: Option<Vec<(Span, String)>>,
+}
+
+impl LoopAnalysis<'_> {
+    /// Returns `false` if the budget ran out.
+    fn process<S>(
+        &mut self,
+        process: &Process<Type<S>, S>,
+        mut sizes: HashMap<LocalName, Size>,
+    ) -> bool {
+        if self.diverging.is_some() {
+            return true;
+        }
+        let Some(budget) = self.budget.checked_sub(1) else {
+            return false;
+        };
+        self.budget = budget;
+
+        match process {
+            Process::Let {
+                span,
+                name,
+                value,
+                then,
+                ..
+            } => {
+                let size = match value.as_ref() {
+                    Expression::Variable(_, variable, _, _) => size_of(&sizes, variable),
+                    _ => Size::Unrelated(Some(span.clone())),
+                };
+                if !self.expression(value, &sizes) {
+                    return false;
+                }
+                sizes.insert(name.clone(), size);
+                self.process(then, sizes)
+            }
+            Process::Do {
+                span,
+                name,
+                command,
+                ..
+            } => self.command(span, name, command, sizes),
+            Process::Poll {
+                clients,
+                then,
+                else_,
+                ..
+            } => {
+                clients.iter().all(|client| self.expression(client, &sizes))
+                    && self.process(then, sizes.clone())
+                    && self.process(else_, sizes)
+            }
+            Process::Submit { values, .. } => {
+                values.iter().all(|value| self.expression(value, &sizes))
+            }
+            Process::Block(_, _, body, then) => {
+                self.process(body, sizes.clone()) && self.process(then, sizes)
+            }
+            Process::Goto(..) | Process::Unreachable(_) => true,
+        }
+    }
+
+    fn command<S>(
+        &mut self,
+        span: &Span,
+        object: &LocalName,
+        command: &Command<Type<S>, S>,
+        mut sizes: HashMap<LocalName, Size>,
+    ) -> bool {
+        // Any command on the subject, or on a part of it, takes it apart.
+        let part = match size_of(&sizes, object) {
+            Size::Same | Size::Smaller => Size::Smaller,
+            Size::Unrelated(bound) => Size::Unrelated(bound),
+        };
+
+        match command {
+            Command::Noop(process) | Command::Continue(process) => self.process(process, sizes),
+            Command::Link(expression) => self.expression(expression, &sizes),
+            Command::Send(argument, process) => {
+                if !self.expression(argument, &sizes) {
+                    return false;
+                }
+                sizes.insert(object.clone(), part);
+                self.process(process, sizes)
+            }
+            Command::Receive(parameter, _, _, process, _) => {
+                sizes.insert(parameter.clone(), part.clone());
+                sizes.insert(object.clone(), part);
+                self.process(process, sizes)
+            }
+            Command::Signal(_, process) => {
+                sizes.insert(object.clone(), part);
+                self.process(process, sizes)
+            }
+            Command::Case(branches, processes, else_process) => {
+                sizes.insert(object.clone(), part);
+                let branches = branches
+                    .iter()
+                    .map(|branch| format!("takes the `.{branch}` branch"))
+                    .chain(
+                        else_process
+                            .iter()
+                            .map(|_| String::from("takes the `else` branch")),
+                    );
+                for (step, process) in branches.zip(processes.iter().chain(else_process)) {
+                    self.path.push((span.clone(), step));
+                    let finished = self.process(process, sizes.clone());
+                    self.path.pop();
+                    if !finished {
+                        return false;
+                    }
+                }
+                true
+            }
+            Command::Break => true,
+            // An inner loop with the same label shadows this one.
+            Command::Begin { label, body, .. } => label == self.label || self.process(body, sizes),
+            Command::Loop(label, _, _) => {
+                if label != self.label {
+                    return true;
+                }
+                let mut path = self.path.clone();
+                match size_of(&sizes, object) {
+                    Size::Smaller => return true,
+                    Size::Same => path.push((
+                        span.clone(),
+                        format!("loops with `{object}` before taking it apart"),
+                    )),
+                    Size::Unrelated(bound) => {
+                        if let Some(bound) = bound {
+                            path.push((
+                                bound,
+                                format!(
+                                    "binds `{object}` to something that isn't a part of the subject"
+                                ),
+                            ));
+                        }
+                        path.push((span.clone(), format!("loops with `{object}`")));
+                    }
+                }
+                self.diverging = Some(path);
+                true
+            }
+            Command::SendType(_, process) | Command::ReceiveType(_, process) => {
+                self.process(process, sizes)
+            }
+        }
+    }
+
+    /// Looks for loops in the processes inside an expression.
+    fn expression<S>(
+        &mut self,
+        expression: &Expression<Type<S>, S>,
+        sizes: &HashMap<LocalName, Size>,
+    ) -> bool {
+        match expression {
+            Expression::Box(_, _, expression, _) => self.expression(expression, sizes),
+            Expression::Chan { process, .. } => self.process(process, sizes.clone()),
+            Expression::Global(..)
+            | Expression::Variable(..)
+            | Expression::Primitive(..)
+            | Expression::External(..) => true,
+        }
+    }
+}
+
+fn size_of(sizes: &HashMap<LocalName, Size>, variable: &LocalName) -> Size {
+    sizes
+        .get(variable)
+        .cloned()
+        .unwrap_or(Size::Unrelated(None))
+}
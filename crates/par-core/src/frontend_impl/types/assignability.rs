@@ -1,8 +1,10 @@
+use crate::frontend_impl::language::LocalName;
 use crate::frontend_impl::language::TypeConstraint;
 use crate::frontend_impl::language::TypeParameter;
 use crate::frontend_impl::types::assignability::SubtypeResult::{Compatible, Cycle, Incompatible};
-use crate::frontend_impl::types::{PrimitiveType, Type, TypeDefs, TypeError};
+use crate::frontend_impl::types::{LoopId, PrimitiveType, Type, TypeDefs, TypeError, UnionKind};
 use crate::location::Span;
+use crate::stack;
 use indexmap::IndexSet;
 use std::cmp::max;
 use std::collections::BTreeMap;
@@ -32,6 +34,14 @@ impl<'a, S: Clone + Eq + std::hash::Hash> SubtypeContext<'a, S> {
             Type::DualName(span, name, args) => {
                 self.normalize(self.type_defs.get(&span, &name, &args)?.dual(Span::None))?
             }
+            Type::Union(span, kind, left, right) => {
+                let typ = self.type_defs.union(&span, kind, &left, &right)?;
+                // An open row is as normal as it gets.
+                match typ.as_open_row() {
+                    Some(_) => typ,
+                    None => self.normalize(typ)?,
+                }
+            }
             t => t,
         })
     }
@@ -207,6 +217,14 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
     }
 
     fn is_subtype_helper(
+        type1: Self,
+        type2: Self,
+        ctx: SubtypeContext<S>,
+    ) -> Result<SubtypeResult<S>, TypeError<S>> {
+        stack::grow(|| Self::is_subtype_helper_inner(type1, type2, ctx))
+    }
+
+    fn is_subtype_helper_inner(
         mut type1: Self,
         mut type2: Self,
         mut ctx: SubtypeContext<S>,
@@ -368,7 +386,7 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
         if let Type::Iterative { asc: asc1, .. } = type1 {
             if !asc1.is_empty() {
                 return Some(if let Self::Recursive { asc: asc2, .. } = type2 {
-                    if asc1.is_subset(asc2) {
+                    if LoopId::all_in(asc1, asc2) {
                         Compatible
                     } else {
                         Incompatible
@@ -382,7 +400,7 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
         if let Type::Recursive { asc: asc2, .. } = type2 {
             if !asc2.is_empty() {
                 return Some(if let Self::Recursive { asc: asc1, .. } = type1 {
-                    if asc2.is_subset(asc1) {
+                    if LoopId::all_in(asc2, asc1) {
                         Compatible
                     } else {
                         Incompatible
@@ -402,14 +420,14 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
         ctx: &SubtypeContext<S>,
     ) -> Result<Option<SubtypeResult<S>>, TypeError<S>> {
         if let Type::Recursive { .. } | Type::Iterative { .. } = type1 {
-            let type1 = Type::expand_fixpoint_unfounded(type1)?;
+            let type1 = type1.expand_fixpoint_unfounded(ctx.type_defs)?;
             return Ok(Some(
                 Type::is_subtype_helper(type1, type2.clone(), ctx.clone())?.ttl_dec(),
             ));
         }
 
         if let Type::Recursive { .. } | Type::Iterative { .. } = type2 {
-            let type2 = Type::expand_fixpoint_unfounded(type2)?;
+            let type2 = type2.expand_fixpoint_unfounded(ctx.type_defs)?;
             return Ok(Some(
                 Type::is_subtype_helper(type1.clone(), type2, ctx.clone())?.ttl_dec(),
             ));
@@ -538,6 +556,9 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
         type2: Self,
         ctx: SubtypeContext<S>,
     ) -> Result<SubtypeResult<S>, TypeError<S>> {
+        if let Some((kind, _, _)) = type1.as_open_row().or(type2.as_open_row()) {
+            return Type::is_subtype_row(kind, type1, type2, ctx);
+        }
         match (type1, type2) {
             (Self::Either(_, branches1), _) if branches1.is_empty() => Ok(Compatible),
             (Self::Either(_, branches1), Self::Either(_, branches2)) => {
@@ -579,6 +600,61 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
         }
     }
 
+    /// The known branches of an `either` or a `choice`, depending on `kind`,
+    /// and the type variable standing for the rest, if there is one. A type
+    /// variable alone is a row with no known branches.
+    fn row_parts(kind: UnionKind, typ: &Self) -> Option<(BTreeMap<LocalName, Self>, Option<Self>)> {
+        if let Some((row_kind, branches, rest)) = typ.as_open_row() {
+            return (row_kind == kind).then(|| (branches.clone(), Some(rest.clone())));
+        }
+        match (kind, typ) {
+            (UnionKind::Either, Self::Either(_, branches))
+            | (UnionKind::Choice, Self::Choice(_, branches)) => Some((branches.clone(), None)),
+            (_, Self::Var(..) | Self::DualVar(..)) => Some((BTreeMap::new(), Some(typ.clone()))),
+            _ => None,
+        }
+    }
+
+    /// Subtyping of rows, when one side is open. An `either` may only gain
+    /// branches, and a `choice` only lose them, so the rest of the smaller
+    /// side must be the rest of the bigger one.
+    fn is_subtype_row(
+        kind: UnionKind,
+        type1: Self,
+        type2: Self,
+        ctx: SubtypeContext<S>,
+    ) -> Result<SubtypeResult<S>, TypeError<S>> {
+        let (Some((branches1, rest1)), Some((branches2, rest2))) =
+            (Self::row_parts(kind, &type1), Self::row_parts(kind, &type2))
+        else {
+            return Ok(Incompatible);
+        };
+        let ((smaller, smaller_rest), (bigger, bigger_rest)) = match kind {
+            UnionKind::Either => ((branches1, rest1), (branches2, rest2)),
+            UnionKind::Choice => ((branches2, rest2), (branches1, rest1)),
+        };
+        let rest_included = match (&smaller_rest, &bigger_rest) {
+            (None, _) => true,
+            (Some(rest1), Some(rest2)) => rest1.is_same_row_variable(rest2),
+            (Some(_), None) => false,
+        };
+        if !rest_included {
+            return Ok(Incompatible);
+        }
+        let mut res = Compatible;
+        for (branch, t) in smaller {
+            let Some(u) = bigger.get(&branch) else {
+                return Ok(Incompatible);
+            };
+            res = res
+                & match kind {
+                    UnionKind::Either => Type::is_subtype_helper(t, u.clone(), ctx.clone())?,
+                    UnionKind::Choice => Type::is_subtype_helper(u.clone(), t, ctx.clone())?,
+                };
+        }
+        Ok(res)
+    }
+
     fn is_subtype_quantified(
         loc: Span,
         param1: TypeParameter,
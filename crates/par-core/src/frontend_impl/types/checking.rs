@@ -1,7 +1,7 @@
 use super::super::language::{LocalName, TypeConstraint, TypeParameter};
 use super::super::process::{Captures, Command, Expression, PollKind, Process, VariableUsage};
-use super::context::{BlockPathContext, BlockScope, PollPointScope, PollScope};
-use super::core::{LoopId, Operation, Type, get_primitive_type};
+use super::context::{BlockPathContext, BlockScope, LoopPoint, PollPointScope, PollScope};
+use super::core::{LoopId, Operation, Type, UnionKind, get_primitive_type};
 use super::error::TypeError;
 use super::lattice::union_types;
 use super::{Context, TypeDefs};
@@ -9,6 +9,7 @@ use crate::frontend::TypeError::TypeMustBeKnownAtThisPoint;
 use crate::frontend_impl::types::implicit::{resolve_holes, substitute_holes};
 use crate::frontend_impl::types::lattice::intersect_types;
 use crate::location::Span;
+use crate::stack;
 use im::HashMap;
 use indexmap::{IndexMap, IndexSet};
 use par_runtime::primitive::Primitive;
@@ -95,6 +96,14 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         &mut self,
         process: &Process<(), S>,
         emit: &mut impl FnMut(TypeError<S>),
+    ) -> Arc<Process<Type<S>, S>> {
+        stack::grow(|| self.check_process_inner(process, emit))
+    }
+
+    fn check_process_inner(
+        &mut self,
+        process: &Process<(), S>,
+        emit: &mut impl FnMut(TypeError<S>),
     ) -> Arc<Process<Type<S>, S>> {
         match process {
             Process::Let {
@@ -174,7 +183,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         }
         let expression = self.check_expression(None, expression, annotated_type, emit);
         let typ = annotated_type.clone();
-        if let Err(e) = self.put(span, name.clone(), typ.clone()) {
+        if let Err(e) = self.bind(span, name.clone(), typ.clone()) {
             emit(e);
         }
         let process = self.check_process(process, emit);
@@ -198,7 +207,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         emit: &mut impl FnMut(TypeError<S>),
     ) -> Arc<Process<Type<S>, S>> {
         let (expression, typ) = self.infer_expression(None, expression, emit);
-        if let Err(e) = self.put(span, name.clone(), typ.clone()) {
+        if let Err(e) = self.bind(span, name.clone(), typ.clone()) {
             emit(e);
         }
         let process = self.check_process(process, emit);
@@ -305,17 +314,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             for client in clients {
                 let (typed, typ) = self.infer_expression(None, client, emit);
                 typed_clients.push(typed);
-                let mut typ = typ;
-                loop {
-                    let next = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
-                        emit(e);
-                        Type::Fail(span.clone())
-                    });
-                    if next == typ {
-                        break;
-                    }
-                    typ = next;
-                }
+                let typ = self.expand_definitions(span, typ, emit);
                 let Type::Recursive { .. } = typ else {
                     emit(TypeError::PollClientMustBeRecursive(span.clone(), typ));
                     continue;
@@ -354,6 +353,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 &point_label,
                 &point_body,
                 display_hint.0.as_ref(),
+                self.type_defs.expansion_limit,
             )
             .unwrap_or_else(|e| {
                 emit(e);
@@ -403,19 +403,8 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 });
             }
 
-            let mut client_type = client_type.expect("clients is not empty");
-            loop {
-                let next = client_type
-                    .expand_definition(&self.type_defs)
-                    .unwrap_or_else(|e| {
-                        emit(e);
-                        Type::Fail(span.clone())
-                    });
-                if next == client_type {
-                    break;
-                }
-                client_type = next;
-            }
+            let client_type =
+                self.expand_definitions(span, client_type.expect("clients is not empty"), emit);
 
             base = self.clone();
 
@@ -447,11 +436,17 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 display_hint: display_hint.clone(),
             };
 
-            name_typ = Type::expand_recursive(&asc, &label, &body, display_hint.0.as_ref())
-                .unwrap_or_else(|e| {
-                    emit(e);
-                    Type::Fail(span.clone())
-                });
+            name_typ = Type::expand_recursive(
+                &asc,
+                &label,
+                &body,
+                display_hint.0.as_ref(),
+                self.type_defs.expansion_limit,
+            )
+            .unwrap_or_else(|e| {
+                emit(e);
+                Type::Fail(span.clone())
+            });
 
             then_ctx = base.clone();
             let prev_poll = then_ctx.poll.take();
@@ -477,7 +472,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         if let Err(e) = then_ctx.put(span, driver.clone(), Type::Continue(span.clone())) {
             emit(e);
         }
-        if let Err(e) = then_ctx.put(span, name.clone(), name_typ.clone()) {
+        if let Err(e) = then_ctx.bind(span, name.clone(), name_typ.clone()) {
             emit(e);
         }
         let typed_then = then_ctx.check_process(then, emit);
@@ -578,17 +573,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         let mut typed_values = Vec::with_capacity(values.len());
         for value in values {
             let (typed, typ) = self.infer_expression(None, value, emit);
-            let mut typ = typ;
-            loop {
-                let next = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
-                    emit(e);
-                    Type::Fail(span.clone())
-                });
-                if next == typ {
-                    break;
-                }
-                typ = next;
-            }
+            let typ = self.expand_definitions(span, typ, emit);
             if !typ
                 .require_assignable_to(&poll_pool_type, &self.type_defs)
                 .unwrap_or(true)
@@ -785,6 +770,23 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 emit,
             );
         }
+        if let Type::Union(..) = typ
+            && typ.as_open_row().is_none()
+        {
+            let expanded = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
+                emit(e);
+                Type::Fail(span.clone())
+            });
+            return self.check_command(
+                inference_subject,
+                span,
+                object,
+                &expanded,
+                command,
+                mode,
+                emit,
+            );
+        }
         if let Type::Box(_, inner) = typ {
             return self.check_command(inference_subject, span, object, inner, command, mode, emit);
         }
@@ -813,12 +815,18 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 ..
             } = typ
             {
-                let expanded =
-                    Type::expand_iterative(span, top_asc, top_label, body, display_hint.0.as_ref())
-                        .unwrap_or_else(|e| {
-                            emit(e);
-                            Type::Fail(span.clone())
-                        });
+                let expanded = Type::expand_iterative(
+                    span,
+                    top_asc,
+                    top_label,
+                    body,
+                    display_hint.0.as_ref(),
+                    self.type_defs.expansion_limit,
+                )
+                .unwrap_or_else(|e| {
+                    emit(e);
+                    Type::Fail(span.clone())
+                });
                 return self.check_command(
                     inference_subject,
                     span,
@@ -839,12 +847,17 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 ..
             } = typ
             {
-                let expanded =
-                    Type::expand_recursive(top_asc, top_label, body, display_hint.0.as_ref())
-                        .unwrap_or_else(|e| {
-                            emit(e);
-                            Type::Fail(span.clone())
-                        });
+                let expanded = Type::expand_recursive(
+                    top_asc,
+                    top_label,
+                    body,
+                    display_hint.0.as_ref(),
+                    self.type_defs.expansion_limit,
+                )
+                .unwrap_or_else(|e| {
+                    emit(e);
+                    Type::Fail(span.clone())
+                });
                 return self.check_command(
                     inference_subject,
                     span,
@@ -962,6 +975,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             Command::Begin {
                 unfounded,
                 label,
+                subjects,
                 captures,
                 body: process,
             } => self.check_command_begin(
@@ -971,6 +985,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 typ,
                 *unfounded,
                 label,
+                subjects,
                 captures,
                 process,
                 mode,
@@ -1161,7 +1176,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             }
             let fail = Type::Fail(span.clone());
             self.put(span, object.clone(), fail.clone()).ok();
-            self.put(span, parameter.clone(), fail.clone()).ok();
+            self.bind(span, parameter.clone(), fail.clone()).ok();
             let (process, inferred) = self.analyze_process(process, mode, emit);
             return (
                 Command::Receive(
@@ -1185,7 +1200,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             ));
             let fail = Type::Fail(span.clone());
             self.put(span, object.clone(), fail.clone()).ok();
-            self.put(span, parameter.clone(), fail.clone()).ok();
+            self.bind(span, parameter.clone(), fail.clone()).ok();
             let (process, inferred) = self.analyze_process(process, mode, emit);
             return (
                 Command::Receive(
@@ -1341,7 +1356,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 emit(e);
             }
         }
-        if let Err(e) = self.put(span, parameter.clone(), param_type.clone()) {
+        if let Err(e) = self.bind(span, parameter.clone(), param_type.clone()) {
             emit(e);
         }
         if let Err(e) = self.put(span, object.clone(), then_type) {
@@ -1370,7 +1385,15 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         mode: &ProcessAnalyzerMode,
         emit: &mut impl FnMut(TypeError<S>),
     ) -> (Command<Type<S>, S>, Option<Type<S>>) {
-        let Type::Choice(_, branches) = typ else {
+        // Of an open row, only the known branches can be chosen.
+        let branches = match typ {
+            Type::Choice(_, branches) => Some(branches),
+            _ => match typ.as_open_row() {
+                Some((UnionKind::Choice, branches, _)) => Some(branches),
+                _ => None,
+            },
+        };
+        let Some(branches) = branches else {
             if !matches!(typ, Type::Fail(_)) {
                 emit(TypeError::InvalidOperation(
                     span.clone(),
@@ -1414,7 +1437,14 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         mode: &ProcessAnalyzerMode,
         emit: &mut impl FnMut(TypeError<S>),
     ) -> (Command<Type<S>, S>, Option<Type<S>>) {
-        let Type::Either(_, branch_types) = typ else {
+        let (branch_types, rest) = match typ {
+            Type::Either(_, branch_types) => (Some(branch_types), None),
+            _ => match typ.as_open_row() {
+                Some((UnionKind::Either, branch_types, rest)) => (Some(branch_types), Some(rest)),
+                _ => (None, None),
+            },
+        };
+        let Some(branch_types) = branch_types else {
             if !matches!(typ, Type::Fail(_)) {
                 emit(TypeError::InvalidOperation(
                     span.clone(),
@@ -1470,11 +1500,21 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             );
         }
 
+        // The branches behind the type variable can't be listed, only
+        // handled by an `else`.
+        if rest.is_some() && else_process.is_none() {
+            emit(TypeError::CaseOnOpenRowWithoutElse(
+                span.clone(),
+                typ.clone(),
+            ));
+        }
+
         let typed_else_process = match else_process {
             Some(process) => Some(self.check_command_case_else(
                 span,
                 object,
                 &mut remaining_branches,
+                rest,
                 &original_context,
                 process,
                 &mut inferred_type,
@@ -1540,6 +1580,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         span: &Span,
         object: &LocalName,
         remaining_branches: &mut BTreeMap<LocalName, Type<S>>,
+        rest: Option<&Type<S>>,
         original_context: &Self,
         process: &Arc<Process<(), S>>,
         inferred_type: &mut Option<Type<S>>,
@@ -1547,7 +1588,17 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         emit: &mut impl FnMut(TypeError<S>),
     ) -> Arc<Process<Type<S>, S>> {
         *self = original_context.clone();
-        let object_type = Type::Either(Span::None, std::mem::take(remaining_branches));
+        let remaining = std::mem::take(remaining_branches);
+        let object_type = match rest {
+            Some(rest) if remaining.is_empty() => rest.clone(),
+            Some(rest) => Type::Union(
+                Span::None,
+                UnionKind::Either,
+                Box::new(Type::Either(Span::None, remaining)),
+                Box::new(rest.clone()),
+            ),
+            None => Type::Either(Span::None, remaining),
+        };
         if let Err(e) = self.put(span, object.clone(), object_type) {
             emit(e);
         }
@@ -1606,6 +1657,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         typ: &Type<S>,
         unfounded: bool,
         label: &Option<LocalName>,
+        subjects: &Arc<[LocalName]>,
         captures: &Captures,
         process: &Arc<Process<(), S>>,
         mode: &ProcessAnalyzerMode,
@@ -1623,6 +1675,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 Command::Begin {
                     unfounded,
                     label: label.clone(),
+                    subjects: subjects.clone(),
                     captures: captures.clone(),
                     body: process,
                 },
@@ -1651,6 +1704,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 Command::Begin {
                     unfounded,
                     label: label.clone(),
+                    subjects: subjects.clone(),
                     captures: captures.clone(),
                     body: process,
                 },
@@ -1660,36 +1714,45 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
 
         let mut typ_asc = typ_asc.clone();
 
-        if !unfounded {
-            let loop_id = LoopId::new();
-            typ_asc.insert(loop_id);
+        let driver_id = (!unfounded).then(LoopId::new);
+        if let Some(loop_id) = &driver_id {
+            typ_asc.insert(loop_id.clone());
         }
+        let variables = Arc::new(
+            self.variables
+                .iter()
+                .filter(|&(name, _)| captures.names.contains_key(name))
+                .map(|(name, typ)| (name.clone(), typ.clone()))
+                .collect::<IndexMap<_, _>>(),
+        );
+        let marked_subjects = self.mark_subjects_of_begin(span, subjects, emit);
         self.loop_points.insert(
             label.clone(),
-            (
-                Type::Recursive {
+            LoopPoint {
+                driver_type: Type::Recursive {
                     span: typ_span.clone(),
                     asc: typ_asc.clone(),
                     label: typ_label.clone(),
                     body: typ_body.clone(),
                     display_hint: display_hint.clone(),
                 },
-                Arc::new(
-                    self.variables
-                        .iter()
-                        .filter(|&(name, _)| captures.names.contains_key(name))
-                        .map(|(name, typ)| (name.clone(), typ.clone()))
-                        .collect::<IndexMap<_, _>>(),
-                ),
-            ),
+                driver_id,
+                variables,
+                subjects: marked_subjects,
+            },
         );
 
-        let expanded =
-            Type::expand_recursive(&typ_asc, typ_label, typ_body, display_hint.0.as_ref())
-                .unwrap_or_else(|e| {
-                    emit(e);
-                    Type::Fail(span.clone())
-                });
+        let expanded = Type::expand_recursive(
+            &typ_asc,
+            typ_label,
+            typ_body,
+            display_hint.0.as_ref(),
+            self.type_defs.expansion_limit,
+        )
+        .unwrap_or_else(|e| {
+            emit(e);
+            Type::Fail(span.clone())
+        });
         if let Err(e) = self.put(span, object.clone(), expanded) {
             emit(e);
         }
@@ -1698,6 +1761,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             Command::Begin {
                 unfounded,
                 label: label.clone(),
+                subjects: subjects.clone(),
                 captures: captures.clone(),
                 body: process,
             },
@@ -1705,6 +1769,77 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         )
     }
 
+    /// Marks the subjects given after `&` as not yet smaller than at the
+    /// `begin`. They're left out of the loop's variables, so that the loop
+    /// only requires them to be of the same types.
+    fn mark_subjects_of_begin(
+        &mut self,
+        span: &Span,
+        subjects: &[LocalName],
+        emit: &mut impl FnMut(TypeError<S>),
+    ) -> Arc<[(LocalName, LoopId)]> {
+        let mut marked = Vec::new();
+        for subject in subjects {
+            let Some(typ) = self.variables.get(subject).cloned() else {
+                emit(TypeError::VariableDoesNotExist(
+                    span.clone(),
+                    subject.clone(),
+                ));
+                continue;
+            };
+            match self.expand_definitions(span, typ, emit) {
+                Type::Recursive {
+                    span: typ_span,
+                    mut asc,
+                    label,
+                    body,
+                    display_hint,
+                } => {
+                    let loop_id = LoopId::new();
+                    asc.insert(loop_id.weak());
+                    self.variables.insert(
+                        subject.clone(),
+                        Type::Recursive {
+                            span: typ_span,
+                            asc,
+                            label,
+                            body,
+                            display_hint,
+                        },
+                    );
+                    marked.push((subject.clone(), loop_id));
+                }
+                Type::Fail(_) => {}
+                typ => emit(TypeError::InvalidOperation(
+                    span.clone(),
+                    Operation::Begin,
+                    typ,
+                )),
+            }
+        }
+        marked.into()
+    }
+
+    /// Goes through the subjects of a `begin` given after `&`, in order, at a
+    /// `loop`. It's `Ok(true)` if one is smaller with the ones before it kept,
+    /// `Ok(false)` if they're all kept, and otherwise the first one that's
+    /// neither.
+    fn smaller_subject(&self, subjects: &[(LocalName, LoopId)]) -> Result<bool, LocalName> {
+        for (subject, loop_id) in subjects {
+            let asc = match self.variables.get(subject) {
+                Some(Type::Recursive { asc, .. }) => asc.clone(),
+                _ => Default::default(),
+            };
+            if asc.contains(&loop_id.strict()) {
+                return Ok(true);
+            }
+            if !asc.contains(&loop_id.weak()) {
+                return Err(subject.clone());
+            }
+        }
+        Ok(false)
+    }
+
     fn check_command_loop(
         &mut self,
         inference_subject: Option<&LocalName>,
@@ -1729,26 +1864,52 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 None,
             );
         }
-        let Some((driver_type, variables)) = self.loop_points.get(label).cloned() else {
+        let Some(point) = self.loop_points.get(label).cloned() else {
             emit(TypeError::NoSuchLoopPoint(span.clone(), label.clone()));
             return (Command::Break, None);
         };
-        if let Err(e) = self.put(span, driver.clone(), typ.clone()) {
-            emit(e);
-        }
+        let LoopPoint {
+            mut driver_type,
+            driver_id,
+            variables,
+            subjects,
+        } = point;
 
         if let (Type::Recursive { asc: asc1, .. }, Type::Recursive { asc: asc2, .. }) =
-            (typ, &driver_type)
+            (typ, &mut driver_type)
         {
-            for loop_id in asc2 {
-                if !asc1.contains(loop_id) {
-                    emit(TypeError::DoesNotDescendSubjectOfBegin(
+            if subjects.is_empty() {
+                for loop_id in asc2.iter() {
+                    if !asc1.contains(loop_id) && !asc1.contains(&loop_id.strict()) {
+                        emit(TypeError::DoesNotDescendSubjectOfBegin(
+                            span.clone(),
+                            loop_id.clone(),
+                        ));
+                    }
+                }
+            } else {
+                let descends = driver_id
+                    .as_ref()
+                    .is_none_or(|loop_id| asc1.contains(loop_id));
+                match self.smaller_subject(&subjects) {
+                    Ok(true) => {}
+                    Ok(false) if descends => {}
+                    Ok(false) => emit(TypeError::DoesNotDescendSubjectsOfBegin(span.clone(), None)),
+                    Err(subject) => emit(TypeError::DoesNotDescendSubjectsOfBegin(
                         span.clone(),
-                        loop_id.clone(),
-                    ));
+                        Some(subject),
+                    )),
+                }
+                // The subjects decide whether it's smaller, so the driver only
+                // has to keep its type.
+                if let Some(loop_id) = &driver_id {
+                    asc2.remove(loop_id);
                 }
             }
         }
+        if let Err(e) = self.put(span, driver.clone(), typ.clone()) {
+            emit(e);
+        }
 
         let mut inferred_loop = None;
 
@@ -2023,7 +2184,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         inference_subject: &LocalName,
         emit: &mut impl FnMut(TypeError<S>),
     ) -> (Arc<Process<Type<S>, S>>, Type<S>) {
-        if let Err(e) = self.put(span, name.clone(), typ.clone()) {
+        if let Err(e) = self.bind(span, name.clone(), typ.clone()) {
             emit(e);
         }
         let (process, subject_type) = self.infer_process(process, inference_subject, emit);
@@ -2179,17 +2340,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             for client in clients {
                 let (typed, typ) = self.infer_expression(Some(inference_subject), client, emit);
                 typed_clients.push(typed);
-                let mut typ = typ;
-                loop {
-                    let next = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
-                        emit(e);
-                        Type::Fail(span.clone())
-                    });
-                    if next == typ {
-                        break;
-                    }
-                    typ = next;
-                }
+                let typ = self.expand_definitions(span, typ, emit);
                 let Type::Recursive { .. } = typ else {
                     emit(TypeError::PollClientMustBeRecursive(span.clone(), typ));
                     continue;
@@ -2228,6 +2379,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 &point_label,
                 &point_body,
                 display_hint.0.as_ref(),
+                self.type_defs.expansion_limit,
             )
             .unwrap_or_else(|e| {
                 emit(e);
@@ -2281,19 +2433,8 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 });
             }
 
-            let mut client_type = client_type.expect("clients is not empty");
-            loop {
-                let next = client_type
-                    .expand_definition(&self.type_defs)
-                    .unwrap_or_else(|e| {
-                        emit(e);
-                        Type::Fail(span.clone())
-                    });
-                if next == client_type {
-                    break;
-                }
-                client_type = next;
-            }
+            let client_type =
+                self.expand_definitions(span, client_type.expect("clients is not empty"), emit);
 
             base = self.clone();
 
@@ -2328,11 +2469,17 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 display_hint: display_hint.clone(),
             };
 
-            name_typ = Type::expand_recursive(&asc, &label, &body, display_hint.0.as_ref())
-                .unwrap_or_else(|e| {
-                    emit(e);
-                    Type::Fail(span.clone())
-                });
+            name_typ = Type::expand_recursive(
+                &asc,
+                &label,
+                &body,
+                display_hint.0.as_ref(),
+                self.type_defs.expansion_limit,
+            )
+            .unwrap_or_else(|e| {
+                emit(e);
+                Type::Fail(span.clone())
+            });
 
             then_ctx = base.clone();
             let prev_poll = then_ctx.poll.take();
@@ -2358,7 +2505,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         if let Err(e) = then_ctx.put(span, driver.clone(), Type::Continue(span.clone())) {
             emit(e);
         }
-        if let Err(e) = then_ctx.put(span, name.clone(), name_typ.clone()) {
+        if let Err(e) = then_ctx.bind(span, name.clone(), name_typ.clone()) {
             emit(e);
         }
         let (typed_then, then_type) = then_ctx.infer_process(then, inference_subject, emit);
@@ -2469,17 +2616,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         let mut typed_values = Vec::with_capacity(values.len());
         for value in values {
             let (typed, typ) = self.infer_expression(Some(inference_subject), value, emit);
-            let mut typ = typ;
-            loop {
-                let next = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
-                    emit(e);
-                    Type::Fail(span.clone())
-                });
-                if next == typ {
-                    break;
-                }
-                typ = next;
-            }
+            let typ = self.expand_definitions(span, typ, emit);
             if !typ
                 .require_assignable_to(&poll_pool_type, &self.type_defs)
                 .unwrap_or(true)
@@ -2767,7 +2904,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 parameter.clone(),
             ));
             let fail = Type::Fail(span.clone());
-            if let Err(e) = self.put(span, parameter.clone(), fail.clone()) {
+            if let Err(e) = self.bind(span, parameter.clone(), fail.clone()) {
                 emit(e);
             }
             let (process, _then_type) = self.infer_process(process, subject, emit);
@@ -2782,7 +2919,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 Type::Fail(span.clone()),
             );
         };
-        if let Err(e) = self.put(span, parameter.clone(), param_type.clone()) {
+        if let Err(e) = self.bind(span, parameter.clone(), param_type.clone()) {
             emit(e);
         }
         let (process, then_type) = self.infer_process(process, subject, emit);
@@ -2887,7 +3024,12 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         captures: &Captures,
         emit: &mut impl FnMut(TypeError<S>),
     ) -> (Command<Type<S>, S>, Type<S>) {
-        let Some((driver_type, variables)) = self.loop_points.get(label).cloned() else {
+        let Some(LoopPoint {
+            driver_type,
+            variables,
+            ..
+        }) = self.loop_points.get(label).cloned()
+        else {
             emit(TypeError::NoSuchLoopPoint(span.clone(), label.clone()));
             return (Command::Break, Type::Fail(span.clone()));
         };
@@ -2944,6 +3086,18 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         expression: &Expression<(), S>,
         target_type: &Type<S>,
         emit: &mut impl FnMut(TypeError<S>),
+    ) -> Arc<Expression<Type<S>, S>> {
+        stack::grow(|| {
+            self.check_expression_inner(inference_subject, expression, target_type, emit)
+        })
+    }
+
+    fn check_expression_inner(
+        &mut self,
+        inference_subject: Option<&LocalName>,
+        expression: &Expression<(), S>,
+        target_type: &Type<S>,
+        emit: &mut impl FnMut(TypeError<S>),
     ) -> Arc<Expression<Type<S>, S>> {
         match expression {
             Expression::Global(span, name, ()) => {
@@ -2994,6 +3148,15 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         inference_subject: Option<&LocalName>,
         expression: &Expression<(), S>,
         emit: &mut impl FnMut(TypeError<S>),
+    ) -> (Arc<Expression<Type<S>, S>>, Type<S>) {
+        stack::grow(|| self.infer_expression_inner(inference_subject, expression, emit))
+    }
+
+    fn infer_expression_inner(
+        &mut self,
+        inference_subject: Option<&LocalName>,
+        expression: &Expression<(), S>,
+        emit: &mut impl FnMut(TypeError<S>),
     ) -> (Arc<Expression<Type<S>, S>>, Type<S>) {
         match expression {
             Expression::Global(span, name, ()) => self.infer_expression_global(span, name, emit),
@@ -3114,7 +3277,13 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             emit(e);
         }
         let mut target_inner_type = target_type.clone();
-        loop {
+        let mut expansions = Vec::new();
+        for step in 0.. {
+            if let Err(e) = self.count_expansion(span, &target_inner_type, step, &mut expansions) {
+                emit(e);
+                target_inner_type = Type::Fail(span.clone());
+                break;
+            }
             match target_inner_type
                 .expand_definition(&self.type_defs)
                 .unwrap_or_else(|e| {
@@ -3129,12 +3298,17 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                     body,
                     display_hint,
                 } => {
-                    target_inner_type =
-                        Type::expand_recursive(&asc, &label, &body, display_hint.0.as_ref())
-                            .unwrap_or_else(|e| {
-                                emit(e);
-                                Type::Fail(span.clone())
-                            });
+                    target_inner_type = Type::expand_recursive(
+                        &asc,
+                        &label,
+                        &body,
+                        display_hint.0.as_ref(),
+                        self.type_defs.expansion_limit,
+                    )
+                    .unwrap_or_else(|e| {
+                        emit(e);
+                        Type::Fail(span.clone())
+                    });
                 }
                 Type::Iterative {
                     span: iter_span,
@@ -3149,6 +3323,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                         &label,
                         &body,
                         display_hint.0.as_ref(),
+                        self.type_defs.expansion_limit,
                     )
                     .unwrap_or_else(|e| {
                         emit(e);
@@ -3197,7 +3372,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         if let Err(e) = self.capture(inference_subject, captures, false, &mut context) {
             emit(e);
         }
-        if let Err(e) = context.put(span, channel.clone(), chan_type.clone()) {
+        if let Err(e) = context.bind(span, channel.clone(), chan_type.clone()) {
             emit(e);
         }
         let process = context.check_process(process, emit);
@@ -3356,7 +3531,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 if let Err(e) = self.type_defs.validate_type(typ) {
                     emit(e);
                 }
-                if let Err(e) = context.put(span, channel.clone(), typ.clone()) {
+                if let Err(e) = context.bind(span, channel.clone(), typ.clone()) {
                     emit(e);
                 }
                 (context.check_process(process, emit), typ.clone())
@@ -3443,6 +3618,10 @@ fn free_type_vars<S>(typ: &Type<S>) -> IndexSet<LocalName> {
                     inner(branch, bound, out);
                 }
             }
+            Type::Union(_, _, left, right) => {
+                inner(left, bound, out);
+                inner(right, bound, out);
+            }
             Type::Recursive { body, .. } | Type::Iterative { body, .. } => {
                 inner(body, bound, out);
             }
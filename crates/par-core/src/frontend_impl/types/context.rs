@@ -1,7 +1,8 @@
+use crate::frontend_impl::corecursion::unguarded_globals;
 use crate::frontend_impl::language::{GlobalName, LocalName, TypeConstraint};
 use crate::frontend_impl::process::{Captures, Expression};
 use crate::frontend_impl::program::DefinitionBody;
-use crate::frontend_impl::types::{Type, TypeDefs, TypeError};
+use crate::frontend_impl::types::{LoopId, Type, TypeDefs, TypeError};
 use crate::location::Span;
 use indexmap::{IndexMap, IndexSet};
 use std::sync::{Arc, RwLock};
@@ -42,13 +43,27 @@ pub(crate) struct Context<S> {
     checked_definitions: Arc<RwLock<IndexMap<GlobalName<S>, CheckedDef<S>>>>,
     current_deps: IndexSet<GlobalName<S>>,
     pub(crate) variables: IndexMap<LocalName, Type<S>>,
-    pub(crate) loop_points:
-        IndexMap<Option<LocalName>, (Type<S>, Arc<IndexMap<LocalName, Type<S>>>)>,
+    /// Where each variable that has been in scope was last bound, kept after
+    /// it's taken out of `variables`, so that it's there when it's put back.
+    pub(crate) binders: IndexMap<LocalName, Span>,
+    pub(crate) loop_points: IndexMap<Option<LocalName>, LoopPoint<S>>,
     pub(crate) poll: Option<PollScope<S>>,
     pub(crate) poll_stash: Vec<Option<PollScope<S>>>,
     pub(crate) blocks: IndexMap<usize, BlockScope<S>>,
 }
 
+/// What a `loop` checks against, from its `begin`.
+#[derive(Clone, Debug)]
+pub(crate) struct LoopPoint<S> {
+    pub(crate) driver_type: Type<S>,
+    /// What marks the parts of the driver, unless the `begin` is `unfounded`.
+    pub(crate) driver_id: Option<LoopId>,
+    /// The variables captured by the loop, with their types at the `begin`.
+    pub(crate) variables: Arc<IndexMap<LocalName, Type<S>>>,
+    /// The subjects given after `&`, in order, with what marks them.
+    pub(crate) subjects: Arc<[(LocalName, LoopId)]>,
+}
+
 #[derive(Clone, Debug)]
 struct CheckedDef<S> {
     span: Span,
@@ -72,6 +87,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             checked_definitions: Arc::new(RwLock::new(IndexMap::new())),
             current_deps: IndexSet::new(),
             variables: IndexMap::new(),
+            binders: IndexMap::new(),
             loop_points: IndexMap::new(),
             poll: None,
             poll_stash: Vec::new(),
@@ -79,6 +95,20 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         }
     }
 
+    /// Takes a definition as checked, with type `typ`, without checking it.
+    pub(crate) fn reuse_checked_definition(
+        &mut self,
+        name: GlobalName<S>,
+        span: Span,
+        def: DefinitionBody<Arc<Expression<Type<S>, S>>>,
+        typ: Type<S>,
+    ) {
+        self.checked_definitions
+            .write()
+            .unwrap()
+            .insert(name, CheckedDef { span, def, typ });
+    }
+
     pub(crate) fn check_definition(
         &mut self,
         span: &Span,
@@ -97,18 +127,20 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         let (span_def, unchecked_def) = def.clone();
 
         if !self.current_deps.insert(name.clone()) {
-            emit(TypeError::DependencyCycle(
-                span.clone(),
-                self.current_deps
-                    .iter()
-                    .cloned()
-                    .skip_while(|dep| dep != name)
-                    .collect(),
-            ));
-            return Type::Fail(span.clone());
+            let cycle: Vec<_> = self
+                .current_deps
+                .iter()
+                .cloned()
+                .skip_while(|dep| dep != name)
+                .collect();
+            return self.check_corecursion(span, &cycle).unwrap_or_else(|e| {
+                emit(e);
+                Type::Fail(span.clone())
+            });
         }
 
         let original_variables = self.variables.drain(..).collect();
+        let original_binders = self.binders.drain(..).collect();
         let original_loop_points = self.loop_points.drain(..).collect();
         let original_poll = self.poll.take();
         let original_poll_stash = std::mem::take(&mut self.poll_stash);
@@ -152,6 +184,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         };
 
         self.variables = original_variables;
+        self.binders = original_binders;
         self.loop_points = original_loop_points;
         self.poll = original_poll;
         self.poll_stash = original_poll_stash;
@@ -165,10 +198,75 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                 typ: checked_type.clone(),
             },
         );
+        self.current_deps.shift_remove(name);
 
         checked_type
     }
 
+    /// The type of a reference at `span` back to the first of the definitions
+    /// in `cycle`, each of which refers to the next, while they're being
+    /// checked. That's its declared type if they're corecursive: each is
+    /// declared to produce an `iterative` value, after any arguments, and only
+    /// refers to the next as what a branch of a `case` on that value continues
+    /// with. For the last one, only the reference at `span` counts, so a
+    /// definition with an unguarded reference gets an error there, and not
+    /// at every other.
+    fn check_corecursion(
+        &self,
+        span: &Span,
+        cycle: &[GlobalName<S>],
+    ) -> Result<Type<S>, TypeError<S>> {
+        let mut declared = Vec::new();
+        for member in cycle {
+            match self.declarations.get(member) {
+                Some((_, typ)) if self.produces_iterative(typ) => declared.push(typ),
+                _ => return Err(TypeError::DependencyCycle(span.clone(), cycle.to_vec())),
+            }
+        }
+        let next = cycle.iter().cycle().skip(1);
+        for (i, (member, next)) in cycle.iter().zip(next).enumerate() {
+            let guarded = match &self.unchecked_definitions[member].1 {
+                DefinitionBody::Par(body) => {
+                    let unguarded = unguarded_globals(body);
+                    if i + 1 == cycle.len() {
+                        !unguarded.contains(&(next.clone(), span.clone()))
+                    } else {
+                        !unguarded.iter().any(|(name, _)| name == next)
+                    }
+                }
+                DefinitionBody::External(_) => false,
+            };
+            if !guarded {
+                return Err(TypeError::UnguardedCorecursion(
+                    span.clone(),
+                    cycle.to_vec(),
+                ));
+            }
+        }
+        Ok(declared[0].clone())
+    }
+
+    /// Whether a value of type `typ` is `iterative`, possibly after receiving
+    /// arguments.
+    fn produces_iterative(&self, typ: &Type<S>) -> bool {
+        let mut typ = typ.clone();
+        for _ in 0..self.type_defs.expansion_limit.depth {
+            typ = match typ {
+                Type::Iterative { .. } => return true,
+                Type::Function(_, _, result, _) => *result,
+                Type::Forall(_, _, body) => *body,
+                Type::Name(..) | Type::DualName(..) | Type::Union(..) => {
+                    match typ.expand_definition(&self.type_defs) {
+                        Ok(expanded) => expanded,
+                        Err(_) => return false,
+                    }
+                }
+                _ => return false,
+            };
+        }
+        false
+    }
+
     pub(crate) fn get_checked_definitions(
         &self,
     ) -> IndexMap<GlobalName<S>, (Span, DefinitionBody<Arc<Expression<Type<S>, S>>>, Type<S>)> {
@@ -205,6 +303,7 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             checked_definitions: self.checked_definitions.clone(),
             current_deps: self.current_deps.clone(),
             variables: IndexMap::new(),
+            binders: self.binders.clone(),
             loop_points: self.loop_points.clone(),
             poll: self.poll.clone(),
             poll_stash: self.poll_stash.clone(),
@@ -212,6 +311,54 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
         }
     }
 
+    /// Expands `typ` until it's no longer a named type.
+    pub(crate) fn expand_definitions(
+        &self,
+        span: &Span,
+        typ: Type<S>,
+        emit: &mut impl FnMut(TypeError<S>),
+    ) -> Type<S> {
+        let mut typ = typ;
+        let mut expansions = Vec::new();
+        for step in 0.. {
+            let next = typ.expand_definition(&self.type_defs).unwrap_or_else(|e| {
+                emit(e);
+                Type::Fail(span.clone())
+            });
+            if next == typ {
+                break;
+            }
+            if let Err(e) = self.count_expansion(span, &typ, step, &mut expansions) {
+                emit(e);
+                return Type::Fail(span.clone());
+            }
+            typ = next;
+        }
+        typ
+    }
+
+    /// Notes the names among the types expanded in a row in `expansions`, as
+    /// `typ` is expanded for the `step`-th time, and fails once there are
+    /// more steps than the expansion limit allows.
+    pub(crate) fn count_expansion(
+        &self,
+        span: &Span,
+        typ: &Type<S>,
+        step: usize,
+        expansions: &mut Vec<GlobalName<S>>,
+    ) -> Result<(), TypeError<S>> {
+        if let Type::Name(_, name, _) | Type::DualName(_, name, _) = typ {
+            expansions.push(name.clone());
+        }
+        if step >= self.type_defs.expansion_limit.depth {
+            return Err(TypeError::TypeExpansionTooDeep(
+                span.clone(),
+                self.type_defs.expansion_chain(expansions),
+            ));
+        }
+        Ok(())
+    }
+
     pub(crate) fn get_global(
         &mut self,
         span: &Span,
@@ -244,13 +391,35 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
     ) -> Result<(), TypeError<S>> {
         if let Some(typ) = self.variables.get(&name) {
             if typ.is_linear(&self.type_defs)? {
-                return Err(TypeError::ShadowedObligation(span.clone(), name));
+                let binder = self.binders.get(&name).cloned().unwrap_or(Span::None);
+                return Err(TypeError::ShadowedObligation(
+                    span.clone(),
+                    binder,
+                    name,
+                    typ.clone(),
+                ));
             }
         }
+        if !self.binders.contains_key(&name) {
+            self.binders.insert(name.clone(), binder_span(span, &name));
+        }
         self.variables.insert(name, typ);
         Ok(())
     }
 
+    /// Like [`Context::put`], but for a new variable, bound by `name`, rather
+    /// than for one put back after a command.
+    pub(crate) fn bind(
+        &mut self,
+        span: &Span,
+        name: LocalName,
+        typ: Type<S>,
+    ) -> Result<(), TypeError<S>> {
+        self.put(span, name.clone(), typ)?;
+        self.binders.insert(name.clone(), binder_span(span, &name));
+        Ok(())
+    }
+
     pub(crate) fn capture(
         &mut self,
         inference_subject: Option<&LocalName>,
@@ -277,6 +446,9 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
                     name.clone(),
                 ));
             }
+            if let Some(binder) = self.binders.get(name) {
+                target.binders.insert(name.clone(), binder.clone());
+            }
             target.put(span, name.clone(), typ)?;
         }
         Ok(())
@@ -296,11 +468,27 @@ impl<S: Clone + Eq + std::hash::Hash> Context<S> {
             }
         }
         if self.obligations().any(|_| true) {
-            return Err(TypeError::UnfulfilledObligations(
-                span.clone(),
-                self.obligations().cloned().collect(),
-            ));
+            let obligations = self
+                .obligations()
+                .map(|name| {
+                    let binder = self.binders.get(name).cloned().unwrap_or(Span::None);
+                    // Named types are expanded, so that what's left to do
+                    // with the variable can be told from the type.
+                    let typ =
+                        self.expand_definitions(span, self.variables[name].clone(), &mut |_| {});
+                    (name.clone(), binder, typ)
+                })
+                .collect();
+            return Err(TypeError::UnfulfilledObligations(span.clone(), obligations));
         }
         Ok(())
     }
 }
+
+/// The span of a binding: the name's own, if it has one.
+fn binder_span(span: &Span, name: &LocalName) -> Span {
+    match name.span {
+        Span::None => span.clone(),
+        _ => name.span.clone(),
+    }
+}
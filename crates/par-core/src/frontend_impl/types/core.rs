@@ -2,6 +2,7 @@ use super::super::language::{GlobalName, LocalName, TypeParameter, Unresolved};
 use crate::frontend_impl::types::visit::Polarity;
 use crate::frontend_impl::types::{TypeDefs, TypeError, visit};
 use crate::location::{Span, Spanning};
+use crate::stack;
 use arcstr::ArcStr;
 use im::HashSet;
 use num_bigint::BigInt;
@@ -13,15 +14,35 @@ use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Marks a recursive type as coming from a subject of a `begin`. It's strict
+/// for a part of the subject, and weak for a subject that may still be whole,
+/// which is what the subjects given after `&` start as. Unrolling a type makes
+/// its marks strict.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct LoopId(u64);
+pub struct LoopId(u64, bool);
 
 static NEXT_LOOP_ID: AtomicU64 = AtomicU64::new(0);
 
 impl LoopId {
     pub fn new() -> Self {
         let id = NEXT_LOOP_ID.fetch_add(1, Ordering::SeqCst);
-        Self(id)
+        Self(id, true)
+    }
+
+    pub fn weak(&self) -> Self {
+        Self(self.0, false)
+    }
+
+    pub fn strict(&self) -> Self {
+        Self(self.0, true)
+    }
+
+    /// Whether a type marked with `asc` comes from each subject `required`
+    /// marks. A part of a subject counts where the subject itself is enough.
+    pub fn all_in(required: &HashSet<LoopId>, asc: &HashSet<LoopId>) -> bool {
+        required
+            .iter()
+            .all(|id| asc.contains(id) || asc.contains(&id.strict()))
     }
 }
 
@@ -36,6 +57,30 @@ pub enum PrimitiveType {
     Bytes,
 }
 
+/// Which kind of types a [`Type::Union`] combines: `either` types with `|`,
+/// or `choice` types with `&`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnionKind {
+    Either,
+    Choice,
+}
+
+impl UnionKind {
+    pub fn dual(self) -> Self {
+        match self {
+            Self::Either => Self::Choice,
+            Self::Choice => Self::Either,
+        }
+    }
+
+    pub fn operator(self) -> &'static str {
+        match self {
+            Self::Either => "|",
+            Self::Choice => "&",
+        }
+    }
+}
+
 #[doc(hidden)]
 #[derive(Clone, Debug)]
 pub struct Ignored<T>(pub(crate) T);
@@ -203,6 +248,12 @@ pub enum Type<S> {
     Function(Span, Box<Self>, Box<Self>, Vec<TypeParameter>),
     Either(Span, BTreeMap<LocalName, Self>),
     Choice(Span, BTreeMap<LocalName, Self>),
+    /// The branches of two `either` types, or of two `choice` types, together.
+    /// Expands like a name, into the combined type. If a type variable is
+    /// among the operands, the rest of the branches aren't known, and it
+    /// expands into an open row: the known branches on the left, and the
+    /// variable on the right. See [`Type::as_open_row`].
+    Union(Span, UnionKind, Box<Self>, Box<Self>),
     Break(Span),
     Continue(Span),
     Recursive {
@@ -300,7 +351,9 @@ impl<S: Clone> Type<S> {
             | Self::DualBox(_, inner)
             | Self::Exists(_, _, inner)
             | Self::Forall(_, _, inner) => current_depth_from_children([inner.current_depth()]),
-            Self::Pair(_, left, right, _) | Self::Function(_, left, right, _) => {
+            Self::Pair(_, left, right, _)
+            | Self::Function(_, left, right, _)
+            | Self::Union(_, _, left, right) => {
                 current_depth_from_children([left.current_depth(), right.current_depth()])
             }
             Self::Either(_, branches) | Self::Choice(_, branches) => {
@@ -338,6 +391,9 @@ impl<S: Clone> Type<S> {
             Self::Either(_, branches) | Self::Choice(_, branches) => flattened_depth_from_branches(
                 branches.values().map(|branch| branch.flattened_depth()),
             ),
+            Self::Union(_, _, left, right) => {
+                flattened_depth_from_branches([left.flattened_depth(), right.flattened_depth()])
+            }
             Self::Recursive { body, .. } | Self::Iterative { body, .. } => body.flattened_depth(),
         }
     }
@@ -567,28 +623,91 @@ impl<S: Clone> Type<S> {
     }
 
     pub fn size(&self, defs: &TypeDefs<S>) -> Result<u32, TypeError<S>>
+    where
+        S: Eq + std::hash::Hash,
+    {
+        self.size_expanding(defs, &mut Vec::new())
+    }
+
+    /// Like [`Type::size`], with the names being expanded on the way here.
+    fn size_expanding(
+        &self,
+        defs: &TypeDefs<S>,
+        expanding: &mut Vec<Self>,
+    ) -> Result<u32, TypeError<S>>
+    where
+        S: Eq + std::hash::Hash,
+    {
+        stack::grow(|| self.size_expanding_inner(defs, expanding))
+    }
+
+    fn size_expanding_inner(
+        &self,
+        defs: &TypeDefs<S>,
+        expanding: &mut Vec<Self>,
+    ) -> Result<u32, TypeError<S>>
     where
         S: Eq + std::hash::Hash,
     {
         Ok(match self {
             Self::Primitive(_, _) | Self::DualPrimitive(_, _) => 1,
             Self::Var(_, _) | Self::DualVar(_, _) => 1,
-            Self::Name(span, name, args) => defs.get(span, name, args)?.size(defs)?,
-            Self::DualName(span, name, args) => defs.get_dual(span, name, args)?.size(defs)?,
-            Self::Box(_, inner) | Self::DualBox(_, inner) => 1 + inner.size(defs)?,
-            Self::Pair(_, left, right, _) => 1 + left.size(defs)? + right.size(defs)?,
-            Self::Function(_, input, output, _) => 1 + input.size(defs)? + output.size(defs)?,
+            // A name met again inside its own expansion refers back among
+            // mutually recursive types, and counts like a `self`.
+            Self::Name(..) | Self::DualName(..) if expanding.contains(self) => 1,
+            Self::Name(span, name, args) => {
+                let typ = defs.get(span, name, args)?;
+                expanding.push(self.clone());
+                let size = typ.size_expanding(defs, expanding);
+                expanding.pop();
+                size?
+            }
+            Self::DualName(span, name, args) => {
+                let typ = defs.get_dual(span, name, args)?;
+                expanding.push(self.clone());
+                let size = typ.size_expanding(defs, expanding);
+                expanding.pop();
+                size?
+            }
+            Self::Box(_, inner) | Self::DualBox(_, inner) => {
+                1 + inner.size_expanding(defs, expanding)?
+            }
+            Self::Pair(_, left, right, _) => {
+                1 + left.size_expanding(defs, expanding)? + right.size_expanding(defs, expanding)?
+            }
+            Self::Function(_, input, output, _) => {
+                1 + input.size_expanding(defs, expanding)?
+                    + output.size_expanding(defs, expanding)?
+            }
+            Self::Union(..) => {
+                let typ = self.expand_definition(defs)?;
+                match typ.as_open_row() {
+                    // The type variable counts as one more.
+                    Some((_, branches, _)) => {
+                        let mut res: u32 = 2;
+                        for branch in branches.values() {
+                            res += branch.size_expanding(defs, expanding)?
+                        }
+                        res
+                    }
+                    None => typ.size_expanding(defs, expanding)?,
+                }
+            }
             Self::Either(_, branches) | Self::Choice(_, branches) => {
                 let mut res: u32 = 1;
                 for branch in branches.values() {
-                    res += branch.size(defs)?
+                    res += branch.size_expanding(defs, expanding)?
                 }
                 res
             }
             Self::Break(_) | Self::Continue(_) => 1,
-            Self::Recursive { body, .. } | Self::Iterative { body, .. } => 1 + body.size(defs)?,
+            Self::Recursive { body, .. } | Self::Iterative { body, .. } => {
+                1 + body.size_expanding(defs, expanding)?
+            }
             Self::Self_(_, _) | Self::DualSelf(_, _) => 1,
-            Self::Exists(_, _, body) | Self::Forall(_, _, body) => 1 + body.size(defs)?,
+            Self::Exists(_, _, body) | Self::Forall(_, _, body) => {
+                1 + body.size_expanding(defs, expanding)?
+            }
             Self::Hole(_, _, _) | Self::DualHole(_, _, _) => 1,
             Self::Fail(_) => 1,
         })
@@ -663,6 +782,12 @@ impl<S: Clone> Type<S> {
                     .collect::<Result<BTreeMap<_, _>, E>>()?;
                 Type::Choice(span, mapped)
             }
+            Self::Union(span, kind, left, right) => Type::Union(
+                span,
+                kind,
+                Box::new(left.map_global_names(f)?),
+                Box::new(right.map_global_names(f)?),
+            ),
             Self::Recursive {
                 span,
                 asc,
@@ -741,6 +866,7 @@ impl<S> Spanning for Type<S> {
             | Self::Function(span, _, _, _)
             | Self::Either(span, _)
             | Self::Choice(span, _)
+            | Self::Union(span, _, _, _)
             | Self::Break(span)
             | Self::Continue(span)
             | Self::Recursive { span, .. }
@@ -757,6 +883,32 @@ impl<S> Spanning for Type<S> {
 }
 
 impl<S> Type<S> {
+    /// The kind, the known branches, and the type variable standing for the
+    /// rest of the branches, if this is an expanded open row, like
+    /// `either { .a A } | r`.
+    pub(crate) fn as_open_row(&self) -> Option<(UnionKind, &BTreeMap<LocalName, Self>, &Self)> {
+        let Self::Union(_, kind, known, rest) = self else {
+            return None;
+        };
+        match (kind, known.as_ref(), rest.as_ref()) {
+            (UnionKind::Either, Self::Either(_, branches), Self::Var(..) | Self::DualVar(..))
+            | (UnionKind::Choice, Self::Choice(_, branches), Self::Var(..) | Self::DualVar(..)) => {
+                Some((*kind, branches, rest))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether two type variables standing for the rest of open rows are the
+    /// same one.
+    pub(crate) fn is_same_row_variable(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Var(_, name1), Self::Var(_, name2))
+            | (Self::DualVar(_, name1), Self::DualVar(_, name2)) => name1 == name2,
+            _ => false,
+        }
+    }
+
     pub(crate) fn display_hint(&self) -> Option<&NamedTypeDisplay<S>> {
         match self {
             Self::Recursive { display_hint, .. } | Self::Iterative { display_hint, .. } => {
@@ -1,14 +1,41 @@
 use crate::frontend_impl::language::{GlobalName, LocalName, TypeConstraint, TypeParameter};
 use crate::frontend_impl::types::core::NamedTypeDisplay;
-use crate::frontend_impl::types::{Type, TypeError, visit};
-use crate::location::Span;
+use crate::frontend_impl::types::{Type, TypeError, TypeFunction, UnionKind, visit};
+use crate::location::{Span, Spanning};
 use indexmap::{IndexMap, IndexSet};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+/// How far types may be expanded before the expansion is reported as runaway,
+/// so that a type that keeps unfolding is reported instead of hanging the check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpansionLimit {
+    /// How many named types may be expanded in a row, or inside one another.
+    pub depth: usize,
+    /// How many nodes unfolding a `recursive` or `iterative` may produce.
+    pub size: usize,
+}
+
+impl Default for ExpansionLimit {
+    fn default() -> Self {
+        Self {
+            depth: 128,
+            size: 100_000,
+        }
+    }
+}
+
+/// The branches of one side of a union, and the type variable for the rest.
+type UnionRow<S> = (BTreeMap<LocalName, Type<S>>, Option<Type<S>>);
+
 #[derive(Clone, Debug)]
 pub struct TypeDefs<S> {
     pub globals: Arc<IndexMap<GlobalName<S>, (Span, Vec<TypeParameter>, Type<S>)>>,
     pub vars: IndexMap<LocalName, TypeConstraint>,
+    /// The definitions among `globals` that are built into the compiler, and
+    /// computed from their arguments, instead of substituted into their body.
+    pub functions: Arc<IndexMap<GlobalName<S>, TypeFunction>>,
+    pub expansion_limit: ExpansionLimit,
 }
 
 impl<S: Clone + Eq + std::hash::Hash> Default for TypeDefs<S> {
@@ -16,6 +43,8 @@ impl<S: Clone + Eq + std::hash::Hash> Default for TypeDefs<S> {
         Self {
             globals: Default::default(),
             vars: Default::default(),
+            functions: Default::default(),
+            expansion_limit: Default::default(),
         }
     }
 }
@@ -31,6 +60,27 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
             ),
         >,
     ) -> (Self, IndexSet<TypeError<S>>)
+    where
+        S: 'a,
+    {
+        Self::new_with_functions_and_validation(globals, IndexMap::new(), ExpansionLimit::default())
+    }
+
+    /// Like [`TypeDefs::new_with_validation`], with some of the `globals`
+    /// being [type functions](TypeFunction), computed from their arguments,
+    /// and types expanded only as far as `expansion_limit`.
+    pub fn new_with_functions_and_validation<'a>(
+        globals: impl Iterator<
+            Item = (
+                &'a Span,
+                &'a GlobalName<S>,
+                &'a Vec<TypeParameter>,
+                &'a Type<S>,
+            ),
+        >,
+        functions: IndexMap<GlobalName<S>, TypeFunction>,
+        expansion_limit: ExpansionLimit,
+    ) -> (Self, IndexSet<TypeError<S>>)
     where
         S: 'a,
     {
@@ -51,6 +101,8 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
         let type_defs = Self {
             globals: Arc::new(globals_map),
             vars: Default::default(),
+            functions: Arc::new(functions),
+            expansion_limit,
         };
 
         let mut deps_map: IndexMap<GlobalName<S>, Vec<GlobalName<S>>> = Default::default();
@@ -58,13 +110,49 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
             deps_map.insert(name.clone(), typ.get_dependencies());
         }
 
+        // Named types may refer to one another the way a fixpoint refers to
+        // itself with `self`. In a group whose references among each other are
+        // all positive, and applied to type variables only, the ones that are
+        // also guarded are well-founded, and only a cycle of the rest is a
+        // definition in terms of itself.
+        for group in recursive_groups(&deps_map) {
+            let references: Vec<_> = group
+                .iter()
+                .map(|name| {
+                    let (_, _, typ) = &type_defs.globals[name];
+                    (name, typ.get_dependency_references(&type_defs))
+                })
+                .collect();
+            let well_founded = references
+                .iter()
+                .flat_map(|(_, deps)| deps)
+                .filter(|dep| group.contains(&dep.name))
+                .all(|dep| dep.positive && dep.applied_to_vars);
+            if !well_founded {
+                continue;
+            }
+            for (name, deps) in references {
+                deps_map[name] = deps
+                    .into_iter()
+                    .filter(|dep| !(dep.guarded && group.contains(&dep.name)))
+                    .map(|dep| dep.name)
+                    .collect();
+            }
+        }
+
+        let mut cyclic = IndexSet::new();
         for (name, _) in type_defs.globals.iter() {
             if let Err(e) = type_defs.validate_acyclic(name, &Default::default(), &deps_map) {
                 errors.insert(e);
+                cyclic.insert(name.clone());
             }
         }
 
-        for (_, (_, params, typ)) in type_defs.globals.iter() {
+        for (name, (_, params, typ)) in type_defs.globals.iter() {
+            // Expanding a cyclic definition never terminates; the cycle is already reported.
+            if cyclic.contains(name) {
+                continue;
+            }
             let mut type_defs = type_defs.clone();
             type_defs.extend_vars(params.iter().cloned());
             if let Err(e) = type_defs.validate_type(typ) {
@@ -75,6 +163,25 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
         (type_defs, errors)
     }
 
+    /// Warnings for the parameters of type definitions that their bodies never
+    /// use. Such a parameter is often a typo: a name in the body meant to
+    /// refer to it, but spelled differently, refers to a global type instead.
+    pub fn unused_type_parameters(&self) -> Vec<TypeError<S>> {
+        let mut warnings = Vec::new();
+        for (name, (_, params, typ)) in self.globals.iter() {
+            for param in params {
+                if !typ.contains_var(&param.name) {
+                    warnings.push(TypeError::UnusedTypeParameter(
+                        param.name.span.clone(),
+                        name.clone(),
+                        param.name.clone(),
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
     pub fn get(
         &self,
         span: &Span,
@@ -90,24 +197,11 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
         name: &GlobalName<S>,
         args: &[Type<S>],
     ) -> Result<(&Span, Type<S>), TypeError<S>> {
-        match self.globals.get(name) {
-            Some((definition_span, params, typ)) => {
-                if params.len() != args.len() {
-                    return Err(TypeError::WrongNumberOfTypeArgs(
-                        span.clone(),
-                        name.clone(),
-                        params.len(),
-                        args.len(),
-                    ));
-                }
-                let typ = typ
-                    .clone()
-                    .substitute(params.iter().map(|param| &param.name).zip(args).collect())?
-                    .with_display_hint(NamedTypeDisplay::new(name.clone(), args.to_vec(), false));
-                Ok((definition_span, typ))
-            }
-            None => Err(TypeError::TypeNameNotDefined(span.clone(), name.clone())),
-        }
+        let (definition_span, typ) = self.instantiate(span, name, args, false)?;
+        let typ = self
+            .resolve_dual_alias(name, typ)
+            .with_display_hint(NamedTypeDisplay::new(name.clone(), args.to_vec(), false));
+        Ok((definition_span, typ))
     }
 
     pub fn get_dual(
@@ -126,25 +220,151 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
         name: &GlobalName<S>,
         args: &[Type<S>],
     ) -> Result<(&Span, Type<S>), TypeError<S>> {
-        match self.globals.get(name) {
-            Some((definition_span, params, typ)) => {
-                if params.len() != args.len() {
-                    return Err(TypeError::WrongNumberOfTypeArgs(
-                        span.clone(),
-                        name.clone(),
-                        params.len(),
-                        args.len(),
-                    ));
+        let (definition_span, typ) = self.instantiate(span, name, args, true)?;
+        let typ = self
+            .resolve_dual_alias(name, typ)
+            .with_display_hint(NamedTypeDisplay::new(name.clone(), args.to_vec(), true));
+        Ok((definition_span, typ))
+    }
+
+    /// The body of a definition with the arguments substituted, or its dual.
+    fn instantiate(
+        &self,
+        span: &Span,
+        name: &GlobalName<S>,
+        args: &[Type<S>],
+        dual: bool,
+    ) -> Result<(&Span, Type<S>), TypeError<S>> {
+        let Some((definition_span, params, typ)) = self.globals.get(name) else {
+            return Err(TypeError::TypeNameNotDefined(span.clone(), name.clone()));
+        };
+        if params.len() != args.len() {
+            return Err(TypeError::WrongNumberOfTypeArgs(
+                span.clone(),
+                name.clone(),
+                params.len(),
+                args.len(),
+            ));
+        }
+        if let Some(&function) = self.functions.get(name) {
+            let typ = self.apply_function(span, function, name, args)?;
+            let typ = if dual { typ.dual(Span::None) } else { typ };
+            return Ok((definition_span, typ));
+        }
+        let typ = if dual {
+            typ.clone().dual(Span::None)
+        } else {
+            typ.clone()
+        };
+        let typ = typ.substitute(params.iter().map(|param| &param.name).zip(args).collect())?;
+        Ok((definition_span, typ))
+    }
+
+    /// A definition of the form `type Client = dual Server` is resolved through
+    /// to the dual of what `Server` stands for, so that the fixpoint it ends
+    /// up as is displayed as `Client`, rather than as `dual Server`. Names
+    /// that don't resolve, or resolve for too long, are left as they are for
+    /// the rest of the checker to report.
+    fn resolve_dual_alias(&self, name: &GlobalName<S>, mut typ: Type<S>) -> Type<S> {
+        if !matches!(self.globals[name].2, Type::DualName(..)) {
+            return typ;
+        }
+        for _ in 0..self.expansion_limit.depth {
+            let resolved = match &typ {
+                Type::Name(span, alias, args) => self.instantiate(span, alias, args, false),
+                Type::DualName(span, alias, args) => self.instantiate(span, alias, args, true),
+                _ => break,
+            };
+            match resolved {
+                Ok((_, resolved)) => typ = resolved,
+                Err(_) => break,
+            }
+        }
+        typ
+    }
+
+    /// The branches of `left` and `right` together, in an `either` or a
+    /// `choice`, depending on `kind`. Both must expand to that kind of type,
+    /// and no label may be on both sides. One of the sides may also be a
+    /// type variable, standing for the rest of the branches, and then the
+    /// result is an open row: see [`Type::as_open_row`].
+    pub fn union(
+        &self,
+        span: &Span,
+        kind: UnionKind,
+        left: &Type<S>,
+        right: &Type<S>,
+    ) -> Result<Type<S>, TypeError<S>> {
+        let (mut branches, mut rest) = self.union_branches(kind, left)?;
+        let (right_branches, right_rest) = self.union_branches(kind, right)?;
+        for (label, typ) in right_branches {
+            if let Some((existing, _)) = branches.get_key_value(&label) {
+                return Err(TypeError::OverlappingUnionLabels(
+                    span.clone(),
+                    existing.clone(),
+                    label,
+                ));
+            }
+            branches.insert(label, typ);
+        }
+        match (&rest, right_rest) {
+            (Some(rest), Some(right_rest)) => {
+                return Err(TypeError::UnionOfTwoTypeVariables(
+                    span.clone(),
+                    rest.clone(),
+                    right_rest,
+                ));
+            }
+            (None, right_rest) => rest = right_rest,
+            (Some(_), None) => {}
+        }
+        let known = match kind {
+            UnionKind::Either => Type::Either(span.clone(), branches),
+            UnionKind::Choice => Type::Choice(span.clone(), branches),
+        };
+        Ok(match rest {
+            Some(rest) => Type::Union(span.clone(), kind, Box::new(known), Box::new(rest)),
+            None => known,
+        })
+    }
+
+    /// The branches of one side of a union, and the type variable standing
+    /// for the rest of them, if it has one. A side that has already failed
+    /// contributes none, so that the failure isn't reported again.
+    fn union_branches(
+        &self,
+        kind: UnionKind,
+        operand: &Type<S>,
+    ) -> Result<UnionRow<S>, TypeError<S>> {
+        let mut typ = operand.clone();
+        for _ in 0..self.expansion_limit.depth {
+            if let Some((row_kind, branches, rest)) = typ.as_open_row() {
+                if row_kind != kind {
+                    return Err(TypeError::InvalidUnionOperand(operand.span(), kind, typ));
                 }
-                let typ = typ
-                    .clone()
-                    .dual(Span::None)
-                    .substitute(params.iter().map(|param| &param.name).zip(args).collect())?
-                    .with_display_hint(NamedTypeDisplay::new(name.clone(), args.to_vec(), true));
-                Ok((definition_span, typ))
+                return Ok((branches.clone(), Some(rest.clone())));
             }
-            None => Err(TypeError::TypeNameNotDefined(span.clone(), name.clone())),
+            typ = match (kind, typ) {
+                (UnionKind::Either, Type::Either(_, branches))
+                | (UnionKind::Choice, Type::Choice(_, branches)) => return Ok((branches, None)),
+                (_, typ @ (Type::Var(..) | Type::DualVar(..))) => {
+                    return Ok((BTreeMap::new(), Some(typ)));
+                }
+                (_, Type::Fail(_)) => return Ok((BTreeMap::new(), None)),
+                (_, typ @ (Type::Name(..) | Type::DualName(..))) => typ.expand_definition(self)?,
+                (_, Type::Union(span, kind, left, right)) => {
+                    self.union(&span, kind, &left, &right)?
+                }
+                (_, typ) => {
+                    return Err(TypeError::InvalidUnionOperand(operand.span(), kind, typ));
+                }
+            };
         }
+        Err(TypeError::InvalidUnionOperand(
+            operand.span(),
+            kind,
+            operand.clone(),
+        ))
     }
 
     pub fn insert_var(&mut self, param: TypeParameter) {
@@ -195,17 +415,76 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
         struct Ctx<S> {
             defs: TypeDefs<S>,
             check_self: bool,
-            self_polarity: IndexMap<Option<LocalName>, bool>,
+            /// For each label in scope, whether its `recursive` or `iterative` is
+            /// positive, where it is, and how many `flips` there were above it.
+            self_polarity: IndexMap<Option<LocalName>, (bool, Span, usize)>,
             unguarded_self_rec: IndexSet<Option<LocalName>>,
             unguarded_self_iter: IndexSet<Option<LocalName>>,
+            expansions: Vec<GlobalName<S>>,
+            /// How many of the `expansions` came before the innermost
+            /// `recursive` or `iterative`.
+            fixpoint_depth: usize,
+            /// The places on the way here where the polarity flipped.
+            flips: Vec<(Span, &'static str)>,
+        }
+        impl<S: Clone> Ctx<S> {
+            fn enter(
+                &self,
+                parent: &Type<S>,
+                child: &Type<S>,
+                positive: bool,
+                child_positive: bool,
+            ) -> Self {
+                let mut ctx = self.clone();
+                if positive != child_positive {
+                    ctx.flips.push(match parent {
+                        Type::Function(..) => (child.span(), "function argument"),
+                        Type::DualBox(span, _) => (span.clone(), "`dual box`"),
+                        _ => (parent.span(), "dual type"),
+                    });
+                }
+                ctx
+            }
+            fn self_used_in_negative_position(
+                &self,
+                span: &Span,
+                label: &Option<LocalName>,
+            ) -> TypeError<S> {
+                let (_, binder, depth) = &self.self_polarity[label];
+                TypeError::SelfUsedInNegativePosition(
+                    span.clone(),
+                    binder.clone(),
+                    self.flips[*depth..].to_vec(),
+                )
+            }
+        }
+        fn descend<S: Clone + Eq + std::hash::Hash>(
+            typ: &Type<S>,
+            positive: bool,
+            ctx: &Ctx<S>,
+        ) -> Result<(), TypeError<S>> {
+            visit::continue_deref_polarized(typ, positive, &ctx.defs, |child, child_positive| {
+                inner(
+                    child,
+                    child_positive,
+                    ctx.enter(typ, child, positive, child_positive),
+                )
+            })
         }
         fn inner<S: Clone + Eq + std::hash::Hash>(
             typ: &Type<S>,
             positive: bool,
             mut ctx: Ctx<S>,
         ) -> Result<(), TypeError<S>> {
+            if typ.is_fixpoint() {
+                ctx.fixpoint_depth = ctx.expansions.len();
+            }
             match typ {
-                Type::Name(_span, _name, args) | Type::DualName(_span, _name, args) => {
+                // A name met again behind a fixpoint is a reference back among
+                // mutually recursive types, already validated as a group.
+                Type::Name(_, name, _) | Type::DualName(_, name, _)
+                    if ctx.expansions[..ctx.fixpoint_depth].contains(name) => {}
+                Type::Name(span, name, args) | Type::DualName(span, name, args) => {
                     for arg in args {
                         inner(
                             arg,
@@ -216,12 +495,20 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                                 self_polarity: IndexMap::new(),
                                 unguarded_self_rec: IndexSet::new(),
                                 unguarded_self_iter: IndexSet::new(),
+                                expansions: ctx.expansions.clone(),
+                                fixpoint_depth: ctx.fixpoint_depth,
+                                flips: Vec::new(),
                             },
                         )?;
                     }
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    ctx.expansions.push(name.clone());
+                    if ctx.expansions.len() > ctx.defs.expansion_limit.depth {
+                        return Err(TypeError::TypeExpansionTooDeep(
+                            span.clone(),
+                            ctx.defs.expansion_chain(&ctx.expansions),
+                        ));
+                    }
+                    descend(typ, positive, &ctx)?;
                 }
                 Type::Function(span, arg, res, vars) if !vars.is_empty() => {
                     ctx.defs.extend_vars(vars.iter().cloned());
@@ -245,9 +532,11 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                 }
                 Type::Exists(_span, param, _body) | Type::Forall(_span, param, _body) => {
                     ctx.defs.insert_var(param.clone());
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    descend(typ, positive, &ctx)?;
+                }
+                Type::Union(..) => {
+                    descend(typ, positive, &ctx)?;
+                    typ.expand_definition(&ctx.defs)?;
                 }
                 Type::Var(span, name) | Type::DualVar(span, name) => {
                     if ctx.defs.contains_var(name) {
@@ -259,38 +548,32 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                         ));
                     }
                 }
-                Type::Recursive { label, .. } if ctx.check_self => {
+                Type::Recursive { span, label, .. } if ctx.check_self => {
                     ctx.unguarded_self_rec.insert(label.clone());
                     ctx.unguarded_self_iter.shift_remove(label);
-                    ctx.self_polarity.insert(label.clone(), positive);
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    ctx.self_polarity
+                        .insert(label.clone(), (positive, span.clone(), ctx.flips.len()));
+                    descend(typ, positive, &ctx)?;
                 }
-                Type::Iterative { label, .. } if ctx.check_self => {
+                Type::Iterative { span, label, .. } if ctx.check_self => {
                     ctx.unguarded_self_iter.insert(label.clone());
                     ctx.unguarded_self_rec.shift_remove(label);
-                    ctx.self_polarity.insert(label.clone(), positive);
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    ctx.self_polarity
+                        .insert(label.clone(), (positive, span.clone(), ctx.flips.len()));
+                    descend(typ, positive, &ctx)?;
                 }
                 Type::Either(..) if ctx.check_self => {
                     ctx.unguarded_self_rec = IndexSet::new();
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    descend(typ, positive, &ctx)?;
                 }
                 Type::Choice(..) if ctx.check_self => {
                     ctx.unguarded_self_iter = IndexSet::new();
-                    visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                        inner(typ, positive, ctx.clone())
-                    })?;
+                    descend(typ, positive, &ctx)?;
                 }
                 Type::Self_(span, label) if ctx.check_self => {
-                    if let Some(is_positive) = ctx.self_polarity.get(label) {
+                    if let Some((is_positive, _, _)) = ctx.self_polarity.get(label) {
                         if *is_positive != positive {
-                            return Err(TypeError::SelfUsedInNegativePosition(span.clone()));
+                            return Err(ctx.self_used_in_negative_position(span, label));
                         }
                     } else {
                         return Err(TypeError::NoMatchingRecursiveOrIterative(span.clone()));
@@ -303,9 +586,10 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                     }
                 }
                 Type::DualSelf(span, label) if ctx.check_self => {
-                    if let Some(is_positive) = ctx.self_polarity.get(label) {
+                    if let Some((is_positive, _, _)) = ctx.self_polarity.get(label) {
                         if *is_positive == positive {
-                            return Err(TypeError::SelfUsedInNegativePosition(span.clone()));
+                            ctx.flips.push((span.clone(), "`dual self`"));
+                            return Err(ctx.self_used_in_negative_position(span, label));
                         }
                     } else {
                         return Err(TypeError::NoMatchingRecursiveOrIterative(span.clone()));
@@ -317,9 +601,7 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                         return Err(TypeError::UnguardedIterativeSelf(span.clone()));
                     }
                 }
-                _ => visit::continue_deref_polarized(typ, positive, &ctx.defs, |typ, positive| {
-                    inner(typ, positive, ctx.clone())
-                })?,
+                _ => descend(typ, positive, &ctx)?,
             }
             Ok(())
         }
@@ -332,7 +614,67 @@ impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
                 self_polarity: IndexMap::new(),
                 unguarded_self_rec: IndexSet::new(),
                 unguarded_self_iter: IndexSet::new(),
+                expansions: Vec::new(),
+                fixpoint_depth: 0,
+                flips: Vec::new(),
             },
         )
     }
+
+    /// The definitions along a runaway expansion, cut after the first name that
+    /// repeats, so that a loop through aliases is shown exactly once.
+    pub(crate) fn expansion_chain(
+        &self,
+        expansions: &[GlobalName<S>],
+    ) -> Vec<(GlobalName<S>, Span)> {
+        let mut seen = IndexSet::new();
+        let mut chain = Vec::new();
+        for name in expansions {
+            let span = self
+                .globals
+                .get(name)
+                .map_or(Span::None, |(span, _, _)| span.clone());
+            chain.push((name.clone(), span));
+            if !seen.insert(name) {
+                break;
+            }
+        }
+        chain
+    }
+}
+
+/// The groups of named types that depend on each other, directly or not,
+/// including a single type that depends on itself.
+fn recursive_groups<S: Clone + Eq + std::hash::Hash>(
+    deps_map: &IndexMap<GlobalName<S>, Vec<GlobalName<S>>>,
+) -> Vec<IndexSet<GlobalName<S>>> {
+    let reachable = |from: &GlobalName<S>| {
+        let mut seen = IndexSet::new();
+        let mut stack: Vec<_> = deps_map.get(from).into_iter().flatten().collect();
+        while let Some(name) = stack.pop() {
+            if seen.insert(name.clone()) {
+                stack.extend(deps_map.get(name).into_iter().flatten());
+            }
+        }
+        seen
+    };
+    let reachability: IndexMap<_, _> = deps_map
+        .keys()
+        .map(|name| (name, reachable(name)))
+        .collect();
+
+    let mut groups: Vec<IndexSet<GlobalName<S>>> = Vec::new();
+    for (name, reached) in &reachability {
+        if !reached.contains(*name) || groups.iter().any(|group| group.contains(*name)) {
+            continue;
+        }
+        groups.push(
+            reached
+                .iter()
+                .filter(|other| reachability.get(other).is_some_and(|r| r.contains(*name)))
+                .cloned()
+                .collect(),
+        );
+    }
+    groups
 }
@@ -1,6 +1,22 @@
 use super::super::language::GlobalName;
 use super::core::Type;
-use crate::frontend_impl::types::visit;
+use super::definitions::TypeDefs;
+use crate::frontend_impl::types::visit::{self, Polarity};
+
+/// A reference from a type to a named type, with what decides whether named
+/// types referring to one another are well-founded.
+#[derive(Clone, Debug)]
+pub(crate) struct Dependency<S> {
+    pub(crate) name: GlobalName<S>,
+    /// Inside a `recursive` and then an `either`, or an `iterative` and then a
+    /// `choice`, like a `self` has to be.
+    pub(crate) guarded: bool,
+    /// Under an even number of `dual`s and function arguments.
+    pub(crate) positive: bool,
+    /// Applied to type variables only, so that expanding it over and over
+    /// keeps coming back to the same few types.
+    pub(crate) applied_to_vars: bool,
+}
 
 impl<S: Clone> Type<S> {
     pub fn get_dependencies(&self) -> Vec<GlobalName<S>> {
@@ -23,3 +39,96 @@ impl<S: Clone> Type<S> {
         res
     }
 }
+
+impl<S: Clone + Eq + std::hash::Hash> Type<S> {
+    /// Every reference to a named type, in the order of
+    /// [`Type::get_dependencies`], with where it is.
+    pub(crate) fn get_dependency_references(&self, defs: &TypeDefs<S>) -> Vec<Dependency<S>> {
+        #[derive(Clone, Copy)]
+        struct Position {
+            polarity: Polarity,
+            in_recursive: bool,
+            in_iterative: bool,
+            guarded: bool,
+        }
+        fn inner<S: Clone + Eq + std::hash::Hash>(
+            typ: &Type<S>,
+            mut pos: Position,
+            defs: &TypeDefs<S>,
+            deps: &mut Vec<Dependency<S>>,
+        ) -> Result<(), ()> {
+            match typ {
+                Type::Name(span, name, args) | Type::DualName(span, name, args) => {
+                    let polarity = match typ {
+                        Type::DualName(..) => pos.polarity.dual(),
+                        _ => pos.polarity,
+                    };
+                    deps.push(Dependency {
+                        name: name.clone(),
+                        guarded: pos.guarded,
+                        positive: matches!(polarity, Polarity::Positive),
+                        applied_to_vars: args.iter().all(|arg| matches!(arg, Type::Var(..))),
+                    });
+                    // Arguments of a type that doesn't resolve could be anywhere.
+                    let arg_polarities = visit::get_args_polarity(span, name, defs)
+                        .unwrap_or_else(|_| vec![Polarity::Both; args.len()]);
+                    for (arg, arg_polarity) in args.iter().zip(arg_polarities) {
+                        inner(
+                            arg,
+                            Position {
+                                polarity: polarity.xor(arg_polarity),
+                                ..pos
+                            },
+                            defs,
+                            deps,
+                        )?;
+                    }
+                    return Ok(());
+                }
+                Type::Recursive { .. } => pos.in_recursive = true,
+                Type::Iterative { .. } => pos.in_iterative = true,
+                Type::Either(..) if pos.in_recursive => pos.guarded = true,
+                Type::Choice(..) if pos.in_iterative => pos.guarded = true,
+                _ => {}
+            }
+            match typ {
+                Type::DualBox(_, inner_type) => inner(
+                    inner_type,
+                    Position {
+                        polarity: pos.polarity.dual(),
+                        ..pos
+                    },
+                    defs,
+                    deps,
+                ),
+                Type::Function(_, left, right, _) => {
+                    inner(
+                        left,
+                        Position {
+                            polarity: pos.polarity.dual(),
+                            ..pos
+                        },
+                        defs,
+                        deps,
+                    )?;
+                    inner(right, pos, defs, deps)
+                }
+                _ => visit::continue_(typ, |child| inner(child, pos, defs, deps)),
+            }
+        }
+        let mut res = vec![];
+        inner(
+            self,
+            Position {
+                polarity: Polarity::Positive,
+                in_recursive: false,
+                in_iterative: false,
+                guarded: false,
+            },
+            defs,
+            &mut res,
+        )
+        .unwrap();
+        res
+    }
+}
@@ -7,16 +7,63 @@ use crate::location::{Span, Spanning};
 use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub trait GlobalNameWriter<S> {
     fn write_global_name<W: Write>(&self, f: &mut W, name: &GlobalName<S>) -> fmt::Result;
 }
 
+/// How much of a type to show. Types in error reports can be huge, especially
+/// unrolled recursive ones, so they're cut short where they get too deep or too
+/// wide, with `...` in place of what's left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeTruncation {
+    /// How deeply branches, arguments, and payloads may nest.
+    pub max_depth: Option<usize>,
+    /// How many branches of an `either` or a `choice`, or arguments of a type,
+    /// are shown.
+    pub max_width: Option<usize>,
+    /// Whether a `recursive` or `iterative` type shows its body only the first
+    /// time, and `...` after that.
+    pub elide_repeated_recursion: bool,
+}
+
+impl TypeTruncation {
+    pub const FULL: Self = Self {
+        max_depth: None,
+        max_width: None,
+        elide_repeated_recursion: false,
+    };
+
+    pub const REPORTS: Self = Self {
+        max_depth: Some(6),
+        max_width: Some(12),
+        elide_repeated_recursion: true,
+    };
+}
+
+static FULL_TYPES_IN_REPORTS: AtomicBool = AtomicBool::new(false);
+
+/// Show types in error reports in full, instead of truncated.
+pub fn set_full_types_in_reports(full: bool) {
+    FULL_TYPES_IN_REPORTS.store(full, Ordering::Relaxed);
+}
+
+pub(crate) fn report_truncation() -> TypeTruncation {
+    if FULL_TYPES_IN_REPORTS.load(Ordering::Relaxed) {
+        TypeTruncation::FULL
+    } else {
+        TypeTruncation::REPORTS
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct TypeRenderOptions {
     indent: usize,
     compact: bool,
     prefer_display_hints: bool,
+    truncation: TypeTruncation,
+    depth: usize,
 }
 
 impl TypeRenderOptions {
@@ -25,6 +72,8 @@ impl TypeRenderOptions {
             indent,
             compact: false,
             prefer_display_hints: true,
+            truncation: TypeTruncation::FULL,
+            depth: 0,
         }
     }
 
@@ -33,6 +82,8 @@ impl TypeRenderOptions {
             indent: 0,
             compact: true,
             prefer_display_hints: true,
+            truncation: TypeTruncation::FULL,
+            depth: 0,
         }
     }
 
@@ -43,6 +94,10 @@ impl TypeRenderOptions {
         }
     }
 
+    pub(crate) const fn with_truncation(self, truncation: TypeTruncation) -> Self {
+        Self { truncation, ..self }
+    }
+
     fn next_indent(self) -> Self {
         Self {
             indent: self.indent + 1,
@@ -50,6 +105,25 @@ impl TypeRenderOptions {
         }
     }
 
+    fn deeper(self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+
+    fn too_deep(self) -> bool {
+        self.truncation
+            .max_depth
+            .is_some_and(|max_depth| self.depth > max_depth)
+    }
+
+    fn shown_width(self, width: usize) -> usize {
+        self.truncation
+            .max_width
+            .map_or(width, |max| width.min(max))
+    }
+
     fn write_indentation(self, f: &mut impl Write) -> fmt::Result {
         if !self.compact {
             write!(f, "\n")?;
@@ -85,7 +159,7 @@ impl<S: Clone> Type<S> {
         names: &N,
         options: TypeRenderOptions,
     ) -> fmt::Result {
-        write_type_with_options(f, names, self, options)
+        write_type_with_options(f, names, &mut Vec::new(), self, options)
     }
 
     pub fn types_at_spans(
@@ -109,7 +183,7 @@ impl<S: Clone> Type<S> {
                         name.clone(),
                         args.clone(),
                         typ,
-                        docs.type_doc(name).cloned(),
+                        docs.type_doc_with_branches(name),
                         def_span.clone(),
                     ),
                 );
@@ -133,7 +207,7 @@ impl<S: Clone> Type<S> {
                         name.clone(),
                         args.clone(),
                         typ,
-                        docs.type_doc(name).cloned(),
+                        docs.type_doc_with_branches(name),
                         def_span.clone(),
                     ),
                 );
@@ -171,6 +245,13 @@ impl<S: Clone> Type<S> {
                     t.types_at_spans(type_defs, docs, consume);
                 }
             }
+            Self::Union(span, _, left, right) => {
+                if let Ok(typ) = self.expand_definition(type_defs) {
+                    consume(span.clone(), HoverInfo::unnamed(typ));
+                }
+                left.types_at_spans(type_defs, docs, consume);
+                right.types_at_spans(type_defs, docs, consume);
+            }
             Self::Break(_) => {}
             Self::Continue(_) => {}
             Self::Recursive { body, .. } => {
@@ -196,14 +277,18 @@ impl<S: Clone> Type<S> {
 fn write_type_with_options<S: Clone, N: GlobalNameWriter<S>>(
     f: &mut impl Write,
     names: &N,
+    seen: &mut Vec<String>,
     typ: &Type<S>,
     options: TypeRenderOptions,
 ) -> fmt::Result {
     if options.prefer_display_hints {
         if let Some(display_hint) = typ.display_hint() {
-            return write_named_type_display(f, names, display_hint, options);
+            return write_named_type_display(f, names, seen, display_hint, options);
         }
     }
+    if options.too_deep() && !is_atomic(typ) {
+        return write!(f, "...");
+    }
 
     match typ {
         Type::Primitive(_, primitive) => write_primitive_type(f, primitive),
@@ -215,28 +300,33 @@ fn write_type_with_options<S: Clone, N: GlobalNameWriter<S>>(
         Type::DualVar(_, name) => write!(f, "dual {name}"),
         Type::Name(_, name, args) => {
             names.write_global_name(f, name)?;
-            write_type_args(f, names, args, options)
+            write_type_args(f, names, seen, args, options)
         }
         Type::DualName(_, name, args) => {
             write!(f, "dual ")?;
             names.write_global_name(f, name)?;
-            write_type_args(f, names, args, options)
+            write_type_args(f, names, seen, args, options)
         }
         Type::Box(_, body) => {
             write!(f, "box ")?;
-            write_type_with_options(f, names, body, options)
+            write_type_with_options(f, names, seen, body, options.deeper())
         }
         Type::DualBox(_, body) => {
             write!(f, "dual box ")?;
-            write_type_with_options(f, names, body, options)
+            write_type_with_options(f, names, seen, body, options.deeper())
         }
-        Type::Pair(_, _, _, _) => write_pair_like(f, names, "(", ")", typ, false, options),
-        Type::Function(_, _, _, _) => write_pair_like(f, names, "[", "]", typ, true, options),
+        Type::Pair(_, _, _, _) => write_pair_like(f, names, seen, "(", ")", typ, false, options),
+        Type::Function(_, _, _, _) => write_pair_like(f, names, seen, "[", "]", typ, true, options),
         Type::Either(_, branches) => {
-            write_braced_branches(f, names, "either", branches, false, options)
+            write_braced_branches(f, names, seen, "either", branches, false, options)
         }
         Type::Choice(_, branches) => {
-            write_braced_branches(f, names, "choice", branches, true, options)
+            write_braced_branches(f, names, seen, "choice", branches, true, options)
+        }
+        Type::Union(_, kind, left, right) => {
+            write_type_with_options(f, names, seen, left, options)?;
+            write!(f, " {} ", kind.operator())?;
+            write_type_with_options(f, names, seen, right, options)
         }
         Type::Break(_) => write!(f, "!"),
         Type::Continue(_) => write!(f, "?"),
@@ -248,7 +338,7 @@ fn write_type_with_options<S: Clone, N: GlobalNameWriter<S>>(
                 }
             }
             write!(f, " ")?;
-            write_type_with_options(f, names, body, options)
+            write_recursive_body(f, names, seen, body, options)
         }
         Type::Iterative { label, body, .. } => {
             write!(f, "iterative")?;
@@ -258,7 +348,7 @@ fn write_type_with_options<S: Clone, N: GlobalNameWriter<S>>(
                 }
             }
             write!(f, " ")?;
-            write_type_with_options(f, names, body, options)
+            write_recursive_body(f, names, seen, body, options)
         }
         Type::Self_(_, label) => {
             write!(f, "self")?;
@@ -274,8 +364,8 @@ fn write_type_with_options<S: Clone, N: GlobalNameWriter<S>>(
             }
             Ok(())
         }
-        Type::Exists(_, _, _) => write_pair_like(f, names, "(", ")", typ, false, options),
-        Type::Forall(_, _, _) => write_pair_like(f, names, "[", "]", typ, true, options),
+        Type::Exists(_, _, _) => write_pair_like(f, names, seen, "(", ")", typ, false, options),
+        Type::Forall(_, _, _) => write_pair_like(f, names, seen, "[", "]", typ, true, options),
         Type::Hole(_, name, _) => write!(f, "%{name}"),
         Type::DualHole(_, name, _) => write!(f, "dual %{name}"),
         Type::Fail(_) => write!(f, "<error>"),
@@ -298,6 +388,7 @@ fn write_primitive_type(f: &mut impl Write, primitive: &PrimitiveType) -> fmt::R
 fn write_type_args<S: Clone, N: GlobalNameWriter<S>>(
     f: &mut impl Write,
     names: &N,
+    seen: &mut Vec<String>,
     args: &[Type<S>],
     options: TypeRenderOptions,
 ) -> fmt::Result {
@@ -305,12 +396,16 @@ fn write_type_args<S: Clone, N: GlobalNameWriter<S>>(
         return Ok(());
     }
 
+    let shown = options.shown_width(args.len());
     write!(f, "<")?;
-    for (i, arg) in args.iter().enumerate() {
+    for (i, arg) in args.iter().take(shown).enumerate() {
         if i > 0 {
             write!(f, ", ")?;
         }
-        write_type_with_options(f, names, arg, options)?;
+        write_type_with_options(f, names, seen, arg, options.deeper())?;
+    }
+    if shown < args.len() {
+        write!(f, ", ...")?;
     }
     write!(f, ">")
 }
@@ -318,6 +413,7 @@ fn write_type_args<S: Clone, N: GlobalNameWriter<S>>(
 fn write_pair_like<S: Clone, N: GlobalNameWriter<S>>(
     f: &mut impl Write,
     names: &N,
+    seen: &mut Vec<String>,
     open: &str,
     close: &str,
     typ: &Type<S>,
@@ -334,7 +430,7 @@ fn write_pair_like<S: Clone, N: GlobalNameWriter<S>>(
                 write!(f, ", {var}")?;
             }
             write!(f, ">{open}")?;
-            write_type_with_options(f, names, arg, options)?;
+            write_type_with_options(f, names, seen, arg, options.deeper())?;
             then = next_then;
         }
         Type::Pair(_, arg, next_then, vars) if !function && !vars.is_empty() => {
@@ -343,7 +439,7 @@ fn write_pair_like<S: Clone, N: GlobalNameWriter<S>>(
                 write!(f, ", {var}")?;
             }
             write!(f, ">{open}")?;
-            write_type_with_options(f, names, arg, options)?;
+            write_type_with_options(f, names, seen, arg, options.deeper())?;
             then = next_then;
         }
         _ => {
@@ -368,14 +464,14 @@ fn write_pair_like<S: Clone, N: GlobalNameWriter<S>>(
                         if wrote_prefix_item {
                             write!(f, ", ")?;
                         }
-                        write_type_with_options(f, names, arg, options)?;
+                        write_type_with_options(f, names, seen, arg, options.deeper())?;
                         then = next_then;
                     }
                     Type::Pair(_, arg, next_then, vars) if !function && vars.is_empty() => {
                         if wrote_prefix_item {
                             write!(f, ", ")?;
                         }
-                        write_type_with_options(f, names, arg, options)?;
+                        write_type_with_options(f, names, seen, arg, options.deeper())?;
                         then = next_then;
                     }
                     _ => break,
@@ -398,13 +494,14 @@ fn write_pair_like<S: Clone, N: GlobalNameWriter<S>>(
         }
     } else {
         write!(f, "{close} ")?;
-        write_type_with_options(f, names, then, options)
+        write_type_with_options(f, names, seen, then, options)
     }
 }
 
 fn write_braced_branches<S: Clone, N: GlobalNameWriter<S>>(
     f: &mut impl Write,
     names: &N,
+    seen: &mut Vec<String>,
     prefix: &str,
     branches: &BTreeMap<LocalName, Type<S>>,
     choice: bool,
@@ -416,18 +513,19 @@ fn write_braced_branches<S: Clone, N: GlobalNameWriter<S>>(
 
     write!(f, "{prefix} {{")?;
 
-    for (branch, branch_type) in branches {
-        let options = options.next_indent();
+    let shown = options.shown_width(branches.len());
+    for (branch, branch_type) in branches.iter().take(shown) {
+        let options = options.next_indent().deeper();
         options.write_indentation(f)?;
         write!(f, ".{branch}")?;
         if choice {
             if matches!(branch_type, Type::Function(.., vars) if vars.is_empty())
                 || matches!(branch_type, Type::Forall(..))
             {
-                write_pair_like(f, names, "(", ") =>", branch_type, true, options)?;
+                write_pair_like(f, names, seen, "(", ") =>", branch_type, true, options)?;
             } else {
                 write!(f, " => ")?;
-                write_type_with_options(f, names, branch_type, options)?;
+                write_type_with_options(f, names, seen, branch_type, options)?;
             }
         } else {
             if matches!(branch_type, Type::Break(_) | Type::Exists(..))
@@ -437,17 +535,67 @@ fn write_braced_branches<S: Clone, N: GlobalNameWriter<S>>(
             } else {
                 write!(f, " ")?;
             }
-            write_type_with_options(f, names, branch_type, options)?;
+            write_type_with_options(f, names, seen, branch_type, options)?;
         }
         write!(f, ",")?;
     }
+    if shown < branches.len() {
+        options.next_indent().write_indentation(f)?;
+        write!(f, "...")?;
+    }
     options.write_indentation(f)?;
     write!(f, "}}")
 }
 
+/// The body of a `recursive` or `iterative` type, unless the same one was
+/// already shown and repeated ones are elided.
+fn write_recursive_body<S: Clone, N: GlobalNameWriter<S>>(
+    f: &mut impl Write,
+    names: &N,
+    seen: &mut Vec<String>,
+    body: &Type<S>,
+    options: TypeRenderOptions,
+) -> fmt::Result {
+    if options.truncation.elide_repeated_recursion {
+        let mut key = String::new();
+        write_type_with_options(
+            &mut key,
+            names,
+            &mut Vec::new(),
+            body,
+            TypeRenderOptions::pretty_compact(),
+        )?;
+        if seen.contains(&key) {
+            return write!(f, "...");
+        }
+        seen.push(key);
+    }
+    write_type_with_options(f, names, seen, body, options)
+}
+
+/// Whether a type is shown the same no matter how deeply it's nested.
+fn is_atomic<S>(typ: &Type<S>) -> bool {
+    match typ {
+        Type::Name(_, _, args) | Type::DualName(_, _, args) => args.is_empty(),
+        Type::Primitive(..)
+        | Type::DualPrimitive(..)
+        | Type::Var(..)
+        | Type::DualVar(..)
+        | Type::Break(_)
+        | Type::Continue(_)
+        | Type::Self_(..)
+        | Type::DualSelf(..)
+        | Type::Hole(..)
+        | Type::DualHole(..)
+        | Type::Fail(_) => true,
+        _ => false,
+    }
+}
+
 fn write_named_type_display<S: Clone, N: GlobalNameWriter<S>>(
     f: &mut impl Write,
     names: &N,
+    seen: &mut Vec<String>,
     display_hint: &NamedTypeDisplay<S>,
     options: TypeRenderOptions,
 ) -> fmt::Result {
@@ -455,21 +603,29 @@ fn write_named_type_display<S: Clone, N: GlobalNameWriter<S>>(
         write!(f, "dual ")?;
     }
     names.write_global_name(f, &display_hint.name)?;
-    write_type_args(f, names, &display_hint.args, options)
+    write_type_args(f, names, seen, &display_hint.args, options)
 }
 
 fn dual_name_hover_span<S>(full_span: &Span, name: &GlobalName<S>) -> Span {
     match (name.span().start(), full_span.end(), full_span.file()) {
-        (Some(start), Some(end), Some(file)) => Span::At { start, end, file },
+        (Some(start), Some(end), Some(file)) => Span::At {
+            start,
+            end,
+            file,
+            expansion: None,
+        },
         _ => name.span(),
     }
 }
 
 fn dual_keyword_hover_span<S>(full_span: &Span, name: &GlobalName<S>) -> Span {
     match (full_span.start(), name.span().start(), full_span.file()) {
-        (Some(start), Some(end), Some(file)) if start.offset < end.offset => {
-            Span::At { start, end, file }
-        }
+        (Some(start), Some(end), Some(file)) if start.offset < end.offset => Span::At {
+            start,
+            end,
+            file,
+            expansion: None,
+        },
         _ => Span::None,
     }
 }
@@ -39,6 +39,12 @@ impl<S> Type<S> {
                     .map(|(branch, t)| (branch.clone(), t.dual(Span::None)))
                     .collect(),
             ),
+            Self::Union(span, kind, left, right) => Self::Union(
+                span0.join(span),
+                kind.dual(),
+                Box::new(left.dual(Span::None)),
+                Box::new(right.dual(Span::None)),
+            ),
             Self::Break(span) => Self::Continue(span0.join(span)),
             Self::Continue(span) => Self::Break(span0.join(span)),
 
@@ -1,9 +1,9 @@
 use crate::frontend_impl::language::{GlobalName, LocalName, TypeConstraint, Universal};
-use crate::frontend_impl::types::{LoopId, Operation, Type};
-use crate::location::Span;
-use crate::workspace::{FileImportScope, render_global_name_in_scope, render_type_in_scope};
-use miette::{LabeledSpan, SourceOffset, SourceSpan};
-use std::fmt::Write;
+use crate::frontend_impl::types::{LoopId, Operation, Type, UnionKind};
+use crate::location::{FileName, Span};
+use crate::workspace::{FileImportScope, render_global_name_in_scope, render_report_type_in_scope};
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceOffset, SourceSpan};
+use std::fmt::{self, Write};
 use std::sync::Arc;
 
 use super::Visibility;
@@ -19,22 +19,29 @@ pub enum TypeError<S> {
     GlobalNameNotVisible(Span, GlobalName<S>, Visibility),
     VisibleItemExposesHiddenType(Span, GlobalName<S>, Visibility, GlobalName<S>, Visibility),
     NoMatchingRecursiveOrIterative(Span),
-    SelfUsedInNegativePosition(Span),
+    /// The `self`, the `recursive` or `iterative` it refers to, and every place
+    /// in between where the polarity flips, with what flips it there.
+    SelfUsedInNegativePosition(Span, Span, Vec<(Span, &'static str)>),
     UnguardedRecursiveSelf(Span),
     UnguardedIterativeSelf(Span),
     TypeNameNotDefined(Span, GlobalName<S>),
     TypeVariableNotDefined(Span, LocalName),
     DependencyCycle(Span, Vec<GlobalName<S>>),
+    TypeExpansionTooDeep(Span, Vec<(GlobalName<S>, Span)>),
     WrongNumberOfTypeArgs(Span, GlobalName<S>, usize, usize),
     GlobalNameNotDefined(Span, GlobalName<S>),
     VariableDoesNotExist(Span, LocalName),
-    ShadowedObligation(Span, LocalName),
+    /// Where the variable is bound again, where it was bound before, and the
+    /// type it still has there.
+    ShadowedObligation(Span, Span, LocalName, Type<S>),
     TypeMustBeKnownAtThisPoint(Span, #[allow(unused)] LocalName),
     ParameterTypeMustBeKnown(Span, LocalName),
     CannotAssignFromTo(Span, Type<S>, Type<S>),
     TypeDoesNotSatisfyConstraint(Span, LocalName, Type<S>, TypeConstraint),
     TypeParameterConstraintMismatch(Span, LocalName, TypeConstraint, TypeConstraint),
-    UnfulfilledObligations(Span, Vec<LocalName>),
+    /// Where the process ends, and each variable it hasn't handled: where it's
+    /// bound, and its type.
+    UnfulfilledObligations(Span, Vec<(LocalName, Span, Type<S>)>),
     InvalidOperation(Span, #[allow(unused)] Operation, Type<S>),
     InvalidBranch(Span, LocalName, Type<S>),
     MissingBranch(Span, LocalName, Type<S>),
@@ -43,8 +50,16 @@ pub enum TypeError<S> {
     MergeVariableTypesCannotBeUnified(Span, LocalName, Type<S>, Type<S>),
     VariableEscapesTypeScope(Span, LocalName),
     TypesCannotBeUnified(Span, Type<S>, Type<S>),
+    /// Like `TypesCannotBeUnified`, for the payloads of a branch that both
+    /// sides have.
+    BranchTypesCannotBeUnified(Span, LocalName, Type<S>, Type<S>),
     NoSuchLoopPoint(Span, #[allow(unused)] Option<LocalName>),
     DoesNotDescendSubjectOfBegin(Span, #[allow(unused)] LoopId),
+    /// A `loop` of a `begin` with subjects after `&` makes none of them
+    /// smaller, keeping the ones before. It's the first subject that's neither
+    /// kept nor smaller, or `None` if they're all kept, but the driver isn't
+    /// smaller either.
+    DoesNotDescendSubjectsOfBegin(Span, Option<LocalName>),
     CannotUnrollAscendantIterative(Span, #[allow(unused)] Option<LocalName>),
     LoopVariableNotPreserved(Span, LocalName),
     LoopVariableChangedType(Span, LocalName, Type<S>, Type<S>),
@@ -60,6 +75,21 @@ pub enum TypeError<S> {
     PollBranchMustSubmit(Span),
     CannotUseLinearVariableInBox(Span, LocalName),
     NonExhaustiveIf(Span),
+    /// An operand of a union that isn't the kind of type the union combines.
+    InvalidUnionOperand(Span, UnionKind, Type<S>),
+    /// The union, and the label as it is in either operand.
+    OverlappingUnionLabels(Span, LocalName, LocalName),
+    /// A parameter of a type definition that its body doesn't use. A warning,
+    /// see [`TypeError::is_warning`].
+    UnusedTypeParameter(Span, GlobalName<S>, LocalName),
+    /// A reference closing a cycle of corecursive definitions, and the cycle,
+    /// in which some definition refers to the next other than from a `case`.
+    UnguardedCorecursion(Span, Vec<GlobalName<S>>),
+    /// A union with a type variable on both sides. Only one may stand for the
+    /// rest of the branches.
+    UnionOfTwoTypeVariables(Span, Type<S>, Type<S>),
+    /// A `.case` without an `else` on a value whose branches aren't all known.
+    CaseOnOpenRowWithoutElse(Span, Type<S>),
 }
 
 /// Create a `LabeledSpan` without a label at `span`
@@ -78,6 +108,15 @@ pub(crate) fn labels_from_span(_code: &str, span: &Span) -> Vec<LabeledSpan> {
         .collect()
 }
 
+/// `source_code` as the text of the file `span` is in. Files of builtin
+/// packages are named, so their snippets aren't taken for the user's own code.
+pub(crate) fn source_of(span: &Span, source_code: Arc<str>) -> Arc<dyn SourceCode + Send + Sync> {
+    match span.file().filter(FileName::is_builtin) {
+        Some(file) => Arc::new(NamedSource::new(file.0.as_str(), source_code)),
+        None => Arc::new(source_code),
+    }
+}
+
 fn two_labels_from_two_spans(
     code: &str,
     span1: &Span,
@@ -95,6 +134,35 @@ fn two_labels_from_two_spans(
     labels
 }
 
+/// What's left to do with a variable of this type, if it can be told from the
+/// outermost part of the type.
+fn obligation_hint<S>(name: &LocalName, typ: &Type<S>) -> Option<String> {
+    match typ {
+        Type::Recursive { body, .. } | Type::Iterative { body, .. } => obligation_hint(name, body),
+        Type::Continue(_) => Some(format!("it still expects `{}!`", name)),
+        Type::Choice(_, branches) if !branches.is_empty() => {
+            let labels = branches
+                .keys()
+                .map(|label| format!("`.{}`", label))
+                .collect::<Vec<_>>();
+            Some(match labels.as_slice() {
+                [label] => format!("this channel still expects {}", label),
+                _ => format!("this channel still expects one of {}", labels.join(", ")),
+            })
+        }
+        Type::Either(..) => Some(format!("it still needs to be matched with `{}.case`", name)),
+        Type::Function(..) | Type::Forall(..) => Some(format!(
+            "it still expects an argument, as in `{}(...)`",
+            name
+        )),
+        Type::Pair(..) | Type::Exists(..) => Some(format!(
+            "it still has a value to receive, as in `{}[...]`",
+            name
+        )),
+        _ => None,
+    }
+}
+
 impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
     fn to_report_with(
         &self,
@@ -103,7 +171,7 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
         render_type: impl Fn(&Type<S>, usize) -> String,
     ) -> miette::Report {
         let code = &source_code;
-        match self {
+        let report = match self {
             Self::TypeNameAlreadyDefined(span1, span2, name) => {
                 let name = render_name(name);
                 miette::miette!(
@@ -196,11 +264,27 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                     "This `self` has no matching `recursive` or `iterative`.",
                 )
             }
-            Self::SelfUsedInNegativePosition(span) => {
-                let labels = labels_from_span(code, span);
+            Self::SelfUsedInNegativePosition(span, binder, flips) => {
+                let mut labels = two_labels_from_two_spans(
+                    code,
+                    span,
+                    binder,
+                    "this `self`".to_owned(),
+                    "refers to this".to_owned(),
+                );
+                let mut path = String::new();
+                for (flip, reason) in flips {
+                    let mut flip_labels = labels_from_span(code, flip);
+                    flip_labels.iter_mut().for_each(|label| {
+                        label.set_label(Some(format!("{reason}, flips the polarity")))
+                    });
+                    labels.extend(flip_labels);
+                    write!(&mut path, "\n  - {reason} at {flip}").unwrap();
+                }
                 miette::miette!(
                     labels = labels,
-                    "This `self` is used in a negative position.\n\nNegative self-references are not allowed."
+                    "This `self` is used in a negative position.\n\nOn the way from the type it refers to, the polarity flips at:{}\n\nAn odd number of flips makes the type refer to its own dual, like a function taking the type itself as an argument. Such a type could be used to loop forever, so negative self-references are not allowed. See \"Self-references must be positive\" in the chapter on recursive types.",
+                    path
                 )
             }
             Self::UnguardedRecursiveSelf(span) => {
@@ -241,6 +325,29 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                     deps_str
                 )
             }
+            Self::TypeExpansionTooDeep(span, chain) if chain.is_empty() => {
+                let labels = labels_from_span(code, span);
+                miette::miette!(
+                    labels = labels,
+                    "Expanding this type did not settle within the checker's limits.\n\nIt keeps unfolding into more and more of itself. Check its definition for `self` references that grow on every expansion."
+                )
+            }
+            Self::TypeExpansionTooDeep(span, chain) => {
+                let labels = labels_from_span(code, span);
+                let mut chain_str = String::new();
+                for (i, (name, definition)) in chain.iter().enumerate() {
+                    if i > 0 {
+                        write!(&mut chain_str, "\n  -> ").unwrap();
+                    }
+                    write!(&mut chain_str, "{} (defined at {})", render_name(name), definition)
+                        .unwrap();
+                }
+                miette::miette!(
+                    labels = labels,
+                    "Expanding this type does not terminate. The named types expand into each other:\n\n  {}\n\nCheck these definitions for aliases that refer back to one another.",
+                    chain_str
+                )
+            }
             Self::WrongNumberOfTypeArgs(span, name, required_number, provided_number) => {
                 let labels = labels_from_span(code, span);
                 let name = render_name(name);
@@ -261,12 +368,19 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                 let labels = labels_from_span(code, span);
                 miette::miette!(labels = labels, "Variable `{}` does not exist.", name)
             }
-            Self::ShadowedObligation(span, name) => {
-                let labels = labels_from_span(code, span);
+            Self::ShadowedObligation(span, binder, name, typ) => {
+                let labels = two_labels_from_two_spans(
+                    code,
+                    span,
+                    binder,
+                    "re-assigned here".to_owned(),
+                    format!("`{}` is bound here", name),
+                );
                 miette::miette!(
                     labels = labels,
-                    "Cannot re-assign `{}` before handling it.",
+                    "Cannot re-assign `{}` before handling it. It still has this type:\n\n  {}\n",
                     name,
+                    render_type(typ, 1),
                 )
             }
             Self::TypeMustBeKnownAtThisPoint(span, name) => {
@@ -334,19 +448,33 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                     expected,
                 )
             }
-            Self::UnfulfilledObligations(span, names) => {
-                let labels = labels_from_span(code, span);
+            Self::UnfulfilledObligations(span, obligations) => {
+                let mut labels = labels_from_span(code, span);
+                for (name, binder, typ) in obligations {
+                    let label = match obligation_hint(name, typ) {
+                        Some(hint) => format!("`{}` is bound here; {}", name, hint),
+                        None => format!("`{}` is bound here", name),
+                    };
+                    labels.extend(labels_from_span(code, binder).into_iter().map(|mut binder| {
+                        binder.set_label(Some(label.clone()));
+                        binder
+                    }));
+                }
                 miette::miette!(
                     labels = labels,
-                    "Cannot end this process before handling {}.",
-                    names
+                    "Cannot end this process before handling {}.\n\n{}",
+                    obligations
                         .iter()
                         .enumerate()
-                        .map(|(i, name)| if i == 0 {
+                        .map(|(i, (name, _, _))| if i == 0 {
                             format!("`{}`", name)
                         } else {
                             format!(", `{}`", name)
                         })
+                        .collect::<String>(),
+                    obligations
+                        .iter()
+                        .map(|(name, _, typ)| format!("  {} : {}\n", name, render_type(typ, 1)))
                         .collect::<String>()
                 )
             }
@@ -417,10 +545,27 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                     name,
                 )
             }
-            Self::TypesCannotBeUnified(span, _typ1, _typ2) => {
+            Self::TypesCannotBeUnified(span, typ1, typ2) => {
+                let labels = labels_from_span(code, span);
+                let t1s = render_type(typ1, 1);
+                let t2s = render_type(typ2, 1);
                 miette::miette!(
-                    labels = labels_from_span(code, span),
-                    "Types could not be unified here."
+                    labels = labels,
+                    "Types could not be unified here, no type fits both:\n\n  {}\n\n  {}\n",
+                    t1s,
+                    t2s
+                )
+            }
+            Self::BranchTypesCannotBeUnified(span, branch, typ1, typ2) => {
+                let labels = labels_from_span(code, span);
+                let t1s = render_type(typ1, 1);
+                let t2s = render_type(typ2, 1);
+                miette::miette!(
+                    labels = labels,
+                    "Types could not be unified here, no type fits both versions of the `.{}` branch:\n\n  {}\n\n  {}\n",
+                    branch,
+                    t1s,
+                    t2s
                 )
             }
             Self::NoSuchLoopPoint(span, _) => {
@@ -434,6 +579,20 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                     "This `loop` may diverge. Value does not descend from the corresponding `begin`.\n\nIf this is intended, use `unfounded`.",
                 )
             }
+            Self::DoesNotDescendSubjectsOfBegin(span, subject) => {
+                let labels = labels_from_span(code, span);
+                match subject {
+                    Some(subject) => miette::miette!(
+                        labels = labels,
+                        "This `loop` may diverge. `{}` is neither kept nor made smaller since the corresponding `begin`, and no subject before it is made smaller.\n\nIf this is intended, use `unfounded`.",
+                        subject,
+                    ),
+                    None => miette::miette!(
+                        labels = labels,
+                        "This `loop` may diverge. The subjects after `&` are kept, but the value does not descend from the corresponding `begin`.\n\nIf this is intended, use `unfounded`.",
+                    ),
+                }
+            }
             Self::LoopVariableNotPreserved(span, name) => {
                 let labels = labels_from_span(code, span);
                 miette::miette!(
@@ -539,6 +698,75 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                 let labels = labels_from_span(code, span);
                 miette::miette!(labels = labels, "Conditions are not exhaustive; an `else` branch is required here.")
             }
+            Self::InvalidUnionOperand(span, kind, typ) => {
+                let labels = labels_from_span(code, span);
+                let combined = match kind {
+                    UnionKind::Either => "either",
+                    UnionKind::Choice => "choice",
+                };
+                miette::miette!(
+                    labels = labels,
+                    "`{}` combines `{}` types, but this is not one:\n\n  {}\n",
+                    kind.operator(),
+                    combined,
+                    render_type(typ, 1)
+                )
+            }
+            Self::OverlappingUnionLabels(span, label1, label2) => {
+                let mut labels = two_labels_from_two_spans(code, &label1.span, &label2.span, "this".to_owned(), "and this".to_owned());
+                labels.extend(labels_from_span(code, span));
+                miette::miette!(
+                    labels = labels,
+                    "Both sides of this union have a branch `.{}`.",
+                    label1
+                )
+            }
+            Self::UnusedTypeParameter(span, name, parameter) => {
+                let labels = labels_from_span(code, span);
+                let name = render_name(name);
+                miette::miette!(
+                    labels = labels,
+                    help = "Remove it, or check the body for a misspelling: a name that isn't a parameter refers to a global type.",
+                    "Type parameter `{}` of `{}` is never used.",
+                    parameter,
+                    name,
+                )
+            }
+            Self::UnguardedCorecursion(span, deps) => {
+                let labels = labels_from_span(code, span);
+                let mut deps_str = String::new();
+                for (i, dep) in deps.iter().enumerate() {
+                    if i > 0 {
+                        write!(&mut deps_str, " -> ").unwrap();
+                    }
+                    write!(&mut deps_str, "{}", render_name(dep)).unwrap();
+                }
+                miette::miette!(
+                    labels = labels,
+                    help = "Refer back only as what a branch of a `case` offered by the value being defined continues with, so that each step waits for a request.",
+                    "These definitions refer to each other without waiting for a request:\n\n  {}\n\nUsing them would loop forever.",
+                    deps_str
+                )
+            }
+            Self::UnionOfTwoTypeVariables(span, left, right) => {
+                let labels = labels_from_span(code, span);
+                miette::miette!(
+                    labels = labels,
+                    help = "Only one side of a union may be a type variable, standing for the rest of the branches.",
+                    "Both sides of this union are type variables: `{}` and `{}`.",
+                    render_type(left, 0),
+                    render_type(right, 0),
+                )
+            }
+            Self::CaseOnOpenRowWithoutElse(span, typ) => {
+                let labels = labels_from_span(code, span);
+                miette::miette!(
+                    labels = labels,
+                    help = "Add an `else` branch to handle the branches that aren't listed.",
+                    "Not all branches of this type are known, so the `.case` can't cover them:\n\n  {}\n",
+                    render_type(typ, 1)
+                )
+            }
             Self::CannotUnrollAscendantIterative(span, _) => {
                 let labels = labels_from_span(code, span);
                 miette::miette!(
@@ -547,7 +775,109 @@ impl<S: Clone + Eq + std::hash::Hash + std::fmt::Display> TypeError<S> {
                 )
 
             }
-        }.with_source_code(source_code)
+        }.with_source_code(source_of(&self.spans().0, source_code));
+        let report = miette::Report::new(WithCode {
+            report,
+            code: self.code(),
+        });
+        match WithExpansion::label(&self.spans().0) {
+            Some(label) => miette::Report::new(WithExpansion { report, label }),
+            None => report,
+        }
+    }
+}
+
+/// A report with the stable code of the error it reports.
+struct WithCode {
+    report: miette::Report,
+    code: &'static str,
+}
+
+impl fmt::Debug for WithCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.report, f)
+    }
+}
+
+impl fmt::Display for WithCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.report, f)
+    }
+}
+
+impl std::error::Error for WithCode {}
+
+impl Diagnostic for WithCode {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.report.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.report.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.report.labels()
+    }
+}
+
+/// A report with an extra label at the sugar its main span was expanded from.
+struct WithExpansion {
+    report: miette::Report,
+    label: LabeledSpan,
+}
+
+impl WithExpansion {
+    fn label(span: &Span) -> Option<LabeledSpan> {
+        let expansion = span.expansion()?;
+        if &expansion.span == span {
+            return None;
+        }
+        let start = expansion.span.start()?;
+        Some(LabeledSpan::new_with_span(
+            Some(format!("in code expanded from this {}", expansion.sugar)),
+            SourceSpan::new(
+                SourceOffset::from(start.offset as usize),
+                expansion.span.len() as usize,
+            ),
+        ))
+    }
+}
+
+impl fmt::Debug for WithExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.report, f)
+    }
+}
+
+impl fmt::Display for WithExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.report, f)
+    }
+}
+
+impl std::error::Error for WithExpansion {}
+
+impl Diagnostic for WithExpansion {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.report.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.report.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.report.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let labels = self.report.labels().into_iter().flatten();
+        Some(Box::new(labels.chain([self.label.clone()])))
     }
 }
 
@@ -560,17 +890,89 @@ impl TypeError<Universal> {
         self.to_report_with(
             source_code,
             |name| render_global_name_in_scope(scope, name),
-            |typ, indent| render_type_in_scope(scope, typ, indent),
+            |typ, indent| render_report_type_in_scope(scope, typ, indent),
         )
     }
 }
 
 impl<S: Clone + Eq + std::hash::Hash> TypeError<S> {
+    /// The stable code of this kind of error, explained by `par explain`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TypeNameAlreadyDefined(..) => "PAR0002",
+            Self::NameAlreadyDeclared(..) => "PAR0003",
+            Self::NameAlreadyDefined(..) => "PAR0004",
+            Self::DeclaredButNotDefined(..) => "PAR0005",
+            Self::ModuleExportInconsistent(..) => "PAR0006",
+            Self::ImportedModuleNotExported(..) => "PAR0007",
+            Self::GlobalNameNotVisible(..) => "PAR0008",
+            Self::VisibleItemExposesHiddenType(..) => "PAR0009",
+            Self::NoMatchingRecursiveOrIterative(..) => "PAR0010",
+            Self::SelfUsedInNegativePosition(..) => "PAR0011",
+            Self::UnguardedRecursiveSelf(..) => "PAR0012",
+            Self::UnguardedIterativeSelf(..) => "PAR0013",
+            Self::TypeNameNotDefined(..) => "PAR0014",
+            Self::TypeVariableNotDefined(..) => "PAR0015",
+            Self::DependencyCycle(..) => "PAR0016",
+            Self::TypeExpansionTooDeep(..) => "PAR0017",
+            Self::WrongNumberOfTypeArgs(..) => "PAR0019",
+            Self::GlobalNameNotDefined(..) => "PAR0020",
+            Self::VariableDoesNotExist(..) => "PAR0021",
+            Self::ShadowedObligation(..) => "PAR0022",
+            Self::TypeMustBeKnownAtThisPoint(..) => "PAR0023",
+            Self::ParameterTypeMustBeKnown(..) => "PAR0024",
+            Self::CannotAssignFromTo(..) => "PAR0025",
+            Self::TypeDoesNotSatisfyConstraint(..) => "PAR0026",
+            Self::TypeParameterConstraintMismatch(..) => "PAR0027",
+            Self::UnfulfilledObligations(..) => "PAR0028",
+            Self::InvalidOperation(..) => "PAR0029",
+            Self::InvalidBranch(..) => "PAR0030",
+            Self::MissingBranch(..) => "PAR0031",
+            Self::RedundantBranch(..) => "PAR0032",
+            Self::MergeVariableMissing(..) => "PAR0033",
+            Self::MergeVariableTypesCannotBeUnified(..) => "PAR0034",
+            Self::VariableEscapesTypeScope(..) => "PAR0035",
+            Self::TypesCannotBeUnified(..) => "PAR0036",
+            Self::BranchTypesCannotBeUnified(..) => "PAR0037",
+            Self::NoSuchLoopPoint(..) => "PAR0038",
+            Self::DoesNotDescendSubjectOfBegin(..) => "PAR0039",
+            Self::DoesNotDescendSubjectsOfBegin(..) => "PAR0061",
+            Self::CannotUnrollAscendantIterative(..) => "PAR0040",
+            Self::LoopVariableNotPreserved(..) => "PAR0041",
+            Self::LoopVariableChangedType(..) => "PAR0042",
+            Self::PollMustHaveAtLeastOneClient(..) => "PAR0043",
+            Self::PollClientMustBeRecursive(..) => "PAR0044",
+            Self::SubmitOutsidePoll(..) => "PAR0045",
+            Self::RepollOutsidePoll(..) => "PAR0046",
+            Self::SubmittedClientNotAssignableToPoll(..) => "PAR0047",
+            Self::SubmittedClientDoesNotDescend(..) => "PAR0048",
+            Self::SubmitCannotTargetPollPoint(..) => "PAR0049",
+            Self::PollVariableNotPreserved(..) => "PAR0050",
+            Self::PollVariableChangedType(..) => "PAR0051",
+            Self::PollBranchMustSubmit(..) => "PAR0052",
+            Self::CannotUseLinearVariableInBox(..) => "PAR0053",
+            Self::NonExhaustiveIf(..) => "PAR0054",
+            Self::InvalidUnionOperand(..) => "PAR0055",
+            Self::OverlappingUnionLabels(..) => "PAR0056",
+            Self::UnusedTypeParameter(..) => "PAR0057",
+            Self::UnguardedCorecursion(..) => "PAR0058",
+            Self::UnionOfTwoTypeVariables(..) => "PAR0059",
+            Self::CaseOnOpenRowWithoutElse(..) => "PAR0060",
+        }
+    }
+
+    /// Whether this is only a warning: it doesn't stop the program from being
+    /// checked or run.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::UnusedTypeParameter(..))
+    }
+
     pub fn spans(&self) -> (Span, Option<Span>) {
         match self {
             Self::TypeNameAlreadyDefined(span1, span2, _)
             | Self::NameAlreadyDeclared(span1, span2, _)
             | Self::NameAlreadyDefined(span1, span2, _)
+            | Self::ShadowedObligation(span1, span2, _, _)
             | Self::ModuleExportInconsistent(span1, span2, _) => {
                 (span1.clone(), Some(span2.clone()))
             }
@@ -580,16 +982,16 @@ impl<S: Clone + Eq + std::hash::Hash> TypeError<S> {
             | Self::GlobalNameNotVisible(span, _, _)
             | Self::VisibleItemExposesHiddenType(span, _, _, _, _)
             | Self::NoMatchingRecursiveOrIterative(span)
-            | Self::SelfUsedInNegativePosition(span)
+            | Self::SelfUsedInNegativePosition(span, _, _)
             | Self::UnguardedRecursiveSelf(span)
             | Self::UnguardedIterativeSelf(span)
             | Self::TypeNameNotDefined(span, _)
             | Self::TypeVariableNotDefined(span, _)
             | Self::DependencyCycle(span, _)
+            | Self::TypeExpansionTooDeep(span, _)
             | Self::WrongNumberOfTypeArgs(span, _, _, _)
             | Self::GlobalNameNotDefined(span, _)
             | Self::VariableDoesNotExist(span, _)
-            | Self::ShadowedObligation(span, _)
             | Self::TypeMustBeKnownAtThisPoint(span, _)
             | Self::ParameterTypeMustBeKnown(span, _)
             | Self::CannotAssignFromTo(span, _, _)
@@ -605,6 +1007,7 @@ impl<S: Clone + Eq + std::hash::Hash> TypeError<S> {
             | Self::VariableEscapesTypeScope(span, _)
             | Self::NoSuchLoopPoint(span, _)
             | Self::DoesNotDescendSubjectOfBegin(span, _)
+            | Self::DoesNotDescendSubjectsOfBegin(span, _)
             | Self::LoopVariableNotPreserved(span, _)
             | Self::LoopVariableChangedType(span, _, _, _)
             | Self::PollMustHaveAtLeastOneClient(span)
@@ -619,9 +1022,16 @@ impl<S: Clone + Eq + std::hash::Hash> TypeError<S> {
             | Self::PollBranchMustSubmit(span)
             | Self::CannotUseLinearVariableInBox(span, _)
             | Self::NonExhaustiveIf(span)
+            | Self::InvalidUnionOperand(span, _, _)
+            | Self::OverlappingUnionLabels(span, _, _)
+            | Self::UnusedTypeParameter(span, _, _)
+            | Self::UnguardedCorecursion(span, _)
+            | Self::UnionOfTwoTypeVariables(span, _, _)
+            | Self::CaseOnOpenRowWithoutElse(span, _)
             | Self::CannotUnrollAscendantIterative(span, _) => (span.clone(), None),
 
-            Self::TypesCannotBeUnified(span, _typ1, _typ2) => (span.clone(), None),
+            Self::TypesCannotBeUnified(span, _typ1, _typ2)
+            | Self::BranchTypesCannotBeUnified(span, _, _typ1, _typ2) => (span.clone(), None),
         }
     }
 }
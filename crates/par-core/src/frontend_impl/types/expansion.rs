@@ -1,9 +1,11 @@
 use crate::frontend_impl::language::LocalName;
 use crate::frontend_impl::types::core::{Ignored, NamedTypeDisplay};
+use crate::frontend_impl::types::definitions::ExpansionLimit;
 use crate::frontend_impl::types::visit;
 use crate::frontend_impl::types::{LoopId, Type, TypeDefs, TypeError};
-use crate::location::Span;
+use crate::location::{Span, Spanning};
 use im::HashSet;
+use std::collections::BTreeMap;
 
 impl<S: Clone> Type<S> {
     pub fn expand_definition(&self, type_defs: &TypeDefs<S>) -> Result<Self, TypeError<S>>
@@ -13,6 +15,7 @@ impl<S: Clone> Type<S> {
         match self {
             Self::Name(span, name, args) => type_defs.get(span, name, args),
             Self::DualName(span, name, args) => type_defs.get_dual(span, name, args),
+            Self::Union(span, kind, left, right) => type_defs.union(span, *kind, left, right),
             _ => Ok(self.clone()),
         }
     }
@@ -22,8 +25,11 @@ impl<S: Clone> Type<S> {
         label: &Option<LocalName>,
         body: &Self,
         display_hint: Option<&NamedTypeDisplay<S>>,
+        limit: ExpansionLimit,
     ) -> Result<Self, TypeError<S>> {
         let mut typ = body.clone();
+        // What's inside is a part of whatever this is.
+        let asc = asc.iter().map(LoopId::strict).collect();
         fn inner<S: Clone>(
             typ: &mut Type<S>,
             target_label: &Option<LocalName>,
@@ -62,7 +68,10 @@ impl<S: Clone> Type<S> {
             Ok(())
         }
 
-        inner(&mut typ, label, asc, body, display_hint)?;
+        inner(&mut typ, label, &asc, body, display_hint)?;
+        if syntactic_size(&typ, limit.size) > limit.size {
+            return Err(TypeError::TypeExpansionTooDeep(typ.span(), Vec::new()));
+        }
         Ok(typ)
     }
 
@@ -72,6 +81,7 @@ impl<S: Clone> Type<S> {
         label: &Option<LocalName>,
         body: &Self,
         display_hint: Option<&NamedTypeDisplay<S>>,
+        limit: ExpansionLimit,
     ) -> Result<Self, TypeError<S>> {
         if !asc.is_empty() {
             return Err(TypeError::CannotUnrollAscendantIterative(
@@ -80,7 +90,7 @@ impl<S: Clone> Type<S> {
             ));
         }
 
-        Type::expand_iterative_unsafe(span, asc, label, body, display_hint)
+        Type::expand_iterative_unsafe(span, asc, label, body, display_hint, limit)
     }
 
     // This variant does not make sure asc isn't empty
@@ -90,6 +100,7 @@ impl<S: Clone> Type<S> {
         label: &Option<LocalName>,
         body: &Self,
         display_hint: Option<&NamedTypeDisplay<S>>,
+        limit: ExpansionLimit,
     ) -> Result<Self, TypeError<S>> {
         let mut typ = body.clone();
         fn inner<S: Clone>(
@@ -134,11 +145,14 @@ impl<S: Clone> Type<S> {
         }
 
         inner(&mut typ, label, asc, body, display_hint)?;
+        if syntactic_size(&typ, limit.size) > limit.size {
+            return Err(TypeError::TypeExpansionTooDeep(typ.span(), Vec::new()));
+        }
         Ok(typ)
     }
 
     #[allow(dead_code)]
-    pub fn expand_fixpoint(&self) -> Result<Self, TypeError<S>> {
+    pub fn expand_fixpoint(&self, type_defs: &TypeDefs<S>) -> Result<Self, TypeError<S>> {
         match self {
             Type::Recursive {
                 asc,
@@ -146,20 +160,33 @@ impl<S: Clone> Type<S> {
                 body,
                 display_hint,
                 ..
-            } => Self::expand_recursive(asc, label, body, display_hint.0.as_ref()),
+            } => Self::expand_recursive(
+                asc,
+                label,
+                body,
+                display_hint.0.as_ref(),
+                type_defs.expansion_limit,
+            ),
             Type::Iterative {
                 span,
                 asc,
                 label,
                 body,
                 display_hint,
-            } => Self::expand_iterative(span, asc, label, body, display_hint.0.as_ref()),
+            } => Self::expand_iterative(
+                span,
+                asc,
+                label,
+                body,
+                display_hint.0.as_ref(),
+                type_defs.expansion_limit,
+            ),
             _ => Ok(self.clone()),
         }
     }
 
     // This variant does not make sure iterative's asc isn't empty
-    pub fn expand_fixpoint_unfounded(&self) -> Result<Self, TypeError<S>> {
+    pub fn expand_fixpoint_unfounded(&self, type_defs: &TypeDefs<S>) -> Result<Self, TypeError<S>> {
         match self {
             Type::Recursive {
                 asc,
@@ -167,15 +194,112 @@ impl<S: Clone> Type<S> {
                 body,
                 display_hint,
                 ..
-            } => Self::expand_recursive(asc, label, body, display_hint.0.as_ref()),
+            } => Self::expand_recursive(
+                asc,
+                label,
+                body,
+                display_hint.0.as_ref(),
+                type_defs.expansion_limit,
+            ),
             Type::Iterative {
                 span,
                 asc,
                 label,
                 body,
                 display_hint,
-            } => Self::expand_iterative_unsafe(span, asc, label, body, display_hint.0.as_ref()),
+            } => Self::expand_iterative_unsafe(
+                span,
+                asc,
+                label,
+                body,
+                display_hint.0.as_ref(),
+                type_defs.expansion_limit,
+            ),
             _ => Ok(self.clone()),
         }
     }
+
+    /// This type with its names and fixpoints unfolded `steps` times, to see
+    /// what a protocol looks like a few steps in. Each step expands every
+    /// name, and unfolds every `recursive` and `iterative`, that the previous
+    /// step exposed. The copies of a fixpoint left in place of its `self`s are
+    /// only unfolded by the next step. Whatever fails to expand stays as is.
+    pub fn unfold_steps(&self, type_defs: &TypeDefs<S>, steps: usize) -> Self
+    where
+        S: Eq + std::hash::Hash,
+    {
+        fn step<S: Clone + Eq + std::hash::Hash>(typ: &mut Type<S>, type_defs: &TypeDefs<S>) {
+            let named = match typ {
+                Type::Union(..) if typ.as_open_row().is_some() => None,
+                Type::Name(..) | Type::DualName(..) | Type::Union(..) => {
+                    typ.expand_definition(type_defs).ok()
+                }
+                _ => None,
+            };
+            // A name is just what it stands for, so a fixpoint behind one
+            // unfolds in the same step.
+            let target = named.as_ref().unwrap_or(typ);
+            let unfolded = match target {
+                Type::Recursive { .. } | Type::Iterative { .. } => {
+                    target.expand_fixpoint_unfounded(type_defs).ok()
+                }
+                _ => None,
+            };
+            match unfolded.or(named) {
+                Some(expanded) => *typ = expanded,
+                None => {
+                    let _ = visit::continue_mut(typ, |child: &mut Type<S>| {
+                        step(child, type_defs);
+                        Ok::<_, ()>(())
+                    });
+                }
+            }
+        }
+
+        let mut typ = self.clone();
+        for _ in 0..steps {
+            step(&mut typ, type_defs);
+        }
+        typ
+    }
+
+    /// The branches of the `either` this type is, unfolding names and
+    /// fixpoints the way the checker does before a `.case`.
+    pub(crate) fn either_branches(
+        &self,
+        type_defs: &TypeDefs<S>,
+    ) -> Option<BTreeMap<LocalName, Self>>
+    where
+        S: Eq + std::hash::Hash,
+    {
+        let mut typ = self.clone();
+        // Unfolded names and fixpoints may alternate, but a name can't come up
+        // twice without an `either` or a `choice` on the way, or the
+        // definitions being rejected as cyclic.
+        for _ in 0..2 * (type_defs.globals.len() + 1) {
+            typ = match typ {
+                Self::Either(_, branches) => return Some(branches),
+                Self::Name(..) | Self::DualName(..) | Self::Union(..) => {
+                    typ.expand_definition(type_defs).ok()?
+                }
+                Self::Recursive { .. } | Self::Iterative { .. } => {
+                    typ.expand_fixpoint_unfounded(type_defs).ok()?
+                }
+                _ => return None,
+            };
+        }
+        None
+    }
+}
+
+/// The number of nodes in `typ`, without expanding named types, counted only up
+/// to a little past `limit`. An unfolded fixpoint with more nodes than its
+/// [`ExpansionLimit`] allows is reported as runaway.
+fn syntactic_size<S>(typ: &Type<S>, limit: usize) -> usize {
+    let mut size = 1;
+    let _ = visit::continue_(typ, |child| {
+        size += syntactic_size(child, limit.saturating_sub(size));
+        if size > limit { Err(()) } else { Ok(()) }
+    });
+    size
 }
@@ -0,0 +1,191 @@
+//! Types built into the compiler as functions of their arguments. They're
+//! declared by the builtin packages, like any other type, and instantiating
+//! one computes the type from the arguments, instead of substituting them into
+//! a body.
+
+use crate::frontend_impl::language::{GlobalName, LocalName};
+use crate::frontend_impl::types::{Type, TypeDefs, TypeError};
+use crate::location::Span;
+
+/// The label of the fixpoint letting a step of an instrumented protocol be
+/// logged to any number of times. Not a valid name in the source, so it can't
+/// capture a `self` of the protocol itself.
+const LOG_LABEL: &str = "#log";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TypeFunction {
+    /// `Protocol.Instrument<T>`: the protocol `T`, with a choice to log a
+    /// `String` before each of its steps. Before every step, whoever holds
+    /// the value picks between `.log(String)`, after which the same choice
+    /// comes again, and `.step`, which goes on with the step of `T`.
+    Instrument,
+}
+
+impl TypeFunction {
+    /// The names of the parameters of the type, for its declaration.
+    pub fn params(self) -> &'static [&'static str] {
+        match self {
+            Self::Instrument => &["t"],
+        }
+    }
+}
+
+impl<S: Clone + Eq + std::hash::Hash> TypeDefs<S> {
+    /// `function`, declared as `name`, applied to `args`. There are as many
+    /// of them as the function has parameters.
+    pub(crate) fn apply_function(
+        &self,
+        span: &Span,
+        function: TypeFunction,
+        name: &GlobalName<S>,
+        args: &[Type<S>],
+    ) -> Result<Type<S>, TypeError<S>> {
+        match function {
+            TypeFunction::Instrument => {
+                let protocol = self.expand_protocol(span, name, &args[0])?;
+                Ok(instrument(name, &protocol))
+            }
+        }
+    }
+
+    /// `typ`, with the names it stands for expanded, until it's a type of its
+    /// own.
+    fn expand_protocol(
+        &self,
+        span: &Span,
+        name: &GlobalName<S>,
+        typ: &Type<S>,
+    ) -> Result<Type<S>, TypeError<S>> {
+        let mut typ = typ.clone();
+        for _ in 0..self.expansion_limit.depth {
+            if !matches!(typ, Type::Name(..) | Type::DualName(..) | Type::Union(..))
+                || typ.as_open_row().is_some()
+            {
+                return Ok(typ);
+            }
+            typ = typ.expand_definition(self)?;
+        }
+        Err(TypeError::TypeExpansionTooDeep(
+            span.clone(),
+            vec![(name.clone(), span.clone())],
+        ))
+    }
+}
+
+/// Puts the choice to log before every step of `typ`. Fixpoints stay where
+/// they are, with their bodies instrumented, so their `self`s refer to the
+/// instrumented protocol. Named types are instrumented lazily, as an
+/// application of `instrument`, the name of the function itself, so that
+/// names referring back to themselves don't expand forever. What has no steps
+/// of its own is left as it is.
+fn instrument<S: Clone>(function: &GlobalName<S>, typ: &Type<S>) -> Type<S> {
+    instrument_step(function, typ, None)
+}
+
+/// Instruments `typ`, the body of the `iterative` labeled `fixpoint`, if it's
+/// one. Logging then goes around that `iterative`, instead of one of its own,
+/// so that a `loop` after logging comes back to the `begin` of the protocol.
+/// A `recursive` gets one of its own, since its `self` must stay guarded by an
+/// `either`.
+fn instrument_step<S: Clone>(
+    function: &GlobalName<S>,
+    typ: &Type<S>,
+    fixpoint: Option<&Option<LocalName>>,
+) -> Type<S> {
+    let inner = |typ: &Type<S>| instrument_step(function, typ, None);
+    let logged = |step: Type<S>| match fixpoint {
+        Some(label) => log_choice(Type::Self_(Span::None, label.clone()), step),
+        None => Type::iterative(
+            Some(LOG_LABEL),
+            log_choice(Type::self_(Some(LOG_LABEL)), step),
+        ),
+    };
+    match typ {
+        Type::Either(span, branches) => logged(Type::Either(
+            span.clone(),
+            branches
+                .iter()
+                .map(|(label, typ)| (label.clone(), inner(typ)))
+                .collect(),
+        )),
+        Type::Choice(span, branches) => logged(Type::Choice(
+            span.clone(),
+            branches
+                .iter()
+                .map(|(label, typ)| (label.clone(), inner(typ)))
+                .collect(),
+        )),
+        Type::Pair(span, value, then, params) => logged(Type::Pair(
+            span.clone(),
+            value.clone(),
+            Box::new(inner(then)),
+            params.clone(),
+        )),
+        Type::Function(span, argument, then, params) => logged(Type::Function(
+            span.clone(),
+            argument.clone(),
+            Box::new(inner(then)),
+            params.clone(),
+        )),
+        Type::Exists(span, param, then) => logged(Type::Exists(
+            span.clone(),
+            param.clone(),
+            Box::new(inner(then)),
+        )),
+        Type::Forall(span, param, then) => logged(Type::Forall(
+            span.clone(),
+            param.clone(),
+            Box::new(inner(then)),
+        )),
+        Type::Break(_) | Type::Continue(_) => logged(typ.clone()),
+        Type::Recursive {
+            span,
+            asc,
+            label,
+            body,
+            display_hint,
+        } => Type::Recursive {
+            span: span.clone(),
+            asc: asc.clone(),
+            label: label.clone(),
+            body: Box::new(inner(body)),
+            display_hint: display_hint.clone(),
+        },
+        Type::Iterative {
+            span,
+            asc,
+            label,
+            body,
+            display_hint,
+        } => Type::Iterative {
+            span: span.clone(),
+            asc: asc.clone(),
+            label: label.clone(),
+            body: Box::new(instrument_step(function, body, Some(label))),
+            display_hint: display_hint.clone(),
+        },
+        Type::Union(..) if typ.as_open_row().is_some() => typ.clone(),
+        Type::Name(span, ..) | Type::DualName(span, ..) | Type::Union(span, ..) => {
+            Type::Name(span.clone(), function.clone(), vec![typ.clone()])
+        }
+        Type::Primitive(..)
+        | Type::DualPrimitive(..)
+        | Type::Var(..)
+        | Type::DualVar(..)
+        | Type::Box(..)
+        | Type::DualBox(..)
+        | Type::Self_(..)
+        | Type::DualSelf(..)
+        | Type::Hole(..)
+        | Type::DualHole(..)
+        | Type::Fail(..) => typ.clone(),
+    }
+}
+
+/// `choice { .log(String) => again, .step => step }`
+fn log_choice<S: Clone>(again: Type<S>, step: Type<S>) -> Type<S> {
+    Type::choice(vec![
+        ("log", Type::function(Type::string(), again)),
+        ("step", step),
+    ])
+}
@@ -1,31 +1,59 @@
+use crate::frontend_impl::language::LocalName;
 use crate::frontend_impl::types::{PrimitiveType, Type, TypeDefs, TypeError};
 use crate::location::Span;
 use std::collections::BTreeMap;
 
+const PRIMITIVES: [PrimitiveType; 7] = [
+    PrimitiveType::Nat,
+    PrimitiveType::Int,
+    PrimitiveType::Float,
+    PrimitiveType::String,
+    PrimitiveType::Char,
+    PrimitiveType::Byte,
+    PrimitiveType::Bytes,
+];
+
+/// The least primitive type that both are subtypes of, like `Bytes` for
+/// `Char` and `Byte`.
 pub(crate) fn union_primitives<S: Clone + Eq + std::hash::Hash>(
     p1: &PrimitiveType,
     p2: &PrimitiveType,
 ) -> Option<PrimitiveType> {
-    if Type::<S>::is_primitive_subtype(p1, p2) {
-        Some(p2.clone())
-    } else if Type::<S>::is_primitive_subtype(p2, p1) {
-        Some(p1.clone())
-    } else {
-        None
-    }
+    let upper_bounds = PRIMITIVES
+        .iter()
+        .filter(|p| {
+            Type::<S>::is_primitive_subtype(p1, p) && Type::<S>::is_primitive_subtype(p2, p)
+        })
+        .collect::<Vec<_>>();
+    upper_bounds
+        .iter()
+        .find(|p| {
+            upper_bounds
+                .iter()
+                .all(|bound| Type::<S>::is_primitive_subtype(p, bound))
+        })
+        .map(|p| (*p).clone())
 }
 
+/// The greatest primitive type that is a subtype of both.
 pub(crate) fn intersect_primitives<S: Clone + Eq + std::hash::Hash>(
     p1: &PrimitiveType,
     p2: &PrimitiveType,
 ) -> Option<PrimitiveType> {
-    if Type::<S>::is_primitive_subtype(p1, p2) {
-        Some(p1.clone())
-    } else if Type::<S>::is_primitive_subtype(p2, p1) {
-        Some(p2.clone())
-    } else {
-        None
-    }
+    let lower_bounds = PRIMITIVES
+        .iter()
+        .filter(|p| {
+            Type::<S>::is_primitive_subtype(p, p1) && Type::<S>::is_primitive_subtype(p, p2)
+        })
+        .collect::<Vec<_>>();
+    lower_bounds
+        .iter()
+        .find(|p| {
+            lower_bounds
+                .iter()
+                .all(|bound| Type::<S>::is_primitive_subtype(bound, p))
+        })
+        .map(|p| (*p).clone())
 }
 
 pub fn union_types<S: Clone + Eq + std::hash::Hash>(
@@ -83,6 +111,12 @@ fn union_types_structural<S: Clone + Eq + std::hash::Hash>(
         (t1, Type::DualName(span2, name2, args2)) => {
             union_types(typedefs, span, t1, &typedefs.get_dual(span2, name2, args2)?)
         }
+        (t1 @ Type::Union(..), t2) if t1.as_open_row().is_none() => {
+            union_types(typedefs, span, &t1.expand_definition(typedefs)?, t2)
+        }
+        (t1, t2 @ Type::Union(..)) if t2.as_open_row().is_none() => {
+            union_types(typedefs, span, t1, &t2.expand_definition(typedefs)?)
+        }
         (t1, t2) => union_types_atoms(typedefs, span, t1, t2),
     }
 }
@@ -110,7 +144,7 @@ fn union_types_atoms<S: Clone + Eq + std::hash::Hash>(
         )),
         (Type::Break(_), Type::Break(_)) => Ok(Type::Break(span.clone())),
         (Type::Continue(_), Type::Continue(_)) => Ok(Type::Continue(span.clone())),
-        (Type::Primitive(_, p1), Type::Primitive(_, p2)) if p1 == p2 => {
+        (Type::Primitive(_, p1), Type::Primitive(_, p2)) => {
             let Some(p) = union_primitives::<S>(p1, p2) else {
                 return Err(TypeError::TypesCannotBeUnified(
                     span.clone(),
@@ -120,7 +154,7 @@ fn union_types_atoms<S: Clone + Eq + std::hash::Hash>(
             };
             Ok(Type::Primitive(span.clone(), p))
         }
-        (Type::DualPrimitive(_, p1), Type::DualPrimitive(_, p2)) if p1 == p2 => {
+        (Type::DualPrimitive(_, p1), Type::DualPrimitive(_, p2)) => {
             let Some(p) = intersect_primitives::<S>(p1, p2) else {
                 return Err(TypeError::TypesCannotBeUnified(
                     span.clone(),
@@ -176,7 +210,9 @@ fn union_types_branching<S: Clone + Eq + std::hash::Hash>(
             let mut new_branches = branches1.clone();
             for (name, typ2) in branches2 {
                 if let Some(typ1) = new_branches.get(name) {
-                    new_branches.insert(name.clone(), union_types(typedefs, span, typ1, typ2)?);
+                    let typ = union_types(typedefs, span, typ1, typ2)
+                        .map_err(|error| in_branch(error, name, typ1, typ2))?;
+                    new_branches.insert(name.clone(), typ);
                 } else {
                     new_branches.insert(name.clone(), typ2.clone());
                 }
@@ -187,7 +223,9 @@ fn union_types_branching<S: Clone + Eq + std::hash::Hash>(
             let mut new_branches = BTreeMap::new();
             for (name, typ1) in branches1 {
                 if let Some(typ2) = branches2.get(name) {
-                    new_branches.insert(name.clone(), union_types(typedefs, span, typ1, typ2)?);
+                    let typ = union_types(typedefs, span, typ1, typ2)
+                        .map_err(|error| in_branch(error, name, typ1, typ2))?;
+                    new_branches.insert(name.clone(), typ);
                 }
             }
             Ok(Type::Choice(span.clone(), new_branches))
@@ -224,6 +262,14 @@ fn union_types_branching<S: Clone + Eq + std::hash::Hash>(
                 )]))?,
             )?),
         )),
+        // A fixpoint against a type that isn't one is unfolded, so that the
+        // branches of its body can be merged with the other type's.
+        (t1, t2) if t1.is_fixpoint() && !t2.is_fixpoint() => {
+            union_types(typedefs, span, &t1.expand_fixpoint_unfounded(typedefs)?, t2)
+        }
+        (t1, t2) if t2.is_fixpoint() && !t1.is_fixpoint() => {
+            union_types(typedefs, span, t1, &t2.expand_fixpoint_unfounded(typedefs)?)
+        }
         (Type::Box(_, t1), t2) => union_types(typedefs, span, t1, t2),
         (t1, Type::Box(_, t2)) => union_types(typedefs, span, t1, t2),
         (t1, t2) => Err(TypeError::TypesCannotBeUnified(
@@ -257,6 +303,12 @@ fn intersect_types_structural<S: Clone + Eq + std::hash::Hash>(
         (t1, Type::DualName(span2, name2, args2)) => {
             intersect_types(typedefs, span, t1, &typedefs.get_dual(span2, name2, args2)?)
         }
+        (t1 @ Type::Union(..), t2) if t1.as_open_row().is_none() => {
+            intersect_types(typedefs, span, &t1.expand_definition(typedefs)?, t2)
+        }
+        (t1, t2 @ Type::Union(..)) if t2.as_open_row().is_none() => {
+            intersect_types(typedefs, span, t1, &t2.expand_definition(typedefs)?)
+        }
         (t1, t2) => intersect_types_atoms(typedefs, span, t1, t2),
     }
 }
@@ -284,7 +336,7 @@ fn intersect_types_atoms<S: Clone + Eq + std::hash::Hash>(
         )),
         (Type::Break(_), Type::Break(_)) => Ok(Type::Break(span.clone())),
         (Type::Continue(_), Type::Continue(_)) => Ok(Type::Continue(span.clone())),
-        (Type::Primitive(_, p1), Type::Primitive(_, p2)) if p1 == p2 => {
+        (Type::Primitive(_, p1), Type::Primitive(_, p2)) => {
             let Some(p) = intersect_primitives::<S>(p1, p2) else {
                 return Err(TypeError::TypesCannotBeUnified(
                     span.clone(),
@@ -294,7 +346,7 @@ fn intersect_types_atoms<S: Clone + Eq + std::hash::Hash>(
             };
             Ok(Type::Primitive(span.clone(), p))
         }
-        (Type::DualPrimitive(_, p1), Type::DualPrimitive(_, p2)) if p1 == p2 => {
+        (Type::DualPrimitive(_, p1), Type::DualPrimitive(_, p2)) => {
             let Some(p) = union_primitives::<S>(p1, p2) else {
                 return Err(TypeError::TypesCannotBeUnified(
                     span.clone(),
@@ -350,7 +402,9 @@ fn intersect_types_branching<S: Clone + Eq + std::hash::Hash>(
             let mut new_branches = BTreeMap::new();
             for (name, typ1) in branches1 {
                 if let Some(typ2) = branches2.get(name) {
-                    new_branches.insert(name.clone(), intersect_types(typedefs, span, typ1, typ2)?);
+                    let typ = intersect_types(typedefs, span, typ1, typ2)
+                        .map_err(|error| in_branch(error, name, typ1, typ2))?;
+                    new_branches.insert(name.clone(), typ);
                 }
             }
             Ok(Type::Either(span.clone(), new_branches))
@@ -359,7 +413,9 @@ fn intersect_types_branching<S: Clone + Eq + std::hash::Hash>(
             let mut new_branches = branches1.clone();
             for (name, typ2) in branches2 {
                 if let Some(typ1) = new_branches.get(name) {
-                    new_branches.insert(name.clone(), intersect_types(typedefs, span, typ1, typ2)?);
+                    let typ = intersect_types(typedefs, span, typ1, typ2)
+                        .map_err(|error| in_branch(error, name, typ1, typ2))?;
+                    new_branches.insert(name.clone(), typ);
                 } else {
                     new_branches.insert(name.clone(), typ2.clone());
                 }
@@ -398,6 +454,14 @@ fn intersect_types_branching<S: Clone + Eq + std::hash::Hash>(
                 )]))?,
             )?),
         )),
+        // A fixpoint against a type that isn't one is unfolded, so that the
+        // branches of its body can be merged with the other type's.
+        (t1, t2) if t1.is_fixpoint() && !t2.is_fixpoint() => {
+            intersect_types(typedefs, span, &t1.expand_fixpoint_unfounded(typedefs)?, t2)
+        }
+        (t1, t2) if t2.is_fixpoint() && !t1.is_fixpoint() => {
+            intersect_types(typedefs, span, t1, &t2.expand_fixpoint_unfounded(typedefs)?)
+        }
         (Type::Box(_, t1), t2) => intersect_types(typedefs, span, t1, t2),
         (t1, Type::Box(_, t2)) => intersect_types(typedefs, span, t1, t2),
         (t1, t2) => Err(TypeError::TypesCannotBeUnified(
@@ -407,3 +471,22 @@ fn intersect_types_branching<S: Clone + Eq + std::hash::Hash>(
         )),
     }
 }
+
+/// Points an error about two types that can't be unified at the branch they
+/// came from, unless it already points at a branch nested deeper.
+fn in_branch<S: Clone>(
+    error: TypeError<S>,
+    branch: &LocalName,
+    type1: &Type<S>,
+    type2: &Type<S>,
+) -> TypeError<S> {
+    match error {
+        TypeError::TypesCannotBeUnified(span, _, _) => TypeError::BranchTypesCannotBeUnified(
+            span,
+            branch.clone(),
+            type1.clone(),
+            type2.clone(),
+        ),
+        error => error,
+    }
+}
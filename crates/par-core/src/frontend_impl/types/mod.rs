@@ -1,6 +1,6 @@
 pub(crate) mod core;
 pub(crate) use core::LoopId;
-pub use core::{Operation, PrimitiveType, Type};
+pub use core::{Operation, PrimitiveType, Type, UnionKind};
 
 pub(crate) mod visibility;
 pub(crate) use visibility::{Visibility, VisibilityIndex, validate_visibility};
@@ -9,18 +9,22 @@ pub(crate) mod error;
 pub use error::TypeError;
 
 pub(crate) mod definitions;
-pub use definitions::TypeDefs;
+pub use definitions::{ExpansionLimit, TypeDefs};
 pub(crate) mod assignability;
 pub(crate) mod checking;
 pub(crate) mod context;
 pub(crate) use context::Context;
 pub(crate) mod dependencies;
 pub(crate) mod display;
-pub use display::GlobalNameWriter;
+pub use display::{GlobalNameWriter, TypeTruncation, set_full_types_in_reports};
 pub(crate) mod duality;
 pub(crate) mod expansion;
+pub(crate) mod functions;
+pub use functions::TypeFunction;
 mod implicit;
 pub(crate) mod lattice;
+pub(crate) mod protocol;
+pub use protocol::ProtocolStep;
 pub(crate) mod registry;
 pub(crate) mod substitution;
 pub(crate) mod tests;
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use crate::frontend_impl::language::{LocalName, TypeParameter};
+use crate::frontend_impl::types::{Type, TypeDefs};
+
+/// What happens next on a channel of some type, seen from the side that
+/// holds a value of it. See [`Type::protocol_step`].
+#[derive(Clone, Debug)]
+pub enum ProtocolStep<S> {
+    /// `either`: the value picks one of the branches.
+    Signal(BTreeMap<LocalName, Type<S>>),
+    /// `choice`: whoever holds the value picks one of the branches.
+    Choose(BTreeMap<LocalName, Type<S>>),
+    /// `(A) B`: the value sends an `A`, then goes on as a `B`.
+    Send(Type<S>, Type<S>),
+    /// `[A] B`: the value receives an `A`, then goes on as a `B`.
+    Receive(Type<S>, Type<S>),
+    /// `(type T) B`: the value sends a type, then goes on as a `B`.
+    SendType(TypeParameter, Type<S>),
+    /// `[type T] B`: the value receives a type, then goes on as a `B`.
+    ReceiveType(TypeParameter, Type<S>),
+    /// `!`: the value closes.
+    Close,
+    /// `?`: whoever holds the value closes it.
+    Wait,
+    /// A primitive, a type variable, or anything else without steps of its
+    /// own, such as a name that failed to expand.
+    Opaque(Type<S>),
+}
+
+impl<S: Clone + Eq + std::hash::Hash> Type<S> {
+    /// The first step of the protocol of this type, unfolding names,
+    /// fixpoints and boxes until it shows. Walking a protocol step by step
+    /// takes the type each step goes on as, and asks again.
+    pub fn protocol_step(&self, type_defs: &TypeDefs<S>) -> ProtocolStep<S> {
+        let mut typ = self.clone();
+        // Like in `either_branches`, a name can't come up twice in a row
+        // without its definition being rejected as cyclic.
+        for _ in 0..2 * (type_defs.globals.len() + 1) {
+            let expanded = match &typ {
+                Self::Either(_, branches) => return ProtocolStep::Signal(branches.clone()),
+                Self::Choice(_, branches) => return ProtocolStep::Choose(branches.clone()),
+                Self::Pair(_, value, then, _) => {
+                    return ProtocolStep::Send((**value).clone(), (**then).clone());
+                }
+                Self::Function(_, argument, then, _) => {
+                    return ProtocolStep::Receive((**argument).clone(), (**then).clone());
+                }
+                Self::Exists(_, parameter, then) => {
+                    return ProtocolStep::SendType(parameter.clone(), (**then).clone());
+                }
+                Self::Forall(_, parameter, then) => {
+                    return ProtocolStep::ReceiveType(parameter.clone(), (**then).clone());
+                }
+                Self::Break(_) => return ProtocolStep::Close,
+                Self::Continue(_) => return ProtocolStep::Wait,
+                Self::Box(_, inner) | Self::DualBox(_, inner) => Ok((**inner).clone()),
+                Self::Name(..) | Self::DualName(..) | Self::Union(..) => {
+                    typ.expand_definition(type_defs)
+                }
+                Self::Recursive { .. } | Self::Iterative { .. } => {
+                    typ.expand_fixpoint_unfounded(type_defs)
+                }
+                _ => break,
+            };
+            match expanded {
+                Ok(expanded) => typ = expanded,
+                Err(_) => break,
+            }
+        }
+        ProtocolStep::Opaque(typ)
+    }
+}
@@ -1,5 +1,5 @@
-use crate::frontend::Type;
 use crate::frontend::language::Unresolved;
+use crate::frontend::{Type, TypeFunction};
 use par_runtime::registry::{DefinitionRef, PackageRef};
 use std::collections::HashMap;
 use std::sync::LazyLock;
@@ -13,18 +13,46 @@ pub struct ExternalTypeDef {
 
 inventory::collect!(ExternalTypeDef);
 
-type Registry = HashMap<PackageRef<'static>, Vec<&'static ExternalTypeDef>>;
+/// A type built into the compiler, computed from its arguments by `function`.
+#[derive(Clone)]
+pub struct ExternalTypeFunction {
+    pub path: DefinitionRef<'static>,
+    pub function: TypeFunction,
+    pub doc: &'static str,
+}
 
-static REGISTRY: LazyLock<Registry> = LazyLock::new(|| {
-    let mut map: Registry = HashMap::new();
+inventory::collect!(ExternalTypeFunction);
+
+type Registry<T> = HashMap<PackageRef<'static>, Vec<&'static T>>;
+
+static REGISTRY: LazyLock<Registry<ExternalTypeDef>> = LazyLock::new(|| {
+    let mut map: Registry<ExternalTypeDef> = HashMap::new();
     for def in inventory::iter::<ExternalTypeDef> {
         map.entry(def.path.package).or_default().push(def);
     }
     map
 });
 
+static FUNCTION_REGISTRY: LazyLock<Registry<ExternalTypeFunction>> = LazyLock::new(|| {
+    let mut map: Registry<ExternalTypeFunction> = HashMap::new();
+    for def in inventory::iter::<ExternalTypeFunction> {
+        map.entry(def.path.package).or_default().push(def);
+    }
+    map
+});
+
 pub fn get_external_type_defs(
     package: PackageRef,
 ) -> impl Iterator<Item = &'static ExternalTypeDef> {
     REGISTRY.get(&package).into_iter().flatten().copied()
 }
+
+pub fn get_external_type_functions(
+    package: PackageRef,
+) -> impl Iterator<Item = &'static ExternalTypeFunction> {
+    FUNCTION_REGISTRY
+        .get(&package)
+        .into_iter()
+        .flatten()
+        .copied()
+}
@@ -37,11 +37,34 @@ impl<S: Clone> Type<S> {
                     inner(body, &map)?
                 }
                 Type::Recursive {
-                    body, display_hint, ..
+                    label,
+                    body,
+                    display_hint,
+                    ..
                 }
                 | Type::Iterative {
-                    body, display_hint, ..
+                    label,
+                    body,
+                    display_hint,
+                    ..
                 } => {
+                    // A `self` in a substituted type belongs to a `recursive` or
+                    // an `iterative` around the type variable, so this one is
+                    // relabeled not to capture it.
+                    let old_label = label.clone();
+                    while map.values().any(|t| t.contains_self(label)) {
+                        let name = match label {
+                            Some(name) => arcstr::format!("{}'", name.string),
+                            None => arcstr::literal!("self'"),
+                        };
+                        *label = Some(LocalName {
+                            span: Span::None,
+                            string: name,
+                        });
+                    }
+                    if old_label != *label {
+                        body.relabel_self(&old_label, label);
+                    }
                     inner(body, map)?;
                     if let Some(display_hint) = display_hint.0.as_mut() {
                         for arg in &mut display_hint.args {
@@ -61,6 +84,48 @@ impl<S: Clone> Type<S> {
         Ok(typ)
     }
 
+    /// Whether a `self` with this label is free in the type.
+    pub fn contains_self(&self, target_label: &Option<LocalName>) -> bool {
+        fn inner<S>(typ: &Type<S>, target_label: &Option<LocalName>) -> Result<(), ()> {
+            match typ {
+                Type::Self_(_, label) | Type::DualSelf(_, label) if label == target_label => {
+                    Err(())
+                }
+                Type::Recursive { label, .. } | Type::Iterative { label, .. }
+                    if label == target_label =>
+                {
+                    // label is shadowed
+                    Ok(())
+                }
+                _ => visit::continue_(typ, |child| inner(child, target_label)),
+            }
+        }
+        inner(self, target_label).is_err()
+    }
+
+    /// Changes the label of every free `self` with the label `from` to `to`.
+    fn relabel_self(&mut self, from: &Option<LocalName>, to: &Option<LocalName>) {
+        fn inner<S>(
+            typ: &mut Type<S>,
+            from: &Option<LocalName>,
+            to: &Option<LocalName>,
+        ) -> Result<(), ()> {
+            match typ {
+                Type::Self_(_, label) | Type::DualSelf(_, label) if label == from => {
+                    *label = to.clone();
+                }
+                Type::Recursive { label, .. } | Type::Iterative { label, .. } if label == from => {
+                    // label is shadowed
+                }
+                _ => {
+                    visit::continue_mut(typ, |child: &mut Type<S>| inner(child, from, to))?;
+                }
+            }
+            Ok(())
+        }
+        inner(self, from, to).unwrap();
+    }
+
     pub fn contains_var(&self, var: &LocalName) -> bool {
         fn inner<S>(result: &mut bool, typ: &Type<S>, target_name: &LocalName) -> Result<(), ()> {
             match typ {
@@ -1,12 +1,22 @@
 #[cfg(test)]
 mod tests {
-    use crate::frontend_impl::language::{GlobalName, LocalName, TypeParameter, Universal};
-    use crate::frontend_impl::types::{GlobalNameWriter, Type, TypeDefs};
-    use crate::location::Span;
+    use crate::frontend_impl::codes::explain_code;
+    use crate::frontend_impl::language::{
+        GlobalName, LocalName, TypeConstraint, TypeParameter, Universal,
+    };
+    use crate::frontend_impl::types::display::TypeRenderOptions;
+    use crate::frontend_impl::types::lattice::union_types;
+    use crate::frontend_impl::types::{
+        ExpansionLimit, GlobalNameWriter, LoopId, Operation, PrimitiveType, ProtocolStep, Type,
+        TypeDefs, TypeError, TypeFunction, TypeTruncation, UnionKind, Visibility,
+    };
+    use crate::location::{FileName, Point, Span};
     use crate::workspace::render_type_in_scope;
     use arcstr::{ArcStr, literal};
     use par_runtime::pkgid::PackageId;
+    use std::collections::HashMap;
     use std::fmt::{self, Write};
+    use std::sync::Arc;
 
     struct TestNameWriter;
 
@@ -61,6 +71,284 @@ mod tests {
         (defs, map_name)
     }
 
+    fn test_name(primary: &str) -> GlobalName<Universal> {
+        GlobalName::new(
+            Span::None,
+            Universal {
+                package: PackageId::Special(literal!("__test__")),
+                directories: vec![],
+                module: "Main".to_string(),
+            },
+            primary.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_cyclic_aliases_are_reported_without_expanding() {
+        let span = Span::None;
+        let (a, b) = (test_name("A"), test_name("B"));
+        let a_body = Type::Name(Span::None, b.clone(), vec![]);
+        let b_body = Type::Name(Span::None, a.clone(), vec![]);
+        let (_, errors) = TypeDefs::new_with_validation(
+            [(&span, &a, &vec![], &a_body), (&span, &b, &vec![], &b_body)].into_iter(),
+        );
+
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error, TypeError::DependencyCycle(..))),
+            "errors: {errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_guarded_mutually_recursive_types_are_accepted() {
+        let span = Span::None;
+        let (tree, forest) = (test_name("Tree"), test_name("Forest"));
+        let tree_body = Type::recursive(
+            None,
+            Type::either(vec![
+                ("leaf", Type::break_()),
+                (
+                    "node",
+                    Type::pair(
+                        Type::Name(Span::None, forest.clone(), vec![]),
+                        Type::break_(),
+                    ),
+                ),
+            ]),
+        );
+        let forest_body = Type::recursive(
+            None,
+            Type::either(vec![
+                ("end", Type::break_()),
+                (
+                    "item",
+                    Type::pair(
+                        Type::Name(Span::None, tree.clone(), vec![]),
+                        Type::self_(None),
+                    ),
+                ),
+            ]),
+        );
+        let (defs, errors) = TypeDefs::new_with_validation(
+            [
+                (&span, &tree, &vec![], &tree_body),
+                (&span, &forest, &vec![], &forest_body),
+            ]
+            .into_iter(),
+        );
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let tree_type = Type::Name(Span::None, tree, vec![]);
+        assert!(tree_type.size(&defs).is_ok());
+        assert!(!tree_type.is_linear(&defs).unwrap());
+    }
+
+    #[test]
+    fn test_mutually_recursive_types_must_refer_to_each_other_positively() {
+        let span = Span::None;
+        let (a, b) = (test_name("A"), test_name("B"));
+        let a_body = Type::recursive(
+            None,
+            Type::either(vec![(
+                "a",
+                Type::function(Type::Name(Span::None, b.clone(), vec![]), Type::break_()),
+            )]),
+        );
+        let b_body = Type::recursive(
+            None,
+            Type::either(vec![("b", Type::Name(Span::None, a.clone(), vec![]))]),
+        );
+        let (_, errors) = TypeDefs::new_with_validation(
+            [(&span, &a, &vec![], &a_body), (&span, &b, &vec![], &b_body)].into_iter(),
+        );
+
+        assert!(
+            !errors.is_empty()
+                && errors
+                    .iter()
+                    .all(|error| matches!(error, TypeError::DependencyCycle(..))),
+            "errors: {errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_unused_type_parameters_are_warned_about() {
+        let span = Span::None;
+        let name = test_name("Box");
+        let used = LocalName {
+            span: Span::None,
+            string: ArcStr::from("a"),
+        };
+        let unused = LocalName {
+            span: Span::None,
+            string: ArcStr::from("b"),
+        };
+        let params = vec![
+            TypeParameter::any(used.clone()),
+            TypeParameter::any(unused.clone()),
+        ];
+        let body = Type::Pair(
+            Span::None,
+            Box::new(Type::Var(Span::None, used)),
+            Box::new(Type::break_()),
+            vec![],
+        );
+        let (defs, errors) =
+            TypeDefs::new_with_validation([(&span, &name, &params, &body)].into_iter());
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let warnings = defs.unused_type_parameters();
+        assert!(
+            matches!(
+                warnings.as_slice(),
+                [warning @ TypeError::UnusedTypeParameter(_, n, p)]
+                    if n == &name && p == &unused && warning.is_warning()
+            ),
+            "warnings: {warnings:?}"
+        );
+    }
+
+    #[test]
+    fn test_runaway_expansion_reports_alias_chain() {
+        let (a, b) = (test_name("A"), test_name("B"));
+        let defs = TypeDefs {
+            globals: std::sync::Arc::new(
+                [
+                    (
+                        a.clone(),
+                        (
+                            Span::None,
+                            vec![],
+                            Type::Name(Span::None, b.clone(), vec![]),
+                        ),
+                    ),
+                    (
+                        b.clone(),
+                        (
+                            Span::None,
+                            vec![],
+                            Type::Name(Span::None, a.clone(), vec![]),
+                        ),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            vars: Default::default(),
+            functions: Default::default(),
+            expansion_limit: Default::default(),
+        };
+
+        // Expanding all the way to the limit takes more stack than a test
+        // thread has in debug builds.
+        let start = a.clone();
+        let result = std::thread::Builder::new()
+            .stack_size(32 * 1024 * 1024)
+            .spawn(move || defs.validate_type(&Type::Name(Span::None, start, vec![])))
+            .unwrap()
+            .join()
+            .unwrap();
+        match result {
+            Err(TypeError::TypeExpansionTooDeep(_, chain)) => {
+                let names: Vec<_> = chain.into_iter().map(|(name, _)| name).collect();
+                assert_eq!(names, vec![a.clone(), b, a]);
+            }
+            other => panic!("expected a runaway expansion, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_checker_gives_up_on_runaway_expansion() {
+        use crate::frontend_impl::types::context::Context;
+
+        let a = test_name("A");
+        let x = LocalName {
+            span: Span::None,
+            string: ArcStr::from("x"),
+        };
+        // type A<x> = A<(x) !>, which grows on every expansion. Validation would
+        // reject it, but the checker must not hang on it anyway.
+        let body = Type::Name(
+            Span::None,
+            a.clone(),
+            vec![Type::pair(Type::var("x"), Type::break_())],
+        );
+        let defs = TypeDefs {
+            globals: std::sync::Arc::new(
+                [(a.clone(), (Span::None, vec![TypeParameter::any(x)], body))]
+                    .into_iter()
+                    .collect(),
+            ),
+            vars: Default::default(),
+            functions: Default::default(),
+            expansion_limit: ExpansionLimit {
+                depth: 8,
+                ..Default::default()
+            },
+        };
+        let context = Context::new(defs, Default::default(), Default::default());
+
+        let mut errors = Vec::new();
+        let typ = Type::Name(Span::None, a.clone(), vec![Type::break_()]);
+        let expanded =
+            context.expand_definitions(&Span::None, typ, &mut |error| errors.push(error));
+
+        assert!(matches!(expanded, Type::Fail(_)));
+        let [TypeError::TypeExpansionTooDeep(_, chain)] = &errors[..] else {
+            panic!("errors: {errors:?}");
+        };
+        let names: Vec<_> = chain.iter().map(|(name, _)| name.clone()).collect();
+        assert_eq!(names, vec![a.clone(), a]);
+    }
+
+    #[test]
+    fn test_unfolding_past_the_size_limit_is_reported() {
+        // recursive either { .end !, .more (self) self }
+        let typ = Type::recursive(
+            None,
+            Type::either(vec![
+                ("end", Type::break_()),
+                ("more", Type::pair(Type::self_(None), Type::self_(None))),
+            ]),
+        );
+        let roomy = TypeDefs::<Universal>::default();
+        assert!(typ.expand_fixpoint(&roomy).is_ok());
+
+        let tight = TypeDefs::<Universal> {
+            expansion_limit: ExpansionLimit {
+                size: 8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            typ.expand_fixpoint(&tight),
+            Err(TypeError::TypeExpansionTooDeep(_, chain)) if chain.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_negative_self_reports_polarity_path() {
+        // recursive either { .f [self] ! }
+        let typ = Type::recursive(
+            None,
+            Type::either(vec![(
+                "f",
+                Type::function(Type::self_(None), Type::break_()),
+            )]),
+        );
+
+        match TypeDefs::<Universal>::default().validate_type(&typ) {
+            Err(TypeError::SelfUsedInNegativePosition(_, _, flips)) => {
+                let reasons: Vec<_> = flips.into_iter().map(|(_, reason)| reason).collect();
+                assert_eq!(reasons, vec!["function argument"]);
+            }
+            other => panic!("expected a negative self-reference, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_iterative_box_choice() {
         let typ: Type<Universal> = Type::iterative_box_choice(
@@ -154,6 +442,266 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_union_unfolds_recursive_types_to_merge_branches() {
+        let type_defs: TypeDefs<Universal> = TypeDefs::default();
+        let list = Type::recursive(
+            None,
+            Type::either(vec![
+                ("end", Type::break_()),
+                ("item", Type::pair(Type::nat(), Type::self_(None))),
+            ]),
+        );
+        let stop = Type::either(vec![("stop", Type::break_())]);
+
+        let Type::Either(_, branches) = union_types(&type_defs, &Span::None, &list, &stop).unwrap()
+        else {
+            panic!("expected an either");
+        };
+        assert_eq!(
+            branches
+                .keys()
+                .map(|branch| branch.string.as_str())
+                .collect::<Vec<_>>(),
+            ["end", "item", "stop"]
+        );
+    }
+
+    #[test]
+    fn test_union_of_unrelated_primitives_is_their_least_upper_bound() {
+        let type_defs: TypeDefs<Universal> = TypeDefs::default();
+        let union = union_types(&type_defs, &Span::None, &Type::char(), &Type::byte()).unwrap();
+        assert!(matches!(union, Type::Primitive(_, PrimitiveType::Bytes)));
+        assert!(union_types(&type_defs, &Span::None, &Type::nat(), &Type::float()).is_err());
+    }
+
+    #[test]
+    fn test_union_error_names_the_conflicting_branch() {
+        let type_defs: TypeDefs<Universal> = TypeDefs::default();
+        let left = Type::either(vec![("a", Type::nat()), ("b", Type::break_())]);
+        let right = Type::either(vec![("a", Type::float())]);
+
+        let error = union_types(&type_defs, &Span::None, &left, &right).unwrap_err();
+        let TypeError::BranchTypesCannotBeUnified(_, branch, _, _) = error else {
+            panic!("expected a branch error, got {error:?}");
+        };
+        assert_eq!(branch.string.as_str(), "a");
+    }
+
+    #[test]
+    fn test_union_operator_merges_branches_of_named_eithers() {
+        let span = Span::None;
+        let (basic, extra) = (test_name("Basic"), test_name("Extra"));
+        let basic_body = Type::either(vec![("a", Type::break_()), ("b", Type::break_())]);
+        let extra_body = Type::either(vec![("c", Type::int())]);
+        let (defs, errors) = TypeDefs::new_with_validation(
+            [
+                (&span, &basic, &vec![], &basic_body),
+                (&span, &extra, &vec![], &extra_body),
+            ]
+            .into_iter(),
+        );
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let named = |name: &GlobalName<Universal>| Type::Name(Span::None, name.clone(), vec![]);
+        let full = Type::Union(
+            Span::None,
+            UnionKind::Either,
+            Box::new(named(&basic)),
+            Box::new(named(&extra)),
+        );
+        let Type::Either(_, branches) = full.expand_definition(&defs).unwrap() else {
+            panic!("expected an either");
+        };
+        let labels: Vec<_> = branches.keys().map(|label| label.to_string()).collect();
+        assert_eq!(labels, ["a", "b", "c"]);
+
+        let overlapping = Type::Union(
+            Span::None,
+            UnionKind::Either,
+            Box::new(full),
+            Box::new(named(&basic)),
+        );
+        assert!(matches!(
+            overlapping.expand_definition(&defs),
+            Err(TypeError::OverlappingUnionLabels(..))
+        ));
+
+        let mixed = Type::Union(
+            Span::None,
+            UnionKind::Choice,
+            Box::new(named(&basic)),
+            Box::new(Type::choice(vec![("d", Type::break_())])),
+        );
+        assert!(matches!(
+            mixed.expand_definition(&defs),
+            Err(TypeError::InvalidUnionOperand(_, UnionKind::Choice, _))
+        ));
+    }
+
+    #[test]
+    fn test_open_rows_gain_or_lose_only_known_branches() {
+        let type_defs: TypeDefs<Universal> = TypeDefs::default();
+        let row = |kind, known: Type<Universal>, rest: &'static str| {
+            Type::Union(Span::None, kind, Box::new(known), Box::new(Type::var(rest)))
+        };
+        let assignable = |t: &Type<Universal>, u: &Type<Universal>| {
+            t.is_definitely_assignable_to(u, &type_defs).unwrap()
+        };
+
+        let a = row(
+            UnionKind::Either,
+            Type::either(vec![("a", Type::break_())]),
+            "r",
+        );
+        assert!(
+            a.expand_definition(&type_defs)
+                .unwrap()
+                .as_open_row()
+                .is_some()
+        );
+        let ab = row(
+            UnionKind::Either,
+            Type::either(vec![("a", Type::break_()), ("b", Type::break_())]),
+            "r",
+        );
+        assert!(assignable(&Type::var("r"), &a));
+        assert!(assignable(&Type::either(vec![("a", Type::break_())]), &a));
+        assert!(assignable(&a, &ab));
+        assert!(!assignable(&ab, &a));
+        assert!(!assignable(&a, &Type::var("r")));
+        assert!(!assignable(
+            &row(
+                UnionKind::Either,
+                Type::either(vec![("a", Type::break_())]),
+                "s"
+            ),
+            &a,
+        ));
+
+        let a = row(
+            UnionKind::Choice,
+            Type::choice(vec![("a", Type::break_())]),
+            "r",
+        );
+        let ab = row(
+            UnionKind::Choice,
+            Type::choice(vec![("a", Type::break_()), ("b", Type::break_())]),
+            "r",
+        );
+        assert!(assignable(&ab, &a));
+        assert!(!assignable(&a, &ab));
+        assert!(!assignable(&Type::choice(vec![("a", Type::break_())]), &a));
+    }
+
+    #[test]
+    fn test_instrument_logs_before_each_step() {
+        let span = Span::None;
+        let (instrument, counter) = (test_name("Instrument"), test_name("Counter"));
+        let instrument_params = vec![TypeParameter::any(LocalName::new("t"))];
+        let instrument_body = Type::var("t");
+        let counter_body = Type::iterative(
+            None,
+            Type::choice(vec![
+                ("incr", Type::self_(None)),
+                ("get", Type::pair(Type::int(), Type::break_())),
+            ]),
+        );
+        let (type_defs, errors) = TypeDefs::new_with_functions_and_validation(
+            [
+                (&span, &instrument, &instrument_params, &instrument_body),
+                (&span, &counter, &vec![], &counter_body),
+            ]
+            .into_iter(),
+            [(instrument.clone(), TypeFunction::Instrument)]
+                .into_iter()
+                .collect(),
+            ExpansionLimit::default(),
+        );
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let logged = |again: Type<Universal>, step: Type<Universal>| {
+            Type::choice(vec![
+                ("log", Type::function(Type::string(), again)),
+                ("step", step),
+            ])
+        };
+        let logged_alone = |step: Type<Universal>| {
+            Type::iterative(Some("log"), logged(Type::self_(Some("log")), step))
+        };
+        // Logging goes around the protocol's own `iterative`, and every step
+        // after `.get` gets a loop of its own.
+        let expected = Type::iterative(
+            None,
+            logged(
+                Type::self_(None),
+                Type::choice(vec![
+                    ("incr", Type::self_(None)),
+                    (
+                        "get",
+                        logged_alone(Type::pair(Type::int(), logged_alone(Type::break_()))),
+                    ),
+                ]),
+            ),
+        );
+        let instrumented = Type::Name(
+            Span::None,
+            instrument,
+            vec![Type::Name(Span::None, counter.clone(), vec![])],
+        );
+        assert!(
+            instrumented
+                .is_definitely_assignable_to(&expected, &type_defs)
+                .unwrap()
+        );
+        assert!(
+            expected
+                .is_definitely_assignable_to(&instrumented, &type_defs)
+                .unwrap()
+        );
+        assert!(
+            !Type::Name(Span::None, counter, vec![])
+                .is_definitely_assignable_to(&instrumented, &type_defs)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_self_in_type_argument_is_not_captured_by_the_definition() {
+        let span = Span::None;
+        let (stream, rose) = (test_name("Stream"), test_name("Rose"));
+        let stream_body = Type::recursive(
+            None,
+            Type::either(vec![
+                ("end", Type::break_()),
+                ("item", Type::pair(Type::var("a"), Type::self_(None))),
+            ]),
+        );
+        let rose_body = Type::recursive(
+            None,
+            Type::either(vec![(
+                "node",
+                Type::Name(Span::None, stream.clone(), vec![Type::self_(None)]),
+            )]),
+        );
+        let params = vec![TypeParameter::any(LocalName::from(literal!("a")))];
+        let (defs, errors) = TypeDefs::new_with_validation(
+            [
+                (&span, &stream, &params, &stream_body),
+                (&span, &rose, &vec![], &rose_body),
+            ]
+            .into_iter(),
+        );
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let forest = defs.get(&span, &stream, &[Type::self_(None)]).unwrap();
+        assert!(forest.contains_self(&None));
+        let Type::Recursive { label, .. } = &forest else {
+            panic!("expected a recursive type, got {forest:?}");
+        };
+        assert!(label.is_some());
+    }
+
     #[test]
     fn test_empty_either_subtype_of_any() {
         let type_defs: TypeDefs<Universal> = TypeDefs::default();
@@ -201,7 +749,7 @@ mod tests {
         let expanded = defs
             .get(&Span::None, &map_name, &[Type::string(), Type::int()])
             .unwrap()
-            .expand_fixpoint()
+            .expand_fixpoint(&defs)
             .unwrap();
         let mut actual = String::new();
         expanded
@@ -210,17 +758,115 @@ mod tests {
 
         assert_eq!(
             actual,
-            "choice {.delete => @__test__/Main.Map<String, Int>,.put => [Int] @__test__/Main.Map<String, Int>,}"
+            "choice {.delete => @__test__/Main.Map<String, Int>,.put(Int) => @__test__/Main.Map<String, Int>,}"
         );
     }
 
+    #[test]
+    fn test_unfold_steps_expands_one_layer_per_step() {
+        let (defs, map_name) = alias_preserving_type_defs();
+        let map = Type::Name(Span::None, map_name, vec![Type::string(), Type::int()]);
+        let render = |steps| {
+            let mut actual = String::new();
+            map.unfold_steps(&defs, steps)
+                .pretty_compact(&mut actual, &TestNameWriter)
+                .unwrap();
+            actual
+        };
+
+        let name = "@__test__/Main.Map<String, Int>";
+        let once = format!("choice {{.delete => {name},.put(Int) => {name},}}");
+        assert_eq!(render(0), name);
+        assert_eq!(render(1), once);
+        assert_eq!(render(2), once.replace(name, &once));
+    }
+
+    #[test]
+    fn test_protocol_steps_walk_through_names_and_fixpoints() {
+        let (defs, map_name) = alias_preserving_type_defs();
+        let map = Type::Name(Span::None, map_name, vec![Type::string(), Type::int()]);
+
+        let ProtocolStep::Choose(branches) = map.protocol_step(&defs) else {
+            panic!("a map is a choice");
+        };
+        let labels = branches.keys().map(|label| label.string.as_str());
+        assert_eq!(labels.collect::<Vec<_>>(), ["delete", "put"]);
+
+        let put = &branches[&LocalName::from(literal!("put"))];
+        let ProtocolStep::Receive(value, then) = put.protocol_step(&defs) else {
+            panic!("`.put` receives a value");
+        };
+        assert_eq!(value, Type::int());
+        assert!(matches!(then.protocol_step(&defs), ProtocolStep::Choose(_)));
+
+        assert!(matches!(
+            Type::<Universal>::break_().protocol_step(&defs),
+            ProtocolStep::Close
+        ));
+        assert!(matches!(
+            Type::int().protocol_step(&defs),
+            ProtocolStep::Opaque(Type::Primitive(..))
+        ));
+    }
+
+    #[test]
+    fn test_dual_alias_resolves_to_the_dual_fixpoint_under_its_own_name() {
+        let span = Span::None;
+        let server_name = test_name("Server");
+        let client_name = test_name("Client");
+        let server = Type::iterative(
+            None,
+            Type::choice(vec![
+                ("close", Type::break_()),
+                (
+                    "echo",
+                    Type::function(
+                        Type::string(),
+                        Type::pair(Type::string(), Type::self_(None)),
+                    ),
+                ),
+            ]),
+        );
+        let client = Type::DualName(Span::None, server_name.clone(), vec![]);
+        let (defs, errors) = TypeDefs::new_with_validation(
+            [
+                (&span, &server_name, &vec![], &server),
+                (&span, &client_name, &vec![], &client),
+            ]
+            .into_iter(),
+        );
+        assert!(errors.is_empty(), "errors: {errors:?}");
+
+        let client = defs.get(&span, &client_name, &[]).unwrap();
+        assert!(matches!(client, Type::Recursive { .. }));
+        let mut rendered = String::new();
+        client
+            .pretty_compact(&mut rendered, &TestNameWriter)
+            .unwrap();
+        assert_eq!(rendered, "@__test__/Main.Client");
+
+        let Type::Either(_, branches) = client.expand_fixpoint(&defs).unwrap() else {
+            panic!("expected an either");
+        };
+        let echo = &branches[&LocalName::from(literal!("echo"))];
+        let Type::Pair(_, _, rest, _) = echo else {
+            panic!("expected a pair, got {echo:?}");
+        };
+        let Type::Function(_, _, rest, _) = rest.as_ref() else {
+            panic!("expected a function, got {rest:?}");
+        };
+        let mut rendered = String::new();
+        rest.pretty_compact(&mut rendered, &TestNameWriter).unwrap();
+        assert_eq!(rendered, "@__test__/Main.Client");
+    }
+
     #[test]
     fn test_workspace_renderer_keeps_named_fixpoint_aliases_after_expansion() {
         let (defs, map_name) = alias_preserving_type_defs();
         let expanded = defs
             .get(&Span::None, &map_name, &[Type::string(), Type::int()])
             .unwrap()
-            .expand_fixpoint()
+            .expand_fixpoint(&defs)
             .unwrap();
 
         assert_eq!(
@@ -228,8 +874,448 @@ mod tests {
             "\
 choice {
   .delete => @__test__/Main.Map<String, Int>,
-  .put => [Int] @__test__/Main.Map<String, Int>,
+  .put(Int) => @__test__/Main.Map<String, Int>,
 }"
         );
     }
+
+    #[test]
+    fn test_truncated_types_elide_deep_wide_and_repeated_parts() {
+        let render = |typ: &Type<Universal>, truncation: TypeTruncation| {
+            let mut rendered = String::new();
+            typ.pretty_with_options(
+                &mut rendered,
+                &TestNameWriter,
+                TypeRenderOptions::pretty(0).with_truncation(truncation),
+            )
+            .unwrap();
+            rendered
+        };
+
+        let nested = Type::either(vec![("a", Type::either(vec![("b", Type::break_())]))]);
+        let deep = TypeTruncation {
+            max_depth: Some(0),
+            ..TypeTruncation::FULL
+        };
+        assert_eq!(render(&nested, deep), "either {\n  .a ...,\n}");
+        assert_eq!(
+            render(&nested, TypeTruncation::FULL),
+            "either {\n  .a either {\n    .b!,\n  },\n}"
+        );
+
+        let wide = Type::either(vec![
+            ("a", Type::break_()),
+            ("b", Type::break_()),
+            ("c", Type::break_()),
+        ]);
+        let narrow = TypeTruncation {
+            max_width: Some(2),
+            ..TypeTruncation::FULL
+        };
+        assert_eq!(render(&wide, narrow), "either {\n  .a!,\n  .b!,\n  ...\n}");
+
+        let list = Type::recursive(
+            None,
+            Type::either(vec![
+                ("end", Type::break_()),
+                (
+                    "item",
+                    Type::pair(Type::nat(), Type::Self_(Span::None, None)),
+                ),
+            ]),
+        );
+        let lists = Type::pair(list.clone(), list);
+        let elided = TypeTruncation {
+            elide_repeated_recursion: true,
+            ..TypeTruncation::FULL
+        };
+        assert_eq!(
+            render(&lists, elided),
+            "(recursive either {\n  .end!,\n  .item(Nat) self,\n}) recursive ..."
+        );
+    }
+
+    #[test]
+    fn test_errors_in_expanded_code_point_at_the_sugar() {
+        let source = "def x = if { a => b, else => c }";
+        let at = |start: u32, end: u32| Span::At {
+            start: Point {
+                offset: start,
+                row: 0,
+                column: start,
+            },
+            end: Point {
+                offset: end,
+                row: 0,
+                column: end,
+            },
+            file: FileName::from("Main.par"),
+            expansion: None,
+        };
+        let if_span = at(8, 33);
+        let error: TypeError<Universal> =
+            TypeError::NonExhaustiveIf(at(13, 19).expanded_from("`if`", &if_span));
+        assert_eq!(error.spans().0, at(13, 19));
+
+        let report = error.to_report(Arc::from(source), None);
+        let labels = report.labels().into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[1].label(), Some("in code expanded from this `if`"));
+        assert_eq!(labels[1].offset(), 8);
+        assert_eq!(
+            report.code().map(|code| code.to_string()).as_deref(),
+            Some("PAR0054")
+        );
+    }
+
+    #[test]
+    fn test_errors_in_builtin_files_name_the_file() {
+        let source = "def x = y";
+        let at = |file: &str| Span::At {
+            start: Point {
+                offset: 8,
+                row: 0,
+                column: 8,
+            },
+            end: Point {
+                offset: 9,
+                row: 0,
+                column: 9,
+            },
+            file: FileName::from(file),
+            expansion: None,
+        };
+        let name_in_report = |file: &str| {
+            let error: TypeError<Universal> =
+                TypeError::VariableDoesNotExist(at(file), LocalName::from(literal!("y")));
+            let report = error.to_report(Arc::from(source), None);
+            let label = report.labels().into_iter().flatten().next().unwrap();
+            let contents = report
+                .source_code()
+                .unwrap()
+                .read_span(label.inner(), 0, 0)
+                .unwrap();
+            assert_eq!(contents.data(), b"y");
+            contents.name().map(str::to_owned)
+        };
+        assert_eq!(
+            name_in_report("par-builtin:core/List.par").as_deref(),
+            Some("par-builtin:core/List.par")
+        );
+        assert_eq!(name_in_report("Main.par"), None);
+    }
+
+    /// An error of every kind.
+    fn every_type_error() -> Vec<TypeError<Universal>> {
+        let span = || Span::None;
+        let name = || test_name("A");
+        let local = || LocalName::from(literal!("x"));
+        let module = || name().module;
+        let typ = Type::nat;
+        vec![
+            TypeError::TypeNameAlreadyDefined(span(), span(), name()),
+            TypeError::NameAlreadyDeclared(span(), span(), name()),
+            TypeError::NameAlreadyDefined(span(), span(), name()),
+            TypeError::DeclaredButNotDefined(span(), name()),
+            TypeError::ModuleExportInconsistent(span(), span(), module()),
+            TypeError::ImportedModuleNotExported(span(), module()),
+            TypeError::GlobalNameNotVisible(span(), name(), Visibility::Module),
+            TypeError::VisibleItemExposesHiddenType(
+                span(),
+                name(),
+                Visibility::Public,
+                test_name("B"),
+                Visibility::Module,
+            ),
+            TypeError::NoMatchingRecursiveOrIterative(span()),
+            TypeError::SelfUsedInNegativePosition(span(), span(), vec![(span(), "a function")]),
+            TypeError::UnguardedRecursiveSelf(span()),
+            TypeError::UnguardedIterativeSelf(span()),
+            TypeError::TypeNameNotDefined(span(), name()),
+            TypeError::TypeVariableNotDefined(span(), local()),
+            TypeError::DependencyCycle(span(), vec![name(), test_name("B")]),
+            TypeError::TypeExpansionTooDeep(span(), vec![(name(), span())]),
+            TypeError::WrongNumberOfTypeArgs(span(), name(), 2, 1),
+            TypeError::GlobalNameNotDefined(span(), name()),
+            TypeError::VariableDoesNotExist(span(), local()),
+            TypeError::ShadowedObligation(span(), span(), local(), typ()),
+            TypeError::TypeMustBeKnownAtThisPoint(span(), local()),
+            TypeError::ParameterTypeMustBeKnown(span(), local()),
+            TypeError::CannotAssignFromTo(span(), typ(), Type::int()),
+            TypeError::TypeDoesNotSatisfyConstraint(span(), local(), typ(), TypeConstraint::Box),
+            TypeError::TypeParameterConstraintMismatch(
+                span(),
+                local(),
+                TypeConstraint::Box,
+                TypeConstraint::Data,
+            ),
+            TypeError::UnfulfilledObligations(span(), vec![(local(), span(), typ())]),
+            TypeError::InvalidOperation(span(), Operation::Send, typ()),
+            TypeError::InvalidBranch(span(), local(), typ()),
+            TypeError::MissingBranch(span(), local(), typ()),
+            TypeError::RedundantBranch(span(), local(), typ()),
+            TypeError::MergeVariableMissing(span(), local()),
+            TypeError::MergeVariableTypesCannotBeUnified(span(), local(), typ(), Type::int()),
+            TypeError::VariableEscapesTypeScope(span(), local()),
+            TypeError::TypesCannotBeUnified(span(), typ(), Type::int()),
+            TypeError::BranchTypesCannotBeUnified(span(), local(), typ(), Type::int()),
+            TypeError::NoSuchLoopPoint(span(), None),
+            TypeError::DoesNotDescendSubjectOfBegin(span(), LoopId::new()),
+            TypeError::DoesNotDescendSubjectsOfBegin(span(), Some(local())),
+            TypeError::CannotUnrollAscendantIterative(span(), None),
+            TypeError::LoopVariableNotPreserved(span(), local()),
+            TypeError::LoopVariableChangedType(span(), local(), typ(), Type::int()),
+            TypeError::PollMustHaveAtLeastOneClient(span()),
+            TypeError::PollClientMustBeRecursive(span(), typ()),
+            TypeError::SubmitOutsidePoll(span()),
+            TypeError::RepollOutsidePoll(span()),
+            TypeError::SubmittedClientNotAssignableToPoll(span(), typ(), Type::int()),
+            TypeError::SubmittedClientDoesNotDescend(span()),
+            TypeError::SubmitCannotTargetPollPoint(span(), typ(), Type::int()),
+            TypeError::PollVariableNotPreserved(span(), local()),
+            TypeError::PollVariableChangedType(span(), local(), typ(), Type::int()),
+            TypeError::PollBranchMustSubmit(span()),
+            TypeError::CannotUseLinearVariableInBox(span(), local()),
+            TypeError::NonExhaustiveIf(span()),
+            TypeError::InvalidUnionOperand(span(), UnionKind::Either, typ()),
+            TypeError::OverlappingUnionLabels(span(), local(), local()),
+            TypeError::UnusedTypeParameter(span(), name(), local()),
+            TypeError::UnguardedCorecursion(span(), vec![name(), test_name("B")]),
+            TypeError::UnionOfTwoTypeVariables(
+                span(),
+                Type::Var(span(), local()),
+                Type::Var(span(), LocalName::from(literal!("y"))),
+            ),
+            TypeError::CaseOnOpenRowWithoutElse(span(), typ()),
+        ]
+    }
+
+    #[test]
+    fn test_error_codes_are_explained() {
+        let mut seen = HashMap::new();
+        for error in every_type_error() {
+            let report = error.to_report(Arc::from(""), None);
+            assert_eq!(
+                report.code().map(|code| code.to_string()).as_deref(),
+                Some(error.code())
+            );
+            let explained = explain_code(error.code())
+                .unwrap_or_else(|| panic!("{} is not explained", error.code()));
+            assert!(!explained.explanation.is_empty());
+            if let Some(other) = seen.insert(error.code(), format!("{error:?}")) {
+                panic!("{} is the code of both {other} and {error:?}", error.code());
+            }
+        }
+        assert_eq!(
+            explain_code("PAR0010").map(|explained| explained.title),
+            Some("`self` outside of a recursive or iterative type")
+        );
+    }
+
+    /// Types covering every constructor and the ways they nest, whose renderings
+    /// are kept in `pretty.golden`.
+    fn golden_types() -> Vec<(&'static str, Type<Universal>)> {
+        let exists = |var: &'static str, body: Type<Universal>| {
+            Type::Exists(
+                Span::None,
+                TypeParameter::any(LocalName::from(ArcStr::from(var))),
+                Box::new(body),
+            )
+        };
+        let list = |arg: Type<Universal>| Type::Name(Span::None, test_name("List"), vec![arg]);
+        let dual_list =
+            |arg: Type<Universal>| Type::DualName(Span::None, test_name("List"), vec![arg]);
+        let dual_var =
+            |name: &'static str| Type::DualVar(Span::None, LocalName::from(ArcStr::from(name)));
+
+        vec![
+            (
+                "primitives",
+                Type::pair(
+                    Type::nat(),
+                    Type::pair(
+                        Type::int(),
+                        Type::pair(
+                            Type::float(),
+                            Type::pair(
+                                Type::string(),
+                                Type::pair(
+                                    Type::char(),
+                                    Type::pair(
+                                        Type::byte(),
+                                        Type::pair(Type::bytes(), Type::break_()),
+                                    ),
+                                ),
+                            ),
+                        ),
+                    ),
+                ),
+            ),
+            (
+                "send chain",
+                Type::pair(Type::int(), Type::pair(Type::string(), Type::var("rest"))),
+            ),
+            (
+                "receive chain",
+                Type::function(
+                    Type::int(),
+                    Type::function(Type::string(), Type::continue_()),
+                ),
+            ),
+            (
+                "receive into either",
+                Type::function(
+                    Type::nat(),
+                    Type::either(vec![("ok", Type::break_()), ("err", Type::string())]),
+                ),
+            ),
+            (
+                "either nested in choice",
+                Type::choice(vec![
+                    ("close", Type::break_()),
+                    (
+                        "get",
+                        Type::either(vec![
+                            ("none", Type::break_()),
+                            ("some", Type::pair(Type::int(), Type::break_())),
+                        ]),
+                    ),
+                    ("put", Type::function(Type::string(), Type::var("next"))),
+                ]),
+            ),
+            (
+                "recursive with label",
+                Type::recursive(
+                    Some("tree"),
+                    Type::either(vec![
+                        ("leaf", Type::break_()),
+                        (
+                            "node",
+                            Type::pair(
+                                Type::self_(Some("tree")),
+                                Type::pair(Type::self_(Some("tree")), Type::break_()),
+                            ),
+                        ),
+                    ]),
+                ),
+            ),
+            (
+                "recursive nested in iterative",
+                Type::iterative(
+                    Some("outer"),
+                    Type::choice(vec![
+                        (
+                            "item",
+                            Type::recursive(
+                                None,
+                                Type::either(vec![
+                                    ("end", Type::break_()),
+                                    ("more", Type::pair(Type::nat(), Type::self_(None))),
+                                ]),
+                            ),
+                        ),
+                        ("next", Type::self_(Some("outer"))),
+                    ]),
+                ),
+            ),
+            (
+                "receive type",
+                Type::forall("a", Type::function(Type::var("a"), Type::var("a"))),
+            ),
+            (
+                "send type",
+                exists("a", Type::pair(Type::var("a"), Type::break_())),
+            ),
+            (
+                "generic function",
+                Type::generic_function(vec!["a", "b"], Type::var("a"), Type::var("b")),
+            ),
+            (
+                "generic pair",
+                Type::generic_pair(vec!["t"], Type::var("t"), Type::break_()),
+            ),
+            (
+                "types in choice branches",
+                Type::choice(vec![
+                    (
+                        "map",
+                        Type::forall("r", Type::function(Type::var("r"), Type::var("r"))),
+                    ),
+                    (
+                        "open",
+                        exists("a", Type::pair(Type::var("a"), Type::break_())),
+                    ),
+                ]),
+            ),
+            (
+                "type in either branch",
+                Type::either(vec![(
+                    "some",
+                    exists("a", Type::pair(Type::var("a"), Type::break_())),
+                )]),
+            ),
+            (
+                "names and duals",
+                Type::pair(
+                    list(Type::int()),
+                    Type::pair(
+                        dual_list(Type::int()),
+                        Type::pair(
+                            dual_var("a"),
+                            Type::pair(
+                                Type::DualPrimitive(Span::None, PrimitiveType::String),
+                                Type::break_(),
+                            ),
+                        ),
+                    ),
+                ),
+            ),
+            (
+                "box",
+                Type::box_(Type::choice(vec![("close", Type::break_())])),
+            ),
+            (
+                "dual box",
+                Type::DualBox(Span::None, Box::new(Type::either(vec![]))),
+            ),
+            (
+                "union",
+                Type::Union(
+                    Span::None,
+                    UnionKind::Either,
+                    Box::new(list(Type::nat())),
+                    Box::new(Type::var("a")),
+                ),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_pretty_rendering_matches_golden_file() {
+        let mut sections = Vec::new();
+        for (name, typ) in golden_types() {
+            let mut pretty = String::new();
+            typ.pretty(&mut pretty, &TestNameWriter, 0).unwrap();
+            sections.push(format!("== {name} (pretty)\n{pretty}\n"));
+            let mut compact = String::new();
+            typ.pretty_compact(&mut compact, &TestNameWriter).unwrap();
+            sections.push(format!("== {name} (compact)\n{compact}\n"));
+        }
+        let actual = sections.join("\n");
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/frontend_impl/types/pretty.golden"
+        );
+        if std::env::var_os("PAR_UPDATE_GOLDEN").is_some() {
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        let expected = include_str!("pretty.golden");
+        assert!(
+            actual == expected,
+            "type rendering changed; rerun with PAR_UPDATE_GOLDEN=1 to accept it, \
+             and review the diff of {path}\n\n{actual}"
+        );
+    }
 }
@@ -18,6 +18,17 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
     where
         S: Clone + Eq + std::hash::Hash,
     {
+        self.satisfies_constraint_expanding(constraint, defs, &mut Vec::new())
+    }
+
+    /// Like [`Type::satisfies_constraint`], with the names being expanded on
+    /// the way here.
+    fn satisfies_constraint_expanding(
+        &self,
+        constraint: TypeConstraint,
+        defs: &TypeDefs<S>,
+        expanding: &mut Vec<Self>,
+    ) -> Result<bool, TypeError<S>> {
         if constraint == TypeConstraint::Any {
             return Ok(true);
         }
@@ -37,14 +48,35 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
             Type::Var(_, name) => Ok(defs
                 .var_constraint(name)
                 .is_some_and(|actual| constraint.is_broader_or_equal_than(actual))),
-            Type::DualName(_, name, args) => defs
-                .get_dual(&self.span(), name, args)
-                .and_then(|typ| typ.satisfies_constraint(constraint, defs)),
-            Type::Name(_, name, args) => defs
-                .get(&self.span(), name, args)
-                .and_then(|typ| typ.satisfies_constraint(constraint, defs)),
+            // Met again inside its own expansion, a name is a reference back
+            // among mutually recursive types, and stands for data like `self`.
+            Type::Name(..) | Type::DualName(..) if expanding.contains(self) => {
+                Ok(satisfies_at_least(TypeConstraint::Data))
+            }
+            Type::DualName(_, name, args) => {
+                let typ = defs.get_dual(&self.span(), name, args)?;
+                expanding.push(self.clone());
+                let satisfies = typ.satisfies_constraint_expanding(constraint, defs, expanding);
+                expanding.pop();
+                satisfies
+            }
+            Type::Name(_, name, args) => {
+                let typ = defs.get(&self.span(), name, args)?;
+                expanding.push(self.clone());
+                let satisfies = typ.satisfies_constraint_expanding(constraint, defs, expanding);
+                expanding.pop();
+                satisfies
+            }
+            // An open row satisfies what both its known branches and its
+            // type variable do.
+            Type::Union(_, _, known, rest) if self.as_open_row().is_some() => Ok(known
+                .satisfies_constraint_expanding(constraint, defs, expanding)?
+                && rest.satisfies_constraint_expanding(constraint, defs, expanding)?),
+            Type::Union(..) => self
+                .expand_definition(defs)
+                .and_then(|typ| typ.satisfies_constraint_expanding(constraint, defs, expanding)),
             Type::Box(_, typ) => Ok(satisfies_at_least(TypeConstraint::Box)
-                || typ.satisfies_constraint(constraint, defs)?),
+                || typ.satisfies_constraint_expanding(constraint, defs, expanding)?),
             Type::Pair(_, left, right, vars) => {
                 let minimum = if vars.is_empty() {
                     TypeConstraint::Data
@@ -55,8 +87,10 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
                     return Ok(false);
                 }
                 Self::with_type_parameters(defs, vars, |defs| {
-                    Ok(left.satisfies_constraint(constraint, defs)?
-                        && right.satisfies_constraint(constraint, defs)?)
+                    Ok(
+                        left.satisfies_constraint_expanding(constraint, defs, expanding)?
+                            && right.satisfies_constraint_expanding(constraint, defs, expanding)?,
+                    )
                 })
             }
             Type::Either(_, branches) => {
@@ -64,21 +98,24 @@ impl<S: Clone + Eq + std::hash::Hash> Type<S> {
                     return Ok(false);
                 }
                 branches.values().try_fold(true, |acc, branch| {
-                    Ok(acc && branch.satisfies_constraint(constraint, defs)?)
+                    Ok(
+                        acc && branch
+                            .satisfies_constraint_expanding(constraint, defs, expanding)?,
+                    )
                 })
             }
             Type::Recursive { body, .. } | Type::Iterative { body, .. } => {
                 if !satisfies_at_least(TypeConstraint::Data) {
                     return Ok(false);
                 }
-                body.satisfies_constraint(constraint, defs)
+                body.satisfies_constraint_expanding(constraint, defs, expanding)
             }
             Type::Exists(_, param, body) | Type::Forall(_, param, body) => {
                 if !satisfies_at_least(TypeConstraint::Box) {
                     return Ok(false);
                 }
                 Self::with_type_parameter(defs, param, |defs| {
-                    body.satisfies_constraint(constraint, defs)
+                    body.satisfies_constraint_expanding(constraint, defs, expanding)
                 })
             }
             Type::Fail(_) => Ok(true),
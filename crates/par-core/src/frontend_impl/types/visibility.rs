@@ -103,7 +103,7 @@ impl VisibilityIndex {
         }
     }
 
-    fn type_visibility(&self, name: &GlobalName<Universal>) -> Visibility {
+    pub(crate) fn type_visibility(&self, name: &GlobalName<Universal>) -> Visibility {
         self.type_defs
             .get(name)
             .copied()
@@ -21,7 +21,9 @@ where
         Type::Box(_, inner) | Type::DualBox(_, inner) => {
             visit(inner)?;
         }
-        Type::Pair(_, left, right, _) | Type::Function(_, left, right, _) => {
+        Type::Pair(_, left, right, _)
+        | Type::Function(_, left, right, _)
+        | Type::Union(_, _, left, right) => {
             visit(left)?;
             visit(right)?;
         }
@@ -69,7 +71,9 @@ where
         Type::Box(_, inner) | Type::DualBox(_, inner) => {
             visit(inner)?;
         }
-        Type::Pair(_, left, right, _) | Type::Function(_, left, right, _) => {
+        Type::Pair(_, left, right, _)
+        | Type::Function(_, left, right, _)
+        | Type::Union(_, _, left, right) => {
             visit(left)?;
             visit(right)?;
         }
@@ -177,7 +181,7 @@ impl Polarity {
     }
 }
 
-fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
+pub(crate) fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
     span: &Span,
     name: &GlobalName<S>,
     defs: &TypeDefs<S>,
@@ -196,8 +200,30 @@ fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
         negative_vars: &mut IndexSet<LocalName>,
         names: &IndexSet<LocalName>,
         defs: &TypeDefs<S>,
+        expanding: &mut Vec<(Type<S>, bool)>,
     ) -> Result<(), TypeError<S>> {
         match typ {
+            // Met again inside its own expansion, the same way around, a name
+            // refers back among mutually recursive types, and adds nothing new.
+            Type::Name(..) | Type::DualName(..)
+                if expanding.contains(&(typ.clone(), is_positive)) => {}
+            Type::Name(..) | Type::DualName(..) => {
+                expanding.push((typ.clone(), is_positive));
+                let result =
+                    continue_deref_polarized(typ, is_positive, defs, |child, is_positive| {
+                        inner(
+                            child,
+                            is_positive,
+                            positive_vars,
+                            negative_vars,
+                            names,
+                            defs,
+                            expanding,
+                        )
+                    });
+                expanding.pop();
+                result?;
+            }
             Type::Var(_, name) if names.contains(name) => {
                 if is_positive {
                     positive_vars.insert(name.clone());
@@ -222,6 +248,7 @@ fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
                     negative_vars,
                     &names,
                     defs,
+                    expanding,
                 )?;
             }
             _ => {
@@ -233,6 +260,7 @@ fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
                         negative_vars,
                         names,
                         defs,
+                        expanding,
                     )
                 })?;
             }
@@ -247,6 +275,7 @@ fn get_args_polarity<S: Clone + Eq + std::hash::Hash>(
         &mut negative_vars,
         &vars.iter().map(|var| var.name.clone()).collect(),
         defs,
+        &mut Vec::new(),
     )?;
 
     Ok(vars
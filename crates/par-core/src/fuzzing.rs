@@ -0,0 +1,366 @@
+//! Entry points for the fuzz targets in `fuzz/`, enabled by the `fuzzing`
+//! feature.
+//!
+//! Each one turns the raw bytes from the fuzzer into an input for a part of
+//! the compiler and runs it, ignoring any errors. They must not panic by
+//! themselves, so every panic the fuzzer finds is a bug in the compiler.
+
+use arcstr::{ArcStr, literal};
+use par_runtime::pkgid::PackageId;
+
+use crate::frontend_impl::language::{GlobalName, LocalName, TypeParameter, Universal};
+use crate::frontend_impl::lexer::{Lexed, SourceTrivia, Token, TokenKind, lex};
+use crate::frontend_impl::parse::parse_lexed;
+use crate::frontend_impl::types::{PrimitiveType, Type, TypeDefs};
+use crate::location::{FileName, Point, Span};
+
+/// The tokens a fuzzed token stream is made of, each with a raw text that the
+/// lexer could have produced for it.
+const VOCABULARY: &[(TokenKind, &str)] = &[
+    (TokenKind::LParen, "("),
+    (TokenKind::RParen, ")"),
+    (TokenKind::LCurly, "{"),
+    (TokenKind::RCurly, "}"),
+    (TokenKind::LBrack, "["),
+    (TokenKind::RBrack, "]"),
+    (TokenKind::Lt, "<"),
+    (TokenKind::Gt, ">"),
+    (TokenKind::LtEq, "<="),
+    (TokenKind::GtEq, ">="),
+    (TokenKind::Slash, "/"),
+    (TokenKind::SlashEq, "/="),
+    (TokenKind::At, "@"),
+    (TokenKind::Colon, ":"),
+    (TokenKind::Semicolon, ";"),
+    (TokenKind::Comma, ","),
+    (TokenKind::Dot, "."),
+    (TokenKind::Eq, "="),
+    (TokenKind::EqEq, "=="),
+    (TokenKind::FatArrow, "=>"),
+    (TokenKind::ThinArrow, "->"),
+    (TokenKind::Bang, "!"),
+    (TokenKind::BangEq, "!="),
+    (TokenKind::Quest, "?"),
+    (TokenKind::Star, "*"),
+    (TokenKind::StarEq, "*="),
+    (TokenKind::Plus, "+"),
+    (TokenKind::PlusEq, "+="),
+    (TokenKind::Minus, "-"),
+    (TokenKind::MinusEq, "-="),
+    (TokenKind::Bar, "|"),
+    (TokenKind::Ampersand, "&"),
+    (TokenKind::Link, "<>"),
+    (TokenKind::Float, "1.5"),
+    (TokenKind::Integer, "7"),
+    (TokenKind::String, "s"),
+    (TokenKind::TemplateStart, "`"),
+    (TokenKind::TemplateEnd, "`"),
+    (TokenKind::TemplateText, "t"),
+    (TokenKind::TemplateStringStart, "${"),
+    (TokenKind::TemplateDataStart, "#{"),
+    (TokenKind::LowercaseIdentifier, "x"),
+    (TokenKind::LowercaseIdentifier, "y"),
+    (TokenKind::UppercaseIdentifier, "Main"),
+    (TokenKind::UppercaseIdentifier, "T"),
+    (TokenKind::Begin, "begin"),
+    (TokenKind::Box, "box"),
+    (TokenKind::Case, "case"),
+    (TokenKind::Catch, "catch"),
+    (TokenKind::Chan, "chan"),
+    (TokenKind::Choice, "choice"),
+    (TokenKind::Dec, "dec"),
+    (TokenKind::Def, "def"),
+    (TokenKind::Do, "do"),
+    (TokenKind::Dual, "dual"),
+    (TokenKind::Either, "either"),
+    (TokenKind::Else, "else"),
+    (TokenKind::Export, "export"),
+    (TokenKind::If, "if"),
+    (TokenKind::Import, "import"),
+    (TokenKind::Is, "is"),
+    (TokenKind::In, "in"),
+    (TokenKind::Iterative, "iterative"),
+    (TokenKind::Let, "let"),
+    (TokenKind::And, "and"),
+    (TokenKind::As, "as"),
+    (TokenKind::Module, "module"),
+    (TokenKind::Neg, "neg"),
+    (TokenKind::Or, "or"),
+    (TokenKind::Not, "not"),
+    (TokenKind::Loop, "loop"),
+    (TokenKind::Macro, "macro"),
+    (TokenKind::Poll, "poll"),
+    (TokenKind::Repoll, "repoll"),
+    (TokenKind::Submit, "submit"),
+    (TokenKind::Recursive, "recursive"),
+    (TokenKind::Self_, "self"),
+    (TokenKind::Throw, "throw"),
+    (TokenKind::Try, "try"),
+    (TokenKind::Default, "default"),
+    (TokenKind::Type, "type"),
+    (TokenKind::Unfounded, "unfounded"),
+    (TokenKind::External, "external"),
+    (TokenKind::Derive, "derive"),
+    (TokenKind::Edition, "edition"),
+];
+
+/// Parses the token stream with one token per byte of `data`.
+pub fn parse_token_stream(data: &[u8]) {
+    let (input, tokens) = token_stream(data);
+    let _ = parse_lexed(
+        &input,
+        Lexed {
+            tokens,
+            comments: Vec::new(),
+            trivia: SourceTrivia::default(),
+        },
+    );
+}
+
+/// The source text with one token per byte of `data`, separated by spaces,
+/// and its tokens.
+fn token_stream(data: &[u8]) -> (String, Vec<Token<'static>>) {
+    let file = FileName::from("fuzz.par");
+    let raws = data
+        .iter()
+        .map(|byte| VOCABULARY[*byte as usize % VOCABULARY.len()])
+        .collect::<Vec<_>>();
+    let input = raws
+        .iter()
+        .map(|(_, raw)| *raw)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut offset = 0;
+    let tokens = raws
+        .iter()
+        .map(|&(kind, raw)| {
+            let point = |offset: usize| Point {
+                offset: offset as u32,
+                row: 0,
+                column: offset as u32,
+            };
+            let span = Span::At {
+                start: point(offset),
+                end: point(offset + raw.len()),
+                file: file.clone(),
+                expansion: None,
+            };
+            offset += raw.len() + 1;
+            Token { kind, raw, span }
+        })
+        .collect();
+    (input, tokens)
+}
+
+/// Encodes the tokens of `source` as an input for [`parse_token_stream`], to
+/// seed the fuzzer with real programs.
+pub fn encode_token_stream(source: &str) -> Vec<u8> {
+    lex(source, &FileName::from("seed.par"))
+        .iter()
+        .filter_map(|token| {
+            VOCABULARY
+                .iter()
+                .position(|(kind, _)| *kind == token.kind)
+                .map(|index| index as u8)
+        })
+        .collect()
+}
+
+/// Builds two types from `data` and checks them against each other and their
+/// duals. Types that don't pass validation, like `recursive self`, are left
+/// out, as the type checker never gets to compare those.
+pub fn check_types(data: &[u8]) {
+    let defs = type_defs();
+    let mut bytes = data.iter().copied();
+    let left = Type::forall("a", arbitrary_type(&mut bytes, 6));
+    let right = Type::forall("a", arbitrary_type(&mut bytes, 6));
+    if defs.validate_type(&left).is_err() || defs.validate_type(&right).is_err() {
+        return;
+    }
+
+    let _ = left.is_definitely_assignable_to(&right, &defs);
+    let _ = right.is_definitely_assignable_to(&left, &defs);
+    let dual_left = left.clone().dual(Span::None);
+    let dual_right = right.clone().dual(Span::None);
+    let _ = dual_right.is_definitely_assignable_to(&dual_left, &defs);
+    let _ = dual_left
+        .dual(Span::None)
+        .is_definitely_assignable_to(&left, &defs);
+}
+
+fn global(primary: &str) -> GlobalName<Universal> {
+    GlobalName::new(
+        Span::None,
+        Universal {
+            package: PackageId::Special(literal!("__fuzz__")),
+            directories: vec![],
+            module: "Main".to_string(),
+        },
+        primary.to_string(),
+    )
+}
+
+/// A list and a stream, for named types to refer to.
+fn type_defs() -> TypeDefs<Universal> {
+    let params = vec![TypeParameter::any(LocalName {
+        span: Span::None,
+        string: ArcStr::from("a"),
+    })];
+    let list = Type::recursive(
+        None,
+        Type::either(vec![
+            ("end", Type::break_()),
+            ("item", Type::pair(Type::var("a"), Type::self_(None))),
+        ]),
+    );
+    let stream = Type::iterative(
+        None,
+        Type::choice(vec![
+            ("close", Type::break_()),
+            ("next", Type::pair(Type::var("a"), Type::self_(None))),
+        ]),
+    );
+    let (list_name, stream_name) = (global("List"), global("Stream"));
+    let (defs, _) = TypeDefs::new_with_validation(
+        [
+            (&Span::None, &list_name, &params, &list),
+            (&Span::None, &stream_name, &params, &stream),
+        ]
+        .into_iter(),
+    );
+    defs
+}
+
+const LABELS: &[&str] = &["a", "b", "c"];
+
+fn arbitrary_type(bytes: &mut impl Iterator<Item = u8>, depth: usize) -> Type<Universal> {
+    let Some(byte) = bytes.next() else {
+        return Type::break_();
+    };
+    // Past the depth limit, only leaves are picked.
+    let choice = if depth == 0 { byte % 5 } else { byte % 16 };
+    let depth = depth.saturating_sub(1);
+    match choice {
+        0 => Type::Primitive(
+            Span::None,
+            PRIMITIVES[byte as usize % PRIMITIVES.len()].clone(),
+        ),
+        1 => Type::var("a"),
+        2 => Type::break_(),
+        3 => Type::continue_(),
+        4 => Type::self_(None),
+        5 => arbitrary_type(bytes, depth).dual(Span::None),
+        6 => Type::box_(arbitrary_type(bytes, depth)),
+        7 => Type::pair(arbitrary_type(bytes, depth), arbitrary_type(bytes, depth)),
+        8 => Type::function(arbitrary_type(bytes, depth), arbitrary_type(bytes, depth)),
+        9 | 10 => {
+            let count = 1 + bytes.next().unwrap_or(0) as usize % LABELS.len();
+            let branches = LABELS[..count]
+                .iter()
+                .map(|label| (*label, arbitrary_type(bytes, depth)))
+                .collect();
+            if choice == 9 {
+                Type::either(branches)
+            } else {
+                Type::choice(branches)
+            }
+        }
+        11 => Type::recursive(None, arbitrary_type(bytes, depth)),
+        12 => Type::iterative(None, arbitrary_type(bytes, depth)),
+        13 => Type::forall("b", arbitrary_type(bytes, depth)),
+        _ => Type::Name(
+            Span::None,
+            global(if choice == 14 { "List" } else { "Stream" }),
+            vec![arbitrary_type(bytes, depth)],
+        ),
+    }
+}
+
+const PRIMITIVES: &[PrimitiveType] = &[
+    PrimitiveType::Nat,
+    PrimitiveType::Int,
+    PrimitiveType::Float,
+    PrimitiveType::String,
+    PrimitiveType::Char,
+    PrimitiveType::Byte,
+    PrimitiveType::Bytes,
+];
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// The examples, which seed the corpus of `parse_tokens`.
+    fn examples() -> Vec<(String, String)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/src");
+        let mut examples = std::fs::read_dir(&dir)
+            .expect("examples directory")
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "par"))
+            .map(|path| {
+                let source = std::fs::read_to_string(&path).expect("example source");
+                (path.display().to_string(), source)
+            })
+            .collect::<Vec<_>>();
+        examples.sort();
+        assert!(!examples.is_empty());
+        examples
+    }
+
+    #[test]
+    fn seeds_keep_every_token_of_the_examples() {
+        for (path, source) in examples() {
+            let lexed = lex(&source, &FileName::from("seed.par"));
+            let (input, tokens) = token_stream(&encode_token_stream(&source));
+            let kinds =
+                |tokens: &[Token]| tokens.iter().map(|token| token.kind).collect::<Vec<_>>();
+            assert_eq!(kinds(&tokens), kinds(&lexed), "the tokens of {path}");
+            for token in &tokens {
+                let (start, end) = token.span.points().unwrap();
+                assert_eq!(
+                    &input[start.offset as usize..end.offset as usize],
+                    token.raw
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_of_the_examples_parse() {
+        // Parsing the longer examples takes more stack than a test thread has
+        // in debug builds.
+        std::thread::Builder::new()
+            .stack_size(32 * 1024 * 1024)
+            .spawn(|| {
+                for (path, source) in examples() {
+                    let (input, tokens) = token_stream(&encode_token_stream(&source));
+                    let parsed = parse_lexed(
+                        &input,
+                        Lexed {
+                            tokens,
+                            comments: Vec::new(),
+                            trivia: SourceTrivia::default(),
+                        },
+                    );
+                    assert!(parsed.is_ok(), "the seed of {path}: {parsed:?}");
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn short_inputs_run_to_the_end() {
+        for first in 0..=u8::MAX {
+            for second in 0..=u8::MAX {
+                parse_token_stream(&[first, second]);
+                check_types(&[first, second]);
+            }
+        }
+    }
+}
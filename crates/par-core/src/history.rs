@@ -0,0 +1,339 @@
+//! Histories of the inputs given to running definitions, for getting back to
+//! an earlier state by replaying them.
+//!
+//! A running net can't be copied: its state is spread over instances shared
+//! with the reducer, the handles held by whoever reads it back, and whatever
+//! tasks its builtins have spawned. So this isn't a snapshot of the net. A
+//! definition only changes course on the inputs it's given, and a [`History`]
+//! is the list of those inputs. Getting back to the state after them means
+//! starting the definition over and answering its requests with [`Replay`],
+//! until the inputs run out; from there, each branch to explore is the
+//! history with a different input added. Each time, the definition runs
+//! again from the start, so going back costs as much as the whole history,
+//! not just the inputs undone.
+//!
+//! To keep what the definition did on the way from being done again, the
+//! restarted run should have its [`Effects`](par_runtime::effects::Effects)
+//! [replaying](par_runtime::effects::Effects::replaying) the ones the earlier
+//! run had. Builtins are then answered with the results the earlier run got,
+//! like the time, the lines read, or a failure to write a file, instead of
+//! having their effects again. A definition that still goes another way may
+//! ask for different inputs during the replay, in which case the replay
+//! stops.
+//!
+//! An input is addressed by the [`Path`] of the request it answers: the
+//! positions of the nested values leading to it from the root, counting the
+//! first halves of every `Times` and `Par` read back on the way. Each of
+//! those values is read in order, so its inputs can be replayed in order too,
+//! however they interleave with the others.
+//!
+//! A history can be [written](History::write_to) and [read](History::read_from)
+//! back by a later version of Par, with a header naming its format. The
+//! results of the effects aren't part of it, so a run replaying a history
+//! read back has its effects for real.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+
+use arcstr::ArcStr;
+use bytes::Bytes;
+use num_bigint::{BigInt, BigUint};
+use par_runtime::primitive::ParString;
+use par_runtime::versioned::{self, Header, Migration};
+use serde::{Deserialize, Serialize};
+
+use crate::typed_readback::{TypedHandle, TypedReadback};
+
+/// Where a request is among the values nested in a definition.
+pub type Path = Vec<usize>;
+
+/// An answer to a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    Nat(BigUint),
+    Int(BigInt),
+    Float(f64),
+    String(String),
+    Char(char),
+    Byte(u8),
+    Bytes(Bytes),
+    Choice(ArcStr),
+}
+
+/// The inputs a running definition was given, in order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    inputs: Vec<(Path, Input)>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inputs(&self) -> &[(Path, Input)] {
+        &self.inputs
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn push(&mut self, path: Path, input: Input) {
+        self.inputs.push((path, input));
+    }
+
+    /// Takes back the last input.
+    pub fn pop(&mut self) -> Option<(Path, Input)> {
+        self.inputs.pop()
+    }
+
+    /// This history followed by one more input, leaving this one as it is.
+    pub fn then(&self, path: Path, input: Input) -> Self {
+        let mut next = self.clone();
+        next.push(path, input);
+        next
+    }
+}
+
+/// Starts every written history.
+const HISTORY_MAGIC: &[u8; 4] = b"PHS\0";
+
+/// The layout of a written history after the header. Bump it whenever a
+/// change to [`History`] or [`Input`] changes how it's serialized, and add a
+/// migration from the previous one to [`HISTORY_MIGRATIONS`].
+pub const HISTORY_FORMAT: u32 = 1;
+
+/// The migration at index `i` upgrades format `i + 1` to `i + 2`.
+const HISTORY_MIGRATIONS: &[Migration] = &[];
+
+/// Why a written history couldn't be read.
+#[derive(Debug)]
+pub enum HistoryError {
+    Io(io::Error),
+    /// The file doesn't start with a well-formed history header.
+    NotAHistory,
+    /// The history was written by a version of Par that this one can't read.
+    Incompatible {
+        created_by: String,
+        format: u32,
+        reason: String,
+    },
+}
+
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to read history: {error}"),
+            Self::NotAHistory => write!(f, "Not a Par history"),
+            Self::Incompatible {
+                created_by,
+                format,
+                reason,
+            } => write!(
+                f,
+                "History created by version {created_by} (format {format}), incompatible because \
+                 {reason}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl From<io::Error> for HistoryError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl History {
+    /// Writes the history, after a header recording the format and the
+    /// version of Par that wrote it.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        versioned::write_header(&mut writer, HISTORY_MAGIC, HISTORY_FORMAT)?;
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    /// Reads a history written by [`write_to`](Self::write_to), by this
+    /// version or an earlier one, migrating it to the current format.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, HistoryError> {
+        let Some(Header { format, created_by }) =
+            versioned::read_header(&mut reader, HISTORY_MAGIC)?
+        else {
+            return Err(HistoryError::NotAHistory);
+        };
+        let incompatible = |reason: String| HistoryError::Incompatible {
+            created_by: created_by.clone(),
+            format,
+            reason,
+        };
+
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        let body = versioned::upgrade(body, format, HISTORY_FORMAT, HISTORY_MIGRATIONS)
+            .map_err(incompatible)?;
+        bincode::deserialize(&body)
+            .map_err(|error| incompatible(format!("its contents are malformed: {error}")))
+    }
+}
+
+/// Answers the requests of a restarted definition with the inputs of a
+/// history.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pending: HashMap<Path, VecDeque<Input>>,
+}
+
+impl Replay {
+    pub fn new(history: &History) -> Self {
+        let mut pending = HashMap::<_, VecDeque<_>>::new();
+        for (path, input) in history.inputs() {
+            pending
+                .entry(path.clone())
+                .or_default()
+                .push_back(input.clone());
+        }
+        Self { pending }
+    }
+
+    /// The next input for the request at `path`, if there's any left.
+    pub fn next(&mut self, path: &[usize]) -> Option<Input> {
+        let inputs = self.pending.get_mut(path)?;
+        let input = inputs.pop_front();
+        if inputs.is_empty() {
+            self.pending.remove(path);
+        }
+        input
+    }
+
+    /// Gives up replaying into `path`, after the definition asked for
+    /// something else there than the history had.
+    pub fn abandon(&mut self, path: &[usize]) {
+        self.pending.remove(path);
+    }
+
+    /// Whether every input has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl TypedReadback {
+    /// Answers a request with `input`. A choice gives back the handle to
+    /// continue with; other requests are done once answered. Anything that
+    /// isn't a request, or a request for something else, is given back
+    /// unchanged.
+    pub fn answer(self, input: Input) -> Result<Option<TypedHandle>, Self> {
+        Ok(match (self, input) {
+            (Self::NatRequest(callback), Input::Nat(value)) => {
+                callback(value);
+                None
+            }
+            (Self::IntRequest(callback), Input::Int(value)) => {
+                callback(value);
+                None
+            }
+            (Self::FloatRequest(callback), Input::Float(value)) => {
+                callback(value);
+                None
+            }
+            (Self::StringRequest(callback), Input::String(value)) => {
+                callback(ParString::from(value));
+                None
+            }
+            (Self::CharRequest(callback), Input::Char(value)) => {
+                callback(value);
+                None
+            }
+            (Self::ByteRequest(callback), Input::Byte(value)) => {
+                callback(value);
+                None
+            }
+            (Self::BytesRequest(callback), Input::Bytes(value)) => {
+                callback(value);
+                None
+            }
+            (Self::Choice(signals, callback), Input::Choice(chosen))
+                if signals.contains(&chosen) =>
+            {
+                Some(callback(chosen))
+            }
+            (readback, _) => return Err(readback),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_gives_each_path_its_inputs_in_order() {
+        let history = History::new()
+            .then(vec![], Input::Choice(ArcStr::from("move")))
+            .then(vec![0], Input::Nat(BigUint::from(1u8)))
+            .then(vec![], Input::Choice(ArcStr::from("pass")));
+        let branch = history.then(vec![], Input::Choice(ArcStr::from("resign")));
+        assert_eq!(history.len(), 3);
+        assert_eq!(branch.len(), 4);
+
+        let mut replay = Replay::new(&history);
+        assert_eq!(replay.next(&[0]), Some(Input::Nat(BigUint::from(1u8))));
+        assert_eq!(replay.next(&[0]), None);
+        assert_eq!(replay.next(&[]), Some(Input::Choice(ArcStr::from("move"))));
+        assert!(!replay.is_finished());
+        replay.abandon(&[]);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn every_earlier_format_has_a_migration() {
+        assert_eq!(HISTORY_MIGRATIONS.len() as u32, HISTORY_FORMAT - 1);
+    }
+
+    #[test]
+    fn histories_round_trip() {
+        let history = History::new()
+            .then(vec![], Input::Choice(ArcStr::from("move")))
+            .then(vec![0, 1], Input::Int(BigInt::from(-3)))
+            .then(vec![1], Input::Bytes(Bytes::from_static(b"\x00\xff")));
+        let mut bytes = Vec::new();
+        history.write_to(&mut bytes).unwrap();
+        assert!(bytes.starts_with(HISTORY_MAGIC));
+        assert_eq!(History::read_from(bytes.as_slice()).unwrap(), history);
+    }
+
+    #[test]
+    fn newer_formats_are_refused_with_the_reason() {
+        let mut bytes = Vec::new();
+        versioned::write_header(&mut bytes, HISTORY_MAGIC, HISTORY_FORMAT + 1).unwrap();
+        let Err(HistoryError::Incompatible { format, reason, .. }) =
+            History::read_from(bytes.as_slice())
+        else {
+            panic!("a history of a newer format was read");
+        };
+        assert_eq!(format, HISTORY_FORMAT + 1);
+        assert_eq!(
+            reason,
+            format!(
+                "of unsupported format version {}: this version only loads formats up to \
+                 {HISTORY_FORMAT}",
+                HISTORY_FORMAT + 1
+            )
+        );
+    }
+
+    #[test]
+    fn other_files_are_not_histories() {
+        assert!(matches!(
+            History::read_from(&b"PVM\0"[..]),
+            Err(HistoryError::NotAHistory)
+        ));
+    }
+}
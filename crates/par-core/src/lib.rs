@@ -1,14 +1,44 @@
+//! The Par compiler, as a library for tools that embed it.
+//!
+//! The public surface is what's reachable from the root of this crate:
+//!
+//! - [`source`] for locations in source files,
+//! - [`frontend`] for parsing, lowering, and type checking single modules,
+//! - [`workspace`] and [`pipeline`] for doing the same to whole packages,
+//! - [`semantic`] for querying checked workspaces,
+//! - [`types`] for comparing and printing types on their own,
+//! - [`runtime`], [`execution`], and [`testing`] for running what was compiled,
+//!   and reading back its results,
+//! - [`doc_examples`] for running the examples in documentation comments.
+//!
+//! These follow semver, which CI checks with `cargo semver-checks` against
+//! the branch a change is merged into. The compiler's internals, like the
+//! lowered processes and decompiling nets, are only public with the
+//! `internals` feature, and the entry points for fuzzing with `fuzzing`.
+//! Those are for the tools in this repository, and change along with the
+//! compiler.
+
 #![deny(unreachable_pub)]
 
 mod backend;
+pub mod doc_examples;
 #[path = "api.rs"]
 mod facade;
 pub(crate) mod frontend_impl;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+mod history;
 pub(crate) mod location;
+pub mod pipeline;
 #[path = "runtime/mod.rs"]
 pub(crate) mod runtime_impl;
+pub mod semantic;
+mod session_type;
+mod stack;
 pub(crate) mod test_assertion;
 mod typed_readback;
+pub mod types;
 pub mod workspace;
 
 pub use facade::{execution, frontend, runtime, source, testing};
@@ -1,6 +1,8 @@
 use arcstr::ArcStr;
 use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
 pub struct Point {
@@ -12,16 +14,69 @@ pub struct Point {
     pub column: u32,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone)]
 pub enum Span {
     None,
     At {
         start: Point,
         end: Point,
         file: FileName,
+        /// Set on the spans of nodes synthesized by desugaring, pointing at the
+        /// sugar they came from. Ignored when comparing spans.
+        expansion: Option<Arc<Expansion>>,
     },
 }
 
+/// Where a synthesized node came from.
+#[derive(Clone, Debug)]
+pub struct Expansion {
+    /// What was desugared, as it should read in "in code expanded from this …",
+    /// like "`if`" or "list literal".
+    pub sugar: &'static str,
+    pub span: Span,
+}
+
+impl PartialEq for Span {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (
+                Self::At {
+                    start: start1,
+                    end: end1,
+                    file: file1,
+                    ..
+                },
+                Self::At {
+                    start: start2,
+                    end: end2,
+                    file: file2,
+                    ..
+                },
+            ) => start1 == start2 && end1 == end2 && file1 == file2,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Self::None => 0u8.hash(state),
+            Self::At {
+                start, end, file, ..
+            } => {
+                1u8.hash(state);
+                start.hash(state);
+                end.hash(state);
+                file.hash(state);
+            }
+        }
+    }
+}
+
 impl Default for Span {
     fn default() -> Self {
         Self::None
@@ -71,10 +126,16 @@ impl Span {
     pub fn only_start(&self) -> Self {
         match self.clone() {
             Self::None => Self::None,
-            Self::At { start, file, .. } => Self::At {
+            Self::At {
+                start,
+                file,
+                expansion,
+                ..
+            } => Self::At {
                 start,
                 end: start,
                 file,
+                expansion,
             },
         }
     }
@@ -82,14 +143,50 @@ impl Span {
     pub fn only_end(&self) -> Self {
         match self.clone() {
             Self::None => Self::None,
-            Self::At { end, file, .. } => Self::At {
+            Self::At {
+                end,
+                file,
+                expansion,
+                ..
+            } => Self::At {
                 start: end,
                 end,
                 file,
+                expansion,
             },
         }
     }
 
+    /// The same location, marked as synthesized by desugaring `sugar` at
+    /// `origin`. A span that's already marked keeps its outermost expansion.
+    pub fn expanded_from(&self, sugar: &'static str, origin: &Span) -> Self {
+        match self.clone() {
+            Self::None => Self::None,
+            span @ Self::At {
+                expansion: Some(_), ..
+            } => span,
+            Self::At {
+                start, end, file, ..
+            } => Self::At {
+                start,
+                end,
+                file,
+                expansion: Some(Arc::new(Expansion {
+                    sugar,
+                    span: origin.clone(),
+                })),
+            },
+        }
+    }
+
+    /// The sugar this span was synthesized from, if any.
+    pub fn expansion(&self) -> Option<&Expansion> {
+        match self {
+            Self::None => None,
+            Self::At { expansion, .. } => expansion.as_deref(),
+        }
+    }
+
     pub fn join(&self, other: Self) -> Self {
         match (self.clone(), other) {
             (Self::None, span) | (span, Self::None) => span,
@@ -98,11 +195,13 @@ impl Span {
                     start: start1,
                     end: end1,
                     file: file1,
+                    expansion,
                 },
                 Self::At {
                     start: start2,
                     end: end2,
                     file: file2,
+                    ..
                 },
             ) => {
                 assert_eq!(file1, file2, "can't join spans from different files");
@@ -118,6 +217,7 @@ impl Span {
                         end2
                     },
                     file: file1,
+                    expansion,
                 }
             }
         }
@@ -141,13 +241,28 @@ impl Point {
             start: *self,
             end: *self,
             file,
+            expansion: None,
         }
     }
 }
 
+/// The scheme naming the files embedded in builtin packages, as in
+/// `par-builtin:core/List.par`.
+pub const BUILTIN_URI_SCHEME: &str = "par-builtin";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FileName(pub ArcStr);
 
+impl FileName {
+    /// Whether this is a file embedded in a builtin package, which the user
+    /// has no copy of to look at.
+    pub fn is_builtin(&self) -> bool {
+        self.0
+            .strip_prefix(BUILTIN_URI_SCHEME)
+            .is_some_and(|rest| rest.starts_with(':'))
+    }
+}
+
 impl Display for FileName {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(self.0.as_str())
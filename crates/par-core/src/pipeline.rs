@@ -0,0 +1,709 @@
+//! The compiler as a sequence of explicit stages, from source files to nets.
+//!
+//! A [`Pipeline`] runs the stages in order, as far as it's asked to, and keeps
+//! what each stage produced in its [`Artifacts`]. It times every stage, and
+//! runs the passes registered after a stage as soon as that stage finishes, so
+//! tools can inspect or adjust the intermediate artifacts.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use par_runtime::linker::Unlinked;
+use par_runtime::pkgid::PackageId;
+
+use crate::frontend_impl::language::Universal;
+use crate::frontend_impl::lexer::lex_with_comments;
+use crate::frontend_impl::parse::{MAX_NESTING_DEPTH, parse_lexed_with_limit};
+use crate::frontend_impl::types::{ExpansionLimit, TypeError};
+use crate::location::FileName;
+use crate::runtime_impl::{Compiled, RuntimeCompilerError};
+use crate::workspace::{
+    CheckedWorkspace, LoadedPackageFile, LoweredFiles, PackageGraph, SourceOverrides, Workspace,
+    WorkspaceDiscoveryError, WorkspaceError, WorkspacePackage, WorkspacePackages,
+    collect_source_files, lower_workspace_packages, parse_loaded_files_with, resolve_workspace,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// Splits every source file into tokens.
+    Lex,
+    /// Parses the tokens of every file, and groups the files into modules.
+    Parse,
+    /// Desugars every file into process syntax and optimizes it.
+    Lower,
+    /// Resolves imports and names, and merges all files into one workspace.
+    Resolve,
+    TypeCheck,
+    /// Compiles the checked workspace into interaction nets.
+    Compile,
+}
+
+impl Stage {
+    pub const ALL: &'static [Stage] = &[
+        Stage::Lex,
+        Stage::Parse,
+        Stage::Lower,
+        Stage::Resolve,
+        Stage::TypeCheck,
+        Stage::Compile,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lex => "lex",
+            Self::Parse => "parse",
+            Self::Lower => "lower",
+            Self::Resolve => "resolve",
+            Self::TypeCheck => "type check",
+            Self::Compile => "compile",
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How thoroughly [`Pipeline::check`] checks the sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckLevel {
+    /// Parses, resolves names, and checks that every name used is defined and
+    /// visible, and that every type gets the right number of arguments,
+    /// without inferring any types. Fast enough to run on every keystroke,
+    /// while a full check runs in the background.
+    Names,
+    /// Type checks everything.
+    Full,
+}
+
+#[derive(Debug, Clone)]
+pub enum PipelineError {
+    Discovery(WorkspaceDiscoveryError),
+    Workspace(WorkspaceError),
+    Compile(RuntimeCompilerError),
+}
+
+/// The source files of one package, before they're parsed.
+#[derive(Debug, Clone)]
+pub struct SourcePackage {
+    pub id: PackageId,
+    pub dependencies: BTreeMap<String, PackageId>,
+    pub files: Vec<LoadedPackageFile>,
+}
+
+/// What the lexer made of a file.
+#[derive(Debug, Clone)]
+pub struct LexedFile {
+    pub name: FileName,
+    pub tokens: usize,
+    pub comments: usize,
+}
+
+/// Everything the stages have produced so far.
+///
+/// A stage takes the artifacts of the previous one when it needs to own them:
+/// resolving takes the parsed packages and the lowered files, so only the
+/// artifacts of the last stage to run are guaranteed to be present.
+pub struct Artifacts {
+    pub root_package: PackageId,
+    pub sources: Vec<SourcePackage>,
+    /// Set by [`Stage::Lex`].
+    pub lexed: Vec<LexedFile>,
+    /// Set by [`Stage::Parse`].
+    pub packages: Option<WorkspacePackages>,
+    /// Set by [`Stage::Lower`].
+    pub lowered: Option<LoweredFiles>,
+    /// Set by [`Stage::Resolve`].
+    pub workspace: Option<Workspace>,
+    /// Set by [`Stage::TypeCheck`], along with `type_errors`.
+    pub checked: Option<CheckedWorkspace>,
+    /// Also set by [`Pipeline::check`], when it checks names only.
+    pub type_errors: Vec<TypeError<Universal>>,
+    /// Set by [`Stage::Compile`].
+    pub compiled: Option<Compiled<Unlinked>>,
+}
+
+pub type Pass<'a> = Box<dyn FnMut(&mut Artifacts) -> Result<(), PipelineError> + 'a>;
+
+pub struct Pipeline<'a> {
+    artifacts: Artifacts,
+    completed: Option<Stage>,
+    max_interactions: u32,
+    max_nesting_depth: usize,
+    expansion_limit: ExpansionLimit,
+    previous: Option<&'a CheckedWorkspace>,
+    passes: Vec<(Stage, Pass<'a>)>,
+    timings: Vec<(Stage, Duration)>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(root_package: PackageId, sources: Vec<SourcePackage>) -> Self {
+        Self {
+            artifacts: Artifacts {
+                root_package,
+                sources,
+                lexed: Vec::new(),
+                packages: None,
+                lowered: None,
+                workspace: None,
+                checked: None,
+                type_errors: Vec::new(),
+                compiled: None,
+            },
+            completed: None,
+            max_interactions: 10_000,
+            max_nesting_depth: MAX_NESTING_DEPTH,
+            expansion_limit: ExpansionLimit::default(),
+            previous: None,
+            passes: Vec::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    /// Discovers the package containing `start` and its dependencies, and
+    /// reads their source files, replacing the contents of the overridden ones.
+    pub fn from_path(
+        start: impl AsRef<Path>,
+        overrides: Option<&SourceOverrides>,
+    ) -> Result<Self, PipelineError> {
+        let graph = PackageGraph::discover_from_path(start).map_err(PipelineError::Discovery)?;
+        let sources = graph
+            .packages
+            .into_iter()
+            .map(|package| {
+                Ok(SourcePackage {
+                    files: collect_source_files(&package.layout, overrides)
+                        .map_err(WorkspaceDiscoveryError::Load)?,
+                    id: package.id,
+                    dependencies: package.dependencies,
+                })
+            })
+            .collect::<Result<_, WorkspaceDiscoveryError>>()
+            .map_err(PipelineError::Discovery)?;
+        Ok(Self::new(graph.root_package, sources))
+    }
+
+    /// Starts from packages that are already parsed, or built in code, so
+    /// the pipeline continues from [`Stage::Lower`].
+    pub fn from_packages(packages: WorkspacePackages) -> Self {
+        let mut pipeline = Self::new(packages.root_package.clone(), Vec::new());
+        pipeline.artifacts.packages = Some(packages);
+        pipeline.completed = Some(Stage::Parse);
+        pipeline
+    }
+
+    /// Sets how many interactions the compiler may spend normalizing each net.
+    pub fn with_max_interactions(mut self, max_interactions: u32) -> Self {
+        self.max_interactions = max_interactions;
+        self
+    }
+
+    /// Sets how many levels deep expressions and types may nest, before
+    /// parsing reports them as a syntax error. It's 128 unless set.
+    pub fn with_max_nesting_depth(mut self, max_nesting_depth: usize) -> Self {
+        self.max_nesting_depth = max_nesting_depth;
+        self
+    }
+
+    /// Sets how far type checking may expand types, before it reports them
+    /// as expanding without end. See [`ExpansionLimit`] for what it is unless
+    /// set.
+    pub fn with_type_expansion_limit(mut self, expansion_limit: ExpansionLimit) -> Self {
+        self.expansion_limit = expansion_limit;
+        self
+    }
+
+    /// Has type checking take the definitions of the packages that didn't
+    /// change since `previous` was checked from it, instead of checking them
+    /// again. See [`Workspace::type_check_reusing`].
+    pub fn reusing(mut self, previous: &'a CheckedWorkspace) -> Self {
+        self.previous = Some(previous);
+        self
+    }
+
+    /// Runs `pass` every time `after` finishes, after the passes added before it.
+    pub fn add_pass(
+        &mut self,
+        after: Stage,
+        pass: impl FnMut(&mut Artifacts) -> Result<(), PipelineError> + 'a,
+    ) {
+        self.passes.push((after, Box::new(pass)));
+    }
+
+    /// Runs the stages that haven't run yet, up to and including `last`.
+    ///
+    /// Type errors don't stop the pipeline; they're collected in the artifacts.
+    pub fn run_until(&mut self, last: Stage) -> Result<(), PipelineError> {
+        for &stage in Stage::ALL {
+            if stage > last || self.completed.is_some_and(|completed| stage <= completed) {
+                continue;
+            }
+            // Parsing lexes each file right before parsing it, and times both,
+            // so lexing only runs on its own when the run stops there.
+            if stage == Stage::Lex && last > Stage::Lex {
+                continue;
+            }
+            let lexed = self.completed == Some(Stage::Lex);
+            let start = Instant::now();
+            self.run_stage(stage)?;
+            if stage != Stage::Parse {
+                self.record(stage, start.elapsed());
+            }
+            self.completed = Some(stage);
+            if stage == Stage::Parse && !lexed {
+                self.run_passes(Stage::Lex)?;
+            }
+            self.run_passes(stage)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the stages needed to check the sources at `level`, leaving the
+    /// errors found in `type_errors`.
+    ///
+    /// Once the sources are type checked, checking names only doesn't replace
+    /// the errors, as type checking finds all the errors it would.
+    pub fn check(&mut self, level: CheckLevel) -> Result<(), PipelineError> {
+        match level {
+            CheckLevel::Names => {
+                self.run_until(Stage::Resolve)?;
+                if self.completed < Some(Stage::TypeCheck) {
+                    let workspace = self
+                        .artifacts
+                        .workspace
+                        .as_ref()
+                        .expect("resolved workspace");
+                    self.artifacts.type_errors = workspace.check_names();
+                }
+                Ok(())
+            }
+            CheckLevel::Full => self.run_until(Stage::TypeCheck),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), PipelineError> {
+        self.run_until(Stage::Compile)
+    }
+
+    /// The last stage that finished.
+    pub fn completed(&self) -> Option<Stage> {
+        self.completed
+    }
+
+    /// How long each stage took, in the order they ran.
+    pub fn timings(&self) -> &[(Stage, Duration)] {
+        &self.timings
+    }
+
+    pub fn artifacts(&self) -> &Artifacts {
+        &self.artifacts
+    }
+
+    pub fn into_artifacts(self) -> Artifacts {
+        self.artifacts
+    }
+
+    fn run_stage(&mut self, stage: Stage) -> Result<(), PipelineError> {
+        let artifacts = &mut self.artifacts;
+        match stage {
+            Stage::Lex => {
+                artifacts.lexed = artifacts
+                    .sources
+                    .iter()
+                    .flat_map(|package| &package.files)
+                    .map(|file| {
+                        let lexed = lex_with_comments(&file.source, &file.name);
+                        LexedFile {
+                            name: file.name.clone(),
+                            tokens: lexed.tokens.len(),
+                            comments: lexed.comments.len(),
+                        }
+                    })
+                    .collect();
+            }
+            Stage::Parse => {
+                let max_nesting_depth = self.max_nesting_depth;
+                let (mut lexing, mut parsing) = (Duration::ZERO, Duration::ZERO);
+                let mut lexed_files = Vec::new();
+                let mut packages = Vec::new();
+                for package in &artifacts.sources {
+                    let parsed = parse_loaded_files_with(package.files.clone(), |file| {
+                        let start = Instant::now();
+                        let lexed = lex_with_comments(&file.source, &file.name);
+                        lexed_files.push(LexedFile {
+                            name: file.name.clone(),
+                            tokens: lexed.tokens.len(),
+                            comments: lexed.comments.len(),
+                        });
+                        let lexed_at = Instant::now();
+                        let parsed = parse_lexed_with_limit(&file.source, lexed, max_nesting_depth);
+                        lexing += lexed_at - start;
+                        parsing += lexed_at.elapsed();
+                        parsed
+                    })
+                    .map_err(|error| {
+                        PipelineError::Discovery(WorkspaceDiscoveryError::Load(error))
+                    })?;
+                    packages.push(
+                        WorkspacePackage::new(package.id.clone(), parsed)
+                            .with_dependencies(package.dependencies.clone()),
+                    );
+                }
+                artifacts.lexed = lexed_files;
+                artifacts.packages = Some(WorkspacePackages {
+                    root_package: artifacts.root_package.clone(),
+                    packages,
+                });
+                self.record(Stage::Lex, lexing);
+                self.record(Stage::Parse, parsing);
+            }
+            Stage::Lower => {
+                let packages = artifacts.packages.as_ref().expect("parsed packages");
+                artifacts.lowered =
+                    Some(lower_workspace_packages(packages).map_err(PipelineError::Workspace)?);
+            }
+            Stage::Resolve => {
+                let packages = artifacts.packages.take().expect("parsed packages");
+                let lowered = artifacts.lowered.take().expect("lowered files");
+                let workspace =
+                    resolve_workspace(packages, lowered).map_err(PipelineError::Workspace)?;
+                artifacts.workspace = Some(workspace.with_expansion_limit(self.expansion_limit));
+            }
+            Stage::TypeCheck => {
+                let workspace = artifacts.workspace.as_ref().expect("resolved workspace");
+                let (checked, type_errors) = match self.previous {
+                    Some(previous) => workspace.type_check_reusing(previous),
+                    None => workspace.type_check(),
+                };
+                artifacts.checked = Some(checked);
+                artifacts.type_errors = type_errors;
+            }
+            Stage::Compile => {
+                let checked = artifacts.checked.as_ref().expect("checked workspace");
+                artifacts.compiled = Some(
+                    checked
+                        .compile_runtime(self.max_interactions)
+                        .map_err(PipelineError::Compile)?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn run_passes(&mut self, after: Stage) -> Result<(), PipelineError> {
+        for (stage, pass) in &mut self.passes {
+            if *stage == after {
+                pass(&mut self.artifacts)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, stage: Stage, duration: Duration) {
+        self.timings.push((stage, duration));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arcstr::literal;
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+
+    fn pipeline<'a>(source: &str) -> Pipeline<'a> {
+        let id = PackageId::Special(literal!("__test__"));
+        Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("local/Main.par"),
+                    relative_path_from_src: PathBuf::from("Main.par"),
+                    source: source.to_owned(),
+                }],
+            }],
+        )
+    }
+
+    const SOURCE: &str = "module Main\n\ndec Unit : either { .unit! }\ndef Unit = .unit!\n";
+
+    #[test]
+    fn runs_only_up_to_the_requested_stage() {
+        let mut pipeline = pipeline(SOURCE);
+        pipeline.run_until(Stage::Lower).unwrap();
+        assert_eq!(pipeline.completed(), Some(Stage::Lower));
+        assert!(pipeline.artifacts().lowered.is_some());
+        assert!(pipeline.artifacts().workspace.is_none());
+
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert_eq!(pipeline.completed(), Some(Stage::TypeCheck));
+        assert!(pipeline.artifacts().checked.is_some());
+        assert!(pipeline.artifacts().type_errors.is_empty());
+        assert_eq!(
+            pipeline
+                .timings()
+                .iter()
+                .map(|(stage, _)| *stage)
+                .collect::<Vec<_>>(),
+            &Stage::ALL[..5],
+        );
+    }
+
+    #[test]
+    fn lexing_alone_counts_tokens() {
+        let mut pipeline = pipeline(SOURCE);
+        pipeline.run_until(Stage::Lex).unwrap();
+        let lexed = &pipeline.artifacts().lexed;
+        assert_eq!(lexed.len(), 1);
+        assert!(lexed[0].tokens > 0);
+        assert!(pipeline.artifacts().packages.is_none());
+    }
+
+    #[test]
+    fn lowering_drops_channels_that_only_link_a_reference() {
+        use crate::frontend_impl::process::Expression;
+        use crate::frontend_impl::program::DefinitionBody;
+
+        let source = format!("{SOURCE}\ndef Alias = let unit = Unit in unit\n");
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::Lower).unwrap();
+        let lowered = pipeline.artifacts().lowered.as_ref().unwrap();
+        let module = lowered.values().next().unwrap();
+        let alias = module
+            .definitions
+            .iter()
+            .find(|definition| definition.name.primary == "Alias")
+            .unwrap();
+        let DefinitionBody::Par(body) = &alias.body else {
+            panic!("`Alias` is not defined in Par");
+        };
+        assert!(
+            matches!(body.as_ref(), Expression::Global(_, name, _) if name.primary == "Unit"),
+            "{body:?}",
+        );
+
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(pipeline.artifacts().type_errors.is_empty());
+    }
+
+    #[test]
+    fn lowered_applications_point_at_both_the_command_and_the_application() {
+        use crate::frontend_impl::process::{Expression, Process};
+        use crate::frontend_impl::program::DefinitionBody;
+        use crate::location::Spanning;
+
+        let source = format!("{SOURCE}\ndef Applied = Unit.unit\n");
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::Lower).unwrap();
+        let lowered = pipeline.artifacts().lowered.as_ref().unwrap();
+        let module = lowered.values().next().unwrap();
+        let applied = module
+            .definitions
+            .iter()
+            .find(|definition| definition.name.primary == "Applied")
+            .unwrap();
+        let DefinitionBody::Par(body) = &applied.body else {
+            panic!("`Applied` is not defined in Par");
+        };
+        let Expression::Chan { span, process, .. } = body.as_ref() else {
+            panic!("{body:?}");
+        };
+        let Process::Let { then, .. } = process.as_ref() else {
+            panic!("{process:?}");
+        };
+        let signal = then.span();
+        let expansion = signal
+            .expansion()
+            .expect("the signal is marked as expanded");
+        assert_eq!(expansion.sugar, "application");
+        assert_eq!(&expansion.span, span);
+        assert_eq!(signal.len(), ".unit".len() as u32);
+    }
+
+    #[test]
+    fn passes_run_after_their_stage() {
+        let seen = RefCell::new(Vec::new());
+        let mut pipeline = pipeline(SOURCE);
+        pipeline.add_pass(Stage::Resolve, |artifacts| {
+            seen.borrow_mut().push(artifacts.workspace.is_some());
+            Ok(())
+        });
+        pipeline.add_pass(Stage::Lex, |artifacts| {
+            seen.borrow_mut().push(!artifacts.lexed.is_empty());
+            Ok(())
+        });
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        drop(pipeline);
+        assert_eq!(seen.into_inner(), vec![true, true]);
+    }
+
+    #[test]
+    fn checking_names_finds_undefined_names_but_not_type_errors() {
+        let source = format!(
+            "{SOURCE}\ndef Missing: either {{ .unit! }} = Nowhere\n\
+             def Wrong: either {{ .unit! }} = .other!\n"
+        );
+        let undefined = |errors: &[TypeError<Universal>]| {
+            errors.iter().any(|error| {
+                matches!(error, TypeError::GlobalNameNotDefined(_, name) if name.primary == "Nowhere")
+            })
+        };
+
+        let mut pipeline = pipeline(&source);
+        pipeline.check(CheckLevel::Names).unwrap();
+        assert_eq!(pipeline.completed(), Some(Stage::Resolve));
+        assert!(pipeline.artifacts().checked.is_none());
+        assert_eq!(pipeline.artifacts().type_errors.len(), 1);
+        assert!(undefined(&pipeline.artifacts().type_errors));
+
+        pipeline.check(CheckLevel::Full).unwrap();
+        assert!(undefined(&pipeline.artifacts().type_errors));
+        assert!(pipeline.artifacts().type_errors.len() > 1);
+    }
+
+    const ONES: &str = "type Digit = either { .one!, .two! }\n\
+                        type Ones = iterative choice { .close => !, .next => (Digit) self }\n";
+
+    #[test]
+    fn corecursive_definitions_are_checked_and_compiled() {
+        let source = format!(
+            "{SOURCE}\n{ONES}\
+             dec Ones : Ones\n\
+             def Ones = case {{ .close => !, .next => (.one!) Twos }}\n\
+             dec Twos : Ones\n\
+             def Twos = case {{ .close => !, .next => (.two!) Ones }}\n"
+        );
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(
+            pipeline.artifacts().type_errors.is_empty(),
+            "{:?}",
+            pipeline.artifacts().type_errors
+        );
+        pipeline.run().unwrap();
+        assert!(pipeline.artifacts().compiled.is_some());
+    }
+
+    #[test]
+    fn boxed_corecursive_definitions_are_checked() {
+        let source = format!(
+            "{SOURCE}\n\
+             type Clock = iterative box choice {{ .tick => self }}\n\
+             dec Ticking : Clock\n\
+             def Ticking = box case {{ .tick => Ticking }}\n"
+        );
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(
+            pipeline.artifacts().type_errors.is_empty(),
+            "{:?}",
+            pipeline.artifacts().type_errors
+        );
+    }
+
+    #[test]
+    fn corecursion_must_wait_for_a_request() {
+        let source = format!(
+            "{SOURCE}\n{ONES}\
+             dec Ones : Ones\n\
+             def Ones = Ones\n"
+        );
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(
+            pipeline
+                .artifacts()
+                .type_errors
+                .iter()
+                .any(|error| matches!(error, TypeError::UnguardedCorecursion(..))),
+            "{:?}",
+            pipeline.artifacts().type_errors
+        );
+    }
+
+    #[test]
+    fn corecursion_must_not_unfold_itself_within_a_step() {
+        let source = format!(
+            "{SOURCE}\n{ONES}\
+             dec Ones : Ones\n\
+             def Ones = case {{\n\
+               .close => !,\n\
+               .next => do {{\n\
+                 let ones = Ones\n\
+                 ones.next[digit]\n\
+                 ones.close\n\
+               }} in (digit) Ones,\n\
+             }}\n"
+        );
+        let mut pipeline = pipeline(&source);
+        pipeline.run_until(Stage::TypeCheck).unwrap();
+        assert!(
+            pipeline
+                .artifacts()
+                .type_errors
+                .iter()
+                .any(|error| matches!(error, TypeError::UnguardedCorecursion(..))),
+            "{:?}",
+            pipeline.artifacts().type_errors
+        );
+    }
+
+    #[test]
+    fn checks_modules_built_in_code() {
+        use crate::frontend_impl::language::{Expression, GlobalName};
+        use crate::frontend_impl::program::{Declaration, Definition, Module, TypeDef};
+        use crate::frontend_impl::types::Type;
+        use crate::workspace::{ModulePath, ParsedModule, ParsedPackage};
+
+        let answer = GlobalName::local("Answer");
+        let module = |signal: &str| Module {
+            type_defs: vec![
+                TypeDef::new(
+                    answer.clone(),
+                    vec![],
+                    Type::either_of([("yes", Type::break_()), ("no", Type::break_())]),
+                )
+                .exported(),
+            ],
+            declarations: vec![
+                Declaration::new(
+                    GlobalName::local("Yes"),
+                    Type::global(answer.clone(), vec![]),
+                )
+                .exported(),
+            ],
+            definitions: vec![Definition::new(
+                GlobalName::local("Yes"),
+                Expression::unit_signal(signal),
+            )],
+        };
+        let check = |module| {
+            let id = PackageId::Special(literal!("__test__"));
+            let path = ModulePath {
+                directories: vec![],
+                module: "Main".to_owned(),
+            };
+            let mut pipeline = Pipeline::from_packages(WorkspacePackages {
+                root_package: id.clone(),
+                packages: vec![WorkspacePackage::new(
+                    id,
+                    ParsedPackage {
+                        modules: vec![ParsedModule::generated(path, vec![], module)],
+                    },
+                )],
+            });
+            pipeline.check(CheckLevel::Full).unwrap();
+            assert!(pipeline.artifacts().checked.is_some());
+            pipeline.into_artifacts().type_errors
+        };
+
+        assert!(check(module("yes")).is_empty());
+        assert!(!check(module("maybe")).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+mod profile;
 pub(crate) mod tree;
 
 pub use crate::backend::compiler::{Compiled, RuntimeCompilerError};
+pub use profile::Profile;
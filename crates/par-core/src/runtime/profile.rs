@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use par_runtime::flat::stats::Rewrites;
+
+use crate::backend::compiler::Compiled;
+use crate::frontend_impl::language::{GlobalName, Universal};
+
+/// Where a run spent its rewrites, by the definition they happened in.
+///
+/// Counting rewrites by package has to be switched on for the run, with
+/// [`Setup::with_profiling`](par_runtime::setup::Setup::with_profiling);
+/// without it, every rewrite is unattributed.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub total: u64,
+    /// The definitions rewrites happened in, and how many, most first.
+    pub definitions: Vec<(GlobalName<Universal>, u64)>,
+    /// Rewrites that happened outside any definition, like those between
+    /// values coming from outside the program.
+    pub unattributed: u64,
+}
+
+impl Profile {
+    pub fn new<Ext: Clone>(compiled: &Compiled<Ext>, rewrites: &Rewrites) -> Self {
+        let mut by_definition = HashMap::<&GlobalName<Universal>, u64>::new();
+        for (package, count) in &rewrites.by_package {
+            if let Some(owner) = compiled.code.package_owners.get(package) {
+                *by_definition.entry(owner).or_default() += count;
+            }
+        }
+        let mut definitions: Vec<_> = by_definition
+            .into_iter()
+            .map(|(name, count)| (name.clone(), count))
+            .collect();
+        definitions.sort_by(|(a_name, a), (b_name, b)| {
+            b.cmp(a)
+                .then_with(|| a_name.to_string().cmp(&b_name.to_string()))
+        });
+        let total = rewrites.total();
+        let attributed: u64 = definitions.iter().map(|(_, count)| count).sum();
+        Self {
+            total,
+            definitions,
+            unattributed: total.saturating_sub(attributed),
+        }
+    }
+
+    /// The share of all rewrites `count` is, in percent.
+    pub fn percent(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// One line per definition with at least a percent of the rewrites, like
+/// `80.0% of rewrites in BubbleSort.Inner (1234)`, and one for the rest.
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rest = self.unattributed;
+        for (name, count) in &self.definitions {
+            if self.percent(*count) < 1.0 {
+                rest += count;
+                continue;
+            }
+            writeln!(
+                f,
+                "{:5.1}% of rewrites in {name} ({count})",
+                self.percent(*count)
+            )?;
+        }
+        if rest > 0 {
+            writeln!(
+                f,
+                "{:5.1}% of rewrites elsewhere ({rest})",
+                self.percent(rest)
+            )?;
+        }
+        Ok(())
+    }
+}
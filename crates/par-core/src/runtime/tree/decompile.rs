@@ -0,0 +1,271 @@
+//! Reads compiled packages back into process-calculus syntax, to compare what a
+//! net encodes against the IR it was compiled from.
+//!
+//! Every package is printed from the point of view of its root port: a `Times`
+//! node sends, `(value) rest`; a `Par` node receives, `[pattern] rest`; a
+//! `Signal` chooses, `.name rest`; and a `Choice` waits for a signal,
+//! `{ .name => @package }`. Wires that stay in the net are printed as shared
+//! variable names, and nodes with no counterpart in the syntax, such as
+//! duplicators, are printed in net notation: `{a b}` for a duplicator and
+//! `@id$context` for a package applied to a context.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use par_runtime::linker::Unlinked;
+use par_runtime::primitive::Primitive;
+
+use super::net::{Net, Tree, VarState, number_to_string};
+use crate::backend::tree::compiler::IcCompiled;
+use crate::frontend_impl::language::{GlobalName, Universal};
+
+/// Decompiles every global in `compiled`, in compilation order, along with the
+/// packages it refers to that aren't globals themselves.
+pub(crate) fn decompile_globals(compiled: &IcCompiled) -> Vec<(GlobalName<Universal>, String)> {
+    let names = compiled
+        .name_to_id
+        .iter()
+        .map(|(name, id)| (*id, name))
+        .collect::<HashMap<_, _>>();
+
+    compiled
+        .name_to_id
+        .iter()
+        .map(|(name, id)| {
+            let mut decompiler = Decompiler {
+                compiled,
+                names: &names,
+                referenced: BTreeSet::new(),
+            };
+            let mut out = String::new();
+            decompiler.write_package(&mut out, *id);
+
+            let mut written = BTreeSet::from([*id]);
+            while let Some(id) = decompiler
+                .referenced
+                .iter()
+                .copied()
+                .find(|id| !written.contains(id))
+            {
+                written.insert(id);
+                let _ = write!(out, "\n  @{id} = ");
+                decompiler.write_package(&mut out, id);
+            }
+            (name.clone(), out)
+        })
+        .collect()
+}
+
+struct Decompiler<'a> {
+    compiled: &'a IcCompiled,
+    names: &'a HashMap<usize, &'a GlobalName<Universal>>,
+    /// Packages that were referenced by id, to be printed after the global.
+    referenced: BTreeSet<usize>,
+}
+
+impl Decompiler<'_> {
+    fn write_package(&mut self, out: &mut String, id: usize) {
+        let Some(net) = self.compiled.id_to_package.get(&id) else {
+            let _ = write!(out, "<missing package>");
+            return;
+        };
+
+        let mut ports = net.ports.iter();
+        match ports.next() {
+            Some(root) => self.write_tree(out, net, root),
+            None => {
+                let _ = write!(out, "<empty>");
+            }
+        }
+        if let Some(context) = ports.next()
+            && !matches!(context, Tree::Break)
+        {
+            let _ = write!(out, "\n    with context ");
+            self.write_tree(out, net, context);
+        }
+        for (a, b) in net.redexes.iter().chain(&net.waiting_for_reducer) {
+            let _ = write!(out, "\n    where ");
+            self.write_tree(out, net, a);
+            let _ = write!(out, " ~ ");
+            self.write_tree(out, net, b);
+        }
+    }
+
+    fn write_tree(&mut self, out: &mut String, net: &Net<Unlinked>, tree: &Tree<Unlinked>) {
+        match tree {
+            Tree::Var(id) => match net.variables.get(*id) {
+                Some(VarState::Linked(linked)) => self.write_tree(out, net, linked),
+                _ => {
+                    let _ = write!(out, "{}", number_to_string(*id));
+                }
+            },
+            Tree::Era => {
+                let _ = write!(out, "*");
+            }
+            Tree::Break => {
+                let _ = write!(out, "!");
+            }
+            Tree::Continue => {
+                let _ = write!(out, "?");
+            }
+            Tree::Times(rest, value) => {
+                let _ = write!(out, "(");
+                self.write_tree(out, net, value);
+                let _ = write!(out, ") ");
+                self.write_tree(out, net, rest);
+            }
+            Tree::Par(rest, pattern) => {
+                let _ = write!(out, "[");
+                self.write_tree(out, net, pattern);
+                let _ = write!(out, "] ");
+                self.write_tree(out, net, rest);
+            }
+            Tree::Dup(a, b) => {
+                let _ = write!(out, "{{");
+                self.write_tree(out, net, a);
+                let _ = write!(out, " ");
+                self.write_tree(out, net, b);
+                let _ = write!(out, "}}");
+            }
+            Tree::Signal(signal, payload) => {
+                let _ = write!(out, ".{signal} ");
+                self.write_tree(out, net, payload);
+            }
+            Tree::Choice(context, branches, else_branch) => {
+                let mut branches = branches.iter().collect::<Vec<_>>();
+                branches.sort();
+                let _ = write!(out, "{{");
+                for (signal, id) in branches {
+                    let _ = write!(out, " .{signal} => @{id},");
+                    self.referenced.insert(*id);
+                }
+                if let Some(id) = else_branch {
+                    let _ = write!(out, " else => @{id},");
+                    self.referenced.insert(*id);
+                }
+                let _ = write!(out, " }}");
+                if !matches!(context.as_ref(), Tree::Break) {
+                    let _ = write!(out, "$");
+                    self.write_tree(out, net, context);
+                }
+            }
+            Tree::Package(id, context, _) => {
+                match self.names.get(id) {
+                    Some(name) => {
+                        let _ = write!(out, "{name}");
+                    }
+                    None => {
+                        let _ = write!(out, "@{id}");
+                        self.referenced.insert(*id);
+                    }
+                }
+                if !matches!(context.as_ref(), Tree::Break) {
+                    let _ = write!(out, "$");
+                    self.write_tree(out, net, context);
+                }
+            }
+            Tree::Primitive(primitive) => self.write_primitive(out, primitive),
+            Tree::SignalRequest(_)
+            | Tree::IntRequest(_)
+            | Tree::StringRequest(_)
+            | Tree::BytesRequest(_) => {
+                let _ = write!(out, "<request>");
+            }
+            Tree::External(_) | Tree::ExternalBox(_) => {
+                let _ = write!(out, "<external>");
+            }
+        }
+    }
+
+    fn write_primitive(&self, out: &mut String, primitive: &Primitive) {
+        if primitive.pretty(out, 0).is_err() {
+            let _ = write!(out, "<primitive>");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_runtime::pkgid::PackageId;
+
+    use super::*;
+    use crate::location::FileName;
+    use crate::pipeline::{Pipeline, SourcePackage, Stage};
+    use crate::workspace::LoadedPackageFile;
+
+    const SOURCE: &str = "module Test
+
+type Bool = either { .true!, .false! }
+
+dec True : Bool
+def True = .true!
+
+dec Not : [Bool] Bool
+def Not = [b] b.case {
+  .true! => .false!,
+  .false! => .true!,
+}
+
+dec Both : (Bool, Bool)!
+def Both = (True, Not(True))!
+
+dec Swap : [(Bool) Bool] (Bool) Bool
+def Swap = [(a) b] (b) a
+";
+
+    fn decompiled(source: &str) -> String {
+        let id = PackageId::Special(literal!("test"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("test/Test.par"),
+                    relative_path_from_src: PathBuf::from("Test.par"),
+                    source: source.to_owned(),
+                }],
+            }],
+        );
+        pipeline
+            .run_until(Stage::TypeCheck)
+            .expect("checked program");
+        let artifacts = pipeline.into_artifacts();
+        assert!(
+            artifacts.type_errors.is_empty(),
+            "{:?}",
+            artifacts.type_errors
+        );
+        let checked = artifacts.checked.expect("checked workspace");
+        let compiled =
+            IcCompiled::compile_file(checked.checked_module(), 10_000).expect("compiled program");
+        decompile_globals(&compiled)
+            .into_iter()
+            .map(|(name, net)| format!("{name} = {net}\n"))
+            .collect()
+    }
+
+    #[test]
+    fn decompiled_nets_match_golden_file() {
+        let actual = decompiled(SOURCE);
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/runtime/tree/decompile.golden"
+        );
+        if std::env::var_os("PAR_UPDATE_GOLDEN").is_some() {
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        let expected = include_str!("decompile.golden");
+        assert!(
+            actual == expected,
+            "decompiled nets changed; rerun with PAR_UPDATE_GOLDEN=1 to accept them, \
+             and review the diff of {path}\n\n{actual}"
+        );
+    }
+}
@@ -14,5 +14,7 @@
 //! The [`readback`] module exposes an async API to progressively read back normal-form interaction combinator nets
 //! back into Par expressions.
 
+#[cfg(feature = "internals")]
+pub(crate) mod decompile;
 pub(crate) mod net;
 pub(crate) use net::Net;
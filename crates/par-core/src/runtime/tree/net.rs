@@ -34,7 +34,7 @@ pub(crate) fn number_to_string(mut number: usize) -> String {
 /// A `Tree` corresponds to a port that is the root of a tree of interaction combinators.
 /// The `Tree` enum itself contains the whole tree, although it some parts of it might be inside
 /// half-linked `Tree::Var`s
-pub enum Tree<Ext> {
+pub(crate) enum Tree<Ext> {
     Break,
     Continue,
     Era,
@@ -46,11 +46,17 @@ pub enum Tree<Ext> {
     Var(usize),
     Package(usize, Box<Tree<Ext>>, FanBehavior),
 
+    // Requests are only made by reading back from a tree net, which the
+    // compiler doesn't do; the runs go through the flat runtime.
+    #[allow(dead_code)]
     SignalRequest(oneshot::Sender<(ArcStr, Box<Tree<Ext>>)>),
 
     Primitive(Primitive),
+    #[allow(dead_code)]
     IntRequest(oneshot::Sender<BigInt>),
+    #[allow(dead_code)]
     StringRequest(oneshot::Sender<ParString>),
+    #[allow(dead_code)]
     BytesRequest(oneshot::Sender<Bytes>),
 
     External(Ext),
@@ -64,7 +70,7 @@ pub enum Tree<Ext> {
 }
 
 impl<Ext> Tree<Ext> {
-    pub fn map_vars(&mut self, m: &mut impl FnMut(VarId) -> VarId) {
+    pub(crate) fn map_vars(&mut self, m: &mut impl FnMut(VarId) -> VarId) {
         match self {
             Self::Var(x) => *x = m(*x),
             Self::Par(a, b) | Self::Times(a, b) => {
@@ -165,7 +171,7 @@ impl<Ext: Clone> Clone for Tree<Ext> {
 }
 
 #[derive(Debug, Default, Clone)]
-pub struct Rewrites {
+pub(crate) struct Rewrites {
     pub commute: u128,
     pub annihilate: u128,
     pub signal: u128,
@@ -201,11 +207,11 @@ impl core::ops::Add<Rewrites> for Rewrites {
 
 #[allow(unused)]
 impl Rewrites {
-    pub fn total(&self) -> u128 {
+    pub(crate) fn total(&self) -> u128 {
         self.commute + self.annihilate + self.signal + self.expand + self.era + self.resp
     }
 
-    pub fn total_per_second(&self) -> u128 {
+    pub(crate) fn total_per_second(&self) -> u128 {
         let micros = self.busy_duration.as_micros();
         if micros == 0 {
             return 0;
@@ -218,7 +224,7 @@ impl Rewrites {
 /// A Net represents the current state of the runtime
 /// It contains a list of active pairs, as well as a list of free ports.
 /// It also stores a map of variables, which records whether variables were linked by either of their sides
-pub struct Net<Ext> {
+pub(crate) struct Net<Ext> {
     pub ports: VecDeque<Tree<Ext>>,
     pub redexes: VecDeque<(Tree<Ext>, Tree<Ext>)>,
     pub variables: Variables<Ext>,
@@ -243,7 +249,7 @@ impl<Ext> Default for Net<Ext> {
 }
 
 #[derive(Debug, Clone)]
-pub struct Variables<Ext> {
+pub(crate) struct Variables<Ext> {
     vars: Vec<VarState<Ext>>,
     free: Vec<VarId>,
 }
@@ -258,17 +264,17 @@ impl<Ext> Default for Variables<Ext> {
 }
 
 #[derive(Debug, Clone)]
-pub enum VarState<Ext> {
+pub(crate) enum VarState<Ext> {
     Free,
     Linked(Tree<Ext>),
 }
 
 impl<Ext> Variables<Ext> {
-    pub fn get(&self, id: VarId) -> Option<&VarState<Ext>> {
+    pub(crate) fn get(&self, id: VarId) -> Option<&VarState<Ext>> {
         self.vars.get(id)
     }
 
-    pub fn remove_linked(&mut self, id: VarId) -> Result<Tree<Ext>, &mut VarState<Ext>> {
+    pub(crate) fn remove_linked(&mut self, id: VarId) -> Result<Tree<Ext>, &mut VarState<Ext>> {
         while self.vars.len() <= id {
             self.vars.push(VarState::Free);
         }
@@ -287,7 +293,7 @@ impl<Ext> Variables<Ext> {
         }
     }
 
-    pub fn alloc(&mut self) -> VarId {
+    pub(crate) fn alloc(&mut self) -> VarId {
         match self.free.pop() {
             Some(id) => id,
             None => {
@@ -477,7 +483,7 @@ impl<Ext: Clone> Net<Ext> {
         root
     }
 
-    pub fn alter_net(&mut self, net: &mut Net<Ext>) {
+    pub(crate) fn alter_net(&mut self, net: &mut Net<Ext>) {
         // Now, we have to freshen all variables in the tree
         let mut allocated = HashMap::new();
         net.map_vars(&mut |id| {
@@ -496,17 +502,8 @@ impl<Ext: Clone> Net<Ext> {
         self.rewrites = core::mem::take(&mut self.rewrites) + net.rewrites.clone();
     }
 
-    pub fn inject_net(&mut self, mut net: Net<Ext>) -> Tree<Ext> {
-        assert!(net.ports.len() == 2);
-        self.alter_net(&mut net);
-        let cx = net.ports.pop_back().unwrap();
-        let root = net.ports.pop_back().unwrap();
-        self.link(cx, Tree::Break);
-        root
-    }
-
     /// Returns whether a reduction was carried out
-    pub fn reduce_one(&mut self) -> bool {
+    pub(crate) fn reduce_one(&mut self) -> bool {
         #[cfg(not(target_family = "wasm"))]
         if self.rewrites.last_busy_start.is_none() {
             self.rewrites.last_busy_start = Some(Instant::now());
@@ -530,7 +527,7 @@ impl<Ext: Clone> Net<Ext> {
     }
 
     /// Where vars occur in the given tree which already have been linked from the other side, finish linking them.
-    pub fn substitute_tree(&mut self, tree: &mut Tree<Ext>) {
+    pub(crate) fn substitute_tree(&mut self, tree: &mut Tree<Ext>) {
         match tree {
             Tree::Times(a, b) | Tree::Par(a, b) | Tree::Dup(a, b) => {
                 self.substitute_tree(a);
@@ -558,21 +555,44 @@ impl<Ext: Clone> Net<Ext> {
         }
     }
 
-    pub fn normal(&mut self, max_interactions: u32) {
+    pub(crate) fn normal(&mut self, max_interactions: u32) {
         let mut interaction_count: u32 = 0;
-        while self.reduce_one() {
+        // Redexes with packages that are still being built, such as a global
+        // that refers back to itself corecursively, stay as they are.
+        let mut pending = Vec::new();
+        loop {
+            if let Some((a, b)) = self.redexes.front()
+                && (self.is_pending_package(a) || self.is_pending_package(b))
+            {
+                pending.extend(self.redexes.pop_front());
+                continue;
+            }
+            if !self.reduce_one() {
+                break;
+            }
             interaction_count += 1;
             if interaction_count > max_interactions {
                 break;
             }
         }
+        self.redexes.extend(pending);
         // dereference all variables
         let mut ports = core::mem::take(&mut self.ports);
         ports.iter_mut().for_each(|x| self.substitute_tree(x));
         self.ports = ports;
     }
 
-    pub fn link(&mut self, a: Tree<Ext>, b: Tree<Ext>) {
+    fn is_pending_package(&self, tree: &Tree<Ext>) -> bool {
+        match tree {
+            Tree::Package(id, _, _) => self
+                .packages
+                .get(id)
+                .is_some_and(|package| package.ports.is_empty()),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn link(&mut self, a: Tree<Ext>, b: Tree<Ext>) {
         match (a, b) {
             (Tree::Var(mut id), y) | (y, Tree::Var(mut id)) => loop {
                 match self.variables.remove_linked(id) {
@@ -594,12 +614,12 @@ impl<Ext: Clone> Net<Ext> {
         }
     }
 
-    pub fn create_wire(&mut self) -> (Tree<Ext>, Tree<Ext>) {
+    pub(crate) fn create_wire(&mut self) -> (Tree<Ext>, Tree<Ext>) {
         let id = self.variables.alloc();
         (Tree::Var(id), Tree::Var(id))
     }
 
-    pub fn map_vars(&mut self, m: &mut impl FnMut(VarId) -> VarId) {
+    pub(crate) fn map_vars(&mut self, m: &mut impl FnMut(VarId) -> VarId) {
         for port in &mut self.ports {
             port.map_vars(m);
         }
@@ -621,11 +641,11 @@ impl<Ext: Clone> Net<Ext> {
         //}
     }
 
-    pub fn show(&self) -> String {
+    pub(crate) fn show(&self) -> String {
         self.show_indent(0)
     }
 
-    pub fn show_indent(&self, indent: usize) -> String {
+    pub(crate) fn show_indent(&self, indent: usize) -> String {
         use core::fmt::Write;
         let indent_string = "    ".repeat(indent);
         let mut s = String::new();
@@ -645,7 +665,7 @@ impl<Ext: Clone> Net<Ext> {
         s
     }
 
-    pub fn show_tree(&self, t: &Tree<Ext>) -> String {
+    pub(crate) fn show_tree(&self, t: &Tree<Ext>) -> String {
         match t {
             Tree::Var(id) => {
                 if let Some(VarState::Linked(b)) = self.variables.get(*id) {
@@ -736,7 +756,7 @@ impl<Ext: Clone> Net<Ext> {
         }
     }
 
-    pub fn assert_valid_with<'a>(&self, iter: impl Iterator<Item = &'a Tree<Ext>>)
+    pub(crate) fn assert_valid_with<'a>(&self, iter: impl Iterator<Item = &'a Tree<Ext>>)
     where
         Ext: 'a,
     {
@@ -776,7 +796,7 @@ impl<Ext: Clone> Net<Ext> {
         // And in any case, each package is checked when it is created
     }
 
-    pub fn assert_valid(&self) {
+    pub(crate) fn assert_valid(&self) {
         self.assert_valid_with(std::iter::empty());
     }
 
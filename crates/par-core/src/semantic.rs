@@ -0,0 +1,528 @@
+//! A queryable model of a type-checked workspace.
+//!
+//! [`SemanticModel`] is built once after type checking and answers the
+//! questions that the language server, the playground, and the documentation
+//! generator all ask: what a name refers to, what type is at a position, where
+//! a global is used, what depends on what, and what a module exports.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::frontend_impl::language::{GlobalName, LocalName, TypeParameter, Universal};
+use crate::frontend_impl::process::{Command, Expression, HoverInfo, Process};
+use crate::frontend_impl::program::{CheckedModule, DefinitionBody, DocComment, HoverIndex};
+use crate::frontend_impl::skeleton::case_skeleton;
+use crate::frontend_impl::types::visibility::Visibility;
+use crate::frontend_impl::types::{ProtocolStep, Type, TypeDefs};
+use crate::location::{FileName, Span, Spanning};
+use crate::workspace::Workspace;
+
+/// Types and declarations live in separate namespaces, so a name alone doesn't
+/// identify a global.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SymbolKind {
+    Type,
+    Declaration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SymbolId {
+    pub kind: SymbolKind,
+    pub name: GlobalName<Universal>,
+}
+
+impl SymbolId {
+    pub fn type_(name: GlobalName<Universal>) -> Self {
+        Self {
+            kind: SymbolKind::Type,
+            name,
+        }
+    }
+
+    pub fn declaration(name: GlobalName<Universal>) -> Self {
+        Self {
+            kind: SymbolKind::Declaration,
+            name,
+        }
+    }
+}
+
+/// A global type or value, as the checker saw it.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub id: SymbolId,
+    /// Empty for declarations.
+    pub params: Vec<TypeParameter>,
+    /// The body of a type. For a value, its declared type if it has one, its
+    /// inferred type otherwise.
+    pub typ: Type<Universal>,
+    pub doc: Option<DocComment>,
+    /// Whether the item is marked with `export` in the source.
+    pub exported: bool,
+    pub visibility: Visibility,
+    /// The `dec` of a value, or the definition of a type.
+    pub declaration: Option<Site>,
+    /// The `def` of a value, or the definition of a type.
+    pub definition: Option<Site>,
+}
+
+/// Where a symbol is declared or defined.
+#[derive(Debug, Clone)]
+pub struct Site {
+    /// Just the name.
+    pub name: Span,
+    /// The whole item.
+    pub item: Span,
+}
+
+impl Symbol {
+    pub fn name(&self) -> &GlobalName<Universal> {
+        &self.id.name
+    }
+
+    pub fn kind(&self) -> SymbolKind {
+        self.id.kind
+    }
+
+    /// The declaration and the definition, whichever there are.
+    pub fn sites(&self) -> impl Iterator<Item = &Site> {
+        self.declaration.iter().chain(&self.definition)
+    }
+}
+
+/// The items a module makes available to other modules.
+#[derive(Debug, Clone)]
+pub struct ModuleInterface {
+    pub module: Universal,
+    pub doc: Option<DocComment>,
+    pub types: Vec<SymbolId>,
+    pub declarations: Vec<SymbolId>,
+}
+
+/// The branches of a `.case`, or of a choice being constructed, checked
+/// against the type being taken apart.
+#[derive(Debug, Clone)]
+pub struct CaseBranches {
+    /// The whole construct, including its branches.
+    pub span: Span,
+    /// The branches that have their own handler, in source order.
+    pub handled: Vec<LocalName>,
+    /// The branches of the type without their own handler, with their
+    /// payload types.
+    pub missing: Vec<(LocalName, Type<Universal>)>,
+    /// Whether an `else` takes care of the missing branches.
+    pub has_else: bool,
+}
+
+#[derive(Clone)]
+pub struct SemanticModel {
+    symbols: IndexMap<SymbolId, Symbol>,
+    references: HashMap<SymbolId, Vec<Span>>,
+    dependencies: IndexMap<SymbolId, IndexSet<SymbolId>>,
+    module_docs: HashMap<Universal, DocComment>,
+    branch_docs: IndexMap<GlobalName<Universal>, IndexMap<LocalName, DocComment>>,
+    hovers: HoverIndex<Universal>,
+    cases: HashMap<FileName, Vec<CaseBranches>>,
+    type_defs: TypeDefs<Universal>,
+}
+
+impl SemanticModel {
+    pub(crate) fn new(
+        workspace: &Workspace,
+        checked: &CheckedModule<Universal>,
+        hovers: HoverIndex<Universal>,
+    ) -> Self {
+        let mut symbols = IndexMap::new();
+
+        for type_def in &workspace.lowered_module().type_defs {
+            let id = SymbolId::type_(type_def.name.clone());
+            symbols.insert(
+                id.clone(),
+                Symbol {
+                    visibility: workspace.type_visibility(&type_def.name),
+                    id,
+                    params: type_def.params.clone(),
+                    typ: type_def.typ.clone(),
+                    doc: workspace.docs().type_doc_with_branches(&type_def.name),
+                    exported: type_def.exported,
+                    declaration: Some(Site {
+                        name: type_def.name.span.clone(),
+                        item: type_def.span.clone(),
+                    }),
+                    definition: Some(Site {
+                        name: type_def.name.span.clone(),
+                        item: type_def.span.clone(),
+                    }),
+                },
+            );
+        }
+
+        for (name, declaration) in &checked.declarations {
+            let id = SymbolId::declaration(name.clone());
+            symbols.insert(
+                id.clone(),
+                Symbol {
+                    id,
+                    params: Vec::new(),
+                    typ: declaration.typ.clone(),
+                    doc: declaration.doc.clone(),
+                    exported: declaration.exported,
+                    visibility: workspace.declaration_visibility(name),
+                    declaration: Some(Site {
+                        name: name.span.clone(),
+                        item: declaration.span.clone(),
+                    }),
+                    definition: None,
+                },
+            );
+        }
+        for (name, (definition, typ)) in &checked.definitions {
+            let id = SymbolId::declaration(name.clone());
+            let symbol = symbols.entry(id.clone()).or_insert_with(|| Symbol {
+                id,
+                params: Vec::new(),
+                typ: typ.clone(),
+                doc: workspace.declaration_doc(name).cloned(),
+                exported: false,
+                visibility: workspace.declaration_visibility(name),
+                declaration: None,
+                definition: None,
+            });
+            symbol.definition = Some(Site {
+                name: name.span.clone(),
+                item: definition.span.clone(),
+            });
+        }
+
+        // Every hover for a global, except on the names of the items that
+        // introduce it, is a reference to it and a dependency of the item it's in.
+        let mut items_by_file = HashMap::<FileName, Vec<(&Span, &SymbolId)>>::new();
+        let mut own_names = HashSet::new();
+        for symbol in symbols.values() {
+            for site in symbol.sites() {
+                own_names.insert(&site.name);
+                if let Some(file) = site.item.file() {
+                    items_by_file
+                        .entry(file)
+                        .or_default()
+                        .push((&site.item, &symbol.id));
+                }
+            }
+        }
+        let mut references = HashMap::<SymbolId, Vec<Span>>::new();
+        let mut dependencies = IndexMap::<SymbolId, IndexSet<SymbolId>>::new();
+        for (span, hover) in hovers.entries() {
+            let Some(target) = hover_symbol(hover) else {
+                continue;
+            };
+            if !symbols.contains_key(&target) || own_names.contains(&span) {
+                continue;
+            }
+            let enclosing = span
+                .file()
+                .and_then(|file| innermost_enclosing(items_by_file.get(&file)?, &span));
+            if let Some(enclosing) = enclosing
+                && *enclosing != target
+            {
+                dependencies
+                    .entry(enclosing.clone())
+                    .or_default()
+                    .insert(target.clone());
+            }
+            references.entry(target).or_default().push(span);
+        }
+        for spans in references.values_mut() {
+            spans.sort_by_key(|span| {
+                (
+                    span.file().map(|file| file.0),
+                    span.start().map(|start| start.offset),
+                )
+            });
+            spans.dedup();
+        }
+
+        let module_docs = symbols
+            .values()
+            .map(|symbol| &symbol.id.name.module)
+            .collect::<IndexSet<_>>()
+            .into_iter()
+            .filter_map(|module| Some((module.clone(), workspace.module_doc(module)?.clone())))
+            .collect();
+
+        let mut cases = HashMap::<FileName, Vec<CaseBranches>>::new();
+        for (definition, _) in checked.definitions.values() {
+            if let DefinitionBody::Par(expression) = &definition.body {
+                collect_cases_in_expression(expression, &checked.type_defs, &mut |case| {
+                    if let Some(file) = case.span.file() {
+                        cases.entry(file).or_default().push(case);
+                    }
+                });
+            }
+        }
+
+        Self {
+            symbols,
+            references,
+            dependencies,
+            module_docs,
+            branch_docs: workspace.docs().branches.clone(),
+            hovers,
+            cases,
+            type_defs: checked.type_defs.clone(),
+        }
+    }
+
+    /// All the symbols, types first, in source order within each kind.
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.values()
+    }
+
+    pub fn symbol(&self, id: &SymbolId) -> Option<&Symbol> {
+        self.symbols.get(id)
+    }
+
+    /// Looks up a global by its module and its name within the module.
+    pub fn lookup(&self, kind: SymbolKind, module: &Universal, primary: &str) -> Option<&Symbol> {
+        self.symbols.values().find(|symbol| {
+            symbol.kind() == kind
+                && symbol.name().module == *module
+                && symbol.name().primary == primary
+        })
+    }
+
+    /// What's at a 0-based position in a file: a global, a variable, or a
+    /// sub-expression, with its type.
+    pub fn hover_at(&self, file: &FileName, row: u32, column: u32) -> Option<HoverInfo<Universal>> {
+        self.hovers.query(file, row, column)
+    }
+
+    /// The type of whatever is at a 0-based position in a file.
+    pub fn type_at(&self, file: &FileName, row: u32, column: u32) -> Option<Type<Universal>> {
+        self.hover_at(file, row, column)?.typ().cloned()
+    }
+
+    /// The global at a 0-based position in a file, whether it's used or
+    /// defined there.
+    pub fn symbol_at(&self, file: &FileName, row: u32, column: u32) -> Option<&Symbol> {
+        self.symbols
+            .get(&hover_symbol(&self.hover_at(file, row, column)?)?)
+    }
+
+    /// The innermost `.case` or choice construct around a 0-based position in
+    /// a file, with the branches it handles and the ones it doesn't.
+    pub fn case_at(&self, file: &FileName, row: u32, column: u32) -> Option<&CaseBranches> {
+        self.cases
+            .get(file)?
+            .iter()
+            .filter_map(|case| {
+                let (start, end) = case.span.points()?;
+                ((start.row, start.column) <= (row, column)
+                    && (row, column) <= (end.row, end.column))
+                    .then_some((end.offset - start.offset, case))
+            })
+            .min_by_key(|(len, _)| *len)
+            .map(|(_, case)| case)
+    }
+
+    /// The type of whatever is at a 0-based position in a file, with its names
+    /// and fixpoints unfolded `steps` times. See [`Type::unfold_steps`].
+    pub fn unfolded_type_at(
+        &self,
+        file: &FileName,
+        row: u32,
+        column: u32,
+        steps: usize,
+    ) -> Option<Type<Universal>> {
+        Some(
+            self.type_at(file, row, column)?
+                .unfold_steps(&self.type_defs, steps),
+        )
+    }
+
+    /// The first step of the protocol of a type, expanding the names of the
+    /// workspace. See [`Type::protocol_step`].
+    pub fn protocol_step(&self, typ: &Type<Universal>) -> ProtocolStep<Universal> {
+        typ.protocol_step(&self.type_defs)
+    }
+
+    /// A `.case { ... }` taking apart the value at a 0-based position in a
+    /// file, if its type is an `either`. Returns the span of the value, after
+    /// which the skeleton goes, and the skeleton itself, indented to follow
+    /// `indent`. See [`case_skeleton`].
+    pub fn case_skeleton_at(
+        &self,
+        file: &FileName,
+        row: u32,
+        column: u32,
+        indent: &str,
+    ) -> Option<(Span, String)> {
+        let (span, hover) = self.hovers.query_span(file, row, column)?;
+        if hover.is_type() {
+            return None;
+        }
+        let skeleton = case_skeleton(hover.typ()?, &self.type_defs, &self.branch_docs, indent)?;
+        Some((span, skeleton))
+    }
+
+    /// Every place a global is used, other than its own declaration and
+    /// definition, sorted by file and position.
+    pub fn references_of(&self, id: &SymbolId) -> &[Span] {
+        self.references.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The globals that a global's declaration or definition refers to.
+    pub fn dependencies_of(&self, id: &SymbolId) -> impl Iterator<Item = &SymbolId> {
+        self.dependencies.get(id).into_iter().flatten()
+    }
+
+    /// The globals whose declaration or definition refers to a global.
+    pub fn dependents_of<'a>(&'a self, id: &'a SymbolId) -> impl Iterator<Item = &'a SymbolId> {
+        self.dependencies
+            .iter()
+            .filter(move |(_, targets)| targets.contains(id))
+            .map(|(dependent, _)| dependent)
+    }
+
+    /// The types and declarations a module makes visible outside of itself.
+    pub fn module_interface(&self, module: &Universal) -> ModuleInterface {
+        let mut interface = ModuleInterface {
+            module: module.clone(),
+            doc: self.module_docs.get(module).cloned(),
+            types: Vec::new(),
+            declarations: Vec::new(),
+        };
+        for symbol in self.symbols.values() {
+            if symbol.name().module != *module || symbol.visibility == Visibility::Module {
+                continue;
+            }
+            match symbol.kind() {
+                SymbolKind::Type => interface.types.push(symbol.id.clone()),
+                SymbolKind::Declaration => interface.declarations.push(symbol.id.clone()),
+            }
+        }
+        interface
+    }
+
+    pub(crate) fn hover_index(&self) -> &HoverIndex<Universal> {
+        &self.hovers
+    }
+}
+
+fn hover_symbol(hover: &HoverInfo<Universal>) -> Option<SymbolId> {
+    let name = hover.global_name()?.clone();
+    if hover.is_type() {
+        Some(SymbolId::type_(name))
+    } else if hover.is_declaration() {
+        Some(SymbolId::declaration(name))
+    } else {
+        None
+    }
+}
+
+/// The smallest of `items` that contains `span`.
+fn innermost_enclosing<'a>(items: &[(&Span, &'a SymbolId)], span: &Span) -> Option<&'a SymbolId> {
+    let (start, end) = span.points()?;
+    items
+        .iter()
+        .filter_map(|(item, id)| {
+            let (item_start, item_end) = item.points()?;
+            (item_start.offset <= start.offset && end.offset <= item_end.offset)
+                .then_some((item_end.offset - item_start.offset, *id))
+        })
+        .min_by_key(|(len, _)| *len)
+        .map(|(_, id)| id)
+}
+
+fn collect_cases_in_expression(
+    expression: &Expression<Type<Universal>, Universal>,
+    type_defs: &TypeDefs<Universal>,
+    consume: &mut impl FnMut(CaseBranches),
+) {
+    match expression {
+        Expression::Box(_, _, expression, _) => {
+            collect_cases_in_expression(expression, type_defs, consume)
+        }
+        Expression::Chan { process, .. } => collect_cases_in_process(process, type_defs, consume),
+        Expression::Global(..)
+        | Expression::Variable(..)
+        | Expression::Primitive(..)
+        | Expression::External(..) => {}
+    }
+}
+
+fn collect_cases_in_process(
+    process: &Process<Type<Universal>, Universal>,
+    type_defs: &TypeDefs<Universal>,
+    consume: &mut impl FnMut(CaseBranches),
+) {
+    match process {
+        Process::Let { value, then, .. } => {
+            collect_cases_in_expression(value, type_defs, consume);
+            collect_cases_in_process(then, type_defs, consume);
+        }
+        Process::Do {
+            span, typ, command, ..
+        } => match command {
+            Command::Link(expression) => {
+                collect_cases_in_expression(expression, type_defs, consume)
+            }
+            Command::Send(argument, process) => {
+                collect_cases_in_expression(argument, type_defs, consume);
+                collect_cases_in_process(process, type_defs, consume);
+            }
+            Command::Case(branches, processes, else_process) => {
+                if let Some(mut remaining) = typ.either_branches(type_defs) {
+                    let span = processes
+                        .iter()
+                        .chain(else_process)
+                        .fold(span.clone(), |span, process| span.join(process.span()));
+                    for branch in branches.iter() {
+                        remaining.remove(branch);
+                    }
+                    consume(CaseBranches {
+                        span,
+                        handled: branches.to_vec(),
+                        missing: remaining.into_iter().collect(),
+                        has_else: else_process.is_some(),
+                    });
+                }
+                for process in processes.iter().chain(else_process) {
+                    collect_cases_in_process(process, type_defs, consume);
+                }
+            }
+            Command::Noop(process)
+            | Command::Receive(_, _, _, process, _)
+            | Command::Signal(_, process)
+            | Command::Continue(process)
+            | Command::Begin { body: process, .. }
+            | Command::SendType(_, process)
+            | Command::ReceiveType(_, process) => {
+                collect_cases_in_process(process, type_defs, consume)
+            }
+            Command::Break | Command::Loop(..) => {}
+        },
+        Process::Poll {
+            clients,
+            then,
+            else_,
+            ..
+        } => {
+            for client in clients {
+                collect_cases_in_expression(client, type_defs, consume);
+            }
+            collect_cases_in_process(then, type_defs, consume);
+            collect_cases_in_process(else_, type_defs, consume);
+        }
+        Process::Submit { values, .. } => {
+            for value in values {
+                collect_cases_in_expression(value, type_defs, consume);
+            }
+        }
+        Process::Block(_, _, body, then) => {
+            collect_cases_in_process(body, type_defs, consume);
+            collect_cases_in_process(then, type_defs, consume);
+        }
+        Process::Goto(..) | Process::Unreachable(_) => {}
+    }
+}
@@ -0,0 +1,231 @@
+//! Session types as two programs compare them, before talking to each other.
+//!
+//! Types are erased when compiled, so a builtin that connects to another
+//! program, like `Remote.Connect`, can't tell what type its caller expects
+//! the session to have. Builtins registered with [`SessionTypeArgument`] are
+//! given it by the compiler instead: their type argument, which would be
+//! erased, is sent to them as the bytes of its [`SessionType`].
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::LazyLock;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use par_runtime::linker::Unlinked;
+use par_runtime::registry::DefinitionRef;
+
+use crate::frontend::{PrimitiveType, Type, TypeDefs};
+use crate::frontend_impl::language::{LocalName, Universal};
+use crate::workspace::render_compact_type_in_scope;
+
+/// An external definition taking a type as its first argument, which is
+/// given the [`SessionType`] of that type, instead of nothing.
+///
+/// If the type can't be laid out, because it's generic or has boxes, the
+/// argument is erased as usual, and the definition receives its next
+/// argument right away.
+#[derive(Clone, Copy)]
+pub struct SessionTypeArgument {
+    pub path: DefinitionRef<'static>,
+}
+
+inventory::collect!(SessionTypeArgument);
+
+static REGISTRY: LazyLock<HashSet<Unlinked>> = LazyLock::new(|| {
+    inventory::iter::<SessionTypeArgument>
+        .into_iter()
+        .map(|argument| argument.path.into())
+        .collect()
+});
+
+pub(crate) fn takes_session_type(path: &Unlinked) -> bool {
+    REGISTRY.contains(path)
+}
+
+/// The steps of a session type, with every name expanded, and how the type
+/// was written, for messages.
+///
+/// Two session types are equal when their steps are, whatever the names of
+/// the types they were written with.
+#[derive(Clone, Debug)]
+pub struct SessionType {
+    steps: Bytes,
+    shown: String,
+}
+
+impl PartialEq for SessionType {
+    fn eq(&self, other: &Self) -> bool {
+        self.steps == other.steps
+    }
+}
+
+impl Eq for SessionType {}
+
+impl SessionType {
+    /// Lays out `typ`, or returns `None` if it has parts that aren't the same
+    /// on both sides of a connection: type variables, boxes, and quantifiers.
+    pub fn of(type_defs: &TypeDefs<Universal>, typ: &Type<Universal>) -> Option<Self> {
+        let mut steps = BytesMut::new();
+        lay_out(type_defs, typ, &mut Vec::new(), &mut steps)?;
+        Some(Self {
+            steps: steps.freeze(),
+            shown: render_compact_type_in_scope(None, typ),
+        })
+    }
+
+    /// The type as it was written.
+    pub fn shown(&self) -> &str {
+        &self.shown
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        put_str(&mut buf, &self.shown);
+        buf.put_slice(&self.steps);
+        buf.freeze()
+    }
+
+    pub fn from_bytes(bytes: &Bytes) -> Option<Self> {
+        let length = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let shown = bytes.get(4..4 + length)?;
+        Some(Self {
+            shown: String::from_utf8(shown.to_vec()).ok()?,
+            steps: bytes.slice(4 + length..),
+        })
+    }
+}
+
+fn put_str(buf: &mut BytesMut, string: &str) {
+    buf.put_u32_le(string.len() as u32);
+    buf.put_slice(string.as_bytes());
+}
+
+fn put_label(buf: &mut BytesMut, label: &Option<LocalName>) {
+    match label {
+        Some(label) => {
+            buf.put_u8(1);
+            put_str(buf, &label.string);
+        }
+        None => buf.put_u8(0),
+    }
+}
+
+/// Primitives are tagged in the order they're listed in `PrimitiveType`.
+fn primitive_tag(typ: &PrimitiveType) -> u8 {
+    match typ {
+        PrimitiveType::Nat => 0,
+        PrimitiveType::Int => 1,
+        PrimitiveType::Float => 2,
+        PrimitiveType::String => 3,
+        PrimitiveType::Char => 4,
+        PrimitiveType::Byte => 5,
+        PrimitiveType::Bytes => 6,
+    }
+}
+
+/// Writes the steps of `typ`, expanding names on the way. A name met again
+/// inside its own expansion is written as how far up it was expanded.
+fn lay_out(
+    type_defs: &TypeDefs<Universal>,
+    typ: &Type<Universal>,
+    expanding: &mut Vec<Type<Universal>>,
+    buf: &mut BytesMut,
+) -> Option<()> {
+    match typ {
+        Type::Primitive(_, primitive) => {
+            buf.put_u8(0);
+            buf.put_u8(primitive_tag(primitive));
+        }
+        Type::DualPrimitive(_, primitive) => {
+            buf.put_u8(1);
+            buf.put_u8(primitive_tag(primitive));
+        }
+        Type::Break(_) => buf.put_u8(2),
+        Type::Continue(_) => buf.put_u8(3),
+
+        Type::Name(..) | Type::DualName(..) if expanding.contains(typ) => {
+            let index = expanding.iter().rev().position(|name| name == typ)?;
+            buf.put_u8(4);
+            buf.put_u32_le(index as u32);
+        }
+        Type::Name(span, name, args) | Type::DualName(span, name, args) => {
+            let expanded = match typ {
+                Type::Name(..) => type_defs.get(span, name, args),
+                _ => type_defs.get_dual(span, name, args),
+            }
+            .ok()?;
+            expanding.push(typ.clone());
+            let laid_out = lay_out(type_defs, &expanded, expanding, buf);
+            expanding.pop();
+            laid_out?;
+        }
+        Type::Union(..) => {
+            let expanded = typ.expand_definition(type_defs).ok()?;
+            lay_out(type_defs, &expanded, expanding, buf)?;
+        }
+
+        Type::Pair(_, _, _, vars) | Type::Function(_, _, _, vars) if !vars.is_empty() => {
+            return None;
+        }
+        Type::Pair(_, left, right, _) => {
+            buf.put_u8(5);
+            lay_out(type_defs, left, expanding, buf)?;
+            lay_out(type_defs, right, expanding, buf)?;
+        }
+        Type::Function(_, left, right, _) => {
+            buf.put_u8(6);
+            lay_out(type_defs, left, expanding, buf)?;
+            lay_out(type_defs, right, expanding, buf)?;
+        }
+        Type::Either(_, branches) => {
+            buf.put_u8(7);
+            lay_out_branches(type_defs, branches, expanding, buf)?;
+        }
+        Type::Choice(_, branches) => {
+            buf.put_u8(8);
+            lay_out_branches(type_defs, branches, expanding, buf)?;
+        }
+        Type::Recursive { label, body, .. } => {
+            buf.put_u8(9);
+            put_label(buf, label);
+            lay_out(type_defs, body, expanding, buf)?;
+        }
+        Type::Iterative { label, body, .. } => {
+            buf.put_u8(10);
+            put_label(buf, label);
+            lay_out(type_defs, body, expanding, buf)?;
+        }
+        Type::Self_(_, label) => {
+            buf.put_u8(11);
+            put_label(buf, label);
+        }
+        Type::DualSelf(_, label) => {
+            buf.put_u8(12);
+            put_label(buf, label);
+        }
+
+        Type::Box(..)
+        | Type::DualBox(..)
+        | Type::Exists(..)
+        | Type::Forall(..)
+        | Type::Var(..)
+        | Type::DualVar(..)
+        | Type::Hole(..)
+        | Type::DualHole(..)
+        | Type::Fail(..) => return None,
+    }
+    Some(())
+}
+
+fn lay_out_branches(
+    type_defs: &TypeDefs<Universal>,
+    branches: &BTreeMap<LocalName, Type<Universal>>,
+    expanding: &mut Vec<Type<Universal>>,
+    buf: &mut BytesMut,
+) -> Option<()> {
+    buf.put_u32_le(branches.len() as u32);
+    for (label, branch) in branches {
+        put_str(buf, &label.string);
+        lay_out(type_defs, branch, expanding, buf)?;
+    }
+    Some(())
+}
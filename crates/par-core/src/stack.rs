@@ -0,0 +1,19 @@
+//! Room on the stack for the passes that recurse into the syntax tree.
+//!
+//! Parsing, lowering, type checking and compiling each descend once per level
+//! of nesting, and in debug builds, a level takes tens of kilobytes. The
+//! parser keeps programs from nesting deeper than
+//! [`MAX_NESTING_DEPTH`](crate::frontend_impl::parse::MAX_NESTING_DEPTH)
+//! levels, and the passes call [`grow`] at every level, so that every program
+//! that parses makes it through, whatever the stack of the thread they run on.
+
+/// Less than this left on the stack, and the next level gets a new segment.
+const RED_ZONE: usize = 1024 * 1024;
+
+/// The size of every new segment.
+const SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+/// Runs `f`, on a new segment of the stack if little is left on this one.
+pub(crate) fn grow<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(RED_ZONE, SEGMENT_SIZE, f)
+}
@@ -1,11 +1,15 @@
 use crate::frontend::{PrimitiveType, Type, TypeDefs};
-use crate::frontend_impl::language::{LocalName, Universal};
+use crate::frontend_impl::language::{GlobalName, LocalName, Universal};
 use crate::location::Span;
+use crate::session_type::SessionType;
 use arcstr::ArcStr;
 use bytes::Bytes;
+use futures::future::BoxFuture;
 use num_bigint::{BigInt, BigUint};
-use par_runtime::primitive::ParString;
+use par_runtime::data::Data;
+use par_runtime::primitive::{Number, ParString, Primitive};
 use par_runtime::readback::Handle;
+use std::fmt;
 
 pub enum TypedReadback {
     Nat(BigUint),
@@ -44,31 +48,156 @@ pub struct TypedHandle {
 }
 
 pub fn type_supports_readback(type_defs: &TypeDefs<Universal>, typ: &Type<Universal>) -> bool {
+    type_supports_readback_with(type_defs, typ, &|_, _| false)
+}
+
+/// Like [`type_supports_readback`], but also accepts any named type for which
+/// `custom(name, dual)` returns `true`, without looking into its definition.
+/// Embedders use this for types they read back themselves.
+pub fn type_supports_readback_with(
+    type_defs: &TypeDefs<Universal>,
+    typ: &Type<Universal>,
+    custom: &dyn Fn(&GlobalName<Universal>, bool) -> bool,
+) -> bool {
+    unsupported_readback_with(type_defs, typ, custom).is_none()
+}
+
+/// The first part of a type that can't be read back, and where it is in the
+/// whole type.
+#[derive(Clone, Debug)]
+pub struct UnsupportedReadback {
+    /// The part that can't be read back.
+    pub typ: Type<Universal>,
+    /// How to get to the part from the whole type, outermost step first.
+    pub path: Vec<ReadbackStep>,
+}
+
+/// A step into a part of a type, from the part containing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadbackStep {
+    /// The value sent first in a pair.
+    Sent,
+    /// What a pair continues as, after its value.
+    Continuation,
+    /// The argument of a function.
+    Argument,
+    /// The result of a function.
+    Result,
+    /// The payload of a branch of an `either` or a `choice`.
+    Branch(ArcStr),
+    /// The definition of a named type.
+    Definition(GlobalName<Universal>),
+}
+
+impl fmt::Display for ReadbackStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sent => write!(f, "sent value"),
+            Self::Continuation => write!(f, "continuation"),
+            Self::Argument => write!(f, "argument"),
+            Self::Result => write!(f, "result"),
+            Self::Branch(label) => write!(f, ".{label}"),
+            Self::Definition(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl UnsupportedReadback {
+    /// The path to the part, like `result > .item > sent value`, or `the whole
+    /// type` if it's the whole type.
+    pub fn location(&self) -> String {
+        if self.path.is_empty() {
+            return String::from("the whole type");
+        }
+        self.path
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+/// Like [`type_supports_readback_with`], but tells the part that isn't
+/// supported, so that running a definition can fail right away, instead of
+/// getting stuck at that part.
+pub fn unsupported_readback_with(
+    type_defs: &TypeDefs<Universal>,
+    typ: &Type<Universal>,
+    custom: &dyn Fn(&GlobalName<Universal>, bool) -> bool,
+) -> Option<UnsupportedReadback> {
+    let mut unsupported = find_unsupported(type_defs, typ, custom, &mut Vec::new())?;
+    unsupported.path.reverse();
+    Some(unsupported)
+}
+
+/// Like [`unsupported_readback_with`], with the names being expanded on the
+/// way here, and the path built innermost step first.
+fn find_unsupported(
+    type_defs: &TypeDefs<Universal>,
+    typ: &Type<Universal>,
+    custom: &dyn Fn(&GlobalName<Universal>, bool) -> bool,
+    expanding: &mut Vec<Type<Universal>>,
+) -> Option<UnsupportedReadback> {
+    let unsupported = || {
+        Some(UnsupportedReadback {
+            typ: typ.clone(),
+            path: Vec::new(),
+        })
+    };
+    let find = |child: &Type<Universal>, step, expanding: &mut Vec<Type<Universal>>| {
+        let mut unsupported = find_unsupported(type_defs, child, custom, expanding)?;
+        unsupported.path.push(step);
+        Some(unsupported)
+    };
     match typ {
         Type::Primitive(..)
         | Type::DualPrimitive(..)
         | Type::Break(..)
         | Type::Continue(..)
         | Type::Self_(..)
-        | Type::DualSelf(..) => true,
-
-        Type::Name(span, name, args) => type_defs
-            .get(span, name, args)
-            .is_ok_and(|typ| type_supports_readback(type_defs, &typ)),
-        Type::DualName(span, name, args) => type_defs
-            .get_dual(span, name, args)
-            .is_ok_and(|typ| type_supports_readback(type_defs, &typ)),
-
-        Type::Pair(_, left, right, vars) | Type::Function(_, left, right, vars) => {
-            vars.is_empty()
-                && type_supports_readback(type_defs, left)
-                && type_supports_readback(type_defs, right)
+        | Type::DualSelf(..) => None,
+
+        Type::Name(_, name, _) if custom(name, false) => None,
+        Type::DualName(_, name, _) if custom(name, true) => None,
+        // A name met again inside its own expansion refers back among mutually
+        // recursive types, and is read back like a `self`.
+        Type::Name(..) | Type::DualName(..) if expanding.contains(typ) => None,
+        Type::Name(span, name, args) | Type::DualName(span, name, args) => {
+            let expanded = match typ {
+                Type::Name(..) => type_defs.get(span, name, args),
+                _ => type_defs.get_dual(span, name, args),
+            };
+            let Ok(expanded) = expanded else {
+                return unsupported();
+            };
+            expanding.push(typ.clone());
+            let found = find(&expanded, ReadbackStep::Definition(name.clone()), expanding);
+            expanding.pop();
+            found
+        }
+
+        Type::Pair(_, _, _, vars) | Type::Function(_, _, _, vars) if !vars.is_empty() => {
+            unsupported()
         }
-        Type::Either(_, branches) | Type::Choice(_, branches) => branches
-            .values()
-            .all(|branch| type_supports_readback(type_defs, branch)),
+        Type::Pair(_, left, right, _) => find(left, ReadbackStep::Sent, expanding)
+            .or_else(|| find(right, ReadbackStep::Continuation, expanding)),
+        Type::Function(_, left, right, _) => find(left, ReadbackStep::Argument, expanding)
+            .or_else(|| find(right, ReadbackStep::Result, expanding)),
+        Type::Either(_, branches) | Type::Choice(_, branches) => {
+            branches.iter().find_map(|(label, branch)| {
+                find(
+                    branch,
+                    ReadbackStep::Branch(label.string.clone()),
+                    expanding,
+                )
+            })
+        }
+        Type::Union(..) => match typ.expand_definition(type_defs) {
+            Ok(typ) => find_unsupported(type_defs, &typ, custom, expanding),
+            Err(_) => unsupported(),
+        },
         Type::Recursive { body, .. } | Type::Iterative { body, .. } => {
-            type_supports_readback(type_defs, body)
+            find_unsupported(type_defs, body, custom, expanding)
         }
 
         Type::Box(..)
@@ -79,7 +208,7 @@ pub fn type_supports_readback(type_defs: &TypeDefs<Universal>, typ: &Type<Univer
         | Type::DualVar(..)
         | Type::Hole(..)
         | Type::DualHole(..)
-        | Type::Fail(..) => false,
+        | Type::Fail(..) => unsupported(),
     }
 }
 
@@ -92,6 +221,34 @@ impl TypedHandle {
         }
     }
 
+    /// The name of the type being read back, if it's a named type, along with
+    /// whether it's the dual of that type. Only meaningful before
+    /// [`readback`](Self::readback), which expands names away.
+    pub fn type_name(&self) -> Option<(&GlobalName<Universal>, bool)> {
+        match &self.typ {
+            Type::Name(_, name, _) => Some((name, false)),
+            Type::DualName(_, name, _) => Some((name, true)),
+            _ => None,
+        }
+    }
+
+    /// The type of what's left to read back, as the program sees it: a request
+    /// shows what the program expects next.
+    pub fn typ(&self) -> &Type<Universal> {
+        &self.typ
+    }
+
+    /// The [`SessionType`] of what's left to read back, if it can be laid out.
+    pub fn session_type(&self) -> Option<SessionType> {
+        SessionType::of(&self.type_defs, &self.typ)
+    }
+
+    /// Gives up the type information, for embedders that interpret the value
+    /// themselves.
+    pub fn into_handle(self) -> Handle {
+        self.handle
+    }
+
     pub async fn readback(mut self) -> TypedReadback {
         self.prepare_for_readback();
         let typ = std::mem::replace(&mut self.typ, Type::Break(Span::None));
@@ -205,6 +362,52 @@ impl TypedHandle {
         }
     }
 
+    /// Reads back a whole value, to its end, as data. Gives `None` at the
+    /// first part that isn't data: one that would wait for something from the
+    /// reader, or that can't be read back at all. Check that the type satisfies
+    /// the `data` constraint beforehand to never get there.
+    pub fn readback_data(self) -> BoxFuture<'static, Option<Data>> {
+        Box::pin(async move {
+            let primitive = |primitive| Some(Data::Primitive(primitive));
+            match self.readback().await {
+                TypedReadback::Nat(value) => {
+                    primitive(Primitive::Number(Number::Int(value.into())))
+                }
+                TypedReadback::Int(value) => primitive(Primitive::Number(Number::Int(value))),
+                TypedReadback::Float(value) => primitive(Primitive::Number(Number::Float(value))),
+                TypedReadback::String(value) => primitive(Primitive::String(value)),
+                TypedReadback::Char(value) => primitive(Primitive::String(
+                    ParString::copy_from_slice(value.encode_utf8(&mut [0; 4])),
+                )),
+                TypedReadback::Byte(value) => {
+                    primitive(Primitive::Bytes(Bytes::copy_from_slice(&[value])))
+                }
+                TypedReadback::Bytes(value) => primitive(Primitive::Bytes(value)),
+                TypedReadback::Times(left, right) => {
+                    let left = left.readback_data().await?;
+                    let right = right.readback_data().await?;
+                    Some(Data::Pair(Box::new(left), Box::new(right)))
+                }
+                TypedReadback::Either(label, payload) => Some(Data::Either(
+                    label,
+                    Box::new(payload.readback_data().await?),
+                )),
+                TypedReadback::Break => Some(Data::Unit),
+                TypedReadback::NatRequest(_)
+                | TypedReadback::IntRequest(_)
+                | TypedReadback::FloatRequest(_)
+                | TypedReadback::StringRequest(_)
+                | TypedReadback::CharRequest(_)
+                | TypedReadback::ByteRequest(_)
+                | TypedReadback::BytesRequest(_)
+                | TypedReadback::Par(_, _)
+                | TypedReadback::Choice(_, _)
+                | TypedReadback::Continue
+                | TypedReadback::Unreadable { .. } => None,
+            }
+        })
+    }
+
     fn prepare_for_readback(&mut self) {
         self.typ = expand_type(
             std::mem::replace(&mut self.typ, Type::Break(Span::None)),
@@ -219,6 +422,9 @@ fn expand_type(typ: Type<Universal>, type_defs: &TypeDefs<Universal>) -> Type<Un
         typ = match typ {
             Type::Name(span, name, args) => type_defs.get(&span, &name, &args).unwrap(),
             Type::DualName(span, name, args) => type_defs.get_dual(&span, &name, &args).unwrap(),
+            Type::Union(span, kind, left, right) => {
+                type_defs.union(&span, kind, &left, &right).unwrap()
+            }
             Type::Box(_, inner) => expand_type(*inner, type_defs),
             Type::DualBox(_, inner) if !inner.is_linear(type_defs).unwrap() => {
                 expand_type(inner.clone().dual(Span::None), type_defs)
@@ -229,7 +435,14 @@ fn expand_type(typ: Type<Universal>, type_defs: &TypeDefs<Universal>) -> Type<Un
                 label,
                 body,
                 display_hint,
-            } => Type::expand_recursive(&asc, &label, &body, display_hint.0.as_ref()).unwrap(),
+            } => Type::expand_recursive(
+                &asc,
+                &label,
+                &body,
+                display_hint.0.as_ref(),
+                type_defs.expansion_limit,
+            )
+            .unwrap(),
             Type::Iterative {
                 span,
                 asc,
@@ -244,6 +457,7 @@ fn expand_type(typ: Type<Universal>, type_defs: &TypeDefs<Universal>) -> Type<Un
                         &label,
                         &body,
                         display_hint.0.as_ref(),
+                        type_defs.expansion_limit,
                     )
                     .unwrap()
                 } else {
@@ -260,3 +474,42 @@ fn expand_type(typ: Type<Universal>, type_defs: &TypeDefs<Universal>) -> Type<Un
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_parts_are_found_with_their_location() {
+        let type_defs = TypeDefs::default();
+        let supported = Type::function(
+            Type::function(Type::int(), Type::int()),
+            Type::either(vec![
+                ("end", Type::break_()),
+                ("item", Type::pair(Type::nat(), Type::break_())),
+            ]),
+        );
+        assert!(unsupported_readback_with(&type_defs, &supported, &|_, _| false).is_none());
+
+        let boxed = Type::box_(Type::int());
+        let typ = Type::function(
+            Type::nat(),
+            Type::either(vec![
+                ("end", Type::break_()),
+                ("item", Type::pair(boxed.clone(), Type::break_())),
+            ]),
+        );
+        let unsupported = unsupported_readback_with(&type_defs, &typ, &|_, _| false).unwrap();
+        assert_eq!(unsupported.typ, boxed);
+        assert_eq!(
+            unsupported.path,
+            [
+                ReadbackStep::Result,
+                ReadbackStep::Branch(ArcStr::from("item")),
+                ReadbackStep::Sent,
+            ]
+        );
+        assert_eq!(unsupported.location(), "result > .item > sent value");
+        assert!(!type_supports_readback(&type_defs, &typ));
+    }
+}
@@ -0,0 +1,235 @@
+//! Types on their own: reading their definitions from source, comparing them,
+//! taking their duals, and printing them.
+//!
+//! For tools that ask questions about protocols, like whether one refines
+//! another, without checking a whole program:
+//!
+//! ```
+//! use par_core::types::Types;
+//!
+//! let types = Types::from_source(
+//!     "module Types
+//!
+//!     type Request = choice { .get => ! }
+//!     type Service = choice { .get => !, .put => ! }
+//!     ",
+//! )
+//! .unwrap();
+//! let request = types.named("Request").unwrap();
+//! let service = types.named("Service").unwrap();
+//!
+//! // Anything offering both `.get` and `.put` can stand in for a `Request`.
+//! assert!(types.refines(&service, &request).unwrap());
+//! assert!(!types.refines(&request, &service).unwrap());
+//! assert_eq!(types.render_compact(&types.dual(&request)), "dual Request");
+//! ```
+//!
+//! Like the rest of the public surface, this follows semver.
+
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arcstr::literal;
+use par_runtime::pkgid::PackageId;
+
+use crate::frontend_impl::parse::parse_source_file;
+use crate::location::{FileName, Span};
+use crate::pipeline::{Pipeline, PipelineError, SourcePackage, Stage};
+use crate::workspace::{
+    CheckedWorkspace, FileImportScope, LoadedPackageFile, render_compact_type_in_scope,
+    render_type_in_scope,
+};
+
+pub use crate::frontend_impl::language::{GlobalName, Universal};
+pub use crate::frontend_impl::types::{
+    ExpansionLimit, PrimitiveType, ProtocolStep, Type, TypeDefs, TypeError,
+};
+
+/// Type definitions, and the module that names in them are written from.
+#[derive(Clone, Debug)]
+pub struct Types {
+    type_defs: TypeDefs<Universal>,
+    scope: Option<FileImportScope<Universal>>,
+}
+
+#[derive(Debug)]
+pub enum TypesError {
+    /// The source couldn't be parsed, or its names resolved.
+    Pipeline(PipelineError),
+    /// Some type definitions are invalid, like ones referring to types that
+    /// don't exist, or defined in terms of themselves.
+    Invalid {
+        source: Arc<str>,
+        errors: Vec<TypeError<Universal>>,
+    },
+}
+
+impl Types {
+    /// The type definitions in the source of a module, with its `module`
+    /// declaration. Types can only refer to each other, as there are no
+    /// other modules to import; declarations and definitions are ignored.
+    pub fn from_source(source: &str) -> Result<Self, TypesError> {
+        // A file has to be named after its module.
+        let module = parse_source_file(source, FileName::from("Types.par"))
+            .ok()
+            .and_then(|file| file.module_decl)
+            .map_or_else(|| String::from("Types"), |module| module.name);
+        let file_name = FileName::from(format!("{module}.par"));
+
+        let id = PackageId::Special(literal!("types"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: Default::default(),
+                files: vec![LoadedPackageFile {
+                    name: file_name.clone(),
+                    relative_path_from_src: PathBuf::from(format!("{module}.par")),
+                    source: source.to_owned(),
+                }],
+            }],
+        );
+        pipeline
+            .run_until(Stage::Resolve)
+            .map_err(TypesError::Pipeline)?;
+        let workspace = pipeline.artifacts().workspace.as_ref().expect("resolved");
+
+        let type_defs = &workspace.lowered_module().type_defs;
+        let (type_defs, errors) = TypeDefs::new_with_functions_and_validation(
+            type_defs
+                .iter()
+                .map(|def| (&def.span, &def.name, &def.params, &def.typ)),
+            type_defs
+                .iter()
+                .filter_map(|def| Some((def.name.clone(), def.function?)))
+                .collect(),
+            ExpansionLimit::default(),
+        );
+        if !errors.is_empty() {
+            return Err(TypesError::Invalid {
+                source: Arc::from(source),
+                errors: errors.into_iter().collect(),
+            });
+        }
+        Ok(Self {
+            type_defs,
+            scope: workspace.import_scope(&file_name).cloned(),
+        })
+    }
+
+    /// The type definitions of a checked workspace, with names written as
+    /// they would be in `file`.
+    pub fn from_workspace(checked: &CheckedWorkspace, file: &FileName) -> Self {
+        Self {
+            type_defs: checked.checked_module().type_defs.clone(),
+            scope: checked.workspace().import_scope(file).cloned(),
+        }
+    }
+
+    pub fn type_defs(&self) -> &TypeDefs<Universal> {
+        &self.type_defs
+    }
+
+    /// The type defined as `name`, written as it's rendered: unqualified for
+    /// types of the module itself, and qualified by module otherwise. Types
+    /// with parameters have to be applied to arguments instead, with
+    /// [`Type::Name`].
+    pub fn named(&self, name: &str) -> Option<Type<Universal>> {
+        self.type_defs
+            .globals
+            .iter()
+            .filter(|(_, (_, params, _))| params.is_empty())
+            .map(|(global, _)| Type::Name(Span::None, global.clone(), vec![]))
+            .find(|typ| self.render_compact(typ) == name)
+    }
+
+    /// Whether a value of type `refined` can be used where a `general` is
+    /// expected: it's either the same protocol, or one that offers more
+    /// choices and sends fewer signals.
+    pub fn refines(
+        &self,
+        refined: &Type<Universal>,
+        general: &Type<Universal>,
+    ) -> Result<bool, TypeError<Universal>> {
+        refined.is_definitely_assignable_to(general, &self.type_defs)
+    }
+
+    /// The protocol of the other side of a channel of type `typ`.
+    pub fn dual(&self, typ: &Type<Universal>) -> Type<Universal> {
+        typ.clone().dual(Span::None)
+    }
+
+    /// `typ` as it would be written in the source, laid out over lines.
+    pub fn render(&self, typ: &Type<Universal>) -> String {
+        render_type_in_scope(self.scope.as_ref(), typ, 0)
+    }
+
+    /// `typ` on a single line, cut short where it gets too deep or too wide.
+    pub fn render_compact(&self, typ: &Type<Universal>) -> String {
+        render_compact_type_in_scope(self.scope.as_ref(), typ)
+    }
+}
+
+impl Display for TypesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pipeline(PipelineError::Workspace(error)) => write!(f, "{error}"),
+            Self::Pipeline(error) => write!(f, "{error:?}"),
+            Self::Invalid { source, errors } => {
+                for error in errors {
+                    writeln!(f, "{:?}", error.to_report(Arc::clone(source), None))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypesError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+module Protocols
+
+type Counter = iterative choice {
+  .increment => self,
+  .close => !,
+}
+type Resettable = iterative choice {
+  .increment => self,
+  .reset => self,
+  .close => !,
+}
+type Pair<a> = (a) a
+";
+
+    #[test]
+    fn refinement_is_assignability_between_named_types() {
+        let types = Types::from_source(SOURCE).unwrap();
+        let counter = types.named("Counter").unwrap();
+        let resettable = types.named("Resettable").unwrap();
+
+        assert!(types.refines(&resettable, &counter).unwrap());
+        assert!(!types.refines(&counter, &resettable).unwrap());
+        assert!(
+            types
+                .refines(&types.dual(&counter), &types.dual(&resettable))
+                .unwrap()
+        );
+        assert!(types.named("Pair").is_none());
+        assert!(types.named("Missing").is_none());
+    }
+
+    #[test]
+    fn invalid_definitions_are_reported() {
+        let error = Types::from_source("module Types\n\ntype Broken = Missing\n").unwrap_err();
+        assert!(
+            matches!(&error, TypesError::Invalid { errors, .. } if errors.len() == 1),
+            "{error}"
+        );
+    }
+}
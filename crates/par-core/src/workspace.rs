@@ -1,21 +1,33 @@
-use crate::frontend::lower;
+//! Loading, resolving, and checking whole packages.
+//!
+//! A [`Workspace`] is a root package with its dependencies, each made of
+//! modules spread over files. Checking it resolves the imports between them
+//! and type checks every module, giving a [`CheckedWorkspace`] to compile and
+//! run, or to query with [`semantic`](crate::semantic).
+
+use crate::frontend::lower_with_declared_parameters;
 use crate::frontend::parse_source_file;
 use crate::frontend_impl::language::{
-    BuiltinOperatorModule, CompileError, GlobalName, Resolved, ResolvedPackageRef, TypeParameter,
-    Universal, Unresolved,
+    BuiltinOperatorModule, CompileError, DeclaredParameters, GlobalName, LocalName, Resolved,
+    ResolvedPackageRef, TypeParameter, Universal, Unresolved,
 };
 use crate::frontend_impl::parse::SyntaxError;
 use crate::frontend_impl::process;
 use crate::frontend_impl::program::{
-    CheckedModule, DocComment, Docs, HoverIndex, ImportDecl, ImportPath, Module, SourceFile,
+    CheckedDefinitions, CheckedModule, DocComment, Docs, EntryPoint, HoverIndex, ImportDecl,
+    ImportPath, Module, SourceFile,
 };
-use crate::frontend_impl::types::display::{GlobalNameWriter, TypeRenderOptions};
-use crate::frontend_impl::types::error::labels_from_span;
+use crate::frontend_impl::totality::{DEFAULT_TOTALITY_BUDGET, TotalityHint, totality_hints};
+use crate::frontend_impl::types::display::{
+    GlobalNameWriter, TypeRenderOptions, TypeTruncation, report_truncation,
+};
+use crate::frontend_impl::types::error::{labels_from_span, source_of};
 use crate::frontend_impl::types::{
-    Type, TypeError, Visibility, VisibilityIndex, validate_visibility,
+    ExpansionLimit, Type, TypeError, Visibility, VisibilityIndex, validate_visibility,
 };
 use crate::location::{FileName, Span, Spanning};
 use crate::runtime_impl::{Compiled, RuntimeCompilerError};
+use crate::semantic::SemanticModel;
 use arcstr::ArcStr;
 use indexmap::{IndexMap, IndexSet};
 use par_runtime::linker::Unlinked;
@@ -24,13 +36,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashMap, btree_map::Entry};
 use std::fmt::{self, Display, Formatter, Write};
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 pub type ExternalModule = Module<Arc<process::Expression<(), Unresolved>>, Unresolved>;
 
 const MANIFEST_FILE: &str = "Par.toml";
 const SOURCE_DIRECTORY: &str = "src";
+const ASSETS_DIRECTORY: &str = "assets";
 pub const DEPENDENCIES_DIRECTORY: &str = "dependencies";
 
 fn message_report(message: impl Into<String>) -> miette::Report {
@@ -48,7 +63,7 @@ fn report_with_source_span(
     } else {
         source
     };
-    miette::miette!(labels = labels, "{}", message.into()).with_source_code(code)
+    miette::miette!(labels = labels, "{}", message.into()).with_source_code(source_of(span, code))
 }
 
 #[derive(Debug, Clone)]
@@ -84,6 +99,11 @@ impl PackageLayout {
         }
     }
 
+    /// Where the package keeps the data files it bundles as assets.
+    pub fn assets_dir(&self) -> PathBuf {
+        self.root_dir.join(ASSETS_DIRECTORY)
+    }
+
     fn from_existing_root_dir(root_dir: PathBuf) -> Result<Option<Self>, WorkspaceDiscoveryError> {
         let manifest_path = root_dir.join(MANIFEST_FILE);
         if !manifest_path.is_file() {
@@ -279,6 +299,9 @@ impl Display for RemoteDependencySource {
 pub struct PackageManifest {
     pub name: String,
     pub dependencies: BTreeMap<String, DependencySpec>,
+    /// Default configuration values for runs of the package, from its
+    /// `[config]` section.
+    pub config: BTreeMap<String, String>,
     pub builtin: bool,
 }
 
@@ -320,6 +343,7 @@ impl PackageManifest {
                     Ok((alias, spec))
                 })
                 .collect::<Result<_, _>>()?,
+            config: manifest.config,
         })
     }
 
@@ -340,6 +364,7 @@ impl PackageManifest {
                     (alias.clone(), source)
                 })
                 .collect(),
+            config: self.config.clone(),
         };
         toml::to_string(&manifest).expect("package manifest should serialize to TOML")
     }
@@ -350,6 +375,8 @@ struct ManifestToml {
     package: ManifestPackageToml,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     dependencies: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    config: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -540,9 +567,8 @@ impl PackageLoadError {
 
     pub fn to_report(&self) -> miette::Report {
         match self {
-            Self::ParseError { source, error, .. } => {
-                miette::Report::from(error.to_owned()).with_source_code(source.clone())
-            }
+            Self::ParseError { source, error, .. } => miette::Report::from(error.to_owned())
+                .with_source_code(source_of(&error.span(), source.clone())),
             Self::DirectoryReadError { .. }
             | Self::FileReadError { .. }
             | Self::InvalidSourceFilePath { .. }
@@ -1033,9 +1059,13 @@ pub struct Workspace {
     docs: Docs<Universal>,
     visibility: VisibilityIndex,
     package_modules: BTreeMap<PackageId, Vec<ModulePath>>,
+    /// What each package was built from, hashed: its files, and what the
+    /// packages it depends on were built from. See [`package_inputs`].
+    package_inputs: BTreeMap<PackageId, u64>,
     file_scopes: HashMap<FileName, FileImportScope<Universal>>,
     import_spans: HashMap<FileName, Vec<(Span, Universal)>>,
     sources: HashMap<FileName, Arc<str>>,
+    expansion_limit: ExpansionLimit,
 }
 
 impl Workspace {
@@ -1090,11 +1120,78 @@ impl Workspace {
         self.docs.declaration_doc(name)
     }
 
+    /// How a definition presents itself in menus of things to run, from the
+    /// doc comment on its declaration.
+    pub fn entry_point(&self, name: &GlobalName<Universal>) -> EntryPoint {
+        self.declaration_doc(name)
+            .map(DocComment::entry_point)
+            .unwrap_or_default()
+    }
+
     pub fn declaration_visibility(&self, name: &GlobalName<Universal>) -> Visibility {
         self.visibility.declaration_visibility(name)
     }
 
+    pub fn type_visibility(&self, name: &GlobalName<Universal>) -> Visibility {
+        self.visibility.type_visibility(name)
+    }
+
+    /// The errors that can be found without type checking: what
+    /// `Module::check_names` finds, and names used where they aren't visible.
+    pub fn check_names(&self) -> Vec<TypeError<Universal>> {
+        let mut errors = IndexSet::new();
+        errors.extend(validate_visibility(
+            &self.lowered,
+            &self.visibility,
+            &self.file_scopes,
+            &self.import_spans,
+        ));
+        errors.extend(self.lowered.check_names());
+        errors.into_iter().collect()
+    }
+
+    /// Has type checking expand types only as far as `expansion_limit`.
+    pub fn with_expansion_limit(mut self, expansion_limit: ExpansionLimit) -> Self {
+        self.expansion_limit = expansion_limit;
+        self
+    }
+
     pub fn type_check(&self) -> (CheckedWorkspace, Vec<TypeError<Universal>>) {
+        self.type_check_reusing_definitions(IndexMap::new())
+    }
+
+    /// Type checks the workspace like [`Self::type_check`], taking the
+    /// definitions of the packages that are built from the same inputs as in
+    /// `previous` as they were checked there, instead of checking them again.
+    ///
+    /// Nothing is taken from `previous` if it had type errors, as those
+    /// wouldn't be reported again.
+    pub fn type_check_reusing(
+        &self,
+        previous: &CheckedWorkspace,
+    ) -> (CheckedWorkspace, Vec<TypeError<Universal>>) {
+        if previous.had_errors {
+            return self.type_check();
+        }
+        let unchanged = |package: &PackageId| {
+            self.package_inputs.contains_key(package)
+                && self.package_inputs.get(package)
+                    == previous.workspace.package_inputs.get(package)
+        };
+        let reused = previous
+            .checked
+            .definitions
+            .iter()
+            .filter(|(name, _)| unchanged(&name.module.package))
+            .map(|(name, definition)| (name.clone(), definition.clone()))
+            .collect();
+        self.type_check_reusing_definitions(reused)
+    }
+
+    fn type_check_reusing_definitions(
+        &self,
+        reused: CheckedDefinitions<Universal>,
+    ) -> (CheckedWorkspace, Vec<TypeError<Universal>>) {
         let mut errors = IndexSet::new();
         errors.extend(validate_visibility(
             &self.lowered,
@@ -1103,7 +1200,9 @@ impl Workspace {
             &self.import_spans,
         ));
 
-        let (checked, type_errors) = self.lowered.type_check();
+        let (checked, type_errors) = self
+            .lowered
+            .type_check_reusing(reused, self.expansion_limit);
         errors.extend(type_errors);
         let hover_index = HoverIndex::new(
             &checked,
@@ -1124,11 +1223,13 @@ impl Workspace {
                     .declaration_visible_from(&scope.current_module, name)
             },
         );
+        let semantic = SemanticModel::new(self, &checked, hover_index);
         (
             CheckedWorkspace {
                 workspace: self.clone(),
                 checked,
-                hover_index,
+                semantic,
+                had_errors: !errors.is_empty(),
             },
             errors.into_iter().collect(),
         )
@@ -1139,7 +1240,8 @@ impl Workspace {
 pub struct CheckedWorkspace {
     workspace: Workspace,
     checked: CheckedModule<Universal>,
-    hover_index: HoverIndex<Universal>,
+    semantic: SemanticModel,
+    had_errors: bool,
 }
 
 impl CheckedWorkspace {
@@ -1151,8 +1253,36 @@ impl CheckedWorkspace {
         &self.checked
     }
 
+    /// Hints for each `unfounded` loop on whether it appears total. See
+    /// [`totality_hints`].
+    pub fn totality_hints(&self) -> Vec<TotalityHint> {
+        totality_hints(&self.checked, DEFAULT_TOTALITY_BUDGET)
+    }
+
+    /// Problems that don't stop the workspace from being checked or run, such
+    /// as unused type parameters. See [`TypeError::is_warning`].
+    pub fn warnings(&self) -> &[TypeError<Universal>] {
+        &self.checked.warnings
+    }
+
+    /// Every definition with how long it took to type check, slowest first.
+    pub fn definitions_by_check_time(&self) -> Vec<(&GlobalName<Universal>, Duration)> {
+        let mut times = self
+            .checked
+            .check_times
+            .iter()
+            .map(|(name, time)| (name, *time))
+            .collect::<Vec<_>>();
+        times.sort_by(|(_, a), (_, b)| b.cmp(a));
+        times
+    }
+
+    pub fn semantic_model(&self) -> &SemanticModel {
+        &self.semantic
+    }
+
     pub fn hover_index(&self) -> &HoverIndex<Universal> {
-        &self.hover_index
+        self.semantic.hover_index()
     }
 
     pub fn hover_at(
@@ -1161,7 +1291,7 @@ impl CheckedWorkspace {
         row: u32,
         column: u32,
     ) -> Option<process::HoverInfo<Universal>> {
-        self.hover_index.query(file, row, column)
+        self.semantic.hover_at(file, row, column)
     }
 
     pub fn compile_runtime(
@@ -1308,6 +1438,38 @@ pub fn render_type_in_scope(
     output
 }
 
+/// A type on a single line, cut short where it gets too deep or too wide.
+pub fn render_compact_type_in_scope(
+    scope: Option<&FileImportScope<Universal>>,
+    typ: &Type<Universal>,
+) -> String {
+    let mut output = String::new();
+    let _ = write_type_in_scope_with_options(
+        &mut output,
+        scope,
+        typ,
+        TypeRenderOptions::pretty_compact().with_truncation(TypeTruncation::REPORTS),
+    );
+    output
+}
+
+/// Like [`render_type_in_scope`], but truncated unless types in reports are
+/// set to be shown in full.
+pub fn render_report_type_in_scope(
+    scope: Option<&FileImportScope<Universal>>,
+    typ: &Type<Universal>,
+    indent: usize,
+) -> String {
+    let mut output = String::new();
+    let _ = write_type_in_scope_with_options(
+        &mut output,
+        scope,
+        typ,
+        TypeRenderOptions::pretty(indent).with_truncation(report_truncation()),
+    );
+    output
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 struct AbsoluteModuleLookupKey {
     package: PackageId,
@@ -1315,8 +1477,139 @@ struct AbsoluteModuleLookupKey {
     module_lower: String,
 }
 
+/// The lowered body of every file in a workspace, before names are resolved.
+pub type LoweredFiles =
+    HashMap<FileName, Module<Arc<process::Expression<(), Unresolved>>, Unresolved>>;
+
 pub fn assemble_workspace(
     workspace_packages: WorkspacePackages,
+) -> Result<Workspace, WorkspaceError> {
+    let lowered = lower_workspace_packages(&workspace_packages)?;
+    resolve_workspace(workspace_packages, lowered)
+}
+
+/// Lowers every file of every package to process syntax, which also runs the
+/// process-level optimizations.
+pub(crate) fn lower_workspace_packages(
+    workspace_packages: &WorkspacePackages,
+) -> Result<LoweredFiles, WorkspaceError> {
+    let module_lookup = build_module_lookup(&workspace_packages.packages);
+    let parameters_by_module = declared_parameters_by_module(&workspace_packages.packages);
+    let mut lowered = LoweredFiles::new();
+    for package in &workspace_packages.packages {
+        for module in &package.parsed.modules {
+            let current_module_path = resolved_module_path(&module.path, ResolvedPackageRef::Local);
+            for file in &module.files {
+                let imports = build_file_import_aliases(
+                    file,
+                    &current_module_path,
+                    &package.id,
+                    &package.dependencies,
+                    &module_lookup,
+                )?;
+                let scope = universalize_file_scope(
+                    &imports,
+                    &current_module_path,
+                    &package.id,
+                    &package.dependencies,
+                    Arc::clone(&file.source),
+                )?;
+                let declared_parameters =
+                    declared_parameters_in_scope(&parameters_by_module, &scope);
+                let lowered_file = lower_with_declared_parameters(
+                    file.source_file.body.clone(),
+                    Arc::new(declared_parameters),
+                )
+                .map_err(|error| WorkspaceError::LowerError {
+                    file: file.name.clone(),
+                    source: Arc::clone(&file.source),
+                    error,
+                })?;
+                lowered.insert(file.name.clone(), lowered_file);
+            }
+        }
+    }
+    Ok(lowered)
+}
+
+/// The names declarations give to the arguments of definitions, by module.
+fn declared_parameters_by_module(
+    packages: &[WorkspacePackage],
+) -> HashMap<Universal, Vec<(String, Vec<LocalName>)>> {
+    let mut parameters = HashMap::<Universal, Vec<_>>::new();
+    for package in packages {
+        for module in &package.parsed.modules {
+            let universal_module = Universal {
+                package: package.id.clone(),
+                directories: module.path.directories.clone(),
+                module: module.path.module.clone(),
+            };
+            for file in &module.files {
+                for declaration in &file.source_file.body.declarations {
+                    if declaration.parameter_names.is_empty() {
+                        continue;
+                    }
+                    parameters
+                        .entry(universal_module.clone())
+                        .or_default()
+                        .push((
+                            declaration.name.primary.clone(),
+                            declaration.parameter_names.clone(),
+                        ));
+                }
+            }
+        }
+    }
+    parameters
+}
+
+/// The declared parameters of definitions, by the names a file with `scope`
+/// refers to them with, the same way `resolve_name_to_resolved` resolves them.
+fn declared_parameters_in_scope(
+    parameters_by_module: &HashMap<Universal, Vec<(String, Vec<LocalName>)>>,
+    scope: &FileImportScope<Universal>,
+) -> DeclaredParameters {
+    let in_module = |module: &Universal| {
+        parameters_by_module
+            .get(module)
+            .into_iter()
+            .flatten()
+            .map(|(primary, names)| (primary.as_str(), names))
+    };
+    let name = |qualifier: Option<&String>, primary: &str| {
+        GlobalName::new(
+            Span::None,
+            Unresolved::Path {
+                qualifier: qualifier.cloned(),
+            },
+            primary.to_owned(),
+        )
+    };
+
+    let mut declared = DeclaredParameters::new();
+    for (primary, names) in in_module(&scope.current_module) {
+        declared.insert(name(None, primary), names.clone());
+    }
+    for (alias, module) in &scope.aliases {
+        // An unqualified alias stands for the definition named after its module.
+        declared.remove(&name(None, alias));
+        for (primary, names) in in_module(module) {
+            if *module != scope.current_module {
+                declared.insert(name(Some(alias), primary), names.clone());
+            }
+            if primary == module.module {
+                declared.insert(name(None, alias), names.clone());
+            }
+        }
+    }
+    declared
+}
+
+/// Resolves the names in files lowered by `lower_workspace_packages`, and
+/// merges them into one workspace.
+pub(crate) fn resolve_workspace(
+    workspace_packages: WorkspacePackages,
+    mut lowered_files: LoweredFiles,
 ) -> Result<Workspace, WorkspaceError> {
     let WorkspacePackages {
         root_package,
@@ -1324,6 +1617,7 @@ pub fn assemble_workspace(
     } = workspace_packages;
     let module_lookup = build_module_lookup(&packages);
     let package_modules = package_module_paths(&packages);
+    let package_inputs = package_inputs(&packages);
 
     let mut lowered = Module::default();
     let mut module_docs = IndexMap::new();
@@ -1341,6 +1635,7 @@ pub fn assemble_workspace(
             &mut file_scopes,
             &mut import_spans,
             &module_lookup,
+            &mut lowered_files,
             package,
         )?;
     }
@@ -1353,13 +1648,63 @@ pub fn assemble_workspace(
         docs,
         visibility,
         package_modules,
+        package_inputs,
         file_scopes,
         import_spans,
         sources,
+        expansion_limit: ExpansionLimit::default(),
     })
 }
 
-fn collect_source_files(
+/// Hashes what each of `packages` is built from: the names and contents of
+/// its files, and what the packages it depends on, under which aliases, are
+/// built from. A package with the same hash in two workspaces checks to the
+/// same definitions in both.
+fn package_inputs(packages: &[WorkspacePackage]) -> BTreeMap<PackageId, u64> {
+    fn hash_package(
+        id: &PackageId,
+        packages: &BTreeMap<&PackageId, &WorkspacePackage>,
+        hashes: &mut BTreeMap<PackageId, u64>,
+    ) -> u64 {
+        if let Some(hash) = hashes.get(id) {
+            return *hash;
+        }
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        if let Some(package) = packages.get(id) {
+            // Seen while hashing its dependencies, should they depend on it.
+            hashes.insert(id.clone(), 0);
+            let files = package
+                .parsed
+                .modules
+                .iter()
+                .flat_map(|module| &module.files);
+            for file in files {
+                file.name.hash(&mut hasher);
+                file.source.hash(&mut hasher);
+            }
+            for (alias, dependency) in &package.dependencies {
+                alias.hash(&mut hasher);
+                hash_package(dependency, packages, hashes).hash(&mut hasher);
+            }
+        }
+        let hash = hasher.finish();
+        hashes.insert(id.clone(), hash);
+        hash
+    }
+
+    let by_id = packages
+        .iter()
+        .map(|package| (&package.id, package))
+        .collect();
+    let mut hashes = BTreeMap::new();
+    for package in packages {
+        hash_package(&package.id, &by_id, &mut hashes);
+    }
+    hashes
+}
+
+pub(crate) fn collect_source_files(
     layout: &PackageLayout,
     overrides: Option<&SourceOverrides>,
 ) -> Result<Vec<LoadedPackageFile>, PackageLoadError> {
@@ -1390,6 +1735,21 @@ pub fn load_package_source_files(
 
 pub fn parse_loaded_files(
     files: Vec<LoadedPackageFile>,
+) -> Result<ParsedPackage, PackageLoadError> {
+    parse_loaded_files_with(files, |file| {
+        parse_source_file(&file.source, file.name.clone())
+    })
+}
+
+/// Like `parse_loaded_files`, but parses each file with `parse`.
+pub(crate) fn parse_loaded_files_with(
+    files: Vec<LoadedPackageFile>,
+    mut parse: impl FnMut(
+        &LoadedPackageFile,
+    ) -> Result<
+        SourceFile<crate::frontend_impl::language::Expression<Unresolved>>,
+        SyntaxError,
+    >,
 ) -> Result<ParsedPackage, PackageLoadError> {
     let mut modules_by_key: BTreeMap<ModulePathKey, ParsedModule> = BTreeMap::new();
 
@@ -1397,12 +1757,10 @@ pub fn parse_loaded_files(
         let (file_module_name, module_part_suffix) =
             parse_module_name_from_file_name(&file.relative_path_from_src)?;
         let source: Arc<str> = Arc::from(file.source.as_str());
-        let source_file = parse_source_file(&file.source, file.name.clone()).map_err(|error| {
-            PackageLoadError::ParseError {
-                file: file.name.clone(),
-                source: Arc::clone(&source),
-                error,
-            }
+        let source_file = parse(&file).map_err(|error| PackageLoadError::ParseError {
+            file: file.name.clone(),
+            source: Arc::clone(&source),
+            error,
         })?;
 
         let declared_module_name = source_file
@@ -1791,6 +2149,7 @@ fn load_package(
     file_scopes: &mut HashMap<FileName, FileImportScope<Universal>>,
     import_spans: &mut HashMap<FileName, Vec<(Span, Universal)>>,
     module_lookup: &BTreeMap<AbsoluteModuleLookupKey, ModulePath>,
+    lowered_files: &mut LoweredFiles,
     package: WorkspacePackage,
 ) -> Result<(), WorkspaceError> {
     let WorkspacePackage {
@@ -1862,13 +2221,9 @@ fn load_package(
             }
 
             let imported_aliases = imported_aliases(&imports, &current_module_path);
-            let mut lowered_file = lower(file.source_file.body.clone()).map_err(|error| {
-                WorkspaceError::LowerError {
-                    file: file.name.clone(),
-                    source: Arc::clone(&file.source),
-                    error,
-                }
-            })?;
+            let mut lowered_file = lowered_files
+                .remove(&file.name)
+                .expect("every workspace file should be lowered before resolution");
             if file.module_part_suffix.is_none() {
                 if let Some(external_module) = externals.remove(&parsed_module.path) {
                     merge_module(&mut lowered_file, external_module);
@@ -2014,16 +2369,51 @@ fn universalize_module_path(
                 module: module.clone(),
             })
         }
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Bool) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Bool"),
+        }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Bytes) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Bytes"),
+        }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Codec) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Codec"),
+        }),
         Resolved::BuiltinOperator(BuiltinOperatorModule::Data) => Ok(Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
             module: String::from("Data"),
         }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Float) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Float"),
+        }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Json) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Json"),
+        }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::List) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("List"),
+        }),
         Resolved::BuiltinOperator(BuiltinOperatorModule::Number) => Ok(Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
             module: String::from("Number"),
         }),
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Ordering) => Ok(Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Ordering"),
+        }),
         Resolved::BuiltinOperator(BuiltinOperatorModule::String) => Ok(Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
@@ -2261,16 +2651,51 @@ fn resolve_name_to_universal(
                 module,
             }
         }
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Bool) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Bool"),
+        },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Bytes) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Bytes"),
+        },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Codec) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Codec"),
+        },
         Resolved::BuiltinOperator(BuiltinOperatorModule::Data) => Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
             module: String::from("Data"),
         },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Float) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Float"),
+        },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Json) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Json"),
+        },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::List) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("List"),
+        },
         Resolved::BuiltinOperator(BuiltinOperatorModule::Number) => Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
             module: String::from("Number"),
         },
+        Resolved::BuiltinOperator(BuiltinOperatorModule::Ordering) => Universal {
+            package: PackageId::Builtin(BuiltinPackage::Core),
+            directories: vec![],
+            module: String::from("Ordering"),
+        },
         Resolved::BuiltinOperator(BuiltinOperatorModule::String) => Universal {
             package: PackageId::Builtin(BuiltinPackage::Core),
             directories: vec![],
@@ -2568,8 +2993,10 @@ fn write_type_parameters(f: &mut impl Write, params: &[TypeParameter]) -> fmt::R
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::frontend_impl::language::TypeConstraint;
+    use crate::frontend_impl::language::{NamedArgumentsError, TypeConstraint};
+    use crate::frontend_impl::totality::TotalityVerdict;
     use crate::frontend_impl::types::Visibility;
+    use crate::semantic::SymbolKind;
     use arcstr::literal;
     use std::fs;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -2714,6 +3141,44 @@ def Run = external
         );
     }
 
+    #[test]
+    fn entry_points_are_described_by_their_declaration_docs() {
+        let source = "\
+module Main
+
+// Plays a round
+// against the computer.
+//
+// Category: Games
+//
+// More about how it's played.
+dec Play : !
+def Play = !
+
+dec Plain : !
+def Plain = !
+";
+        let checked = checked_workspace_from_source(source);
+        let entry_point = |primary: &str| {
+            let name = checked
+                .checked_module()
+                .declarations
+                .keys()
+                .find(|name| name.primary == primary)
+                .unwrap();
+            checked.workspace().entry_point(name)
+        };
+
+        assert_eq!(
+            entry_point("Play"),
+            EntryPoint {
+                description: Some(String::from("Plays a round against the computer.")),
+                category: Some(String::from("Games")),
+            }
+        );
+        assert_eq!(entry_point("Plain"), EntryPoint::default());
+    }
+
     #[test]
     fn module_doc_comments_only_come_from_main_module_file() {
         let parsed = parsed_package_from_files(
@@ -2789,6 +3254,58 @@ def Main = Run
         );
     }
 
+    #[test]
+    fn branch_docs_show_in_hovers_and_case_skeletons() {
+        let source = "\
+module Main
+
+type Nat = recursive either { .zero!, .succ self }
+
+// A result.
+type Outcome = either {
+  // It worked.
+  .done!,
+  // It didn't,
+  // with a code.
+  .failed Nat,
+}
+
+dec Code : [Outcome] Nat
+def Code = [o] o.case {
+  .done! => .zero!,
+  .failed n => n,
+}
+";
+        let checked = checked_workspace_from_source(source);
+        let file = FileName::from("local/Main.par");
+
+        let (row, column) = row_and_column(source, source.find("[Outcome]").unwrap() + 1);
+        let hover = checked.hover_at(&file, row, column).unwrap();
+        assert_eq!(
+            hover.doc().map(|doc| doc.markdown.as_str()),
+            Some(
+                "A result.\n\n## Branches\n\n- `.done`: It worked.\n- `.failed`: It didn't,\n  with a code."
+            )
+        );
+
+        let (row, column) = row_and_column(source, source.find("o.case").unwrap());
+        let (_, skeleton) = checked
+            .semantic_model()
+            .case_skeleton_at(&file, row, column, "")
+            .unwrap();
+        assert_eq!(
+            skeleton,
+            "\
+.case {
+  // It worked.
+  .done! => todo,
+  // It didn't,
+  // with a code.
+  .failed nat => todo,
+}"
+        );
+    }
+
     #[test]
     fn import_hover_includes_module_doc_comments() {
         let main_source = "\
@@ -2825,6 +3342,331 @@ export module Helper
         );
     }
 
+    #[test]
+    fn semantic_model_tracks_references_and_dependencies() {
+        let source = "\
+module Main
+
+type Item = !
+
+dec Run : Item
+def Run = !
+
+dec Main : Item
+def Main = Run
+";
+        let checked = checked_workspace_from_source(source);
+        let file = checked.workspace().sources().keys().next().unwrap();
+        let model = checked.semantic_model();
+
+        let run = model
+            .symbols()
+            .find(|symbol| symbol.name().primary == "Run")
+            .unwrap();
+        let main = model
+            .symbols()
+            .find(|symbol| symbol.name().primary == "Main")
+            .unwrap();
+        let item = model
+            .symbols()
+            .find(|symbol| symbol.name().primary == "Item")
+            .unwrap();
+        assert_eq!(run.kind(), SymbolKind::Declaration);
+        assert_eq!(item.kind(), SymbolKind::Type);
+        assert!(run.declaration.is_some() && run.definition.is_some());
+
+        let run_usage = source.match_indices("Run").last().unwrap().0;
+        let references = model.references_of(&run.id);
+        assert_eq!(references.len(), 1);
+        assert_eq!(
+            references[0].start().map(|start| start.offset),
+            Some(run_usage as u32)
+        );
+
+        let (row, column) = row_and_column(source, run_usage);
+        assert_eq!(
+            model.symbol_at(file, row, column).map(|symbol| &symbol.id),
+            Some(&run.id)
+        );
+
+        assert_eq!(model.references_of(&item.id).len(), 2);
+        assert!(model.dependencies_of(&main.id).any(|id| *id == run.id));
+        assert!(model.dependencies_of(&run.id).any(|id| *id == item.id));
+        assert!(model.dependents_of(&item.id).any(|id| *id == main.id));
+    }
+
+    #[test]
+    fn semantic_model_lists_missing_branches_of_a_case() {
+        let source = "\
+module Main
+
+type Color = either {
+  .red!,
+  .green!,
+  .blue!,
+}
+
+dec IsRed : [Color] Color
+def IsRed = [c] c.case {
+  .red! => .red!,
+}
+";
+        let parsed = parsed_package_from_files("local", &[("Main.par", source)]);
+        let (checked, type_errors) = assemble_workspace(WorkspacePackages {
+            root_package: test_package_id(),
+            packages: vec![WorkspacePackage::new(test_package_id(), parsed)],
+        })
+        .unwrap()
+        .type_check();
+        assert!(
+            type_errors
+                .iter()
+                .any(|error| matches!(error, TypeError::MissingBranch(..))),
+            "type errors: {:?}",
+            type_errors
+        );
+
+        let file = FileName::from("local/Main.par");
+        let (row, column) = row_and_column(source, source.find(".red! =>").unwrap());
+        let case = checked
+            .semantic_model()
+            .case_at(&file, row, column)
+            .unwrap();
+        assert_eq!(
+            case.handled
+                .iter()
+                .map(|branch| branch.string.as_str())
+                .collect::<Vec<_>>(),
+            ["red"]
+        );
+        assert_eq!(
+            case.missing
+                .iter()
+                .map(|(branch, _)| branch.string.as_str())
+                .collect::<Vec<_>>(),
+            ["blue", "green"]
+        );
+        assert!(!case.has_else);
+    }
+
+    #[test]
+    fn semantic_model_generates_case_skeletons_for_either_values() {
+        let source = "\
+module Main
+
+type Nat = recursive either { .zero!, .succ self }
+
+type Shape = either {
+  .circle Nat,
+  .empty!,
+  .rect(Nat, Nat)!,
+}
+
+dec Area : [Shape] Nat
+def Area = [s] s.case {
+  .circle r => r,
+  .empty! => .zero!,
+  .rect(w, h)! => w,
+}
+";
+        let parsed = parsed_package_from_files("local", &[("Main.par", source)]);
+        let (checked, type_errors) = assemble_workspace(WorkspacePackages {
+            root_package: test_package_id(),
+            packages: vec![WorkspacePackage::new(test_package_id(), parsed)],
+        })
+        .unwrap()
+        .type_check();
+        assert!(type_errors.is_empty(), "type errors: {:?}", type_errors);
+
+        let file = FileName::from("local/Main.par");
+        let (row, column) = row_and_column(source, source.find("s.case").unwrap());
+        let (_, skeleton) = checked
+            .semantic_model()
+            .case_skeleton_at(&file, row, column, "  ")
+            .unwrap();
+        assert_eq!(
+            skeleton,
+            "\
+.case {
+    .circle nat => todo,
+    .empty! => todo,
+    .rect(nat, nat2)! => todo,
+  }"
+        );
+
+        let (row, column) = row_and_column(source, source.find("Area =").unwrap());
+        assert!(
+            checked
+                .semantic_model()
+                .case_skeleton_at(&file, row, column, "")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn totality_hints_tell_total_unfounded_loops_from_diverging_ones() {
+        let source = "\
+module Main
+
+type Peano = recursive either {
+  .zero!,
+  .succ self,
+}
+
+dec Drain : [Peano] !
+def Drain = [n] chan out {
+  n.unfounded
+  n.case {
+    .zero! => { out <> ! }
+    .succ => { n.loop }
+  }
+}
+
+dec Spin : [Peano] !
+def Spin = [n] chan out {
+  n.unfounded
+  n.case {
+    .zero! => { out <> ! }
+    .succ => {
+      let n: Peano = .zero!
+      n.loop
+    }
+  }
+}
+";
+        let checked = checked_workspace_from_source(source);
+        let hints = checked.totality_hints();
+        assert_eq!(hints.len(), 2);
+
+        assert!(matches!(hints[0].verdict, TotalityVerdict::AppearsTotal));
+        let TotalityVerdict::LikelyDiverges { path } = &hints[1].verdict else {
+            panic!("expected `Spin` to likely diverge: {}", hints[1]);
+        };
+        let steps = path
+            .iter()
+            .map(|(_, step)| step.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                "takes the `.succ` branch",
+                "binds `n` to something that isn't a part of the subject",
+                "loops with `n`",
+            ]
+        );
+    }
+
+    #[test]
+    fn loops_over_begin_subjects_descend_lexicographically() {
+        let source = |rebuild: &str| {
+            format!(
+                "\
+module Main
+
+type Peano = recursive either {{
+  .zero!,
+  .succ self,
+}}
+
+dec Both : [Peano, Peano] !
+def Both = [m, n] chan out {{
+  n.begin@both & m
+  n.case {{
+    .zero! => {{
+      m.case {{
+        .zero! => {{ out <> ! }}
+        .succ => {{
+          {rebuild}
+          let n: Peano = .succ .succ .zero!
+          n.loop@both
+        }}
+      }}
+    }}
+    .succ => {{ n.loop@both }}
+  }}
+}}
+"
+            )
+        };
+
+        checked_workspace_from_source(&source(""));
+
+        let errors = workspace_type_errors(vec![WorkspacePackage::new(
+            test_package_id(),
+            parsed_package_from_files("local", &[("Main.par", &source("let m: Peano = .succ m"))]),
+        )]);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                TypeError::DoesNotDescendSubjectsOfBegin(_, Some(name))
+                    if name.string.as_str() == "m"
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn case_on_open_either_passes_the_rest_to_else() {
+        let source = |else_branch: &str| {
+            format!(
+                "\
+module Main
+
+type Timeout<r> = either {{ .timeout! }} | r
+
+dec Widen : [type r] [r] Timeout<r>
+def Widen = [type r] [x] x
+
+dec Retry : [type r] [Timeout<r>] either {{ .retried! }} | r
+def Retry = [type r] [x] x.case {{
+  .timeout! => .retried!,
+  {else_branch}
+}}
+
+dec Done : [either {{ .ok! }}] !
+def Done = [x] Retry(type either {{ .ok! }})(Widen(type either {{ .ok! }})(x)).case {{
+  .retried! => !,
+  .ok! => !,
+}}
+"
+            )
+        };
+
+        checked_workspace_from_source(&source("else y => y,"));
+
+        let errors = workspace_type_errors(vec![WorkspacePackage::new(
+            test_package_id(),
+            parsed_package_from_files("local", &[("Main.par", &source(""))]),
+        )]);
+        assert!(
+            errors
+                .iter()
+                .any(|error| matches!(error, TypeError::CaseOnOpenRowWithoutElse(..))),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn unused_type_parameters_are_warnings_and_not_errors() {
+        let source = "\
+module Main
+
+type Tagged<tag, a> = (a) !
+";
+        let checked = checked_workspace_from_source(source);
+        let warnings = checked.warnings();
+        assert!(
+            matches!(
+                warnings,
+                [TypeError::UnusedTypeParameter(span, name, parameter)]
+                    if name.primary == "Tagged"
+                        && parameter.string == "tag"
+                        && span.start().is_some_and(|start| start.column == 12)
+            ),
+            "warnings: {warnings:?}"
+        );
+    }
+
     #[test]
     fn hover_info_includes_type_for_empty_local_command_chain_before_continuation() {
         let source = "\
@@ -3001,6 +3843,58 @@ def Main : ! = do {
         )));
     }
 
+    #[test]
+    fn unfulfilled_obligations_report_binders_and_types() {
+        let source = "\
+module Main
+
+type Door = iterative choice { .close => !, .knock => self }
+
+def Leave : [Door] ! = [door] !
+";
+        let errors = workspace_type_errors(vec![WorkspacePackage::new(
+            test_package_id(),
+            parsed_package_from_files("local", &[("Main.par", source)]),
+        )]);
+
+        let binder = source.find("door]").unwrap();
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            TypeError::UnfulfilledObligations(_, obligations)
+                if matches!(
+                    obligations.as_slice(),
+                    [(name, span, Type::Iterative { .. })]
+                        if name.string.as_str() == "door"
+                            && span.start().map(|start| start.offset as usize) == Some(binder)
+                )
+        )));
+    }
+
+    #[test]
+    fn shadowed_obligations_report_both_binders_and_the_type() {
+        let source = "\
+module Main
+
+type Door = iterative choice { .close => !, .knock => self }
+
+def Swap : [Door, Door] ! = [door, other] do {
+  let door = other
+} in !
+";
+        let errors = workspace_type_errors(vec![WorkspacePackage::new(
+            test_package_id(),
+            parsed_package_from_files("local", &[("Main.par", source)]),
+        )]);
+
+        let binder = source.find("door,").unwrap();
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            TypeError::ShadowedObligation(_, span, name, Type::Name(..) | Type::Iterative { .. })
+                if name.string.as_str() == "door"
+                    && span.start().map(|start| start.offset as usize) == Some(binder)
+        )));
+    }
+
     #[test]
     fn same_package_can_use_package_visible_export() {
         let errors = workspace_type_errors(vec![WorkspacePackage::new(
@@ -3069,6 +3963,79 @@ type Uses = Hidden.Secret
         )));
     }
 
+    #[test]
+    fn rechecking_reuses_the_packages_that_did_not_change() {
+        let dependency_id = PackageId::Remote(literal!("dep"));
+        let workspace = |dependency: &str, main: &str| {
+            let dependency = WorkspacePackage::new(
+                dependency_id.clone(),
+                parsed_package_from_files("dep", &[("Lib.par", dependency)]),
+            );
+            let local = WorkspacePackage::new(
+                test_package_id(),
+                parsed_package_from_files("local", &[("Main.par", main)]),
+            )
+            .with_dependency("dep", dependency_id.clone());
+            assemble_workspace(WorkspacePackages {
+                root_package: test_package_id(),
+                packages: vec![local, dependency],
+            })
+            .unwrap()
+        };
+        let checked_names = |checked: &CheckedWorkspace| {
+            checked
+                .checked_module()
+                .check_times
+                .keys()
+                .map(|name| name.primary.clone())
+                .collect::<Vec<_>>()
+        };
+        let lib = "export module Lib\n\nexport dec Unit : !\ndef Unit = !\n";
+        let main = |name: &str| format!("module Main\nimport @dep/Lib\n\ndef {name} = Lib.Unit\n");
+
+        let (first, errors) = workspace(lib, &main("First")).type_check();
+        assert!(errors.is_empty(), "type errors: {errors:?}");
+
+        let (second, errors) = workspace(lib, &main("Second")).type_check_reusing(&first);
+        assert!(errors.is_empty(), "type errors: {errors:?}");
+        assert_eq!(checked_names(&second), ["Second"]);
+        assert!(
+            second
+                .checked_module()
+                .definitions
+                .keys()
+                .any(|name| name.primary == "Unit")
+        );
+
+        let changed_lib =
+            "export module Lib\n\nexport dec Unit : !\ndef Unit = !\n\ndef Other = !\n";
+        let (third, errors) = workspace(changed_lib, &main("Second")).type_check_reusing(&second);
+        assert!(errors.is_empty(), "type errors: {errors:?}");
+        let mut names = checked_names(&third);
+        names.sort();
+        assert_eq!(names, ["Other", "Second", "Unit"]);
+    }
+
+    #[test]
+    fn rechecking_reuses_nothing_from_a_check_with_errors() {
+        let workspace = |source: &str| {
+            assemble_workspace(WorkspacePackages {
+                root_package: test_package_id(),
+                packages: vec![WorkspacePackage::new(
+                    test_package_id(),
+                    parsed_package_from_files("local", &[("Main.par", source)]),
+                )],
+            })
+            .unwrap()
+        };
+        let source = "module Main\n\ndec Wrong : !\ndef Wrong = .wrong!\n";
+
+        let (first, errors) = workspace(source).type_check();
+        assert!(!errors.is_empty());
+        let (_, errors) = workspace(source).type_check_reusing(&first);
+        assert!(!errors.is_empty());
+    }
+
     #[test]
     fn cross_package_import_requires_exported_module() {
         let dependency_id = PackageId::Remote(literal!("dep"));
@@ -3724,6 +4691,37 @@ url = \"github.com/example/root\"
         );
     }
 
+    #[test]
+    fn manifest_config_is_read_and_rendered_back() {
+        let root = temp_package_root("manifest-config");
+        write_package(
+            &root,
+            "\
+[package]
+name = \"root\"
+
+[config]
+greeting = \"hello\"
+rounds = \"3\"
+",
+            &[("src/Main.par", "module Main\n")],
+        );
+
+        let manifest = PackageManifest::read_from(&root.join("Par.toml")).unwrap();
+        assert_eq!(
+            manifest.config,
+            BTreeMap::from([
+                (String::from("greeting"), String::from("hello")),
+                (String::from("rounds"), String::from("3")),
+            ])
+        );
+        fs::write(root.join("Par.toml"), manifest.render()).unwrap();
+        assert_eq!(
+            PackageManifest::read_from(&root.join("Par.toml")).unwrap(),
+            manifest
+        );
+    }
+
     #[test]
     fn import_hover_hides_module_private_items() {
         let main_source = "\
@@ -3766,4 +4764,57 @@ def Secret: ! = external
         assert!(!signature.contains("Hidden"));
         assert!(!signature.contains("Secret"));
     }
+
+    #[test]
+    fn named_arguments_follow_imported_declarations() {
+        let helper_source = "\
+module Helper
+
+export dec Pair : [first: !, second: !] !
+def Pair = [first, second] do { first? second? } in !
+";
+        checked_workspace_from_files(
+            "local",
+            &[
+                ("Helper.par", helper_source),
+                (
+                    "Main.par",
+                    "\
+module Main
+import Helper as H
+
+def Main: ! = H.Pair(second: !, first: !)
+",
+                ),
+            ],
+        );
+
+        let parsed = parsed_package_from_files(
+            "local",
+            &[
+                ("Helper.par", helper_source),
+                (
+                    "Main.par",
+                    "\
+module Main
+import Helper as H
+
+def Main: ! = H.Pair(second: !, third: !)
+",
+                ),
+            ],
+        );
+        let error = assemble_workspace(WorkspacePackages {
+            root_package: test_package_id(),
+            packages: vec![WorkspacePackage::new(test_package_id(), parsed)],
+        })
+        .expect_err("unknown argument name should fail to lower");
+        assert!(matches!(
+            error,
+            WorkspaceError::LowerError {
+                error: CompileError::BadNamedArguments(_, NamedArgumentsError::Unknown(ref name)),
+                ..
+            } if name.string.as_str() == "third"
+        ));
+    }
 }
@@ -0,0 +1,871 @@
+/**
+ * @file Tree-sitter grammar for Par, for highlighting and folding in editors.
+ *
+ * It follows `src/frontend_impl/grammar.ebnf`, which follows the parser, but
+ * is looser where that keeps it simple: the sugar of the parser that tries
+ * alternatives in order is written here as one rule with precedences, so some
+ * programs the parser rejects still get a tree. The tokens are the same as the
+ * lexer's, and the tests in `src/frontend_impl/grammar.rs` check that every
+ * file in the repository lexes the same way with the rules below.
+ */
+
+/// <reference types="tree-sitter-cli/dsl" />
+// @ts-check
+
+const PREC = {
+  construction: -1,
+  or: 1,
+  and: 2,
+  not: 3,
+  is: 4,
+  comparison: 5,
+  additive: 6,
+  multiplicative: 7,
+  neg: 8,
+  apply: 10,
+  type_prefix: 1,
+  type_combination: 2,
+};
+
+/**
+ * One or more `rule`, separated by commas, with an optional trailing comma.
+ *
+ * @param {RuleOrLiteral} rule
+ */
+function commaSep1(rule) {
+  return seq(rule, repeat(seq(',', rule)), optional(','));
+}
+
+/**
+ * Like `commaSep1`, but possibly empty.
+ *
+ * @param {RuleOrLiteral} rule
+ */
+function commaSep(rule) {
+  return optional(commaSep1(rule));
+}
+
+/**
+ * `rule`, followed by an optional comma, any number of times.
+ *
+ * @param {RuleOrLiteral} rule
+ */
+function commaTerminated(rule) {
+  return repeat(seq(rule, optional(',')));
+}
+
+module.exports = grammar({
+  name: 'par',
+
+  word: $ => $.lower_name,
+
+  externals: $ => [$.block_comment],
+
+  extras: $ => [/\s/, $.line_comment, $.block_comment],
+
+  supertypes: $ => [$._type, $._pattern, $._expression, $._statement],
+
+  conflicts: $ => [
+    [$.named_parameter, $.type_variable],
+    [$.named_argument, $._expression],
+    [$.explicit_type_argument, $.type_in_expression],
+    [$.if_statement],
+    [$.command],
+    [$.global_command],
+    [$.macro_invocation, $.global_command],
+  ],
+
+  rules: {
+    // Files
+
+    source_file: $ => seq(
+      optional($.edition_declaration),
+      optional($.module_declaration),
+      repeat($.import),
+      repeat($._item),
+    ),
+
+    edition_declaration: $ => seq('edition', field('edition', $.integer)),
+
+    module_declaration: $ => seq(
+      optional('export'),
+      'module',
+      field('name', $.upper_name),
+    ),
+
+    import: $ => seq(
+      'import',
+      choice(
+        seq('{', commaTerminated($.import_entry), '}'),
+        $.import_entry,
+      ),
+    ),
+
+    import_entry: $ => seq(
+      field('path', $.import_path),
+      optional(seq('as', field('alias', $.upper_name))),
+    ),
+
+    import_path: $ => seq(
+      optional(seq('@', field('package', $._lowercase_identifier), '/')),
+      $._any_identifier,
+      repeat(seq('/', $._any_identifier)),
+    ),
+
+    _item: $ => choice(
+      $.export,
+      $.type_definition,
+      $.declaration,
+      $.definition,
+      $.macro_definition,
+    ),
+
+    export: $ => seq(
+      'export',
+      choice(
+        seq('{', repeat(choice($.type_definition, $.declaration)), '}'),
+        $.type_definition,
+        $.declaration,
+      ),
+    ),
+
+    type_definition: $ => seq(
+      'type',
+      field('name', $.upper_name),
+      optional(field('parameters', $.type_parameters)),
+      '=',
+      field('type', $._type),
+      optional($.derive_clause),
+    ),
+
+    derive_clause: $ => seq('derive', commaSep1($.upper_name)),
+
+    declaration: $ => seq(
+      'dec',
+      field('name', $.upper_name),
+      ':',
+      field('type', $._declaration_type),
+    ),
+
+    _declaration_type: $ => choice($.named_parameters_type, $._type),
+
+    named_parameters_type: $ => prec.right(PREC.type_prefix, seq(
+      '[',
+      commaSep1($.named_parameter),
+      ']',
+      field('result', $._type),
+    )),
+
+    named_parameter: $ => seq(
+      field('name', $._lowercase_identifier),
+      ':',
+      field('type', $._type),
+    ),
+
+    definition: $ => seq(
+      'def',
+      field('name', $.upper_name),
+      optional(seq(':', field('type', $._declaration_type))),
+      '=',
+      field('value', choice($.external, $._expression)),
+    ),
+
+    external: _ => 'external',
+
+    // Names
+
+    _lowercase_identifier: $ => choice(
+      $.lower_name,
+      alias(choice('and', 'neg', 'not', 'or'), $.lower_name),
+    ),
+
+    _any_identifier: $ => choice($._lowercase_identifier, $.upper_name),
+
+    global_name: $ => seq(
+      optional(seq(field('qualifier', $.upper_name), '.')),
+      field('name', $.upper_name),
+    ),
+
+    label: $ => seq('@', $.lower_name),
+
+    // Types
+
+    _type: $ => choice(
+      $.type_combination,
+      $.type_variable,
+      $.type_name,
+      $.box_type,
+      $.dual_type,
+      $.either_type,
+      $.choice_type,
+      $.unit_type,
+      $.continue_type,
+      $.recursive_type,
+      $.iterative_type,
+      $.self_type,
+      $.send_type,
+      $.receive_type,
+      $.generic_type,
+    ),
+
+    type_combination: $ => prec.left(PREC.type_combination, seq(
+      field('left', $._type),
+      field('operator', choice('|', '&')),
+      field('right', $._type),
+    )),
+
+    type_variable: $ => $._lowercase_identifier,
+
+    type_name: $ => seq(
+      field('name', $.global_name),
+      optional(field('arguments', $.type_arguments)),
+    ),
+
+    type_arguments: $ => seq('<', commaSep1($._type), '>'),
+
+    type_parameters: $ => seq('<', commaSep1($._lowercase_identifier), '>'),
+
+    box_type: $ => prec.right(PREC.type_prefix, seq('box', $._type)),
+
+    dual_type: $ => prec.right(PREC.type_prefix, seq('dual', $._type)),
+
+    either_type: $ => seq(
+      'either',
+      '{',
+      commaTerminated($.either_branch),
+      '}',
+    ),
+
+    either_branch: $ => seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      field('type', $._type),
+    ),
+
+    choice_type: $ => seq(
+      'choice',
+      '{',
+      commaTerminated($.choice_branch),
+      '}',
+    ),
+
+    choice_branch: $ => seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      repeat(seq('(', commaSep1($._type_prefix_item), ')')),
+      '=>',
+      field('type', $._type),
+    ),
+
+    unit_type: _ => '!',
+
+    continue_type: _ => '?',
+
+    recursive_type: $ => prec.right(PREC.type_prefix, seq(
+      'recursive',
+      optional($.label),
+      $._type,
+    )),
+
+    iterative_type: $ => prec.right(PREC.type_prefix, seq(
+      'iterative',
+      optional($.label),
+      $._type,
+    )),
+
+    self_type: $ => prec.right(seq('self', optional($.label))),
+
+    send_type: $ => prec.right(PREC.type_prefix, seq(
+      '(',
+      commaSep1($._type_prefix_item),
+      ')',
+      field('then', $._type),
+    )),
+
+    receive_type: $ => prec.right(PREC.type_prefix, seq(
+      '[',
+      commaSep1($._type_prefix_item),
+      ']',
+      field('then', $._type),
+    )),
+
+    generic_type: $ => prec.right(PREC.type_prefix, seq(
+      '<',
+      commaSep1($.type_parameter),
+      '>',
+      choice(
+        seq('(', $._type, ')'),
+        seq('[', $._type, ']'),
+      ),
+      field('then', $._type),
+    )),
+
+    type_parameter: $ => seq(
+      field('name', $._lowercase_identifier),
+      optional(seq(':', field('constraint', $.type_constraint))),
+    ),
+
+    type_constraint: $ => choice('box', $.lower_name),
+
+    explicit_type_parameter: $ => seq('type', $.type_parameter),
+
+    _type_prefix_item: $ => choice($.explicit_type_parameter, $._type),
+
+    // Patterns
+
+    _pattern: $ => choice(
+      $.name_pattern,
+      $.receive_pattern,
+      $.generic_receive_pattern,
+      $.continue_pattern,
+      $.default_pattern,
+      $.try_pattern,
+    ),
+
+    name_pattern: $ => seq(
+      field('name', $._lowercase_identifier),
+      optional(seq(':', field('type', $._type))),
+    ),
+
+    receive_pattern: $ => seq(
+      '(',
+      commaSep1($._pattern_prefix_item),
+      ')',
+      field('then', $._pattern),
+    ),
+
+    generic_receive_pattern: $ => seq(
+      '<',
+      commaSep1($.type_parameter),
+      '>',
+      '(',
+      $._pattern,
+      ')',
+      field('then', $._pattern),
+    ),
+
+    continue_pattern: _ => '!',
+
+    default_pattern: $ => seq(
+      'default',
+      '(',
+      field('default', $._expression),
+      ')',
+      field('then', $._pattern),
+    ),
+
+    try_pattern: $ => seq('try', optional($.label), field('then', $._pattern)),
+
+    _pattern_prefix_item: $ => choice($.explicit_type_parameter, $._pattern),
+
+    // Expressions
+
+    _expression: $ => choice(
+      $.binary_expression,
+      $.not_expression,
+      $.neg_expression,
+      $.is_expression,
+      $.integer,
+      $.float,
+      $.string,
+      $.template_string,
+      $.bytes,
+      $.list,
+      $.let_expression,
+      $.catch_expression,
+      $.throw_expression,
+      $.type_in_expression,
+      $.poll_expression,
+      $.submit_expression,
+      $.if_expression,
+      $.do_expression,
+      $.box_expression,
+      $.chan_expression,
+      $.macro_invocation,
+      $.global_name,
+      $._lowercase_identifier,
+      $.grouped_expression,
+      $.application,
+      $.signal_construction,
+      $.send_construction,
+      $.receive_construction,
+      $.generic_receive_construction,
+      $.case_construction,
+      $.begin_construction,
+      $.loop_construction,
+      $.unit,
+    ),
+
+    binary_expression: $ => {
+      const operators = [
+        [PREC.or, 'or'],
+        [PREC.and, 'and'],
+        [PREC.comparison, choice('<=', '>=', '==', '!=', '<', '>')],
+        [PREC.additive, choice('+', '-')],
+        [PREC.multiplicative, choice('*', '/')],
+      ];
+      return choice(...operators.map(([precedence, operator]) =>
+        prec.left(/** @type {number} */ (precedence), seq(
+          field('left', $._expression),
+          field('operator', operator),
+          field('right', $._expression),
+        )),
+      ));
+    },
+
+    not_expression: $ => prec(PREC.not, seq('not', $._expression)),
+
+    neg_expression: $ => prec(PREC.neg, seq('neg', $._expression)),
+
+    is_expression: $ => prec.left(PREC.is, seq(
+      field('subject', $._expression),
+      'is',
+      '.',
+      field('branch', $._lowercase_identifier),
+      optional(field('payload', choice(
+        $.receive_pattern,
+        $.continue_pattern,
+        $.name_pattern,
+      ))),
+    )),
+
+    grouped_expression: $ => seq('{', $._expression, '}'),
+
+    list: $ => seq('*', '(', commaSep($._expression), ')'),
+
+    bytes: $ => choice(
+      seq('<', '<>', '>'),
+      seq('<', '<', repeat($.integer), '>', '>'),
+    ),
+
+    template_string: $ => seq(
+      '`',
+      repeat(choice($.template_text, $.template_substitution)),
+      '`',
+    ),
+
+    template_substitution: $ => seq(
+      choice('${', '#{'),
+      $._expression,
+      '}',
+    ),
+
+    let_expression: $ => seq(
+      'let',
+      field('pattern', $._pattern),
+      '=',
+      field('value', $._expression),
+      'in',
+      field('body', $._expression),
+    ),
+
+    catch_expression: $ => seq(
+      'catch',
+      optional($.label),
+      field('pattern', $._pattern),
+      '=>',
+      field('handler', $._expression),
+      'in',
+      field('body', $._expression),
+    ),
+
+    throw_expression: $ => prec.right(seq(
+      'throw',
+      optional($.label),
+      $._expression,
+    )),
+
+    type_in_expression: $ => seq('type', $._type, 'in', $._expression),
+
+    if_expression: $ => seq(
+      'if',
+      '{',
+      repeat1($.if_branch),
+      optional(seq('else', '=>', field('else', $._expression), optional(','))),
+      '}',
+    ),
+
+    if_branch: $ => seq(
+      field('condition', $._expression),
+      '=>',
+      field('body', $._expression),
+      optional(','),
+    ),
+
+    poll_expression: $ => seq(
+      choice('poll', 'repoll'),
+      optional($.label),
+      '(',
+      commaSep($._expression),
+      ')',
+      '{',
+      field('name', $._lowercase_identifier),
+      '=>',
+      field('then', $._expression),
+      optional(','),
+      'else',
+      '=>',
+      field('else', $._expression),
+      optional(','),
+      '}',
+    ),
+
+    submit_expression: $ => seq(
+      'submit',
+      optional($.label),
+      '(',
+      commaSep($._expression),
+      ')',
+    ),
+
+    do_expression: $ => seq('do', $.block, 'in', field('result', $._expression)),
+
+    box_expression: $ => prec.right(seq('box', $._expression)),
+
+    chan_expression: $ => seq('chan', field('pattern', $._pattern), $.block),
+
+    // Applications
+
+    application: $ => prec.left(PREC.apply, seq(
+      field('subject', choice(
+        $.global_name,
+        $._lowercase_identifier,
+        $.grouped_expression,
+        $.application,
+      )),
+      choice(
+        $.arguments,
+        $.selection,
+        $.begin,
+        $.loop,
+        $.case,
+        $.default,
+        $.try,
+        $.pipe,
+      ),
+    )),
+
+    arguments: $ => seq(
+      '(',
+      commaSep1(choice($.named_argument, $.explicit_type_argument, $._expression)),
+      ')',
+    ),
+
+    named_argument: $ => seq(
+      field('name', $._lowercase_identifier),
+      ':',
+      field('value', $._expression),
+    ),
+
+    explicit_type_argument: $ => seq('type', $._type),
+
+    selection: $ => seq('.', field('name', $._lowercase_identifier)),
+
+    begin: $ => seq('.', choice('begin', 'unfounded'), optional($.label)),
+
+    loop: $ => seq('.', 'loop', optional($.label)),
+
+    case: $ => seq(
+      '.',
+      'case',
+      '{',
+      commaTerminated($.apply_branch),
+      optional(seq('else', $._apply_branch_body, optional(','))),
+      '}',
+    ),
+
+    apply_branch: $ => seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      $._apply_branch_body,
+    ),
+
+    _apply_branch_body: $ => seq(
+      repeat($._branch_prefix),
+      choice($._lowercase_identifier, $.continue_pattern),
+      '=>',
+      field('body', $._expression),
+    ),
+
+    _branch_prefix: $ => choice(
+      seq('(', commaSep1($._pattern_prefix_item), ')'),
+      seq('<', commaSep1($.type_parameter), '>', '(', $._pattern, ')'),
+      seq('try', optional($.label)),
+      seq('default', '(', $._expression, ')'),
+    ),
+
+    default: $ => seq('.', 'default', '(', $._expression, ')'),
+
+    try: $ => seq('.', 'try', optional($.label)),
+
+    pipe: $ => seq(
+      '->',
+      field('function', choice($.global_name, $._lowercase_identifier, $.grouped_expression)),
+    ),
+
+    // Constructions
+
+    signal_construction: $ => prec.right(PREC.construction, seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      field('then', $._expression),
+    )),
+
+    send_construction: $ => prec.right(PREC.construction, seq(
+      '(',
+      commaSep1(choice($.explicit_type_argument, $._expression)),
+      ')',
+      field('then', $._expression),
+    )),
+
+    receive_construction: $ => prec.right(PREC.construction, seq(
+      '[',
+      commaSep1($._pattern_prefix_item),
+      ']',
+      field('body', $._expression),
+    )),
+
+    generic_receive_construction: $ => prec.right(PREC.construction, seq(
+      '<',
+      commaSep1($.type_parameter),
+      '>',
+      '[',
+      $._pattern,
+      ']',
+      field('body', $._expression),
+    )),
+
+    case_construction: $ => seq(
+      'case',
+      '{',
+      commaTerminated($.construction_branch),
+      optional(seq('else', $._construction_branch_body, optional(','))),
+      '}',
+    ),
+
+    construction_branch: $ => seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      $._construction_branch_body,
+    ),
+
+    _construction_branch_body: $ => seq(
+      repeat(choice(
+        seq('(', commaSep1($._pattern_prefix_item), ')'),
+        seq('<', commaSep1($.type_parameter), '>', '(', $._pattern, ')'),
+      )),
+      '=>',
+      field('body', $._expression),
+    ),
+
+    begin_construction: $ => prec.right(PREC.construction, seq(
+      choice('begin', 'unfounded'),
+      optional($.label),
+      $._expression,
+    )),
+
+    loop_construction: $ => prec.right(seq('loop', optional($.label))),
+
+    unit: _ => '!',
+
+    // Processes
+
+    block: $ => seq('{', repeat($._statement), '}'),
+
+    _statement: $ => choice(
+      $.let_statement,
+      $.compound_assignment,
+      $.catch_statement,
+      $.throw_statement,
+      $.if_statement,
+      $.poll_statement,
+      $.submit_statement,
+      $.global_command,
+      $.command,
+    ),
+
+    let_statement: $ => seq(
+      'let',
+      field('pattern', $._pattern),
+      '=',
+      field('value', $._expression),
+    ),
+
+    compound_assignment: $ => seq(
+      field('name', $._lowercase_identifier),
+      field('operator', choice('+=', '-=', '*=', '/=')),
+      field('value', $._expression),
+    ),
+
+    catch_statement: $ => seq(
+      'catch',
+      optional($.label),
+      field('pattern', $._pattern),
+      '=>',
+      field('handler', $.block),
+    ),
+
+    throw_statement: $ => seq('throw', optional($.label), $._expression),
+
+    if_statement: $ => seq(
+      'if',
+      choice(
+        seq(
+          '{',
+          repeat1($.if_statement_branch),
+          optional(seq('else', '=>', field('else', $.block), optional(','))),
+          '}',
+        ),
+        seq(field('condition', $._expression), '=>', field('body', $.block)),
+      ),
+    ),
+
+    if_statement_branch: $ => seq(
+      field('condition', $._expression),
+      '=>',
+      field('body', $.block),
+      optional(','),
+    ),
+
+    poll_statement: $ => seq(
+      choice('poll', 'repoll'),
+      optional($.label),
+      '(',
+      commaSep($._expression),
+      ')',
+      '{',
+      field('name', $._lowercase_identifier),
+      '=>',
+      field('then', $.block),
+      optional(','),
+      'else',
+      '=>',
+      field('else', $.block),
+      optional(','),
+      '}',
+    ),
+
+    submit_statement: $ => seq(
+      'submit',
+      optional($.label),
+      '(',
+      commaSep($._expression),
+      ')',
+    ),
+
+    global_command: $ => prec.right(seq(
+      field('subject', $.global_name),
+      repeat1($._command_step),
+      optional(';'),
+    )),
+
+    command: $ => prec.right(seq(
+      field('subject', $._lowercase_identifier),
+      repeat($._command_step),
+      optional(';'),
+    )),
+
+    _command_step: $ => choice(
+      $.link,
+      $.selection,
+      $.command_case,
+      $.break,
+      $.continue,
+      $.begin,
+      $.begin_subject,
+      $.loop,
+      $.arguments,
+      $.receive,
+      $.try,
+      $.default,
+      $.pipe,
+    ),
+
+    begin_subject: $ => seq('&', field('name', $._lowercase_identifier)),
+
+    link: $ => seq('<>', $._expression),
+
+    break: _ => '!',
+
+    continue: _ => '?',
+
+    receive: $ => choice(
+      seq('[', commaSep1($._pattern_prefix_item), ']'),
+      seq('<', commaSep1($.type_parameter), '>', '[', $._pattern, ']'),
+    ),
+
+    command_case: $ => seq(
+      '.',
+      'case',
+      '{',
+      commaTerminated($.command_branch),
+      optional(seq('else', $._command_branch_body, optional(','))),
+      '}',
+    ),
+
+    command_branch: $ => seq(
+      '.',
+      field('name', $._lowercase_identifier),
+      $._command_branch_body,
+    ),
+
+    _command_branch_body: $ => seq(
+      repeat($._branch_prefix),
+      optional(choice($._lowercase_identifier, $.continue_pattern)),
+      '=>',
+      field('body', $.block),
+    ),
+
+    // Macros, expanded on the tokens before parsing
+
+    macro_definition: $ => seq(
+      'macro',
+      field('name', $.upper_name),
+      '(',
+      commaSep(choice($.lower_name, $.upper_name)),
+      ')',
+      field('body', $.token_tree),
+    ),
+
+    macro_invocation: $ => seq(
+      field('name', $.upper_name),
+      token.immediate('!'),
+      '(',
+      repeat($._token),
+      ')',
+    ),
+
+    token_tree: $ => seq('{', repeat($._token), '}'),
+
+    _token: $ => choice(
+      $.token_tree,
+      seq('(', repeat($._token), ')'),
+      seq('[', repeat($._token), ']'),
+      $.lower_name,
+      $.upper_name,
+      $.integer,
+      $.float,
+      $.string,
+      $.template_string,
+      $.punctuation,
+    ),
+
+    punctuation: _ => /[^\s\w{}()\[\]"`]+/,
+
+    // Tokens
+
+    lower_name: _ => /[a-z_][a-zA-Z0-9_]*/,
+
+    upper_name: _ => /[A-Z][a-zA-Z0-9_]*/,
+
+    integer: _ => /[+-]?[0-9](_?[0-9])*/,
+
+    float: _ => /[+-]?[0-9](_?[0-9])*\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?/,
+
+    string: _ => /"([^"\\]|\\(.|\n))*"/,
+
+    template_text: _ => token.immediate(prec(1, /([^`$#\\]|\\(.|\n)|[$#]+([^`{$#\\]|\\(.|\n)))+[$#]*|[$#]+/)),
+
+    line_comment: _ => /\/\/[^\n]*/,
+  },
+});
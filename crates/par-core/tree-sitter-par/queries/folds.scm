@@ -0,0 +1,20 @@
+[
+  (import)
+  (export)
+  (type_definition)
+  (definition)
+  (either_type)
+  (choice_type)
+  (block)
+  (case)
+  (case_construction)
+  (command_case)
+  (if_expression)
+  (if_statement)
+  (poll_expression)
+  (poll_statement)
+  (list)
+  (arguments)
+  (token_tree)
+  (block_comment)
+] @fold
@@ -0,0 +1,157 @@
+; Comments
+
+(line_comment) @comment
+(block_comment) @comment
+
+; Literals
+
+(integer) @number
+(float) @number.float
+(string) @string
+(template_string "`" @string)
+(template_text) @string
+(template_substitution ["${" "#{" "}"] @punctuation.special)
+(bytes) @number
+
+; Names
+
+(module_declaration name: (upper_name) @module)
+(import_path (upper_name) @module)
+(import_path package: (lower_name) @module)
+(import_entry alias: (upper_name) @module)
+(global_name qualifier: (upper_name) @module)
+
+(type_definition name: (upper_name) @type.definition)
+(type_name name: (global_name name: (upper_name) @type))
+(derive_clause (upper_name) @type)
+(type_parameters (lower_name) @type.parameter)
+(type_parameter name: (lower_name) @type.parameter)
+(type_variable) @type.parameter
+(type_constraint) @type.builtin
+
+(declaration name: (upper_name) @function)
+(definition name: (upper_name) @function)
+(macro_definition name: (upper_name) @function.macro)
+(macro_invocation name: (upper_name) @function.macro)
+(application subject: (global_name name: (upper_name) @function.call))
+(pipe function: (global_name name: (upper_name) @function.call))
+(global_command subject: (global_name name: (upper_name) @function.call))
+(global_name name: (upper_name) @constant)
+
+(named_parameter name: (lower_name) @variable.parameter)
+(named_argument name: (lower_name) @variable.parameter)
+(name_pattern name: (lower_name) @variable)
+(lower_name) @variable
+
+(either_branch name: (lower_name) @constructor)
+(choice_branch name: (lower_name) @constructor)
+(signal_construction name: (lower_name) @constructor)
+(construction_branch name: (lower_name) @constructor)
+(apply_branch name: (lower_name) @constructor)
+(command_branch name: (lower_name) @constructor)
+(selection name: (lower_name) @constructor)
+(is_expression branch: (lower_name) @constructor)
+
+(label) @label
+
+; Keywords
+
+[
+  "edition"
+  "module"
+  "import"
+  "export"
+  "as"
+  "type"
+  "dec"
+  "def"
+  "derive"
+  "macro"
+] @keyword
+
+[
+  "either"
+  "choice"
+  "box"
+  "dual"
+  "recursive"
+  "iterative"
+  "self"
+] @keyword.type
+
+[
+  "let"
+  "in"
+  "do"
+  "chan"
+  "begin"
+  "unfounded"
+  "loop"
+  "case"
+  "default"
+] @keyword
+
+[
+  "if"
+  "else"
+] @keyword.conditional
+
+[
+  "catch"
+  "throw"
+  "try"
+] @keyword.exception
+
+[
+  "poll"
+  "repoll"
+  "submit"
+] @keyword.coroutine
+
+[
+  "and"
+  "or"
+  "not"
+  "neg"
+  "is"
+] @keyword.operator
+
+(external) @keyword
+
+; Operators and punctuation
+
+[
+  "="
+  "=>"
+  "->"
+  "<>"
+  "|"
+  "&"
+  "+"
+  "-"
+  "*"
+  "/"
+  "=="
+  "!="
+  "<="
+  ">="
+  "+="
+  "-="
+  "*="
+  "/="
+] @operator
+
+(binary_expression operator: ["<" ">"] @operator)
+
+[
+  (unit)
+  (unit_type)
+  (break)
+  (continue)
+  (continue_type)
+  (continue_pattern)
+] @constant.builtin
+
+["(" ")" "[" "]" "{" "}"] @punctuation.bracket
+
+["," "." ":" ";" "@"] @punctuation.delimiter
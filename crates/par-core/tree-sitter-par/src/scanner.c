@@ -0,0 +1,61 @@
+// Block comments nest, like they do in the lexer, which a regular expression
+// can't match, so they're scanned here.
+
+#include "tree_sitter/parser.h"
+
+enum TokenType {
+  BLOCK_COMMENT,
+};
+
+void *tree_sitter_par_external_scanner_create(void) { return NULL; }
+
+void tree_sitter_par_external_scanner_destroy(void *payload) {}
+
+unsigned tree_sitter_par_external_scanner_serialize(void *payload, char *buffer) { return 0; }
+
+void tree_sitter_par_external_scanner_deserialize(void *payload, const char *buffer, unsigned length) {}
+
+bool tree_sitter_par_external_scanner_scan(void *payload, TSLexer *lexer, const bool *valid_symbols) {
+  if (!valid_symbols[BLOCK_COMMENT]) {
+    return false;
+  }
+
+  while (lexer->lookahead == ' ' || lexer->lookahead == '\t' || lexer->lookahead == '\r' ||
+         lexer->lookahead == '\n') {
+    lexer->advance(lexer, true);
+  }
+
+  if (lexer->lookahead != '/') {
+    return false;
+  }
+  lexer->advance(lexer, false);
+  if (lexer->lookahead != '*') {
+    return false;
+  }
+  lexer->advance(lexer, false);
+
+  // An unclosed comment isn't one: the lexer reads its `/` as a slash.
+  unsigned nesting = 0;
+  while (!lexer->eof(lexer)) {
+    if (lexer->lookahead == '/') {
+      lexer->advance(lexer, false);
+      if (lexer->lookahead == '*') {
+        lexer->advance(lexer, false);
+        nesting++;
+      }
+    } else if (lexer->lookahead == '*') {
+      lexer->advance(lexer, false);
+      if (lexer->lookahead == '/') {
+        lexer->advance(lexer, false);
+        if (nesting == 0) {
+          lexer->result_symbol = BLOCK_COMMENT;
+          return true;
+        }
+        nesting--;
+      }
+    } else {
+      lexer->advance(lexer, false);
+    }
+  }
+  return false;
+}
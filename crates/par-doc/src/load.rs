@@ -5,9 +5,10 @@ use std::sync::Arc;
 use par_builtin::{builtin_packages, inject_builtin_packages};
 use par_core::frontend::TypeError;
 use par_core::frontend::language::Universal;
+use par_core::semantic::SymbolKind;
 use par_core::workspace::{
-    PackageGraph, ParsedModule, Workspace, WorkspaceDiscoveryError, WorkspacePackage,
-    WorkspacePackages, assemble_workspace,
+    CheckedWorkspace, PackageGraph, ParsedModule, Workspace, WorkspaceDiscoveryError,
+    WorkspacePackage, WorkspacePackages, assemble_workspace,
 };
 use par_runtime::pkgid::PackageId;
 
@@ -73,10 +74,10 @@ fn load_workspace_site(graph: PackageGraph, only_exported: bool) -> Result<Loade
         &direct_dependencies,
     );
     let workspace = assemble_workspace(workspace_packages).map_err(DocError::Workspace)?;
-    ensure_type_checked(&workspace)?;
+    let checked = ensure_type_checked(&workspace)?;
 
     Ok(LoadedSite {
-        model: build_site_model(&workspace, package_meta, only_exported),
+        model: build_site_model(&workspace, &checked, package_meta, only_exported),
         default_out_dir,
     })
 }
@@ -88,18 +89,18 @@ fn load_builtin_site(start: &Path, only_exported: bool) -> Result<LoadedSite, Do
     };
     let package_meta = build_builtin_meta(&workspace_packages);
     let workspace = assemble_workspace(workspace_packages).map_err(DocError::Workspace)?;
-    ensure_type_checked(&workspace)?;
+    let checked = ensure_type_checked(&workspace)?;
 
     Ok(LoadedSite {
-        model: build_site_model(&workspace, package_meta, only_exported),
+        model: build_site_model(&workspace, &checked, package_meta, only_exported),
         default_out_dir: fallback_out_dir(start),
     })
 }
 
-fn ensure_type_checked(workspace: &Workspace) -> Result<(), DocError> {
-    let (_checked, type_errors) = workspace.type_check();
+fn ensure_type_checked(workspace: &Workspace) -> Result<CheckedWorkspace, DocError> {
+    let (checked, type_errors) = workspace.type_check();
     if type_errors.is_empty() {
-        Ok(())
+        Ok(checked)
     } else {
         Err(DocError::TypeCheck(render_type_errors(
             workspace,
@@ -188,35 +189,30 @@ fn build_builtin_meta(workspace_packages: &WorkspacePackages) -> BTreeMap<Packag
 
 fn build_site_model(
     workspace: &Workspace,
+    checked: &CheckedWorkspace,
     package_meta: BTreeMap<PackageId, PackageMeta>,
     only_exported: bool,
 ) -> SiteModel {
     let mut raw_items_by_module = BTreeMap::<Universal, Vec<RawItem>>::new();
 
-    for type_def in &workspace.lowered_module().type_defs {
-        raw_items_by_module
-            .entry(type_def.name.module.clone())
-            .or_default()
-            .push(RawItem {
-                kind: ItemKind::Type,
-                name: type_def.name.clone(),
-                params: type_def.params.iter().map(ToString::to_string).collect(),
-                typ: type_def.typ.clone(),
-                doc_markdown: type_def.doc.as_ref().map(|doc| doc.markdown.to_string()),
-                exported: type_def.exported,
-            });
-    }
-    for declaration in &workspace.lowered_module().declarations {
+    // Values are documented by their `dec`s only.
+    for symbol in checked.semantic_model().symbols() {
+        if symbol.declaration.is_none() {
+            continue;
+        }
         raw_items_by_module
-            .entry(declaration.name.module.clone())
+            .entry(symbol.name().module.clone())
             .or_default()
             .push(RawItem {
-                kind: ItemKind::Declaration,
-                name: declaration.name.clone(),
-                params: Vec::new(),
-                typ: declaration.typ.clone(),
-                doc_markdown: declaration.doc.as_ref().map(|doc| doc.markdown.to_string()),
-                exported: declaration.exported,
+                kind: match symbol.kind() {
+                    SymbolKind::Type => ItemKind::Type,
+                    SymbolKind::Declaration => ItemKind::Declaration,
+                },
+                name: symbol.name().clone(),
+                params: symbol.params.iter().map(ToString::to_string).collect(),
+                typ: symbol.typ.clone(),
+                doc_markdown: symbol.doc.as_ref().map(|doc| doc.markdown.to_string()),
+                exported: symbol.exported,
             });
     }
 
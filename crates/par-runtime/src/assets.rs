@@ -0,0 +1,41 @@
+//! Read-only data files bundled with a program.
+//!
+//! A package's `assets/` directory is collected at build time and carried in
+//! compiled artifacts, so a program can read its word lists, sample inputs and
+//! the like through the `Assets` builtin without depending on paths on the
+//! machine it runs on. Names are the `/`-separated paths relative to that
+//! directory. A run is given the assets it can read in its
+//! [`Setup`](crate::setup::Setup).
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{fs, io};
+
+use bytes::Bytes;
+
+/// The assets of a program, by name.
+pub type Assets = BTreeMap<String, Bytes>;
+
+/// Collects every file under `dir` as an asset.
+///
+/// A missing directory has no assets; it isn't an error.
+pub fn load_dir(dir: &Path) -> io::Result<Assets> {
+    let mut assets = Assets::new();
+    if dir.is_dir() {
+        load_dir_into(dir, "", &mut assets)?;
+    }
+    Ok(assets)
+}
+
+fn load_dir_into(dir: &Path, prefix: &str, assets: &mut Assets) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            load_dir_into(&entry.path(), &format!("{name}/"), assets)?;
+        } else {
+            assets.insert(name, Bytes::from(fs::read(entry.path())?));
+        }
+    }
+    Ok(())
+}
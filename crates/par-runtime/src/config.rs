@@ -0,0 +1,26 @@
+//! Configuration values of a run.
+//!
+//! A program reads them through the `Config` builtin, as string values looked
+//! up by string keys. They come from the `[config]` section of the package's
+//! `Par.toml`, overridden by `--config key=value` on the command line, or from
+//! the configuration dialog of the playground, so a program can be
+//! parameterized without editing its code. A run is given the values it reads
+//! in its [`Setup`](crate::setup::Setup).
+
+use std::collections::BTreeMap;
+
+/// The configuration values of a run, by key.
+pub type Config = BTreeMap<String, String>;
+
+/// Parses a `key=value` entry, as given on the command line. The value may
+/// contain further `=` signs, and may be empty; the key may not.
+pub fn parse_entry(entry: &str) -> Result<(String, String), String> {
+    let Some((key, value)) = entry.split_once('=') else {
+        return Err(format!("expected `key=value`, found `{entry}`"));
+    };
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("missing key in `{entry}`"));
+    }
+    Ok((key.to_owned(), value.to_owned()))
+}
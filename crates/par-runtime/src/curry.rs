@@ -0,0 +1,191 @@
+//! Adapters between Par's curried functions and Rust functions taking tuples.
+//!
+//! A Par function of several arguments, like `[Int, Int] Int`, receives them
+//! one at a time, so an external implementing it would otherwise start with a
+//! `handle.receive()` per argument. Instead, it can hand a Rust function of
+//! the arguments, as a tuple, to [`Handle::uncurry`]:
+//!
+//! ```ignore
+//! async fn int_min(handle: Handle) {
+//!     handle.uncurry(|(x, y): (BigInt, BigInt)| x.min(y)).await
+//! }
+//! ```
+//!
+//! A function of a single argument is handed to [`Handle::unary`] instead,
+//! taking the argument as it is.
+//!
+//! The other way around, [`Handle::send_args`] applies a Par function to a
+//! tuple of arguments.
+
+use std::future::{self, Future};
+
+use arcstr::literal;
+use bytes::Bytes;
+use num_bigint::{BigInt, BigUint};
+
+use crate::data::Data;
+use crate::primitive::{Number, ParString};
+use crate::readback::Handle;
+
+/// A value an external can receive from Par.
+pub trait Receive: Sized + Send + 'static {
+    fn receive(handle: Handle) -> impl Future<Output = Self> + Send + 'static;
+}
+
+/// A value an external can give to Par.
+pub trait Provide {
+    fn provide(self, handle: Handle);
+}
+
+/// The arguments of a curried Par function, received one by one into a tuple.
+pub trait ReceiveArgs: Sized + Send + 'static {
+    fn receive_args(handle: &mut Handle) -> impl Future<Output = Self> + Send;
+}
+
+/// Arguments to apply a curried Par function to, sent one by one from a tuple.
+pub trait SendArgs {
+    fn send_args(self, handle: &mut Handle);
+}
+
+macro_rules! primitive {
+    ($typ:ty, $receive:ident, $provide:ident($($by_ref:tt)?)) => {
+        impl Receive for $typ {
+            fn receive(handle: Handle) -> impl Future<Output = Self> + Send + 'static {
+                handle.$receive()
+            }
+        }
+
+        impl Provide for $typ {
+            fn provide(self, handle: Handle) {
+                handle.$provide($($by_ref)? self)
+            }
+        }
+    };
+}
+
+primitive!(BigInt, int, provide_int());
+primitive!(BigUint, nat, provide_nat());
+primitive!(f64, float, provide_float());
+primitive!(ParString, string, provide_string());
+primitive!(Bytes, bytes, provide_bytes());
+primitive!(char, char, provide_char());
+primitive!(u8, byte, provide_byte());
+primitive!(Number, number, provide_number(&));
+primitive!(Data, data, provide_data(&));
+
+/// Anything else is received as the handle itself, to read back by hand.
+impl Receive for Handle {
+    fn receive(handle: Handle) -> impl Future<Output = Self> + Send + 'static {
+        future::ready(handle)
+    }
+}
+
+impl Provide for Handle {
+    fn provide(self, handle: Handle) {
+        handle.link(self)
+    }
+}
+
+/// `!`, waited for when received.
+impl Receive for () {
+    fn receive(handle: Handle) -> impl Future<Output = Self> + Send + 'static {
+        handle.continue_();
+        future::ready(())
+    }
+}
+
+impl Provide for () {
+    fn provide(self, handle: Handle) {
+        handle.break_()
+    }
+}
+
+/// `Bool`, as `either { .false!, .true! }`.
+impl Provide for bool {
+    fn provide(self, mut handle: Handle) {
+        handle.signal(if self {
+            literal!("true")
+        } else {
+            literal!("false")
+        });
+        handle.break_();
+    }
+}
+
+/// `Option<T>`, as `either { .none!, .some T }`.
+impl<T: Provide> Provide for Option<T> {
+    fn provide(self, mut handle: Handle) {
+        match self {
+            Some(value) => {
+                handle.signal(literal!("some"));
+                value.provide(handle);
+            }
+            None => {
+                handle.signal(literal!("none"));
+                handle.break_();
+            }
+        }
+    }
+}
+
+macro_rules! args {
+    ($($arg:ident $value:ident),+) => {
+        impl<$($arg: Receive),+> ReceiveArgs for ($($arg,)+) {
+            fn receive_args(handle: &mut Handle) -> impl Future<Output = Self> + Send {
+                // Receiving an argument doesn't wait for it, so all of them
+                // can be split off before waiting for any.
+                $(let $value = handle.receive();)+
+                async move { ($($arg::receive($value).await,)+) }
+            }
+        }
+
+        impl<$($arg: Provide),+> SendArgs for ($($arg,)+) {
+            fn send_args(self, handle: &mut Handle) {
+                let ($($value,)+) = self;
+                $($value.provide(handle.send());)+
+            }
+        }
+    };
+}
+
+args!(A a, B b);
+args!(A a, B b, C c);
+args!(A a, B b, C c, D d);
+args!(A a, B b, C c, D d, E e);
+args!(A a, B b, C c, D d, E e, F f);
+
+impl Handle {
+    /// Receives all the arguments of a curried function, in order.
+    pub fn receive_args<A: ReceiveArgs>(&mut self) -> impl Future<Output = A> + Send {
+        A::receive_args(self)
+    }
+
+    /// Applies the curried function this is to all of `args`, in order,
+    /// leaving this as its result.
+    pub fn send_args(&mut self, args: impl SendArgs) {
+        args.send_args(self)
+    }
+
+    /// Implements a curried function with `f` of its arguments, as a tuple.
+    pub async fn uncurry<A: ReceiveArgs, R: Provide>(mut self, f: impl FnOnce(A) -> R) {
+        let args = self.receive_args().await;
+        f(args).provide(self)
+    }
+
+    /// Implements a function of a single argument with `f` of it.
+    pub async fn unary<A: Receive, R: Provide>(mut self, f: impl FnOnce(A) -> R) {
+        let arg = A::receive(self.receive()).await;
+        f(arg).provide(self)
+    }
+
+    /// Like [`Handle::uncurry`], for a function that's asynchronous itself.
+    pub async fn uncurry_async<A, R, Fut>(mut self, f: impl FnOnce(A) -> Fut)
+    where
+        A: ReceiveArgs,
+        R: Provide,
+        Fut: Future<Output = R>,
+    {
+        let args = self.receive_args().await;
+        f(args).await.provide(self)
+    }
+}
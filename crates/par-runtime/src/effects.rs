@@ -0,0 +1,227 @@
+//! The effects a run has on the world outside its net, and their results.
+//!
+//! Every builtin that reaches outside the net, to the console, the file
+//! system, the host's clock or the network, has its effect through the run's
+//! [`Effects`], which records the result. A run that's started over to get
+//! back to where an earlier one was, like when undoing in the playground, is
+//! [given](Effects::replaying) the results the earlier run recorded. Each of
+//! its effects is then answered with the result the earlier run got in its
+//! place, instead of being had again: nothing is printed or written twice, a
+//! failure stays a failure, and what was read is read the same.
+//!
+//! Effects are told apart by a key, like the name of the builtin and the path
+//! it works on, and replayed in order among those with the same key, so it
+//! doesn't matter how effects with different keys interleave. An effect with
+//! no recorded result left under its key, or with one of another type, is had
+//! for real.
+//!
+//! Some effects can't be recorded, like serving requests. A run that has one
+//! [can't be replayed](Effects::forbid_replay) past it.
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Outcome = Arc<dyn Any + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct Effects {
+    journal: Arc<Mutex<Journal>>,
+}
+
+struct Journal {
+    /// The key and result of every effect the run had, in order.
+    had: Vec<(String, Outcome)>,
+    /// Results of an earlier run's effects, by key, yet to be replayed.
+    replay: HashMap<String, VecDeque<Outcome>>,
+    replayable: bool,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            had: Vec::new(),
+            replay: HashMap::new(),
+            replayable: true,
+        }
+    }
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The effects of a run replaying the first `count` effects `earlier`
+    /// had.
+    pub fn replaying(earlier: &Effects, count: usize) -> Self {
+        let mut replay: HashMap<String, VecDeque<Outcome>> = HashMap::new();
+        for (key, outcome) in earlier.journal().had.iter().take(count) {
+            replay
+                .entry(key.clone())
+                .or_default()
+                .push_back(Arc::clone(outcome));
+        }
+        Self {
+            journal: Arc::new(Mutex::new(Journal {
+                replay,
+                ..Journal::default()
+            })),
+        }
+    }
+
+    /// How many effects the run had so far, replayed ones included.
+    pub fn len(&self) -> usize {
+        self.journal().had.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether any results are left to replay.
+    pub fn is_replaying(&self) -> bool {
+        !self.journal().replay.is_empty()
+    }
+
+    /// Drops the results left to replay, so every effect from here on is had
+    /// for real.
+    pub fn stop_replaying(&self) {
+        self.journal().replay.clear();
+    }
+
+    /// Notes that the run had an effect whose result can't be recorded, so
+    /// it can't be replayed up to here.
+    pub fn forbid_replay(&self) {
+        self.journal().replayable = false;
+    }
+
+    /// Whether every effect the run had so far was recorded.
+    pub fn is_replayable(&self) -> bool {
+        self.journal().replayable
+    }
+
+    /// Has the effect under `key` with `effect`, and records its result. While
+    /// replaying, the result an earlier run recorded in its place is given
+    /// instead, and `effect` isn't called.
+    pub async fn have<T, F>(&self, key: impl Into<String>, effect: impl FnOnce() -> F) -> T
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = T>,
+    {
+        let key = key.into();
+        let outcome = match self.replayed(&key) {
+            Some(outcome) => outcome,
+            None => effect().await,
+        };
+        self.record(key, &outcome);
+        outcome
+    }
+
+    /// Like [`have`](Self::have), for an effect that's had right away.
+    pub fn have_now<T>(&self, key: impl Into<String>, effect: impl FnOnce() -> T) -> T
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let key = key.into();
+        let outcome = self.replayed(&key).unwrap_or_else(effect);
+        self.record(key, &outcome);
+        outcome
+    }
+
+    /// The next result recorded under `key`, if there's one left and it's a
+    /// `T`. Otherwise, the run went another way than the earlier one, and
+    /// nothing more is replayed under `key`.
+    fn replayed<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        let mut journal = self.journal();
+        let queue = journal.replay.get_mut(key)?;
+        let outcome = queue.pop_front();
+        let replayed = outcome
+            .as_ref()
+            .and_then(|outcome| outcome.downcast_ref::<T>());
+        let replayed = replayed.cloned();
+        if replayed.is_none() || queue.is_empty() {
+            journal.replay.remove(key);
+        }
+        replayed
+    }
+
+    fn record<T: Clone + Send + Sync + 'static>(&self, key: String, outcome: &T) {
+        self.journal().had.push((key, Arc::new(outcome.clone())));
+    }
+
+    fn journal(&self) -> MutexGuard<'_, Journal> {
+        self.journal
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_replaying_run_gets_the_results_recorded_before() {
+        let earlier = Effects::new();
+        assert_eq!(
+            earlier.have_now("line", || Some(String::from("Ada"))),
+            Some(String::from("Ada"))
+        );
+        assert_eq!(
+            earlier.have_now("remove", || Err::<(), _>(String::from("not found"))),
+            Err(String::from("not found"))
+        );
+        assert_eq!(earlier.have_now("line", || None::<String>), None);
+
+        let replaying = Effects::replaying(&earlier, earlier.len());
+        assert!(replaying.is_replaying());
+        let unhad = || -> Result<(), String> { unreachable!("replayed effects aren't had again") };
+        assert_eq!(
+            replaying.have_now("remove", unhad),
+            Err(String::from("not found"))
+        );
+        assert_eq!(
+            replaying.have_now("line", || -> Option<String> { unreachable!() }),
+            Some(String::from("Ada"))
+        );
+        assert_eq!(
+            replaying.have_now("line", || -> Option<String> { unreachable!() }),
+            None
+        );
+        assert!(!replaying.is_replaying());
+        assert_eq!(
+            replaying.have_now("line", || Some(String::from("new"))),
+            Some(String::from("new"))
+        );
+        assert_eq!(replaying.len(), 4);
+    }
+
+    #[test]
+    fn only_the_first_effects_are_replayed() {
+        let earlier = Effects::new();
+        earlier.have_now("time", || 1);
+        earlier.have_now("time", || 2);
+        let replaying = Effects::replaying(&earlier, 1);
+        assert_eq!(replaying.have_now("time", || 3), 1);
+        assert_eq!(replaying.have_now("time", || 3), 3);
+    }
+
+    #[test]
+    fn a_result_of_another_type_stops_the_replay() {
+        let earlier = Effects::new();
+        earlier.have_now("read", || 1u8);
+        earlier.have_now("read", || 2u8);
+        let replaying = Effects::replaying(&earlier, 2);
+        assert_eq!(replaying.have_now("read", || String::from("new")), "new");
+        assert_eq!(replaying.have_now("read", || 3u8), 3);
+    }
+
+    #[test]
+    fn a_forbidden_replay_sticks() {
+        let effects = Effects::new();
+        assert!(effects.is_replayable());
+        effects.forbid_replay();
+        assert!(!effects.clone().is_replayable());
+    }
+}
@@ -0,0 +1,101 @@
+//! What a run sees of the world outside its net.
+//!
+//! Every run is started with an [`Environment`] of its own, and every
+//! [`Handle`](crate::readback::Handle) the run gives to builtins carries it.
+//! Nothing in it is shared with other runs, so runs in one process, like those
+//! of the playground, can't disturb each other, and a run that's started over
+//! starts from scratch.
+//!
+//! A deterministic environment stands in for the host's clock: it's virtual,
+//! and only advances when it is read. Together with a single-threaded
+//! executor, this makes every run of a program go the same way.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How far the virtual clock advances on every reading.
+const VIRTUAL_TICK_NANOS: i128 = 1_000_000;
+
+/// Fixed starting point of the virtual clock: 2024-01-01T00:00:00Z.
+const VIRTUAL_EPOCH_NANOS: i128 = 1_704_067_200 * 1_000_000_000;
+
+#[derive(Clone)]
+pub struct Environment {
+    deterministic: bool,
+    /// How many times the virtual clock was read.
+    ticks: Arc<AtomicU64>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    /// An environment backed by the host's clock.
+    pub fn new() -> Self {
+        Self {
+            deterministic: false,
+            ticks: Arc::default(),
+        }
+    }
+
+    /// A deterministic environment, with a virtual clock.
+    pub fn deterministic() -> Self {
+        Self {
+            deterministic: true,
+            ticks: Arc::default(),
+        }
+    }
+
+    /// The same environment, for a run that starts over: whether it's
+    /// deterministic is kept, but nothing the previous run went through, so a
+    /// deterministic environment's clock starts over too.
+    pub fn renewed(&self) -> Self {
+        Self {
+            deterministic: self.deterministic,
+            ticks: Arc::default(),
+        }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Reads the virtual clock, in nanoseconds since the Unix epoch, if the
+    /// run is deterministic. Otherwise, the host's clock is the one to read.
+    pub fn virtual_now_nanos(&self) -> Option<i128> {
+        if !self.deterministic {
+            return None;
+        }
+        let ticks = self.ticks.fetch_add(1, Ordering::SeqCst);
+        Some(VIRTUAL_EPOCH_NANOS + ticks as i128 * VIRTUAL_TICK_NANOS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_environment_starts_its_own_clock() {
+        let first = Environment::deterministic();
+        first.virtual_now_nanos();
+        first.virtual_now_nanos();
+        assert_eq!(
+            Environment::deterministic().virtual_now_nanos(),
+            Some(VIRTUAL_EPOCH_NANOS)
+        );
+        assert_eq!(Environment::new().virtual_now_nanos(), None);
+    }
+
+    #[test]
+    fn a_renewed_environment_starts_over() {
+        let environment = Environment::deterministic();
+        environment.virtual_now_nanos();
+        let renewed = environment.renewed();
+        assert!(renewed.is_deterministic());
+        assert_eq!(renewed.virtual_now_nanos(), Some(VIRTUAL_EPOCH_NANOS));
+    }
+}
@@ -1,22 +1,44 @@
 use crate::flat::arena::Arena;
 use crate::flat::reducer::Reducer;
 use crate::flat::runtime::{PackagePtr, Runtime};
-use crate::flat::stats::Rewrites;
+use crate::flat::stats::{Progress, Rewrites};
 use crate::linker::Linked;
 use crate::readback::Handle;
+use crate::setup::Setup;
 use futures::future::RemoteHandle;
 use futures::task::{Spawn, SpawnExt};
 use std::sync::Arc;
 
+/// Starts a run of `package` with `setup`, or just in an
+/// [`Environment`](crate::environment::Environment), returning a handle to its
+/// root and the reducer, which finishes with the run's statistics.
 pub fn start_and_instantiate(
     spawner: Arc<dyn Spawn + Send + Sync + 'static>,
+    setup: impl Into<Setup>,
     arena: Arc<Arena<Linked>>,
     package: PackagePtr<Linked>,
 ) -> (Handle, RemoteHandle<Rewrites>) {
-    let (reducer, net_handle) = Reducer::from(Runtime::from(arena.clone()), spawner.clone());
+    let (handle, reducer_future, _) =
+        start_and_instantiate_with_progress(spawner, setup, arena, package);
+    (handle, reducer_future)
+}
+
+/// Like [`start_and_instantiate`], but also returns a live view of the
+/// reducer's statistics.
+pub fn start_and_instantiate_with_progress(
+    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
+    setup: impl Into<Setup>,
+    arena: Arc<Arena<Linked>>,
+    package: PackagePtr<Linked>,
+) -> (Handle, RemoteHandle<Rewrites>, Progress) {
+    let setup = setup.into();
+    let (reducer, net_handle) =
+        Reducer::from(Runtime::new(arena.clone(), setup.clone()), spawner.clone());
+    let progress = reducer.progress();
     let reducer_future = reducer.spawn_reducer();
     let handle =
-        crate::flat::readback::Handle::from_package(arena.clone(), net_handle, package).unwrap();
+        crate::flat::readback::Handle::from_package(arena.clone(), setup, net_handle, package)
+            .unwrap();
     (
         Handle::from(handle),
         spawner
@@ -25,5 +47,6 @@ pub fn start_and_instantiate(
                 reducer.runtime.rewrites
             })
             .unwrap(),
+        progress,
     )
 }
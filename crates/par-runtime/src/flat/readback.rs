@@ -1,9 +1,14 @@
 use super::reducer::{NetHandle, ReducerMessage};
 use super::runtime::{ExternalFn, Global, GlobalCont, Linear, Node, PackagePtr, Value};
+use crate::assets::Assets;
+use crate::config::Config;
 use crate::data::Data;
+use crate::effects::Effects;
+use crate::environment::Environment;
 use crate::flat::arena::{Arena, Index};
 use crate::flat::runtime::Linker;
 use crate::primitive::{Number, Primitive};
+use crate::setup::Setup;
 use arcstr::ArcStr;
 use futures::task::FutureObj;
 use std::future::Future;
@@ -18,15 +23,32 @@ pub enum Error {
     InvalidNode(Node<Linked>),
     InvalidValue(Value<Node<Linked>, Linked>),
     InvalidPrimitive(Primitive),
+    /// The other side was dropped without ever providing a value.
+    Disconnected,
     Panicked,
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidNode(node) => write!(f, "unexpected node {node:?}"),
+            Error::InvalidValue(value) => write!(f, "unexpected value {value:?}"),
+            Error::InvalidPrimitive(primitive) => write!(f, "unexpected primitive {primitive:?}"),
+            Error::Disconnected => write!(f, "no value was ever provided"),
+            Error::Panicked => write!(f, "the provider of the value panicked"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub(crate) type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Clone)]
 struct HandleLinker {
     net: NetHandle,
     arena: Arc<Arena<Linked>>,
+    setup: Setup,
 }
 
 pub struct Handle {
@@ -50,19 +72,25 @@ impl Handle {
         }
     }
 
-    pub fn from_node(arena: Arc<Arena<Linked>>, net: NetHandle, node: Node<Linked>) -> Self {
+    pub(crate) fn from_node(
+        arena: Arc<Arena<Linked>>,
+        setup: Setup,
+        net: NetHandle,
+        node: Node<Linked>,
+    ) -> Self {
         Self {
-            linker: HandleLinker { arena, net },
+            linker: HandleLinker { arena, net, setup },
             node: Box::new(node),
         }
     }
 
-    pub fn from_package(
+    pub(crate) fn from_package(
         arena: Arc<Arena<Linked>>,
+        setup: Setup,
         net: NetHandle,
         package: PackagePtr<Linked>,
     ) -> Result<Handle> {
-        let mut linker = HandleLinker { arena, net };
+        let mut linker = HandleLinker { arena, net, setup };
         let root = linker.instantiate_package_captures(
             package,
             Node::Linear(Linear::Value(Box::new(Value::Break))),
@@ -73,6 +101,22 @@ impl Handle {
         })
     }
 
+    pub fn environment(&self) -> &Environment {
+        self.linker.setup.environment()
+    }
+
+    pub fn config(&self) -> &Config {
+        self.linker.setup.config()
+    }
+
+    pub fn assets(&self) -> &Assets {
+        self.linker.setup.assets()
+    }
+
+    pub fn effects(&self) -> &Effects {
+        self.linker.setup.effects()
+    }
+
     pub fn link_with(mut self, dual: Handle) {
         self.linker.link(self.node, dual.node);
     }
@@ -99,9 +143,9 @@ impl Handle {
             .unwrap();
     }
 
-    pub async fn await_ready(mut self) -> Self {
-        let value = self.destruct().await;
-        self.new(Node::Linear(Linear::Value(Box::new(value))))
+    pub async fn await_ready(mut self) -> Result<Self> {
+        let value = self.destruct().await?;
+        Ok(self.new(Node::Linear(Linear::Value(Box::new(value)))))
     }
 
     pub fn provide_external_closure<Fun, Fut>(mut self, f: Fun)
@@ -141,7 +185,7 @@ impl Handle {
     }
 
     pub async fn primitive(mut self) -> Result<Primitive> {
-        let primitive = match self.destruct().await {
+        let primitive = match self.destruct().await? {
             Value::Primitive(p) => p,
             node => return Err(Error::InvalidValue(node)),
         };
@@ -157,7 +201,7 @@ impl Handle {
     }
 
     pub async fn data(mut self) -> Result<Data> {
-        let value = self.destruct().await;
+        let value = self.destruct().await?;
         self.data_from_value(value).await
     }
 
@@ -199,7 +243,7 @@ impl Handle {
     }
 
     pub async fn receive_data(&mut self) -> Result<Data> {
-        let value = self.destruct().await;
+        let value = self.destruct().await?;
         let Value::Pair(left, right) = value else {
             return Err(Error::InvalidValue(value));
         };
@@ -208,7 +252,7 @@ impl Handle {
     }
 
     pub async fn receive_number(&mut self) -> Result<Number> {
-        let value = self.destruct().await;
+        let value = self.destruct().await?;
         let Value::Pair(left, right) = value else {
             return Err(Error::InvalidValue(value));
         };
@@ -240,17 +284,18 @@ impl Handle {
         self.linker.link(choice, Box::new(either));
     }
 
-    pub async fn case(&mut self) -> ArcStr {
+    pub async fn case(&mut self) -> Result<ArcStr> {
         let linker = self.linker.clone();
 
-        let Value::Either(name, payload) = (*self).destruct().await else {
-            unreachable!()
+        let (name, payload) = match (*self).destruct().await? {
+            Value::Either(name, payload) => (name, payload),
+            value => return Err(Error::InvalidValue(value)),
         };
         *self = Handle {
             linker,
             node: Box::new(payload),
         };
-        self.linker.arena.get(name).into()
+        Ok(self.linker.arena.get(name).into())
     }
 
     pub fn break_(mut self) {
@@ -304,20 +349,22 @@ impl Handle {
         self.new(Node::Shared(shared).into())
     }
 
-    async fn destruct(&mut self) -> Value<Node<Linked>, Linked> {
+    async fn destruct(&mut self) -> Result<Value<Node<Linked>, Linked>> {
         let node = mem::replace(self.node.as_mut(), Node::Empty);
         let node = self.linker.deref(node);
-        match self.linker.destruct(node) {
-            Ok(value) => {
-                return value;
-            }
-            Err(node) => {
-                let (tx, rx) = oneshot::channel();
-                self.linker
-                    .link(Box::new(Node::Linear(Linear::Request(tx))), Box::new(node));
-                rx.await.unwrap()
+        let node = match self.linker.destruct(node) {
+            // An external function standing for a value, such as a definition
+            // `def X = external`, has to be called to produce it.
+            Ok(value @ (Value::ExternalFn(_) | Value::ExternalArc(_))) => {
+                Node::Linear(Linear::Value(Box::new(value)))
             }
-        }
+            Ok(value) => return Ok(value),
+            Err(node) => node,
+        };
+        let (tx, rx) = oneshot::channel();
+        self.linker
+            .link(Box::new(Node::Linear(Linear::Request(tx))), Box::new(node));
+        rx.await.map_err(|_| Error::Disconnected)
     }
 
     fn data_node(&self, data: &Data) -> Node<Linked> {
@@ -1,5 +1,6 @@
 use super::readback::Handle;
 use crate::flat::runtime::{Node, Runtime, UserData};
+use crate::flat::stats::Progress;
 use crate::linker::Linked;
 use futures::future::RemoteHandle;
 use futures::task::{FutureObj, Spawn, SpawnExt};
@@ -38,8 +39,14 @@ pub(crate) struct Reducer {
     inbox: mpsc::UnboundedReceiver<ReducerMessage>,
     sender: mpsc::WeakUnboundedSender<ReducerMessage>,
     num_handles: Arc<AtomicUsize>,
+    progress: Progress,
 }
 
+/// How many reductions happen between two updates of [`Progress`], and
+/// between two times the reducer checks if the run is cancelled and lets
+/// other tasks go.
+const PROGRESS_PERIOD: u32 = 4096;
+
 impl Reducer {
     pub(crate) fn from(
         runtime: Runtime,
@@ -54,6 +61,7 @@ impl Reducer {
                 inbox: rx,
                 sender: tx.downgrade(),
                 num_handles: num_handles.clone(),
+                progress: Progress::default(),
             },
             NetHandle(tx, 0, num_handles),
         )
@@ -97,7 +105,7 @@ impl Reducer {
     fn handle_message(&mut self, msg: ReducerMessage) {
         match msg {
             ReducerMessage::Redex(a, b) => {
-                self.runtime.redexes.push((a, b));
+                self.runtime.push_redex(a, b);
             }
             ReducerMessage::Spawn(s) => {
                 self.spawner.spawn_obj(s).unwrap();
@@ -106,25 +114,45 @@ impl Reducer {
             ReducerMessage::Created(_) => {}
         }
     }
+    pub(crate) fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
     pub(crate) async fn run(&mut self) {
+        let mut since_progress = 0;
         loop {
             loop {
                 if !self.runtime.redexes.is_empty() {
+                    since_progress += 1;
+                    if since_progress >= PROGRESS_PERIOD {
+                        since_progress = 0;
+                        self.progress.publish(&self.runtime.rewrites);
+                        if self.runtime.setup.is_cancelled() {
+                            return;
+                        }
+                        tokio::task::yield_now().await;
+                    }
                     #[cfg(not(target_family = "wasm"))]
                     let start = Instant::now();
                     if let Some((a, b)) = self.runtime.reduce() {
                         match (a, b) {
                             (UserData::ExternalFn(f), other) => {
+                                self.runtime.rewrites.count_external_call(f as usize);
                                 let handle = Handle::from_node(
                                     self.runtime.arena.clone(),
+                                    self.runtime.setup.clone(),
                                     self.net_handle().await,
                                     other,
                                 );
                                 self.spawner.spawn(f(handle.into())).unwrap();
                             }
                             (UserData::ExternalArc(f), other) => {
+                                self.runtime
+                                    .rewrites
+                                    .count_external_call(Arc::as_ptr(&f.0) as *const () as usize);
                                 let handle = Handle::from_node(
                                     self.runtime.arena.clone(),
+                                    self.runtime.setup.clone(),
                                     self.net_handle().await,
                                     other,
                                 );
@@ -147,6 +175,7 @@ impl Reducer {
                     }
                 }
             }
+            self.progress.publish(&self.runtime.rewrites);
             match self.inbox.recv().await {
                 Some(msg) => {
                     self.handle_message(msg);
@@ -38,6 +38,7 @@ use super::arena::*;
 use crate::fan_behavior::FanBehavior;
 use crate::flat::stats::Rewrites;
 use crate::linker::Linked;
+use crate::setup::Setup;
 use atomicbox::AtomicOptionBox;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::Ordering::AcqRel;
@@ -70,6 +71,8 @@ struct InstanceInner(Box<[AtomicOptionBox<Node<Linked>>]>);
 /// the runtime is designed, all slots inside of it must be empty. This is when the `Instance` is destroyed.
 pub struct Instance {
     vars: Arc<InstanceInner>,
+    /// The package this is an instance of, for profiling.
+    package: PackagePtr<Linked>,
 }
 
 impl Instance {
@@ -311,6 +314,10 @@ pub struct Runtime {
     pub arena: Arc<Arena<Linked>>,
     pub redexes: Vec<(Box<Node<Linked>>, Box<Node<Linked>>)>,
     pub rewrites: Rewrites,
+    /// What the run was started with, given to its builtins.
+    pub(crate) setup: Setup,
+    /// Whether to fill in [`Rewrites::by_package`].
+    profile: bool,
 }
 
 /// This trait is implemented by everything that knows how to link two nodes together
@@ -404,7 +411,11 @@ pub(crate) trait Linker {
     }
 
     // Package-related methods
-    fn create_package_instance(&mut self, package: &Package<Linked>) -> Instance {
+    fn create_package_instance(
+        &mut self,
+        ptr: PackagePtr<Linked>,
+        package: &Package<Linked>,
+    ) -> Instance {
         let num_vars = package.num_vars;
         let mut vars = Vec::with_capacity(num_vars);
         for _ in 0..num_vars {
@@ -412,6 +423,7 @@ pub(crate) trait Linker {
         }
         Instance {
             vars: Arc::new(InstanceInner(vars.into_boxed_slice())),
+            package: ptr,
         }
     }
 
@@ -431,22 +443,15 @@ pub(crate) trait Linker {
             Node::Global(instance.clone(), body.captures),
         )
     }
-    fn instantiate_package_captures_direct(
-        &mut self,
-        package: &Package<Linked>,
-        captures: Node<Linked>,
-    ) -> Node<Linked> {
-        let instance = self.create_package_instance(package);
-        self.instantiate_package_body_captures(instance, &package.body, captures)
-    }
     fn instantiate_package_captures(
         &mut self,
-        package: Index<Linked, OnceLock<Package<Linked>>>,
+        ptr: Index<Linked, OnceLock<Package<Linked>>>,
         captures: Node<Linked>,
     ) -> Node<Linked> {
         let arena = self.arena();
-        let package = arena.get(package).get().unwrap();
-        self.instantiate_package_captures_direct(package, captures)
+        let package = arena.get(ptr).get().unwrap();
+        let instance = self.create_package_instance(ptr, package);
+        self.instantiate_package_body_captures(instance, &package.body, captures)
     }
     fn instantiate_package_body_captures(
         &mut self,
@@ -480,12 +485,14 @@ pub(crate) trait Linker {
     }
 }
 
-impl From<Arc<Arena<Linked>>> for Runtime {
-    fn from(arena: Arc<Arena<Linked>>) -> Self {
+impl Runtime {
+    pub fn new(arena: Arc<Arena<Linked>>, setup: Setup) -> Self {
         Self {
             arena,
             redexes: vec![],
             rewrites: Rewrites::default(),
+            profile: setup.is_profiling(),
+            setup,
         }
     }
 }
@@ -498,7 +505,7 @@ macro_rules! sym {
 
 impl Linker for Runtime {
     fn link(&mut self, a: Box<Node<Linked>>, b: Box<Node<Linked>>) {
-        self.redexes.push((a, b));
+        self.push_redex(a, b);
     }
     fn arena(&self) -> Arc<Arena<Linked>> {
         self.arena.clone()
@@ -507,6 +514,13 @@ impl Linker for Runtime {
 
 impl Runtime {
     // Misc methods.
+    pub(crate) fn push_redex(&mut self, a: Box<Node<Linked>>, b: Box<Node<Linked>>) {
+        self.redexes.push((a, b));
+        let waiting = 2 * self.redexes.len() as u64;
+        if waiting > self.rewrites.peak_nodes {
+            self.rewrites.peak_nodes = waiting;
+        }
+    }
     fn set_var(&mut self, instance: Instance, index: usize, value: Box<Node<Linked>>) {
         let slot = instance
             .vars
@@ -535,8 +549,26 @@ impl Runtime {
     /// This function is analogous to a "VM enter"
     pub fn reduce(&mut self) -> Option<(UserData, Node<Linked>)> {
         while let Some((a, b)) = self.redexes.pop() {
-            if let Some(v) = self.interact(a, b) {
-                return Some(v);
+            if !self.profile {
+                if let Some(v) = self.interact(a, b) {
+                    return Some(v);
+                }
+                continue;
+            }
+            let package = [&a, &b].into_iter().find_map(|node| match &**node {
+                Node::Global(instance, _) => Some(instance.package.0),
+                _ => None,
+            });
+            let before = self.rewrites.total();
+            let result = self.interact(a, b);
+            let rewrites = self.rewrites.total() - before;
+            if let Some(package) = package
+                && rewrites > 0
+            {
+                *self.rewrites.by_package.entry(package).or_default() += rewrites;
+            }
+            if result.is_some() {
+                return result;
             }
         }
         None
@@ -851,7 +883,11 @@ impl Runtime {
             sym!(NodeRef::Linear(Linear::ShareHole(hole)), other) => {
                 self.fill_hole(hole, other.into_node())
             }
-            sym!(NodeRef::Linear(Linear::Request(request)), other) => {
+            // An external function isn't a value yet, so it's called with the
+            // request instead, and answers it with the value it produces.
+            sym!(NodeRef::Linear(Linear::Request(request)), other)
+                if other.as_external_fn().is_none() && other.as_external_arc().is_none() =>
+            {
                 let node = other.into_node();
                 let value = self.destruct(node).expect("Request expects a value");
                 request.send(value).unwrap();
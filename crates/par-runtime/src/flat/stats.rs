@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
+use crate::registry;
+
 #[derive(Default, Clone)]
 pub struct Rewrites {
     pub r#continue: u64,
@@ -12,10 +18,18 @@ pub struct Rewrites {
     pub share_sync: u64,
     pub share_async: u64,
     pub net_duration: Duration,
+    /// Rewrites by the index of the package they happened in, when
+    /// [profiling](crate::profile). A rewrite happens in the package of the
+    /// first of its two nodes that came from one.
+    pub by_package: HashMap<usize, u64>,
+    /// The most nodes that were waiting to interact at once.
+    pub peak_nodes: u64,
+    /// Calls to external definitions, by the address of the function called.
+    pub external_calls: HashMap<usize, u64>,
 }
 
 impl Rewrites {
-    fn total(&self) -> u64 {
+    pub fn total(&self) -> u64 {
         self.r#continue
             + self.receive
             + self.r#match
@@ -26,6 +40,33 @@ impl Rewrites {
             + self.share_sync
             + self.share_async
     }
+    pub(crate) fn count_external_call(&mut self, address: usize) {
+        *self.external_calls.entry(address).or_default() += 1;
+    }
+
+    /// What the run cost, once it's done, `wall_time` after it started.
+    pub fn summary(&self, wall_time: Duration) -> RunSummary {
+        let mut external_calls = HashMap::<String, u64>::new();
+        for (address, calls) in &self.external_calls {
+            let name = match registry::external_name(*address) {
+                Some(name) => name.to_string(),
+                None => String::from("replaced"),
+            };
+            *external_calls.entry(name).or_default() += calls;
+        }
+        let mut external_calls = external_calls.into_iter().collect::<Vec<_>>();
+        external_calls.sort_by(|(name1, calls1), (name2, calls2)| {
+            calls2.cmp(calls1).then_with(|| name1.cmp(name2))
+        });
+        RunSummary {
+            rewrites: self.total(),
+            peak_nodes: self.peak_nodes,
+            external_calls,
+            wall_time,
+            busy_time: self.net_duration,
+        }
+    }
+
     pub fn show(&self, elapsed: Duration) -> String {
         let _ = elapsed;
         let per_second = if self.net_duration.is_zero() {
@@ -65,3 +106,124 @@ impl Rewrites {
         )
     }
 }
+
+/// The cost of a finished run, reported at its end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunSummary {
+    pub rewrites: u64,
+    pub peak_nodes: u64,
+    /// Calls to each builtin, most called first. The ones replaced by
+    /// [overrides](crate::registry::Overrides) are counted together.
+    pub external_calls: Vec<(String, u64)>,
+    pub wall_time: Duration,
+    /// How long the reducer was busy rewriting, out of the wall time.
+    pub busy_time: Duration,
+}
+
+impl RunSummary {
+    pub fn total_external_calls(&self) -> u64 {
+        self.external_calls.iter().map(|(_, calls)| calls).sum()
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rewrites={} peak_nodes={} external_calls={}",
+            self.rewrites,
+            self.peak_nodes,
+            self.total_external_calls(),
+        )?;
+        if !self.external_calls.is_empty() {
+            let by_builtin = self
+                .external_calls
+                .iter()
+                .map(|(name, calls)| format!("{name}:{calls}"))
+                .collect::<Vec<_>>();
+            write!(f, " ({})", by_builtin.join(" "))?;
+        }
+        write!(
+            f,
+            " wall_ms={} busy_ms={}",
+            self.wall_time.as_millis(),
+            self.busy_time.as_millis(),
+        )
+    }
+}
+
+/// A live view of the statistics of a running reducer, for reporting progress
+/// while it's still busy. The reducer publishes its counts every so often, so
+/// they lag a little behind.
+#[derive(Default, Clone)]
+pub struct Progress(Arc<ProgressCounters>);
+
+#[derive(Default)]
+struct ProgressCounters {
+    rewrites: AtomicU64,
+    net_nanos: AtomicU64,
+}
+
+impl Progress {
+    pub(crate) fn publish(&self, rewrites: &Rewrites) {
+        self.0.rewrites.store(rewrites.total(), Ordering::Relaxed);
+        self.0.net_nanos.store(
+            rewrites
+                .net_duration
+                .as_nanos()
+                .try_into()
+                .unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn rewrites(&self) -> u64 {
+        self.0.rewrites.load(Ordering::Relaxed)
+    }
+
+    pub fn net_duration(&self) -> Duration {
+        Duration::from_nanos(self.0.net_nanos.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_unregistered_externals_as_replaced_and_lists_busiest_first() {
+        let mut rewrites = Rewrites {
+            ext_call: 5,
+            peak_nodes: 8,
+            net_duration: Duration::from_millis(3),
+            ..Rewrites::default()
+        };
+        rewrites.count_external_call(1);
+        rewrites.count_external_call(2);
+        rewrites.count_external_call(2);
+        let summary = rewrites.summary(Duration::from_millis(12));
+
+        assert_eq!(summary.rewrites, 5);
+        assert_eq!(summary.external_calls, [(String::from("replaced"), 3)]);
+        assert_eq!(
+            summary.to_string(),
+            "rewrites=5 peak_nodes=8 external_calls=3 (replaced:3) wall_ms=12 busy_ms=3"
+        );
+    }
+
+    #[test]
+    fn progress_is_seen_by_every_copy() {
+        let progress = Progress::default();
+        let seen = progress.clone();
+        assert_eq!(seen.rewrites(), 0);
+
+        progress.publish(&Rewrites {
+            receive: 3,
+            r#match: 4,
+            net_duration: Duration::from_millis(7),
+            ..Rewrites::default()
+        });
+        assert_eq!(seen.rewrites(), 7);
+        assert_eq!(seen.net_duration(), Duration::from_millis(7));
+    }
+}
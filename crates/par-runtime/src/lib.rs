@@ -1,4 +1,9 @@
+pub mod assets;
+pub mod config;
+pub mod curry;
 pub mod data;
+pub mod effects;
+pub mod environment;
 mod executor;
 pub mod fan_behavior;
 pub mod flat;
@@ -8,6 +13,8 @@ pub mod poll;
 pub mod primitive;
 pub mod readback;
 pub mod registry;
+pub mod setup;
 pub mod spawn;
+pub mod versioned;
 
-pub use executor::start_and_instantiate;
+pub use executor::{start_and_instantiate, start_and_instantiate_with_progress};
@@ -1,13 +1,17 @@
+use crate::assets::Assets;
+use crate::config::Config;
 use crate::flat::arena::{Arena, Index};
 use crate::flat::runtime::{
     ExternalFn, Global, GlobalCont, GlobalValue, Package, PackageBody, PackagePtr,
 };
 use crate::pkgid::PackageId;
-use crate::registry::{DefinitionRef, PackageRef, get_external_fn};
+use crate::registry::{DefinitionRef, Overrides, PackageRef, get_external_fn};
+use crate::versioned::{self, Header, Migration};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Display};
 use std::hash::Hash;
+use std::io::{self, Read, Write};
 use std::sync::{Arc, OnceLock};
 
 pub type Linked = ExternalFn;
@@ -71,11 +75,20 @@ impl Display for LinkError {
 impl std::error::Error for LinkError {}
 
 pub fn link_arena(arena: &Arena<Unlinked>) -> Result<Arena<Linked>, LinkError> {
+    link_arena_with(arena, &Overrides::new())
+}
+
+/// Links an arena, using the replacements in `overrides` in place of the
+/// registered external definitions they replace.
+pub fn link_arena_with(
+    arena: &Arena<Unlinked>,
+    overrides: &Overrides,
+) -> Result<Arena<Linked>, LinkError> {
     Ok(Arena {
         nodes: arena
             .nodes
             .iter()
-            .map(link_global)
+            .map(|node| link_global(node, overrides))
             .collect::<Result<_, _>>()?,
         strings: arena.strings.clone(),
         string_to_location: arena
@@ -123,7 +136,10 @@ fn link_package_body(body: &PackageBody<Unlinked>) -> Result<PackageBody<Linked>
     })
 }
 
-fn link_global(node: &Global<Unlinked>) -> Result<Global<Linked>, LinkError> {
+fn link_global(
+    node: &Global<Unlinked>,
+    overrides: &Overrides,
+) -> Result<Global<Linked>, LinkError> {
     Ok(match node {
         Global::Variable(id) => Global::Variable(*id),
         Global::Package(package_ptr, global_ptr, fab_behavior) => Global::Package(
@@ -132,21 +148,27 @@ fn link_global(node: &Global<Unlinked>) -> Result<Global<Linked>, LinkError> {
             fab_behavior.clone(),
         ),
         Global::Destruct(cont) => Global::Destruct(link_global_cont(cont)?),
-        Global::Value(value) => Global::Value(link_global_value(value)?),
+        Global::Value(value) => Global::Value(link_global_value(value, overrides)?),
         Global::Fanout(fanout) => Global::Fanout(Index(fanout.0.clone())),
     })
 }
 
-fn link_global_value(p0: &GlobalValue<Unlinked>) -> Result<GlobalValue<Linked>, LinkError> {
+fn link_global_value(
+    p0: &GlobalValue<Unlinked>,
+    overrides: &Overrides,
+) -> Result<GlobalValue<Linked>, LinkError> {
     Ok(match p0 {
         GlobalValue::Break => GlobalValue::Break,
         GlobalValue::Pair(a, b) => GlobalValue::Pair(Index(a.0.clone()), Index(b.0.clone())),
         GlobalValue::Either(s, v) => GlobalValue::Either(Index(s.0.clone()), Index(v.0.clone())),
-        GlobalValue::ExternalFn(unlinked) => {
-            GlobalValue::ExternalFn(get_external_fn(unlinked).ok_or_else(|| LinkError {
-                missing: unlinked.clone(),
-            })?)
-        }
+        GlobalValue::ExternalFn(unlinked) => match overrides.get(unlinked) {
+            Some(replacement) => GlobalValue::ExternalArc(replacement.clone()),
+            None => {
+                GlobalValue::ExternalFn(get_external_fn(unlinked).ok_or_else(|| LinkError {
+                    missing: unlinked.clone(),
+                })?)
+            }
+        },
         GlobalValue::ExternalArc(e) => GlobalValue::ExternalArc(e.clone()),
         GlobalValue::Primitive(p) => GlobalValue::Primitive(p.clone()),
     })
@@ -167,7 +189,12 @@ pub fn link_package_ptr(package: &PackagePtr<Unlinked>) -> PackagePtr<Linked> {
 #[derive(Serialize, Deserialize)]
 pub struct Artifact<Ext: Clone> {
     pub arena: Arc<Arena<Ext>>,
-    pub definition_to_package: HashMap<String, PackagePtr<Ext>>,
+    /// Sorted by name, so compiling the same program gives the same artifact.
+    pub definition_to_package: BTreeMap<String, PackagePtr<Ext>>,
+    pub assets: Assets,
+    /// The default configuration values, overridden by those given when
+    /// running the artifact.
+    pub config: Config,
 }
 
 impl Artifact<Unlinked> {
@@ -179,6 +206,239 @@ impl Artifact<Unlinked> {
                 .iter()
                 .map(|(k, v)| (k.clone(), link_package_ptr(v)))
                 .collect(),
+            assets: self.assets.clone(),
+            config: self.config.clone(),
         })
     }
 }
+
+/// Starts every artifact file, so anything else is recognized before it's
+/// decoded.
+const ARTIFACT_MAGIC: &[u8; 4] = b"PVM\0";
+
+/// The layout of the artifact after the header. Bump it whenever a change to
+/// [`Artifact`] or to what it contains changes how it's serialized, and add a
+/// migration from the previous one to [`ARTIFACT_MIGRATIONS`].
+pub const ARTIFACT_FORMAT: u32 = 2;
+
+/// The migration at index `i` upgrades format `i + 1` to `i + 2`, so an
+/// artifact of any earlier format is brought up to [`ARTIFACT_FORMAT`] by
+/// running the ones after it in turn.
+const ARTIFACT_MIGRATIONS: &[Migration] = &[sort_definitions];
+
+/// An artifact of format 1, which kept its definitions in no particular
+/// order.
+#[derive(Deserialize)]
+struct ArtifactV1 {
+    arena: Arc<Arena<Unlinked>>,
+    definition_to_package: HashMap<String, PackagePtr<Unlinked>>,
+    assets: Assets,
+    config: Config,
+}
+
+/// Upgrades format 1 to 2, sorting the definitions by name.
+fn sort_definitions(body: Vec<u8>) -> Result<Vec<u8>, String> {
+    let artifact: ArtifactV1 = bincode::deserialize(&body).map_err(|error| error.to_string())?;
+    bincode::serialize(&Artifact {
+        arena: artifact.arena,
+        definition_to_package: artifact.definition_to_package.into_iter().collect(),
+        assets: artifact.assets,
+        config: artifact.config,
+    })
+    .map_err(|error| error.to_string())
+}
+
+/// Why an artifact file couldn't be loaded.
+#[derive(Debug)]
+pub enum ArtifactError {
+    Io(io::Error),
+    /// The file doesn't start with a well-formed artifact header. Files
+    /// compiled before artifacts had one don't either.
+    NotAnArtifact,
+    /// The file was compiled by a version of Par that this one can't load.
+    Incompatible {
+        created_by: String,
+        format: u32,
+        reason: String,
+    },
+}
+
+impl Display for ArtifactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to read artifact: {error}"),
+            Self::NotAnArtifact => write!(
+                f,
+                "Not a Par artifact, or one compiled by a version from before artifacts were \
+                 versioned. Compile it again with `par compile`"
+            ),
+            Self::Incompatible {
+                created_by,
+                format,
+                reason,
+            } => write!(
+                f,
+                "Artifact created by version {created_by} (format {format}), incompatible because \
+                 {reason}. Compile it again with `par compile`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArtifactError {}
+
+impl From<io::Error> for ArtifactError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Artifact<Unlinked> {
+    /// Writes the artifact, after a header recording the format and the
+    /// version of Par that wrote it.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        versioned::write_header(&mut writer, ARTIFACT_MAGIC, ARTIFACT_FORMAT)?;
+        bincode::serialize_into(writer, self).map_err(io::Error::other)
+    }
+
+    /// Reads an artifact written by [`write_to`](Self::write_to), by this
+    /// version or an earlier one, migrating it to the current format.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, ArtifactError> {
+        let Some(Header { format, created_by }) =
+            versioned::read_header(&mut reader, ARTIFACT_MAGIC)?
+        else {
+            return Err(ArtifactError::NotAnArtifact);
+        };
+        let incompatible = |reason: String| ArtifactError::Incompatible {
+            created_by: created_by.clone(),
+            format,
+            reason,
+        };
+
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        let body = versioned::upgrade(body, format, ARTIFACT_FORMAT, ARTIFACT_MIGRATIONS)
+            .map_err(incompatible)?;
+        bincode::deserialize(&body)
+            .map_err(|error| incompatible(format!("its contents are malformed: {error}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versioned::MAX_CREATED_BY_LEN;
+
+    fn empty_artifact() -> Artifact<Unlinked> {
+        Artifact {
+            arena: Arc::new(Arena {
+                nodes: Vec::new(),
+                strings: String::new(),
+                string_to_location: Default::default(),
+                case_branches: Vec::new(),
+                packages: Vec::new(),
+                redexes: Vec::new(),
+            }),
+            definition_to_package: BTreeMap::new(),
+            assets: Assets::default(),
+            config: Config::default(),
+        }
+    }
+
+    fn header(format: u32, version: &str) -> Vec<u8> {
+        let mut bytes = ARTIFACT_MAGIC.to_vec();
+        bytes.extend(format.to_le_bytes());
+        bytes.extend((version.len() as u32).to_le_bytes());
+        bytes.extend(version.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn every_earlier_format_has_a_migration() {
+        assert_eq!(ARTIFACT_MIGRATIONS.len() as u32, ARTIFACT_FORMAT - 1);
+    }
+
+    #[test]
+    fn artifacts_round_trip() {
+        let mut bytes = Vec::new();
+        empty_artifact().write_to(&mut bytes).unwrap();
+        assert!(bytes.starts_with(&header(ARTIFACT_FORMAT, env!("CARGO_PKG_VERSION"))));
+        let artifact = Artifact::read_from(bytes.as_slice()).unwrap();
+        assert!(artifact.definition_to_package.is_empty());
+    }
+
+    #[test]
+    fn format_1_artifacts_are_upgraded() {
+        let definitions: HashMap<String, PackagePtr<Unlinked>> = ["Main.Main", "Lib.Helper"]
+            .into_iter()
+            .enumerate()
+            .map(|(package, name)| (name.to_owned(), Index(package)))
+            .collect();
+        let mut bytes = header(1, "0.1.0");
+        let artifact = empty_artifact();
+        let config = Config::from([(String::from("size"), String::from("3"))]);
+        bincode::serialize_into(
+            &mut bytes,
+            &(&artifact.arena, &definitions, &artifact.assets, &config),
+        )
+        .unwrap();
+        let artifact = Artifact::read_from(bytes.as_slice()).unwrap();
+        let definitions: Vec<_> = artifact
+            .definition_to_package
+            .iter()
+            .map(|(name, package)| (name.as_str(), package.0))
+            .collect();
+        assert_eq!(definitions, [("Lib.Helper", 1), ("Main.Main", 0)]);
+        assert_eq!(artifact.config, config);
+    }
+
+    #[test]
+    fn unversioned_files_are_not_artifacts() {
+        let mut bytes = Vec::new();
+        bincode::serialize_into(&mut bytes, &empty_artifact()).unwrap();
+        assert!(matches!(
+            Artifact::read_from(bytes.as_slice()),
+            Err(ArtifactError::NotAnArtifact)
+        ));
+    }
+
+    #[test]
+    fn newer_formats_name_their_version() {
+        let bytes = header(ARTIFACT_FORMAT + 1, "9.9.9");
+        let Err(error) = Artifact::read_from(bytes.as_slice()) else {
+            panic!("an artifact of a newer format was loaded");
+        };
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Artifact created by version 9.9.9 (format {0}), incompatible because of \
+                 unsupported format version {0}: this version only loads formats up to \
+                 {ARTIFACT_FORMAT}. Compile it again with `par compile`",
+                ARTIFACT_FORMAT + 1
+            )
+        );
+    }
+
+    #[test]
+    fn overlong_versions_are_not_artifacts() {
+        let mut bytes = header(ARTIFACT_FORMAT, &"9".repeat(MAX_CREATED_BY_LEN + 1));
+        empty_artifact().write_to(&mut bytes).unwrap();
+        assert!(matches!(
+            Artifact::read_from(bytes.as_slice()),
+            Err(ArtifactError::NotAnArtifact)
+        ));
+    }
+
+    #[test]
+    fn malformed_contents_are_reported() {
+        let mut bytes = header(ARTIFACT_FORMAT, "0.0.1");
+        bytes.extend([0xff; 3]);
+        assert!(matches!(
+            Artifact::read_from(bytes.as_slice()),
+            Err(ArtifactError::Incompatible {
+                format: ARTIFACT_FORMAT,
+                ..
+            })
+        ));
+    }
+}
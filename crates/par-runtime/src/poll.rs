@@ -21,8 +21,9 @@ async fn poll_token_server(mut handle: crate::readback::Handle) {
     use futures::stream::FuturesUnordered;
     use futures::stream::StreamExt as _;
 
-    let mut clients: FuturesUnordered<BoxFuture<'static, crate::flat::readback::Handle>> =
-        FuturesUnordered::new();
+    let mut clients: FuturesUnordered<
+        BoxFuture<'static, crate::flat::readback::Result<crate::flat::readback::Handle>>,
+    > = FuturesUnordered::new();
 
     loop {
         let op = handle.case().await;
@@ -32,16 +33,20 @@ async fn poll_token_server(mut handle: crate::readback::Handle) {
                 // implemented as a Pair(left = next_slot, right = result_slot)
                 let mut result_slot = handle.receive();
 
-                if clients.is_empty() {
+                // A client dropped without ever being provided has nothing to
+                // poll, so it's skipped.
+                let client = loop {
+                    match clients.next().await {
+                        Some(Ok(client)) => break Some(client),
+                        Some(Err(_)) => continue,
+                        None => break None,
+                    }
+                };
+                let Some(client) = client else {
                     result_slot.signal(ArcStr::from("#empty"));
                     result_slot.break_();
                     continue;
-                }
-
-                let client = clients
-                    .next()
-                    .await
-                    .expect("poll clients stream unexpectedly empty");
+                };
 
                 result_slot.signal(ArcStr::from("#client"));
                 result_slot.link(crate::readback::Handle::from(client));
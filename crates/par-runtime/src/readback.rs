@@ -1,4 +1,20 @@
+//! Reading values out of a running program, and providing values to it, from
+//! Rust.
+//!
+//! Each reader comes in two flavors. The plain one, such as [`Handle::string`],
+//! panics if the value isn't what was asked for, which can only happen if an
+//! external definition's declared type doesn't match what it does. The `try_`
+//! one, such as [`Handle::try_string`], returns an [`Error`] instead, for
+//! reading values whose shape isn't guaranteed by a type.
+
+#![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
+
+pub use crate::assets::Assets;
+pub use crate::config::Config;
 pub use crate::data::Data;
+pub use crate::effects::Effects;
+pub use crate::environment::Environment;
+pub use crate::flat::readback::Error;
 pub use crate::primitive::Number;
 
 use crate::primitive::{ParString, Primitive};
@@ -19,6 +35,22 @@ impl From<super::flat::readback::Handle> for Handle {
 }
 
 impl Handle {
+    pub fn environment(&self) -> &Environment {
+        self.handle.environment()
+    }
+
+    pub fn config(&self) -> &Config {
+        self.handle.config()
+    }
+
+    pub fn assets(&self) -> &Assets {
+        self.handle.assets()
+    }
+
+    pub fn effects(&self) -> &Effects {
+        self.handle.effects()
+    }
+
     pub fn erase(self) {
         self.handle.erase()
     }
@@ -32,6 +64,10 @@ impl Handle {
     }
 
     pub async fn case(&mut self) -> ArcStr {
+        expect("an either", self.try_case().await)
+    }
+
+    pub async fn try_case(&mut self) -> Result<ArcStr, Error> {
         self.handle.case().await
     }
 
@@ -48,11 +84,11 @@ impl Handle {
     }
 
     pub async fn receive_number(&mut self) -> Number {
-        self.handle.receive_number().await.unwrap()
+        expect("a number", self.handle.receive_number().await)
     }
 
     pub async fn receive_data(&mut self) -> Data {
-        self.handle.receive_data().await.unwrap()
+        expect("data", self.handle.receive_data().await)
     }
 
     pub fn duplicate(&mut self) -> Handle {
@@ -109,93 +145,105 @@ impl Handle {
     }
 
     pub async fn byte(self) -> u8 {
-        let primitive = self.handle.primitive().await.unwrap();
-        let Primitive::Bytes(value) = primitive else {
-            panic!(
-                "Unexpected primitive in Handle! Expected Bytes, got {:?}",
-                primitive
-            )
-        };
-        assert!(value.len() == 1);
-        value.as_ref()[0]
+        expect("a Byte", self.try_byte().await)
+    }
+
+    pub async fn try_byte(self) -> Result<u8, Error> {
+        match self.handle.primitive().await? {
+            Primitive::Bytes(value) if value.len() == 1 => Ok(value[0]),
+            primitive => Err(Error::InvalidPrimitive(primitive)),
+        }
     }
 
     pub async fn char(self) -> char {
-        let primitive = self.handle.primitive().await.unwrap();
-        let Primitive::String(value) = primitive else {
-            panic!(
-                "Unexpected primitive in Handle! Expected String, got {:?}",
-                primitive
-            )
-        };
-        let mut chars = value.as_str().chars();
-        let ch = chars.next().unwrap();
-        assert!(chars.next().is_none());
-        ch
+        expect("a Char", self.try_char().await)
+    }
+
+    pub async fn try_char(self) -> Result<char, Error> {
+        let primitive = self.handle.primitive().await?;
+        if let Primitive::String(value) = &primitive {
+            let mut chars = value.as_str().chars();
+            if let (Some(ch), None) = (chars.next(), chars.next()) {
+                return Ok(ch);
+            }
+        }
+        Err(Error::InvalidPrimitive(primitive))
     }
 
     pub async fn string(self) -> ParString {
-        let primitive = self.handle.primitive().await.unwrap();
-        let Primitive::String(value) = primitive else {
-            panic!(
-                "Unexpected primitive in Handle! Expected String, got {:?}",
-                primitive
-            )
-        };
-        value
+        expect("a String", self.try_string().await)
+    }
+
+    pub async fn try_string(self) -> Result<ParString, Error> {
+        match self.handle.primitive().await? {
+            Primitive::String(value) => Ok(value),
+            primitive => Err(Error::InvalidPrimitive(primitive)),
+        }
     }
 
     pub async fn bytes(self) -> Bytes {
-        match self.handle.primitive().await.unwrap() {
-            Primitive::String(e) => e.as_bytes(),
-            Primitive::Bytes(e) => e,
-            primitive => panic!(
-                "Unexpected primitive in Handle! Expected String or Bytes, got {:?}",
-                primitive
-            ),
+        expect("a String or Bytes", self.try_bytes().await)
+    }
+
+    pub async fn try_bytes(self) -> Result<Bytes, Error> {
+        match self.handle.primitive().await? {
+            Primitive::String(e) => Ok(e.as_bytes()),
+            Primitive::Bytes(e) => Ok(e),
+            primitive => Err(Error::InvalidPrimitive(primitive)),
         }
     }
 
     pub async fn int(self) -> BigInt {
-        match self.number().await {
-            Number::Zero => BigInt::ZERO,
-            Number::Int(value) => value,
-            number => panic!(
-                "Unexpected number in Handle! Expected Int, got {:?}",
-                number
-            ),
+        expect("an Int", self.try_int().await)
+    }
+
+    pub async fn try_int(self) -> Result<BigInt, Error> {
+        match self.try_number().await? {
+            Number::Zero => Ok(BigInt::ZERO),
+            Number::Int(value) => Ok(value),
+            number => Err(Error::InvalidPrimitive(Primitive::Number(number))),
         }
     }
 
     pub async fn float(self) -> f64 {
-        match self.number().await {
-            Number::Zero => 0.0,
-            Number::Float(value) => value,
-            number => panic!(
-                "Unexpected number in Handle! Expected Float, got {:?}",
-                number
-            ),
+        expect("a Float", self.try_float().await)
+    }
+
+    pub async fn try_float(self) -> Result<f64, Error> {
+        match self.try_number().await? {
+            Number::Zero => Ok(0.0),
+            Number::Float(value) => Ok(value),
+            number => Err(Error::InvalidPrimitive(Primitive::Number(number))),
         }
     }
 
     pub async fn nat(self) -> BigUint {
+        expect("a Nat", self.try_nat().await)
+    }
+
+    pub async fn try_nat(self) -> Result<BigUint, Error> {
         use num_bigint::Sign::*;
-        match self.number().await {
-            Number::Zero => BigUint::ZERO,
-            Number::Int(value) if matches!(value.sign(), NoSign | Plus) => value.into_parts().1,
-            number => panic!(
-                "Unexpected number in Handle! Expected Int, got {:?}",
-                number
-            ),
+        match self.try_number().await? {
+            Number::Zero => Ok(BigUint::ZERO),
+            Number::Int(value) if matches!(value.sign(), NoSign | Plus) => Ok(value.into_parts().1),
+            number => Err(Error::InvalidPrimitive(Primitive::Number(number))),
         }
     }
 
     pub async fn number(self) -> Number {
-        self.handle.number().await.unwrap()
+        expect("a number", self.try_number().await)
+    }
+
+    pub async fn try_number(self) -> Result<Number, Error> {
+        self.handle.number().await
     }
 
     pub async fn data(self) -> Data {
-        self.handle.data().await.unwrap()
+        expect("data", self.try_data().await)
+    }
+
+    pub async fn try_data(self) -> Result<Data, Error> {
+        self.handle.data().await
     }
 
     pub fn link(self, dual: Handle) {
@@ -218,3 +266,11 @@ impl Handle {
             .provide_external_closure(move |handle| f(Handle { handle }))
     }
 }
+
+/// The value read by an infallible reader, where a mismatch is a bug in the
+/// external definition doing the reading.
+#[allow(clippy::panic)]
+fn expect<T>(expected: &str, result: Result<T, Error>) -> T {
+    result
+        .unwrap_or_else(|error| panic!("Unexpected value in Handle! Expected {expected}: {error}"))
+}
@@ -1,8 +1,9 @@
-use crate::flat::runtime::ExternalFn;
+use crate::flat::runtime::{ExternalArc, ExternalFn};
 use crate::linker::{Linked, Unlinked};
 use crate::pkgid::BuiltinPackage;
+use crate::readback::Handle;
 use std::collections::HashMap;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PackageRef<'a> {
@@ -45,3 +46,55 @@ static REGISTRY: LazyLock<Registry> = LazyLock::new(|| {
 pub fn get_external_fn(path: &Unlinked) -> Option<ExternalFn> {
     REGISTRY.get(path).copied()
 }
+
+static NAMES: LazyLock<HashMap<usize, Unlinked>> = LazyLock::new(|| {
+    inventory::iter::<ExternalDef>
+        .into_iter()
+        .map(|&ExternalDef { path, f }| (f as usize, path.into()))
+        .collect()
+});
+
+/// The path of the registered external definition at `address`, the address
+/// of its function, for reporting which builtins a run called.
+pub fn external_name(address: usize) -> Option<&'static Unlinked> {
+    NAMES.get(&address)
+}
+
+/// Replacements for registered external definitions, taking their place when
+/// a program is linked with them.
+///
+/// Tests use these to swap a single builtin, such as `Console.Open`, for a stub
+/// that records what it's given, without compiling the program again. Only
+/// external definitions can be replaced; ones written in Par are compiled in.
+#[derive(Clone, Default)]
+pub struct Overrides {
+    replacements: HashMap<Unlinked, ExternalArc>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the external definition at `path` with `f`, which is called
+    /// with a handle to each use of it, the same as a registered one.
+    pub fn replace<Fun, Fut>(mut self, path: DefinitionRef<'_>, f: Fun) -> Self
+    where
+        Fun: 'static + Send + Sync + Fn(Handle) -> Fut,
+        Fut: 'static + Send + Future<Output = ()>,
+    {
+        self.replacements.insert(
+            path.into(),
+            ExternalArc(Arc::new(move |handle| Box::pin(f(handle)))),
+        );
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    pub(crate) fn get(&self, path: &Unlinked) -> Option<&ExternalArc> {
+        self.replacements.get(path)
+    }
+}
@@ -0,0 +1,126 @@
+//! What a run is started with, besides the definition it runs.
+//!
+//! A [`Setup`] puts together the separate things a run is given: the
+//! [`Environment`] it sees of the host, its [configuration](crate::config),
+//! its [assets](crate::assets), the [`Effects`] its builtins have outside,
+//! whether its rewrites are profiled, and the token that stops it. Each one is set on its own, and
+//! builtins reach each one on its own, through their
+//! [`Handle`](crate::readback::Handle).
+//!
+//! An [`Environment`] alone is a setup with nothing else given, so the
+//! functions starting runs take either.
+
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::assets::Assets;
+use crate::config::Config;
+use crate::effects::Effects;
+use crate::environment::Environment;
+
+#[derive(Clone, Default)]
+pub struct Setup {
+    environment: Environment,
+    config: Arc<Config>,
+    assets: Arc<Assets>,
+    effects: Effects,
+    profiling: bool,
+    cancel_token: CancellationToken,
+}
+
+impl From<Environment> for Setup {
+    fn from(environment: Environment) -> Self {
+        Self::new(environment)
+    }
+}
+
+impl Setup {
+    /// A run in `environment`, given nothing else.
+    pub fn new(environment: Environment) -> Self {
+        Self {
+            environment,
+            config: Arc::default(),
+            assets: Arc::default(),
+            effects: Effects::new(),
+            profiling: false,
+            cancel_token: CancellationToken::new(),
+        }
+    }
+
+    /// Makes `config` the configuration values the run reads.
+    pub fn with_config(mut self, config: Arc<Config>) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Makes `assets` the ones the run can read.
+    pub fn with_assets(mut self, assets: Arc<Assets>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Makes the run have its effects through `effects`, which may be
+    /// [replaying](Effects::replaying) an earlier run's.
+    pub fn with_effects(mut self, effects: Effects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    /// Makes the reducer attribute the run's rewrites to the packages whose
+    /// nodes took part in them, in
+    /// [`Rewrites::by_package`](crate::flat::stats::Rewrites::by_package).
+    /// This costs a lookup per rewrite, so it's off unless asked for.
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    /// Makes the run stop once `cancel_token` is cancelled. The reducer checks
+    /// it between rewrites, so even a run that never waits on a builtin stops.
+    pub fn with_cancel_token(mut self, cancel_token: CancellationToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    /// The same setup, in `environment` instead.
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// The same setup, for a run that starts over, in a
+    /// [renewed](Environment::renewed) environment and with no effects had.
+    pub fn renewed(&self) -> Self {
+        self.clone()
+            .with_environment(self.environment.renewed())
+            .with_effects(Effects::new())
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// The configuration values of the run, by key.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The assets the run can read, by name.
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    /// The effects the run had, and those it's replaying.
+    pub fn effects(&self) -> &Effects {
+        &self.effects
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profiling
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+}
@@ -0,0 +1,136 @@
+//! Headers of the files Par writes to be loaded by another run, naming their
+//! format and the version of Par that wrote them.
+//!
+//! A file of an earlier format is brought up to the current one by running
+//! the migrations after its format in turn, and one that can't be is refused
+//! with the reason, instead of being decoded into garbage.
+
+use std::io::{self, Read, Write};
+
+/// The longest version a header can name. A header claiming a longer one is
+/// corrupted.
+pub(crate) const MAX_CREATED_BY_LEN: usize = 256;
+
+/// Upgrades the body of one format to the next one.
+pub type Migration = fn(Vec<u8>) -> Result<Vec<u8>, String>;
+
+/// What a header says about the body after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub format: u32,
+    /// The version of Par that wrote the file.
+    pub created_by: String,
+}
+
+/// Writes a header starting with `magic`, for a body of `format`.
+pub fn write_header(mut writer: impl Write, magic: &[u8; 4], format: u32) -> io::Result<()> {
+    let version = env!("CARGO_PKG_VERSION").as_bytes();
+    if version.len() > MAX_CREATED_BY_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the version is longer than {MAX_CREATED_BY_LEN} bytes"),
+        ));
+    }
+    writer.write_all(magic)?;
+    writer.write_all(&format.to_le_bytes())?;
+    writer.write_all(&(version.len() as u32).to_le_bytes())?;
+    writer.write_all(version)
+}
+
+/// Reads a header written by [`write_header`] with `magic`, or `None` if the
+/// reader doesn't start with a well-formed one.
+pub fn read_header(mut reader: impl Read, magic: &[u8; 4]) -> io::Result<Option<Header>> {
+    let mut start = [0; 4];
+    match reader.read_exact(&mut start) {
+        Ok(()) if &start == magic => {}
+        Ok(()) => return Ok(None),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+    let format = read_u32(&mut reader)?;
+    let created_by_len = read_u32(&mut reader)? as usize;
+    if created_by_len > MAX_CREATED_BY_LEN {
+        return Ok(None);
+    }
+    let mut created_by = vec![0; created_by_len];
+    reader.read_exact(&mut created_by)?;
+    Ok(Some(Header {
+        format,
+        created_by: String::from_utf8_lossy(&created_by).into_owned(),
+    }))
+}
+
+/// Brings `body`, of `format`, up to `current`. The migration at index `i`
+/// of `migrations` upgrades format `i + 1` to `i + 2`. Gives back why the
+/// body can't be loaded if it can't be upgraded, including when `migrations`
+/// don't reach `current`.
+pub fn upgrade(
+    mut body: Vec<u8>,
+    format: u32,
+    current: u32,
+    migrations: &[Migration],
+) -> Result<Vec<u8>, String> {
+    if format == 0 || format > current {
+        return Err(format!(
+            "of unsupported format version {format}: this version only loads formats up to \
+             {current}"
+        ));
+    }
+    let reached = migrations.len() as u32 + 1;
+    if reached != current {
+        return Err(format!(
+            "of unsupported format version {format}: migrations only reach format {reached}, \
+             not {current}"
+        ));
+    }
+    for (from, migrate) in migrations.iter().enumerate().skip(format as usize - 1) {
+        body = migrate(body)
+            .map_err(|error| format!("upgrading it from format {} failed: {error}", from + 1))?;
+    }
+    Ok(body)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        |mut body| {
+            body.push(2);
+            Ok(body)
+        },
+        |mut body| {
+            body.push(3);
+            Ok(body)
+        },
+    ];
+
+    #[test]
+    fn earlier_formats_run_the_migrations_after_them() {
+        assert_eq!(upgrade(vec![1], 1, 3, MIGRATIONS), Ok(vec![1, 2, 3]));
+        assert_eq!(upgrade(vec![2], 2, 3, MIGRATIONS), Ok(vec![2, 3]));
+        assert_eq!(upgrade(vec![3], 3, 3, MIGRATIONS), Ok(vec![3]));
+    }
+
+    #[test]
+    fn future_formats_are_unsupported() {
+        assert_eq!(
+            upgrade(vec![], 4, 3, MIGRATIONS),
+            Err("of unsupported format version 4: this version only loads formats up to 3".into())
+        );
+    }
+
+    #[test]
+    fn formats_the_migrations_dont_reach_are_unsupported() {
+        assert_eq!(
+            upgrade(vec![1], 1, 4, MIGRATIONS),
+            Err("of unsupported format version 1: migrations only reach format 3, not 4".into())
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Seeds the `parse_tokens` corpus with the token streams of the examples.
+//!
+//! Run from `fuzz/` with `cargo run --bin seed_corpus`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples/src");
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus/parse_tokens");
+    fs::create_dir_all(&corpus).expect("failed to create the corpus directory");
+
+    let mut files = Vec::new();
+    collect_par_files(&examples, &mut files);
+    for file in &files {
+        let Ok(source) = fs::read_to_string(file) else {
+            eprintln!("skipping {}", file.display());
+            continue;
+        };
+        let name = file
+            .strip_prefix(&examples)
+            .unwrap_or(file)
+            .to_string_lossy()
+            .replace(['/', '\\'], "_");
+        let seed = par_core::fuzzing::encode_token_stream(&source);
+        fs::write(corpus.join(name), seed).expect("failed to write a seed");
+    }
+    println!("wrote {} seeds to {}", files.len(), corpus.display());
+}
+
+fn collect_par_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_par_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "par") {
+            files.push(path);
+        }
+    }
+}
@@ -13,3 +13,20 @@ impl Request for GetBuiltinFileContent {
 pub struct GetBuiltinFileContentParams {
     pub builtin_path: String,
 }
+
+/// The type at a position, with its names and fixpoints unfolded a number of
+/// times, for clients to step through what a protocol looks like.
+pub enum UnfoldType {}
+
+impl Request for UnfoldType {
+    type Params = UnfoldTypeParams;
+    type Result = Option<String>;
+    const METHOD: &'static str = "par-lang/unfoldType";
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnfoldTypeParams {
+    #[serde(flatten)]
+    pub text_document_position: lsp_types::TextDocumentPositionParams,
+    pub steps: usize,
+}
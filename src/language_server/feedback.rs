@@ -56,6 +56,17 @@ impl FeedbackBookKeeper {
 }
 
 pub fn diagnostic_for_error(err: &CompileError, fallback_uri: &Uri) -> (Uri, lsp::Diagnostic) {
+    let severity = match err {
+        CompileError::Type { error, .. } if error.error.is_warning() => {
+            lsp::DiagnosticSeverity::WARNING
+        }
+        _ => lsp::DiagnosticSeverity::ERROR,
+    };
+    let code = match err {
+        CompileError::Type { error, .. } => Some(error.error.code().to_owned()),
+        CompileError::Discovery(error) => error.to_report().code().map(|code| code.to_string()),
+        CompileError::Workspace(_) => None,
+    };
     let (span, message, _related_spans) = match err {
         CompileError::Type { error, sources } => {
             let (span, related_span) = error.error.spans();
@@ -78,8 +89,8 @@ pub fn diagnostic_for_error(err: &CompileError, fallback_uri: &Uri) -> (Uri, lsp
         uri_for_error(err).unwrap_or_else(|| fallback_uri.clone()),
         lsp::Diagnostic {
             range: span_to_lsp_range(&span),
-            severity: Some(lsp::DiagnosticSeverity::ERROR),
-            code: None,
+            severity: Some(severity),
+            code: code.map(lsp::NumberOrString::String),
             code_description: None,
             source: None,
             message,
@@ -1,3 +1,4 @@
+use super::ext::UnfoldTypeParams;
 use super::io::IO;
 use crate::language_server::data::ToLspPosition;
 use crate::package_utils::SourceLookup;
@@ -5,9 +6,8 @@ use crate::workspace_support::{
     ScopedTypeError, WorkspaceBuildError, checked_workspace_from_path,
     checked_workspace_from_single_file,
 };
-use indexmap::IndexMap;
 use lsp_types::{self as lsp, Uri};
-use par_core::frontend::language::GlobalName;
+use par_core::semantic::SymbolKind;
 use par_core::source::{FileName, Span};
 use par_core::workspace::{
     CheckedWorkspace, SourceOverrides, WorkspaceDiscoveryError, WorkspaceError,
@@ -26,12 +26,6 @@ pub enum CompileError {
     },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum SymbolKey<S> {
-    Type(GlobalName<S>),
-    Value(GlobalName<S>),
-}
-
 pub struct Instance {
     uri: Uri,
     file: FileName,
@@ -79,6 +73,20 @@ impl Instance {
         Some(hover)
     }
 
+    pub fn handle_unfold_type(&self, params: &UnfoldTypeParams) -> Option<String> {
+        tracing::debug!("Handling unfold type request for {} steps", params.steps);
+
+        let pos = params.text_document_position.position;
+        let checked = self.checked.as_ref()?;
+        let typ = checked.semantic_model().unfolded_type_at(
+            &self.file,
+            pos.line,
+            pos.character,
+            params.steps,
+        )?;
+        Some(checked.render_type_in_file(&self.file, &typ, 0))
+    }
+
     /* todo:
     look at C language servers, how they handle split declaration/definition
     look at Rust language servers, what "kind" they use for type aliases & traits
@@ -93,10 +101,10 @@ impl Instance {
         let Some(checked) = self.checked.as_ref() else {
             return None;
         };
-        let checked_module = checked.checked_module();
+        let model = checked.semantic_model();
         let same_file = |span: &Span| span.file() == Some(self.file.clone());
 
-        let mut symbols = IndexMap::new();
+        let mut symbols = Vec::new();
 
         /* kinds (maybe like this):
         CLASS: choice type
@@ -112,107 +120,47 @@ impl Instance {
         TYPE_PARAMETER: type alias
          */
 
-        for (name, (span, _, _)) in checked_module.type_defs.globals.as_ref() {
-            if !same_file(span) {
+        for symbol in model.symbols() {
+            // A definition in this file takes precedence over a declaration.
+            let Some(site) = (symbol.definition.iter())
+                .chain(&symbol.declaration)
+                .find(|site| same_file(&site.item))
+            else {
                 continue;
-            }
-            if let (Some((name_start, name_end)), Some((start, end))) =
-                (name.span.points(), span.points())
-            {
-                symbols.insert(
-                    SymbolKey::Type(name.clone()),
-                    lsp::DocumentSymbol {
-                        name: checked.render_global_in_file(&self.file, name),
-                        detail: None,
-                        kind: lsp::SymbolKind::INTERFACE,
-                        tags: None,
-                        deprecated: None, // must be specified
-                        range: lsp::Range {
-                            start: start.to_lsp_position(),
-                            end: end.to_lsp_position(),
-                        },
-                        selection_range: lsp::Range {
-                            start: name_start.to_lsp_position(),
-                            end: name_end.to_lsp_position(),
-                        },
-                        children: None,
-                    },
-                );
-            }
-        }
-
-        for (name, declaration) in &checked_module.declarations {
-            if !same_file(&declaration.span) {
+            };
+            let (Some((name_start, name_end)), Some((start, end))) =
+                (site.name.points(), site.item.points())
+            else {
                 continue;
-            }
-            let detail = checked.render_type_in_file(&self.file, &declaration.typ, 0);
-
-            if let (Some((name_start, name_end)), Some((start, end))) =
-                (name.span.points(), declaration.span.points())
-            {
-                symbols.insert(
-                    SymbolKey::Value(name.clone()),
-                    lsp::DocumentSymbol {
-                        name: checked.render_global_in_file(&self.file, name),
-                        detail: Some(detail),
-                        kind: lsp::SymbolKind::FUNCTION,
-                        tags: None,
-                        deprecated: None, // must be specified
-                        range: lsp::Range {
-                            start: start.to_lsp_position(),
-                            end: end.to_lsp_position(),
-                        },
-                        selection_range: lsp::Range {
-                            start: name_start.to_lsp_position(),
-                            end: name_end.to_lsp_position(),
-                        },
-                        children: None,
-                    },
-                );
-            }
-        }
-
-        for (name, (definition, typ)) in &checked_module.definitions {
-            if !same_file(&definition.span) {
-                continue;
-            }
-            if let (Some((name_start, name_end)), Some((start, end))) =
-                (name.span.points(), definition.span.points())
-            {
-                let range = lsp::Range {
+            };
+            let (kind, detail) = match symbol.kind() {
+                SymbolKind::Type => (lsp::SymbolKind::INTERFACE, None),
+                SymbolKind::Declaration => (
+                    lsp::SymbolKind::FUNCTION,
+                    Some(checked.render_type_in_file(&self.file, &symbol.typ, 0)),
+                ),
+            };
+            symbols.push(lsp::DocumentSymbol {
+                name: checked.render_global_in_file(&self.file, symbol.name()),
+                detail,
+                kind,
+                tags: None,
+                deprecated: None, // must be specified
+                range: lsp::Range {
                     start: start.to_lsp_position(),
                     end: end.to_lsp_position(),
-                };
-                let selection_range = lsp::Range {
+                },
+                selection_range: lsp::Range {
                     start: name_start.to_lsp_position(),
                     end: name_end.to_lsp_position(),
-                };
-                symbols
-                    .entry(SymbolKey::Value(name.clone()))
-                    .and_modify(|symbol| {
-                        symbol.range = range;
-                        symbol.selection_range = selection_range;
-                    })
-                    .or_insert({
-                        let detail = checked.render_type_in_file(&self.file, &typ, 0);
-
-                        lsp::DocumentSymbol {
-                            name: checked.render_global_in_file(&self.file, name),
-                            detail: Some(detail),
-                            kind: lsp::SymbolKind::FUNCTION,
-                            tags: None,
-                            deprecated: None, // must be specified
-                            range,
-                            selection_range,
-                            children: None,
-                        }
-                    });
-            }
+                },
+                children: None,
+            });
         }
 
         // todo: fix the bug that causes this
         // the same bug also causes run labels to appear on usages of the name
-        for symbol in symbols.values() {
+        for symbol in &symbols {
             let range = symbol.range;
             let selection_range = symbol.selection_range;
             let inside = range.start.character <= selection_range.start.character
@@ -227,9 +175,7 @@ impl Instance {
             }
         }
 
-        Some(lsp::DocumentSymbolResponse::Nested(
-            symbols.into_values().collect(),
-        ))
+        Some(lsp::DocumentSymbolResponse::Nested(symbols))
     }
     pub fn handle_goto_declaration(
         &self,
@@ -290,6 +236,27 @@ impl Instance {
         }))
     }
 
+    pub fn handle_references(&self, params: &lsp::ReferenceParams) -> Option<Vec<lsp::Location>> {
+        tracing::debug!("Handling references request with params: {:?}", params);
+        let Some(checked) = self.checked.as_ref() else {
+            return None;
+        };
+
+        let pos = params.text_document_position.position;
+
+        let model = checked.semantic_model();
+        let symbol = model.symbol_at(&self.file, pos.line, pos.character)?;
+
+        let mut spans = Vec::new();
+        if params.context.include_declaration {
+            spans.extend(symbol.sites().map(|site| &site.name));
+        }
+        spans.extend(model.references_of(&symbol.id));
+        spans.dedup();
+
+        Some(spans.into_iter().filter_map(span_to_location).collect())
+    }
+
     /// Last compile/type errors, if any
     pub fn last_errors(&self) -> &[CompileError] {
         &self.errors
@@ -405,6 +372,7 @@ fn build_compile_result(
     let errors = build
         .type_errors
         .into_iter()
+        .chain(build.warnings)
         .map(|error| CompileError::Type {
             error,
             sources: sources.clone(),
@@ -434,6 +402,17 @@ fn file_name_for_uri(uri: &Uri) -> FileName {
         .unwrap_or_else(|| uri.as_str().into())
 }
 
+fn span_to_location(span: &Span) -> Option<lsp::Location> {
+    let (start, end) = span.points()?;
+    Some(lsp::Location {
+        uri: file_name_to_uri(&span.file()?)?,
+        range: lsp::Range {
+            start: start.to_lsp_position(),
+            end: end.to_lsp_position(),
+        },
+    })
+}
+
 fn file_name_to_uri(file: &FileName) -> Option<Uri> {
     let path = PathBuf::from(file.0.as_str());
     if path.is_absolute() {
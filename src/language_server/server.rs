@@ -1,10 +1,12 @@
 use super::io::IO;
-use crate::language_server::ext::{GetBuiltinFileContent, GetBuiltinFileContentParams};
+use crate::language_server::ext::{GetBuiltinFileContent, GetBuiltinFileContentParams, UnfoldType};
 use crate::language_server::feedback::{FeedbackBookKeeper, diagnostic_for_error};
 use crate::language_server::instance::Instance;
 use lsp_server::{Connection, ErrorCode};
 use lsp_types::notification::DidSaveTextDocument;
-use lsp_types::request::{DocumentSymbolRequest, ExecuteCommand, GotoDeclaration, GotoDefinition};
+use lsp_types::request::{
+    DocumentSymbolRequest, ExecuteCommand, GotoDeclaration, GotoDefinition, References,
+};
 use lsp_types::{self as lsp, InitializeParams, Uri};
 use par_builtin::get_builtin_source;
 use std::collections::HashMap;
@@ -86,6 +88,14 @@ impl<'c> LanguageServer<'c> {
                     |instance| instance.handle_goto_definition(&params),
                 )
             }
+            References::METHOD => {
+                let params = extract_request::<References>(request);
+                self.handle_request_instance(
+                    request_id,
+                    &params.text_document_position.text_document.uri,
+                    |instance| instance.handle_references(&params),
+                )
+            }
             ExecuteCommand::METHOD => {
                 let params = extract_request::<ExecuteCommand>(request);
                 match params.command.as_str() {
@@ -110,6 +120,14 @@ impl<'c> LanguageServer<'c> {
                     }
                 }
             }
+            UnfoldType::METHOD => {
+                let params = extract_request::<UnfoldType>(request);
+                self.handle_request_instance(
+                    request_id,
+                    &params.text_document_position.text_document.uri,
+                    |instance| instance.handle_unfold_type(&params),
+                )
+            }
             GetBuiltinFileContent::METHOD => {
                 let GetBuiltinFileContentParams { builtin_path } =
                     extract_request::<GetBuiltinFileContent>(request);
@@ -258,6 +276,7 @@ fn initialize_lsp(connection: &Connection) -> InitializeParams {
         document_symbol_provider: Some(lsp::OneOf::Left(true)),
         declaration_provider: Some(lsp::DeclarationCapability::Simple(true)),
         definition_provider: Some(lsp::OneOf::Left(true)),
+        references_provider: Some(lsp::OneOf::Left(true)),
         execute_command_provider: Some(lsp::ExecuteCommandOptions {
             commands: vec!["run".to_owned()],
             work_done_progress_options: Default::default(),
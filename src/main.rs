@@ -1,27 +1,44 @@
 use crate::package_manager::AddedDependencyStatus;
 use crate::package_utils::{
-    SourceLookup, find_local_module, parse_target, root_module_slash_path, source_for_fallback,
+    SourceLookup, find_local_module, load_package_assets, load_package_config, parse_target,
+    root_module_slash_path, source_for_fallback,
 };
 #[cfg(feature = "playground")]
-use crate::playground::Playground;
+use crate::playground::{Playground, RendererRegistry};
+use crate::summary::BuildSummary;
+#[cfg(not(target_family = "wasm"))]
+use crate::workspace_support::rechecked_workspace_from_path;
 use crate::workspace_support::{
     CheckedWorkspaceBuild, ScopedTypeError, WorkspaceBuildError, checked_workspace_from_path,
+    name_errors_from_path, scoped_warnings,
 };
-use clap::{Command, arg, command, value_parser};
+use clap::{ArgMatches, Command, arg, command, value_parser};
 use colored::Colorize;
 #[cfg(feature = "playground")]
 use eframe::egui;
 use par_core::{
-    frontend::{Type, set_miette_hook},
-    runtime::RuntimeCompilerError,
-    workspace::{CheckedWorkspace, ModulePath, WorkspaceDiscoveryError, WorkspaceError},
+    frontend::{
+        DefinitionBody, GRAMMAR, TotalityVerdict, Type, codes::explain_code, decompile_runtime,
+        language::TypeConstraint, lexer::Edition, migrate::migrate_source,
+        set_full_types_in_reports, set_miette_hook,
+    },
+    pipeline::Stage,
+    runtime::{Profile, RuntimeCompilerError, TypedHandle, unsupported_readback_with},
+    workspace::{
+        CheckedWorkspace, ModulePath, PackageGraph, PackageLayout, SourceOverrides,
+        WorkspaceDiscoveryError, WorkspaceError, load_package_source_files,
+        render_compact_type_in_scope,
+    },
 };
 use par_doc::DocOptions;
 use tokio::time::Instant;
 #[cfg(not(target_family = "wasm"))]
 use url::Url;
 
+use par_runtime::environment::Environment;
+use par_runtime::flat::stats::Progress;
 use par_runtime::linker::{Artifact, Linked, Unlinked};
+use par_runtime::setup::Setup;
 use par_runtime::spawn::TokioSpawn;
 use std::fmt::Display;
 use std::fs::{self, File};
@@ -31,6 +48,10 @@ use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::sync::Arc;
+#[cfg(not(target_family = "wasm"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
 #[cfg(not(target_arch = "wasm32"))]
 use mimalloc::MiMalloc;
@@ -45,11 +66,15 @@ mod package_manager;
 mod package_utils;
 #[cfg(feature = "playground")]
 mod playground;
+mod reduce;
+mod summary;
 mod test;
 mod test_runner;
 mod tokio_factory;
 #[cfg(target_family = "wasm")]
 mod wasm_spawn;
+#[cfg(not(target_family = "wasm"))]
+mod watch;
 mod workspace_support;
 
 const MAX_INTERACTIONS_DEFAULT: u32 = 10_000;
@@ -166,9 +191,20 @@ impl Display for NewPackageError {
     }
 }
 
-fn build_checked_package(package_path: &PathBuf) -> Result<CheckedWorkspaceBuild, BuildError> {
+fn build_checked_package(package_path: &Path) -> Result<CheckedWorkspaceBuild, BuildError> {
+    build_checked_package_with_overrides(package_path, None)
+}
+
+fn build_checked_package_with_overrides(
+    package_path: &Path,
+    overrides: Option<&SourceOverrides>,
+) -> Result<CheckedWorkspaceBuild, BuildError> {
     let build =
-        checked_workspace_from_path(package_path, None).map_err(map_workspace_build_error)?;
+        checked_workspace_from_path(package_path, overrides).map_err(map_workspace_build_error)?;
+    without_type_errors(build)
+}
+
+fn without_type_errors(build: CheckedWorkspaceBuild) -> Result<CheckedWorkspaceBuild, BuildError> {
     if !build.type_errors.is_empty() {
         return Err(BuildError::Type {
             errors: build.type_errors,
@@ -178,20 +214,31 @@ fn build_checked_package(package_path: &PathBuf) -> Result<CheckedWorkspaceBuild
     Ok(build)
 }
 
+/// A package built but not linked yet, with the modules of its own, where its
+/// sources came from, and how long each stage of building it took.
+type UnlinkedPackage = (
+    CheckedWorkspace,
+    par_core::runtime::Compiled<Unlinked>,
+    Vec<ModulePath>,
+    SourceLookup,
+    Vec<(Stage, Duration)>,
+);
+
+/// Builds a package, also returning how long each stage took.
 fn build_unlinked_package(
-    package_path: &PathBuf,
+    package_path: &Path,
+    max_interactions: u32,
+) -> Result<UnlinkedPackage, BuildError> {
+    compile_unlinked_package(build_checked_package(package_path)?, max_interactions)
+}
+
+fn compile_unlinked_package(
+    build: CheckedWorkspaceBuild,
     max_interactions: u32,
-) -> Result<
-    (
-        CheckedWorkspace,
-        par_core::runtime::Compiled<Unlinked>,
-        Vec<ModulePath>,
-        SourceLookup,
-    ),
-    BuildError,
-> {
-    let build = build_checked_package(package_path)?;
+) -> Result<UnlinkedPackage, BuildError> {
     let sources = build.sources.clone();
+    let mut timings = build.timings.clone();
+    let start = Instant::now();
     let (checked, rt_compiled, sources) =
         build
             .compile_unlinked(max_interactions)
@@ -199,23 +246,58 @@ fn build_unlinked_package(
                 error,
                 sources: sources.clone(),
             })?;
+    timings.push((Stage::Compile, start.elapsed()));
     let local_modules = checked.workspace().root_modules();
-    Ok((checked, rt_compiled, local_modules, sources))
+    Ok((checked, rt_compiled, local_modules, sources, timings))
 }
 
+/// A package built and linked, with the modules of its own, and how long
+/// each stage of building it took.
+type RuntimePackage = (
+    CheckedWorkspace,
+    par_core::runtime::Compiled<Linked>,
+    Vec<ModulePath>,
+    Vec<(Stage, Duration)>,
+);
+
+/// Builds and links a package, also returning how long each stage took.
 fn build_runtime_package(
-    package_path: &PathBuf,
+    package_path: &Path,
+    max_interactions: u32,
+) -> Result<RuntimePackage, BuildError> {
+    compile_runtime_package(build_checked_package(package_path)?, max_interactions)
+}
+
+/// Builds and links a package again after it changed, like
+/// [`build_runtime_package`], without type checking again the packages that
+/// didn't change since `previous` was checked. Returns `Ok(None)` if
+/// `cancelled` is raised before it's done.
+#[cfg(not(target_family = "wasm"))]
+fn rebuild_runtime_package(
+    package_path: &Path,
+    max_interactions: u32,
+    previous: Option<&CheckedWorkspace>,
+    cancelled: &AtomicBool,
+) -> Result<Option<RuntimePackage>, BuildError> {
+    let is_cancelled = || cancelled.load(Ordering::Relaxed);
+    let Some(build) = rechecked_workspace_from_path(package_path, previous, is_cancelled)
+        .map_err(map_workspace_build_error)?
+    else {
+        return Ok(None);
+    };
+    let build = without_type_errors(build)?;
+    if is_cancelled() {
+        return Ok(None);
+    }
+    compile_runtime_package(build, max_interactions).map(Some)
+}
+
+fn compile_runtime_package(
+    build: CheckedWorkspaceBuild,
     max_interactions: u32,
-) -> Result<
-    (
-        CheckedWorkspace,
-        par_core::runtime::Compiled<Linked>,
-        Vec<ModulePath>,
-    ),
-    BuildError,
-> {
-    let (checked, rt_compiled, local_modules, sources) =
-        build_unlinked_package(package_path, max_interactions)?;
+) -> Result<RuntimePackage, BuildError> {
+    let (checked, rt_compiled, local_modules, sources, timings) =
+        compile_unlinked_package(build, max_interactions)?;
     Ok((
         checked,
         rt_compiled
@@ -225,6 +307,7 @@ fn build_runtime_package(
                 sources: sources.clone(),
             })?,
         local_modules,
+        timings,
     ))
 }
 
@@ -263,6 +346,7 @@ fn is_reserved_package_name(name: &str) -> bool {
             | "or"
             | "not"
             | "loop"
+            | "macro"
             | "poll"
             | "repoll"
             | "submit"
@@ -274,6 +358,7 @@ fn is_reserved_package_name(name: &str) -> bool {
             | "type"
             | "unfounded"
             | "external"
+            | "derive"
     )
 }
 
@@ -356,6 +441,10 @@ fn create_new_package_in(base_dir: &Path, package_name: &str) -> Result<PathBuf,
 fn main() -> ExitCode {
     let matches = command!()
         .subcommand_required(true)
+        .arg(
+            arg!(--full_types "Show types in error messages in full, instead of truncated")
+                .global(true),
+        )
         .subcommand(
             Command::new("new")
                 .about("Create a new Par package")
@@ -389,6 +478,9 @@ fn main() -> ExitCode {
             Command::new("run")
                 .about("Run a definition in a Par package")
                 .arg(arg!(--stats "Print statistics after running the definition"))
+                .arg(arg!(--profile "Print which definitions the run spent its rewrites in"))
+                .arg(arg!(--value "Run a definition of a data type to completion and print only its final value"))
+                .arg(arg!(--watch "Run the definition again whenever a file of the package changes"))
                 .arg(
                     arg!(--package <PACKAGE> "Path to package directory (or any file/directory inside it)")
                         .value_parser(value_parser!(PathBuf))
@@ -396,6 +488,28 @@ fn main() -> ExitCode {
                 )
                 .arg(arg!([target] "Target to run: `path/to/Module` or `path/to/Module.Def`"))
                 .arg(arg!(-f --flag <FLAG> ... "Set a flag"))
+                .arg(arg!(--config <ENTRY> ... "Set a configuration value as `key=value`, overriding the manifest")
+                    .value_parser(par_runtime::config::parse_entry))
+                .arg(arg!(--max_interactions <MAX_INTERACTIONS> ... "Maximum number of interactions during compilation")
+            .value_parser(value_parser!(u32)))
+                .arg(arg!(--deterministic "Reproduce a run exactly: fix the scheduling order and virtualize time"))
+                .arg(arg!(--progress <SECONDS> "Report progress on stderr at this interval while the program is busy; 0 turns it off")
+                    .value_parser(value_parser!(u64))
+                    .default_value("10")),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve a definition over TCP, for other programs to connect to with `Remote.Connect`")
+                .arg(
+                    arg!(--package <PACKAGE> "Path to package directory (or any file/directory inside it)")
+                        .value_parser(value_parser!(PathBuf))
+                        .default_value("."),
+                )
+                .arg(arg!([target] "Target to serve: `path/to/Module` or `path/to/Module.Def`"))
+                .arg(arg!(--address <ADDRESS> "Address to listen on")
+                    .default_value("127.0.0.1:8080"))
+                .arg(arg!(--config <ENTRY> ... "Set a configuration value as `key=value`, overriding the manifest")
+                    .value_parser(par_runtime::config::parse_entry))
                 .arg(arg!(--max_interactions <MAX_INTERACTIONS> ... "Maximum number of interactions during compilation")
             .value_parser(value_parser!(u32))),
         )
@@ -407,7 +521,52 @@ fn main() -> ExitCode {
                         .value_parser(value_parser!(PathBuf))
                         .default_value("."),
                 )
-                .arg(arg!(-f --flag <FLAG> ... "Set a flag")),
+                .arg(arg!(-f --flag <FLAG> ... "Set a flag"))
+                .arg(arg!(--totality "Report whether each `unfounded` loop appears total or likely diverges"))
+                .arg(arg!(--names "Only check names and type arities, without type checking; much faster")
+                    .conflicts_with("totality")),
+        )
+        .subcommand(
+            Command::new("fix")
+                .about("Rewrite a Par package from older syntax to the current syntax")
+                .arg(
+                    arg!(--package <PACKAGE> "Path to package directory (or any file/directory inside it)")
+                        .value_parser(value_parser!(PathBuf))
+                        .default_value("."),
+                )
+                .arg(arg!(--check "Only report what would change, and fail if anything would"))
+                .arg(arg!(--from <EDITION> "Edition of the files without an `edition` declaration [default: 2025]")
+                    .value_parser(parse_edition))
+                .arg(arg!(--to <EDITION> "Edition to move the files to [default: latest]")
+                    .value_parser(parse_edition)),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Explain a diagnostic code, such as PAR0007, with an example")
+                .arg(arg!(<code> "Diagnostic code shown with an error or a warning")),
+        )
+        .subcommand(
+            Command::new("grammar")
+                .about("Print the grammar of Par in EBNF, for editors and other tools"),
+        )
+        .subcommand(
+            Command::new("dump-ir")
+                .about("Print the desugared process calculus of the definitions in a Par file")
+                .arg(arg!(<file> "Par file inside a package").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--typed "Annotate every bound variable with its type"))
+                .arg(
+                    arg!(--net "Print what the compiled interaction nets encode, read back into process syntax")
+                        .conflicts_with("typed"),
+                ),
+        )
+        .subcommand(
+            Command::new("reduce")
+                .about("Shrink a Par file to a minimal version that still fails in the same way")
+                .arg(arg!(<file> "Par file inside a package").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--message <TEXT> "Keep the versions whose errors or panic message contain this text; without it, keep the versions that make the compiler panic"))
+                .arg(arg!(-o --output <PATH> "Write the reduced file here instead of printing it").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--max_interactions <MAX_INTERACTIONS> ... "Maximum number of interactions during compilation")
+            .value_parser(value_parser!(u32))),
         )
         .subcommand(
             Command::new("doc")
@@ -457,7 +616,9 @@ fn main() -> ExitCode {
                         .default_value("./compiled.pvm"),
                 )
                 .arg(arg!([target] "Target to run: `path/to/Module` or `path/to/Module.Def`"))
-                .arg(arg!(-f --flag <FLAG> ... "Set a flag")),
+                .arg(arg!(-f --flag <FLAG> ... "Set a flag"))
+                .arg(arg!(--config <ENTRY> ... "Set a configuration value as `key=value`, overriding the compiled-in one")
+                    .value_parser(par_runtime::config::parse_entry)),
         )
         .subcommand(
             Command::new("lsp")
@@ -485,10 +646,32 @@ fn main() -> ExitCode {
                 .arg(arg!(--filter <FILTER> "Only run tests matching this filter").required(false))
                 .arg(arg!(-f --flag <FLAG> ... "Set a flag"))
                 .arg(arg!(--max_interactions <MAX_INTERACTIONS> ... "Maximum number of interactions during compilation")
-            .value_parser(value_parser!(u32))),
+            .value_parser(value_parser!(u32)))
+                .arg(
+                    arg!(--doc_fuel <REWRITES> "Maximum number of rewrites each documentation example may take")
+                        .required(false)
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    arg!(--jobs <JOBS> "How many tests to run at the same time (default: one per CPU)")
+                        .required(false)
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    arg!(--time_limit <SECONDS> "Fail any test that takes longer than this (0 for no limit)")
+                        .required(false)
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    arg!(--shard <SHARD> "Only run one shard of the tests, like `1/4`")
+                        .required(false)
+                        .value_parser(value_parser!(test_runner::Shard)),
+                ),
         )
         .get_matches_from(wild::args());
 
+    set_full_types_in_reports(*matches.get_one::<bool>("full_types").unwrap());
+
     match matches.subcommand() {
         Some(("new", args)) => {
             let package = args.get_one::<String>("package").unwrap();
@@ -519,13 +702,49 @@ fn main() -> ExitCode {
         }
         Some(("run", args)) => {
             let stats = *args.get_one::<bool>("stats").unwrap();
+            let profile = *args.get_one::<bool>("profile").unwrap();
+            let value = *args.get_one::<bool>("value").unwrap();
+            let package = args.get_one::<PathBuf>("package").unwrap().clone();
+            let target = args.get_one::<String>("target").cloned();
+            let max_interactions = args
+                .get_one::<u32>("max_interactions")
+                .cloned()
+                .unwrap_or(MAX_INTERACTIONS_DEFAULT);
+            let deterministic = *args.get_one::<bool>("deterministic").unwrap();
+            let progress = *args.get_one::<u64>("progress").unwrap();
+            let config = config_overrides(args);
+            let options = RunOptions {
+                print_stats: stats,
+                print_profile: profile,
+                print_value: value,
+                max_interactions,
+                deterministic,
+                progress_interval: progress,
+                config,
+            };
+            #[cfg(not(target_family = "wasm"))]
+            if *args.get_one::<bool>("watch").unwrap() {
+                if watch_definition(package, target, options).is_err() {
+                    return ExitCode::FAILURE;
+                }
+                return ExitCode::SUCCESS;
+            }
+            run_definition(package, target, options);
+        }
+        Some(("serve", args)) => {
             let package = args.get_one::<PathBuf>("package").unwrap().clone();
             let target = args.get_one::<String>("target").cloned();
+            let address = args.get_one::<String>("address").unwrap().clone();
             let max_interactions = args
                 .get_one::<u32>("max_interactions")
                 .cloned()
                 .unwrap_or(MAX_INTERACTIONS_DEFAULT);
-            run_definition(package, target, stats, max_interactions);
+            let config = config_overrides(args);
+            if let Err(error) = serve_definition(package, target, address, max_interactions, config)
+            {
+                eprintln!("{}", error.bright_red());
+                return ExitCode::FAILURE;
+            }
         }
         Some(("compile", args)) => {
             let package = args.get_one::<PathBuf>("package").unwrap().clone();
@@ -539,11 +758,63 @@ fn main() -> ExitCode {
             let stats = *args.get_one::<bool>("stats").unwrap();
             let file = args.get_one::<PathBuf>("file").unwrap().clone();
             let target = args.get_one::<String>("target").cloned();
-            run_definition_vm(file, target, stats);
+            let config = config_overrides(args);
+            run_definition_vm(file, target, stats, config);
         }
         Some(("check", args)) => {
             let package = args.get_one::<PathBuf>("package").unwrap().clone();
-            if check(package).is_err() {
+            let totality = *args.get_one::<bool>("totality").unwrap();
+            let names = *args.get_one::<bool>("names").unwrap();
+            let checked = if names {
+                check_names(package)
+            } else {
+                check(package, totality)
+            };
+            if checked.is_err() {
+                return ExitCode::FAILURE;
+            }
+        }
+        Some(("fix", args)) => {
+            let package = args.get_one::<PathBuf>("package").unwrap().clone();
+            let check_only = *args.get_one::<bool>("check").unwrap();
+            let from = args
+                .get_one::<Edition>("from")
+                .copied()
+                .unwrap_or(Edition::UNDECLARED);
+            let to = args
+                .get_one::<Edition>("to")
+                .copied()
+                .unwrap_or(Edition::LATEST);
+            if fix(package, check_only, from, to).is_err() {
+                return ExitCode::FAILURE;
+            }
+        }
+        Some(("explain", args)) => {
+            let code = args.get_one::<String>("code").unwrap();
+            if explain(code).is_err() {
+                return ExitCode::FAILURE;
+            }
+        }
+        Some(("grammar", _)) => {
+            print!("{GRAMMAR}");
+        }
+        Some(("dump-ir", args)) => {
+            let file = args.get_one::<PathBuf>("file").unwrap().clone();
+            let typed = *args.get_one::<bool>("typed").unwrap();
+            let net = *args.get_one::<bool>("net").unwrap();
+            if dump_ir(file, typed, net).is_err() {
+                return ExitCode::FAILURE;
+            }
+        }
+        Some(("reduce", args)) => {
+            let file = args.get_one::<PathBuf>("file").unwrap().clone();
+            let message = args.get_one::<String>("message").cloned();
+            let output = args.get_one::<PathBuf>("output").cloned();
+            let max_interactions = args
+                .get_one::<u32>("max_interactions")
+                .copied()
+                .unwrap_or(MAX_INTERACTIONS_DEFAULT);
+            if reduce_file(file, message, output, max_interactions).is_err() {
                 return ExitCode::FAILURE;
             }
         }
@@ -582,7 +853,26 @@ fn main() -> ExitCode {
                 .get_one::<u32>("max_interactions")
                 .cloned()
                 .unwrap_or(MAX_INTERACTIONS_DEFAULT);
-            if !run_tests(package, target, filter.cloned(), max_interactions) {
+            let doc_fuel = args
+                .get_one::<u64>("doc_fuel")
+                .cloned()
+                .unwrap_or(test_runner::DOC_EXAMPLE_FUEL_DEFAULT);
+            let jobs = args.get_one::<usize>("jobs").cloned().unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+            });
+            let time_limit = args
+                .get_one::<u64>("time_limit")
+                .cloned()
+                .unwrap_or(test_runner::TIME_LIMIT_DEFAULT_SECONDS);
+            let options = test_runner::TestOptions {
+                filter: filter.cloned(),
+                max_interactions,
+                doc_example_fuel: doc_fuel,
+                jobs,
+                time_limit: (time_limit > 0).then(|| Duration::from_secs(time_limit)),
+                shard: args.get_one::<test_runner::Shard>("shard").cloned(),
+            };
+            if !run_tests(package, target, options) {
                 return ExitCode::FAILURE;
             }
         }
@@ -619,7 +909,14 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Playground::new(cc, file, MAX_INTERACTIONS_DEFAULT))),
+                Box::new(|cc| {
+                    Ok(Playground::new(
+                        cc,
+                        file,
+                        MAX_INTERACTIONS_DEFAULT,
+                        RendererRegistry::default(),
+                    ))
+                }),
             )
             .await;
 
@@ -679,69 +976,447 @@ fn run_playground(file: Option<PathBuf>, max_interactions: u32) {
     eframe::run_native(
         "Par Playground",
         options,
-        Box::new(|cc| Ok(Playground::new(cc, file, max_interactions))),
+        Box::new(|cc| {
+            Ok(Playground::new(
+                cc,
+                file,
+                max_interactions,
+                RendererRegistry::default(),
+            ))
+        }),
     )
     .expect("egui crashed");
 }
 
-fn run_definition(
-    package_path: PathBuf,
-    target: Option<String>,
+/// How `par run` runs a definition, and what it tells about the run.
+#[derive(Clone)]
+struct RunOptions {
     print_stats: bool,
+    print_profile: bool,
+    print_value: bool,
     max_interactions: u32,
-) {
-    let runtime = tokio_factory::create_runtime().expect("Failed to create Tokio runtime");
-    runtime.block_on(async {
-        let (checked, rt_compiled, local_modules) =
-            match build_runtime_package(&package_path, max_interactions) {
-                Ok((checked, rt_compiled, local_modules)) => (checked, rt_compiled, local_modules),
-                Err(error) => {
-                    println!("{}", error.display().bright_red());
-                    return;
-                }
-            };
+    deterministic: bool,
+    progress_interval: u64,
+    config: Vec<(String, String)>,
+}
 
-        let Some(name) = resolve_target_definition(target.as_deref(), &checked, &local_modules)
-        else {
-            let target = target.unwrap_or_else(|| "Main.Main".to_string());
-            println!("{}: {}", "Definition not found".bright_red(), target);
-            return;
+impl RunOptions {
+    /// A runtime for one run, single-threaded if the run is deterministic.
+    fn create_runtime(&self) -> tokio::runtime::Runtime {
+        if self.deterministic {
+            eprintln!("{}", "Deterministic run".bright_green());
+            tokio_factory::create_deterministic_runtime()
+        } else {
+            tokio_factory::create_runtime()
+        }
+        .expect("Failed to create Tokio runtime")
+    }
+
+    /// The setup for one run, in a fresh environment, deterministic if the
+    /// run is.
+    fn create_setup(&self) -> Setup {
+        let environment = if self.deterministic {
+            Environment::deterministic()
+        } else {
+            Environment::new()
         };
+        Setup::new(environment).with_profiling(self.print_profile)
+    }
+}
 
-        let Some(Type::Break(_)) = rt_compiled.get_type_of(name) else {
-            println!(
-                "{}: {}",
-                "Definition does not have the unit (!) type".bright_red(),
-                target.unwrap_or_else(|| "Main.Main".to_string())
-            );
-            return;
+fn run_definition(package_path: PathBuf, target: Option<String>, options: RunOptions) {
+    let runtime = options.create_runtime();
+    runtime.block_on(run_package_definition(
+        &package_path,
+        target.as_deref(),
+        &options,
+    ));
+}
+
+/// Runs the definition like `par run`, then again each time the package or
+/// one of its dependencies changes, until stopped. A change stops a build or
+/// a run that's still going, and a change that leaves the packages' files as
+/// they were doesn't count. Rebuilds only type check the packages that
+/// changed, and those depending on them.
+#[cfg(not(target_family = "wasm"))]
+fn watch_definition(
+    package_path: PathBuf,
+    target: Option<String>,
+    options: RunOptions,
+) -> Result<(), ()> {
+    let changed = Arc::new(tokio::sync::Notify::new());
+    let watch_roots = |roots: &[PathBuf]| {
+        let mut files = watch::WatchedFiles::new(roots);
+        let notify_changed = Arc::clone(&changed);
+        // Changes are reported with absolute paths.
+        let root_dir = fs::canonicalize(&roots[0]).unwrap_or_else(|_| roots[0].clone());
+        watch::PackageWatcher::new(roots, move |paths| {
+            if !files.update(&paths) {
+                return;
+            }
+            for path in &paths {
+                let shown = path.strip_prefix(&root_dir).unwrap_or(path);
+                eprintln!("{} {}", "Changed:".bright_yellow(), shown.display());
+            }
+            notify_changed.notify_one();
+        })
+    };
+
+    let mut roots = match watched_roots(&package_path) {
+        Ok(roots) => roots,
+        Err(error) => {
+            println!("{}", error.to_string().bright_red());
+            return Err(());
+        }
+    };
+    let mut _watcher = match watch_roots(&roots) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            println!("{}: {error}", "Failed to watch the package".bright_red());
+            return Err(());
+        }
+    };
+
+    let mut previous: Option<Arc<CheckedWorkspace>> = None;
+    loop {
+        let runtime = options.create_runtime();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let build = {
+            let (package_path, previous) = (package_path.clone(), previous.clone());
+            let (cancelled, max_interactions) = (Arc::clone(&cancelled), options.max_interactions);
+            move || {
+                rebuild_runtime_package(
+                    &package_path,
+                    max_interactions,
+                    previous.as_deref(),
+                    &cancelled,
+                )
+            }
         };
+        let interrupted = runtime.block_on(async {
+            tokio::select! {
+                _ = async {
+                    let built = build_on_main_thread_stack(build).await;
+                    match built {
+                        Ok(Some((checked, rt_compiled, local_modules, timings))) => {
+                            let checked = Arc::new(checked);
+                            previous = Some(Arc::clone(&checked));
+                            run_built_definition(
+                                &package_path,
+                                target.as_deref(),
+                                &options,
+                                &checked,
+                                &rt_compiled,
+                                &local_modules,
+                                timings,
+                            )
+                            .await;
+                        }
+                        Ok(None) => {}
+                        Err(error) => println!("{}", error.display().bright_red()),
+                    }
+                } => false,
+                _ = changed.notified() => true,
+            }
+        });
+        // Stops the build, if it's still going, before its next stage.
+        cancelled.store(true, Ordering::Relaxed);
+        // The run may have left tasks that never yield; don't wait for them.
+        runtime.shutdown_background();
+        if !interrupted {
+            eprintln!("{}", "Waiting for changes...".bright_green());
+            futures::executor::block_on(changed.notified());
+        }
 
-        let start = Instant::now();
-        let package_to_run = rt_compiled.code.get_with_name(name).unwrap();
-        let (root, reducer_future) = par_runtime::start_and_instantiate(
-            Arc::new(TokioSpawn::new()),
-            rt_compiled.code.arena.clone(),
-            package_to_run,
+        // A manifest may have changed which packages are depended on.
+        if let Ok(current) = watched_roots(&package_path)
+            && current != roots
+        {
+            match watch_roots(&current) {
+                Ok(rewatched) => (_watcher, roots) = (rewatched, current),
+                Err(error) => println!("{}: {error}", "Failed to watch the package".bright_red()),
+            }
+        }
+    }
+}
+
+/// Runs `build` on a thread of its own with the stack the main thread gets,
+/// which checking a deeply nested program needs.
+#[cfg(not(target_family = "wasm"))]
+async fn build_on_main_thread_stack<T: Send + 'static>(
+    build: impl FnOnce() -> T + Send + 'static,
+) -> T {
+    const MAIN_THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+    let (sender, built) = futures::channel::oneshot::channel();
+    std::thread::Builder::new()
+        .name("par-build".to_string())
+        .stack_size(MAIN_THREAD_STACK_SIZE)
+        .spawn(move || _ = sender.send(build()))
+        .expect("failed to spawn the build thread");
+    built.await.expect("the build panicked")
+}
+
+/// The roots of the package containing `package_path`, and of the packages
+/// it depends on, its own first. If its dependencies can't be found, as when
+/// its manifest is being edited, only its own.
+#[cfg(not(target_family = "wasm"))]
+fn watched_roots(package_path: &Path) -> Result<Vec<PathBuf>, WorkspaceDiscoveryError> {
+    let root_dir = PackageLayout::find_from(package_path)?.root_dir;
+    let Ok(graph) = PackageGraph::discover_from_path(package_path) else {
+        return Ok(vec![root_dir]);
+    };
+    let mut roots = vec![root_dir.clone()];
+    roots.extend(
+        graph
+            .packages
+            .into_iter()
+            .map(|package| package.layout.root_dir)
+            .filter(|root| *root != root_dir),
+    );
+    Ok(roots)
+}
+
+/// Builds the package and runs the definition, reporting any problem.
+async fn run_package_definition(package_path: &Path, target: Option<&str>, options: &RunOptions) {
+    let (checked, rt_compiled, local_modules, timings) =
+        match build_runtime_package(package_path, options.max_interactions) {
+            Ok(built) => built,
+            Err(error) => {
+                println!("{}", error.display().bright_red());
+                return;
+            }
+        };
+    run_built_definition(
+        package_path,
+        target,
+        options,
+        &checked,
+        &rt_compiled,
+        &local_modules,
+        timings,
+    )
+    .await;
+}
+
+/// Runs the definition of a package that's built, reporting any problem.
+async fn run_built_definition(
+    package_path: &Path,
+    target: Option<&str>,
+    options: &RunOptions,
+    checked: &CheckedWorkspace,
+    rt_compiled: &par_core::runtime::Compiled<Linked>,
+    local_modules: &[ModulePath],
+    timings: Vec<(Stage, Duration)>,
+) {
+    let Some(name) = resolve_target_definition(target, checked, local_modules) else {
+        let target = target.unwrap_or("Main.Main");
+        println!("{}: {}", "Definition not found".bright_red(), target);
+        return;
+    };
+    let assets = match load_package_assets(package_path) {
+        Ok(assets) => assets,
+        Err(error) => {
+            println!("{}", error.bright_red());
+            return;
+        }
+    };
+    let config = match load_package_config(package_path, options.config.clone()) {
+        Ok(config) => config,
+        Err(error) => {
+            println!("{}", error.bright_red());
+            return;
+        }
+    };
+
+    let type_defs = &checked.checked_module().type_defs;
+    let typ = rt_compiled.get_type_of(name);
+    let runnable = match &typ {
+        Some(typ) if options.print_value => typ
+            .satisfies_constraint(TypeConstraint::Data, type_defs)
+            .unwrap_or(false),
+        Some(Type::Break(_)) => true,
+        _ => false,
+    };
+    if !runnable {
+        let message = if options.print_value {
+            "Definition does not have a data type"
+        } else {
+            "Definition does not have the unit (!) type"
+        };
+        println!(
+            "{}: {}",
+            message.bright_red(),
+            target.unwrap_or("Main.Main")
         );
+        return;
+    }
 
+    let start = Instant::now();
+    let package_to_run = rt_compiled.package(name).unwrap();
+    let (root, reducer_future, progress) = par_runtime::start_and_instantiate_with_progress(
+        Arc::new(TokioSpawn::new()),
+        options
+            .create_setup()
+            .with_config(Arc::new(config))
+            .with_assets(Arc::new(assets)),
+        rt_compiled.arena(),
+        package_to_run,
+    );
+    // Progress goes to stderr, so it doesn't get mixed into a printed value.
+    let _reporter = (options.progress_interval > 0).then(|| {
+        report_progress(
+            progress,
+            start,
+            Duration::from_secs(options.progress_interval),
+        )
+    });
+
+    if options.print_value {
+        let typ = typ.expect("checked to be data above");
+        match TypedHandle::new(type_defs.clone(), typ, root)
+            .readback_data()
+            .await
+        {
+            Some(value) => println!("{value}"),
+            None => println!("{}", "The value could not be read back".bright_red()),
+        }
+    } else {
         root.continue_();
-        let stats = reducer_future.await;
+    }
+    let stats = reducer_future.await;
+    eprintln!(
+        "{} {}",
+        "Finished:".bright_green(),
+        stats.summary(start.elapsed())
+    );
 
-        if print_stats {
-            eprintln!("{}", stats.show(start.elapsed()));
-            eprintln!("\tArena size: {}", rt_compiled.code.arena.memory_size());
+    if options.print_stats {
+        eprintln!("{}", stats.show(start.elapsed()));
+        eprintln!("\tArena size: {}", rt_compiled.arena().memory_size());
+        eprint!("{}", BuildSummary::new(checked, timings).render());
+    }
+    if options.print_profile {
+        eprintln!("{}", "Profile:".bright_green());
+        eprint!("{}", Profile::new(rt_compiled, &stats));
+    }
+}
+
+/// Serves a definition on `address` until stopped. Every connection gets an
+/// instance of its own, which the other side interacts with through
+/// `Remote.Connect`.
+#[cfg(not(target_family = "wasm"))]
+fn serve_definition(
+    package_path: PathBuf,
+    target: Option<String>,
+    address: String,
+    max_interactions: u32,
+    config: Vec<(String, String)>,
+) -> Result<(), String> {
+    let runtime = tokio_factory::create_runtime().map_err(|error| error.to_string())?;
+    runtime.block_on(async {
+        let (checked, rt_compiled, local_modules, _timings) =
+            build_runtime_package(&package_path, max_interactions)
+                .map_err(|error| error.display())?;
+
+        let target = target.unwrap_or_else(|| "Main.Main".to_string());
+        let Some(name) = resolve_target_definition(Some(&target), &checked, &local_modules) else {
+            return Err(format!("Definition not found: {target}"));
+        };
+        let assets = load_package_assets(&package_path)?;
+        let config = load_package_config(&package_path, config)?;
+        let setup = Setup::new(Environment::new())
+            .with_config(Arc::new(config))
+            .with_assets(Arc::new(assets));
+
+        let type_defs = checked.checked_module().type_defs.clone();
+        let Some(typ) = rt_compiled.get_type_of(name) else {
+            return Err(format!("Definition's type can't be served: {target}"));
+        };
+        if let Some(unsupported) = unsupported_readback_with(&type_defs, &typ, &|_, _| false) {
+            return Err(format!(
+                "Definition's type can't be served: {}, which has `{}` at {} of its type",
+                target,
+                render_compact_type_in_scope(None, &unsupported.typ),
+                unsupported.location(),
+            ));
+        }
+
+        let listener = tokio::net::TcpListener::bind(&address)
+            .await
+            .map_err(|error| format!("Can't serve on {address}: {error}"))?;
+        println!("{} {} on {}", "Serving".bright_green(), name, address);
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    eprintln!("{}", error.to_string().bright_red());
+                    continue;
+                }
+            };
+            let package = rt_compiled.package(name).unwrap();
+            let (root, reducer_future) = par_runtime::start_and_instantiate(
+                Arc::new(TokioSpawn::new()),
+                setup.renewed(),
+                rt_compiled.arena(),
+                package,
+            );
+            let session = TypedHandle::new(type_defs.clone(), typ.clone(), root);
+            tokio::spawn(async move {
+                match par_builtin::serve_session(session, stream).await {
+                    Ok(()) => {
+                        reducer_future.await;
+                    }
+                    Err(error) => {
+                        eprintln!("{} {}: {}", "Session failed:".bright_red(), peer, error)
+                    }
+                }
+            });
+        }
+    })
+}
+
+/// Prints how far a run got on stderr, every `interval` in which it made
+/// progress, until the returned sender is dropped.
+fn report_progress(progress: Progress, start: Instant, interval: Duration) -> mpsc::Sender<()> {
+    let (stop, stopped) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        let mut last = 0;
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+            let rewrites = progress.rewrites();
+            if rewrites == last {
+                continue;
+            }
+            eprintln!(
+                "{} {:.1}s elapsed, {} rewrites ({}/s)",
+                "Still running:".bright_yellow(),
+                start.elapsed().as_secs_f64(),
+                rewrites,
+                ((rewrites - last) as f64 / interval.as_secs_f64()) as u64,
+            );
+            last = rewrites;
         }
     });
+    stop
 }
 
-fn run_definition_vm(binary_path: PathBuf, target: Option<String>, print_stats: bool) {
+fn run_definition_vm(
+    binary_path: PathBuf,
+    target: Option<String>,
+    print_stats: bool,
+    config: Vec<(String, String)>,
+) {
     let runtime = tokio_factory::create_runtime().expect("Failed to create Tokio runtime");
     runtime.block_on(async {
         let file = File::open(binary_path).expect("Failed to open file");
         let reader = BufReader::new(file);
-        let artifact: Artifact<Unlinked> =
-            bincode::deserialize_from(reader).expect("Failed to deserialize artifact");
+        let artifact = match Artifact::read_from(reader) {
+            Ok(artifact) => artifact,
+            Err(error) => {
+                println!("{}", error.to_string().bright_red());
+                return;
+            }
+        };
         let artifact = match artifact.link() {
             Ok(artifact) => artifact,
             Err(error) => {
@@ -749,6 +1424,11 @@ fn run_definition_vm(binary_path: PathBuf, target: Option<String>, print_stats:
                 return;
             }
         };
+        let mut artifact_config = artifact.config.clone();
+        artifact_config.extend(config);
+        let setup = Setup::new(Environment::new())
+            .with_config(Arc::new(artifact_config))
+            .with_assets(Arc::new(artifact.assets.clone()));
 
         let parsed_target = parse_target(target.as_deref().unwrap_or("Main.Main"));
         let definition_target = parsed_target
@@ -763,12 +1443,18 @@ fn run_definition_vm(binary_path: PathBuf, target: Option<String>, print_stats:
             .expect(format!("Definition {target} not found").as_str());
         let (root, reducer_future) = par_runtime::start_and_instantiate(
             Arc::new(TokioSpawn::new()),
+            setup,
             artifact.arena.clone(),
             package_to_run.clone(),
         );
 
         root.continue_();
         let stats = reducer_future.await;
+        eprintln!(
+            "{} {}",
+            "Finished:".bright_green(),
+            stats.summary(start.elapsed())
+        );
 
         if print_stats {
             eprintln!("{}", stats.show(start.elapsed()));
@@ -778,23 +1464,46 @@ fn run_definition_vm(binary_path: PathBuf, target: Option<String>, print_stats:
 }
 
 fn compile(package_path: PathBuf, max_interactions: u32) {
-    let (checked, rt_compiled, _local_modules, _sources) =
+    let (checked, rt_compiled, _local_modules, _sources, _timings) =
         match build_unlinked_package(&package_path, max_interactions) {
-            Ok((checked, rt_compiled, local_modules, sources)) => {
-                (checked, rt_compiled, local_modules, sources)
-            }
+            Ok(built) => built,
             Err(error) => {
                 println!("{}", error.display().bright_red());
                 return;
             }
         };
 
-    let artifact: Artifact<Unlinked> = rt_compiled
-        .code
-        .into_artifact(checked.workspace().root_package());
+    let assets = match load_package_assets(&package_path) {
+        Ok(assets) => assets,
+        Err(error) => {
+            println!("{}", error.bright_red());
+            return;
+        }
+    };
+    let config = match load_package_config(&package_path, []) {
+        Ok(config) => config,
+        Err(error) => {
+            println!("{}", error.bright_red());
+            return;
+        }
+    };
+
+    let mut artifact: Artifact<Unlinked> =
+        rt_compiled.into_artifact(checked.workspace().root_package());
+    artifact.assets = assets;
+    artifact.config = config;
     let file = File::create("compiled.pvm").expect("Failed to create file");
     let writer = BufWriter::new(file);
-    bincode::serialize_into(writer, &artifact).expect("Failed to serialize");
+    artifact.write_to(writer).expect("Failed to serialize");
+}
+
+/// The `--config` entries given to a command, in order.
+fn config_overrides(args: &ArgMatches) -> Vec<(String, String)> {
+    args.get_many::<(String, String)>("config")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
 }
 
 fn resolve_target_definition<'a>(
@@ -823,18 +1532,275 @@ fn resolve_target_definition<'a>(
         .map(|(name, _)| name)
 }
 
-fn check(package_path: PathBuf) -> Result<(), String> {
+fn check(package_path: PathBuf, totality: bool) -> Result<(), String> {
     println!("Checking package: {}", package_path.display());
 
-    let build_result = build_runtime_package(&package_path, MAX_INTERACTIONS_DEFAULT);
-    if let Err(error) = build_result {
-        let error_string = error.display();
-        eprintln!("{}", error_string.bright_red());
-        return Err(error_string);
+    let (checked, _, _, timings) =
+        match build_runtime_package(&package_path, MAX_INTERACTIONS_DEFAULT) {
+            Ok(built) => built,
+            Err(error) => {
+                let error_string = error.display();
+                eprintln!("{}", error_string.bright_red());
+                if let BuildError::Type { errors, .. } = &error {
+                    print!("{}", BuildSummary::failed(errors.len()).render());
+                }
+                return Err(error_string);
+            }
+        };
+    let sources = checked.workspace().sources();
+    for warning in scoped_warnings(&checked) {
+        eprintln!(
+            "{}",
+            format!("{:?}", warning.to_report(sources)).bright_yellow()
+        );
+    }
+    if totality {
+        for hint in checked.totality_hints() {
+            match hint.verdict {
+                TotalityVerdict::AppearsTotal => println!("{}", hint.to_string().bright_green()),
+                TotalityVerdict::LikelyDiverges { .. } => {
+                    println!("{}", hint.to_string().bright_yellow())
+                }
+            }
+        }
+    }
+    print!("{}", BuildSummary::new(&checked, timings).render());
+    Ok(())
+}
+
+fn check_names(package_path: PathBuf) -> Result<(), String> {
+    println!("Checking names in package: {}", package_path.display());
+
+    let error = match name_errors_from_path(&package_path) {
+        Ok((errors, _)) if errors.is_empty() => return Ok(()),
+        Ok((errors, sources)) => BuildError::Type { errors, sources },
+        Err(error) => map_workspace_build_error(error),
+    };
+    let error_string = error.display();
+    eprintln!("{}", error_string.bright_red());
+    Err(error_string)
+}
+
+fn parse_edition(year: &str) -> Result<Edition, String> {
+    Edition::from_year(year).ok_or_else(|| {
+        let known = Edition::ALL
+            .iter()
+            .map(Edition::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("unknown edition `{year}`, expected one of {known}")
+    })
+}
+
+fn fix(package_path: PathBuf, check_only: bool, from: Edition, to: Edition) -> Result<(), String> {
+    let report = |error: String| {
+        eprintln!("{}", error.bright_red());
+        error
+    };
+    let layout =
+        PackageLayout::find_from(&package_path).map_err(|error| report(error.to_string()))?;
+    let files = load_package_source_files(&layout).map_err(|error| report(error.to_string()))?;
+
+    let mut changed_files = 0;
+    for file in files {
+        let migrated = migrate_source(&file.source, &file.name, from, to);
+        if !migrated.is_changed() {
+            continue;
+        }
+        changed_files += 1;
+        for edit in &migrated.edits {
+            println!(
+                "{} `{}` -> `{}` ({})",
+                edit.span, edit.original, edit.replacement, edit.migration
+            );
+        }
+        if !check_only {
+            let path = layout.src_dir.join(&file.relative_path_from_src);
+            fs::write(&path, &migrated.source)
+                .map_err(|error| report(format!("Failed to write {}: {error}", path.display())))?;
+        }
+    }
+
+    if changed_files == 0 {
+        println!("{}", "Nothing to fix.".bright_green());
+    } else if check_only {
+        return Err(report(format!(
+            "{changed_files} file(s) use outdated syntax"
+        )));
+    } else {
+        println!("{} {changed_files} file(s)", "Fixed:".bright_green());
     }
     Ok(())
 }
 
+fn explain(code: &str) -> Result<(), String> {
+    let Some(entry) = explain_code(code) else {
+        let error = format!("Unknown diagnostic code: {code}");
+        eprintln!("{}", error.bright_red());
+        return Err(error);
+    };
+    println!(
+        "{} {}",
+        entry.code.bright_yellow().bold(),
+        entry.title.bold()
+    );
+    println!();
+    print!("{}", entry.explanation);
+    Ok(())
+}
+
+fn dump_ir(file: PathBuf, typed: bool, net: bool) -> Result<(), String> {
+    use std::fmt::Write;
+
+    let report = |error: String| {
+        eprintln!("{}", error.bright_red());
+        error
+    };
+    let target = fs::canonicalize(&file)
+        .map_err(|error| report(format!("Failed to read {}: {error}", file.display())))?;
+    let build = build_checked_package(&file).map_err(|error| report(error.display()))?;
+    let checked = &build.checked;
+    let in_target = |file: &Option<par_core::source::FileName>| {
+        file.as_ref()
+            .and_then(|file| fs::canonicalize(file.0.as_str()).ok())
+            .is_some_and(|path| path == target)
+    };
+
+    let mut out = String::new();
+    if net {
+        let module = checked.checked_module();
+        let decompiled = decompile_runtime(module, MAX_INTERACTIONS_DEFAULT)
+            .map_err(|error| report(error.display("")))?;
+        for (name, decompiled) in decompiled {
+            let Some((definition, _)) = module.definitions.get(&name) else {
+                continue;
+            };
+            let file = definition.span.file();
+            if !in_target(&file) {
+                continue;
+            }
+            let name = checked.render_global_in_file(&file.unwrap(), &name);
+            let _ = write!(out, "def {name} = {decompiled}\n\n");
+        }
+    } else if typed {
+        for (definition, typ) in checked.checked_module().definitions.values() {
+            let file = definition.span.file();
+            if !in_target(&file) {
+                continue;
+            }
+            let file = file.unwrap();
+            let name = checked.render_global_in_file(&file, &definition.name);
+            let _ = writeln!(
+                out,
+                "dec {name} : {}",
+                checked.render_type_in_file(&file, typ, 0)
+            );
+            let _ = write!(out, "def {name} = ");
+            let _ = match &definition.body {
+                DefinitionBody::Par(expression) => expression.pretty_typed(&mut out, 0),
+                DefinitionBody::External(_) => write!(out, "<external>"),
+            };
+            let _ = write!(out, "\n\n");
+        }
+    } else {
+        for definition in &checked.workspace().lowered_module().definitions {
+            let file = definition.span.file();
+            if !in_target(&file) {
+                continue;
+            }
+            let name = checked.render_global_in_file(&file.unwrap(), &definition.name);
+            let _ = write!(out, "def {name} = ");
+            let _ = match &definition.body {
+                DefinitionBody::Par(expression) => expression.pretty(&mut out, 0),
+                DefinitionBody::External(_) => write!(out, "<external>"),
+            };
+            let _ = write!(out, "\n\n");
+        }
+    }
+
+    if out.is_empty() {
+        return Err(report(format!(
+            "No definitions found in {}",
+            file.display()
+        )));
+    }
+    print!("{out}");
+    Ok(())
+}
+
+fn reduce_file(
+    file: PathBuf,
+    message: Option<String>,
+    output: Option<PathBuf>,
+    max_interactions: u32,
+) -> Result<(), String> {
+    let report = |error: String| {
+        eprintln!("{}", error.bright_red());
+        error
+    };
+    let target = fs::canonicalize(&file)
+        .map_err(|error| report(format!("Failed to read {}: {error}", file.display())))?;
+    let source = fs::read_to_string(&target)
+        .map_err(|error| report(format!("Failed to read {}: {error}", file.display())))?;
+
+    let still_fails = |source: &str| {
+        let overrides = SourceOverrides::from([(target.clone(), source.to_owned())]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            build_checked_package_with_overrides(&target, Some(&overrides))?
+                .compile_linked(max_interactions)
+                .map(|_| ())
+                .map_err(|(checked, error)| BuildError::InetCompile {
+                    error,
+                    sources: checked.workspace().sources().clone(),
+                })
+        }));
+        match (&message, result) {
+            (_, Ok(Ok(()))) => false,
+            (Some(message), Ok(Err(error))) => error.display().contains(message.as_str()),
+            (None, Ok(Err(_))) => false,
+            (Some(message), Err(payload)) => panic_message(&*payload).contains(message.as_str()),
+            (None, Err(_)) => true,
+        }
+    };
+
+    // The compiler panicking is what's being looked for, so keep it quiet.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let reduced = if still_fails(&source) {
+        let lines = |text: &str| text.lines().count();
+        Some(reduce::reduce(
+            &source,
+            &par_core::source::FileName::from(target.as_path()),
+            still_fails,
+            |smaller| eprintln!("Reduced to {} lines", lines(smaller)),
+        ))
+    } else {
+        None
+    };
+    std::panic::set_hook(default_hook);
+
+    let Some(reduced) = reduced else {
+        return Err(report(match &message {
+            Some(message) => format!("{} doesn't fail with `{message}`", file.display()),
+            None => format!("{} doesn't make the compiler panic", file.display()),
+        }));
+    };
+    match output {
+        Some(output) => fs::write(&output, &reduced)
+            .map_err(|error| report(format!("Failed to write {}: {error}", output.display())))?,
+        None => print!("{reduced}"),
+    }
+    Ok(())
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
 fn create_temp_dir(prefix: &str) -> Result<PathBuf, String> {
     let unique = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -1018,8 +1984,7 @@ fn run_language_server() {
 fn run_tests(
     package_path: PathBuf,
     target: Option<String>,
-    filter: Option<String>,
-    max_interactions: u32,
+    options: test_runner::TestOptions,
 ) -> bool {
-    test_runner::run_tests(package_path, target, filter, max_interactions)
+    test_runner::run_tests(package_path, target, options)
 }
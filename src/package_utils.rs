@@ -1,10 +1,13 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use par_core::frontend::TypeError;
 use par_core::frontend::language::Universal;
 use par_core::source::FileName;
-use par_core::workspace::ModulePath;
+use par_core::workspace::{ModulePath, PackageLayout, PackageManifest};
+use par_runtime::assets::{self, Assets};
+use par_runtime::config::Config;
 use par_runtime::pkgid::PackageId;
 
 pub type SourceLookup = HashMap<FileName, Arc<str>>;
@@ -24,6 +27,28 @@ pub fn source_for_type_error(error: &TypeError<Universal>, sources: &SourceLooku
         .unwrap_or_else(|| source_for_fallback(sources))
 }
 
+/// Collects the assets bundled with the package containing `package_path`.
+pub fn load_package_assets(package_path: &Path) -> Result<Assets, String> {
+    let layout = PackageLayout::find_from(package_path).map_err(|error| error.to_string())?;
+    let dir = layout.assets_dir();
+    assets::load_dir(&dir)
+        .map_err(|error| format!("Failed to load assets from {}: {error}", dir.display()))
+}
+
+/// The configuration values of the package containing `package_path`, from
+/// its manifest, with `overrides` taking precedence.
+pub fn load_package_config(
+    package_path: &Path,
+    overrides: impl IntoIterator<Item = (String, String)>,
+) -> Result<Config, String> {
+    let layout = PackageLayout::find_from(package_path).map_err(|error| error.to_string())?;
+    let mut config = PackageManifest::read_from(&layout.manifest_path)
+        .map_err(|error| error.to_string())?
+        .config;
+    config.extend(overrides);
+    Ok(config)
+}
+
 pub fn root_module_slash_path(root_package: &PackageId, module: &Universal) -> Option<String> {
     if &module.package != root_package {
         return None;
@@ -1,45 +1,90 @@
-use std::{
-    collections::BTreeSet,
-    path::PathBuf,
-    sync::{Arc, Mutex},
-};
+#[cfg(target_family = "wasm")]
+use std::sync::Mutex;
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
 
 use super::{
+    background::{BackgroundBuild, BuildJob},
     build::BuildResult,
-    readback::Element,
+    config::ConfigPanel,
+    explorer::ExplorerPanel,
+    history::{HistoryAction, HistoryPanel},
+    library::LibraryPanel,
+    renderers::RendererRegistry,
+    run::{RunContext, RunStatus},
     run_menu,
+    search::{SearchJump, SearchPanel},
+    sequence_diagram::{DiagramFormat, sequence_diagram},
     sources::{SourceSet, SourceSetKind},
+    undo::DEFAULT_UNDO_LIMIT,
+    unfold::UnfoldPanel,
 };
 use eframe::egui::{self, RichText, Theme};
 use egui_code_editor::{CodeEditor, ColorTheme, Completer, Syntax};
-use futures::task::Spawn;
 
-#[cfg(target_family = "wasm")]
-use crate::wasm_spawn::WasmSpawn;
+use par_core::frontend::set_full_types_in_reports;
+use par_core::runtime::Profile;
 use par_core::source::FileName;
-#[cfg(not(target_family = "wasm"))]
-use par_runtime::spawn::TokioSpawn;
-use tokio_util::sync::CancellationToken;
+use par_core::workspace::CheckedWorkspace;
+use par_runtime::assets::Assets;
+use par_runtime::environment::Environment;
+use par_runtime::pkgid::PackageId;
+use par_runtime::setup::Setup;
 
 pub struct Playground {
     sources: SourceSet,
     build: BuildResult,
     built_code: Arc<str>,
+    /// The [fingerprint](SourceSet::fingerprint) of the sources `build` is
+    /// of.
+    built_fingerprint: Option<u64>,
+    background: BackgroundBuild,
     editor_font_size: f32,
     show_compiled: bool,
-    element: Option<Arc<Mutex<Element>>>,
+    /// Show types in errors in full, instead of truncated.
+    full_types: bool,
+    /// Count which definitions runs spend their rewrites in, from the next
+    /// run on.
+    profile_runs: bool,
+    /// The assets of the package, as of the last build, for runs to read.
+    assets: Arc<Assets>,
+    /// The definition being run, if any. Each run is isolated in its own
+    /// context, and replacing or clearing it discards the run.
+    run: Option<RunContext>,
+    /// How many inputs a run keeps for undoing.
+    undo_limit: usize,
+    /// Restart the run on the next build, because files it's built from
+    /// were reloaded after changing on disk.
+    #[cfg(not(target_family = "wasm"))]
+    restart_on_build: bool,
     cursor_pos: (u32, u32),
+    /// Character index of the editor cursor, where library names get inserted.
+    cursor_index: usize,
     theme_mode: ThemeMode,
-    #[cfg(not(target_family = "wasm"))]
-    _rt: tokio::runtime::Runtime,
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: Option<CancellationToken>,
     max_interactions: u32,
     #[cfg(target_family = "wasm")]
     pending_web_clipboard_paste: Arc<Mutex<Option<String>>>,
     completer: Completer,
     #[cfg(not(target_family = "wasm"))]
     open_error: Option<String>,
+    #[cfg(not(target_family = "wasm"))]
+    save_error: Option<String>,
+    new_scratch: Option<NewScratchDialog>,
+    library: LibraryPanel,
+    unfold: UnfoldPanel,
+    explorer: ExplorerPanel,
+    search: SearchPanel,
+    /// Character range for the editor to select, and scroll to, once it's
+    /// shown the next time.
+    pending_selection: Option<(usize, usize)>,
+    config: ConfigPanel,
+    history: HistoryPanel,
+    renderers: Arc<RendererRegistry>,
+}
+
+#[derive(Default)]
+struct NewScratchDialog {
+    name: String,
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -82,6 +127,7 @@ impl Playground {
         cc: &eframe::CreationContext<'_>,
         file_path: Option<PathBuf>,
         max_interactions: u32,
+        renderers: RendererRegistry,
     ) -> Box<Self> {
         let system_dark = cc
             .egui_ctx
@@ -108,34 +154,41 @@ impl Playground {
             style.wrap_mode = Some(egui::TextWrapMode::Extend);
         });
 
-        #[cfg(not(target_family = "wasm"))]
-        let runtime =
-            crate::tokio_factory::create_runtime().expect("Failed to create Tokio runtime");
-        #[cfg(not(target_family = "wasm"))]
-        let spawner = Arc::new(TokioSpawn::from_handle(runtime.handle().clone()));
-
-        #[cfg(target_family = "wasm")]
-        let spawner = Arc::new(WasmSpawn::new());
-
         let playground = Box::new(Self {
             sources: SourceSet::bundled_examples(),
             build: BuildResult::None,
             built_code: Arc::from(""),
+            built_fingerprint: None,
+            background: BackgroundBuild::default(),
             editor_font_size: 16.0,
             show_compiled: false,
-            element: None,
+            full_types: false,
+            profile_runs: false,
+            assets: Arc::default(),
+            run: None,
+            undo_limit: DEFAULT_UNDO_LIMIT,
+            #[cfg(not(target_family = "wasm"))]
+            restart_on_build: false,
             cursor_pos: (0, 0),
+            cursor_index: 0,
             theme_mode: ThemeMode::System,
-            #[cfg(not(target_family = "wasm"))]
-            _rt: runtime,
-            spawner,
-            cancel_token: None,
             max_interactions,
             #[cfg(target_family = "wasm")]
             pending_web_clipboard_paste: Arc::new(Mutex::new(None)),
             completer: Completer::new_with_syntax(&par_syntax()).with_auto_indent(),
             #[cfg(not(target_family = "wasm"))]
             open_error: None,
+            #[cfg(not(target_family = "wasm"))]
+            save_error: None,
+            new_scratch: None,
+            library: LibraryPanel::default(),
+            unfold: UnfoldPanel::default(),
+            explorer: ExplorerPanel::default(),
+            search: SearchPanel::default(),
+            pending_selection: None,
+            config: ConfigPanel::default(),
+            history: HistoryPanel::default(),
+            renderers: Arc::new(renderers),
         });
 
         #[cfg(not(target_family = "wasm"))]
@@ -178,7 +231,21 @@ impl eframe::App for Playground {
         #[cfg(target_family = "wasm")]
         self.inject_pending_web_clipboard_paste(ui.ctx());
 
-        self.sources.reload_active_if_changed();
+        #[cfg(not(target_family = "wasm"))]
+        if self.sources.reload_changed() {
+            self.restart_on_build = true;
+        }
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let ctx = ui.ctx().clone();
+            if let Some(error) = self
+                .sources
+                .persist_scratch(|delay| ctx.request_repaint_after(delay))
+            {
+                self.save_error = Some(error);
+            }
+        }
+        self.compile_when_idle(ui.ctx());
 
         egui::CentralPanel::default()
             .frame(egui::Frame::new().fill(ui.visuals().panel_fill))
@@ -227,6 +294,104 @@ impl eframe::App for Playground {
 
                                 ui.add_space(5.0);
 
+                                if ui
+                                    .selectable_label(
+                                        self.library.open,
+                                        egui::RichText::new("Library").strong(),
+                                    )
+                                    .clicked()
+                                {
+                                    self.library.open = !self.library.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                if ui
+                                    .selectable_label(
+                                        self.unfold.open,
+                                        egui::RichText::new("Unfold").strong(),
+                                    )
+                                    .on_hover_text("Unfold the type under the cursor step by step")
+                                    .clicked()
+                                {
+                                    self.unfold.open = !self.unfold.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                if ui
+                                    .selectable_label(
+                                        self.explorer.open,
+                                        egui::RichText::new("Explore").strong(),
+                                    )
+                                    .on_hover_text("Walk the protocol of the type under the cursor")
+                                    .clicked()
+                                {
+                                    self.explorer.open = !self.explorer.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                if ui
+                                    .selectable_label(
+                                        self.search.open,
+                                        egui::RichText::new("Search").strong(),
+                                    )
+                                    .on_hover_text("Search the examples and all open sources")
+                                    .clicked()
+                                {
+                                    self.search.open = !self.search.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                if ui
+                                    .selectable_label(
+                                        self.history.open,
+                                        egui::RichText::new("History").strong(),
+                                    )
+                                    .on_hover_text(
+                                        "Compare, restore, and retract versions of definitions",
+                                    )
+                                    .clicked()
+                                {
+                                    self.history.open = !self.history.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                if ui
+                                    .selectable_label(
+                                        self.config.open,
+                                        egui::RichText::new("Config").strong(),
+                                    )
+                                    .on_hover_text("Set the values programs read through Config")
+                                    .clicked()
+                                {
+                                    self.config.open = !self.config.open;
+                                }
+
+                                ui.add_space(5.0);
+
+                                let skeleton = self.case_skeleton_at_cursor();
+                                if ui
+                                    .add_enabled(
+                                        skeleton.is_some(),
+                                        egui::Button::new(
+                                            egui::RichText::new("Insert .case").strong(),
+                                        ),
+                                    )
+                                    .on_disabled_hover_text(
+                                        "Put the cursor on a value of an either type, and build",
+                                    )
+                                    .clicked()
+                                    && let Some((offset, skeleton)) = skeleton
+                                {
+                                    self.insert_at_offset(offset, &skeleton);
+                                }
+
+                                ui.add_space(5.0);
+
                                 self.show_source_menu(ui);
                             });
                         });
@@ -253,11 +418,46 @@ impl eframe::App for Playground {
                                             &mut self.completer,
                                         );
 
+                                    if let Some((start, end)) = self.pending_selection.take() {
+                                        let (start, end) = (
+                                            egui::text::CCursor::new(start),
+                                            egui::text::CCursor::new(end),
+                                        );
+                                        let mut state = editor.state.clone();
+                                        state.cursor.set_char_range(Some(
+                                            egui::text::CCursorRange::two(start, end),
+                                        ));
+                                        state.store(ui.ctx(), editor.response.response.id);
+                                        editor.response.response.request_focus();
+                                        let rect = editor
+                                            .galley
+                                            .pos_from_cursor(start)
+                                            .translate(editor.galley_pos.to_vec2());
+                                        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                    }
+
                                     if let Some(cursor) = editor.cursor_range {
+                                        self.cursor_index = cursor.primary.index;
                                         self.cursor_pos = row_and_column(
                                             self.sources.active_source(),
                                             cursor.primary.index,
                                         );
+
+                                        if let Some(checked) = self.build.checked()
+                                            && editor.response.response.has_focus()
+                                        {
+                                            let cursor_rect = editor
+                                                .galley
+                                                .pos_from_cursor(cursor.primary)
+                                                .translate(editor.galley_pos.to_vec2());
+                                            show_builtin_doc_popup(
+                                                ui,
+                                                &checked,
+                                                &self.active_file_name(),
+                                                self.cursor_pos,
+                                                cursor_rect,
+                                            );
+                                        }
                                     }
 
                                     if let (Some(checked), Some(hover_pos)) =
@@ -290,7 +490,28 @@ impl eframe::App for Playground {
             });
 
         #[cfg(not(target_family = "wasm"))]
-        self.show_open_error_dialog(ui.ctx());
+        show_error_dialog(ui.ctx(), "Could not open file", &mut self.open_error);
+        #[cfg(not(target_family = "wasm"))]
+        show_error_dialog(ui.ctx(), "Could not save", &mut self.save_error);
+        self.show_new_scratch_dialog(ui.ctx());
+        self.show_library(ui.ctx());
+        self.unfold.show(
+            ui.ctx(),
+            self.build.checked(),
+            &self.active_file_name(),
+            self.cursor_pos,
+        );
+        self.explorer.show(
+            ui.ctx(),
+            self.build.checked(),
+            &self.active_file_name(),
+            self.cursor_pos,
+        );
+        if let Some(jump) = self.search.show(ui.ctx(), &self.sources) {
+            self.jump_to(jump);
+        }
+        self.show_history(ui.ctx());
+        self.config.show(ui.ctx());
     }
 }
 
@@ -319,6 +540,42 @@ fn editor_hover_pos(output: &egui::text_edit::TextEditOutput) -> Option<(u32, u3
     Some(row_and_column(&output.galley.job.text, hover_cursor.index))
 }
 
+/// Shows the type and documentation of the builtin item under the text cursor,
+/// so the standard library can be explored without leaving the editor.
+fn show_builtin_doc_popup(
+    ui: &egui::Ui,
+    checked: &CheckedWorkspace,
+    file: &FileName,
+    (row, column): (u32, u32),
+    cursor_rect: egui::Rect,
+) {
+    let Some(info) = checked.hover_at(file, row, column) else {
+        return;
+    };
+    let is_builtin = info
+        .global_name()
+        .is_some_and(|name| matches!(name.module.package, PackageId::Builtin(_)));
+    if !is_builtin {
+        return;
+    }
+
+    let signature = checked.render_hover_signature_in_file(file, &info);
+    egui::Area::new(ui.id().with("builtin_doc_popup"))
+        .order(egui::Order::Tooltip)
+        .fixed_pos(cursor_rect.left_bottom() + egui::vec2(0.0, 4.0))
+        .interactable(false)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(480.0);
+                ui.label(RichText::new(signature).code());
+                if let Some(doc) = info.doc() {
+                    ui.separator();
+                    ui.label(doc.markdown.as_str());
+                }
+            });
+        });
+}
+
 fn show_toolbar(ui: &mut egui::Ui, add_contents: impl FnOnce(&mut egui::Ui)) {
     let previous_item_spacing = ui.spacing().item_spacing;
     ui.spacing_mut().item_spacing.y = 0.0;
@@ -508,7 +765,9 @@ impl Playground {
                     .checkbox(&mut do_reload, egui::RichText::new("Reload").strong())
                     .clicked()
                 {
-                    self.sources.set_active_reload_enabled(do_reload);
+                    let ctx = ui.ctx().clone();
+                    self.sources
+                        .set_active_reload_enabled(do_reload, move || ctx.request_repaint());
                     ui.close();
                 }
             }
@@ -541,6 +800,31 @@ impl Playground {
                             ui.close();
                         }
                     }
+
+                    if self.sources.supports_scratch() {
+                        ui.separator();
+                        if ui
+                            .button(RichText::new("New scratch...").strong())
+                            .clicked()
+                        {
+                            self.new_scratch = Some(NewScratchDialog::default());
+                            ui.close();
+                        }
+                        if self.sources.is_active_scratch()
+                            && ui
+                                .button(RichText::new("Delete scratch").strong())
+                                .clicked()
+                        {
+                            self.sources.remove_active_scratch();
+                            ui.close();
+                        }
+                        if self.sources.is_active_pinned()
+                            && ui.button(RichText::new("Unpin").strong()).clicked()
+                        {
+                            self.sources.unpin_active();
+                            ui.close();
+                        }
+                    }
                 });
                 response
             })
@@ -553,6 +837,139 @@ impl Playground {
         self.sources.set_active(index);
     }
 
+    /// Shows a match of a search, selected in the editor.
+    fn jump_to(&mut self, jump: SearchJump) {
+        let Some((start, end)) = jump.span.points() else {
+            return;
+        };
+        self.switch_to_source(jump.buffer);
+        let source = self.sources.active_source();
+        let char_index = |offset: u32| source[..offset as usize].chars().count();
+        self.pending_selection = Some((char_index(start.offset), char_index(end.offset)));
+    }
+
+    fn show_new_scratch_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.new_scratch else {
+            return;
+        };
+        let mut create = false;
+        let mut cancel = false;
+
+        egui::Window::new("New scratch program")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Name:");
+                let response = ui.text_edit_singleline(&mut dialog.name);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    create = true;
+                }
+                if let Some(error) = &dialog.error {
+                    ui.label(RichText::new(error).color(red()));
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button(RichText::new("Create").strong()).clicked() {
+                        create = true;
+                    }
+                    if ui.button(RichText::new("Cancel").strong()).clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if create {
+            match self.sources.add_scratch(dialog.name.trim()) {
+                Ok(()) => self.new_scratch = None,
+                Err(message) => dialog.error = Some(message),
+            }
+        } else if cancel {
+            self.new_scratch = None;
+        }
+    }
+
+    fn show_library(&mut self, ctx: &egui::Context) {
+        let file = self.active_file_name();
+        if let Some(name) = self.library.show(ctx, self.build.checked(), &file) {
+            self.insert_at_cursor(&name);
+        }
+    }
+
+    fn show_history(&mut self, ctx: &egui::Context) {
+        let file = self.active_file_name();
+        let source = self.sources.active_source();
+        let editable = *self.built_code == *source && self.build.well_typed().is_some();
+        let pinnable = self.sources.supports_scratch() && !self.sources.is_active_pinned();
+        let mut pinned = None;
+        let edited = match self.history.show(ctx, &file, editable, pinnable) {
+            Some(HistoryAction::Retract(name)) => self.history.store.retract(&file, source, &name),
+            Some(HistoryAction::Restore(name, hash)) => {
+                self.history.store.restore(&file, source, &name, hash)
+            }
+            Some(HistoryAction::Pin(name)) => {
+                pinned = self
+                    .build
+                    .well_typed()
+                    .and_then(|checked| self.history.store.pin(&checked, &file, source, &name))
+                    .map(|part| (name, part));
+                None
+            }
+            None => None,
+        };
+        if let Some(edited) = edited {
+            *self.sources.active_source_mut() = edited;
+            self.recompile();
+        }
+        if let Some((name, part)) = pinned
+            && self.sources.pin(&name, &part).is_ok()
+        {
+            self.recompile();
+        }
+    }
+
+    fn insert_at_cursor(&mut self, text: &str) {
+        let source = self.sources.active_source_mut();
+        let offset = source
+            .char_indices()
+            .nth(self.cursor_index)
+            .map_or(source.len(), |(offset, _)| offset);
+        source.insert_str(offset, text);
+        self.cursor_index += text.chars().count();
+    }
+
+    fn insert_at_offset(&mut self, offset: usize, text: &str) {
+        let source = self.sources.active_source_mut();
+        let index = source[..offset].chars().count();
+        source.insert_str(offset, text);
+        if index <= self.cursor_index {
+            self.cursor_index += text.chars().count();
+        }
+    }
+
+    /// A `.case` skeleton for the value under the text cursor, with the byte
+    /// offset right after the value, where it goes. Only offered while the
+    /// source is what was last built, so that the positions still line up.
+    fn case_skeleton_at_cursor(&self) -> Option<(usize, String)> {
+        let checked = self.build.checked()?;
+        let source = self.sources.active_source();
+        if *self.built_code != *source {
+            return None;
+        }
+        let (row, column) = self.cursor_pos;
+        let line = source.lines().nth(row as usize)?;
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let (span, skeleton) = checked.semantic_model().case_skeleton_at(
+            &self.active_file_name(),
+            row,
+            column,
+            indent,
+        )?;
+        let (_, end) = span.points()?;
+        Some((end.offset as usize, skeleton))
+    }
+
     #[cfg(not(target_family = "wasm"))]
     fn open_file(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_file() {
@@ -574,43 +991,17 @@ impl Playground {
         }
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    fn show_open_error_dialog(&mut self, ctx: &egui::Context) {
-        let Some(message) = self.open_error.clone() else {
-            return;
-        };
-        let mut close = false;
-
-        egui::Window::new("Could not open file")
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .show(ctx, |ui| {
-                ui.label(message);
-                ui.add_space(8.0);
-                if ui.button(egui::RichText::new("OK").strong()).clicked() {
-                    close = true;
-                }
-            });
-
-        if close {
-            self.open_error = None;
-        }
-    }
-
     #[cfg(not(target_family = "wasm"))]
     fn clear_build_and_interaction(&mut self) {
         self.cancel_interaction();
         self.build = BuildResult::None;
         self.built_code = Arc::from("");
+        self.built_fingerprint = None;
     }
 
     #[cfg(not(target_family = "wasm"))]
     fn cancel_interaction(&mut self) {
-        if let Some(cancel_token) = self.cancel_token.take() {
-            cancel_token.cancel();
-        }
-        self.element = None;
+        self.run = None;
     }
 
     fn get_theme(&self, ui: &egui::Ui) -> ColorTheme {
@@ -628,26 +1019,107 @@ impl Playground {
     }
 
     fn recompile(&mut self) {
-        self.build = match self.sources.kind() {
-            SourceSetKind::BundledExamples => BuildResult::from_loaded_package(
-                self.sources.loaded_files(),
-                SourceSet::bundled_package_id(),
-                self.max_interactions,
-            ),
+        match self.build_job() {
+            Some(job) => self.finish_build(job()),
+            None => self.built_code = Arc::from(self.sources.active_source()),
+        }
+    }
+
+    /// Builds the sources once they've stopped changing for a moment, so
+    /// that they're ready to run without waiting for "Compile".
+    fn compile_when_idle(&mut self, ctx: &egui::Context) {
+        let fingerprint = self.sources.fingerprint();
+        let mut background = std::mem::take(&mut self.background);
+        let build = background.poll(ctx, fingerprint, self.built_fingerprint, || {
+            self.build_job()
+        });
+        self.background = background;
+        if let Some(build) = build {
+            self.finish_build(build);
             #[cfg(not(target_family = "wasm"))]
-            SourceSetKind::DiskPackage => {
-                let Some(active_path) = self.sources.active_disk_path() else {
-                    self.built_code = Arc::from(self.sources.active_source());
-                    return;
-                };
-                BuildResult::from_package_with_overrides(
-                    active_path,
-                    self.sources.source_overrides(),
-                    self.max_interactions,
-                )
+            if std::mem::take(&mut self.restart_on_build) {
+                self.restart_run(ctx);
             }
+        }
+    }
+
+    /// The setup the next run starts with.
+    fn run_setup(&self) -> Setup {
+        Setup::new(Environment::new())
+            .with_profiling(self.profile_runs)
+            .with_config(Arc::clone(self.config.config()))
+            .with_assets(Arc::clone(&self.assets))
+    }
+
+    /// Starts the run over on the current build, replaying what was given
+    /// to it, unless it was stopped or the build failed.
+    #[cfg(not(target_family = "wasm"))]
+    fn restart_run(&mut self, ctx: &egui::Context) {
+        let (Some(checked), Some(rt_compiled)) =
+            (self.build.well_typed(), self.build.rt_compiled())
+        else {
+            return;
+        };
+        let setup = self.run_setup();
+        let Some(run) = &mut self.run else {
+            return;
         };
+        if run.status() == RunStatus::Cancelled {
+            return;
+        }
+        let display_scope = checked
+            .workspace()
+            .import_scope(&self.sources.active_file_name())
+            .cloned();
+        if let Err(error) = run.restart(setup, checked, rt_compiled, display_scope, ctx) {
+            eprintln!("Failed to restart the run: {error}");
+        }
+    }
+
+    /// Building the sources as they are now, to do here or elsewhere.
+    fn build_job(&self) -> Option<BuildJob> {
+        let max_interactions = self.max_interactions;
+        match self.sources.kind() {
+            SourceSetKind::BundledExamples => {
+                let files = self.sources.loaded_files();
+                Some(Box::new(move || {
+                    BuildResult::from_loaded_package(
+                        files,
+                        SourceSet::bundled_package_id(),
+                        max_interactions,
+                    )
+                }))
+            }
+            #[cfg(not(target_family = "wasm"))]
+            SourceSetKind::DiskPackage => {
+                let active_path = self.sources.active_disk_path()?.to_path_buf();
+                let overrides = self.sources.source_overrides();
+                Some(Box::new(move || {
+                    BuildResult::from_package_with_overrides(
+                        &active_path,
+                        overrides,
+                        max_interactions,
+                    )
+                }))
+            }
+        }
+    }
+
+    /// Shows `build`, of the sources as they are now.
+    fn finish_build(&mut self, build: BuildResult) {
+        self.build = build;
         self.built_code = Arc::from(self.sources.active_source());
+        self.built_fingerprint = Some(self.sources.fingerprint());
+        #[cfg(not(target_family = "wasm"))]
+        match self.sources.load_assets() {
+            Ok(assets) => self.assets = Arc::new(assets),
+            Err(error) => eprintln!("{error}"),
+        }
+        if let Some(checked) = self.build.well_typed() {
+            self.history
+                .store
+                .record_build(&checked, &self.active_file_name(), &self.built_code);
+        }
     }
 
     fn show_interaction(&mut self, ui: &mut egui::Ui) {
@@ -663,9 +1135,9 @@ impl Playground {
                             (self.build.checked(), self.build.rt_compiled())
                         {
                             let active_file = self.active_file_name();
-                            let spawner = self.spawner.clone();
-                            let cancel_token = &mut self.cancel_token;
-                            let element = &mut self.element;
+                            let setup = self.run_setup();
+                            let run = &mut self.run;
+                            let renderers = &self.renderers;
                             let name_to_ty = &rt_compiled.name_to_ty;
                             ui.scope(|ui| {
                                 tint_button_visuals(
@@ -680,9 +1152,9 @@ impl Playground {
                                 .ui(ui, |ui| {
                                     egui::ScrollArea::vertical().show(ui, |ui| {
                                         run_menu::show_run_menu(
-                                            spawner.clone(),
-                                            cancel_token,
-                                            element,
+                                            run,
+                                            renderers,
+                                            &setup,
                                             ui,
                                             &active_file,
                                             checked.clone(),
@@ -694,10 +1166,62 @@ impl Playground {
                             });
                         }
 
+                        if self
+                            .run
+                            .as_ref()
+                            .is_some_and(|run| run.status() == RunStatus::Running)
+                            && ui
+                                .button("Stop")
+                                .on_hover_text("Discard the running definition")
+                                .clicked()
+                        {
+                            self.run = None;
+                        }
+
+                        if let Some(run) = &mut self.run {
+                            run.set_undo_limit(self.undo_limit);
+                            let undo_steps = run.undo_steps();
+                            let response = ui
+                                .add_enabled(undo_steps > 0, egui::Button::new("Undo"))
+                                .on_hover_text(format!(
+                                    "Run again without the last input ({undo_steps} left)"
+                                ))
+                                .on_disabled_hover_text(
+                                    "There is no input to undo, or it came from a custom editor",
+                                );
+                            response.context_menu(|ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.undo_limit)
+                                        .range(1..=1000)
+                                        .prefix("Keep ")
+                                        .suffix(" inputs"),
+                                );
+                            });
+                            if response.clicked()
+                                && let Err(error) = run.undo(ui.ctx())
+                            {
+                                eprintln!("Failed to undo: {error}");
+                            }
+                        }
+
                         ui.checkbox(
                             &mut self.show_compiled,
                             egui::RichText::new("Show compiled"),
                         );
+
+                        ui.checkbox(&mut self.profile_runs, "Profile")
+                            .on_hover_text(
+                                "Show which definitions the next runs spend their rewrites in",
+                            );
+                    }
+
+                    if self.build.error().is_some()
+                        && ui
+                            .checkbox(&mut self.full_types, "Full types")
+                            .on_hover_text("Show types in errors in full, instead of truncated")
+                            .changed()
+                    {
+                        set_full_types_in_reports(self.full_types);
                     }
                 });
             });
@@ -730,8 +1254,37 @@ impl Playground {
                     }
 
                     if !self.show_compiled {
-                        if let Some(element) = &mut self.element {
-                            element.lock().unwrap().show(ui);
+                        if let Some(run) = &self.run {
+                            if let RunStatus::Crashed(message) = run.status() {
+                                ui.label(
+                                    egui::RichText::new(format!("This run crashed: {message}"))
+                                        .color(red())
+                                        .code(),
+                                );
+                            }
+                            // A run that panicked while showing its element may
+                            // have poisoned it; only its own display is lost.
+                            let Ok(mut element) = run.element().lock() else {
+                                return;
+                            };
+                            ui.horizontal(|ui| {
+                                for format in [DiagramFormat::Mermaid, DiagramFormat::PlantUml] {
+                                    if ui
+                                        .small_button(format!("Copy as {}", format.name()))
+                                        .on_hover_text("Copy the interaction as a sequence diagram")
+                                        .clicked()
+                                    {
+                                        ui.ctx().copy_text(sequence_diagram(&element, format));
+                                    }
+                                }
+                            });
+                            element.show(ui);
+                            drop(element);
+
+                            if let Some(profile) = run.profile() {
+                                ui.separator();
+                                show_profile(ui, &profile);
+                            }
                         }
                     }
                 });
@@ -740,6 +1293,62 @@ impl Playground {
     }
 }
 
+/// A window showing `message` until it's dismissed, which clears it.
+#[cfg(not(target_family = "wasm"))]
+fn show_error_dialog(ctx: &egui::Context, title: &str, message: &mut Option<String>) {
+    let Some(text) = message.clone() else {
+        return;
+    };
+    let mut close = false;
+
+    egui::Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(text);
+            ui.add_space(8.0);
+            if ui.button(egui::RichText::new("OK").strong()).clicked() {
+                close = true;
+            }
+        });
+
+    if close {
+        *message = None;
+    }
+}
+
+/// The stats of a finished run: how many rewrites it took, and the
+/// definitions it spent the most of them in.
+fn show_profile(ui: &mut egui::Ui, profile: &Profile) {
+    egui::CollapsingHeader::new(format!("Profile: {} rewrites", profile.total))
+        .default_open(true)
+        .show(ui, |ui| {
+            for (name, count) in profile.definitions.iter().take(10) {
+                ui.label(
+                    RichText::new(format!(
+                        "{:5.1}% in {name} ({count})",
+                        profile.percent(*count)
+                    ))
+                    .code(),
+                );
+            }
+            let shown: u64 = profile
+                .definitions
+                .iter()
+                .take(10)
+                .map(|(_, count)| count)
+                .sum();
+            let rest = profile.total - shown;
+            if rest > 0 {
+                ui.label(
+                    RichText::new(format!("{:5.1}% elsewhere ({rest})", profile.percent(rest)))
+                        .code(),
+                );
+            }
+        });
+}
+
 #[cfg(target_family = "wasm")]
 async fn read_web_clipboard_text() -> Option<String> {
     let window = web_sys::window()?;
@@ -765,6 +1374,7 @@ fn par_syntax() -> Syntax {
             "dec",
             "def",
             "type",
+            "derive",
             "chan",
             "dual",
             "let",
@@ -774,6 +1384,8 @@ fn par_syntax() -> Syntax {
             "begin",
             "unfounded",
             "loop",
+            "macro",
+            "edition",
             "module",
             "import",
             "as",
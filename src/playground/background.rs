@@ -0,0 +1,94 @@
+//! Compiling in the background once the user pauses typing, so that running
+//! right after an edit finds the build already done.
+//!
+//! Sources are told apart by a fingerprint of every buffer. Once they stay
+//! the same for [`IDLE_DELAY`], and the last build wasn't of them, a build
+//! of them starts on a thread of its own. It's handed over when it finishes,
+//! unless the sources changed in the meantime. On the web there are no
+//! threads, so the build runs right away instead, still only once idle.
+
+#[cfg(not(target_family = "wasm"))]
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+use eframe::egui;
+
+use super::build::BuildResult;
+
+/// How long the sources have to stay the same before they're built.
+const IDLE_DELAY: f64 = 0.6;
+
+pub(super) type BuildJob = Box<dyn FnOnce() -> BuildResult + Send>;
+
+#[derive(Default)]
+pub(super) struct BackgroundBuild {
+    /// The fingerprint of the sources, and when they last changed, in
+    /// seconds of [`egui::InputState::time`].
+    watched: Option<(u64, f64)>,
+    /// The build on its way, with the fingerprint of what it's building.
+    #[cfg(not(target_family = "wasm"))]
+    in_flight: Option<(u64, Receiver<BuildResult>)>,
+}
+
+impl BackgroundBuild {
+    /// Called every frame with the fingerprint of the sources, and that of
+    /// the sources of the build shown. Starts a build with `job` once the
+    /// sources have been idle long enough, and returns one that finished for
+    /// the sources as they are.
+    pub(super) fn poll(
+        &mut self,
+        ctx: &egui::Context,
+        sources: u64,
+        built: Option<u64>,
+        job: impl FnOnce() -> Option<BuildJob>,
+    ) -> Option<BuildResult> {
+        let now = ctx.input(|input| input.time);
+        if self.watched.is_none_or(|(watched, _)| watched != sources) {
+            self.watched = Some((sources, now));
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        if let Some((fingerprint, finished)) = &self.in_flight {
+            match finished.try_recv() {
+                Ok(build) => {
+                    let fresh = *fingerprint == sources;
+                    self.in_flight = None;
+                    if fresh {
+                        return Some(build);
+                    }
+                }
+                // It'll ask for a repaint once it's done.
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => self.in_flight = None,
+            }
+        }
+
+        if built == Some(sources) {
+            return None;
+        }
+        let idle = now - self.watched.map_or(now, |(_, changed)| changed);
+        if idle < IDLE_DELAY {
+            ctx.request_repaint_after(Duration::from_secs_f64(IDLE_DELAY - idle));
+            return None;
+        }
+        let job = job()?;
+        self.start(ctx, sources, job)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn start(&mut self, ctx: &egui::Context, sources: u64, job: BuildJob) -> Option<BuildResult> {
+        let (send, finished) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = send.send(job());
+            ctx.request_repaint();
+        });
+        self.in_flight = Some((sources, finished));
+        None
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn start(&mut self, _ctx: &egui::Context, _sources: u64, job: BuildJob) -> Option<BuildResult> {
+        Some(job())
+    }
+}
@@ -129,6 +129,17 @@ impl BuildResult {
         }
     }
 
+    /// The checked workspace, if it has no type errors.
+    pub(super) fn well_typed(&self) -> Option<Arc<CheckedWorkspace>> {
+        match self {
+            Self::InetError { checked, .. } | Self::Ok { checked, .. } => Some(Arc::clone(checked)),
+            Self::None
+            | Self::DiscoveryError { .. }
+            | Self::WorkspaceError { .. }
+            | Self::TypeError { .. } => None,
+        }
+    }
+
     pub(super) fn rt_compiled(&self) -> Option<&Compiled<Linked>> {
         match self {
             Self::Ok { rt_compiled, .. } => Some(rt_compiled),
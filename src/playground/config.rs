@@ -0,0 +1,67 @@
+//! The "Config" panel: the configuration values programs read through
+//! `@core/Config`, one `key=value` per line.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, RichText};
+use par_runtime::config::{self, Config};
+
+#[derive(Default)]
+pub(super) struct ConfigPanel {
+    pub(super) open: bool,
+    text: String,
+    /// The values in `text`, as of the last time it parsed.
+    config: Arc<Config>,
+    error: Option<String>,
+}
+
+impl ConfigPanel {
+    /// The values for runs started now to read.
+    pub(super) fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+
+    pub(super) fn show(&mut self, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Configuration")
+            .open(&mut open)
+            .default_size([360.0, 240.0])
+            .show(ctx, |ui| {
+                ui.label("One `key=value` per line, read by runs started from now on.");
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.text)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .hint_text("greeting=hello"),
+                );
+                if response.changed() {
+                    match parse_config(&self.text) {
+                        Ok(parsed) => {
+                            self.config = Arc::new(parsed);
+                            self.error = None;
+                        }
+                        Err(error) => self.error = Some(error),
+                    }
+                }
+                if let Some(error) = &self.error {
+                    ui.label(RichText::new(error).color(ui.visuals().error_fg_color));
+                }
+            });
+        self.open = open;
+    }
+}
+
+/// The values in `text`, skipping blank lines.
+fn parse_config(text: &str) -> Result<Config, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            config::parse_entry(line).map_err(|error| format!("Line {}: {error}", index + 1))
+        })
+        .collect()
+}
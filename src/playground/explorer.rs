@@ -0,0 +1,249 @@
+//! The "Explore" panel: walks the protocol of the type under the text cursor,
+//! one step at a time, without any code implementing it.
+//!
+//! The other side is a stand-in that echoes: whenever the protocol has it
+//! send a value, it sends back the last value it was given. Branches are
+//! picked by hand on both sides, and values are plain text, not checked
+//! against their types.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, RichText};
+use par_core::frontend::language::{LocalName, Universal};
+use par_core::frontend::{ProtocolStep, Type};
+use par_core::source::FileName;
+use par_core::workspace::CheckedWorkspace;
+
+#[derive(Default)]
+pub(super) struct ExplorerPanel {
+    pub(super) open: bool,
+    /// Where the walk is at, or `None` before it starts and after it ends.
+    current: Option<Type<Universal>>,
+    transcript: Vec<Message>,
+    /// The last value sent to the stand-in, for it to echo.
+    echo: Option<String>,
+    input: String,
+}
+
+struct Message {
+    /// Sent by the user, rather than by the stand-in.
+    outgoing: bool,
+    text: String,
+}
+
+/// What a step of the walk did.
+struct Step {
+    message: Message,
+    then: Option<Type<Universal>>,
+}
+
+impl ExplorerPanel {
+    pub(super) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        checked: Option<Arc<CheckedWorkspace>>,
+        file: &FileName,
+        (row, column): (u32, u32),
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Explore protocol")
+            .open(&mut open)
+            .default_size([420.0, 420.0])
+            .show(ctx, |ui| {
+                let Some(checked) = checked else {
+                    ui.label("Compile the program to explore protocols.");
+                    return;
+                };
+                let model = checked.semantic_model();
+
+                let at_cursor = model.type_at(file, row, column);
+                if ui
+                    .add_enabled(at_cursor.is_some(), egui::Button::new("Start from cursor"))
+                    .on_disabled_hover_text("Put the cursor on something that has a type")
+                    .clicked()
+                    && let Some(typ) = at_cursor
+                {
+                    self.transcript.clear();
+                    self.echo = None;
+                    self.input.clear();
+                    self.current = Some(typ);
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .max_height(200.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for message in &self.transcript {
+                            let (arrow, side) = if message.outgoing {
+                                ("→", "you")
+                            } else {
+                                ("←", "it")
+                            };
+                            ui.label(
+                                RichText::new(format!("{arrow} {side}: {}", message.text)).code(),
+                            );
+                        }
+                    });
+                ui.separator();
+
+                let Some(typ) = self.current.clone() else {
+                    ui.label(if self.transcript.is_empty() {
+                        "Put the cursor on something that has a type, and start."
+                    } else {
+                        "The protocol is over."
+                    });
+                    return;
+                };
+                let render = |typ: &Type<Universal>| checked.render_type_in_file(file, typ, 0);
+                ui.label(RichText::new(render(&typ)).code());
+                ui.add_space(4.0);
+
+                let step = match model.protocol_step(&typ) {
+                    ProtocolStep::Choose(branches) => {
+                        ui.label("Choose a branch:");
+                        branch_buttons(ui, branches, true)
+                    }
+                    ProtocolStep::Signal(branches) => {
+                        ui.label("The other side picks a branch. Pick it for them:");
+                        branch_buttons(ui, branches, false)
+                    }
+                    ProtocolStep::Receive(value, then) => {
+                        ui.label(format!("Send a value of type {}:", render(&value)));
+                        self.send_input(ui, "Send").map(|text| {
+                            self.echo = Some(text.clone());
+                            Step {
+                                message: Message {
+                                    outgoing: true,
+                                    text: format!("({text})"),
+                                },
+                                then: Some(then),
+                            }
+                        })
+                    }
+                    ProtocolStep::Send(value, then) => {
+                        ui.label(format!(
+                            "The other side sends a value of type {}.",
+                            render(&value)
+                        ));
+                        ui.button("Receive").clicked().then(|| Step {
+                            message: Message {
+                                outgoing: false,
+                                text: format!("({})", self.echo_of(&render(&value))),
+                            },
+                            then: Some(then),
+                        })
+                    }
+                    ProtocolStep::ReceiveType(parameter, then) => {
+                        ui.label(format!("Send a type for `{}`:", parameter.name));
+                        self.send_input(ui, "Send type").map(|text| Step {
+                            message: Message {
+                                outgoing: true,
+                                text: format!("(type {text})"),
+                            },
+                            then: Some(then),
+                        })
+                    }
+                    ProtocolStep::SendType(parameter, then) => {
+                        ui.label(format!(
+                            "The other side sends a type for `{}`.",
+                            parameter.name
+                        ));
+                        ui.button("Receive type").clicked().then(|| Step {
+                            message: Message {
+                                outgoing: false,
+                                text: format!("(type {})", parameter.name),
+                            },
+                            then: Some(then),
+                        })
+                    }
+                    ProtocolStep::Close => {
+                        ui.label("The other side closes the channel.");
+                        ui.button("Finish").clicked().then(|| Step {
+                            message: Message {
+                                outgoing: false,
+                                text: String::from("!"),
+                            },
+                            then: None,
+                        })
+                    }
+                    ProtocolStep::Wait => {
+                        ui.label("Close the channel to finish.");
+                        ui.button("Close").clicked().then(|| Step {
+                            message: Message {
+                                outgoing: true,
+                                text: String::from("!"),
+                            },
+                            then: None,
+                        })
+                    }
+                    ProtocolStep::Opaque(value) => {
+                        ui.label("This has no steps of its own; the other side just sends it.");
+                        ui.button("Receive").clicked().then(|| Step {
+                            message: Message {
+                                outgoing: false,
+                                text: self.echo_of(&render(&value)),
+                            },
+                            then: None,
+                        })
+                    }
+                };
+
+                if let Some(Step { message, then }) = step {
+                    self.transcript.push(message);
+                    self.current = then;
+                }
+            });
+        self.open = open;
+    }
+
+    /// What the stand-in sends for a value of a type: the last value it was
+    /// given, or a placeholder naming the type.
+    fn echo_of(&self, typ: &str) -> String {
+        match &self.echo {
+            Some(echo) => echo.clone(),
+            None => format!("<{typ}>"),
+        }
+    }
+
+    /// A text field with a button sending what's in it.
+    fn send_input(&mut self, ui: &mut egui::Ui, button: &str) -> Option<String> {
+        ui.horizontal(|ui| {
+            let response = ui.text_edit_singleline(&mut self.input);
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let sent = ui
+                .add_enabled(!self.input.trim().is_empty(), egui::Button::new(button))
+                .clicked();
+            ((sent || entered) && !self.input.trim().is_empty())
+                .then(|| std::mem::take(&mut self.input).trim().to_owned())
+        })
+        .inner
+    }
+}
+
+fn branch_buttons(
+    ui: &mut egui::Ui,
+    branches: impl IntoIterator<Item = (LocalName, Type<Universal>)>,
+    outgoing: bool,
+) -> Option<Step> {
+    let mut picked = None;
+    ui.horizontal_wrapped(|ui| {
+        for (label, then) in branches {
+            if ui.button(format!(".{label}")).clicked() {
+                picked = Some(Step {
+                    message: Message {
+                        outgoing,
+                        text: format!(".{label}"),
+                    },
+                    then: Some(then),
+                });
+            }
+        }
+    });
+    picked
+}
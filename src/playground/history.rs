@@ -0,0 +1,675 @@
+//! The "History" panel: every version of the active file's definitions that
+//! passed type checking, stored by the hash of its content, so that versions
+//! can be compared, earlier ones restored, and definitions retracted.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use eframe::egui::{self, RichText};
+use par_core::frontend::DefinitionBody;
+use par_core::source::{FileName, Spanning};
+use par_core::workspace::CheckedWorkspace;
+
+use super::pinned::pinned_part_source;
+
+/// The hash of a definition's content: its declared type and its body, after
+/// lowering, so that changes to formatting and comments don't make new
+/// versions. Stable across runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct DefinitionHash(u64);
+
+impl DefinitionHash {
+    /// 64-bit FNV-1a.
+    fn of(content: &str) -> Self {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for byte in content.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Self(hash)
+    }
+
+    fn short(&self) -> String {
+        format!("#{:08x}", self.0 >> 32)
+    }
+}
+
+impl fmt::Display for DefinitionHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:016x}", self.0)
+    }
+}
+
+/// A version of a definition, as it was written when it was first seen.
+struct StoredDefinition {
+    declaration: Option<String>,
+    definition: String,
+}
+
+impl StoredDefinition {
+    fn source(&self) -> String {
+        match &self.declaration {
+            Some(declaration) => format!("{declaration}\n{}", self.definition),
+            None => self.definition.clone(),
+        }
+    }
+}
+
+/// A definition of the last recorded build, with where it is in its file.
+struct CurrentDefinition {
+    hash: DefinitionHash,
+    definition: Range<usize>,
+    /// A separate `dec`, if there is one.
+    declaration: Option<Range<usize>>,
+    /// Whether the type is declared, separately or as an annotation.
+    declared: bool,
+}
+
+/// The definitions of one file.
+#[derive(Default)]
+struct FileHistory {
+    /// The versions of each definition, by name, oldest first.
+    versions: BTreeMap<String, Vec<DefinitionHash>>,
+    /// The definitions of the last recorded build.
+    current: BTreeMap<String, CurrentDefinition>,
+}
+
+impl FileHistory {
+    /// The hash of the current version of `name`, if it's still defined.
+    fn current_hash(&self, name: &str) -> Option<DefinitionHash> {
+        self.current.get(name).map(|current| current.hash)
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.current
+            .get(name)
+            .is_some_and(|current| current.declared)
+    }
+}
+
+/// Definitions by content hash, and the versions each one has had, for one
+/// playground session.
+#[derive(Default)]
+pub(super) struct DefinitionStore {
+    definitions: HashMap<DefinitionHash, StoredDefinition>,
+    files: HashMap<FileName, FileHistory>,
+}
+
+impl DefinitionStore {
+    /// Records the definitions in `file` of a build that passed type
+    /// checking. `source` is the text of `file` that was built.
+    pub(super) fn record_build(
+        &mut self,
+        checked: &CheckedWorkspace,
+        file: &FileName,
+        source: &str,
+    ) {
+        let history = self.files.entry(file.clone()).or_default();
+        history.current.clear();
+
+        let lowered = checked.workspace().lowered_module();
+        let in_file = |span: &par_core::source::Span| {
+            let (start, end) = span.points()?;
+            (span.file().as_ref() == Some(file))
+                .then_some(start.offset as usize..end.offset as usize)
+                .filter(|range| source.get(range.clone()).is_some())
+        };
+
+        for definition in &lowered.definitions {
+            let Some(definition_range) = in_file(&definition.span) else {
+                continue;
+            };
+            let name = definition.name.primary.clone();
+            let declaration = lowered
+                .declarations
+                .iter()
+                .find(|declaration| declaration.name == definition.name);
+
+            let mut content = String::new();
+            if let Some(declaration) = declaration {
+                let typ = checked.render_type_in_file(file, &declaration.typ, 0);
+                let _ = writeln!(content, "dec {name} : {typ}");
+            }
+            let _ = write!(content, "def {name} = ");
+            match &definition.body {
+                DefinitionBody::Par(expression) => {
+                    let _ = expression.pretty(&mut content, 0);
+                }
+                DefinitionBody::External(_) => content.push_str("<external>"),
+            }
+            let hash = DefinitionHash::of(&content);
+
+            // A `dec` is separate unless it was written as the definition's
+            // annotation, in which case it has the same span.
+            let declaration_range = declaration
+                .and_then(|declaration| in_file(&declaration.span))
+                .filter(|range| *range != definition_range);
+            self.definitions
+                .entry(hash)
+                .or_insert_with(|| StoredDefinition {
+                    declaration: declaration_range
+                        .clone()
+                        .map(|range| source[range].to_owned()),
+                    definition: source[definition_range.clone()].to_owned(),
+                });
+
+            let versions = history.versions.entry(name.clone()).or_default();
+            if versions.last() != Some(&hash) {
+                versions.push(hash);
+            }
+            history.current.insert(
+                name,
+                CurrentDefinition {
+                    hash,
+                    definition: definition_range,
+                    declaration: declaration_range,
+                    declared: declaration.is_some(),
+                },
+            );
+        }
+    }
+
+    /// `source` without the definition of `name`, nor its `dec`. The source
+    /// must be the one last recorded for `file`.
+    pub(super) fn retract(&self, file: &FileName, source: &str, name: &str) -> Option<String> {
+        let current = self.files.get(file)?.current.get(name)?;
+        let mut ranges = vec![current.definition.clone()];
+        ranges.extend(current.declaration.clone());
+        ranges.sort_by_key(|range| std::cmp::Reverse(range.start));
+
+        let mut source = source.to_owned();
+        for range in ranges {
+            source.replace_range(range, "");
+        }
+        Some(source)
+    }
+
+    /// The current version of `name`, as a part of the pinned module, with
+    /// the imports of `file` and its declaration exported. Only declared
+    /// definitions can be pinned, since the declaration is written in terms of
+    /// those imports. The source must be the one last recorded for `file`,
+    /// and `checked` the build it was recorded from.
+    pub(super) fn pin(
+        &self,
+        checked: &CheckedWorkspace,
+        file: &FileName,
+        source: &str,
+        name: &str,
+    ) -> Option<String> {
+        let current = self.files.get(file)?.current.get(name)?;
+        let workspace = checked.workspace();
+        let lowered = workspace.lowered_module();
+        let definition = lowered.definitions.iter().find(|definition| {
+            definition.name.primary == name && definition.span.file().as_ref() == Some(file)
+        })?;
+        let declaration = lowered
+            .declarations
+            .iter()
+            .find(|declaration| declaration.name == definition.name);
+
+        let mut text = source.get(current.definition.clone())?.to_owned();
+        let declaration = match (&current.declaration, declaration) {
+            (Some(range), Some(_)) => {
+                let written = source.get(range.clone())?;
+                if written.starts_with("export") {
+                    written.to_owned()
+                } else {
+                    format!("export {written}")
+                }
+            }
+            (None, Some(declaration)) => {
+                // Written as the definition's annotation, which can't be
+                // exported, so it moves into a declaration of its own.
+                let (type_start, type_end) = declaration.typ.span().points()?;
+                let (type_start, type_end) = (type_start.offset as usize, type_end.offset as usize);
+                let start = current.definition.start;
+                let colon = start + source.get(start..type_start)?.rfind(':')?;
+                text = format!(
+                    "{}{}",
+                    source[start..colon].trim_end(),
+                    source.get(type_end..current.definition.end)?
+                );
+                format!("export dec {name} : {}", &source[type_start..type_end])
+            }
+            (_, None) => return None,
+        };
+
+        // Imports as written, with the aliases they were given.
+        let mut imports = Vec::<String>::new();
+        let scope = workspace.import_scope(file);
+        for (span, module) in workspace.import_spans().get(file).into_iter().flatten() {
+            let Some((start, end)) = span.points() else {
+                continue;
+            };
+            let Some(path) = source.get(start.offset as usize..end.offset as usize) else {
+                continue;
+            };
+            let alias = scope.and_then(|scope| {
+                scope
+                    .aliases
+                    .iter()
+                    .find(|(_, aliased)| *aliased == module)
+                    .map(|(alias, _)| alias)
+            });
+            let import = match alias {
+                Some(alias) if *alias != module.module => format!("{path} as {alias}"),
+                _ => path.to_owned(),
+            };
+            if !imports.contains(&import) {
+                imports.push(import);
+            }
+        }
+
+        Some(pinned_part_source(&imports, &declaration, &text))
+    }
+
+    /// `source` with the definition of `name` replaced by the version `hash`,
+    /// or with that version appended, if `name` has been retracted. The
+    /// source must be the one last recorded for `file`.
+    pub(super) fn restore(
+        &self,
+        file: &FileName,
+        source: &str,
+        name: &str,
+        hash: DefinitionHash,
+    ) -> Option<String> {
+        let stored = self.definitions.get(&hash)?;
+        let mut source = source.to_owned();
+        match self.files.get(file)?.current.get(name) {
+            Some(current) => {
+                let mut edits = vec![(current.definition.clone(), stored.definition.as_str())];
+                if let (Some(range), Some(declaration)) =
+                    (&current.declaration, &stored.declaration)
+                {
+                    edits.push((range.clone(), declaration.as_str()));
+                }
+                edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+                for (range, text) in edits {
+                    source.replace_range(range, text);
+                }
+            }
+            None => {
+                if !source.ends_with('\n') {
+                    source.push('\n');
+                }
+                source.push('\n');
+                source.push_str(&stored.source());
+                source.push('\n');
+            }
+        }
+        Some(source)
+    }
+}
+
+/// One line of a diff between two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The lines of `old` and `new`, lined up along their longest common
+/// subsequence.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+
+    // `common[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`.
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        } else {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        }
+    }
+    lines
+}
+
+/// A change to the active source, asked for in the panel.
+pub(super) enum HistoryAction {
+    Retract(String),
+    Restore(String, DefinitionHash),
+    Pin(String),
+}
+
+#[derive(Default)]
+pub(super) struct HistoryPanel {
+    pub(super) open: bool,
+    pub(super) store: DefinitionStore,
+    selected: Option<String>,
+    /// The version compared with the one before it.
+    compared: Option<DefinitionHash>,
+}
+
+impl HistoryPanel {
+    /// Shows the panel, returning a change to make to the source, if one was
+    /// asked for. Changes are only offered if `editable`, that is, if the
+    /// source is the one last recorded, and pinning only if `pinnable`.
+    pub(super) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        file: &FileName,
+        editable: bool,
+        pinnable: bool,
+    ) -> Option<HistoryAction> {
+        if !self.open {
+            return None;
+        }
+
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_size([520.0, 480.0])
+            .show(ctx, |ui| {
+                let store = &self.store;
+                let Some(history) = store
+                    .files
+                    .get(file)
+                    .filter(|history| !history.versions.is_empty())
+                else {
+                    ui.label("Compile the program without type errors to record its definitions.");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label("Definition:");
+                    egui::ComboBox::from_id_salt("history_definition")
+                        .selected_text(self.selected.as_deref().unwrap_or("—"))
+                        .show_ui(ui, |ui| {
+                            for name in history.versions.keys() {
+                                let mut label = name.clone();
+                                if history.current_hash(name).is_none() {
+                                    label.push_str(" (retracted)");
+                                }
+                                if ui
+                                    .selectable_label(self.selected.as_ref() == Some(name), label)
+                                    .clicked()
+                                {
+                                    self.selected = Some(name.clone());
+                                    self.compared = None;
+                                }
+                            }
+                        });
+                });
+                ui.separator();
+
+                let Some(name) = self.selected.clone() else {
+                    return;
+                };
+                let Some(versions) = history.versions.get(&name) else {
+                    return;
+                };
+                let current = history.current_hash(&name);
+
+                if current.is_some() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(editable, egui::Button::new("Retract"))
+                            .on_hover_text(
+                                "Remove the definition, and its declaration, from the file",
+                            )
+                            .on_disabled_hover_text("Compile the program first")
+                            .clicked()
+                        {
+                            action = Some(HistoryAction::Retract(name.clone()));
+                        }
+                        let declared = history.is_declared(&name);
+                        if pinnable
+                            && ui
+                                .add_enabled(editable && declared, egui::Button::new("Pin"))
+                                .on_hover_text(
+                                    "Keep this version in the `Pinned` module, \
+                                     across playground sessions",
+                                )
+                                .on_disabled_hover_text(if declared {
+                                    "Compile the program first"
+                                } else {
+                                    "Declare the definition's type first"
+                                })
+                                .clicked()
+                        {
+                            action = Some(HistoryAction::Pin(name.clone()));
+                        }
+                    });
+                }
+
+                egui::ScrollArea::vertical()
+                    .id_salt("history_versions")
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        for (index, hash) in versions.iter().enumerate().rev() {
+                            ui.horizontal(|ui| {
+                                let mut label = format!("{} {}", index + 1, hash.short());
+                                if current == Some(*hash) {
+                                    label.push_str(" (current)");
+                                }
+                                if ui
+                                    .selectable_label(self.compared == Some(*hash), label)
+                                    .on_hover_text(hash.to_string())
+                                    .clicked()
+                                {
+                                    self.compared = Some(*hash);
+                                }
+                                if current != Some(*hash)
+                                    && ui
+                                        .add_enabled(editable, egui::Button::new("Restore"))
+                                        .on_disabled_hover_text("Compile the program first")
+                                        .clicked()
+                                {
+                                    action = Some(HistoryAction::Restore(name.clone(), *hash));
+                                }
+                            });
+                        }
+                    });
+                ui.separator();
+
+                let Some(compared) = self.compared else {
+                    ui.label("Select a version to see what it changed.");
+                    return;
+                };
+                let Some(position) = versions.iter().position(|hash| *hash == compared) else {
+                    return;
+                };
+                let new = store.definitions[&compared].source();
+                let old = match position.checked_sub(1) {
+                    Some(previous) => store.definitions[&versions[previous]].source(),
+                    None => String::new(),
+                };
+                egui::ScrollArea::vertical()
+                    .id_salt("history_diff")
+                    .show(ui, |ui| {
+                        for line in diff_lines(&old, &new) {
+                            let text = match line {
+                                DiffLine::Same(text) => RichText::new(format!("  {text}")),
+                                DiffLine::Removed(text) => RichText::new(format!("- {text}"))
+                                    .color(egui::Color32::from_hex("#DE3C4B").unwrap()),
+                                DiffLine::Added(text) => RichText::new(format!("+ {text}"))
+                                    .color(egui::Color32::from_hex("#7ac74f").unwrap()),
+                            };
+                            ui.label(text.code());
+                        }
+                    });
+            });
+        self.open = open;
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_core::pipeline::{Pipeline, SourcePackage, Stage};
+    use par_core::workspace::LoadedPackageFile;
+    use par_runtime::pkgid::PackageId;
+
+    use super::*;
+
+    const FIRST: &str = "module Test
+
+type Bool = either { .true!, .false! }
+
+dec Flip : [Bool] Bool
+def Flip = [b] b.case {
+  .true! => .false!,
+  .false! => .true!,
+}
+
+def Yes: Bool = .true!
+";
+
+    /// `FIRST` with its formatting and comments changed, but not its content.
+    const REFORMATTED: &str = "module Test
+
+type Bool = either { .true!, .false! }
+
+// Swaps the two.
+dec Flip : [Bool] Bool
+def Flip = [b] b.case { .true! => .false!, .false! => .true! }
+
+def Yes: Bool =
+  .true!
+";
+
+    /// `FIRST` with `Flip` changed.
+    const SECOND: &str = "module Test
+
+type Bool = either { .true!, .false! }
+
+dec Flip : [Bool] Bool
+def Flip = [b] b.case {
+  .true! => .true!,
+  .false! => .false!,
+}
+
+def Yes: Bool = .true!
+";
+
+    fn file() -> FileName {
+        FileName::from("test/Test.par")
+    }
+
+    fn check(source: &str) -> CheckedWorkspace {
+        let id = PackageId::Special(literal!("test"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: file(),
+                    relative_path_from_src: PathBuf::from("Test.par"),
+                    source: source.to_owned(),
+                }],
+            }],
+        );
+        pipeline
+            .run_until(Stage::TypeCheck)
+            .expect("checked program");
+        let artifacts = pipeline.into_artifacts();
+        assert!(
+            artifacts.type_errors.is_empty(),
+            "{:?}",
+            artifacts.type_errors
+        );
+        artifacts.checked.expect("checked workspace")
+    }
+
+    fn record(store: &mut DefinitionStore, source: &str) {
+        store.record_build(&check(source), &file(), source);
+    }
+
+    fn versions<'a>(store: &'a DefinitionStore, name: &str) -> &'a [DefinitionHash] {
+        &store.files[&file()].versions[name]
+    }
+
+    #[test]
+    fn only_changes_to_the_content_make_new_versions() {
+        let mut store = DefinitionStore::default();
+        record(&mut store, FIRST);
+        record(&mut store, REFORMATTED);
+        assert_eq!(versions(&store, "Flip").len(), 1);
+        assert_eq!(versions(&store, "Yes").len(), 1);
+
+        record(&mut store, SECOND);
+        assert_eq!(versions(&store, "Flip").len(), 2);
+        assert_eq!(versions(&store, "Yes").len(), 1);
+    }
+
+    #[test]
+    fn earlier_versions_are_restored_as_they_were_written() {
+        let mut store = DefinitionStore::default();
+        record(&mut store, REFORMATTED);
+        record(&mut store, SECOND);
+        let first = versions(&store, "Flip")[0];
+
+        let restored = store
+            .restore(&file(), SECOND, "Flip", first)
+            .expect("restored source");
+        assert_eq!(
+            restored,
+            SECOND.replace(
+                "def Flip = [b] b.case {\n  .true! => .true!,\n  .false! => .false!,\n}",
+                "def Flip = [b] b.case { .true! => .false!, .false! => .true! }",
+            )
+        );
+    }
+
+    #[test]
+    fn retracted_definitions_can_be_restored() {
+        let mut store = DefinitionStore::default();
+        record(&mut store, FIRST);
+        let retracted = store
+            .retract(&file(), FIRST, "Flip")
+            .expect("retracted source");
+        assert!(!retracted.contains("Flip"), "{retracted}");
+
+        record(&mut store, &retracted);
+        assert_eq!(store.files[&file()].current_hash("Flip"), None);
+        let hash = versions(&store, "Flip")[0];
+        let restored = store
+            .restore(&file(), &retracted, "Flip", hash)
+            .expect("restored source");
+        let flip = "dec Flip : [Bool] Bool\ndef Flip = [b] b.case {\n  .true! => .false!,\n  .false! => .true!,\n}";
+        assert!(restored.ends_with(&format!("\n\n{flip}\n")), "{restored}");
+        record(&mut store, &restored);
+        assert_eq!(store.files[&file()].current_hash("Flip"), Some(hash));
+    }
+
+    #[test]
+    fn diffs_line_up_the_unchanged_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! The "Library" panel: a searchable list of every module, type, and declaration
+//! available to the active file, with click-to-insert of qualified names.
+//!
+//! Builtin modules are only listed once the active file imports them, unless
+//! the panel is asked to show all of them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use eframe::egui::{self, RichText};
+use par_core::frontend::language::{GlobalName, Universal};
+use par_core::source::FileName;
+use par_core::workspace::CheckedWorkspace;
+use par_runtime::pkgid::PackageId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LibraryItemKind {
+    Module,
+    Type,
+    Declaration,
+}
+
+impl LibraryItemKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            Self::Module => "module",
+            Self::Type => "type",
+            Self::Declaration => "dec",
+        }
+    }
+}
+
+pub(super) struct LibraryItem {
+    pub(super) kind: LibraryItemKind,
+    /// The module the item belongs to, as written in the active file.
+    pub(super) module: String,
+    /// The name to insert, qualified as needed from the active file.
+    pub(super) qualified: String,
+    pub(super) signature: String,
+    pub(super) doc: Option<String>,
+    pub(super) builtin: bool,
+    /// Whether the active file imports the item's module, or is in it.
+    pub(super) imported: bool,
+}
+
+impl LibraryItem {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        query.split_whitespace().all(|word| {
+            self.qualified.to_lowercase().contains(word)
+                || self
+                    .doc
+                    .as_deref()
+                    .is_some_and(|doc| doc.to_lowercase().contains(word))
+        })
+    }
+}
+
+/// Everything visible from one file of one build.
+pub(super) struct LibraryIndex {
+    checked: Arc<CheckedWorkspace>,
+    file: FileName,
+    items: Vec<LibraryItem>,
+}
+
+impl LibraryIndex {
+    pub(super) fn build(checked: Arc<CheckedWorkspace>, file: FileName) -> Self {
+        let workspace = checked.workspace();
+        let lowered = workspace.lowered_module();
+        let root = workspace.root_package();
+        let visible =
+            |name: &GlobalName<Universal>, exported: bool| exported || &name.module.package == root;
+        let imported = workspace
+            .import_scope(&file)
+            .map(|scope| {
+                let mut modules = scope.aliases.values().cloned().collect::<BTreeSet<_>>();
+                modules.insert(scope.current_module.clone());
+                modules
+            })
+            .unwrap_or_default();
+
+        let mut modules = BTreeMap::<String, (Universal, bool)>::new();
+        let mut items = Vec::new();
+
+        for type_def in &lowered.type_defs {
+            if !visible(&type_def.name, type_def.exported) {
+                continue;
+            }
+            let qualified = checked.render_global_in_file(&file, &type_def.name);
+            let mut signature = format!("type {qualified}");
+            if !type_def.params.is_empty() {
+                let params = type_def
+                    .params
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let _ = write!(signature, "<{params}>");
+            }
+            items.push(Self::item(
+                LibraryItemKind::Type,
+                &type_def.name,
+                qualified,
+                signature,
+                type_def.doc.as_ref().map(|doc| doc.markdown.to_string()),
+                &imported,
+                &mut modules,
+            ));
+        }
+
+        for declaration in &lowered.declarations {
+            if !visible(&declaration.name, declaration.exported) {
+                continue;
+            }
+            let qualified = checked.render_global_in_file(&file, &declaration.name);
+            let signature = format!(
+                "dec {qualified} : {}",
+                checked.render_type_in_file(&file, &declaration.typ, 0)
+            );
+            items.push(Self::item(
+                LibraryItemKind::Declaration,
+                &declaration.name,
+                qualified,
+                signature,
+                declaration.doc.as_ref().map(|doc| doc.markdown.to_string()),
+                &imported,
+                &mut modules,
+            ));
+        }
+
+        for (qualifier, (module, builtin)) in modules {
+            items.push(LibraryItem {
+                kind: LibraryItemKind::Module,
+                module: qualifier.clone(),
+                signature: format!("module {qualifier}"),
+                doc: workspace
+                    .module_doc(&module)
+                    .map(|doc| doc.markdown.to_string()),
+                qualified: qualifier,
+                builtin,
+                imported: imported.contains(&module),
+            });
+        }
+
+        items.sort_by(|left, right| {
+            (
+                &left.module,
+                left.kind != LibraryItemKind::Module,
+                &left.qualified,
+            )
+                .cmp(&(
+                    &right.module,
+                    right.kind != LibraryItemKind::Module,
+                    &right.qualified,
+                ))
+        });
+
+        Self {
+            checked,
+            file,
+            items,
+        }
+    }
+
+    fn item(
+        kind: LibraryItemKind,
+        name: &GlobalName<Universal>,
+        qualified: String,
+        signature: String,
+        doc: Option<String>,
+        imported: &BTreeSet<Universal>,
+        modules: &mut BTreeMap<String, (Universal, bool)>,
+    ) -> LibraryItem {
+        let builtin = matches!(name.module.package, PackageId::Builtin(_));
+        let module = qualified
+            .strip_suffix(name.primary.as_str())
+            .and_then(|prefix| prefix.strip_suffix('.'))
+            .unwrap_or(name.module.module.as_str())
+            .to_owned();
+        modules
+            .entry(module.clone())
+            .or_insert_with(|| (name.module.clone(), builtin));
+        LibraryItem {
+            kind,
+            module,
+            qualified,
+            signature,
+            doc,
+            builtin,
+            imported: imported.contains(&name.module),
+        }
+    }
+
+    pub(super) fn is_for(&self, checked: &Arc<CheckedWorkspace>, file: &FileName) -> bool {
+        Arc::ptr_eq(&self.checked, checked) && &self.file == file
+    }
+
+    /// The items matching `query`, leaving out builtin modules the file
+    /// doesn't import unless `all_builtins` is set.
+    pub(super) fn search<'a>(
+        &'a self,
+        query: &'a str,
+        all_builtins: bool,
+    ) -> impl Iterator<Item = &'a LibraryItem> {
+        self.items
+            .iter()
+            .filter(move |item| all_builtins || !item.builtin || item.imported)
+            .filter(move |item| item.matches(query))
+    }
+}
+
+#[derive(Default)]
+pub(super) struct LibraryPanel {
+    pub(super) open: bool,
+    query: String,
+    all_builtins: bool,
+    index: Option<LibraryIndex>,
+}
+
+impl LibraryPanel {
+    /// Shows the panel, returning a name to insert at the cursor, if one was clicked.
+    pub(super) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        checked: Option<Arc<CheckedWorkspace>>,
+        file: &FileName,
+    ) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        if let Some(checked) = checked
+            && !self
+                .index
+                .as_ref()
+                .is_some_and(|index| index.is_for(&checked, file))
+        {
+            self.index = Some(LibraryIndex::build(checked, file.clone()));
+        }
+
+        let mut insert = None;
+        let mut open = self.open;
+        egui::Window::new("Library")
+            .open(&mut open)
+            .default_size([420.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.text_edit_singleline(&mut self.query);
+                });
+                ui.checkbox(
+                    &mut self.all_builtins,
+                    "Show builtin modules that aren't imported",
+                );
+                ui.separator();
+
+                let Some(index) = &self.index else {
+                    ui.label("Compile the program to browse its library.");
+                    return;
+                };
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut current_module = None;
+                    for item in index.search(&self.query, self.all_builtins) {
+                        if current_module != Some(&item.module) {
+                            current_module = Some(&item.module);
+                            ui.add_space(6.0);
+                            let mut heading = RichText::new(&item.module).strong();
+                            if item.builtin {
+                                heading = heading.italics();
+                            }
+                            ui.label(heading);
+                        }
+                        if item.kind == LibraryItemKind::Module {
+                            continue;
+                        }
+
+                        let response = ui
+                            .add(
+                                egui::Label::new(RichText::new(&item.signature).code())
+                                    .sense(egui::Sense::click()),
+                            )
+                            .on_hover_text(format!(
+                                "{}\n\nClick to insert `{}` at the cursor.",
+                                item.doc.as_deref().unwrap_or(item.kind.keyword()),
+                                item.qualified
+                            ));
+                        if response.clicked() {
+                            insert = Some(item.qualified.clone());
+                        }
+                    }
+                });
+            });
+        self.open = open;
+        insert
+    }
+}
@@ -1,8 +1,22 @@
 mod app;
+mod background;
 mod build;
+mod config;
 mod examples;
+mod explorer;
+mod history;
+mod library;
+mod pinned;
 mod readback;
+mod renderers;
+mod run;
 mod run_menu;
+mod scratch;
+mod search;
+mod sequence_diagram;
 mod sources;
+mod undo;
+mod unfold;
 
 pub use app::Playground;
+pub use renderers::RendererRegistry;
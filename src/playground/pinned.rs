@@ -0,0 +1,37 @@
+//! Pinned definitions: a personal library kept across playground sessions.
+//!
+//! Each pinned definition is its own part of the `Pinned` module, holding the
+//! imports of the file it was pinned from and an exported declaration, so any
+//! program can `import Pinned` and use it. Pins are built along with every
+//! other source, so they get checked again each time the playground starts,
+//! and on native builds their sources are stored next to the scratch programs.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// The module every pinned definition is a part of.
+pub(super) const PINNED_MODULE: &str = "Pinned";
+
+/// Where the part holding `name` goes, relative to the bundled package's `src`.
+pub(super) fn pinned_part_path(name: &str) -> PathBuf {
+    PathBuf::from(format!("{PINNED_MODULE}.{name}.par"))
+}
+
+/// The source of a part of the `Pinned` module, out of the pieces of the
+/// definition's original file.
+pub(super) fn pinned_part_source(
+    imports: &[String],
+    declaration: &str,
+    definition: &str,
+) -> String {
+    let mut source = format!("module {PINNED_MODULE}\n\n");
+    if !imports.is_empty() {
+        source.push_str("import {\n");
+        for import in imports {
+            let _ = writeln!(source, "  {import}");
+        }
+        source.push_str("}\n\n");
+    }
+    let _ = writeln!(source, "{declaration}\n{definition}");
+    source
+}
@@ -8,13 +8,21 @@ use futures::{
 };
 use num_bigint::{BigInt, BigUint};
 use par_core::{
-    frontend::{ParString, Primitive, language::Universal, parse_bytes},
-    runtime::{TypedHandle, TypedReadback},
-    workspace::{FileImportScope, render_type_in_scope},
+    frontend::{ParString, Primitive, language::Universal},
+    runtime::{Input, TypedHandle, TypedReadback},
+    workspace::{FileImportScope, render_compact_type_in_scope, render_type_in_scope},
 };
+use par_runtime::flat::stats::RunSummary;
 use par_runtime::primitive::{format_float, parse_float_text};
+use par_runtime::readback::Handle;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
 
+use super::renderers::{RendererRegistry, ValueInput, ValueView};
+use super::undo::Journal;
+
 enum Request {
     Nat(String, Box<dyn Send + FnOnce(BigUint)>),
     Int(String, Box<dyn Send + FnOnce(BigInt)>),
@@ -24,6 +32,49 @@ enum Request {
     Byte(String, Box<dyn Send + FnOnce(u8)>),
     Bytes(String, Box<dyn Send + FnOnce(Bytes)>),
     Choice(Vec<ArcStr>, Box<dyn Send + FnOnce(ArcStr)>),
+    Custom(Box<dyn ValueInput>, Handle),
+}
+
+impl Request {
+    fn answer(self, input: Input) -> Result<Event, Self> {
+        Ok(match (self, input) {
+            (Self::Nat(_, callback), Input::Nat(number)) => {
+                callback(number.clone());
+                Event::NatRequest(number)
+            }
+            (Self::Int(_, callback), Input::Int(number)) => {
+                callback(number.clone());
+                Event::IntRequest(number)
+            }
+            (Self::Float(_, callback), Input::Float(number)) => {
+                callback(number);
+                Event::FloatRequest(number)
+            }
+            (Self::String(_, callback), Input::String(string)) => {
+                callback(ParString::from(string.clone()));
+                Event::StringRequest(string)
+            }
+            (Self::Char(_, callback), Input::Char(character)) => {
+                callback(character);
+                Event::CharRequest(character)
+            }
+            (Self::Byte(_, callback), Input::Byte(byte)) => {
+                callback(byte);
+                Event::ByteRequest(byte)
+            }
+            (Self::Bytes(_, callback), Input::Bytes(bytes)) => {
+                callback(bytes.clone());
+                Event::BytesRequest(bytes)
+            }
+            (Self::Choice(signals, callback), Input::Choice(chosen))
+                if signals.contains(&chosen) =>
+            {
+                callback(chosen.clone());
+                Event::Choice(chosen)
+            }
+            (request, _) => return Err(request),
+        })
+    }
 }
 
 pub enum Event {
@@ -47,6 +98,10 @@ pub enum Event {
     ByteRequest(u8),
     Bytes(Bytes),
     BytesRequest(Bytes),
+    Custom(Box<dyn ValueView>),
+    CustomRequest(Box<dyn ValueView>),
+
+    Summary(RunSummary),
 
     #[allow(unused)]
     Unreadable {
@@ -84,26 +139,245 @@ impl Event {
             Self::ByteRequest(_) => Polarity::Negative,
             Self::Bytes(_) => Polarity::Positive,
             Self::BytesRequest(_) => Polarity::Negative,
+            Self::Custom(_) => Polarity::Positive,
+            Self::CustomRequest(_) => Polarity::Negative,
+            Self::Summary(_) => Polarity::Positive,
             Self::Unreadable { .. } => Polarity::Positive,
         }
     }
+
+    fn is_same_signal(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Either(name1), Self::Either(name2)) => name1 == name2,
+            (Self::Choice(name1), Self::Choice(name2)) => name1 == name2,
+            _ => false,
+        }
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        let text = match self {
+            Self::Either(name) | Self::Choice(name) => name.to_string(),
+            event => match event.value_text() {
+                Some(text) => text,
+                None => return false,
+            },
+        };
+        text.to_lowercase().contains(query)
+    }
+
+    pub(super) fn is_from_program(&self) -> bool {
+        self.polarity() == Polarity::Positive
+    }
+
+    pub(super) fn value_text(&self) -> Option<String> {
+        Some(match self {
+            Self::Nat(i) | Self::NatRequest(i) => i.to_string(),
+            Self::Int(i) | Self::IntRequest(i) => i.to_string(),
+            Self::Float(value) | Self::FloatRequest(value) => format_float(*value),
+            Self::String(s) | Self::StringRequest(s) => format!("{:?}", s),
+            Self::Char(c) | Self::CharRequest(c) => format!("{:?}", c.encode_utf8(&mut [0u8; 4])),
+            Self::Byte(b) | Self::ByteRequest(b) => {
+                Primitive::Bytes(Bytes::copy_from_slice(&[*b])).pretty_string()
+            }
+            Self::Bytes(b) | Self::BytesRequest(b) => Primitive::Bytes(b.clone()).pretty_string(),
+            _ => return None,
+        })
+    }
 }
 
 pub struct Element {
     history: Vec<Event>,
+    lines: Vec<usize>,
+    line_sizes: Vec<egui::Vec2>,
+    shown_lines: usize,
+    children: Vec<usize>,
     request: Option<Request>,
+    expected: Option<String>,
+    path: Vec<usize>,
+    journal: Arc<Mutex<Journal>>,
+    expanded_signals: HashSet<usize>,
+    search: HistorySearch,
+    typ: String,
+    collapsed: bool,
+    copy_failed: bool,
+}
+
+enum Step {
+    Signal(ArcStr),
+    Send(String),
+    Break,
+    Primitive(String),
 }
 
+fn write_literal(steps: &[Step]) -> String {
+    if let Some(items) = list_items(steps) {
+        return format!("*({})", items.join(", "));
+    }
+
+    let mut out = String::new();
+    let mut steps = steps.iter().peekable();
+    while let Some(step) = steps.next() {
+        let token = match step {
+            Step::Signal(name) => format!(".{name}"),
+            Step::Send(value) => {
+                let mut values = vec![value.as_str()];
+                while let Some(Step::Send(value)) = steps.peek() {
+                    values.push(value);
+                    steps.next();
+                }
+                format!("({})", values.join(", "))
+            }
+            Step::Break => String::from("!"),
+            Step::Primitive(value) => value.clone(),
+        };
+        if !out.is_empty() && !token.starts_with(['(', '!']) {
+            out.push(' ');
+        }
+        out.push_str(&token);
+    }
+    out
+}
+
+fn list_items(mut steps: &[Step]) -> Option<Vec<&str>> {
+    let mut items = Vec::new();
+    loop {
+        match steps {
+            [Step::Signal(item), Step::Send(value), rest @ ..] if item == "item" => {
+                items.push(value.as_str());
+                steps = rest;
+            }
+            [Step::Signal(end), Step::Break] if end == "end" && !items.is_empty() => {
+                return Some(items);
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct HistorySearch {
+    query: String,
+    matches: Vec<(Vec<usize>, usize)>,
+    current: usize,
+    scroll: bool,
+}
+
+struct Found<'a> {
+    query: String,
+    current: Option<&'a (Vec<usize>, usize)>,
+    scroll: Cell<bool>,
+}
+
+impl Found<'_> {
+    fn show(
+        &self,
+        ui: &mut egui::Ui,
+        text: RichText,
+        path: &[usize],
+        range: Range<usize>,
+        event: &Event,
+        hover: Option<&str>,
+    ) -> egui::Response {
+        let current = self
+            .current
+            .is_some_and(|(current_path, index)| current_path == path && range.contains(index));
+        let text = if self.query.is_empty() || !event.matches(&self.query) {
+            text
+        } else if current {
+            text.background_color(ui.visuals().selection.bg_fill)
+        } else {
+            text.background_color(ui.visuals().selection.bg_fill.gamma_multiply(0.35))
+        };
+        let response = match hover {
+            Some(hover) => ui
+                .add(egui::Label::new(text).sense(egui::Sense::click()))
+                .on_hover_text(hover),
+            None => ui.label(text),
+        };
+        if current && self.scroll.get() {
+            response.scroll_to_me(Some(egui::Align::Center));
+            self.scroll.set(false);
+        }
+        response
+    }
+}
+
+const COALESCE_SIGNALS_FROM: usize = 4;
+
+const HISTORY_PAGE: usize = 200;
+
 impl Element {
+    pub(super) fn history(&self) -> &[Event] {
+        &self.history
+    }
+
+    pub(super) fn finish(&mut self, summary: RunSummary) {
+        self.push(Event::Summary(summary));
+    }
+
+    fn push(&mut self, event: Event) {
+        let new_line = match self.history.last() {
+            None | Some(Event::Times(_) | Event::Par(_)) => true,
+            Some(last) => last.polarity() != event.polarity(),
+        };
+        if new_line {
+            self.lines.push(self.history.len());
+            self.line_sizes.push(egui::Vec2::ZERO);
+        }
+        if let Event::Times(_) | Event::Par(_) = event {
+            self.children.push(self.history.len());
+        }
+        self.history.push(event);
+    }
+
+    #[cfg(test)]
+    pub(super) fn from_history(history: Vec<Event>) -> Arc<Mutex<Self>> {
+        let mut element = Self {
+            history: vec![],
+            lines: vec![],
+            line_sizes: vec![],
+            shown_lines: HISTORY_PAGE,
+            children: vec![],
+            request: None,
+            expected: None,
+            path: vec![],
+            journal: Arc::new(Mutex::new(Journal::new(super::undo::DEFAULT_UNDO_LIMIT))),
+            expanded_signals: HashSet::new(),
+            search: HistorySearch::default(),
+            typ: String::new(),
+            collapsed: false,
+            copy_failed: false,
+        };
+        for event in history {
+            element.push(event);
+        }
+        Arc::new(Mutex::new(element))
+    }
+
     pub fn new(
         refresh: Arc<dyn Fn() + Send + Sync>,
         spawner: Arc<dyn Spawn + Send + Sync>,
         scope: Option<FileImportScope<Universal>>,
+        renderers: Arc<RendererRegistry>,
         handle: TypedHandle,
+        path: Vec<usize>,
+        journal: Arc<Mutex<Journal>>,
     ) -> Arc<Mutex<Self>> {
         let element = Arc::new(Mutex::new(Self {
             history: vec![],
+            lines: vec![],
+            line_sizes: vec![],
+            shown_lines: HISTORY_PAGE,
+            children: vec![],
             request: None,
+            expected: None,
+            path,
+            journal,
+            expanded_signals: HashSet::new(),
+            search: HistorySearch::default(),
+            typ: render_compact_type_in_scope(scope.as_ref(), handle.typ()),
+            collapsed: false,
+            copy_failed: false,
         }));
 
         spawner
@@ -111,6 +385,7 @@ impl Element {
                 refresh,
                 Arc::clone(&spawner),
                 scope,
+                renderers,
                 handle,
                 Arc::clone(&element),
             ))
@@ -118,298 +393,557 @@ impl Element {
         element
     }
 
+    fn request(&mut self, request: Request) {
+        let journal = Arc::clone(&self.journal);
+        let mut journal = journal.lock().expect("lock failed");
+        let Some(input) = journal.next_replay(&self.path) else {
+            self.request = Some(request);
+            return;
+        };
+        match request.answer(input) {
+            Ok(event) => self.push(event),
+            Err(request) => {
+                journal.abandon_replay(&self.path);
+                self.request = Some(request);
+            }
+        }
+    }
+
+    fn record(&self, input: Input) {
+        self.journal
+            .lock()
+            .expect("lock failed")
+            .record(&self.path, input);
+    }
+
+    fn child_path(&self) -> Vec<usize> {
+        let mut path = self.path.clone();
+        path.push(self.children.len());
+        path
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) {
         ui.vertical(|ui| {
+            self.show_search(ui);
+            let mut search = std::mem::take(&mut self.search);
+            let found = Found {
+                query: search.query.to_lowercase(),
+                current: search.matches.get(search.current),
+                scroll: Cell::new(search.scroll),
+            };
             ui.horizontal(|ui| {
-                self.show_content(ui);
+                self.show_content(ui, &found);
             });
+            search.scroll = found.scroll.get();
+            self.search = search;
         });
     }
 
-    pub fn show_content(&mut self, ui: &mut egui::Ui) {
+    fn show_search(&mut self, ui: &mut egui::Ui) {
+        let (edited, step) = ui
+            .horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.search.query)
+                        .hint_text("Find in history")
+                        .desired_width(160.0),
+                );
+                let mut step = None;
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    step = Some(1);
+                    response.request_focus();
+                }
+                let searching = !self.search.query.is_empty();
+                if ui
+                    .add_enabled(searching, egui::Button::new("▲").small())
+                    .on_hover_text("Previous match")
+                    .clicked()
+                {
+                    step = Some(-1);
+                }
+                if ui
+                    .add_enabled(searching, egui::Button::new("▼").small())
+                    .on_hover_text("Next match")
+                    .clicked()
+                {
+                    step = Some(1);
+                }
+                if searching {
+                    ui.label(match self.search.matches.len() {
+                        0 => String::from("No matches"),
+                        count => format!("{} of {count}", self.search.current + 1),
+                    });
+                }
+                (response.changed(), step)
+            })
+            .inner;
+        if !edited && step.is_none() {
+            return;
+        }
+
+        // Matching the whole tree is only done when asked to, rather than
+        // every frame, so it only costs anything while searching.
+        let mut matches = Vec::new();
+        let query = self.search.query.to_lowercase();
+        if !query.is_empty() {
+            self.collect_matches(&query, &mut matches);
+        }
+        let search = &mut self.search;
+        search.matches = matches;
+        let count = search.matches.len();
+        search.current = match step {
+            _ if count == 0 => 0,
+            None => 0,
+            Some(step) => (search.current as isize + step).rem_euclid(count as isize) as usize,
+        };
+        search.scroll = count > 0;
+    }
+
+    fn collect_matches(&self, query: &str, matches: &mut Vec<(Vec<usize>, usize)>) {
+        for (index, event) in self.history.iter().enumerate() {
+            match event {
+                Event::Times(child) | Event::Par(child) => child
+                    .lock()
+                    .expect("lock failed")
+                    .collect_matches(query, matches),
+                event if event.matches(query) => matches.push((self.path.clone(), index)),
+                _ => {}
+            }
+        }
+    }
+
+    fn show_content(&mut self, ui: &mut egui::Ui, found: &Found) {
+        if self.collapsed
+            && found.scroll.get()
+            && found
+                .current
+                .is_some_and(|(path, _)| path.starts_with(&self.path))
+        {
+            self.collapsed = false;
+        }
+
         egui::Frame::default()
             .stroke(egui::Stroke::new(1.0, egui::Color32::GRAY))
             .inner_margin(egui::Margin::same(4))
             .outer_margin(egui::Margin::same(2))
             .show(ui, |ui| {
-                ui.vertical(|ui| {
-                    Self::show_history(ui, &self.history);
-
-                    if let Some(request) = self.request.take() {
-                        match request {
-                            Request::Nat(mut input, callback) => {
-                                let input_number = BigUint::parse_bytes(input.as_bytes(), 10);
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input)
-                                                .hint_text("Type a natural number..."),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_number.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_number.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let number = input_number.unwrap();
-                                    self.history.push(Event::NatRequest(number.clone()));
-                                    callback(number);
-                                } else {
-                                    self.request = Some(Request::Nat(input, callback));
-                                }
+                if self.collapsed {
+                    self.show_collapsed(ui);
+                    return;
+                }
+                ui.horizontal_top(|ui| {
+                    let nested = !self.path.is_empty();
+                    if nested || self.is_finished() {
+                        ui.vertical(|ui| {
+                            if nested {
+                                self.show_toggle(ui);
                             }
+                            self.show_copy(ui);
+                        });
+                    }
+                    ui.vertical(|ui| {
+                        self.show_history(ui, found);
 
-                            Request::Int(mut input, callback) => {
-                                let input_number = BigInt::parse_bytes(input.as_bytes(), 10);
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input)
-                                                .hint_text("Type an integer..."),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_number.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_number.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let number = input_number.unwrap();
-                                    self.history.push(Event::IntRequest(number.clone()));
-                                    callback(number);
-                                } else {
-                                    self.request = Some(Request::Int(input, callback));
-                                }
-                            }
+                        if let Some(request) = self.request.take() {
+                            let expected = self.expected.clone();
+                            ui.horizontal_top(|ui| {
+                                match request {
+                                    Request::Nat(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type a natural number...",
+                                            parse_nat_input,
+                                        ) {
+                                            Some(number) => {
+                                                self.record(Input::Nat(number.clone()));
+                                                self.push(Event::NatRequest(number.clone()));
+                                                callback(number);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Nat(input, callback))
+                                            }
+                                        }
+                                    }
 
-                            Request::Float(mut input, callback) => {
-                                let input_float = parse_float_text(&input);
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input)
-                                                .hint_text("Type a float..."),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_float.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_float.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let number = input_float.unwrap();
-                                    self.history.push(Event::FloatRequest(number));
-                                    callback(number);
-                                } else {
-                                    self.request = Some(Request::Float(input, callback));
-                                }
-                            }
+                                    Request::Int(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type an integer...",
+                                            parse_int_input,
+                                        ) {
+                                            Some(number) => {
+                                                self.record(Input::Int(number.clone()));
+                                                self.push(Event::IntRequest(number.clone()));
+                                                callback(number);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Int(input, callback))
+                                            }
+                                        }
+                                    }
 
-                            Request::String(mut input, callback) => {
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::multiline(&mut input)
-                                                .desired_rows(1)
-                                                .desired_width(200.0)
-                                                .hint_text("Type a string..."),
-                                        );
-                                        ui.add(egui::Button::small(egui::Button::new("OK")))
-                                            .clicked()
-                                    })
-                                    .inner;
-                                if entered {
-                                    self.history.push(Event::StringRequest(input.clone()));
-                                    callback(ParString::from(input));
-                                } else {
-                                    self.request = Some(Request::String(input, callback));
-                                }
-                            }
+                                    Request::Float(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type a float...",
+                                            parse_float_input,
+                                        ) {
+                                            Some(number) => {
+                                                self.record(Input::Float(number));
+                                                self.push(Event::FloatRequest(number));
+                                                callback(number);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Float(input, callback))
+                                            }
+                                        }
+                                    }
 
-                            Request::Char(mut input, callback) => {
-                                let input_char = Some(&input)
-                                    .filter(|s| s.chars().count() == 1)
-                                    .and_then(|s| s.chars().next());
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input)
-                                                .hint_text("Type a single character..."),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_char.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_char.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let character = input_char.unwrap();
-                                    self.history.push(Event::CharRequest(character));
-                                    callback(character);
-                                } else {
-                                    self.request = Some(Request::Char(input, callback));
-                                }
-                            }
+                                    Request::String(mut input, callback) => {
+                                        let entered = ui
+                                            .horizontal(|ui| {
+                                                ui.add(
+                                                    egui::TextEdit::multiline(&mut input)
+                                                        .desired_rows(1)
+                                                        .desired_width(200.0)
+                                                        .hint_text("Type a string..."),
+                                                );
+                                                ui.add(egui::Button::small(egui::Button::new("OK")))
+                                                    .clicked()
+                                            })
+                                            .inner;
+                                        if entered {
+                                            self.record(Input::String(input.clone()));
+                                            self.push(Event::StringRequest(input.clone()));
+                                            callback(ParString::from(input));
+                                        } else {
+                                            self.request = Some(Request::String(input, callback));
+                                        }
+                                    }
 
-                            Request::Byte(mut input, callback) => {
-                                let input_byte = parse_bytes(&input, &"input".into())
-                                    .filter(|b| b.len() == 1)
-                                    .and_then(|b| b.get(0).copied());
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input)
-                                                .hint_text("Type a single byte code 0-255..."),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_byte.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_byte.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let byte = input_byte.unwrap();
-                                    self.history.push(Event::ByteRequest(byte));
-                                    callback(byte);
-                                } else {
-                                    self.request = Some(Request::Byte(input, callback));
-                                }
-                            }
+                                    Request::Char(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type a single character...",
+                                            parse_char_input,
+                                        ) {
+                                            Some(character) => {
+                                                self.record(Input::Char(character));
+                                                self.push(Event::CharRequest(character));
+                                                callback(character);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Char(input, callback))
+                                            }
+                                        }
+                                    }
 
-                            Request::Bytes(mut input, callback) => {
-                                let input_bytes = parse_bytes(&input, &"input".into());
-                                let entered = ui
-                                    .horizontal(|ui| {
-                                        ui.add(
-                                            egui::TextEdit::singleline(&mut input).hint_text(
-                                                "Type a sequence of byte codes 0-255...",
-                                            ),
-                                        );
-                                        let button = ui.add_enabled(
-                                            input_bytes.is_some(),
-                                            egui::Button::small(egui::Button::new("OK")),
-                                        );
-                                        button.clicked() && input_bytes.is_some()
-                                    })
-                                    .inner;
-                                if entered {
-                                    let bytes = Bytes::from(input_bytes.unwrap());
-                                    self.history.push(Event::BytesRequest(bytes.clone()));
-                                    callback(bytes);
-                                } else {
-                                    self.request = Some(Request::Bytes(input, callback));
-                                }
-                            }
+                                    Request::Byte(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type a single byte code 0-255...",
+                                            parse_byte_input,
+                                        ) {
+                                            Some(byte) => {
+                                                self.record(Input::Byte(byte));
+                                                self.push(Event::ByteRequest(byte));
+                                                callback(byte);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Byte(input, callback))
+                                            }
+                                        }
+                                    }
+
+                                    Request::Bytes(mut input, callback) => {
+                                        match show_request_input(
+                                            ui,
+                                            &mut input,
+                                            "Type a sequence of byte codes 0-255...",
+                                            parse_bytes_input,
+                                        ) {
+                                            Some(bytes) => {
+                                                let bytes = Bytes::from(bytes);
+                                                self.record(Input::Bytes(bytes.clone()));
+                                                self.push(Event::BytesRequest(bytes.clone()));
+                                                callback(bytes);
+                                            }
+                                            None => {
+                                                self.request = Some(Request::Bytes(input, callback))
+                                            }
+                                        }
+                                    }
+
+                                    Request::Choice(signals, callback) => {
+                                        let mut chosen = None;
+                                        ui.vertical(|ui| {
+                                            for signal in &signals {
+                                                if ui
+                                                    .button(
+                                                        RichText::new(signal.to_string()).strong(),
+                                                    )
+                                                    .clicked()
+                                                {
+                                                    chosen = Some(signal.clone());
+                                                }
+                                            }
+                                        });
+                                        if let Some(chosen) = chosen {
+                                            self.record(Input::Choice(chosen.clone()));
+                                            self.push(Event::Choice(chosen.clone()));
+                                            callback(chosen);
+                                        } else {
+                                            self.request = Some(Request::Choice(signals, callback));
+                                        }
+                                    }
 
-                            Request::Choice(signals, callback) => {
-                                let mut chosen = None;
-                                ui.vertical(|ui| {
-                                    for signal in &signals {
-                                        if ui
-                                            .button(RichText::new(signal.to_string()).strong())
-                                            .clicked()
-                                        {
-                                            chosen = Some(signal.clone());
+                                    Request::Custom(mut input, handle) => {
+                                        if input.show(ui) {
+                                            // What a custom editor provides can't be replayed.
+                                            self.journal.lock().expect("lock failed").forbid_undo();
+                                            self.push(Event::CustomRequest(input.provide(handle)));
+                                        } else {
+                                            self.request = Some(Request::Custom(input, handle));
                                         }
                                     }
-                                });
-                                if let Some(chosen) = chosen {
-                                    self.history.push(Event::Choice(chosen.clone()));
-                                    callback(chosen);
-                                } else {
-                                    self.request = Some(Request::Choice(signals, callback));
                                 }
-                            }
+                                if let Some(expected) = expected {
+                                    ui.label(RichText::new(expected).code().weak())
+                                        .on_hover_text("The type the program expects here");
+                                }
+                            });
                         }
-                    }
+                    });
                 });
             });
     }
 
-    fn show_history<'h>(ui: &mut egui::Ui, events: &'h [Event]) {
-        let mut events = events;
-        ui.vertical(|ui| {
-            while !events.is_empty() {
-                events = Self::show_history_line(ui, events);
+    fn show_toggle(&mut self, ui: &mut egui::Ui) {
+        let (arrow, hover) = if self.collapsed {
+            ("▶", "Click to show this part of the value")
+        } else {
+            ("▼", "Click to show this part of the value in one line")
+        };
+        let toggle = ui
+            .add(egui::Label::new(RichText::from(arrow).weak()).sense(egui::Sense::click()))
+            .on_hover_text(hover);
+        if toggle.clicked() {
+            self.collapsed = !self.collapsed;
+        }
+    }
+
+    fn show_copy(&mut self, ui: &mut egui::Ui) {
+        if !self.is_finished() {
+            return;
+        }
+        let hover = if self.copy_failed {
+            "Parts of this value can't be written in Par"
+        } else {
+            "Click to copy this value, written in Par"
+        };
+        let copy = ui
+            .add(egui::Label::new(RichText::from("📋").weak()).sense(egui::Sense::click()))
+            .on_hover_text(hover);
+        if copy.clicked() {
+            match self.literal() {
+                Some(literal) => {
+                    ui.ctx().copy_text(literal);
+                    self.copy_failed = false;
+                }
+                None => self.copy_failed = true,
             }
-        });
+        }
     }
 
-    fn show_history_line<'h>(ui: &mut egui::Ui, events: &'h [Event]) -> &'h [Event] {
-        let mut polarity = None::<Polarity>;
-        let mut events = events;
+    fn is_finished(&self) -> bool {
+        self.request.is_none()
+            && self
+                .history
+                .iter()
+                .rev()
+                .find(|event| !matches!(event, Event::Summary(_)))
+                .is_some_and(|event| {
+                    matches!(event, Event::Break)
+                        || event.is_from_program() && event.value_text().is_some()
+                })
+    }
 
+    pub(super) fn literal(&self) -> Option<String> {
+        let mut steps = Vec::new();
+        for event in &self.history {
+            steps.push(match event {
+                Event::Either(name) => Step::Signal(name.clone()),
+                Event::Times(child) => Step::Send(child.lock().expect("lock failed").literal()?),
+                Event::Break => Step::Break,
+                Event::Summary(_) => continue,
+                event if event.is_from_program() => Step::Primitive(event.value_text()?),
+                _ => return None,
+            });
+        }
+        if !matches!(steps.last(), Some(Step::Break | Step::Primitive(_))) {
+            return None;
+        }
+        Some(write_literal(&steps))
+    }
+
+    fn show_collapsed(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
-            while let Some(event) = events.get(0) {
-                if polarity.map_or(false, |p| p != event.polarity()) {
-                    return events;
+            self.show_toggle(ui);
+            self.show_copy(ui);
+            ui.label(RichText::from(self.typ.as_str()).code());
+            let events = match self.history.len() {
+                1 => String::from("1 event"),
+                count => format!("{count} events"),
+            };
+            ui.label(RichText::from(events).small().weak());
+            if self.request.is_some() {
+                ui.label(RichText::from("waiting for input").small().strong());
+            }
+        });
+    }
+
+    fn show_history(&mut self, ui: &mut egui::Ui, found: &Found) {
+        // The line with the match to scroll to, or with the element it's in,
+        // is shown whether it's out of view or hidden.
+        let reveal = found
+            .current
+            .filter(|_| found.scroll.get())
+            .and_then(|(path, index)| {
+                let nested = path.strip_prefix(self.path.as_slice())?;
+                let index = match nested.first() {
+                    None => *index,
+                    Some(child) => *self.children.get(*child)?,
+                };
+                Some(self.lines.partition_point(|&start| start <= index) - 1)
+            });
+        if let Some(line) = reveal {
+            self.shown_lines = self.shown_lines.max(self.lines.len() - line);
+        }
+
+        let hidden = self.lines.len().saturating_sub(self.shown_lines);
+        ui.vertical(|ui| {
+            if hidden > 0 {
+                let more = hidden.min(HISTORY_PAGE);
+                if ui
+                    .small_button(format!("Show {more} earlier lines"))
+                    .on_hover_text(format!("{hidden} lines of the history are hidden"))
+                    .clicked()
+                {
+                    self.shown_lines += more;
+                }
+            }
+            for line in hidden..self.lines.len() {
+                let start = self.lines[line];
+                let end = self
+                    .lines
+                    .get(line + 1)
+                    .copied()
+                    .unwrap_or(self.history.len());
+                let size = self.line_sizes[line];
+                if size != egui::Vec2::ZERO
+                    && reveal != Some(line)
+                    && !ui.is_rect_visible(egui::Rect::from_min_size(ui.cursor().min, size))
+                {
+                    ui.allocate_space(size);
+                    continue;
                 }
+                self.line_sizes[line] = Self::show_history_line(
+                    ui,
+                    &self.history,
+                    &self.path,
+                    start..end,
+                    &mut self.expanded_signals,
+                    found,
+                );
+            }
+        });
+    }
 
-                if polarity == None {
-                    match event.polarity() {
-                        Polarity::Positive => {
-                            ui.label(RichText::from(">").code());
-                        }
-                        Polarity::Negative => {
-                            ui.label(RichText::from("<").code());
-                        }
-                    }
+    fn show_history_line(
+        ui: &mut egui::Ui,
+        history: &[Event],
+        path: &[usize],
+        line: Range<usize>,
+        expanded: &mut HashSet<usize>,
+        found: &Found,
+    ) -> egui::Vec2 {
+        ui.horizontal(|ui| {
+            match history[line.start].polarity() {
+                Polarity::Positive => {
+                    ui.label(RichText::from(">").code());
                 }
+                Polarity::Negative => {
+                    ui.label(RichText::from("<").code());
+                }
+            }
 
-                polarity = Some(event.polarity());
-                events = &events[1..];
+            let mut index = line.start;
+            while index < line.end {
+                let event = &history[index];
+                let first = index;
+                index += 1;
 
                 match event {
                     Event::Times(child) | Event::Par(child) => {
-                        child.lock().unwrap().show_content(ui);
-                        return events;
+                        child.lock().unwrap().show_content(ui, found);
                     }
                     Event::Either(name) | Event::Choice(name) => {
-                        ui.label(RichText::from(name.to_string()).strong());
+                        let repeats = 1 + history[index..line.end]
+                            .iter()
+                            .take_while(|next| next.is_same_signal(event))
+                            .count();
+                        let label = RichText::from(name.to_string()).strong();
+                        if repeats < COALESCE_SIGNALS_FROM {
+                            found.show(ui, label, path, first..index, event, None);
+                            continue;
+                        }
+                        if expanded.contains(&first) {
+                            for signal in first..first + repeats {
+                                found.show(
+                                    ui,
+                                    label.clone(),
+                                    path,
+                                    signal..signal + 1,
+                                    event,
+                                    None,
+                                );
+                            }
+                            let collapse = ui
+                                .add(
+                                    egui::Label::new(RichText::from("−").weak())
+                                        .sense(egui::Sense::click()),
+                                )
+                                .on_hover_text("Click to show these signals as one");
+                            if collapse.clicked() {
+                                expanded.remove(&first);
+                            }
+                        } else {
+                            let expand = found.show(
+                                ui,
+                                RichText::from(format!("{name} ×{repeats}")).strong(),
+                                path,
+                                first..first + repeats,
+                                event,
+                                Some("Click to show each signal"),
+                            );
+                            if expand.clicked() {
+                                expanded.insert(first);
+                            }
+                        }
+                        index += repeats - 1;
                     }
                     Event::Break | Event::Continue => {
                         ui.label(RichText::from("!").strong().code());
                     }
-                    Event::Nat(i) | Event::NatRequest(i) => {
-                        ui.label(RichText::from(format!("{}", i)).strong().code());
-                    }
-                    Event::Int(i) | Event::IntRequest(i) => {
-                        ui.label(RichText::from(format!("{}", i)).strong().code());
-                    }
-                    Event::Float(value) | Event::FloatRequest(value) => {
-                        ui.label(RichText::from(format_float(*value)).strong().code());
-                    }
-                    Event::String(s) | Event::StringRequest(s) => {
-                        ui.label(RichText::from(format!("{:?}", s)).strong().code());
-                    }
-                    Event::Char(s) | Event::CharRequest(s) => {
-                        ui.label(
-                            RichText::from(format!("{:?}", s.encode_utf8(&mut [0u8; 4])))
-                                .strong()
-                                .code(),
-                        );
+                    Event::Custom(view) | Event::CustomRequest(view) => {
+                        view.show(ui);
                     }
-                    Event::Byte(b) | Event::ByteRequest(b) => {
-                        ui.label(
-                            RichText::from(
-                                Primitive::Bytes(Bytes::copy_from_slice(&[*b])).pretty_string(),
-                            )
-                            .strong()
-                            .code(),
-                        );
-                    }
-                    Event::Bytes(b) | Event::BytesRequest(b) => {
-                        ui.label(
-                            RichText::from(Primitive::Bytes(b.clone()).pretty_string())
-                                .strong()
-                                .code(),
-                        );
+                    Event::Summary(summary) => {
+                        ui.label(RichText::from(summary.to_string()).small().weak());
                     }
                     Event::Unreadable { .. } => {
                         ui.label(
@@ -418,131 +952,269 @@ impl Element {
                                 .code(),
                         );
                     }
+                    event => {
+                        if let Some(text) = event.value_text() {
+                            let text = RichText::from(text).strong().code();
+                            found.show(ui, text, path, first..index, event, None);
+                        }
+                    }
                 }
             }
-
-            &[]
         })
-        .inner
+        .response
+        .rect
+        .size()
+    }
+}
+
+pub(super) fn show_request_input<T>(
+    ui: &mut egui::Ui,
+    input: &mut String,
+    hint: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Option<T> {
+    ui.vertical(|ui| {
+        let (response, clicked) = ui
+            .horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(input).hint_text(hint));
+                let clicked = ui
+                    .add(egui::Button::small(egui::Button::new("OK")))
+                    .clicked();
+                (response, clicked)
+            })
+            .inner;
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        let parsed = parse(input);
+        if let Err(message) = &parsed
+            && !input.trim().is_empty()
+        {
+            ui.label(
+                RichText::new(message)
+                    .small()
+                    .color(ui.visuals().error_fg_color),
+            );
+        }
+
+        if !(clicked || entered) {
+            return None;
+        }
+        match parsed {
+            Ok(value) => Some(value),
+            Err(_) => {
+                response.request_focus();
+                None
+            }
+        }
+    })
+    .inner
+}
+
+fn parse_nat_input(input: &str) -> Result<BigUint, String> {
+    let input = input.trim();
+    if input.starts_with('-') {
+        return Err(String::from("A natural number cannot be negative."));
     }
+    BigUint::parse_bytes(input.as_bytes(), 10).ok_or_else(|| not_a_number(input, "natural number"))
+}
+
+fn parse_int_input(input: &str) -> Result<BigInt, String> {
+    let input = input.trim();
+    BigInt::parse_bytes(input.as_bytes(), 10).ok_or_else(|| not_a_number(input, "integer"))
+}
+
+fn parse_float_input(input: &str) -> Result<f64, String> {
+    parse_float_text(input.trim())
+        .ok_or_else(|| format!("`{}` is not a valid float.", input.trim()))
+}
+
+fn not_a_number(input: &str, what: &str) -> String {
+    let digits = input.strip_prefix(['-', '+']).unwrap_or(input);
+    match digits.chars().find(|c| !c.is_ascii_digit() && *c != '_') {
+        Some(c) => format!("`{c}` is not a digit; expected a whole {what}."),
+        None => format!("Expected a whole {what}."),
+    }
+}
+
+fn parse_char_input(input: &str) -> Result<char, String> {
+    let mut chars = input.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        (None, _) => Err(String::from("Type a character.")),
+        (Some(_), Some(_)) => Err(format!(
+            "Expected a single character, got {}.",
+            input.chars().count()
+        )),
+    }
+}
+
+fn parse_byte_input(input: &str) -> Result<u8, String> {
+    match parse_bytes_input(input)?.as_slice() {
+        [byte] => Ok(*byte),
+        [] => Err(String::from("Type a byte code 0-255.")),
+        bytes => Err(format!("Expected a single byte, got {}.", bytes.len())),
+    }
+}
+
+fn parse_bytes_input(input: &str) -> Result<Vec<u8>, String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|code| !code.is_empty())
+        .map(|code| match BigUint::parse_bytes(code.as_bytes(), 10) {
+            Some(value) => u8::try_from(&value)
+                .map_err(|_| format!("`{code}` is out of range; a byte is 0-255.")),
+            None => Err(format!("`{code}` is not a byte code 0-255.")),
+        })
+        .collect()
 }
 
 async fn handle_coroutine(
     refresh: Arc<dyn Fn() + Send + Sync>,
     spawner: Arc<dyn Spawn + Send + Sync>,
     scope: Option<FileImportScope<Universal>>,
+    renderers: Arc<RendererRegistry>,
     handle: TypedHandle,
     element: Arc<Mutex<Element>>,
 ) {
     let mut handle = handle;
 
     loop {
+        let expected = render_compact_type_in_scope(scope.as_ref(), handle.typ());
+        if let Some((name, dual)) = handle.type_name() {
+            if dual && let Some(editor) = renderers.editor(name) {
+                let mut lock = element.lock().expect("lock failed");
+                lock.expected = Some(expected);
+                lock.request(Request::Custom(editor(), handle.into_handle()));
+                refresh();
+                break;
+            }
+            if !dual && let Some(render) = renderers.renderer(name) {
+                let view = render(handle.into_handle()).await;
+                let mut lock = element.lock().expect("lock failed");
+                lock.push(Event::Custom(view));
+                refresh();
+                break;
+            }
+        }
+
         match handle.readback().await {
             TypedReadback::Nat(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Nat(value));
+                lock.push(Event::Nat(value));
                 refresh();
                 break;
             }
 
             TypedReadback::NatRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Nat(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Nat(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Int(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Int(value));
+                lock.push(Event::Int(value));
                 refresh();
                 break;
             }
 
             TypedReadback::IntRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Int(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Int(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Float(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Float(value));
+                lock.push(Event::Float(value));
                 refresh();
                 break;
             }
 
             TypedReadback::FloatRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Float(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Float(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::String(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::String(value.as_str().to_string()));
+                lock.push(Event::String(value.as_str().to_string()));
                 refresh();
                 break;
             }
 
             TypedReadback::StringRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::String(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::String(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Char(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Char(value));
+                lock.push(Event::Char(value));
                 refresh();
                 break;
             }
 
             TypedReadback::CharRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Char(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Char(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Byte(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Byte(value));
+                lock.push(Event::Byte(value));
                 refresh();
                 break;
             }
 
             TypedReadback::ByteRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Byte(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Byte(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Bytes(value) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Bytes(value));
+                lock.push(Event::Bytes(value));
                 refresh();
                 break;
             }
 
             TypedReadback::BytesRequest(callback) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.request = Some(Request::Bytes(String::new(), callback));
+                lock.expected = Some(expected);
+                lock.request(Request::Bytes(String::new(), callback));
                 refresh();
                 break;
             }
 
             TypedReadback::Times(handle1, handle2) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Times(Element::new(
+                let path = lock.child_path();
+                let journal = Arc::clone(&lock.journal);
+                lock.push(Event::Times(Element::new(
                     Arc::clone(&refresh),
                     Arc::clone(&spawner),
                     scope.clone(),
+                    Arc::clone(&renderers),
                     handle1,
+                    path,
+                    journal,
                 )));
                 handle = handle2;
                 refresh();
@@ -550,11 +1222,16 @@ async fn handle_coroutine(
 
             TypedReadback::Par(handle1, handle2) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Par(Element::new(
+                let path = lock.child_path();
+                let journal = Arc::clone(&lock.journal);
+                lock.push(Event::Par(Element::new(
                     Arc::clone(&refresh),
                     Arc::clone(&spawner),
                     scope.clone(),
+                    Arc::clone(&renderers),
                     handle1,
+                    path,
+                    journal,
                 )));
                 handle = handle2;
                 refresh();
@@ -562,7 +1239,7 @@ async fn handle_coroutine(
 
             TypedReadback::Either(chosen, handle1) => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Either(chosen));
+                lock.push(Event::Either(chosen));
                 handle = handle1;
                 refresh();
             }
@@ -571,7 +1248,8 @@ async fn handle_coroutine(
                 let rx: oneshot::Receiver<TypedHandle> = {
                     let (tx, rx) = oneshot::channel::<TypedHandle>();
                     let mut lock = element.lock().expect("lock failed");
-                    lock.request = Some(Request::Choice(
+                    lock.expected = Some(expected);
+                    lock.request(Request::Choice(
                         signals,
                         Box::new(move |chosen| {
                             let handle = callback(chosen);
@@ -586,21 +1264,21 @@ async fn handle_coroutine(
 
             TypedReadback::Break => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Break);
+                lock.push(Event::Break);
                 refresh();
                 break;
             }
 
             TypedReadback::Continue => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Continue);
+                lock.push(Event::Continue);
                 refresh();
                 break;
             }
 
             TypedReadback::Unreadable { typ, handle } => {
                 let mut lock = element.lock().expect("lock failed");
-                lock.history.push(Event::Unreadable {
+                lock.push(Event::Unreadable {
                     typ: render_type_in_scope(scope.as_ref(), &typ, 2),
                     handle: Arc::new(handle),
                 });
@@ -610,3 +1288,191 @@ async fn handle_coroutine(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_core::frontend::language::GlobalName;
+    use par_core::pipeline::{Pipeline, PipelineError, SourcePackage, Stage};
+    use par_core::source::FileName;
+    use par_core::workspace::{CheckedWorkspace, LoadedPackageFile};
+    use par_runtime::environment::Environment;
+    use par_runtime::pkgid::PackageId;
+    use par_runtime::spawn::TokioSpawn;
+
+    use super::*;
+
+    const LARGE_TEST_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+    const TEXT: &str = "say \"hi\"\\\tthen\nleave é 😀";
+
+    fn value(history: Vec<Event>) -> String {
+        Element::from_history(history)
+            .lock()
+            .unwrap()
+            .literal()
+            .expect("a value that can be written in Par")
+    }
+
+    fn pair(first: Event, second: Event) -> Event {
+        Event::Times(Element::from_history(vec![
+            Event::Times(Element::from_history(vec![first])),
+            Event::Times(Element::from_history(vec![second])),
+            Event::Break,
+        ]))
+    }
+
+    fn literals() -> Vec<(&'static str, &'static str, String)> {
+        vec![
+            (
+                "Text",
+                "String",
+                value(vec![Event::String(TEXT.to_owned())]),
+            ),
+            ("Letter", "Char", value(vec![Event::Char('"')])),
+            (
+                "Numbers",
+                "(Nat, Int, Float)!",
+                value(vec![
+                    Event::Times(Element::from_history(vec![Event::Nat(BigUint::from(7u8))])),
+                    Event::Times(Element::from_history(vec![Event::Int(BigInt::from(-3))])),
+                    Event::Times(Element::from_history(vec![Event::Float(2.5)])),
+                    Event::Break,
+                ]),
+            ),
+            (
+                "Pairs",
+                "List<(String, Nat)!>",
+                value(vec![
+                    Event::Either(literal!("item")),
+                    pair(
+                        Event::String("one".to_owned()),
+                        Event::Nat(BigUint::from(1u8)),
+                    ),
+                    Event::Either(literal!("item")),
+                    pair(
+                        Event::String(TEXT.to_owned()),
+                        Event::Nat(BigUint::from(2u8)),
+                    ),
+                    Event::Either(literal!("end")),
+                    Event::Break,
+                ]),
+            ),
+            (
+                "Found",
+                "Option<Bytes>",
+                value(vec![
+                    Event::Either(literal!("some")),
+                    Event::Bytes(Bytes::from_static(b"\x00ab\xff")),
+                ]),
+            ),
+            (
+                "Missing",
+                "Option<Bytes>",
+                value(vec![Event::Either(literal!("none")), Event::Break]),
+            ),
+        ]
+    }
+
+    fn check(literals: &[(&str, &str, String)]) -> CheckedWorkspace {
+        let mut source = String::from(
+            "module Copied
+
+import {
+  @core/Bytes
+  @core/Char
+  @core/Float
+  @core/Int
+  @core/List
+  @core/Nat
+  @core/Option
+  @core/String
+}
+",
+        );
+        for (name, typ, literal) in literals {
+            source.push_str(&format!("\ndef {name}: {typ} = {literal}\n"));
+        }
+        let id = PackageId::Special(literal!("test"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("test/Copied.par"),
+                    relative_path_from_src: PathBuf::from("Copied.par"),
+                    source: source.clone(),
+                }],
+            }],
+        );
+        pipeline.add_pass(Stage::Parse, |artifacts| {
+            let packages = artifacts.packages.as_mut().expect("parsed packages");
+            par_builtin::inject_builtin_packages(packages).map_err(PipelineError::Discovery)
+        });
+        pipeline
+            .run_until(Stage::TypeCheck)
+            .unwrap_or_else(|error| panic!("{error:?} in\n{source}"));
+        let artifacts = pipeline.into_artifacts();
+        assert!(
+            artifacts.type_errors.is_empty(),
+            "{:?} in\n{source}",
+            artifacts.type_errors
+        );
+        artifacts.checked.expect("checked workspace")
+    }
+
+    fn run_string(checked: &CheckedWorkspace, name: &str) -> String {
+        let definition: &GlobalName<Universal> = checked
+            .checked_module()
+            .definitions
+            .keys()
+            .find(|global| global.module.module == "Copied" && global.primary == name)
+            .expect("definition in Copied");
+        let compiled = checked
+            .compile_runtime(10_000)
+            .expect("compiled program")
+            .link()
+            .expect("linked program");
+        let package = compiled.package(definition).expect("compiled definition");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("tokio runtime");
+        runtime.block_on(async {
+            let (root, fut) = par_runtime::start_and_instantiate(
+                Arc::new(TokioSpawn::new()),
+                Environment::new(),
+                compiled.arena(),
+                package,
+            );
+            let text = root.string().await.as_str().to_owned();
+            fut.await;
+            text
+        })
+    }
+
+    #[test]
+    fn copied_values_parse_back_with_their_types() {
+        let text = std::thread::Builder::new()
+            .stack_size(LARGE_TEST_STACK_SIZE)
+            .spawn(|| {
+                let checked = check(&literals());
+                run_string(&checked, "Text")
+            })
+            .expect("failed to spawn large-stack test thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        assert_eq!(text, TEXT);
+    }
+
+    #[test]
+    fn lists_are_copied_as_list_literals() {
+        let literals = literals();
+        let (_, _, pairs) = &literals[3];
+        assert!(pairs.starts_with("*((\"one\", 1)!, ("), "{pairs}");
+    }
+}
@@ -0,0 +1,189 @@
+//! Custom readback for specific named types. Embedders register a renderer
+//! for values of a type, or an editor for requests of it, and the readback
+//! panel uses them instead of showing the type's structure.
+
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use eframe::egui::{self, RichText};
+use futures::future::{BoxFuture, FutureExt};
+use num_bigint::BigInt;
+use par_core::frontend::language::{GlobalName, Universal};
+use par_runtime::readback::Handle;
+
+use super::readback::show_request_input;
+
+/// A value that was read back, or submitted, as shown in the history.
+pub trait ValueView: Send {
+    fn show(&self, ui: &mut egui::Ui);
+
+    /// A plain-text version of the value, for exports.
+    fn text(&self) -> String;
+}
+
+/// The input for a request of a custom type.
+pub trait ValueInput: Send {
+    /// Shows the input, returning `true` once a value is ready to submit.
+    fn show(&mut self, ui: &mut egui::Ui) -> bool;
+
+    /// Provides the submitted value, returning how to show it in the history.
+    fn provide(self: Box<Self>, handle: Handle) -> Box<dyn ValueView>;
+}
+
+pub(super) type Renderer =
+    Arc<dyn Fn(Handle) -> BoxFuture<'static, Box<dyn ValueView>> + Send + Sync>;
+pub(super) type Editor = Arc<dyn Fn() -> Box<dyn ValueInput> + Send + Sync>;
+
+/// Renderers and editors, keyed by type name.
+///
+/// A name is either qualified with its module, like `Time.Duration`, or bare,
+/// like `Color`. The qualified name is tried first, so a bare name applies to
+/// a type of that name in any module.
+#[derive(Clone)]
+pub struct RendererRegistry {
+    renderers: HashMap<String, Renderer>,
+    editors: HashMap<String, Editor>,
+}
+
+impl RendererRegistry {
+    pub fn empty() -> Self {
+        Self {
+            renderers: HashMap::new(),
+            editors: HashMap::new(),
+        }
+    }
+
+    /// Reads back values of `type_name` with `render`, which receives the raw
+    /// handle of the value.
+    pub fn register_renderer<F, Fut>(&mut self, type_name: impl Into<String>, render: F)
+    where
+        F: Fn(Handle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Box<dyn ValueView>> + Send + 'static,
+    {
+        self.renderers.insert(
+            type_name.into(),
+            Arc::new(move |handle| render(handle).boxed()),
+        );
+    }
+
+    /// Answers requests of `type_name` with an input made by `editor`.
+    pub fn register_editor<F>(&mut self, type_name: impl Into<String>, editor: F)
+    where
+        F: Fn() -> Box<dyn ValueInput> + Send + Sync + 'static,
+    {
+        self.editors.insert(type_name.into(), Arc::new(editor));
+    }
+
+    pub(super) fn renderer(&self, name: &GlobalName<Universal>) -> Option<Renderer> {
+        lookup(&self.renderers, name).cloned()
+    }
+
+    pub(super) fn editor(&self, name: &GlobalName<Universal>) -> Option<Editor> {
+        lookup(&self.editors, name).cloned()
+    }
+
+    pub(super) fn supports(&self, name: &GlobalName<Universal>, dual: bool) -> bool {
+        if dual {
+            lookup(&self.editors, name).is_some()
+        } else {
+            lookup(&self.renderers, name).is_some()
+        }
+    }
+}
+
+impl Default for RendererRegistry {
+    /// The registry the playground ships with.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_renderer("Time.Duration", |handle: Handle| async move {
+            Box::new(DurationView(handle.int().await)) as Box<dyn ValueView>
+        });
+        registry.register_editor("Time.Duration", || {
+            Box::new(DurationInput::default()) as Box<dyn ValueInput>
+        });
+        registry
+    }
+}
+
+fn lookup<'a, T>(map: &'a HashMap<String, T>, name: &GlobalName<Universal>) -> Option<&'a T> {
+    map.get(&format!("{}.{}", name.module.module, name.primary))
+        .or_else(|| map.get(name.primary.as_str()))
+}
+
+const DURATION_UNITS: &[(&str, i128)] = &[
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+struct DurationView(BigInt);
+
+impl ValueView for DurationView {
+    fn show(&self, ui: &mut egui::Ui) {
+        ui.label(RichText::from(format_duration(&self.0)).strong().code())
+            .on_hover_text(format!("{} nanoseconds", self.0));
+    }
+
+    fn text(&self) -> String {
+        format_duration(&self.0)
+    }
+}
+
+#[derive(Default)]
+struct DurationInput {
+    input: String,
+    nanos: Option<BigInt>,
+}
+
+impl ValueInput for DurationInput {
+    fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        self.nanos = show_request_input(
+            ui,
+            &mut self.input,
+            "Type a duration, like 1.5s...",
+            parse_duration,
+        );
+        self.nanos.is_some()
+    }
+
+    fn provide(self: Box<Self>, handle: Handle) -> Box<dyn ValueView> {
+        let nanos = self.nanos.unwrap_or_default();
+        handle.provide_int(nanos.clone());
+        Box::new(DurationView(nanos))
+    }
+}
+
+/// Shows a duration in the largest unit it has at least one of.
+fn format_duration(nanos: &BigInt) -> String {
+    let Ok(nanos) = i128::try_from(nanos) else {
+        return format!("{nanos}ns");
+    };
+    let (unit, size) = DURATION_UNITS
+        .iter()
+        .find(|(_, size)| nanos.abs() >= *size)
+        .unwrap_or(&("ns", 1));
+    if nanos % size == 0 {
+        format!("{}{unit}", nanos / size)
+    } else {
+        format!("{}{unit}", nanos as f64 / *size as f64)
+    }
+}
+
+fn parse_duration(input: &str) -> Result<BigInt, String> {
+    let input = input.trim();
+    let (unit, size) = DURATION_UNITS
+        .iter()
+        .filter(|(unit, _)| input.ends_with(unit))
+        .max_by_key(|(unit, _)| unit.len())
+        .ok_or_else(|| String::from("End the duration with a unit: h, m, s, ms, us, or ns."))?;
+    let amount = input[..input.len() - unit.len()].trim();
+    let amount: f64 = amount
+        .parse()
+        .map_err(|_| format!("`{amount}` is not a number."))?;
+    if !amount.is_finite() {
+        return Err(format!("`{amount}` is not a finite number."));
+    }
+    Ok(BigInt::from((amount * *size as f64).round() as i128))
+}
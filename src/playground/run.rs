@@ -0,0 +1,325 @@
+//! A single run of a definition in the playground.
+//!
+//! Everything a run touches is owned by its [`RunContext`]: the net it reduces,
+//! the executor its reducer and builtins are spawned on, the setup its
+//! builtins see, with its environment, configuration and assets, its
+//! cancellation, and the element showing it. Discarding the context discards
+//! the run, so one that crashed or hung never takes the playground, or the
+//! next run, down with it. The only state shared between runs is process-wide
+//! by nature: the miette hook and the crash-report hook, both installed once
+//! at startup.
+//!
+//! A context also keeps what it was started from, so that undoing an input
+//! can start the same definition over and replay the inputs before it, and a
+//! change to the program can restart it on the new build.
+
+use std::sync::{Arc, Mutex};
+#[cfg(target_family = "wasm")]
+use std::time::Duration;
+#[cfg(not(target_family = "wasm"))]
+use std::time::Instant;
+
+use eframe::egui;
+use futures::{
+    FutureExt,
+    task::{Spawn, SpawnExt},
+};
+use par_core::{
+    frontend::{
+        Type,
+        language::{GlobalName, Universal},
+    },
+    runtime::{Compiled, Profile, TypedHandle, unsupported_readback_with},
+    workspace::{CheckedWorkspace, FileImportScope, render_compact_type_in_scope},
+};
+use par_runtime::linker::Linked;
+use par_runtime::setup::Setup;
+#[cfg(not(target_family = "wasm"))]
+use par_runtime::spawn::TokioSpawn;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(target_family = "wasm")]
+use crate::wasm_spawn::WasmSpawn;
+
+use super::{
+    readback::Element,
+    renderers::RendererRegistry,
+    undo::{DEFAULT_UNDO_LIMIT, Journal},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum RunStatus {
+    Running,
+    Finished,
+    Cancelled,
+    /// The reducer panicked, with this message.
+    Crashed(String),
+}
+
+pub(super) struct RunContext {
+    /// A runtime of the run's own, so that shutting it down stops every task
+    /// the run spawned, even ones stuck in a builtin.
+    #[cfg(not(target_family = "wasm"))]
+    runtime: Option<tokio::runtime::Runtime>,
+    cancel_token: CancellationToken,
+    status: Arc<Mutex<RunStatus>>,
+    /// Where the run spent its rewrites, once it finished, if it was
+    /// profiled.
+    profile: Arc<Mutex<Option<Profile>>>,
+    element: Arc<Mutex<Element>>,
+    journal: Arc<Mutex<Journal>>,
+    origin: RunOrigin,
+}
+
+/// What a run was started from.
+#[derive(Clone)]
+struct RunOrigin {
+    renderers: Arc<RendererRegistry>,
+    setup: Setup,
+    program: Arc<CheckedWorkspace>,
+    compiled: Compiled<Linked>,
+    ty: Type<Universal>,
+    name: GlobalName<Universal>,
+    display_scope: Option<FileImportScope<Universal>>,
+}
+
+/// Why a definition of type `ty` can't be run in the playground, if it can't:
+/// the first part of its type that the playground has no way to show, or to
+/// ask for, and where that part is.
+pub(super) fn unsupported_entry_type(
+    renderers: &RendererRegistry,
+    program: &CheckedWorkspace,
+    ty: &Type<Universal>,
+    display_scope: Option<&FileImportScope<Universal>>,
+) -> Option<String> {
+    let unsupported =
+        unsupported_readback_with(&program.checked_module().type_defs, ty, &|name, dual| {
+            renderers.supports(name, dual)
+        })?;
+    Some(format!(
+        "The playground can't interact with `{}`, at {} of the type.",
+        render_compact_type_in_scope(display_scope, &unsupported.typ),
+        unsupported.location(),
+    ))
+}
+
+impl RunContext {
+    pub(super) fn start(
+        renderers: &Arc<RendererRegistry>,
+        setup: Setup,
+        program: Arc<CheckedWorkspace>,
+        compiled: &Compiled<Linked>,
+        ty: &Type<Universal>,
+        name: &GlobalName<Universal>,
+        display_scope: Option<FileImportScope<Universal>>,
+        ctx: &egui::Context,
+    ) -> Result<Self, String> {
+        // Fail now, rather than get stuck once the run reaches that part.
+        if let Some(reason) =
+            unsupported_entry_type(renderers, &program, ty, display_scope.as_ref())
+        {
+            return Err(reason);
+        }
+        let origin = RunOrigin {
+            renderers: Arc::clone(renderers),
+            setup,
+            program,
+            compiled: compiled.clone(),
+            ty: ty.clone(),
+            name: name.clone(),
+            display_scope,
+        };
+        Self::start_from(origin, Journal::new(DEFAULT_UNDO_LIMIT), ctx)
+    }
+
+    fn start_from(
+        origin: RunOrigin,
+        mut journal: Journal,
+        ctx: &egui::Context,
+    ) -> Result<Self, String> {
+        let RunOrigin {
+            renderers,
+            setup,
+            program,
+            compiled,
+            ty,
+            name,
+            display_scope,
+        } = &origin;
+
+        #[cfg(not(target_family = "wasm"))]
+        let runtime = crate::tokio_factory::create_runtime()
+            .map_err(|error| format!("Failed to start a runtime for the run: {error}"))?;
+        #[cfg(not(target_family = "wasm"))]
+        let spawner: Arc<dyn Spawn + Send + Sync + 'static> =
+            Arc::new(TokioSpawn::from_handle(runtime.handle().clone()));
+        #[cfg(target_family = "wasm")]
+        let spawner: Arc<dyn Spawn + Send + Sync + 'static> = Arc::new(WasmSpawn::new());
+
+        let package = compiled
+            .package(name)
+            .ok_or_else(|| format!("`{}` was not compiled", name.primary))?;
+        let cancel_token = CancellationToken::new();
+        let setup = setup
+            .renewed()
+            .with_effects(journal.start())
+            .with_cancel_token(cancel_token.clone());
+        let (handle, reducer_future) = par_runtime::start_and_instantiate(
+            spawner.clone(),
+            setup.clone(),
+            compiled.arena(),
+            package,
+        );
+
+        let journal = Arc::new(Mutex::new(journal));
+        let repaint_ctx = ctx.clone();
+        let element = Element::new(
+            Arc::new(move || {
+                repaint_ctx.request_repaint();
+            }),
+            spawner.clone(),
+            display_scope.clone(),
+            Arc::clone(renderers),
+            TypedHandle::new(
+                program.checked_module().type_defs.clone(),
+                ty.clone(),
+                handle,
+            ),
+            vec![],
+            Arc::clone(&journal),
+        );
+
+        let status = Arc::new(Mutex::new(RunStatus::Running));
+        let token = cancel_token.clone();
+        let task_status = Arc::clone(&status);
+        let profile = Arc::new(Mutex::new(None));
+        let task_profile = Arc::clone(&profile);
+        let profiled = setup.is_profiling().then(|| compiled.clone());
+        let task_element = Arc::clone(&element);
+        #[cfg(not(target_family = "wasm"))]
+        let start = Instant::now();
+        let repaint_ctx = ctx.clone();
+        let _ = spawner.spawn(async move {
+            let outcome = tokio::select! {
+                _ = token.cancelled() => RunStatus::Cancelled,
+                result = std::panic::AssertUnwindSafe(reducer_future).catch_unwind() => {
+                    match result {
+                        Ok(rewrites) => {
+                            // Time isn't measured on the web, same as the
+                            // reducer's busy time.
+                            #[cfg(not(target_family = "wasm"))]
+                            let wall_time = start.elapsed();
+                            #[cfg(target_family = "wasm")]
+                            let wall_time = Duration::ZERO;
+                            let summary = rewrites.summary(wall_time);
+                            println!("Note: Run summary: {summary}");
+                            task_element.lock().unwrap().finish(summary);
+                            if let Some(compiled) = profiled {
+                                *task_profile.lock().unwrap() =
+                                    Some(Profile::new(&compiled, &rewrites));
+                            }
+                            RunStatus::Finished
+                        }
+                        Err(payload) => RunStatus::Crashed(crate::panic_message(&*payload)),
+                    }
+                }
+            };
+            match &outcome {
+                RunStatus::Cancelled => println!("Note: Reducer cancelled."),
+                RunStatus::Crashed(message) => println!("Note: Reducer crashed: {message}"),
+                _ => println!("Note: Reducer completed."),
+            }
+            *task_status.lock().unwrap() = outcome;
+            repaint_ctx.request_repaint();
+        });
+
+        Ok(Self {
+            #[cfg(not(target_family = "wasm"))]
+            runtime: Some(runtime),
+            cancel_token,
+            status,
+            profile,
+            element,
+            journal,
+            origin,
+        })
+    }
+
+    pub(super) fn status(&self) -> RunStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub(super) fn profile(&self) -> Option<Profile> {
+        self.profile.lock().unwrap().clone()
+    }
+
+    pub(super) fn element(&self) -> &Arc<Mutex<Element>> {
+        &self.element
+    }
+
+    /// How many of the last inputs can be undone.
+    pub(super) fn undo_steps(&self) -> usize {
+        self.journal.lock().unwrap().undo_steps()
+    }
+
+    pub(super) fn set_undo_limit(&self, limit: usize) {
+        self.journal.lock().unwrap().set_limit(limit);
+    }
+
+    /// Undoes the last input by starting the definition over, in a new
+    /// context, and replaying the inputs before it. This context is
+    /// discarded.
+    pub(super) fn undo(&mut self, ctx: &egui::Context) -> Result<(), String> {
+        let Some(journal) = self.journal.lock().unwrap().undone() else {
+            return Err(String::from("There is nothing to undo"));
+        };
+        *self = Self::start_from(self.origin.clone(), journal, ctx)?;
+        Ok(())
+    }
+
+    /// Starts the same definition over on a new build of the program, and
+    /// replays every input given so far, so the run picks up where it was.
+    /// If the new build can't run the definition, this run is kept.
+    pub(super) fn restart(
+        &mut self,
+        setup: Setup,
+        program: Arc<CheckedWorkspace>,
+        compiled: &Compiled<Linked>,
+        display_scope: Option<FileImportScope<Universal>>,
+        ctx: &egui::Context,
+    ) -> Result<(), String> {
+        let name = self.origin.name.clone();
+        let Some(ty) = compiled.name_to_ty.get(&name) else {
+            return Err(format!("`{}` is no longer defined", name.primary));
+        };
+        let renderers = Arc::clone(&self.origin.renderers);
+        if let Some(reason) =
+            unsupported_entry_type(&renderers, &program, ty, display_scope.as_ref())
+        {
+            return Err(reason);
+        }
+        let origin = RunOrigin {
+            renderers,
+            setup,
+            program,
+            compiled: compiled.clone(),
+            ty: ty.clone(),
+            name,
+            display_scope,
+        };
+        let journal = self.journal.lock().unwrap().restarted();
+        *self = Self::start_from(origin, journal, ctx)?;
+        Ok(())
+    }
+}
+
+impl Drop for RunContext {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(runtime) = self.runtime.take() {
+            // Don't wait for tasks that may never yield.
+            runtime.shutdown_background();
+        }
+    }
+}
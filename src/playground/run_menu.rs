@@ -1,24 +1,26 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    sync::{Arc, Mutex},
+    sync::Arc,
 };
 
 use eframe::egui::{self, RichText};
-use futures::task::{Spawn, SpawnExt};
 use par_core::{
     frontend::{
         Type, Visibility,
         language::{GlobalName, Universal},
     },
-    runtime::{Compiled, TypedHandle, type_supports_readback},
+    runtime::Compiled,
     source::FileName,
     workspace::{CheckedWorkspace, FileImportScope, ModulePath},
 };
 use par_runtime::linker::Linked;
 use par_runtime::pkgid::PackageId;
-use tokio_util::sync::CancellationToken;
+use par_runtime::setup::Setup;
 
-use super::readback::Element;
+use super::{
+    renderers::RendererRegistry,
+    run::{RunContext, unsupported_entry_type},
+};
 
 #[derive(Default)]
 struct ModuleMenuTree<'a> {
@@ -78,20 +80,24 @@ fn module_has_visible_definitions(
     })
 }
 
-fn definition_supports_readback(
+/// Why the definition can't be run, if it can't.
+fn definition_unsupported_reason(
     program: &CheckedWorkspace,
+    renderers: &RendererRegistry,
     name_to_ty: &HashMap<GlobalName<Universal>, Type<Universal>>,
+    display_scope: Option<&FileImportScope<Universal>>,
     name: &GlobalName<Universal>,
-) -> bool {
-    name_to_ty
-        .get(name)
-        .is_some_and(|typ| type_supports_readback(&program.checked_module().type_defs, typ))
+) -> Option<String> {
+    match name_to_ty.get(name) {
+        Some(typ) => unsupported_entry_type(renderers, program, typ, display_scope),
+        None => Some(format!("`{}` has no type to run it with.", name.primary)),
+    }
 }
 
 fn run_definition(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     program: Arc<CheckedWorkspace>,
     compiled: &Compiled<Linked>,
     name_to_ty: &HashMap<GlobalName<Universal>, Type<Universal>>,
@@ -99,48 +105,28 @@ fn run_definition(
     display_scope: Option<FileImportScope<Universal>>,
     ctx: &egui::Context,
 ) {
-    if let Some(cancel_token) = cancel_token {
-        cancel_token.cancel();
-    }
-    let token = CancellationToken::new();
-    *cancel_token = Some(token.clone());
-
+    // Discard the previous run before starting the next one.
+    *run = None;
     let ty = name_to_ty.get(name).unwrap();
-    let package = compiled.code.get_with_name(name).unwrap();
-    let (handle, reducer_future) =
-        par_runtime::start_and_instantiate(spawner.clone(), compiled.code.arena.clone(), package);
-
-    let repaint_ctx = ctx.clone();
-    *element = Some(Element::new(
-        Arc::new(move || {
-            repaint_ctx.request_repaint();
-        }),
-        spawner.clone(),
+    match RunContext::start(
+        renderers,
+        setup.clone(),
+        program,
+        compiled,
+        ty,
+        name,
         display_scope,
-        TypedHandle::new(
-            program.checked_module().type_defs.clone(),
-            ty.clone(),
-            handle,
-        ),
-    ));
-    let repaint_ctx = ctx.clone();
-    let _ = spawner.spawn(async move {
-        tokio::select! {
-            _ = token.cancelled() => {
-                println!("Note: Reducer cancelled.");
-            }
-            _ = reducer_future => {
-                repaint_ctx.request_repaint();
-                println!("Note: Reducer completed.");
-            }
-        }
-    });
+        ctx,
+    ) {
+        Ok(context) => *run = Some(context),
+        Err(error) => eprintln!("Failed to run `{}`: {error}", name.primary),
+    }
 }
 
 fn show_definition_item(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     ui: &mut egui::Ui,
     program: Arc<CheckedWorkspace>,
     compiled: &Compiled<Linked>,
@@ -149,16 +135,27 @@ fn show_definition_item(
     name: &GlobalName<Universal>,
     label: &str,
 ) {
-    let supports_readback = definition_supports_readback(&program, name_to_ty, name);
+    let unsupported = definition_unsupported_reason(
+        &program,
+        renderers,
+        name_to_ty,
+        display_scope.as_ref(),
+        name,
+    );
+    let description = program.workspace().entry_point(name).description;
 
-    if ui
-        .add_enabled(supports_readback, egui::Button::new(label))
-        .clicked()
-    {
+    let mut response = ui.add_enabled(unsupported.is_none(), egui::Button::new(label));
+    if let Some(description) = description {
+        response = response.on_hover_text(description);
+    }
+    if let Some(unsupported) = unsupported {
+        response = response.on_disabled_hover_text(unsupported);
+    }
+    if response.clicked() {
         run_definition(
-            spawner,
-            cancel_token,
-            element,
+            run,
+            renderers,
+            setup,
             program,
             compiled,
             name_to_ty,
@@ -170,10 +167,62 @@ fn show_definition_item(
     }
 }
 
+/// Shows definitions without a category first, then a submenu for each
+/// category, named in the doc comments of their declarations.
+fn show_definition_items<'a>(
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
+    ui: &mut egui::Ui,
+    program: Arc<CheckedWorkspace>,
+    compiled: &Compiled<Linked>,
+    name_to_ty: &HashMap<GlobalName<Universal>, Type<Universal>>,
+    display_scope: Option<FileImportScope<Universal>>,
+    items: impl IntoIterator<Item = (&'a GlobalName<Universal>, String)>,
+) {
+    let mut categories = BTreeMap::<String, Vec<_>>::new();
+    for (name, label) in items {
+        match program.workspace().entry_point(name).category {
+            Some(category) => categories.entry(category).or_default().push((name, label)),
+            None => show_definition_item(
+                run,
+                renderers,
+                setup,
+                ui,
+                program.clone(),
+                compiled,
+                name_to_ty,
+                display_scope.clone(),
+                name,
+                &label,
+            ),
+        }
+    }
+
+    for (category, items) in categories {
+        ui.menu_button(category, |ui| {
+            for (name, label) in items {
+                show_definition_item(
+                    run,
+                    renderers,
+                    setup,
+                    ui,
+                    program.clone(),
+                    compiled,
+                    name_to_ty,
+                    display_scope.clone(),
+                    name,
+                    &label,
+                );
+            }
+        });
+    }
+}
+
 fn show_module_definitions(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     ui: &mut egui::Ui,
     program: Arc<CheckedWorkspace>,
     compiled: &Compiled<Linked>,
@@ -183,35 +232,38 @@ fn show_module_definitions(
     module: &ModulePath,
     only_exported_definitions: bool,
 ) {
-    let mut has_definitions = false;
-    for name in program.checked_module().definitions.keys().filter(|name| {
-        definition_in_module(name, package, module)
-            && definition_visible_in_menu(&program, name, only_exported_definitions)
-    }) {
-        has_definitions = true;
-        show_definition_item(
-            spawner.clone(),
-            cancel_token,
-            element,
-            ui,
-            program.clone(),
-            compiled,
-            name_to_ty,
-            display_scope.clone(),
-            name,
-            &name.primary,
-        );
-    }
+    let definitions = program
+        .checked_module()
+        .definitions
+        .keys()
+        .filter(|name| {
+            definition_in_module(name, package, module)
+                && definition_visible_in_menu(&program, name, only_exported_definitions)
+        })
+        .map(|name| (name, name.primary.clone()))
+        .collect::<Vec<_>>();
 
-    if !has_definitions {
+    if definitions.is_empty() {
         ui.label(RichText::new("No definitions").italics());
     }
+
+    show_definition_items(
+        run,
+        renderers,
+        setup,
+        ui,
+        program.clone(),
+        compiled,
+        name_to_ty,
+        display_scope,
+        definitions,
+    );
 }
 
 fn show_module_tree(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     ui: &mut egui::Ui,
     program: Arc<CheckedWorkspace>,
     compiled: &Compiled<Linked>,
@@ -224,9 +276,9 @@ fn show_module_tree(
     for (directory, subtree) in &tree.directories {
         ui.menu_button(*directory, |ui| {
             show_module_tree(
-                spawner.clone(),
-                cancel_token,
-                element,
+                run,
+                renderers,
+                setup,
                 ui,
                 program.clone(),
                 compiled,
@@ -242,9 +294,9 @@ fn show_module_tree(
     for (module_name, module) in &tree.modules {
         ui.menu_button(*module_name, |ui| {
             show_module_definitions(
-                spawner.clone(),
-                cancel_token,
-                element,
+                run,
+                renderers,
+                setup,
                 ui,
                 program.clone(),
                 compiled,
@@ -259,9 +311,9 @@ fn show_module_tree(
 }
 
 fn show_package_modules(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     ui: &mut egui::Ui,
     program: Arc<CheckedWorkspace>,
     compiled: &Compiled<Linked>,
@@ -302,9 +354,9 @@ fn show_package_modules(
 
     let tree = ModuleMenuTree::from_modules(&modules);
     show_module_tree(
-        spawner,
-        cancel_token,
-        element,
+        run,
+        renderers,
+        setup,
         ui,
         program,
         compiled,
@@ -325,9 +377,9 @@ fn package_label(root_package: &PackageId, package: &PackageId) -> String {
 }
 
 pub(super) fn show_run_menu(
-    spawner: Arc<dyn Spawn + Send + Sync + 'static>,
-    cancel_token: &mut Option<CancellationToken>,
-    element: &mut Option<Arc<Mutex<Element>>>,
+    run: &mut Option<RunContext>,
+    renderers: &Arc<RendererRegistry>,
+    setup: &Setup,
     ui: &mut egui::Ui,
     active_file: &FileName,
     program: Arc<CheckedWorkspace>,
@@ -362,9 +414,9 @@ pub(super) fn show_run_menu(
                 package_label(program.workspace().root_package(), package),
                 |ui| {
                     show_package_modules(
-                        spawner.clone(),
-                        cancel_token,
-                        element,
+                        run,
+                        renderers,
+                        setup,
                         ui,
                         program.clone(),
                         compiled,
@@ -382,9 +434,9 @@ pub(super) fn show_run_menu(
     if let Some(package) = current_package.as_ref() {
         ui.menu_button("Modules", |ui| {
             show_package_modules(
-                spawner.clone(),
-                cancel_token,
-                element,
+                run,
+                renderers,
+                setup,
                 ui,
                 program.clone(),
                 compiled,
@@ -409,20 +461,20 @@ pub(super) fn show_run_menu(
             ui.separator();
         }
 
-        for name in current_definitions {
-            let label = program.render_global_in_file(active_file, name);
-            show_definition_item(
-                spawner.clone(),
-                cancel_token,
-                element,
-                ui,
-                program.clone(),
-                compiled,
-                name_to_ty,
-                current_scope.clone(),
-                name,
-                &label,
-            );
-        }
+        let items = current_definitions
+            .into_iter()
+            .map(|name| (name, program.render_global_in_file(active_file, name)))
+            .collect::<Vec<_>>();
+        show_definition_items(
+            run,
+            renderers,
+            setup,
+            ui,
+            program.clone(),
+            compiled,
+            name_to_ty,
+            current_scope.clone(),
+            items,
+        );
     }
 }
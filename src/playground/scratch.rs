@@ -0,0 +1,90 @@
+//! Named scratch programs, kept next to the bundled examples.
+//!
+//! Each scratch program is its own module under `Scratch/`, so it can import
+//! the examples and be run like any of them. On native builds, scratch sources
+//! are persisted in the user's home directory and restored on the next start.
+//! The web build doesn't persist them: they're lost when the page is closed.
+
+#[cfg(not(target_family = "wasm"))]
+use std::{fs, io, path::PathBuf};
+
+/// Directory (relative to the bundled package's `src`) holding scratch modules.
+pub(super) const SCRATCH_DIR: &str = "Scratch";
+
+/// Scratch names double as module names, so they follow the same rules.
+pub(super) fn is_valid_scratch_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(super) fn scratch_template(name: &str) -> String {
+    format!("module {name}\n\nimport @core/Debug\n\ndef Main : ! = Debug.Log(\"Hello, {name}!\")\n")
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(super) struct ScratchStore {
+    dir: PathBuf,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl ScratchStore {
+    /// The store under `~/.par/playground/scratch`, if a home directory is known.
+    pub(super) fn open_default() -> Option<Self> {
+        Self::open_in_home("scratch")
+    }
+
+    /// The store of [pinned](super::pinned) definitions, under
+    /// `~/.par/playground/pinned`. They're named like scratch programs.
+    pub(super) fn open_pinned() -> Option<Self> {
+        Self::open_in_home("pinned")
+    }
+
+    fn open_in_home(name: &str) -> Option<Self> {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        let mut dir = PathBuf::from(home);
+        dir.extend([".par", "playground", name]);
+        Some(Self { dir })
+    }
+
+    /// All stored programs as `(name, source)`, sorted by name.
+    pub(super) fn load_all(&self) -> Vec<(String, String)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut programs: Vec<_> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "par" {
+                    return None;
+                }
+                let name = path.file_stem()?.to_str()?.to_owned();
+                if !is_valid_scratch_name(&name) {
+                    return None;
+                }
+                let source = fs::read_to_string(&path).ok()?;
+                Some((name, source))
+            })
+            .collect();
+        programs.sort_by(|left, right| left.0.cmp(&right.0));
+        programs
+    }
+
+    pub(super) fn save(&self, name: &str, source: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(name), source)
+    }
+
+    pub(super) fn delete(&self, name: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(name)) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.par"))
+    }
+}
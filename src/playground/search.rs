@@ -0,0 +1,245 @@
+//! The "Search" panel: finds plain text, or matches of a regular expression,
+//! in all the open sources, the bundled examples among them, and jumps to
+//! them in the editor.
+
+use std::ops::Range;
+
+use eframe::egui::{self, RichText, text::LayoutJob};
+use par_core::source::{FileName, Point, Span};
+use regex::{Regex, RegexBuilder};
+
+use super::sources::SourceSet;
+
+/// Past this many, a search is too broad to be useful, and listing all the
+/// matches would slow down every frame.
+const MAX_MATCHES: usize = 500;
+
+#[derive(Default)]
+pub(super) struct SearchPanel {
+    pub(super) open: bool,
+    query: String,
+    regex: bool,
+    case_sensitive: bool,
+    /// What `matches` were found for: the query, its options, and the
+    /// [fingerprint](SourceSet::fingerprint) of the sources.
+    searched: Option<(String, bool, bool, u64)>,
+    matches: Vec<SearchMatch>,
+    /// Why the query isn't a valid regular expression.
+    error: Option<String>,
+}
+
+struct SearchMatch {
+    buffer: usize,
+    label: String,
+    span: Span,
+    /// The line the match starts on, and the part of it that matched.
+    line: String,
+    highlight: Range<usize>,
+}
+
+/// A match to move the editor to: the buffer it's in, and the part to select.
+pub(super) struct SearchJump {
+    pub(super) buffer: usize,
+    pub(super) span: Span,
+}
+
+impl SearchPanel {
+    pub(super) fn show(&mut self, ctx: &egui::Context, sources: &SourceSet) -> Option<SearchJump> {
+        if !self.open {
+            return None;
+        }
+
+        let mut jump = None;
+        let mut open = self.open;
+        egui::Window::new("Search")
+            .open(&mut open)
+            .default_size([480.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.query).hint_text("Search all sources"),
+                    );
+                    ui.checkbox(&mut self.regex, "Regex");
+                    ui.checkbox(&mut self.case_sensitive, "Match case");
+                });
+                self.refresh(sources);
+                ui.separator();
+
+                if let Some(error) = &self.error {
+                    ui.label(
+                        RichText::new(error)
+                            .code()
+                            .color(ui.visuals().error_fg_color),
+                    );
+                    return;
+                }
+                if self.query.is_empty() {
+                    ui.label("Type to search the examples and all the open sources.");
+                    return;
+                }
+                ui.label(match self.matches.len() {
+                    0 => String::from("No matches."),
+                    1 => String::from("1 match."),
+                    MAX_MATCHES => format!("Showing the first {MAX_MATCHES} matches."),
+                    count => format!("{count} matches."),
+                });
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        let mut last_buffer = None;
+                        for found in &self.matches {
+                            if last_buffer != Some(found.buffer) {
+                                ui.add_space(4.0);
+                                ui.label(RichText::new(&found.label).strong());
+                                last_buffer = Some(found.buffer);
+                            }
+                            if ui
+                                .add(egui::Button::new(preview(ui, found)).frame(false))
+                                .on_hover_text("Jump to this match")
+                                .clicked()
+                            {
+                                jump = Some(SearchJump {
+                                    buffer: found.buffer,
+                                    span: found.span.clone(),
+                                });
+                            }
+                        }
+                    });
+            });
+        self.open = open;
+        jump
+    }
+
+    /// Searches again, if the query, its options, or the sources changed.
+    fn refresh(&mut self, sources: &SourceSet) {
+        let key = (
+            self.query.clone(),
+            self.regex,
+            self.case_sensitive,
+            sources.fingerprint(),
+        );
+        if self.searched.as_ref() == Some(&key) {
+            return;
+        }
+        self.searched = Some(key);
+        self.matches.clear();
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        let pattern = match RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+        {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                self.error = Some(error.to_string());
+                return;
+            }
+        };
+
+        for buffer in 0..sources.buffer_count() {
+            let label = sources.buffer_label(buffer);
+            let file = sources.buffer_file_name(buffer);
+            let source = sources.buffer_source(buffer);
+            for (span, line, highlight) in find_matches(&pattern, source, &file) {
+                if self.matches.len() == MAX_MATCHES {
+                    return;
+                }
+                self.matches.push(SearchMatch {
+                    buffer,
+                    label: label.clone(),
+                    span,
+                    line,
+                    highlight,
+                });
+            }
+        }
+    }
+}
+
+/// The non-empty matches of `pattern` in `source`, each with the line it
+/// starts on, and the part of that line it covers.
+fn find_matches<'a>(
+    pattern: &'a Regex,
+    source: &'a str,
+    file: &'a FileName,
+) -> impl Iterator<Item = (Span, String, Range<usize>)> + 'a {
+    let mut point = Point::default();
+    pattern
+        .find_iter(source)
+        .filter(|found| !found.is_empty())
+        .map(move |found| {
+            let start = advance(point, &source[point.offset as usize..found.start()]);
+            let end = advance(start, found.as_str());
+            point = end;
+
+            let line_start = found.start() - start.column as usize;
+            let line_end = source[found.start()..]
+                .find('\n')
+                .map_or(source.len(), |index| found.start() + index);
+            let line = &source[line_start..line_end];
+            let highlight = start.column as usize..(found.end() - line_start).min(line.len());
+
+            let span = Span::At {
+                start,
+                end,
+                file: file.clone(),
+                expansion: None,
+            };
+            (span, line.to_owned(), highlight)
+        })
+}
+
+/// Where `point` gets to past `text`, with columns in bytes, like the lexer
+/// counts them.
+fn advance(mut point: Point, text: &str) -> Point {
+    point.offset += text.len() as u32;
+    match text.rfind('\n') {
+        Some(index) => {
+            point.row += text.matches('\n').count() as u32;
+            point.column = (text.len() - index - 1) as u32;
+        }
+        None => point.column += text.len() as u32,
+    }
+    point
+}
+
+/// A match as a line of `row:column`, and the line it's on, with the match
+/// stressed.
+fn preview(ui: &egui::Ui, found: &SearchMatch) -> LayoutJob {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let plain = egui::TextFormat::simple(font.clone(), ui.visuals().weak_text_color());
+    let text = egui::TextFormat::simple(font.clone(), color);
+    let stressed = egui::TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        ..egui::TextFormat::simple(font, color)
+    };
+
+    let mut job = LayoutJob::default();
+    if let Some(start) = found.span.start() {
+        job.append(
+            &format!("{}:{}  ", start.row + 1, start.column + 1),
+            0.0,
+            plain,
+        );
+    }
+    let indent = found.line.len() - found.line.trim_start().len();
+    let before = indent.min(found.highlight.start);
+    job.append(
+        &found.line[before..found.highlight.start],
+        0.0,
+        text.clone(),
+    );
+    job.append(&found.line[found.highlight.clone()], 0.0, stressed);
+    job.append(&found.line[found.highlight.end..], 0.0, text);
+    job
+}
@@ -0,0 +1,204 @@
+//! Exports the interaction history of a run as a sequence diagram, with the
+//! user and the program as the two lanes.
+
+use std::fmt::Write;
+
+use super::readback::{Element, Event};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DiagramFormat {
+    Mermaid,
+    PlantUml,
+}
+
+impl DiagramFormat {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Self::Mermaid => "Mermaid",
+            Self::PlantUml => "PlantUML",
+        }
+    }
+
+    fn begin(self, out: &mut String) {
+        match self {
+            Self::Mermaid => {
+                let _ = writeln!(out, "sequenceDiagram");
+                let _ = writeln!(out, "    participant User");
+                let _ = writeln!(out, "    participant Program");
+            }
+            Self::PlantUml => {
+                let _ = writeln!(out, "@startuml");
+                let _ = writeln!(out, "participant User");
+                let _ = writeln!(out, "participant Program");
+            }
+        }
+    }
+
+    fn end(self, out: &mut String) {
+        if self == Self::PlantUml {
+            let _ = writeln!(out, "@enduml");
+        }
+    }
+
+    fn message(self, out: &mut String, depth: usize, from_program: bool, label: &str) {
+        let (from, to) = if from_program {
+            ("Program", "User")
+        } else {
+            ("User", "Program")
+        };
+        let label = self.escape(label);
+        match self {
+            Self::Mermaid => {
+                let _ = writeln!(out, "{}{from}->>{to}: {label}", indent(depth));
+            }
+            Self::PlantUml => {
+                let _ = writeln!(out, "{}{from} -> {to} : {label}", indent(depth));
+            }
+        }
+    }
+
+    fn note(self, out: &mut String, depth: usize, label: &str) {
+        let label = self.escape(label);
+        match self {
+            Self::Mermaid => {
+                let _ = writeln!(out, "{}Note over User,Program: {label}", indent(depth));
+            }
+            Self::PlantUml => {
+                let _ = writeln!(out, "{}note over User, Program : {label}", indent(depth));
+            }
+        }
+    }
+
+    fn begin_group(self, out: &mut String, depth: usize, label: &str) {
+        let label = self.escape(label);
+        match self {
+            Self::Mermaid => {
+                let _ = writeln!(out, "{}rect rgba(128, 128, 128, 0.1)", indent(depth));
+                let _ = writeln!(out, "{}Note over User,Program: {label}", indent(depth + 1));
+            }
+            Self::PlantUml => {
+                let _ = writeln!(out, "{}group {label}", indent(depth));
+            }
+        }
+    }
+
+    fn end_group(self, out: &mut String, depth: usize) {
+        let _ = writeln!(out, "{}end", indent(depth));
+    }
+
+    /// Keeps a label on one line, without characters that end a Mermaid message.
+    fn escape(self, label: &str) -> String {
+        let label = label.replace('\n', "\\n");
+        match self {
+            // In one pass, so the `;` of an escaped `#` stays as it is.
+            Self::Mermaid => {
+                let mut escaped = String::with_capacity(label.len());
+                for c in label.chars() {
+                    match c {
+                        '#' => escaped.push_str("#35;"),
+                        ';' => escaped.push_str("#59;"),
+                        c => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+            Self::PlantUml => label,
+        }
+    }
+}
+
+/// Renders the history of `element`, including every nested channel.
+pub(super) fn sequence_diagram(element: &Element, format: DiagramFormat) -> String {
+    let mut out = String::new();
+    format.begin(&mut out);
+    write_history(&mut out, format, element.history(), 1);
+    format.end(&mut out);
+    out
+}
+
+fn write_history(out: &mut String, format: DiagramFormat, events: &[Event], depth: usize) {
+    for event in events {
+        let from_program = event.is_from_program();
+        let label = match event {
+            Event::Times(child) | Event::Par(child) => {
+                let sender = if from_program { "Program" } else { "User" };
+                format.begin_group(out, depth, &format!("{sender} sends a channel"));
+                write_history(out, format, child.lock().unwrap().history(), depth + 1);
+                format.end_group(out, depth);
+                continue;
+            }
+            Event::Either(name) | Event::Choice(name) => format!(".{name}"),
+            Event::Break | Event::Continue => String::from("!"),
+            Event::Custom(view) | Event::CustomRequest(view) => view.text(),
+            Event::Summary(summary) => {
+                format.note(out, depth, &format!("Finished: {summary}"));
+                continue;
+            }
+            Event::Unreadable { typ, .. } => format!("<unreadable {typ}>"),
+            event => event.value_text().unwrap_or_default(),
+        };
+        format.message(out, depth, from_program, &label);
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "    ".repeat(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use arcstr::literal;
+    use num_bigint::BigUint;
+    use par_runtime::flat::stats::RunSummary;
+
+    use super::*;
+
+    /// A finished run that greets, is sent a name, and sends back a channel
+    /// with a number on it.
+    fn history() -> Vec<Event> {
+        vec![
+            Event::Either(literal!("greet")),
+            Event::String("Hello; who's #1?".to_owned()),
+            Event::StringRequest("Ada\nLovelace".to_owned()),
+            Event::Times(Element::from_history(vec![
+                Event::Nat(BigUint::from(42u8)),
+                Event::Break,
+            ])),
+            Event::Choice(literal!("quit")),
+            Event::Break,
+            Event::Summary(RunSummary {
+                rewrites: 120,
+                peak_nodes: 16,
+                external_calls: vec![],
+                wall_time: Duration::from_millis(3),
+                busy_time: Duration::from_millis(1),
+            }),
+        ]
+    }
+
+    #[test]
+    fn diagrams_match_golden_file() {
+        let root = Element::from_history(history());
+        let root = root.lock().unwrap();
+        let actual = [DiagramFormat::Mermaid, DiagramFormat::PlantUml]
+            .map(|format| format!("== {}\n{}", format.name(), sequence_diagram(&root, format)))
+            .join("\n");
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/playground/sequence_diagram.golden"
+        );
+        if std::env::var_os("PAR_UPDATE_GOLDEN").is_some() {
+            std::fs::write(path, &actual).unwrap();
+            return;
+        }
+        let expected = include_str!("sequence_diagram.golden");
+        assert!(
+            actual == expected,
+            "sequence diagrams changed; rerun with PAR_UPDATE_GOLDEN=1 to accept them, \
+             and review the diff of {path}\n\n{actual}"
+        );
+    }
+}
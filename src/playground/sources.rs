@@ -1,9 +1,17 @@
 #[cfg(not(target_family = "wasm"))]
 use std::io;
+#[cfg(not(target_family = "wasm"))]
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+#[cfg(not(target_family = "wasm"))]
+use std::time::{Duration, Instant};
 use std::{
     fs,
+    hash::{DefaultHasher, Hash, Hasher},
     path::{Path, PathBuf},
-    time::{Duration, SystemTime},
+    time::SystemTime,
 };
 
 use arcstr::literal;
@@ -13,14 +21,36 @@ use par_core::workspace::LoadedPackageFile;
 use par_core::workspace::{
     PackageLayout, SourceOverrides, WorkspaceDiscoveryError, load_package_source_files,
 };
+#[cfg(not(target_family = "wasm"))]
+use par_runtime::assets::Assets;
 use par_runtime::pkgid::PackageId;
 
 use super::examples::PLAYGROUND_EXAMPLES;
+use super::pinned::pinned_part_path;
+#[cfg(not(target_family = "wasm"))]
+use super::scratch::ScratchStore;
+use super::scratch::{SCRATCH_DIR, is_valid_scratch_name, scratch_template};
+#[cfg(not(target_family = "wasm"))]
+use crate::package_utils::load_package_assets;
+#[cfg(not(target_family = "wasm"))]
+use crate::watch::PackageWatcher;
 
 pub(super) struct SourceSet {
     kind: SourceSetKind,
     buffers: Vec<SourceBuffer>,
     active: usize,
+    #[cfg(not(target_family = "wasm"))]
+    scratch_store: Option<ScratchStore>,
+    #[cfg(not(target_family = "wasm"))]
+    pinned_store: Option<ScratchStore>,
+    #[cfg(not(target_family = "wasm"))]
+    root_dir: Option<PathBuf>,
+    /// Watches the package while any of its buffers reloads.
+    #[cfg(not(target_family = "wasm"))]
+    watcher: Option<PackageWatcher>,
+    /// Raised by the watcher when the package changed on disk.
+    #[cfg(not(target_family = "wasm"))]
+    reload_pending: Arc<AtomicBool>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,11 +67,32 @@ pub(super) struct SourceBuffer {
     source: String,
     saved_source: String,
     reload_mtime: Option<SystemTime>,
+    scratch_name: Option<String>,
+    /// The name of the definition, if this is a part of the pinned module.
+    pinned_name: Option<String>,
+    /// An edit of a scratch program or pinned definition, waiting to be
+    /// persisted.
+    #[cfg(not(target_family = "wasm"))]
+    pending_save: Option<PendingSave>,
+}
+
+/// Scratch programs and pinned definitions are written once they've been left
+/// alone for this long, not on every keystroke.
+#[cfg(not(target_family = "wasm"))]
+const SAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[cfg(not(target_family = "wasm"))]
+struct PendingSave {
+    /// Hash of the source when it was last seen changing.
+    source: u64,
+    since: Instant,
+    /// A save of this source failed, and isn't retried until it changes.
+    failed: bool,
 }
 
 impl SourceSet {
     pub(super) fn bundled_examples() -> Self {
-        let buffers = PLAYGROUND_EXAMPLES
+        let mut buffers: Vec<_> = PLAYGROUND_EXAMPLES
             .iter()
             .map(|example| {
                 SourceBuffer::memory(
@@ -55,10 +106,44 @@ impl SourceSet {
             })
             .collect();
 
+        #[cfg(not(target_family = "wasm"))]
+        let scratch_store = ScratchStore::open_default();
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(store) = &scratch_store {
+            buffers.extend(
+                store
+                    .load_all()
+                    .into_iter()
+                    .map(|(name, source)| SourceBuffer::scratch(name, &source)),
+            );
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        let pinned_store = ScratchStore::open_pinned();
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(store) = &pinned_store {
+            buffers.extend(
+                store
+                    .load_all()
+                    .into_iter()
+                    .map(|(name, source)| SourceBuffer::pinned(name, &source)),
+            );
+        }
+
         Self {
             kind: SourceSetKind::BundledExamples,
             buffers,
             active: 0,
+            #[cfg(not(target_family = "wasm"))]
+            scratch_store,
+            #[cfg(not(target_family = "wasm"))]
+            pinned_store,
+            #[cfg(not(target_family = "wasm"))]
+            root_dir: None,
+            #[cfg(not(target_family = "wasm"))]
+            watcher: None,
+            #[cfg(not(target_family = "wasm"))]
+            reload_pending: Arc::default(),
         }
     }
 
@@ -107,9 +192,24 @@ impl SourceSet {
             kind: SourceSetKind::DiskPackage,
             buffers,
             active,
+            scratch_store: None,
+            pinned_store: None,
+            root_dir: Some(layout.root_dir),
+            watcher: None,
+            reload_pending: Arc::default(),
         })
     }
 
+    /// The assets of the package, as they are on disk now. Bundled examples
+    /// have none.
+    #[cfg(not(target_family = "wasm"))]
+    pub(super) fn load_assets(&self) -> Result<Assets, String> {
+        let Some(root_dir) = &self.root_dir else {
+            return Ok(Assets::new());
+        };
+        load_package_assets(root_dir)
+    }
+
     pub(super) fn kind(&self) -> SourceSetKind {
         self.kind
     }
@@ -139,6 +239,14 @@ impl SourceSet {
         label
     }
 
+    pub(super) fn buffer_file_name(&self, index: usize) -> FileName {
+        self.buffers[index].file_name.clone()
+    }
+
+    pub(super) fn buffer_source(&self, index: usize) -> &str {
+        &self.buffers[index].source
+    }
+
     pub(super) fn is_active(&self, index: usize) -> bool {
         self.active == index
     }
@@ -151,6 +259,157 @@ impl SourceSet {
         true
     }
 
+    pub(super) fn supports_scratch(&self) -> bool {
+        matches!(self.kind, SourceSetKind::BundledExamples)
+    }
+
+    pub(super) fn is_active_scratch(&self) -> bool {
+        self.active_buffer().scratch_name.is_some()
+    }
+
+    /// Adds a new scratch program and makes it active.
+    pub(super) fn add_scratch(&mut self, name: &str) -> Result<(), String> {
+        if !self.supports_scratch() {
+            return Err(String::from(
+                "Scratch programs are only available next to the bundled examples.",
+            ));
+        }
+        if !is_valid_scratch_name(name) {
+            return Err(format!(
+                "`{name}` is not a valid scratch name. Use a capitalized identifier, like `Experiment1`."
+            ));
+        }
+        if self
+            .buffers
+            .iter()
+            .any(|buffer| buffer.scratch_name.as_deref() == Some(name))
+        {
+            return Err(format!("A scratch program named `{name}` already exists."));
+        }
+
+        let mut buffer = SourceBuffer::scratch(name.to_owned(), &scratch_template(name));
+        // Mark the fresh template as unsaved, so it gets persisted right away.
+        buffer.saved_source.clear();
+        self.buffers.push(buffer);
+        self.active = self.buffers.len() - 1;
+        Ok(())
+    }
+
+    /// Removes the active scratch program, including its persisted copy.
+    pub(super) fn remove_active_scratch(&mut self) -> bool {
+        let Some(_name) = self.active_buffer().scratch_name.clone() else {
+            return false;
+        };
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(store) = &self.scratch_store {
+            let _ = store.delete(&_name);
+        }
+        self.buffers.remove(self.active);
+        self.active = 0;
+        true
+    }
+
+    pub(super) fn is_active_pinned(&self) -> bool {
+        self.active_buffer().pinned_name.is_some()
+    }
+
+    /// Pins `source`, a part of the pinned module holding the definition
+    /// `name`, replacing an earlier pin of the same name. The active source
+    /// stays as it is.
+    pub(super) fn pin(&mut self, name: &str, source: &str) -> Result<(), String> {
+        if !self.supports_scratch() {
+            return Err(String::from(
+                "Definitions can only be pinned next to the bundled examples.",
+            ));
+        }
+
+        let mut buffer = SourceBuffer::pinned(name.to_owned(), source);
+        // Mark the pin as unsaved, so it gets persisted right away.
+        buffer.saved_source.clear();
+        match self
+            .buffers
+            .iter()
+            .position(|existing| existing.pinned_name.as_deref() == Some(name))
+        {
+            Some(index) => self.buffers[index] = buffer,
+            None => self.buffers.push(buffer),
+        }
+        Ok(())
+    }
+
+    /// Unpins the active pinned definition, including its persisted copy.
+    pub(super) fn unpin_active(&mut self) -> bool {
+        let Some(_name) = self.active_buffer().pinned_name.clone() else {
+            return false;
+        };
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(store) = &self.pinned_store {
+            let _ = store.delete(&_name);
+        }
+        self.buffers.remove(self.active);
+        self.active = 0;
+        true
+    }
+
+    /// Writes scratch programs and pinned definitions to their stores, once
+    /// they've stopped changing for [`SAVE_DEBOUNCE`]. Asks for a repaint when
+    /// one is still waiting, and returns the errors of saves that failed.
+    ///
+    /// A failed save isn't retried until the source changes again.
+    #[cfg(not(target_family = "wasm"))]
+    pub(super) fn persist_scratch(
+        &mut self,
+        request_repaint_after: impl Fn(Duration),
+    ) -> Option<String> {
+        let mut errors = Vec::new();
+        for buffer in &mut self.buffers {
+            let (store, name) = match (&buffer.scratch_name, &buffer.pinned_name) {
+                (Some(name), _) => (&self.scratch_store, name),
+                (None, Some(name)) => (&self.pinned_store, name),
+                (None, None) => continue,
+            };
+            let Some(store) = store else {
+                continue;
+            };
+            if !buffer.is_dirty() {
+                buffer.pending_save = None;
+                continue;
+            }
+
+            let mut hasher = DefaultHasher::new();
+            buffer.source.hash(&mut hasher);
+            let source = hasher.finish();
+            let pending = match &mut buffer.pending_save {
+                Some(pending) if pending.source == source => pending,
+                pending => pending.insert(PendingSave {
+                    source,
+                    since: Instant::now(),
+                    failed: false,
+                }),
+            };
+            if pending.failed {
+                continue;
+            }
+            let elapsed = pending.since.elapsed();
+            if elapsed < SAVE_DEBOUNCE {
+                request_repaint_after(SAVE_DEBOUNCE - elapsed);
+                continue;
+            }
+
+            match store.save(name, &buffer.source) {
+                Ok(()) => {
+                    buffer.saved_source = buffer.source.clone();
+                    buffer.pending_save = None;
+                }
+                Err(error) => {
+                    pending.failed = true;
+                    errors.push(format!("Could not save `{name}`: {error}"));
+                }
+            }
+        }
+        (!errors.is_empty()).then(|| errors.join("\n"))
+    }
+
     pub(super) fn active_source(&self) -> &str {
         &self.active_buffer().source
     }
@@ -181,41 +440,60 @@ impl SourceSet {
         self.active_buffer().reload_mtime.is_some()
     }
 
+    /// Makes the active buffer follow changes made to its file outside of
+    /// the playground, or stop following them. `wake` is called when one of
+    /// the followed files may have changed, from another thread.
     #[cfg(not(target_family = "wasm"))]
-    pub(super) fn set_active_reload_enabled(&mut self, enabled: bool) {
-        let buffer = self.active_buffer_mut();
-        if !enabled {
-            buffer.reload_mtime = None;
-            return;
-        }
-        buffer.reload_mtime = buffer.disk_path.as_deref().and_then(file_mtime);
-    }
-
-    pub(super) fn reload_active_if_changed(&mut self) {
+    pub(super) fn set_active_reload_enabled(
+        &mut self,
+        enabled: bool,
+        wake: impl Fn() + Send + 'static,
+    ) {
         let buffer = self.active_buffer_mut();
-        let Some(old_mtime) = buffer.reload_mtime else {
-            return;
-        };
-        let Some(path) = buffer.disk_path.as_deref() else {
-            return;
+        buffer.reload_mtime = if enabled {
+            buffer.disk_path.as_deref().and_then(file_mtime)
+        } else {
+            None
         };
-        let Some(mtime) = file_mtime(path) else {
+
+        let reloading = self
+            .buffers
+            .iter()
+            .any(|buffer| buffer.reload_mtime.is_some());
+        if !reloading {
+            self.watcher = None;
             return;
-        };
-        if !matches!(
-            mtime
-                .duration_since(old_mtime)
-                .map(|x| x > Duration::new(0, 0)),
-            Ok(true)
-        ) {
+        }
+        if self.watcher.is_some() {
             return;
         }
-        let Ok(source) = fs::read_to_string(path) else {
+        let Some(root_dir) = &self.root_dir else {
             return;
         };
-        buffer.source = source.clone();
-        buffer.saved_source = source;
-        buffer.reload_mtime = Some(mtime);
+        let pending = Arc::clone(&self.reload_pending);
+        match PackageWatcher::new(std::slice::from_ref(root_dir), move |_| {
+            pending.store(true, Ordering::Relaxed);
+            wake();
+        }) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            // Checked every frame instead, as long as there's no watcher.
+            Err(error) => eprintln!("Failed to watch the package for changes: {error}"),
+        }
+    }
+
+    /// Reloads the buffers following their files, whose files changed since
+    /// they were last loaded or saved. Returns whether any was reloaded.
+    #[cfg(not(target_family = "wasm"))]
+    pub(super) fn reload_changed(&mut self) -> bool {
+        let pending = self.reload_pending.swap(false, Ordering::Relaxed);
+        if !pending && self.watcher.is_some() {
+            return false;
+        }
+        let mut reloaded = false;
+        for buffer in &mut self.buffers {
+            reloaded |= buffer.reload_if_changed();
+        }
+        reloaded
     }
 
     #[cfg(not(target_family = "wasm"))]
@@ -242,6 +520,17 @@ impl SourceSet {
             .collect()
     }
 
+    /// Tells the contents of the buffers apart, to know whether they changed
+    /// since they were last built.
+    pub(super) fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for buffer in &self.buffers {
+            buffer.file_name.hash(&mut hasher);
+            buffer.source.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     pub(super) fn bundled_package_id() -> PackageId {
         PackageId::Special(literal!("playground_examples"))
     }
@@ -264,9 +553,38 @@ impl SourceBuffer {
             source: source.to_owned(),
             saved_source: source.to_owned(),
             reload_mtime: None,
+            scratch_name: None,
+            pinned_name: None,
+            #[cfg(not(target_family = "wasm"))]
+            pending_save: None,
         }
     }
 
+    fn scratch(name: String, source: &str) -> Self {
+        let relative_path_from_src = PathBuf::from(SCRATCH_DIR).join(format!("{name}.par"));
+        let mut buffer = Self::memory(
+            FileName::from(format!("playground-examples/src/{SCRATCH_DIR}/{name}.par")),
+            relative_path_from_src,
+            source,
+        );
+        buffer.scratch_name = Some(name);
+        buffer
+    }
+
+    fn pinned(name: String, source: &str) -> Self {
+        let relative_path_from_src = pinned_part_path(&name);
+        let mut buffer = Self::memory(
+            FileName::from(format!(
+                "playground-examples/src/{}",
+                relative_path_from_src.display()
+            )),
+            relative_path_from_src,
+            source,
+        );
+        buffer.pinned_name = Some(name);
+        buffer
+    }
+
     #[cfg(not(target_family = "wasm"))]
     fn disk(
         file_name: FileName,
@@ -281,6 +599,10 @@ impl SourceBuffer {
             saved_source: source.clone(),
             source,
             disk_path: Some(disk_path),
+            scratch_name: None,
+            pinned_name: None,
+            #[cfg(not(target_family = "wasm"))]
+            pending_save: None,
         }
     }
 
@@ -304,8 +626,39 @@ impl SourceBuffer {
     fn is_dirty(&self) -> bool {
         self.source != self.saved_source
     }
+
+    /// Reloads the buffer if it follows its file, and the file changed since
+    /// it was last loaded or saved. Returns whether it was reloaded.
+    #[cfg(not(target_family = "wasm"))]
+    fn reload_if_changed(&mut self) -> bool {
+        let Some(old_mtime) = self.reload_mtime else {
+            return false;
+        };
+        let Some(path) = self.disk_path.as_deref() else {
+            return false;
+        };
+        let Some(mtime) = file_mtime(path) else {
+            return false;
+        };
+        if !matches!(
+            mtime
+                .duration_since(old_mtime)
+                .map(|x| x > Duration::new(0, 0)),
+            Ok(true)
+        ) {
+            return false;
+        }
+        let Ok(source) = fs::read_to_string(path) else {
+            return false;
+        };
+        self.source = source.clone();
+        self.saved_source = source;
+        self.reload_mtime = Some(mtime);
+        true
+    }
 }
 
+#[cfg(not(target_family = "wasm"))]
 fn file_mtime(path: &Path) -> Option<SystemTime> {
     fs::metadata(path).ok()?.modified().ok()
 }
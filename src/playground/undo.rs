@@ -0,0 +1,166 @@
+//! Undoing the inputs given to a run in the playground.
+//!
+//! A run keeps a [`History`] of the inputs given to it so far, and undoing
+//! starts the definition over and replays the history without its last
+//! input. Each element of the run is addressed by its path in the history.
+//!
+//! The restarted run goes through everything the undone one did before its
+//! last input, effects on the world outside included. Those aren't had
+//! again: the run [replays](Effects::replaying) the results the undone run
+//! recorded for the effects it had before its last input, so nothing is
+//! printed or written twice, and everything read, or failed, the first time
+//! reads, or fails, the same. Once the run asks for an input there's no
+//! answer to in the history, it's caught up, and its effects are had for
+//! real.
+
+use par_core::runtime::{History, Input, Replay};
+use par_runtime::effects::Effects;
+
+/// How many inputs a run can undo, unless set otherwise.
+pub(super) const DEFAULT_UNDO_LIMIT: usize = 32;
+
+#[derive(Clone)]
+pub(super) struct Journal {
+    /// Every input given so far.
+    history: History,
+    /// How many effects the run had before each input.
+    effects_before: Vec<usize>,
+    /// Inputs yet to be replayed into a restarted run.
+    replay: Replay,
+    /// How many of the last inputs can be undone.
+    undoable: usize,
+    limit: usize,
+    /// Cleared once the run gets an input that can't be replayed, like one
+    /// from a custom editor; nothing before it can be undone after that.
+    replayable: bool,
+    /// The effects of the run, once it's started.
+    effects: Option<Effects>,
+    /// The effects of the run this journal was kept for, and how many of
+    /// them a run started over replays.
+    earlier: Option<(Effects, usize)>,
+}
+
+impl Journal {
+    pub(super) fn new(limit: usize) -> Self {
+        Self {
+            history: History::new(),
+            effects_before: Vec::new(),
+            replay: Replay::default(),
+            undoable: 0,
+            limit,
+            replayable: true,
+            effects: None,
+            earlier: None,
+        }
+    }
+
+    /// Starts the run with the effects it's to have: replaying those of the
+    /// run this journal was kept for, if any.
+    pub(super) fn start(&mut self) -> Effects {
+        let effects = match &self.earlier {
+            Some((earlier, count)) => Effects::replaying(earlier, *count),
+            None => Effects::new(),
+        };
+        self.effects = Some(effects.clone());
+        effects
+    }
+
+    fn stop_replaying(&self) {
+        if let Some(effects) = &self.effects {
+            effects.stop_replaying();
+        }
+    }
+
+    /// The journal for a run started over with the first `inputs` inputs,
+    /// and the effects had before the next one.
+    fn rewound(&self, inputs: usize, undoable: usize) -> Self {
+        let mut history = self.history.clone();
+        let mut effects_before = self.effects_before.clone();
+        while history.len() > inputs {
+            history.pop();
+        }
+        let effects = self.effects.clone().unwrap_or_default();
+        let replayed = effects_before
+            .get(inputs)
+            .copied()
+            .unwrap_or_else(|| effects.len());
+        effects_before.truncate(inputs);
+        Self {
+            replay: Replay::new(&history),
+            history,
+            effects_before,
+            undoable,
+            limit: self.limit,
+            replayable: true,
+            effects: None,
+            earlier: Some((effects, replayed)),
+        }
+    }
+
+    pub(super) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.undoable = self.undoable.min(limit);
+    }
+
+    pub(super) fn record(&mut self, path: &[usize], input: Input) {
+        let effects = self.effects.as_ref().map_or(0, Effects::len);
+        self.effects_before.push(effects);
+        self.history.push(path.to_vec(), input);
+        self.undoable = (self.undoable + 1).min(self.limit);
+    }
+
+    pub(super) fn forbid_undo(&mut self) {
+        self.replayable = false;
+        self.undoable = 0;
+    }
+
+    /// Whether a run can be started over up to where this one is: not once
+    /// it got an input, or had an effect, that can't be replayed.
+    fn can_replay(&self) -> bool {
+        self.replayable && self.effects.as_ref().is_none_or(Effects::is_replayable)
+    }
+
+    /// How many inputs can be undone, one at a time.
+    pub(super) fn undo_steps(&self) -> usize {
+        if self.can_replay() { self.undoable } else { 0 }
+    }
+
+    /// The journal for a run started over without the last input, or `None`
+    /// if that input can't be undone.
+    pub(super) fn undone(&self) -> Option<Self> {
+        if self.undo_steps() == 0 {
+            return None;
+        }
+        Some(self.rewound(self.history.len() - 1, self.undoable - 1))
+    }
+
+    /// The journal for a run started over with every input given so far, and
+    /// every effect had, as when the program changed under it. If an input
+    /// or an effect can't be replayed, the run starts afresh instead.
+    pub(super) fn restarted(&self) -> Self {
+        if !self.can_replay() {
+            return Self::new(self.limit);
+        }
+        self.rewound(self.history.len(), self.undoable)
+    }
+
+    /// The next input to replay into the element at `path`, if any. Once
+    /// the run asks for an input after the last one, it's caught up with the
+    /// run it replays, and what it does from there is new.
+    pub(super) fn next_replay(&mut self, path: &[usize]) -> Option<Input> {
+        let input = self.replay.next(path);
+        if input.is_none() && self.replay.is_finished() {
+            self.stop_replaying();
+        }
+        input
+    }
+
+    /// Gives up replaying into the element at `path`, because the restarted
+    /// run asked it for something else than before. The journal no longer
+    /// matches the run then, so it can't be undone any further.
+    pub(super) fn abandon_replay(&mut self, path: &[usize]) {
+        self.replay.abandon(path);
+        self.forbid_undo();
+        self.stop_replaying();
+    }
+}
@@ -0,0 +1,75 @@
+//! The "Unfold" panel: the type of whatever is under the text cursor, with its
+//! recursive and iterative types unfolded one step at a time.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, RichText};
+use par_core::source::FileName;
+use par_core::workspace::CheckedWorkspace;
+
+/// Every step can multiply the size of the type, so stop before it gets
+/// unreadable.
+const MAX_STEPS: usize = 8;
+
+#[derive(Default)]
+pub(super) struct UnfoldPanel {
+    pub(super) open: bool,
+    steps: usize,
+}
+
+impl UnfoldPanel {
+    pub(super) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        checked: Option<Arc<CheckedWorkspace>>,
+        file: &FileName,
+        (row, column): (u32, u32),
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Unfold type")
+            .open(&mut open)
+            .default_size([420.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.steps > 0, egui::Button::new("−"))
+                        .clicked()
+                    {
+                        self.steps -= 1;
+                    }
+                    ui.label(match self.steps {
+                        1 => String::from("1 step"),
+                        steps => format!("{steps} steps"),
+                    });
+                    if ui
+                        .add_enabled(self.steps < MAX_STEPS, egui::Button::new("+"))
+                        .clicked()
+                    {
+                        self.steps += 1;
+                    }
+                });
+                ui.separator();
+
+                let Some(checked) = checked else {
+                    ui.label("Compile the program to unfold types.");
+                    return;
+                };
+                let Some(typ) = checked
+                    .semantic_model()
+                    .unfolded_type_at(file, row, column, self.steps)
+                else {
+                    ui.label("Put the cursor on something that has a type.");
+                    return;
+                };
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label(RichText::new(checked.render_type_in_file(file, &typ, 0)).code());
+                });
+            });
+        self.open = open;
+    }
+}
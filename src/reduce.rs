@@ -0,0 +1,236 @@
+//! Shrinks a Par file while it keeps failing in the same way, to make small
+//! reproductions for bug reports.
+//!
+//! The reduction is delta debugging: it removes ever smaller groups of
+//! top-level items (imports, types, declarations, and definitions) and then of
+//! single lines, which in formatted code are mostly branches, keeping every
+//! removal after which the failure still happens.
+
+use std::ops::Range;
+
+use par_core::frontend::parse_source_file;
+use par_core::source::{FileName, Span};
+
+/// Reduces `source` as far as `still_fails` allows. `still_fails` must hold for
+/// `source` itself; `on_progress` is called with every smaller version found.
+pub(crate) fn reduce(
+    source: &str,
+    file: &FileName,
+    mut still_fails: impl FnMut(&str) -> bool,
+    mut on_progress: impl FnMut(&str),
+) -> String {
+    let mut current = source.to_owned();
+    loop {
+        let before = current.len();
+        if let Some(units) = item_units(&current, file) {
+            current = ddmin(&current, units, &mut still_fails, &mut on_progress);
+        }
+        let units = line_units(&current);
+        current = ddmin(&current, units, &mut still_fails, &mut on_progress);
+        if current.len() == before {
+            return current;
+        }
+    }
+}
+
+/// The imports and top-level items of `source`, each with the comments and
+/// blank lines before it, or `None` if it doesn't parse. A declaration goes
+/// together with the definition after it, since removing only one of them is
+/// an error of its own.
+fn item_units(source: &str, file: &FileName) -> Option<Vec<Range<usize>>> {
+    let parsed = parse_source_file(source, file.clone()).ok()?;
+    let module = &parsed.body;
+    let mut ends = parsed
+        .imports
+        .iter()
+        .map(|import| &import.span)
+        .chain(module.type_defs.iter().map(|type_def| &type_def.span))
+        .chain(
+            module
+                .declarations
+                .iter()
+                .filter(|declaration| {
+                    !module.definitions.iter().any(|definition| {
+                        definition.name == declaration.name
+                            && definition.span.start().map(|start| start.offset)
+                                > declaration.span.end().map(|end| end.offset)
+                    })
+                })
+                .map(|declaration| &declaration.span),
+        )
+        .chain(module.definitions.iter().map(|definition| &definition.span))
+        .filter_map(Span::end)
+        .map(|end| line_end(source, end.offset as usize))
+        .collect::<Vec<_>>();
+    ends.sort();
+    ends.dedup();
+
+    let mut start = parsed
+        .module_decl
+        .as_ref()
+        .and_then(|module_decl| module_decl.span.end())
+        .map_or(0, |end| line_end(source, end.offset as usize));
+    Some(
+        ends.into_iter()
+            .filter_map(|end| {
+                if end <= start {
+                    return None;
+                }
+                let unit = start..end;
+                start = end;
+                Some(unit)
+            })
+            .collect(),
+    )
+}
+
+/// Every non-blank line of `source`, except the module declaration.
+fn line_units(source: &str) -> Vec<Range<usize>> {
+    let mut units = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        let end = start + line.len();
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with("module ") {
+            units.push(start..end);
+        }
+        start = end;
+    }
+    units
+}
+
+/// The offset just past the end of the line containing `offset`.
+fn line_end(source: &str, offset: usize) -> usize {
+    let offset = offset.min(source.len());
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |newline| offset + newline + 1)
+}
+
+/// Finds a 1-minimal subset of `units` to keep, in the sense that removing any
+/// single one of them makes the failure go away.
+fn ddmin(
+    source: &str,
+    mut kept: Vec<Range<usize>>,
+    still_fails: &mut impl FnMut(&str) -> bool,
+    on_progress: &mut impl FnMut(&str),
+) -> String {
+    let all = kept.clone();
+    let mut chunks = 2;
+    while !kept.is_empty() {
+        let chunk_len = kept.len().div_ceil(chunks);
+        let reduced = (0..kept.len()).step_by(chunk_len).find_map(|start| {
+            let candidate = kept
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !(start..start + chunk_len).contains(i))
+                .map(|(_, unit)| unit.clone())
+                .collect::<Vec<_>>();
+            let text = render(source, &all, &candidate);
+            still_fails(&text).then_some((candidate, text))
+        });
+        match reduced {
+            Some((candidate, text)) => {
+                on_progress(&text);
+                kept = candidate;
+                chunks = (chunks - 1).max(2);
+            }
+            None if chunk_len == 1 => break,
+            None => chunks = (chunks * 2).min(kept.len()),
+        }
+    }
+    render(source, &all, &kept)
+}
+
+/// `source` without the units in `all` that aren't in `kept`.
+fn render(source: &str, all: &[Range<usize>], kept: &[Range<usize>]) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for unit in all.iter().filter(|unit| !kept.contains(unit)) {
+        out.push_str(&source[cursor..unit.start]);
+        cursor = unit.end;
+    }
+    out.push_str(&source[cursor..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use arcstr::literal;
+    use par_core::pipeline::{Pipeline, SourcePackage, Stage};
+    use par_core::workspace::LoadedPackageFile;
+    use par_runtime::pkgid::PackageId;
+
+    use super::*;
+
+    /// The codes of the type errors in `source`, checked on its own, or
+    /// `None` if it doesn't get as far as type checking.
+    fn type_error_codes(source: &str) -> Option<Vec<&'static str>> {
+        let id = PackageId::Special(literal!("test"));
+        let mut pipeline = Pipeline::new(
+            id.clone(),
+            vec![SourcePackage {
+                id,
+                dependencies: BTreeMap::new(),
+                files: vec![LoadedPackageFile {
+                    name: FileName::from("test/Test.par"),
+                    relative_path_from_src: PathBuf::from("Test.par"),
+                    source: source.to_owned(),
+                }],
+            }],
+        );
+        pipeline.run_until(Stage::TypeCheck).ok()?;
+        let errors = pipeline.into_artifacts().type_errors;
+        Some(errors.iter().map(|error| error.code()).collect())
+    }
+
+    #[test]
+    fn a_failing_program_reduces_to_what_makes_it_fail() {
+        let source = "module Test
+
+type Bool = either { .true!, .false! }
+type Unit = !
+
+dec Yes : Bool
+def Yes = .true!
+
+dec Not : [Bool] Bool
+def Not = [b] b.case {
+  .true! => .false!,
+  .false! => .true!,
+}
+
+dec Broken : Bool
+def Broken = .maybe!
+
+dec No : Bool
+def No = Not(Yes)
+";
+        let codes = type_error_codes(source).expect("checked program");
+        assert_eq!(codes.len(), 1);
+        let still_fails = |text: &str| type_error_codes(text).is_some_and(|now| now == codes);
+
+        let mut progress = Vec::new();
+        let reduced = reduce(
+            source,
+            &FileName::from("test/Test.par"),
+            still_fails,
+            |smaller| progress.push(smaller.to_owned()),
+        );
+        assert_eq!(
+            reduced,
+            "module Test
+
+type Bool = either { .true!, .false! }
+
+dec Broken : Bool
+def Broken = .maybe!
+"
+        );
+        assert!(still_fails(&reduced));
+        assert_eq!(progress.last(), Some(&reduced));
+    }
+}
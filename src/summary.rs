@@ -0,0 +1,161 @@
+//! The footer printed after `par check` and `par run --stats`: what was
+//! checked, how long each stage took, and which definitions were the slowest
+//! to check. It goes to stdout, apart from the diagnostics on stderr.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use colored::Colorize;
+use par_core::pipeline::Stage;
+use par_core::workspace::CheckedWorkspace;
+
+use crate::package_utils::root_module_slash_path;
+
+/// How many of the slowest definitions to list.
+const SLOWEST_SHOWN: usize = 3;
+
+/// The width the headings are right-aligned to, like cargo's.
+const HEADING_WIDTH: usize = 12;
+
+pub(crate) struct BuildSummary {
+    /// Definitions checked in the root package.
+    pub definitions: usize,
+    pub warnings: usize,
+    pub errors: usize,
+    pub stages: Vec<(Stage, Duration)>,
+    /// The slowest definitions of the root package to check, slowest first.
+    pub slowest: Vec<(String, Duration)>,
+}
+
+impl BuildSummary {
+    pub(crate) fn new(checked: &CheckedWorkspace, stages: Vec<(Stage, Duration)>) -> Self {
+        let root_package = checked.workspace().root_package();
+        let local = checked
+            .definitions_by_check_time()
+            .into_iter()
+            .filter_map(|(name, time)| {
+                let module = root_module_slash_path(root_package, &name.module)?;
+                Some((format!("{module}.{}", name.primary), time))
+            })
+            .collect::<Vec<_>>();
+        Self {
+            definitions: local.len(),
+            warnings: checked.warnings().len(),
+            errors: 0,
+            stages,
+            slowest: local.into_iter().take(SLOWEST_SHOWN).collect(),
+        }
+    }
+
+    /// A summary of a build that stopped at type errors.
+    pub(crate) fn failed(errors: usize) -> Self {
+        Self {
+            definitions: 0,
+            warnings: 0,
+            errors,
+            stages: Vec::new(),
+            slowest: Vec::new(),
+        }
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        if self.errors > 0 {
+            let _ = writeln!(
+                out,
+                "{} {}",
+                heading("Failed").bright_red().bold(),
+                count(self.errors, "error"),
+            );
+            return out;
+        }
+
+        let total = self.stages.iter().map(|(_, time)| *time).sum::<Duration>();
+        let mut finished = format!("checked {}", count(self.definitions, "definition"));
+        if self.warnings > 0 {
+            let _ = write!(
+                finished,
+                ", {}",
+                count(self.warnings, "warning").bright_yellow()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{} {finished} in {}",
+            heading("Finished").bright_green().bold(),
+            format_duration(total),
+        );
+
+        let stage_width = self
+            .stages
+            .iter()
+            .map(|(stage, _)| stage.name().len())
+            .max()
+            .unwrap_or(0);
+        for (index, (stage, time)) in self.stages.iter().enumerate() {
+            let title = if index == 0 { "Stages" } else { "" };
+            let _ = writeln!(
+                out,
+                "{} {:<stage_width$}  {:>8}",
+                heading(title).cyan().bold(),
+                stage.name(),
+                format_duration(*time),
+            );
+        }
+
+        let name_width = self
+            .slowest
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (index, (name, time)) in self.slowest.iter().enumerate() {
+            let title = if index == 0 { "Slowest" } else { "" };
+            let _ = writeln!(
+                out,
+                "{} {:<name_width$}  {:>8}",
+                heading(title).cyan().bold(),
+                name,
+                format_duration(*time),
+            );
+        }
+        out
+    }
+}
+
+fn heading(title: &str) -> String {
+    format!("{title:>HEADING_WIDTH$}")
+}
+
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+/// A duration in seconds, milliseconds, or microseconds, whichever is the
+/// largest unit it reaches.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds >= 1.0 {
+        format!("{seconds:.2}s")
+    } else if seconds >= 0.001 {
+        format!("{:.1}ms", seconds * 1e3)
+    } else {
+        format!("{}µs", duration.as_micros())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_shown_in_the_largest_unit_they_reach() {
+        assert_eq!(format_duration(Duration::from_millis(2340)), "2.34s");
+        assert_eq!(format_duration(Duration::from_micros(12_340)), "12.3ms");
+        assert_eq!(format_duration(Duration::from_micros(450)), "450µs");
+    }
+}
@@ -28,7 +28,7 @@ fn check_all_examples() -> Result<(), String> {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("examples");
         eprintln!("Checking {:?}", d);
-        check(d)
+        check(d, false)
     })
 }
 
@@ -38,7 +38,7 @@ fn check_playground_examples() -> Result<(), String> {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("playground-examples");
         eprintln!("Checking {:?}", d);
-        check(d)
+        check(d, false)
     })
 }
 
@@ -48,7 +48,15 @@ fn test_all_files() -> Result<(), String> {
         let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         d.push("tests");
         eprintln!("Testing {:?}", d);
-        if crate::test_runner::run_tests(d, None, None, 10_000) {
+        let options = crate::test_runner::TestOptions {
+            filter: None,
+            max_interactions: 10_000,
+            doc_example_fuel: crate::test_runner::DOC_EXAMPLE_FUEL_DEFAULT,
+            jobs: 1,
+            time_limit: None,
+            shard: None,
+        };
+        if crate::test_runner::run_tests(d, None, options) {
             Ok(())
         } else {
             Err("Some tests failed".to_string())
@@ -1,12 +1,16 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use colored::Colorize;
+use futures::channel::oneshot;
+use futures::future::{Either, select};
 use par_core::{
+    doc_examples::DocExample,
     frontend::{
         Type,
         language::{GlobalName, Universal},
@@ -16,13 +20,19 @@ use par_core::{
     testing::{AssertionResult, provide_test},
     workspace::{CheckedWorkspace, ModulePath, WorkspaceDiscoveryError, WorkspaceError},
 };
+use par_runtime::environment::Environment;
 use par_runtime::linker::Linked;
+use par_runtime::setup::Setup;
 use par_runtime::spawn::TokioSpawn;
+use tokio_util::sync::CancellationToken;
 
 use crate::package_utils::{
-    SourceLookup, find_local_module, parse_target, root_module_slash_path, source_for_fallback,
+    SourceLookup, find_local_module, load_package_assets, load_package_config, parse_target,
+    root_module_slash_path, source_for_fallback,
+};
+use crate::workspace_support::{
+    ScopedTypeError, WorkspaceBuildError, checked_workspace_with_doc_examples,
 };
-use crate::workspace_support::{ScopedTypeError, WorkspaceBuildError, checked_workspace_from_path};
 
 #[derive(Clone)]
 enum BuildError {
@@ -56,12 +66,25 @@ impl BuildError {
     }
 }
 
-fn build_for_run(
-    package_path: &Path,
-    max_interactions: u32,
-) -> Result<(CheckedWorkspace, Compiled<Linked>, Vec<ModulePath>), BuildError> {
-    let build =
-        checked_workspace_from_path(package_path, None).map_err(map_workspace_build_error)?;
+/// How many rewrites a documentation example may take, by default, before
+/// it's considered stuck.
+pub const DOC_EXAMPLE_FUEL_DEFAULT: u64 = 10_000_000;
+
+/// How long a single test may take, by default, before it fails.
+pub const TIME_LIMIT_DEFAULT_SECONDS: u64 = 60;
+
+/// A package built and linked for running its tests, with the modules of its
+/// own and the documentation examples found in them.
+type TestPackage = (
+    CheckedWorkspace,
+    Compiled<Linked>,
+    Vec<ModulePath>,
+    Vec<DocExample>,
+);
+
+fn build_for_run(package_path: &Path, max_interactions: u32) -> Result<TestPackage, BuildError> {
+    let (build, doc_examples) =
+        checked_workspace_with_doc_examples(package_path).map_err(map_workspace_build_error)?;
     if !build.type_errors.is_empty() {
         return Err(BuildError::Type {
             errors: build.type_errors,
@@ -77,7 +100,7 @@ fn build_for_run(
                 sources: sources.clone(),
             })?;
     let local_modules = checked.workspace().root_modules();
-    Ok((checked, rt_compiled, local_modules))
+    Ok((checked, rt_compiled, local_modules, doc_examples))
 }
 
 fn map_workspace_build_error(error: WorkspaceBuildError) -> BuildError {
@@ -124,12 +147,58 @@ pub struct TestResult {
     pub status: TestStatus,
 }
 
-pub fn run_tests(
-    package_path: PathBuf,
-    target: Option<String>,
-    filter: Option<String>,
-    max_interactions: u32,
-) -> bool {
+/// How the selected tests are run.
+pub struct TestOptions {
+    pub filter: Option<String>,
+    pub max_interactions: u32,
+    pub doc_example_fuel: u64,
+    /// How many tests run at the same time.
+    pub jobs: usize,
+    /// How long a single test may take before it fails.
+    pub time_limit: Option<Duration>,
+    /// Only run this shard of the tests: every `count`th one, starting from
+    /// the `index`th, counting from 1.
+    pub shard: Option<Shard>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl std::str::FromStr for Shard {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid shard `{text}`; expected `INDEX/COUNT`, like `1/4`");
+        let (index, count) = text.split_once('/').ok_or_else(invalid)?;
+        let index = index.trim().parse::<usize>().map_err(|_| invalid())?;
+        let count = count.trim().parse::<usize>().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(format!(
+                "Invalid shard `{text}`; the index must be between 1 and {count}"
+            ));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl Shard {
+    fn contains(self, position: usize) -> bool {
+        position % self.count == self.index - 1
+    }
+}
+
+pub fn run_tests(package_path: PathBuf, target: Option<String>, options: TestOptions) -> bool {
+    let TestOptions {
+        filter,
+        max_interactions,
+        doc_example_fuel,
+        jobs,
+        time_limit,
+        shard,
+    } = options;
     set_miette_hook();
     println!(
         "{} {}",
@@ -138,14 +207,31 @@ pub fn run_tests(
     );
     println!();
 
-    let (checked, rt_compiled, local_modules) = match build_for_run(&package_path, max_interactions)
-    {
-        Ok(result) => result,
+    let (checked, rt_compiled, local_modules, doc_examples) =
+        match build_for_run(&package_path, max_interactions) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("{}", error.display().bright_red());
+                return false;
+            }
+        };
+    let assets = match load_package_assets(&package_path) {
+        Ok(assets) => assets,
+        Err(error) => {
+            eprintln!("{}", error.bright_red());
+            return false;
+        }
+    };
+    let config = match load_package_config(&package_path, []) {
+        Ok(config) => config,
         Err(error) => {
-            eprintln!("{}", error.display().bright_red());
+            eprintln!("{}", error.bright_red());
             return false;
         }
     };
+    let setup = Setup::new(Environment::new())
+        .with_config(Arc::new(config))
+        .with_assets(Arc::new(assets));
 
     let parsed_target = target.as_deref().map(parse_target);
     let module_selector = parsed_target
@@ -175,24 +261,41 @@ pub fn run_tests(
     let mut passed_tests = 0usize;
     let start_time = Instant::now();
 
-    let tests = collect_test_definitions(
+    let doc_example_names = doc_examples
+        .iter()
+        .map(|example| example.name.as_str())
+        .collect();
+    let mut tests = collect_test_definitions(
         &checked,
         &local_modules,
+        &doc_example_names,
         selected_module,
         selected_name,
         filter.as_deref(),
     );
+    // Sorted, so that shards split the same way wherever they run.
+    tests.sort_by_cached_key(|(name, _)| name.to_string());
+    let tests = tests
+        .into_iter()
+        .enumerate()
+        .filter(|(position, _)| shard.is_none_or(|shard| shard.contains(*position)))
+        .map(|(_, test)| test)
+        .collect::<Vec<_>>();
 
     if tests.is_empty() {
         println!("{}", "No test definitions found".yellow());
         return false;
     }
 
-    for (name, kind) in tests {
-        let result = match kind {
-            DefinitionKind::Test => test_single_definition(&checked, &rt_compiled, &name),
-            DefinitionKind::Run => run_single_definition(&checked, &rt_compiled, &name),
-        };
+    let results = run_in_parallel(&tests, jobs, |name, kind| match kind {
+        DefinitionKind::Test => test_single_definition(&rt_compiled, &setup, name, time_limit),
+        DefinitionKind::Run => run_single_definition(&rt_compiled, &setup, name, time_limit),
+        DefinitionKind::DocExample => {
+            run_doc_example(&rt_compiled, &setup, name, doc_example_fuel, time_limit)
+        }
+    });
+
+    for ((name, _), result) in tests.iter().zip(results) {
         total_tests += 1;
         if result.status.is_passed() {
             passed_tests += 1;
@@ -212,15 +315,49 @@ pub fn run_tests(
     passed_tests == total_tests
 }
 
+/// Runs `run` on each test, on up to `jobs` threads at once. The results come
+/// in the same order as the tests, however long each one took.
+fn run_in_parallel(
+    tests: &[(GlobalName<Universal>, DefinitionKind)],
+    jobs: usize,
+    run: impl Fn(&GlobalName<Universal>, DefinitionKind) -> TestResult + Sync,
+) -> Vec<TestResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..tests.len()).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, tests.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((name, kind)) = tests.get(index) else {
+                        break;
+                    };
+                    let result = run(name, *kind);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every test was run"))
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy)]
 enum DefinitionKind {
     Test,
     Run,
+    /// An example from the documentation, see [`par_core::doc_examples`].
+    DocExample,
 }
 
 fn collect_test_definitions(
     checked: &CheckedWorkspace,
     local_modules: &[ModulePath],
+    doc_examples: &HashSet<&str>,
     selected_module: Option<&str>,
     selected_name: Option<&str>,
     filter: Option<&str>,
@@ -251,6 +388,9 @@ fn collect_test_definitions(
                 return None;
             }
 
+            if doc_examples.contains(name.primary.as_str()) {
+                return Some((name.clone(), DefinitionKind::DocExample));
+            }
             if name.primary.starts_with("Test") && name.primary != "Test" {
                 return Some((name.clone(), DefinitionKind::Test));
             }
@@ -269,52 +409,92 @@ fn is_local_module(module: &str, local_modules: &[ModulePath]) -> bool {
 }
 
 fn test_single_definition(
-    _program: &CheckedWorkspace,
     rt_compiled: &Compiled<Linked>,
+    setup: &Setup,
     test_name: &GlobalName<Universal>,
+    time_limit: Option<Duration>,
 ) -> TestResult {
-    let start = Instant::now();
-    let name_label = test_name.to_string();
-    let missing_type_name = name_label.clone();
-    let runtime = match crate::tokio_factory::create_runtime() {
-        Ok(rt) => rt,
-        Err(e) => {
-            return TestResult {
-                name: name_label,
-                duration: start.elapsed(),
-                status: TestStatus::Failed(format!("Failed to create runtime: {}", e)),
-            };
-        }
-    };
-
-    let result = runtime.block_on(async {
+    run_on_own_runtime(test_name, setup, time_limit, async |setup| {
         let ty = rt_compiled
             .get_type_of(test_name)
-            .ok_or_else(|| format!("Type not found for test '{}'", missing_type_name))?;
-        run_test_with_test_type(rt_compiled, test_name, &ty).await
-    });
-
-    let duration = start.elapsed();
-    let final_result = match result {
-        Ok(status) => status,
-        Err(msg) => TestStatus::Failed(msg),
-    };
-
-    TestResult {
-        name: name_label,
-        duration,
-        status: final_result,
-    }
+            .ok_or_else(|| format!("Type not found for test '{}'", test_name))?;
+        run_test_with_test_type(rt_compiled, &setup, test_name, &ty).await
+    })
 }
 
 fn run_single_definition(
-    _program: &CheckedWorkspace,
     rt_compiled: &Compiled<Linked>,
+    setup: &Setup,
     run_name: &GlobalName<Universal>,
+    time_limit: Option<Duration>,
+) -> TestResult {
+    run_on_own_runtime(run_name, setup, time_limit, async |setup| {
+        let _ty = rt_compiled
+            .get_type_of(run_name)
+            .ok_or_else(|| format!("Type not found for test '{}'", run_name))?;
+        let package = rt_compiled.package(run_name).unwrap();
+
+        let (handle, fut) = par_runtime::start_and_instantiate(
+            Arc::new(TokioSpawn::new()),
+            setup,
+            rt_compiled.arena(),
+            package,
+        );
+        handle.continue_();
+        fut.await;
+        Ok(TestStatus::PassedWithNoAssertions)
+    })
+}
+
+/// Runs a documentation example, which passes if it finishes within `fuel`
+/// rewrites.
+fn run_doc_example(
+    rt_compiled: &Compiled<Linked>,
+    setup: &Setup,
+    example_name: &GlobalName<Universal>,
+    fuel: u64,
+    time_limit: Option<Duration>,
+) -> TestResult {
+    run_on_own_runtime(example_name, setup, time_limit, async |setup| {
+        let package = rt_compiled.package(example_name).unwrap();
+        let (handle, reducer_future, progress) = par_runtime::start_and_instantiate_with_progress(
+            Arc::new(TokioSpawn::new()),
+            setup,
+            rt_compiled.arena(),
+            package,
+        );
+
+        let (out_of_fuel, fuel_exhausted) = oneshot::channel();
+        let (_stop, stopped) = mpsc::channel::<()>();
+        std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(Duration::from_millis(10))
+            {
+                if progress.rewrites() > fuel {
+                    let _ = out_of_fuel.send(());
+                    return;
+                }
+            }
+        });
+
+        handle.continue_();
+        match select(reducer_future, fuel_exhausted).await {
+            Either::Left(_) => Ok(TestStatus::PassedWithNoAssertions),
+            Either::Right(_) => Err(format!("did not finish within {fuel} rewrites")),
+        }
+    })
+}
+
+/// Runs a test on a runtime of its own, in a renewed `setup`, so that it can
+/// be abandoned if it takes longer than `time_limit`.
+fn run_on_own_runtime(
+    name: &GlobalName<Universal>,
+    setup: &Setup,
+    time_limit: Option<Duration>,
+    test: impl AsyncFnOnce(Setup) -> Result<TestStatus, String>,
 ) -> TestResult {
     let start = Instant::now();
-    let name_label = run_name.to_string();
-    let missing_type_name = name_label.clone();
+    let name_label = name.to_string();
     let runtime = match crate::tokio_factory::create_runtime() {
         Ok(rt) => rt,
         Err(e) => {
@@ -326,46 +506,46 @@ fn run_single_definition(
         }
     };
 
+    let cancel_token = CancellationToken::new();
+    let setup = setup.renewed().with_cancel_token(cancel_token.clone());
     let result = runtime.block_on(async {
-        let _ty = rt_compiled
-            .get_type_of(run_name)
-            .ok_or_else(|| format!("Type not found for test '{}'", missing_type_name))?;
-        let package = rt_compiled.code.get_with_name(run_name).unwrap();
-
-        let (handle, fut) = par_runtime::start_and_instantiate(
-            Arc::new(TokioSpawn::new()),
-            rt_compiled.code.arena.clone(),
-            package,
-        );
-        handle.continue_();
-        fut.await;
-        Ok(TestStatus::PassedWithNoAssertions)
+        match time_limit {
+            Some(time_limit) => tokio::time::timeout(time_limit, test(setup))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(format!(
+                        "did not finish within {:.0}s",
+                        time_limit.as_secs_f64()
+                    ))
+                }),
+            None => test(setup).await,
+        }
     });
-
-    let duration = start.elapsed();
-    let final_result = match result {
-        Ok(status) => status,
-        Err(msg) => TestStatus::Failed(msg),
-    };
+    // A test that ran out of time or fuel may still be running, so stop it
+    // instead of waiting for it.
+    cancel_token.cancel();
+    runtime.shutdown_background();
 
     TestResult {
         name: name_label,
-        duration,
-        status: final_result,
+        duration: start.elapsed(),
+        status: result.unwrap_or_else(TestStatus::Failed),
     }
 }
 
 async fn run_test_with_test_type(
     rt_compiled: &Compiled<Linked>,
+    setup: &Setup,
     name: &GlobalName<Universal>,
     _ty: &Type<Universal>,
 ) -> Result<TestStatus, String> {
     let (sender, receiver) = mpsc::channel();
 
-    let package = rt_compiled.code.get_with_name(name).unwrap();
+    let package = rt_compiled.package(name).unwrap();
     let (mut root, reducer_future) = par_runtime::start_and_instantiate(
         Arc::new(TokioSpawn::new()),
-        rt_compiled.code.arena.clone(),
+        setup.renewed(),
+        rt_compiled.arena(),
         package,
     );
 
@@ -453,3 +633,19 @@ fn print_summary(total: usize, passed: usize, duration: std::time::Duration) {
 
     println!("{}", summary);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_split_tests_round_robin() {
+        let shard = "2/3".parse::<Shard>().unwrap();
+        assert_eq!(shard, Shard { index: 2, count: 3 });
+        let positions = (0..7).filter(|&p| shard.contains(p)).collect::<Vec<_>>();
+        assert_eq!(positions, vec![1, 4]);
+        assert!("0/3".parse::<Shard>().is_err());
+        assert!("4/3".parse::<Shard>().is_err());
+        assert!("3".parse::<Shard>().is_err());
+    }
+}
@@ -11,3 +11,16 @@ pub(crate) fn create_runtime() -> std::io::Result<tokio::runtime::Runtime> {
         // .enable_all()
         .build()
 }
+
+/// A single-threaded runtime, so that tasks are always polled in the same order.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn create_deterministic_runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+}
+
+#[cfg(target_family = "wasm")]
+pub(crate) fn create_deterministic_runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    create_runtime()
+}
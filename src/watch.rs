@@ -0,0 +1,285 @@
+//! Watching a package for changes made outside of Par, for `par run --watch`
+//! and the playground's Reload toggle.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long a package has to stay unchanged before its changes are reported.
+/// Editors often save in several steps, and `par update` replaces many files
+/// at once; those should be reported once, as a whole.
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// Stops watching when dropped.
+pub struct PackageWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl PackageWatcher {
+    /// Watches the files under `roots`, the roots of a package and of the
+    /// packages it depends on, that go into building or running them:
+    /// sources, manifests, and assets. `on_change` is called on a thread of
+    /// the watcher's own with the paths that changed, once they settle.
+    pub fn new(
+        roots: &[PathBuf],
+        mut on_change: impl FnMut(Vec<PathBuf>) + Send + 'static,
+    ) -> notify::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        // Changes are reported with absolute paths.
+        let watched_roots = canonical_roots(roots);
+        // Dependencies kept under the package are watched along with it.
+        let to_watch: Vec<_> = watched_roots
+            .iter()
+            .filter(|root| {
+                !watched_roots
+                    .iter()
+                    .any(|other| other != *root && root.starts_with(other))
+            })
+            .cloned()
+            .collect();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                for path in event.paths {
+                    if is_watched(&watched_roots, &path) {
+                        let _ = sender.send(path);
+                    }
+                }
+            })?;
+        for root in &to_watch {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+
+        thread::spawn(move || {
+            // Ends once the watcher, and so the sender, is dropped.
+            while let Ok(path) = receiver.recv() {
+                let mut paths = vec![path];
+                loop {
+                    match receiver.recv_timeout(SETTLE_TIME) {
+                        Ok(path) => paths.push(path),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                paths.sort();
+                paths.dedup();
+                on_change(paths);
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// The contents of the watched files under some package roots, hashed, so
+/// that a change that left them as they were, like saving a file without
+/// editing it, can be ignored.
+pub struct WatchedFiles {
+    roots: Vec<PathBuf>,
+    hashes: BTreeMap<PathBuf, u64>,
+}
+
+impl WatchedFiles {
+    /// Hashes every watched file under `roots`.
+    pub fn new(roots: &[PathBuf]) -> Self {
+        let roots = canonical_roots(roots);
+        let mut files = Self {
+            hashes: BTreeMap::new(),
+            roots: roots.clone(),
+        };
+        for root in &roots {
+            files.hash_dir(root);
+        }
+        files
+    }
+
+    /// Hashes the files at `paths` again, as reported by [`PackageWatcher`],
+    /// and returns whether any of them was added, removed, or has different
+    /// contents. Files under a path that's a directory are hashed again too.
+    pub fn update(&mut self, paths: &[PathBuf]) -> bool {
+        let mut changed = false;
+        for path in paths {
+            let under: Vec<_> = self
+                .hashes
+                .range(path.clone()..)
+                .take_while(|(file, _)| file.starts_with(path))
+                .map(|(file, hash)| (file.clone(), *hash))
+                .collect();
+            for (file, _) in &under {
+                self.hashes.remove(file);
+            }
+            if path.is_dir() {
+                self.hash_dir(path);
+            } else {
+                self.hash_file(path);
+            }
+            let now = self
+                .hashes
+                .range(path.clone()..)
+                .take_while(|(file, _)| file.starts_with(path));
+            changed |= !now.eq(under.iter().map(|(file, hash)| (file, hash)));
+        }
+        changed
+    }
+
+    fn hash_dir(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                if is_watched_dir(&self.roots, &path) {
+                    self.hash_dir(&path);
+                }
+            } else {
+                self.hash_file(&path);
+            }
+        }
+    }
+
+    fn hash_file(&mut self, path: &Path) {
+        if !is_watched(&self.roots, path) {
+            return;
+        }
+        let Ok(contents) = fs::read(path) else {
+            return;
+        };
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        self.hashes.insert(path.to_path_buf(), hasher.finish());
+    }
+}
+
+fn canonical_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .map(|root| fs::canonicalize(root).unwrap_or_else(|_| root.clone()))
+        .collect()
+}
+
+/// The root in `roots` that `path` is in, and where in it. The innermost one
+/// if `path` is in a few, as dependencies can be kept under the package that
+/// depends on them.
+fn relative_to_root<'a>(roots: &[PathBuf], path: &'a Path) -> Option<&'a Path> {
+    roots
+        .iter()
+        .filter_map(|root| path.strip_prefix(root).ok())
+        .min_by_key(|relative| relative.components().count())
+}
+
+/// Whether a change to the file at `path` can change what one of the
+/// packages at `roots` builds into, or what its programs see when run.
+fn is_watched(roots: &[PathBuf], path: &Path) -> bool {
+    relative_to_root(roots, path).is_some_and(affects_package)
+}
+
+/// Whether the directory at `path` can have files in it that are watched.
+fn is_watched_dir(roots: &[PathBuf], path: &Path) -> bool {
+    relative_to_root(roots, path).is_some_and(|relative| !is_hidden(relative))
+}
+
+/// Whether a change to `path`, relative to the package root, can change what
+/// the package builds into, or what its programs see when run.
+fn affects_package(path: &Path) -> bool {
+    if is_hidden(path) {
+        return false;
+    }
+    match path.components().next() {
+        Some(Component::Normal(first)) if first == "assets" => true,
+        _ => path.extension().is_some_and(|ext| ext == "par") || path == Path::new("Par.toml"),
+    }
+}
+
+/// Whether `path` is in a hidden directory, like `.git`, or a hidden file,
+/// like an editor's swap file.
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|component| {
+        matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn only_package_files_are_watched() {
+        let affects = |path: &str| affects_package(Path::new(path));
+
+        assert!(affects("src/Main.par"));
+        assert!(affects("src/data/Post.errors.par"));
+        assert!(affects("Par.toml"));
+        assert!(affects("assets/data/words.txt"));
+        assert!(affects("dependencies/web/src/Server.par"));
+
+        assert!(!affects("src/.Main.par.swp"));
+        assert!(!affects(".git/src/Main.par"));
+        assert!(!affects("src/Par.toml"));
+        assert!(!affects("README.md"));
+    }
+
+    #[test]
+    fn only_changes_to_contents_count() {
+        let root = temp_package("contents");
+        fs::write(root.join("src/Main.par"), "module Main\n").unwrap();
+        let main = fs::canonicalize(root.join("src/Main.par")).unwrap();
+        let notes = fs::canonicalize(&root).unwrap().join("notes.txt");
+        let mut files = WatchedFiles::new(std::slice::from_ref(&root));
+
+        fs::write(&main, "module Main\n").unwrap();
+        fs::write(&notes, "not a part of the package").unwrap();
+        assert!(!files.update(&[main.clone(), notes]));
+
+        fs::write(&main, "module Main\n\ndef X = 1\n").unwrap();
+        assert!(files.update(std::slice::from_ref(&main)));
+        assert!(!files.update(std::slice::from_ref(&main)));
+
+        fs::remove_file(&main).unwrap();
+        assert!(files.update(&[main]));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn a_dependency_is_watched_from_its_own_root() {
+        let root = temp_package("root");
+        let dependency = temp_package("dependency");
+        let manifest = fs::canonicalize(dependency.join("Par.toml")).unwrap();
+        let mut files = WatchedFiles::new(&[root.clone(), dependency.clone()]);
+
+        fs::write(&manifest, "[package]\nname = \"renamed\"\n").unwrap();
+        assert!(files.update(&[manifest]));
+
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&dependency);
+    }
+
+    /// A new package named `name`, in a directory of its own.
+    fn temp_package(name: &str) -> PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("par-watch-{name}-{unique}"));
+        fs::create_dir_all(root.join("src")).expect("failed to create src directory");
+        fs::write(
+            root.join("Par.toml"),
+            format!("[package]\nname = \"{name}\"\n"),
+        )
+        .unwrap();
+        root
+    }
+}
@@ -1,15 +1,18 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crate::package_utils::{SourceLookup, source_for_type_error};
 use arcstr::literal;
 use par_builtin::inject_builtin_packages;
+use par_core::doc_examples::{DocExample, with_doc_examples};
 use par_core::frontend::{TypeError, language::Universal};
+use par_core::pipeline::{CheckLevel, Pipeline, PipelineError, SourcePackage, Stage};
 use par_core::runtime::{Compiled, RuntimeCompilerError};
 use par_core::source::FileName;
 use par_core::workspace::{
-    CheckedWorkspace, FileImportScope, LoadedPackageFile, ParsedPackage, SourceOverrides,
-    Workspace, WorkspaceDiscoveryError, WorkspacePackage, WorkspacePackages, assemble_workspace,
-    discover_workspace_packages_from_path, parse_loaded_files,
+    CheckedWorkspace, FileImportScope, LoadedPackageFile, SourceOverrides, Workspace,
+    WorkspaceDiscoveryError,
 };
 use par_runtime::linker::{Linked, Unlinked};
 use par_runtime::pkgid::PackageId;
@@ -20,6 +23,18 @@ pub(crate) enum WorkspaceBuildError {
     Workspace(par_core::workspace::WorkspaceError),
 }
 
+impl WorkspaceBuildError {
+    fn from_pipeline(error: PipelineError) -> Self {
+        match error {
+            PipelineError::Discovery(error) => Self::Discovery(error),
+            PipelineError::Workspace(error) => Self::Workspace(error),
+            PipelineError::Compile(_) => {
+                unreachable!("workspaces are only built up to type checking")
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ScopedTypeError {
     pub error: TypeError<Universal>,
@@ -46,24 +61,41 @@ impl ScopedTypeError {
     }
 }
 
+/// The warnings of `checked`, each with the import scope of its file.
+pub(crate) fn scoped_warnings(checked: &CheckedWorkspace) -> Vec<ScopedTypeError> {
+    checked
+        .warnings()
+        .iter()
+        .map(|warning| ScopedTypeError::from_workspace(checked.workspace(), warning.clone()))
+        .collect()
+}
+
 #[derive(Clone)]
 pub(crate) struct CheckedWorkspaceBuild {
     pub checked: CheckedWorkspace,
     pub sources: SourceLookup,
     pub type_errors: Vec<ScopedTypeError>,
+    pub warnings: Vec<ScopedTypeError>,
+    /// How long each stage of the pipeline took.
+    pub timings: Vec<(Stage, Duration)>,
 }
 
 impl CheckedWorkspaceBuild {
-    fn from_workspace(workspace: Workspace) -> Self {
-        let sources = workspace.sources().clone();
-        let (checked, type_errors) = workspace.type_check();
+    fn from_checked(
+        checked: CheckedWorkspace,
+        type_errors: Vec<TypeError<Universal>>,
+        timings: Vec<(Stage, Duration)>,
+    ) -> Self {
+        let workspace = checked.workspace();
         Self {
-            checked,
-            sources,
+            sources: workspace.sources().clone(),
             type_errors: type_errors
                 .into_iter()
-                .map(|error| ScopedTypeError::from_workspace(&workspace, error))
+                .map(|error| ScopedTypeError::from_workspace(workspace, error))
                 .collect(),
+            warnings: scoped_warnings(&checked),
+            timings,
+            checked,
         }
     }
 
@@ -78,6 +110,8 @@ impl CheckedWorkspaceBuild {
             checked,
             sources,
             type_errors: _,
+            warnings: _,
+            timings: _,
         } = self;
         match checked.compile_runtime(max_interactions) {
             Ok(compiled) => Ok((checked, compiled, sources)),
@@ -100,61 +134,147 @@ impl CheckedWorkspaceBuild {
     }
 }
 
-pub fn default_workspace_packages_from_path(
-    start: impl AsRef<Path>,
-    overrides: Option<&SourceOverrides>,
-) -> Result<WorkspacePackages, WorkspaceDiscoveryError> {
-    let mut discovered = discover_workspace_packages_from_path(start, overrides)?;
-    inject_builtin_packages(&mut discovered)?;
-    Ok(discovered)
+/// Has `pipeline` inject the builtin packages once the given ones are parsed.
+fn add_builtin_packages(pipeline: &mut Pipeline<'_>) {
+    pipeline.add_pass(Stage::Parse, |artifacts| {
+        let packages = artifacts
+            .packages
+            .as_mut()
+            .expect("parsing should produce the workspace packages");
+        inject_builtin_packages(packages).map_err(PipelineError::Discovery)
+    });
 }
 
-pub fn default_workspace_packages_from_parsed(
-    root_package: PackageId,
-    local: ParsedPackage,
-) -> WorkspacePackages {
-    let mut packages = WorkspacePackages {
-        root_package: root_package.clone(),
-        packages: vec![WorkspacePackage::new(root_package, local)],
-    };
-    inject_builtin_packages(&mut packages)
-        .expect("synthetic workspace should not conflict with builtin aliases");
-    packages
+/// Runs `pipeline` up to type checking, with the builtin packages injected
+/// once the given ones are parsed.
+fn checked_workspace_from_pipeline(
+    pipeline: Pipeline<'_>,
+) -> Result<CheckedWorkspaceBuild, WorkspaceBuildError> {
+    let build = checked_workspace_until_cancelled(pipeline, || false)?;
+    Ok(build.expect("a build that can't be cancelled finishes"))
 }
 
-pub fn assemble_default_workspace(
-    workspace_packages: WorkspacePackages,
-) -> Result<Workspace, par_core::workspace::WorkspaceError> {
-    assemble_workspace(workspace_packages)
+/// Like [`checked_workspace_from_pipeline`], but stops between stages once
+/// `cancelled` returns true, returning `Ok(None)`.
+fn checked_workspace_until_cancelled(
+    mut pipeline: Pipeline<'_>,
+    cancelled: impl Fn() -> bool,
+) -> Result<Option<CheckedWorkspaceBuild>, WorkspaceBuildError> {
+    add_builtin_packages(&mut pipeline);
+    // Lexing runs as part of parsing.
+    for stage in [Stage::Parse, Stage::Lower, Stage::Resolve, Stage::TypeCheck] {
+        if cancelled() {
+            return Ok(None);
+        }
+        pipeline
+            .run_until(stage)
+            .map_err(WorkspaceBuildError::from_pipeline)?;
+    }
+    let timings = pipeline.timings().to_vec();
+    let artifacts = pipeline.into_artifacts();
+    Ok(Some(CheckedWorkspaceBuild::from_checked(
+        artifacts
+            .checked
+            .expect("type checking should produce a checked workspace"),
+        artifacts.type_errors,
+        timings,
+    )))
 }
 
 pub(crate) fn checked_workspace_from_path(
     start: impl AsRef<Path>,
     overrides: Option<&SourceOverrides>,
 ) -> Result<CheckedWorkspaceBuild, WorkspaceBuildError> {
-    let packages = default_workspace_packages_from_path(start, overrides)
-        .map_err(WorkspaceBuildError::Discovery)?;
-    let workspace = assemble_default_workspace(packages).map_err(WorkspaceBuildError::Workspace)?;
-    Ok(CheckedWorkspaceBuild::from_workspace(workspace))
+    let pipeline =
+        Pipeline::from_path(start, overrides).map_err(WorkspaceBuildError::from_pipeline)?;
+    checked_workspace_from_pipeline(pipeline)
 }
 
-fn checked_workspace_from_parsed(
-    root_package: PackageId,
-    parsed: ParsedPackage,
-) -> Result<CheckedWorkspaceBuild, WorkspaceBuildError> {
-    let workspace_packages = default_workspace_packages_from_parsed(root_package, parsed);
-    let workspace =
-        assemble_default_workspace(workspace_packages).map_err(WorkspaceBuildError::Workspace)?;
-    Ok(CheckedWorkspaceBuild::from_workspace(workspace))
+/// Like [`checked_workspace_from_path`], for building a package again after
+/// it changed: the packages that didn't change since `previous` was checked
+/// aren't checked again. Stops between stages once `cancelled` returns true,
+/// returning `Ok(None)`.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn rechecked_workspace_from_path(
+    start: impl AsRef<Path>,
+    previous: Option<&CheckedWorkspace>,
+    cancelled: impl Fn() -> bool,
+) -> Result<Option<CheckedWorkspaceBuild>, WorkspaceBuildError> {
+    let pipeline = Pipeline::from_path(start, None).map_err(WorkspaceBuildError::from_pipeline)?;
+    match previous {
+        Some(previous) => checked_workspace_until_cancelled(pipeline.reusing(previous), cancelled),
+        None => checked_workspace_until_cancelled(pipeline, cancelled),
+    }
+}
+
+/// The errors in the package containing `start` that can be found without
+/// type checking it, as [`CheckLevel::Names`] finds them.
+pub(crate) fn name_errors_from_path(
+    start: impl AsRef<Path>,
+) -> Result<(Vec<ScopedTypeError>, SourceLookup), WorkspaceBuildError> {
+    let mut pipeline =
+        Pipeline::from_path(start, None).map_err(WorkspaceBuildError::from_pipeline)?;
+    add_builtin_packages(&mut pipeline);
+    pipeline
+        .check(CheckLevel::Names)
+        .map_err(WorkspaceBuildError::from_pipeline)?;
+    let artifacts = pipeline.into_artifacts();
+    let workspace = artifacts
+        .workspace
+        .expect("checking names should resolve the workspace");
+    Ok((
+        artifacts
+            .type_errors
+            .into_iter()
+            .map(|error| ScopedTypeError::from_workspace(&workspace, error))
+            .collect(),
+        workspace.sources().clone(),
+    ))
+}
+
+/// Like [`checked_workspace_from_path`], but with a definition appended to
+/// every file of the root package for each example in its documentation, to be
+/// run as tests. Returns the examples along with the build.
+pub(crate) fn checked_workspace_with_doc_examples(
+    start: impl AsRef<Path>,
+) -> Result<(CheckedWorkspaceBuild, Vec<DocExample>), WorkspaceBuildError> {
+    let mut examples = Vec::new();
+    let mut pipeline =
+        Pipeline::from_path(start, None).map_err(WorkspaceBuildError::from_pipeline)?;
+    pipeline.add_pass(Stage::Lex, |artifacts| {
+        let root_package = &artifacts.root_package;
+        for package in &mut artifacts.sources {
+            if &package.id != root_package {
+                continue;
+            }
+            for file in &mut package.files {
+                let (source, found) = with_doc_examples(&file.source, &file.name);
+                file.source = source;
+                examples.extend(found);
+            }
+        }
+        Ok(())
+    });
+    // Parsing reads the sources as the passes after lexing leave them.
+    pipeline
+        .run_until(Stage::Lex)
+        .map_err(WorkspaceBuildError::from_pipeline)?;
+    let build = checked_workspace_from_pipeline(pipeline)?;
+    Ok((build, examples))
 }
 
 pub(crate) fn checked_workspace_from_loaded_package(
     files: Vec<LoadedPackageFile>,
     root_package: PackageId,
 ) -> Result<CheckedWorkspaceBuild, WorkspaceBuildError> {
-    let parsed = parse_loaded_files(files)
-        .map_err(|error| WorkspaceBuildError::Discovery(WorkspaceDiscoveryError::Load(error)))?;
-    checked_workspace_from_parsed(root_package, parsed)
+    checked_workspace_from_pipeline(Pipeline::new(
+        root_package.clone(),
+        vec![SourcePackage {
+            id: root_package,
+            dependencies: BTreeMap::new(),
+            files,
+        }],
+    ))
 }
 
 pub(crate) fn checked_workspace_from_single_file(